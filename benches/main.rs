@@ -4,7 +4,7 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use tests::{
     dbscan_build, dbscan_fixed_clusters, dbscan_uniform_clusters, hdbscan_build,
     hdbscan_fixed_clusters, hdbscan_uniform_clusters, optics_build, optics_fixed_clusters,
-    optics_uniform_clusters,
+    optics_large_eps, optics_uniform_clusters,
 };
 
 criterion_group! {
@@ -15,7 +15,7 @@ config = Criterion::default()
 targets =
     dbscan_build, dbscan_fixed_clusters, dbscan_uniform_clusters,
     hdbscan_build, hdbscan_fixed_clusters, hdbscan_uniform_clusters,
-    optics_build, optics_fixed_clusters, optics_uniform_clusters
+    optics_build, optics_fixed_clusters, optics_large_eps, optics_uniform_clusters
 }
 
 criterion_main!(benches);
@@ -0,0 +1,65 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ndarray::{Array2, ArrayView};
+use ndarray_rand::rand::{rngs::StdRng, Rng, SeedableRng};
+use petal_clustering::simd::{squared_euclidean, SimdEuclidean};
+use petal_clustering::{Dbscan, Fit, HDbscan, Optics};
+use petal_neighbors::distance::{Euclidean, Metric};
+
+fn random_points(n: usize, dim: usize) -> Array2<f64> {
+    let mut rng = StdRng::from_seed(*b"simd distance kernel bench seed ");
+    let data: Vec<f64> = (0..n * dim).map(|_| rng.random()).collect();
+    ArrayView::from_shape((n, dim), &data).unwrap().to_owned()
+}
+
+fn kernels(c: &mut Criterion) {
+    let points = random_points(2, black_box(256));
+    let (x, y) = (points.row(0), points.row(1));
+    c.bench_function("simd::kernel_scalar", |b| {
+        b.iter(|| Euclidean::default().rdistance(black_box(&x), black_box(&y)));
+    });
+    c.bench_function("simd::kernel_vectorized", |b| {
+        b.iter(|| squared_euclidean(black_box(&x), black_box(&y)));
+    });
+}
+
+fn dbscan(c: &mut Criterion) {
+    let array = random_points(black_box(2000), black_box(64));
+    c.bench_function("simd::dbscan_scalar", |b| {
+        b.iter(|| Dbscan::new(3., 10, Euclidean::default()).fit(&array, None));
+    });
+    c.bench_function("simd::dbscan_vectorized", |b| {
+        b.iter(|| Dbscan::new(3., 10, SimdEuclidean).fit(&array, None));
+    });
+}
+
+fn optics(c: &mut Criterion) {
+    let array = random_points(black_box(2000), black_box(64));
+    c.bench_function("simd::optics_scalar", |b| {
+        b.iter(|| Optics::new(3., 10, Euclidean::default()).fit(&array, None));
+    });
+    c.bench_function("simd::optics_vectorized", |b| {
+        b.iter(|| Optics::new(3., 10, SimdEuclidean).fit(&array, None));
+    });
+}
+
+fn hdbscan(c: &mut Criterion) {
+    let array = random_points(black_box(2000), black_box(64));
+    c.bench_function("simd::hdbscan_scalar", |b| {
+        b.iter(|| HDbscan::new(10, Euclidean::default()).fit(&array, None));
+    });
+    c.bench_function("simd::hdbscan_vectorized", |b| {
+        b.iter(|| HDbscan::new(10, SimdEuclidean).fit(&array, None));
+    });
+}
+
+criterion_group! {
+name = benches;
+config = Criterion::default()
+    .sample_size(20)
+    .measurement_time(std::time::Duration::new(20, 0));
+targets = kernels, dbscan, optics, hdbscan
+}
+
+criterion_main!(benches);
@@ -3,10 +3,9 @@ use std::hint::black_box;
 use criterion::Criterion;
 use ndarray::{arr2, ArrayView};
 use ndarray_rand::rand::{rngs::StdRng, Rng, SeedableRng};
+use petal_clustering::datasets::{make_blobs, make_blobs_around};
 use petal_clustering::{Fit, HDbscan};
 
-use super::setup::{make_blobs, CenterConfig};
-
 pub fn build(c: &mut Criterion) {
     let n = black_box(5000);
     let dim = black_box(3);
@@ -18,7 +17,7 @@ pub fn build(c: &mut Criterion) {
         b.iter(|| {
             let mut model = HDbscan::default();
             model.fit(&array, None);
-        })
+        });
     });
 }
 
@@ -26,26 +25,25 @@ pub fn uniform_clusters(c: &mut Criterion) {
     let n = black_box(500);
     let dim = black_box(3);
 
-    let array = make_blobs(n, dim, None, None, None);
+    let (array, _) = make_blobs(n, dim, 3, 1., 0);
     c.bench_function("hdbscan::uniform_clusters", |b| {
         b.iter(|| {
             let mut model = HDbscan::default();
             model.fit(&array.view(), None);
-        })
+        });
     });
 }
 
 pub fn fixed_clusters(c: &mut Criterion) {
     let n = black_box(500);
-    let dim = black_box(3);
     let centers = arr2(&[[1., 1., 1.], [-1., -1., -1.], [1., -1., 1.]]);
 
-    let array = make_blobs(n, dim, Some(CenterConfig::Fixed(centers)), Some(0.4), None);
+    let (array, _) = make_blobs_around(n, &centers, 0.4, 0);
 
     c.bench_function("hdbscan::fixed_clusters", |b| {
         b.iter(|| {
             let mut model = HDbscan::default();
             model.fit(&array.view(), None);
-        })
+        });
     });
 }
@@ -1,7 +1,6 @@
 mod dbscan;
 mod hdbscan;
 mod optics;
-mod setup;
 
 pub use dbscan::{
     build as dbscan_build, fixed_clusters as dbscan_fixed_clusters,
@@ -12,6 +11,6 @@ pub use hdbscan::{
     uniform_clusters as hdbscan_uniform_clusters,
 };
 pub use optics::{
-    build as optics_build, fixed_clusters as optics_fixed_clusters,
+    build as optics_build, fixed_clusters as optics_fixed_clusters, large_eps as optics_large_eps,
     uniform_clusters as optics_uniform_clusters,
 };
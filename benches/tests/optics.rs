@@ -3,11 +3,10 @@ use std::hint::black_box;
 use criterion::Criterion;
 use ndarray::{arr2, ArrayView};
 use ndarray_rand::rand::{rngs::StdRng, Rng, SeedableRng};
+use petal_clustering::datasets::{make_blobs, make_blobs_around};
 use petal_clustering::{Fit, Optics};
 use petal_neighbors::distance::Euclidean;
 
-use super::setup::{make_blobs, CenterConfig};
-
 pub fn build(c: &mut Criterion) {
     let n = black_box(5000);
     let dim = black_box(3);
@@ -19,7 +18,7 @@ pub fn build(c: &mut Criterion) {
         b.iter(|| {
             let mut model = Optics::new(0.5, 10, Euclidean::default());
             model.fit(&array, None);
-        })
+        });
     });
 }
 
@@ -27,26 +26,40 @@ pub fn uniform_clusters(c: &mut Criterion) {
     let n = black_box(500);
     let dim = black_box(3);
 
-    let array = make_blobs(n, dim, None, None, None);
+    let (array, _) = make_blobs(n, dim, 3, 1., 0);
     c.bench_function("optics::uniform_clusters", |b| {
         b.iter(|| {
             let mut model = Optics::new(1., 10, Euclidean::default());
             model.fit(&array.view(), None);
-        })
+        });
     });
 }
 
 pub fn fixed_clusters(c: &mut Criterion) {
     let n = black_box(500);
-    let dim = black_box(3);
     let centers = arr2(&[[1., 1., 1.], [-1., -1., -1.], [1., -1., 1.]]);
 
-    let array = make_blobs(n, dim, Some(CenterConfig::Fixed(centers)), Some(0.4), None);
+    let (array, _) = make_blobs_around(n, &centers, 0.4, 0);
 
     c.bench_function("optics::fixed_clusters", |b| {
         b.iter(|| {
             let mut model = Optics::new(0.3, 10, Euclidean::default());
             model.fit(&array.view(), None);
-        })
+        });
+    });
+}
+
+pub fn large_eps(c: &mut Criterion) {
+    let n = black_box(2000);
+    let dim = black_box(3);
+
+    let mut rng = StdRng::from_seed(*b"ball tree build bench test seed ");
+    let data: Vec<f64> = (0..n * dim).map(|_| rng.random()).collect();
+    let array = ArrayView::from_shape((n, dim), &data).unwrap();
+    c.bench_function("optics::large_eps", |b| {
+        b.iter(|| {
+            let mut model = Optics::new(0.5, 10, Euclidean::default());
+            model.fit(&array, None);
+        });
     });
 }
@@ -0,0 +1,70 @@
+use std::{
+    env,
+    io::{self, BufRead},
+    process::exit,
+};
+
+use petal_clustering::text::{EditDistance, StringClustering};
+use petal_clustering::{AgglomerativeClustering, Fit, Linkage};
+use petal_neighbors::distance::Euclidean;
+
+fn main() {
+    let threshold = parse();
+    let messages: Vec<String> = io::stdin()
+        .lock()
+        .lines()
+        .map(|line| line.expect("cannot read the input"))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let mut agglomerative = AgglomerativeClustering::new(1, Linkage::Average, Euclidean::default());
+    agglomerative.distance_threshold = Some(threshold);
+    let mut clustering = StringClustering::new(EditDistance::NormalizedLevenshtein, agglomerative);
+    let (clusters, _) = clustering.fit(&messages[..], None);
+
+    let mut clusters: Vec<_> = clusters.into_values().collect();
+    clusters.sort_unstable_by_key(|members| (usize::MAX - members.len(), members[0]));
+    for members in &clusters {
+        println!("{:>6}  {}", members.len(), messages[members[0]]);
+    }
+    eprintln!(
+        "{} messages, {} distinct up to a normalized edit distance of {threshold}",
+        messages.len(),
+        clusters.len()
+    );
+}
+
+fn parse() -> f64 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => 0.2,
+        [flag, ..] if flag == "-h" || flag == "--help" => {
+            help();
+            exit(0);
+        }
+        [threshold] => threshold.parse().unwrap_or_else(|_| {
+            eprintln!("invalid threshold: {threshold}\n");
+            help();
+            exit(2);
+        }),
+        _ => {
+            help();
+            exit(2);
+        }
+    }
+}
+
+fn help() {
+    eprintln!(
+        "USAGE: \
+        \n dedup-logs [THRESHOLD] < FILE \
+        \n \
+        \nGroups the near-identical lines read from stdin, such as log messages that \
+        \ndiffer only in an ID or a number, and prints the size of each group with its \
+        \nfirst line, largest groups first. \
+        \n \
+        \nARGS: \
+        \n <THRESHOLD>  the largest average edit distance between the lines of a group, \
+        \n              relative to their lengths [default: 0.2]"
+    );
+}
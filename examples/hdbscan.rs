@@ -14,14 +14,13 @@ fn main() {
     let mut nfeatures = 0;
     let data: Vec<f64> = rdr
         .deserialize()
-        .map(|v| {
+        .flat_map(|v| {
             let r: Vec<f64> = v.expect("corrupted data");
             if nfeatures < 1 {
                 nfeatures = r.len();
             }
             r.into_iter()
         })
-        .flatten()
         .collect();
     if nfeatures < 1 {
         println!(
@@ -33,14 +32,9 @@ fn main() {
     }
     let nevents = data.len() / nfeatures;
     let data = Array2::from_shape_vec((nevents, nfeatures), data).expect("data shape error");
-    let mut clustering = HDbscan {
-        alpha: 1.,
-        min_samples,
-        min_cluster_size,
-        metric: Euclidean::default(),
-        boruvka: true,
-        extraction: ClusterExtraction::ExcessOfMass,
-    };
+    let mut clustering = HDbscan::new(min_cluster_size, Euclidean::default());
+    clustering.min_samples = min_samples;
+    clustering.extraction = ClusterExtraction::ExcessOfMass;
     let (clusters, noise, outlier_scores) = clustering.fit(&data.view(), None);
     println!("========= Report =========");
     println!("# of events processed: {}", data.nrows());
@@ -48,7 +42,7 @@ fn main() {
     println!("# of clusters: {}", clusters.len());
     println!(
         "# of events clustered: {}",
-        clusters.values().map(|v| v.len()).sum::<usize>(),
+        clusters.values().map(Vec::len).sum::<usize>(),
     );
     println!("# of noise events: {}", noise.len());
     println!(
@@ -78,7 +72,7 @@ fn parse() -> (String, usize, usize) {
 
     println!("unable to process provided arguments: ");
     for (nth, arg) in args.iter().enumerate().take(4) {
-        println!("{}. {:?}", nth, arg);
+        println!("{nth}. {arg:?}");
     }
     exit(0);
 }
@@ -0,0 +1,218 @@
+use std::{env, fs::File, io, process::exit};
+
+use ndarray::{Array2, ArrayView1};
+use petal_clustering::{io::read_csv_to_array, Dbscan, Fit, HDbscan, Optics, Partition};
+use petal_clustering::{ClusterExtraction, PointAssignment};
+use petal_neighbors::distance::{Euclidean, Metric};
+
+/// The distances selectable with `--metric`.
+#[derive(Clone, Copy, Debug)]
+enum Distance {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+}
+
+impl Metric<f64> for Distance {
+    fn distance(&self, x1: &ArrayView1<f64>, x2: &ArrayView1<f64>) -> f64 {
+        match self {
+            Self::Euclidean => Euclidean::default().distance(x1, x2),
+            Self::Manhattan => x1.iter().zip(x2).map(|(a, b)| (a - b).abs()).sum(),
+            Self::Chebyshev => x1
+                .iter()
+                .zip(x2)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0., f64::max),
+        }
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<f64>, x2: &ArrayView1<f64>) -> f64 {
+        match self {
+            Self::Euclidean => Euclidean::default().rdistance(x1, x2),
+            _ => self.distance(x1, x2),
+        }
+    }
+
+    fn rdistance_to_distance(&self, d: f64) -> f64 {
+        match self {
+            Self::Euclidean => Metric::<f64>::rdistance_to_distance(&Euclidean::default(), d),
+            _ => d,
+        }
+    }
+
+    fn distance_to_rdistance(&self, d: f64) -> f64 {
+        match self {
+            Self::Euclidean => Metric::<f64>::distance_to_rdistance(&Euclidean::default(), d),
+            _ => d,
+        }
+    }
+}
+
+struct Options {
+    algorithm: String,
+    eps: Option<f64>,
+    min_samples: usize,
+    min_cluster_size: usize,
+    metric: Distance,
+    columns: Option<Vec<usize>>,
+    output: Option<String>,
+    path: String,
+}
+
+fn main() {
+    let options = parse();
+    let data = read_csv_to_array(&options.path, options.columns.as_deref()).unwrap_or_else(|e| {
+        eprintln!("cannot read {}: {e}", options.path);
+        exit(1);
+    });
+
+    let (partition, confidence) = cluster(&options, &data);
+    eprintln!(
+        "{}: {} points, {} clusters, {} noise points",
+        options.algorithm,
+        data.nrows(),
+        partition.len(),
+        partition.noise().len()
+    );
+
+    let result = match &options.output {
+        Some(path) => File::create(path).and_then(|file| write(&partition, confidence, file)),
+        None => write(&partition, confidence, io::stdout().lock()),
+    };
+    if let Err(e) = result {
+        eprintln!("cannot write the labels: {e}");
+        exit(1);
+    }
+}
+
+/// Clusters `data` as `options` says, returning the partition and, for
+/// HDBSCAN, one minus the outlier score of each point.
+fn cluster(options: &Options, data: &Array2<f64>) -> (Partition, Option<Vec<f64>>) {
+    match options.algorithm.as_str() {
+        "dbscan" => {
+            let eps = options.eps.unwrap_or(0.5);
+            let mut dbscan = Dbscan::new(eps, options.min_samples, options.metric);
+            let (clusters, noise) = dbscan.fit(data, None);
+            (Partition::new(clusters, noise), None)
+        }
+        "optics" => {
+            let mut optics = Optics::new(f64::INFINITY, options.min_samples, options.metric);
+            optics.fit(data, None);
+            let (clusters, noise) =
+                optics.extract_clusters_and_noise(options.eps.unwrap_or(f64::INFINITY));
+            (Partition::new(clusters, noise), None)
+        }
+        "hdbscan" => {
+            let mut hdbscan = HDbscan::new(options.min_cluster_size, options.metric);
+            hdbscan.min_samples = options.min_samples;
+            hdbscan.extraction = ClusterExtraction::ExcessOfMass;
+            if let Some(eps) = options.eps {
+                hdbscan.cluster_selection_epsilon = eps;
+            }
+            let (clusters, noise, outlier_scores) = hdbscan.fit(data, None);
+            let confidence = outlier_scores.iter().map(|s| 1. - s).collect();
+            (Partition::new(clusters, noise), Some(confidence))
+        }
+        algorithm => fail(&format!("unknown algorithm: {algorithm}")),
+    }
+}
+
+fn write<W: io::Write>(
+    partition: &Partition,
+    confidence: Option<Vec<f64>>,
+    writer: W,
+) -> io::Result<()> {
+    match confidence {
+        Some(confidence) => {
+            PointAssignment::write_csv(&partition.assignments_with_confidence(&confidence), writer)
+        }
+        None => PointAssignment::write_csv(&partition.assignments(), writer),
+    }
+}
+
+fn parse() -> Options {
+    let mut options = Options {
+        algorithm: "dbscan".to_string(),
+        eps: None,
+        min_samples: 5,
+        min_cluster_size: 5,
+        metric: Distance::Euclidean,
+        columns: None,
+        output: None,
+        path: String::new(),
+    };
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("missing value for {arg}")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                help();
+                exit(0);
+            }
+            "-a" | "--algorithm" => options.algorithm = value().to_lowercase(),
+            "-e" | "--eps" => options.eps = Some(number(&value())),
+            "-m" | "--min-samples" => options.min_samples = number(&value()),
+            "-c" | "--min-cluster-size" => options.min_cluster_size = number(&value()),
+            "--metric" => {
+                options.metric = match value().to_lowercase().as_str() {
+                    "euclidean" => Distance::Euclidean,
+                    "manhattan" => Distance::Manhattan,
+                    "chebyshev" => Distance::Chebyshev,
+                    metric => fail(&format!("unknown metric: {metric}")),
+                }
+            }
+            "--columns" => {
+                options.columns = Some(value().split(',').map(|c| number(c.trim())).collect());
+            }
+            "-o" | "--output" => options.output = Some(value()),
+            _ if arg.starts_with('-') => fail(&format!("unknown flag: {arg}")),
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(&format!("unexpected argument: {arg}")),
+        }
+    }
+    options.path = path.unwrap_or_else(|| fail("missing data file"));
+    options
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid number: {value}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n");
+    help();
+    exit(2);
+}
+
+fn help() {
+    eprintln!(
+        "USAGE: \
+        \n petal-cluster [OPTIONS] <DATAFILE> \
+        \n \
+        \nClusters the rows of a CSV file and writes the cluster of each row as CSV. \
+        \n \
+        \nOPTIONS: \
+        \n    -a, --algorithm <NAME>       dbscan (default), optics or hdbscan \
+        \n    -e, --eps <EPS>              The radius of a neighborhood for dbscan (default 0.5), \
+        \n                                 the radius to extract clusters at for optics (default \
+        \n                                 infinite), or the cluster selection epsilon for hdbscan \
+        \n    -m, --min-samples <N>        The minimum number of points of a dense region \
+        \n                                 (default 5) \
+        \n    -c, --min-cluster-size <N>   The minimum size of a cluster for hdbscan (default 5) \
+        \n        --metric <NAME>          euclidean (default), manhattan or chebyshev \
+        \n        --columns <I,J,...>      The zero-based indices of the columns to read \
+        \n                                 (default all) \
+        \n    -o, --output <FILE>          Writes the labels to FILE instead of the standard output \
+        \n    -h, --help                   Prints help information \
+        \n \
+        \nThe first line of the file is skipped if it is a header. The output has one row per \
+        \npoint with its index, cluster ID (empty for noise), whether it is noise, and for \
+        \nhdbscan a confidence of one minus its outlier score."
+    );
+}
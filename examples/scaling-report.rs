@@ -0,0 +1,80 @@
+use std::{env, fs::File, io, process::exit};
+
+use petal_clustering::benchmark::{ScalingGrid, ScalingReport};
+
+fn main() {
+    let (grid, output) = parse();
+    let report = ScalingReport::run(&grid);
+    for &n_features in &grid.n_features {
+        for algorithm in ["dbscan", "hdbscan", "optics"] {
+            if let Some(seconds) = report.predict(algorithm, 1_000_000, n_features) {
+                eprintln!("{algorithm}: ~{seconds:.1}s for 1,000,000 points in {n_features}D");
+            }
+        }
+    }
+
+    let result = match &output {
+        Some(path) => File::create(path).and_then(|file| report.write_csv(file)),
+        None => report.write_csv(io::stdout().lock()),
+    };
+    if let Err(e) = result {
+        eprintln!("cannot write the report: {e}");
+        exit(1);
+    }
+}
+
+fn parse() -> (ScalingGrid, Option<String>) {
+    let mut grid = ScalingGrid::default();
+    let mut output = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("missing value for {arg}")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                help();
+                exit(0);
+            }
+            "-n" | "--n-samples" => grid.n_samples = numbers(&value()),
+            "-d" | "--n-features" => grid.n_features = numbers(&value()),
+            "-r" | "--repeats" => grid.repeats = number(&value()),
+            "-o" | "--output" => output = Some(value()),
+            _ => fail(&format!("unexpected argument: {arg}")),
+        }
+    }
+    (grid, output)
+}
+
+fn numbers(value: &str) -> Vec<usize> {
+    value.split(',').map(|v| number(v.trim())).collect()
+}
+
+fn number(value: &str) -> usize {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid number: {value}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n");
+    help();
+    exit(2);
+}
+
+fn help() {
+    eprintln!(
+        "USAGE: \
+        \n scaling-report [OPTIONS] \
+        \n \
+        \nTimes DBSCAN, HDBSCAN and OPTICS on Gaussian blobs of several sizes and writes \
+        \nthe timings as CSV. \
+        \n \
+        \nOPTIONS: \
+        \n -n, --n-samples <N,...>   numbers of points [default: 1000,2000,4000,8000] \
+        \n -d, --n-features <D,...>  numbers of dimensions [default: 2,8,32] \
+        \n -r, --repeats <R>         fits timed per size [default: 3] \
+        \n -o, --output <FILE>       the CSV file to write [default: stdout]"
+    );
+}
@@ -0,0 +1,78 @@
+#![no_main]
+
+use std::collections::{HashMap, HashSet};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use petal_clustering::fuzzing::{
+    hdbscan_condense_mst, hdbscan_find_clusters, hdbscan_glosh, hdbscan_label,
+};
+use petal_clustering::ClusterExtraction;
+
+#[derive(Debug, Arbitrary)]
+enum Extraction {
+    ExcessOfMass,
+    Fbcubed,
+    Leaf,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    /// Edge `i` connects point `i + 1` to an earlier point, so that the edges
+    /// always form a spanning tree.
+    edges: Vec<(u16, u8)>,
+    min_cluster_size: u8,
+    extraction: Extraction,
+    cluster_selection_epsilon: u8,
+    /// Points with a label, given as `(point, label)` pairs.
+    partial_labels: Vec<(u16, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let n = input.edges.len() + 1;
+    if n < 2 {
+        return;
+    }
+    let min_cluster_size = usize::from(input.min_cluster_size).max(2);
+    let mut mst: Vec<(usize, usize, f64)> = input
+        .edges
+        .iter()
+        .enumerate()
+        .map(|(i, &(parent, weight))| (usize::from(parent) % (i + 1), i + 1, f64::from(weight)))
+        .collect();
+    mst.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let labeled = hdbscan_label(&mst);
+    let condensed = hdbscan_condense_mst(&labeled, min_cluster_size);
+
+    let scores = hdbscan_glosh(&condensed, min_cluster_size);
+    assert_eq!(scores.len(), n);
+    assert!(scores.iter().all(|s| s.is_nan() || (0. ..=1.).contains(s)));
+
+    let extraction = match input.extraction {
+        Extraction::ExcessOfMass => ClusterExtraction::ExcessOfMass,
+        Extraction::Fbcubed => ClusterExtraction::Fbcubed,
+        Extraction::Leaf => ClusterExtraction::Leaf,
+    };
+    let mut partial_labels: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(point, label) in &input.partial_labels {
+        partial_labels
+            .entry(usize::from(label))
+            .or_default()
+            .push(usize::from(point) % n);
+    }
+    let (clusters, noise) = hdbscan_find_clusters(
+        &condensed,
+        (!partial_labels.is_empty()).then_some(&partial_labels),
+        extraction,
+        f64::from(input.cluster_selection_epsilon),
+    );
+
+    // every point is either in exactly one cluster or noise
+    let mut seen = HashSet::new();
+    for &i in clusters.values().flatten().chain(&noise) {
+        assert!(i < n);
+        assert!(seen.insert(i), "point {i} assigned twice");
+    }
+    assert_eq!(seen.len(), n);
+});
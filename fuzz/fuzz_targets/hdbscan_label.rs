@@ -0,0 +1,47 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use petal_clustering::fuzzing::hdbscan_label;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    /// Edge `i` connects point `i + 1` to an earlier point, so that the edges
+    /// always form a spanning tree.
+    edges: Vec<(u16, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let n = input.edges.len() + 1;
+    if n < 2 {
+        return;
+    }
+    let mut mst: Vec<(usize, usize, f64)> = input
+        .edges
+        .iter()
+        .enumerate()
+        .map(|(i, &(parent, weight))| (usize::from(parent) % (i + 1), i + 1, f64::from(weight)))
+        .collect();
+    mst.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let labeled = hdbscan_label(&mst);
+
+    // every point and every merged subtree except the root is a child once
+    let mut is_child = vec![false; 2 * n];
+    for &(parent, child, _, size) in &labeled {
+        assert!(parent >= n && parent < 2 * n);
+        assert!(child < parent);
+        assert!(size >= 1);
+        assert!(!is_child[child], "{child} merged twice");
+        is_child[child] = true;
+    }
+    assert!(is_child[..n].iter().all(|&c| c));
+    if let Some(&(root, ..)) = labeled.last() {
+        let size: usize = labeled
+            .iter()
+            .filter(|(parent, ..)| *parent == root)
+            .map(|&(.., size)| size)
+            .sum();
+        assert_eq!(size, n);
+    }
+});
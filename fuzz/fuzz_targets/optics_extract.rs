@@ -0,0 +1,62 @@
+#![no_main]
+
+use std::collections::HashSet;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use petal_clustering::fuzzing::optics_extract_clusters_and_noise;
+
+#[derive(Debug, Arbitrary)]
+struct Point {
+    /// The position of the point in the cluster ordering.
+    order: u16,
+    /// `None` for a point whose reachability is undefined.
+    reachability: Option<u16>,
+    core_distance: u16,
+    neighbors: Vec<u8>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    eps: u16,
+    min_samples: u8,
+    points: Vec<Point>,
+}
+
+fuzz_target!(|input: Input| {
+    let n = input.points.len();
+    if n == 0 {
+        return;
+    }
+    let mut ordered: Vec<usize> = (0..n).collect();
+    ordered.sort_by_key(|&i| input.points[i].order);
+    let reachability = input
+        .points
+        .iter()
+        .map(|p| p.reachability.map_or(f64::NAN, f64::from))
+        .collect();
+    let neighborhoods = input
+        .points
+        .iter()
+        .map(|p| {
+            let neighbors = p.neighbors.iter().map(|&j| usize::from(j) % n).collect();
+            (neighbors, f64::from(p.core_distance))
+        })
+        .collect();
+
+    let (clusters, noise) = optics_extract_clusters_and_noise(
+        f64::from(input.eps),
+        usize::from(input.min_samples),
+        ordered,
+        reachability,
+        neighborhoods,
+    );
+
+    // every point is either in exactly one cluster or noise
+    let mut seen = HashSet::new();
+    for &i in clusters.values().flatten().chain(&noise) {
+        assert!(i < n);
+        assert!(seen.insert(i), "point {i} assigned twice");
+    }
+    assert_eq!(seen.len(), n);
+});
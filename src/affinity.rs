@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+
+/// Affinity propagation clustering algorithm.
+///
+/// Affinity propagation exchanges messages between pairs of points until a
+/// set of exemplars emerges, and assigns each point to its most similar
+/// exemplar. It takes a similarity matrix instead of points, e.g., negative
+/// squared distances, and does not need the number of clusters, which is
+/// instead driven by `preference`.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::{array, Array2};
+/// use petal_clustering::{AffinityPropagation, Fit};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [8.5, 7.5]];
+/// let similarities = Array2::from_shape_fn((6, 6), |(i, j)| {
+///     -(&points.row(i) - &points.row(j)).mapv(|v| v * v).sum()
+/// });
+/// let mut model = AffinityPropagation::default();
+/// let (clusters, noise) = model.fit(&similarities, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// assert!(clusters[&0].contains(&model.exemplars()[0]));
+/// ```
+///
+/// # References
+/// - Frey, Brendan J., and Delbert Dueck. "Clustering by passing messages between data points."
+///   Science 315.5814 (2007): 972-976.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AffinityPropagation<A> {
+    /// The fraction of the previous messages kept in each update, between 0.5
+    /// and 1, which damps oscillations.
+    pub damping: A,
+
+    /// The similarity of each point to itself. Points with larger preferences
+    /// are more likely to become exemplars, so larger preferences lead to
+    /// more clusters. If `None`, the median of the similarities is used.
+    pub preference: Option<A>,
+
+    /// The maximum number of iterations.
+    pub max_iter: usize,
+
+    /// The iterations stop when the exemplars do not change for this many
+    /// iterations.
+    pub convergence_iter: usize,
+
+    /// The seed of the random number generator adding tiny noise to the
+    /// similarities, which breaks ties between equally good exemplars.
+    pub seed: u64,
+
+    exemplars: Vec<usize>,
+    converged: bool,
+}
+
+impl<A> Default for AffinityPropagation<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(A::from(0.5_f32).expect("valid float"), None)
+    }
+}
+
+impl<A> AffinityPropagation<A> {
+    /// Creates a model with the given `damping` and `preference`, running at
+    /// most 200 iterations and stopping after 15 without a change of exemplars.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::AffinityPropagation;
+    ///
+    /// let model = AffinityPropagation::new(0.9, Some(-10.));
+    /// assert_eq!(model.preference, Some(-10.));
+    /// assert_eq!(model.max_iter, 200);
+    /// assert!(model.exemplars().is_empty());
+    /// ```
+    #[must_use]
+    pub fn new(damping: A, preference: Option<A>) -> Self {
+        Self {
+            damping,
+            preference,
+            max_iter: 200,
+            convergence_iter: 15,
+            seed: 0,
+            exemplars: vec![],
+            converged: false,
+        }
+    }
+
+    /// Returns the exemplar of each cluster, indexed by cluster ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{AffinityPropagation, Fit};
+    ///
+    /// let similarities = array![[0., -1., -25.], [-1., 0., -26.], [-25., -26., 0.]];
+    /// let mut model = AffinityPropagation::new(0.5, Some(-5.));
+    /// let (clusters, _) = model.fit(&similarities, None);
+    ///
+    /// for (id, exemplar) in model.exemplars().iter().enumerate() {
+    ///     assert!(clusters[&id].contains(exemplar));
+    /// }
+    /// ```
+    #[must_use]
+    pub fn exemplars(&self) -> &[usize] {
+        &self.exemplars
+    }
+
+    /// Returns whether the last fit converged within `max_iter` iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{AffinityPropagation, Fit};
+    ///
+    /// let similarities = array![[0., -1., -25.], [-1., 0., -26.], [-25., -26., 0.]];
+    /// let mut model = AffinityPropagation::new(0.5, Some(-5.));
+    /// assert!(!model.converged());
+    /// model.fit(&similarities, None);
+    /// assert!(model.converged());
+    /// ```
+    #[must_use]
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+/// Fits the affinity propagation algorithm to the given similarity matrix.
+///
+/// # Parameters
+/// - `input`: A square matrix whose entry `(i, j)` is the similarity of point `i` to point `j`. The diagonal is replaced by `preference`.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster. The exemplar of cluster `i` is `exemplars()[i]`.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which are all points if no exemplar emerges.
+///
+/// # Panics
+/// Panics if `input` is not a square matrix.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for AffinityPropagation<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert_eq!(
+            input.nrows(),
+            input.ncols(),
+            "similarities should be square"
+        );
+        self.exemplars = vec![];
+        self.converged = false;
+        let n = input.nrows();
+        if n == 0 {
+            return (HashMap::new(), Vec::new());
+        }
+        if n == 1 {
+            self.exemplars = vec![0];
+            self.converged = true;
+            return (HashMap::from([(0, vec![0])]), Vec::new());
+        }
+
+        let mut similarities = input.to_owned();
+        let preference = self.preference.unwrap_or_else(|| median(input));
+        similarities.diag_mut().fill(preference);
+        add_noise(&mut similarities, self.seed);
+
+        let (exemplars, converged) = self.propagate(&similarities);
+        self.converged = converged;
+        if exemplars.is_empty() {
+            return (HashMap::new(), (0..n).collect());
+        }
+        self.exemplars = refine_exemplars(&similarities, &exemplars);
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, row) in similarities.rows().into_iter().enumerate() {
+            let cluster = self
+                .exemplars
+                .iter()
+                .position(|e| *e == i)
+                .unwrap_or_else(|| closest_exemplar(&self.exemplars, |e| row[e]));
+            clusters.entry(cluster).or_default().push(i);
+        }
+        (clusters, Vec::new())
+    }
+}
+
+impl<A> AffinityPropagation<A>
+where
+    A: FloatCore + FromPrimitive,
+{
+    /// Exchanges responsibilities and availabilities, and returns the
+    /// exemplars and whether they converged.
+    fn propagate(&self, similarities: &Array2<A>) -> (Vec<usize>, bool) {
+        let n = similarities.nrows();
+        let damping = self.damping;
+        let keep = A::one() - damping;
+        let mut responsibilities = Array2::<A>::zeros((n, n));
+        let mut availabilities = Array2::<A>::zeros((n, n));
+        // the number of consecutive iterations each point has been an
+        // exemplar, or has not been one
+        let mut stable = vec![0; n];
+        let mut is_exemplar = vec![false; n];
+
+        for _ in 0..self.max_iter {
+            for i in 0..n {
+                let (mut first, mut second, mut best) = (A::neg_infinity(), A::neg_infinity(), 0);
+                for k in 0..n {
+                    let v = availabilities[[i, k]] + similarities[[i, k]];
+                    if v > first {
+                        second = first;
+                        first = v;
+                        best = k;
+                    } else if v > second {
+                        second = v;
+                    }
+                }
+                for k in 0..n {
+                    let max_other = if k == best { second } else { first };
+                    let r = similarities[[i, k]] - max_other;
+                    responsibilities[[i, k]] = damping * responsibilities[[i, k]] + keep * r;
+                }
+            }
+
+            for k in 0..n {
+                let positive = (0..n).filter(|i| *i != k).fold(A::zero(), |acc, i| {
+                    acc + responsibilities[[i, k]].max(A::zero())
+                });
+                for i in 0..n {
+                    let a = if i == k {
+                        positive
+                    } else {
+                        let others = positive - responsibilities[[i, k]].max(A::zero());
+                        (responsibilities[[k, k]] + others).min(A::zero())
+                    };
+                    availabilities[[i, k]] = damping * availabilities[[i, k]] + keep * a;
+                }
+            }
+
+            let mut unchanged = true;
+            for k in 0..n {
+                let exemplar = availabilities[[k, k]] + responsibilities[[k, k]] > A::zero();
+                if exemplar == is_exemplar[k] {
+                    stable[k] += 1;
+                } else {
+                    stable[k] = 1;
+                    is_exemplar[k] = exemplar;
+                }
+                unchanged &= stable[k] >= self.convergence_iter;
+            }
+            if unchanged && is_exemplar.contains(&true) {
+                return (exemplars_of(&is_exemplar), true);
+            }
+        }
+        (exemplars_of(&is_exemplar), false)
+    }
+}
+
+fn exemplars_of(is_exemplar: &[bool]) -> Vec<usize> {
+    is_exemplar
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| if *e { Some(i) } else { None })
+        .collect()
+}
+
+/// Replaces each exemplar with the member of its cluster that is the most
+/// similar to the other members.
+fn refine_exemplars<A: FloatCore>(similarities: &Array2<A>, exemplars: &[usize]) -> Vec<usize> {
+    let n = similarities.nrows();
+    let labels: Vec<usize> = (0..n)
+        .map(|i| {
+            exemplars
+                .iter()
+                .position(|e| *e == i)
+                .unwrap_or_else(|| closest_exemplar(exemplars, |e| similarities[[i, e]]))
+        })
+        .collect();
+
+    (0..exemplars.len())
+        .map(|k| {
+            let members: Vec<usize> = (0..n).filter(|i| labels[*i] == k).collect();
+            members
+                .iter()
+                .copied()
+                .map(|j| {
+                    let total = members
+                        .iter()
+                        .fold(A::zero(), |acc, i| acc + similarities[[*i, j]]);
+                    (j, total)
+                })
+                .fold((exemplars[k], A::neg_infinity()), |best, (j, total)| {
+                    if total > best.1 {
+                        (j, total)
+                    } else {
+                        best
+                    }
+                })
+                .0
+        })
+        .collect()
+}
+
+/// Returns the position of the exemplar with the largest similarity.
+fn closest_exemplar<A: FloatCore>(exemplars: &[usize], similarity: impl Fn(usize) -> A) -> usize {
+    exemplars
+        .iter()
+        .enumerate()
+        .fold((0, A::neg_infinity()), |best, (k, e)| {
+            let s = similarity(*e);
+            if s > best.1 {
+                (k, s)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+fn median<S, A>(input: &ArrayBase<S, Ix2>) -> A
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    let mut values: Vec<A> = input.iter().copied().collect();
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).expect("no NaN similarities"));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / (A::one() + A::one())
+    } else {
+        values[mid]
+    }
+}
+
+/// Adds noise, relative to the magnitude of each similarity, that is small
+/// enough not to change the result except for breaking ties.
+fn add_noise<A: FloatCore + FromPrimitive>(similarities: &mut Array2<A>, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let tiny = A::min_positive_value() * A::from_u8(100).expect("valid float");
+    for s in similarities.iter_mut() {
+        let noise = A::from_f64(rng.random::<f64>() - 0.5).expect("valid float");
+        *s = *s + (A::epsilon() * *s + tiny) * noise;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    fn negative_squared_distances(points: &Array2<f64>) -> Array2<f64> {
+        let n = points.nrows();
+        Array2::from_shape_fn((n, n), |(i, j)| {
+            -(&points.row(i) - &points.row(j)).mapv(|v| v * v).sum()
+        })
+    }
+
+    #[test]
+    fn default() {
+        let model = AffinityPropagation::<f32>::default();
+        assert!((model.damping - 0.5).abs() < f32::EPSILON);
+        assert_eq!(model.preference, None);
+        assert_eq!(model.max_iter, 200);
+    }
+
+    #[test]
+    fn affinity_propagation() {
+        let points = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [10., 10.],
+            [10., 11.],
+            [11., 10.],
+            [0., 20.],
+            [1., 20.],
+            [0., 21.],
+        ];
+        let mut model = AffinityPropagation::default();
+        let (clusters, noise) = model.fit(&negative_squared_distances(&points), None);
+        assert!(model.converged());
+        assert!(noise.is_empty());
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(model.exemplars().len(), 3);
+        for (id, members) in &clusters {
+            assert_eq!(members.len(), 3);
+            assert!(members.iter().all(|i| i / 3 == members[0] / 3));
+            assert!(members.contains(&model.exemplars()[*id]));
+        }
+        // the exemplars are the corner points closest to the other two
+        let mut exemplars = model.exemplars().to_vec();
+        exemplars.sort_unstable();
+        assert_eq!(exemplars, [0, 3, 6]);
+    }
+
+    #[test]
+    fn preference() {
+        let points = array![[0.], [1.], [2.], [10.], [11.], [12.]];
+        let similarities = negative_squared_distances(&points);
+        let mut model = AffinityPropagation::new(0.7, Some(-1000.));
+        let (clusters, _) = model.fit(&similarities, None);
+        assert_eq!(clusters.len(), 1);
+
+        model.preference = Some(-0.1);
+        let (clusters, _) = model.fit(&similarities, None);
+        assert!(clusters.len() > 2);
+    }
+
+    #[test]
+    fn fit_small() {
+        let mut model = AffinityPropagation::default();
+        let empty: Array2<f64> = Array2::zeros((0, 0));
+        let (clusters, noise) = model.fit(&empty, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+
+        let (clusters, noise) = model.fit(&array![[0.]], None);
+        assert_eq!(clusters[&0], [0]);
+        assert!(noise.is_empty());
+        assert_eq!(model.exemplars(), [0]);
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+use petal_neighbors::distance::{Euclidean, Metric};
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::dendrogram::Dendrogram;
+use crate::parallel::prelude::*;
+
+/// The distance between two clusters in agglomerative clustering.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Linkage {
+    /// The increase in the sum of squared Euclidean distances to the
+    /// centroids caused by merging the two clusters, as
+    /// `sqrt(2 * |A| * |B| / (|A| + |B|)) * ||c_A - c_B||`. It is computed
+    /// from the centroids, without a distance matrix.
+    #[default]
+    Ward,
+
+    /// The mean distance between the points of the two clusters.
+    Average,
+
+    /// The largest distance between the points of the two clusters.
+    Complete,
+
+    /// The smallest distance between the points of the two clusters.
+    Single,
+}
+
+/// Agglomerative hierarchical clustering with the nearest-neighbor-chain
+/// algorithm.
+///
+/// Starting from singleton clusters, the two closest clusters under
+/// `linkage` are merged until `n_clusters` clusters remain, or until the
+/// closest clusters are farther than `distance_threshold`. The full hierarchy
+/// is kept as a dendrogram.
+///
+/// The nearest-neighbor chain finds the same merges as the naive algorithm in
+/// `O(n^2)` time. With [`Linkage::Ward`], clusters are represented by their
+/// centroids and sizes, so the memory grows linearly with the number of
+/// points and Euclidean distances are used regardless of `metric`. The other
+/// linkages update a matrix of the distances under `metric` between all
+/// clusters, which takes `O(n^2)` memory, as does
+/// [`AgglomerativeClustering::fit_precomputed`] with any linkage.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{AgglomerativeClustering, Fit, Linkage};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let mut agglomerative = AgglomerativeClustering::new(3, Linkage::Ward, Euclidean::default());
+/// let (clusters, noise) = agglomerative.fit(&points, None);
+///
+/// assert_eq!(clusters[&0], [0, 1, 2]);
+/// assert_eq!(clusters[&1], [3, 4]);
+/// assert_eq!(clusters[&2], [5]);
+/// assert!(noise.is_empty());
+/// assert_eq!(agglomerative.dendrogram().len(), 5);
+/// ```
+///
+/// # References
+/// - Müllner, Daniel. "Modern hierarchical, agglomerative clustering algorithms."
+///   arXiv preprint arXiv:1109.2378 (2011).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AgglomerativeClustering<A, M> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The distance above which clusters are not merged. If set, it
+    /// overrides `n_clusters`.
+    pub distance_threshold: Option<A>,
+
+    /// The distance between clusters.
+    pub linkage: Linkage,
+
+    /// The metric to compute distance between the entries, unless `linkage`
+    /// is [`Linkage::Ward`].
+    pub metric: M,
+
+    dendrogram: Dendrogram<A>,
+}
+
+impl<A> Default for AgglomerativeClustering<A, Euclidean> {
+    fn default() -> Self {
+        Self::new(2, Linkage::Ward, Euclidean::default())
+    }
+}
+
+impl<A, M> AgglomerativeClustering<A, M> {
+    /// Creates a model forming `n_clusters` clusters under `linkage`, with
+    /// distances measured by `metric` and no distance threshold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{AgglomerativeClustering, Linkage};
+    ///
+    /// let agglomerative =
+    ///     AgglomerativeClustering::<f64, _>::new(2, Linkage::Average, Euclidean::default());
+    /// assert_eq!(agglomerative.n_clusters, 2);
+    /// assert_eq!(agglomerative.distance_threshold, None);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize, linkage: Linkage, metric: M) -> Self {
+        Self {
+            n_clusters,
+            distance_threshold: None,
+            linkage,
+            metric,
+            dendrogram: Dendrogram::default(),
+        }
+    }
+
+    /// Returns the merges of the last fit in increasing order of distance,
+    /// as `(left, right, distance, size)` tuples. A cluster ID below the
+    /// number of points `n` is a point, and the cluster formed by the `i`-th
+    /// merge has ID `n + i`, as in the linkage matrices of `SciPy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{AgglomerativeClustering, Fit, Linkage};
+    ///
+    /// let points = array![[0.], [1.], [5.]];
+    /// let mut agglomerative = AgglomerativeClustering::new(1, Linkage::Single, Euclidean::default());
+    /// agglomerative.fit(&points, None);
+    ///
+    /// // points 0 and 1 form cluster 3, which merges with point 2
+    /// assert_eq!(agglomerative.dendrogram(), [(0, 1, 1., 2), (2, 3, 4., 3)]);
+    /// ```
+    #[must_use]
+    pub fn dendrogram(&self) -> &[(usize, usize, A, usize)] {
+        self.dendrogram.merges()
+    }
+
+    /// Returns the hierarchy of the last fit, which can be cut into a
+    /// different number of clusters without fitting again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{AgglomerativeClustering, Fit, Linkage};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut agglomerative = AgglomerativeClustering::new(3, Linkage::Ward, Euclidean::default());
+    /// agglomerative.fit(&points, None);
+    /// let (clusters, _) = agglomerative.hierarchy().cut_at(2);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2, 3, 4]);
+    /// assert_eq!(clusters[&1], [5]);
+    /// ```
+    #[must_use]
+    pub fn hierarchy(&self) -> &Dendrogram<A> {
+        &self.dendrogram
+    }
+}
+
+impl<A, M> AgglomerativeClustering<A, M>
+where
+    A: Float + FromPrimitive + Send + Sync,
+{
+    /// Fits agglomerative clustering to a precomputed matrix of distances
+    /// between points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `distances` is not square.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{AgglomerativeClustering, Linkage};
+    ///
+    /// let distances = array![[0., 1., 9.], [1., 0., 8.], [9., 8., 0.]];
+    /// let mut agglomerative = AgglomerativeClustering::new(2, Linkage::Average, Euclidean::default());
+    /// let (clusters, _) = agglomerative.fit_precomputed(&distances);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1]);
+    /// assert_eq!(agglomerative.dendrogram()[1], (2, 3, 8.5, 3));
+    /// ```
+    pub fn fit_precomputed<S>(
+        &mut self,
+        distances: &ArrayBase<S, Ix2>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        assert_eq!(
+            distances.nrows(),
+            distances.ncols(),
+            "distances must be a square matrix"
+        );
+        let mut matrix = DistanceMatrix::new(distances.to_owned(), self.linkage);
+        self.build(distances.nrows(), &mut matrix)
+    }
+
+    /// Builds the dendrogram of `n` points with the nearest-neighbor chain,
+    /// and cuts it.
+    fn build<D: Dissimilarity<A>>(
+        &mut self,
+        n: usize,
+        dissimilarity: &mut D,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let merges = nn_chain(n, dissimilarity);
+        self.dendrogram = Dendrogram::from_edges(n, &merges);
+        match self.distance_threshold {
+            Some(threshold) => self.dendrogram.cut_at_distance(threshold),
+            None => self.dendrogram.cut_at(self.n_clusters),
+        }
+    }
+}
+
+/// Fits agglomerative clustering to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+///   Clusters are numbered in the order of their first point.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A, M>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for AgglomerativeClustering<A, M>
+where
+    A: Float + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n = input.nrows();
+        if self.linkage == Linkage::Ward {
+            let mut centroids = Centroids {
+                centroids: input.to_owned(),
+                sizes: vec![1; n],
+            };
+            return self.build(n, &mut centroids);
+        }
+
+        let input = input.view();
+        let mut matrix = DistanceMatrix::new(
+            pairwise_distances(&input.view(), &self.metric),
+            self.linkage,
+        );
+        self.build(n, &mut matrix)
+    }
+}
+
+/// The distances between the clusters of agglomerative clustering, where the
+/// cluster in slot `i` contains point `i`.
+trait Dissimilarity<A> {
+    fn distance(&self, i: usize, j: usize) -> A;
+
+    /// Merges the cluster in slot `i`, at distance `d`, into that in slot
+    /// `j`.
+    fn merge(&mut self, i: usize, j: usize, d: A);
+}
+
+/// Clusters represented by their centroids and sizes, for Ward linkage.
+struct Centroids<A> {
+    centroids: Array2<A>,
+    sizes: Vec<usize>,
+}
+
+impl<A: Float + FromPrimitive> Dissimilarity<A> for Centroids<A> {
+    fn distance(&self, i: usize, j: usize) -> A {
+        let (ni, nj) = (self.sizes[i], self.sizes[j]);
+        let squared = self
+            .centroids
+            .row(i)
+            .iter()
+            .zip(self.centroids.row(j))
+            .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b));
+        let factor = A::from_usize(2 * ni * nj).expect("valid float")
+            / A::from_usize(ni + nj).expect("valid float");
+        (factor * squared).sqrt()
+    }
+
+    fn merge(&mut self, i: usize, j: usize, _d: A) {
+        let ni = A::from_usize(self.sizes[i]).expect("valid float");
+        let nj = A::from_usize(self.sizes[j]).expect("valid float");
+        let ci = self.centroids.row(i).to_owned();
+        self.centroids
+            .row_mut(j)
+            .zip_mut_with(&ci, |cj, ci| *cj = (*cj * nj + *ci * ni) / (ni + nj));
+        self.sizes[j] += self.sizes[i];
+    }
+}
+
+/// The distances between all clusters, updated with the Lance-Williams
+/// formula of the linkage.
+struct DistanceMatrix<A> {
+    distances: Array2<A>,
+    sizes: Vec<usize>,
+    linkage: Linkage,
+    active: Vec<bool>,
+}
+
+impl<A> DistanceMatrix<A> {
+    fn new(distances: Array2<A>, linkage: Linkage) -> Self {
+        let n = distances.nrows();
+        Self {
+            distances,
+            sizes: vec![1; n],
+            linkage,
+            active: vec![true; n],
+        }
+    }
+}
+
+impl<A: Float + FromPrimitive> Dissimilarity<A> for DistanceMatrix<A> {
+    fn distance(&self, i: usize, j: usize) -> A {
+        self.distances[[i, j]]
+    }
+
+    fn merge(&mut self, i: usize, j: usize, d: A) {
+        self.active[i] = false;
+        let ni = A::from_usize(self.sizes[i]).expect("valid float");
+        let nj = A::from_usize(self.sizes[j]).expect("valid float");
+        for k in 0..self.active.len() {
+            if !self.active[k] || k == j {
+                continue;
+            }
+            let (dki, dkj) = (self.distances[[k, i]], self.distances[[k, j]]);
+            let updated = match self.linkage {
+                Linkage::Ward => {
+                    let nk = A::from_usize(self.sizes[k]).expect("valid float");
+                    (((ni + nk) * dki * dki + (nj + nk) * dkj * dkj - nk * d * d) / (ni + nj + nk))
+                        .sqrt()
+                }
+                Linkage::Average => (ni * dki + nj * dkj) / (ni + nj),
+                Linkage::Complete => dki.max(dkj),
+                Linkage::Single => dki.min(dkj),
+            };
+            self.distances[[k, j]] = updated;
+            self.distances[[j, k]] = updated;
+        }
+        self.sizes[j] += self.sizes[i];
+    }
+}
+
+/// Finds the merges of agglomerative clustering with the nearest-neighbor
+/// chain, as `(i, j, distance)` tuples of slots, in the order they are found
+/// rather than by distance.
+fn nn_chain<A: Float, D: Dissimilarity<A>>(
+    n: usize,
+    dissimilarity: &mut D,
+) -> Vec<(usize, usize, A)> {
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut chain: Vec<usize> = Vec::with_capacity(n);
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+    while active.len() > 1 {
+        if chain.is_empty() {
+            chain.push(active[0]);
+        }
+        let top = *chain.last().expect("non-empty chain");
+        let previous = chain.len().checked_sub(2).map(|i| chain[i]);
+
+        // the previous cluster in the chain wins ties, so the chain ends
+        let mut nearest = previous.map(|p| (dissimilarity.distance(top, p), p));
+        for &other in &active {
+            if other == top {
+                continue;
+            }
+            let distance = dissimilarity.distance(top, other);
+            if nearest.map_or(true, |(best, _)| distance < best) {
+                nearest = Some((distance, other));
+            }
+        }
+        let (distance, other) = nearest.expect("at least two active clusters");
+
+        if Some(other) == previous {
+            chain.truncate(chain.len() - 2);
+            let (removed, kept) = (top.max(other), top.min(other));
+            dissimilarity.merge(removed, kept, distance);
+            active.retain(|c| *c != removed);
+            merges.push((removed, kept, distance));
+        } else {
+            chain.push(other);
+        }
+    }
+    merges
+}
+
+/// Returns the matrix of the distances between all pairs of rows of `input`.
+pub(crate) fn pairwise_distances<A, M>(input: &ArrayView2<A>, metric: &M) -> Array2<A>
+where
+    A: Float + Send + Sync,
+    M: Metric<A> + Sync,
+{
+    let n = input.nrows();
+    let rows: Vec<_> = input.rows().into_iter().collect();
+    let distances: Vec<A> = rows
+        .par_iter()
+        .flat_map_iter(|p| rows.iter().map(|q| metric.distance(p, q)))
+        .collect();
+    Array2::from_shape_vec((n, n), distances).expect("n * n distances")
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn ward_matches_lance_williams() {
+        let data = array![
+            [0., 0.],
+            [0.5, 0.2],
+            [4., 4.],
+            [4.2, 3.9],
+            [9., 0.],
+            [9.5, 0.1],
+            [3., 1.],
+        ];
+        let mut geometric = AgglomerativeClustering::new(3, Linkage::Ward, Euclidean::default());
+        let (clusters, noise) = geometric.fit(&data, None);
+        assert!(noise.is_empty());
+
+        let distances = pairwise_distances(&data.view(), &Euclidean::default());
+        let mut matrix = AgglomerativeClustering::new(3, Linkage::Ward, Euclidean::default());
+        assert_eq!(matrix.fit_precomputed(&distances).0, clusters);
+        for (a, b) in geometric.dendrogram().iter().zip(matrix.dendrogram()) {
+            assert_eq!((a.0, a.1, a.3), (b.0, b.1, b.3));
+            assert!((a.2 - b.2).abs() < 1e-9);
+        }
+        assert_eq!(geometric.dendrogram().last().map(|m| m.3), Some(7));
+    }
+
+    #[test]
+    fn linkages() {
+        // a chain of points and a tight pair
+        let data = array![[0.], [1.], [3.], [6.5], [20.], [20.5]];
+        let expected = [
+            (Linkage::Single, 3.5),
+            (Linkage::Complete, 6.5),
+            (Linkage::Average, 15.5 / 3.),
+        ];
+        for (linkage, distance) in expected {
+            let mut model = AgglomerativeClustering::new(2, linkage, Euclidean::default());
+            let (clusters, _) = model.fit(&data, None);
+            assert_eq!(clusters[&0], [0, 1, 2, 3], "{linkage:?}");
+            assert_eq!(clusters[&1], [4, 5], "{linkage:?}");
+
+            // the merge of the chain is the last one before the two clusters
+            let dendrogram = model.dendrogram();
+            assert_eq!(dendrogram.len(), 5);
+            assert_eq!(dendrogram[0], (4, 5, 0.5, 2));
+            assert!((dendrogram[3].2 - distance).abs() < 1e-12, "{linkage:?}");
+            assert_eq!(dendrogram[3].3, 4);
+        }
+    }
+
+    #[test]
+    fn distance_threshold() {
+        let data = array![[0.], [1.], [5.], [6.], [20.]];
+        let mut model = AgglomerativeClustering::new(1, Linkage::Single, Euclidean::default());
+        model.distance_threshold = Some(2.);
+        let (clusters, _) = model.fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[&0], [0, 1]);
+        assert_eq!(clusters[&1], [2, 3]);
+        assert_eq!(clusters[&2], [4]);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 2));
+        let mut model = AgglomerativeClustering::default();
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+        assert!(model.dendrogram().is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Conversions between Apache Arrow arrays and the inputs and outputs of the
+//! clusterers, enabled with the `arrow` feature.
+//!
+//! Record batches with one column per feature are always copied, one copy
+//! per column, into a column-major array with [`record_batch_to_array`],
+//! since Arrow stores each column in a buffer of its own. Only points stored
+//! as fixed-size lists, as embeddings usually are, are viewed in place,
+//! without copying, with [`fixed_size_list_view`]. Polars data frames are
+//! converted by the `polars` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use arrow_array::{types::Float64Type, Array, Float64Array, RecordBatch};
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{arrow, Dbscan, Fit};
+//!
+//! let batch = RecordBatch::try_from_iter([
+//!     ("x", Arc::new(Float64Array::from(vec![1., 2., 2., 8., 8., 25.])) as _),
+//!     ("y", Arc::new(Float64Array::from(vec![2., 2., 2.3, 7., 8., 80.])) as _),
+//! ])
+//! .unwrap();
+//! let points = arrow::record_batch_to_array::<Float64Type>(&batch).unwrap();
+//! let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit(&points, None);
+//!
+//! let labels = arrow::labels(batch.num_rows(), &clusters, &noise);
+//! assert_eq!(labels.value(0), 0);
+//! assert_eq!(labels.value(3), 1);
+//! assert!(labels.is_null(5));
+//! ```
+
+use std::collections::HashMap;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::ArrowPrimitiveType;
+use arrow_array::{Array, FixedSizeListArray, Int64Array, RecordBatch};
+use arrow_schema::ArrowError;
+use ndarray::{Array2, ArrayView2, ShapeBuilder};
+
+/// Copies the columns of `batch` into an array with one row per record and
+/// one column per field. Unlike [`fixed_size_list_view`], this always copies
+/// every value.
+///
+/// # Errors
+/// Returns an error if a column is not of type `T`, or has null values.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use arrow_array::types::{Float32Type, Float64Type};
+/// use arrow_array::{Float64Array, RecordBatch};
+/// use ndarray::array;
+/// use petal_clustering::arrow::record_batch_to_array;
+///
+/// let batch = RecordBatch::try_from_iter([
+///     ("x", Arc::new(Float64Array::from(vec![1., 2.])) as _),
+///     ("y", Arc::new(Float64Array::from(vec![3., 4.])) as _),
+/// ])
+/// .unwrap();
+/// assert_eq!(record_batch_to_array::<Float64Type>(&batch).unwrap(), array![[1., 3.], [2., 4.]]);
+///
+/// // the columns should all have the requested type
+/// assert!(record_batch_to_array::<Float32Type>(&batch).is_err());
+/// ```
+pub fn record_batch_to_array<T>(batch: &RecordBatch) -> Result<Array2<T::Native>, ArrowError>
+where
+    T: ArrowPrimitiveType,
+{
+    let mut values = Vec::with_capacity(batch.num_rows() * batch.num_columns());
+    for (column, field) in batch.columns().iter().zip(batch.schema().fields()) {
+        let column = column.as_primitive_opt::<T>().ok_or_else(|| {
+            ArrowError::SchemaError(format!(
+                "column `{}` is of type {}, not {}",
+                field.name(),
+                column.data_type(),
+                T::DATA_TYPE
+            ))
+        })?;
+        if column.null_count() > 0 {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "column `{}` has null values",
+                field.name()
+            )));
+        }
+        values.extend_from_slice(column.values());
+    }
+    Array2::from_shape_vec((batch.num_rows(), batch.num_columns()).f(), values)
+        .map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+}
+
+/// Views a fixed-size list array, whose lists are the points, as an array
+/// with one row per point, without copying.
+///
+/// # Errors
+/// Returns an error if the values are not of type `T`, or if there are null
+/// lists or values.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::types::Float64Type;
+/// use arrow_array::FixedSizeListArray;
+/// use ndarray::array;
+/// use petal_clustering::arrow::fixed_size_list_view;
+///
+/// let embeddings = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+///     [Some([Some(1.), Some(2.)]), Some([Some(3.), Some(4.)])],
+///     2,
+/// );
+/// let points = fixed_size_list_view::<Float64Type>(&embeddings).unwrap();
+/// assert_eq!(points, array![[1., 2.], [3., 4.]]);
+/// ```
+pub fn fixed_size_list_view<T>(
+    points: &FixedSizeListArray,
+) -> Result<ArrayView2<'_, T::Native>, ArrowError>
+where
+    T: ArrowPrimitiveType,
+{
+    let values = points.values().as_primitive_opt::<T>().ok_or_else(|| {
+        ArrowError::SchemaError(format!(
+            "values are of type {}, not {}",
+            points.value_type(),
+            T::DATA_TYPE
+        ))
+    })?;
+    if points.null_count() > 0 || values.null_count() > 0 {
+        return Err(ArrowError::InvalidArgumentError(
+            "points have null values".to_string(),
+        ));
+    }
+    let dim = usize::try_from(points.value_length())
+        .map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))?;
+    let n = points.len();
+    let values = values.values();
+    if values.len() < n * dim {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "{} values are not enough for {n} points of {dim} features",
+            values.len()
+        )));
+    }
+    ArrayView2::from_shape((n, dim), &values[..n * dim])
+        .map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+}
+
+/// Returns the cluster ID of each of `n` points, with nulls for noise.
+///
+/// # Panics
+/// Panics if a point is not less than `n`, or if a cluster ID does not fit in
+/// an `i64`.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::Array;
+/// use maplit::hashmap;
+/// use petal_clustering::arrow::labels;
+///
+/// let labels = labels(3, &hashmap! {4 => vec![0, 2]}, &[1]);
+/// assert_eq!(labels.value(2), 4);
+/// assert!(labels.is_null(1));
+/// ```
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn labels(n: usize, clusters: &HashMap<usize, Vec<usize>>, noise: &[usize]) -> Int64Array {
+    let mut labels = vec![None; n];
+    for (id, members) in clusters {
+        let id = i64::try_from(*id).expect("cluster ID fits in an i64");
+        for &i in members {
+            labels[i] = Some(id);
+        }
+    }
+    debug_assert!(noise.iter().all(|i| labels[*i].is_none()));
+    Int64Array::from(labels)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arrow_array::types::{Float32Type, Float64Type};
+    use arrow_array::{Float32Array, Float64Array};
+    use arrow_schema::{DataType, Field};
+    use maplit::hashmap;
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn record_batch() {
+        let batch = RecordBatch::try_from_iter([
+            ("x", Arc::new(Float32Array::from(vec![1., 2., 3.])) as _),
+            ("y", Arc::new(Float32Array::from(vec![4., 5., 6.])) as _),
+        ])
+        .unwrap();
+        let points = record_batch_to_array::<Float32Type>(&batch).unwrap();
+        assert_eq!(points, array![[1., 4.], [2., 5.], [3., 6.]]);
+
+        let error = record_batch_to_array::<Float64Type>(&batch).unwrap_err();
+        assert!(error.to_string().contains("column `x` is of type Float32"));
+
+        let batch = RecordBatch::try_from_iter([(
+            "x",
+            Arc::new(Float64Array::from(vec![Some(1.), None])) as _,
+        )])
+        .unwrap();
+        assert!(record_batch_to_array::<Float64Type>(&batch).is_err());
+    }
+
+    #[test]
+    fn fixed_size_list() {
+        let values = Arc::new(Float64Array::from(vec![1., 2., 3., 4., 5., 6.]));
+        let field = Arc::new(Field::new("item", DataType::Float64, false));
+        let points = FixedSizeListArray::new(field, 2, values, None);
+        let view = fixed_size_list_view::<Float64Type>(&points).unwrap();
+        assert_eq!(view, array![[1., 2.], [3., 4.], [5., 6.]]);
+
+        let sliced = points.slice(1, 2);
+        let view = fixed_size_list_view::<Float64Type>(&sliced).unwrap();
+        assert_eq!(view, array![[3., 4.], [5., 6.]]);
+
+        assert!(fixed_size_list_view::<Float32Type>(&points).is_err());
+    }
+
+    #[test]
+    fn labels() {
+        let labels = super::labels(4, &hashmap! {0 => vec![0, 2], 1 => vec![3]}, &[1]);
+        assert_eq!(
+            labels.iter().collect::<Vec<_>>(),
+            [Some(0), None, Some(0), Some(1)]
+        );
+    }
+}
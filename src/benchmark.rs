@@ -0,0 +1,376 @@
+//! Timing of the clustering algorithms over a grid of input sizes, enabled
+//! with the `benchmark` feature.
+//!
+//! A [`ScalingReport`] times fits on [`make_blobs`] data of every size in a
+//! [`ScalingGrid`], and fits a power law to the timings, so that the runtime
+//! on larger inputs can be estimated on the machine at hand before running a
+//! long parameter sweep. The report can be saved as CSV for plotting or
+//! comparing machines.
+//!
+//! # Examples
+//!
+//! ```
+//! use petal_clustering::benchmark::{ScalingGrid, ScalingReport};
+//!
+//! let grid = ScalingGrid {
+//!     n_samples: vec![200, 400],
+//!     n_features: vec![2],
+//!     repeats: 1,
+//!     ..ScalingGrid::default()
+//! };
+//! let report = ScalingReport::run(&grid);
+//! assert_eq!(report.timings.len(), 6);
+//!
+//! let seconds = report.predict("hdbscan", 100_000, 2).unwrap();
+//! assert!(seconds > 0.);
+//!
+//! let mut csv = Vec::new();
+//! report.write_csv(&mut csv).unwrap();
+//! assert!(csv.starts_with(b"algorithm,n_samples,n_features"));
+//! ```
+
+use std::hint::black_box;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use ndarray::ArrayView2;
+use petal_neighbors::distance::Euclidean;
+use serde::{Deserialize, Serialize};
+
+use crate::datasets::make_blobs;
+use crate::{Dbscan, Fit, HDbscan, Optics};
+
+/// The input sizes a [`ScalingReport`] times an algorithm on.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScalingGrid {
+    /// The numbers of points.
+    pub n_samples: Vec<usize>,
+
+    /// The numbers of dimensions.
+    pub n_features: Vec<usize>,
+
+    /// The number of blobs the points are drawn from.
+    pub n_centers: usize,
+
+    /// The number of fits timed for each size. The median is reported.
+    pub repeats: usize,
+
+    /// The seed of the data.
+    pub seed: u64,
+}
+
+impl Default for ScalingGrid {
+    fn default() -> Self {
+        Self {
+            n_samples: vec![1000, 2000, 4000, 8000],
+            n_features: vec![2, 8, 32],
+            n_centers: 3,
+            repeats: 3,
+            seed: 0,
+        }
+    }
+}
+
+/// The time an algorithm took on one input size.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Timing {
+    /// The name the algorithm was measured under.
+    pub algorithm: String,
+
+    /// The number of points.
+    pub n_samples: usize,
+
+    /// The number of dimensions.
+    pub n_features: usize,
+
+    /// The number of fits timed.
+    pub repeats: usize,
+
+    /// The median time of a fit, in seconds.
+    pub seconds: f64,
+
+    /// The shortest time of a fit, in seconds.
+    pub min_seconds: f64,
+
+    /// The longest time of a fit, in seconds.
+    pub max_seconds: f64,
+}
+
+/// Timings of algorithms over a [`ScalingGrid`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ScalingReport {
+    /// The timings, in the order they were measured.
+    pub timings: Vec<Timing>,
+}
+
+impl ScalingReport {
+    /// Times DBSCAN, HDBSCAN and OPTICS over `grid`, as "dbscan", "hdbscan"
+    /// and "optics". HDBSCAN runs with its default parameters, and DBSCAN and
+    /// OPTICS with `min_samples` of 5 and a radius of half the square root of
+    /// the number of dimensions, so that neighborhoods stay comparable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::benchmark::{ScalingGrid, ScalingReport};
+    ///
+    /// let grid = ScalingGrid {
+    ///     n_samples: vec![100],
+    ///     n_features: vec![2, 4],
+    ///     repeats: 1,
+    ///     ..ScalingGrid::default()
+    /// };
+    /// let report = ScalingReport::run(&grid);
+    ///
+    /// let algorithms: Vec<_> = report.timings.iter().map(|t| t.algorithm.as_str()).collect();
+    /// assert_eq!(algorithms, ["dbscan", "dbscan", "hdbscan", "hdbscan", "optics", "optics"]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn run(grid: &ScalingGrid) -> Self {
+        let mut report = Self::default();
+        report.measure("dbscan", grid, |input| {
+            let eps = 0.5 * (input.ncols() as f64).sqrt();
+            Dbscan::new(eps, 5, Euclidean::default()).fit(input, None)
+        });
+        report.measure("hdbscan", grid, |input| HDbscan::default().fit(input, None));
+        report.measure("optics", grid, |input| {
+            let eps = 0.5 * (input.ncols() as f64).sqrt();
+            Optics::new(eps, 5, Euclidean::default()).fit(input, None)
+        });
+        report
+    }
+
+    /// Times `fit` on every input size of `grid`, and adds the timings under
+    /// the name `algorithm`. The data are generated before the clock starts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::benchmark::{ScalingGrid, ScalingReport};
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let grid = ScalingGrid {
+    ///     n_samples: vec![100, 200],
+    ///     n_features: vec![2],
+    ///     repeats: 1,
+    ///     ..ScalingGrid::default()
+    /// };
+    /// let mut report = ScalingReport::default();
+    /// report.measure("optics (eps = 1)", &grid, |input| {
+    ///     Optics::new(1., 5, Euclidean::default()).fit(input, None)
+    /// });
+    ///
+    /// assert_eq!(report.timings.len(), 2);
+    /// assert_eq!(report.timings[1].n_samples, 200);
+    /// ```
+    pub fn measure<F, O>(&mut self, algorithm: &str, grid: &ScalingGrid, mut fit: F)
+    where
+        F: FnMut(&ArrayView2<f64>) -> O,
+    {
+        for &n_features in &grid.n_features {
+            for &n_samples in &grid.n_samples {
+                let (input, _) = make_blobs(n_samples, n_features, grid.n_centers, 1., grid.seed);
+                let mut seconds: Vec<f64> = (0..grid.repeats.max(1))
+                    .map(|_| {
+                        let start = Instant::now();
+                        black_box(fit(&input.view()));
+                        start.elapsed().as_secs_f64()
+                    })
+                    .collect();
+                seconds.sort_unstable_by(f64::total_cmp);
+                self.timings.push(Timing {
+                    algorithm: algorithm.to_string(),
+                    n_samples,
+                    n_features,
+                    repeats: seconds.len(),
+                    seconds: seconds[seconds.len() / 2],
+                    min_seconds: seconds[0],
+                    max_seconds: seconds[seconds.len() - 1],
+                });
+            }
+        }
+    }
+
+    /// Estimates the time, in seconds, `algorithm` takes on `n_samples`
+    /// points of `n_features` dimensions, by fitting `seconds = c * n^k` to
+    /// the timings with the same number of dimensions.
+    ///
+    /// Returns `None` unless at least two numbers of points with nonzero
+    /// timings were measured for `algorithm` in `n_features` dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::benchmark::{ScalingReport, Timing};
+    ///
+    /// let timing = |n_samples, seconds| Timing {
+    ///     algorithm: "dbscan".to_string(),
+    ///     n_samples,
+    ///     n_features: 2,
+    ///     repeats: 1,
+    ///     seconds,
+    ///     min_seconds: seconds,
+    ///     max_seconds: seconds,
+    /// };
+    /// // a quadratic runtime
+    /// let report = ScalingReport { timings: vec![timing(100, 1.), timing(200, 4.)] };
+    ///
+    /// let seconds = report.predict("dbscan", 400, 2).unwrap();
+    /// assert!((seconds - 16.).abs() < 1e-9);
+    /// assert_eq!(report.predict("dbscan", 400, 8), None);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn predict(&self, algorithm: &str, n_samples: usize, n_features: usize) -> Option<f64> {
+        let points: Vec<(f64, f64)> = self
+            .timings
+            .iter()
+            .filter(|t| t.algorithm == algorithm && t.n_features == n_features)
+            .filter(|t| t.n_samples > 0 && t.seconds > 0.)
+            .map(|t| ((t.n_samples as f64).ln(), t.seconds.ln()))
+            .collect();
+        let len = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / len;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / len;
+        let (sxy, sxx) = points.iter().fold((0., 0.), |(sxy, sxx), (x, y)| {
+            (
+                sxy + (x - mean_x) * (y - mean_y),
+                sxx + (x - mean_x) * (x - mean_x),
+            )
+        });
+        if sxx <= 0. {
+            return None;
+        }
+        let exponent = sxy / sxx;
+        Some((mean_y + exponent * ((n_samples as f64).ln() - mean_x)).exp())
+    }
+
+    /// Writes the timings as CSV with a header row.
+    ///
+    /// # Errors
+    /// Returns the error of `writer`, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::benchmark::{ScalingReport, Timing};
+    ///
+    /// let report = ScalingReport {
+    ///     timings: vec![Timing {
+    ///         algorithm: "dbscan".to_string(),
+    ///         n_samples: 100,
+    ///         n_features: 2,
+    ///         repeats: 3,
+    ///         seconds: 0.5,
+    ///         min_seconds: 0.25,
+    ///         max_seconds: 1.,
+    ///     }],
+    /// };
+    /// let mut csv = Vec::new();
+    /// report.write_csv(&mut csv).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(csv).unwrap(),
+    ///     "algorithm,n_samples,n_features,repeats,seconds,min_seconds,max_seconds\n\
+    ///      dbscan,100,2,3,0.5,0.25,1\n"
+    /// );
+    /// ```
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "algorithm,n_samples,n_features,repeats,seconds,min_seconds,max_seconds"
+        )?;
+        for t in &self.timings {
+            let algorithm = if t.algorithm.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", t.algorithm.replace('"', "\"\""))
+            } else {
+                t.algorithm.clone()
+            };
+            writeln!(
+                writer,
+                "{algorithm},{},{},{},{},{},{}",
+                t.n_samples, t.n_features, t.repeats, t.seconds, t.min_seconds, t.max_seconds
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn timing(algorithm: &str, n_samples: usize, n_features: usize, seconds: f64) -> Timing {
+        Timing {
+            algorithm: algorithm.to_string(),
+            n_samples,
+            n_features,
+            repeats: 1,
+            seconds,
+            min_seconds: seconds,
+            max_seconds: seconds,
+        }
+    }
+
+    #[test]
+    fn measure() {
+        let grid = ScalingGrid {
+            n_samples: vec![10, 20],
+            n_features: vec![1, 3],
+            repeats: 3,
+            ..ScalingGrid::default()
+        };
+        let mut report = ScalingReport::default();
+        let mut calls = 0;
+        report.measure("rows", &grid, |input| {
+            calls += 1;
+            input.nrows()
+        });
+        assert_eq!(calls, 12);
+        let sizes: Vec<_> = report
+            .timings
+            .iter()
+            .map(|t| (t.n_samples, t.n_features, t.repeats))
+            .collect();
+        assert_eq!(sizes, [(10, 1, 3), (20, 1, 3), (10, 3, 3), (20, 3, 3)]);
+        assert!(report
+            .timings
+            .iter()
+            .all(|t| t.min_seconds <= t.seconds && t.seconds <= t.max_seconds));
+    }
+
+    #[test]
+    fn predict() {
+        let report = ScalingReport {
+            timings: vec![
+                timing("quadratic", 100, 2, 0.01),
+                timing("quadratic", 1000, 2, 1.),
+                timing("quadratic", 1000, 8, 5.),
+                timing("linear", 100, 2, 0.5),
+                timing("once", 100, 2, 0.5),
+            ],
+        };
+        let seconds = report.predict("quadratic", 10_000, 2).unwrap();
+        assert!((seconds - 100.).abs() < 1e-9);
+        assert!(report.predict("quadratic", 10_000, 8).is_none());
+        assert!(report.predict("once", 10_000, 2).is_none());
+        assert!(report.predict("missing", 10_000, 2).is_none());
+    }
+
+    #[test]
+    fn write_csv() {
+        let report = ScalingReport {
+            timings: vec![timing("a", 10, 2, 0.5), timing("b,\"c\"", 20, 3, 1.)],
+        };
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "algorithm,n_samples,n_features,repeats,seconds,min_seconds,max_seconds\n\
+             a,10,2,1,0.5,0.5,0.5\n\
+             \"b,\"\"c\"\"\",20,3,1,1,1,1\n"
+        );
+    }
+}
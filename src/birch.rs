@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::parallel::prelude::*;
+use crate::KMeans;
+
+/// BIRCH (balanced iterative reducing and clustering using hierarchies)
+/// clustering algorithm.
+///
+/// BIRCH summarizes the input in a CF (clustering feature) tree, whose leaves
+/// are subclusters of radius at most `threshold`. The tree is built in a
+/// single pass and can be extended batch by batch with
+/// [`Birch::partial_fit`], so the input does not have to fit in memory at
+/// once. The subclusters are then grouped by a global clustering step, which
+/// is k-means with `n_clusters` clusters in `fit`, or any clusterer, such as
+/// DBSCAN, passed to [`Birch::global_clustering`].
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Birch, Fit};
+///
+/// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+/// let mut birch = Birch::new(1., 50);
+/// birch.n_clusters = Some(2);
+/// let (clusters, noise) = birch.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+///
+/// # References
+/// - Zhang, Tian, Raghu Ramakrishnan, and Miron Livny. "BIRCH: an efficient data clustering method for very large databases."
+///   ACM SIGMOD Record 25.2 (1996): 103-114.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Birch<A> {
+    /// The maximum radius of a subcluster. A point that would make the
+    /// closest subcluster larger starts a new subcluster.
+    pub threshold: A,
+
+    /// The maximum number of entries in a node of the CF tree.
+    pub branching_factor: usize,
+
+    /// The number of clusters k-means finds among the subclusters in `fit`.
+    /// If `None`, each subcluster is a cluster of its own.
+    pub n_clusters: Option<usize>,
+
+    nodes: Vec<Node<A>>,
+    root: usize,
+    n_features: usize,
+    /// The cluster of each leaf subcluster given by the global clustering,
+    /// or empty if each subcluster is a cluster of its own.
+    subcluster_labels: Vec<Option<usize>>,
+}
+
+/// A node of the CF tree.
+#[derive(Debug, Deserialize, Serialize)]
+struct Node<A> {
+    entries: Vec<Subcluster<A>>,
+    leaf: bool,
+}
+
+/// The clustering feature of a set of points: their number, linear sum and
+/// squared sum.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Subcluster<A> {
+    n: usize,
+    linear_sum: Vec<A>,
+    squared_sum: A,
+    /// The node summarized by this entry, unless it is a leaf entry.
+    child: Option<usize>,
+}
+
+impl<A> Default for Birch<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(A::from(0.5_f32).expect("valid float"), 50)
+    }
+}
+
+impl<A> Birch<A> {
+    /// Creates a model whose subclusters have a radius of at most `threshold`,
+    /// with at most `branching_factor` subclusters in each node of the CF tree
+    /// and no global clustering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Birch;
+    ///
+    /// let birch = Birch::<f64>::new(0.5, 50);
+    /// assert_eq!(birch.threshold, 0.5);
+    /// assert_eq!(birch.n_clusters, None);
+    /// ```
+    #[must_use]
+    pub fn new(threshold: A, branching_factor: usize) -> Self {
+        Self {
+            threshold,
+            branching_factor,
+            n_clusters: None,
+            nodes: vec![],
+            root: 0,
+            n_features: 0,
+            subcluster_labels: vec![],
+        }
+    }
+}
+
+impl<A> Birch<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Inserts a batch of points into the CF tree.
+    ///
+    /// This resets the result of [`Birch::global_clustering`], so that each
+    /// subcluster is a cluster of its own until it is called again.
+    ///
+    /// # Panics
+    /// Panics if `branching_factor` is less than two, or if the batch has a
+    /// different number of features than the points inserted before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Birch;
+    ///
+    /// let mut birch = Birch::new(1., 50);
+    /// birch.partial_fit(&array![[1., 2.], [1.2, 2.]]);
+    /// birch.partial_fit(&array![[8., 7.], [8., 8.]]);
+    ///
+    /// assert_eq!(birch.subcluster_sizes(), [2, 2]);
+    /// ```
+    pub fn partial_fit<S>(&mut self, batch: &ArrayBase<S, Ix2>)
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(
+            self.branching_factor >= 2,
+            "`branching_factor` should be at least two"
+        );
+        if self.nodes.is_empty() {
+            self.nodes.push(Node {
+                entries: vec![],
+                leaf: true,
+            });
+            self.root = 0;
+            self.n_features = batch.ncols();
+        }
+        assert_eq!(
+            batch.ncols(),
+            self.n_features,
+            "all batches should have the same number of features"
+        );
+
+        self.subcluster_labels.clear();
+        for point in batch.rows() {
+            if let Some(sibling) = self.insert(self.root, &point) {
+                let entries = vec![self.summarize(self.root), self.summarize(sibling)];
+                self.nodes.push(Node {
+                    entries,
+                    leaf: false,
+                });
+                self.root = self.nodes.len() - 1;
+            }
+        }
+    }
+
+    /// Returns the centroids of the leaf subclusters, one row per subcluster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Birch;
+    ///
+    /// let mut birch = Birch::new(1., 50);
+    /// birch.partial_fit(&array![[1., 2.], [1., 3.], [8., 8.]]);
+    ///
+    /// assert_eq!(birch.subcluster_centers(), array![[1., 2.5], [8., 8.]]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the shape matches the number of centroids
+    pub fn subcluster_centers(&self) -> Array2<A> {
+        let centers: Vec<A> = self.leaves().flat_map(Subcluster::centroid).collect();
+        Array2::from_shape_vec(
+            (centers.len() / self.n_features.max(1), self.n_features),
+            centers,
+        )
+        .expect("centroids of the leaf subclusters")
+    }
+
+    /// Returns the number of points in each leaf subcluster, in the same
+    /// order as [`Birch::subcluster_centers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Birch;
+    ///
+    /// let mut birch = Birch::new(1., 50);
+    /// birch.partial_fit(&array![[1., 2.], [1., 3.], [8., 8.]]);
+    ///
+    /// assert_eq!(birch.subcluster_sizes(), [2, 1]);
+    /// ```
+    #[must_use]
+    pub fn subcluster_sizes(&self) -> Vec<usize> {
+        self.leaves().map(|s| s.n).collect()
+    }
+
+    /// Groups the leaf subclusters by fitting `clusterer` to their centroids.
+    ///
+    /// Subclusters that `clusterer` considers noise are noise in the result
+    /// of `predict`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Birch, Dbscan, Predict};
+    ///
+    /// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+    /// let mut birch = Birch::new(0.3, 50);
+    /// birch.partial_fit(&points);
+    /// assert_eq!(birch.subcluster_centers().nrows(), 3);
+    ///
+    /// birch.global_clustering(&mut Dbscan::new(1.5, 1, Euclidean::default()));
+    /// let (clusters, noise) = birch.predict(&points);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert!(noise.is_empty());
+    /// ```
+    pub fn global_clustering<C>(&mut self, clusterer: &mut C)
+    where
+        C: Fit<Array2<A>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>,
+    {
+        let centers = self.subcluster_centers();
+        let (clusters, _) = clusterer.fit(&centers, None);
+        let mut labels = vec![None; centers.nrows()];
+        for (id, members) in clusters {
+            for m in members {
+                labels[m] = Some(id);
+            }
+        }
+        self.subcluster_labels = labels;
+    }
+
+    /// Inserts a point into the subtree rooted at `node`, and returns the new
+    /// sibling of `node` if it had to be split.
+    fn insert(&mut self, node: usize, point: &ArrayView1<A>) -> Option<usize> {
+        let closest = closest_entry(&self.nodes[node].entries, point);
+        if self.nodes[node].leaf {
+            let threshold = self.threshold * self.threshold;
+            match closest {
+                Some(i) if self.nodes[node].entries[i].squared_radius_with(point) <= threshold => {
+                    self.nodes[node].entries[i].add(point);
+                }
+                _ => self.nodes[node].entries.push(Subcluster::new(point)),
+            }
+        } else {
+            let i = closest.expect("non-leaf nodes have entries");
+            let child = self.nodes[node].entries[i]
+                .child
+                .expect("non-leaf entries have children");
+            if let Some(sibling) = self.insert(child, point) {
+                self.nodes[node].entries[i] = self.summarize(child);
+                let entry = self.summarize(sibling);
+                self.nodes[node].entries.push(entry);
+            } else {
+                self.nodes[node].entries[i].add(point);
+            }
+        }
+
+        if self.nodes[node].entries.len() > self.branching_factor {
+            Some(self.split(node))
+        } else {
+            None
+        }
+    }
+
+    /// Splits `node` around its two farthest entries, moving the entries
+    /// closer to the second one into a new node, and returns the new node.
+    fn split(&mut self, node: usize) -> usize {
+        let entries = std::mem::take(&mut self.nodes[node].entries);
+        let centroids: Vec<_> = entries.iter().map(Subcluster::centroid).collect();
+        let mut farthest = (0, 1, A::neg_infinity());
+        for i in 0..centroids.len() {
+            for j in i + 1..centroids.len() {
+                let d = squared_distance(&centroids[i], &centroids[j]);
+                if d > farthest.2 {
+                    farthest = (i, j, d);
+                }
+            }
+        }
+
+        let (p, q, _) = farthest;
+        let (mut left, mut right) = (vec![], vec![]);
+        for (i, entry) in entries.into_iter().enumerate() {
+            let to_left = i == p
+                || (i != q
+                    && squared_distance(&centroids[i], &centroids[p])
+                        <= squared_distance(&centroids[i], &centroids[q]));
+            if to_left {
+                left.push(entry);
+            } else {
+                right.push(entry);
+            }
+        }
+        self.nodes[node].entries = left;
+        let leaf = self.nodes[node].leaf;
+        self.nodes.push(Node {
+            entries: right,
+            leaf,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Returns an entry summarizing all entries of `node`.
+    fn summarize(&self, node: usize) -> Subcluster<A> {
+        let mut summary = Subcluster {
+            n: 0,
+            linear_sum: vec![A::zero(); self.n_features],
+            squared_sum: A::zero(),
+            child: Some(node),
+        };
+        for entry in &self.nodes[node].entries {
+            summary.n += entry.n;
+            for (s, v) in summary.linear_sum.iter_mut().zip(&entry.linear_sum) {
+                *s += *v;
+            }
+            summary.squared_sum += entry.squared_sum;
+        }
+        summary
+    }
+
+    fn leaves(&self) -> impl Iterator<Item = &Subcluster<A>> {
+        self.nodes
+            .iter()
+            .filter(|node| node.leaf)
+            .flat_map(|node| node.entries.iter())
+    }
+}
+
+/// Builds the CF tree from the given input data, and clusters its leaf
+/// subclusters with k-means if `n_clusters` is set.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for Birch<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.nodes.clear();
+        self.subcluster_labels.clear();
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
+        self.partial_fit(input);
+        if let Some(n_clusters) = self.n_clusters {
+            self.global_clustering(&mut KMeans::new(n_clusters));
+        }
+        self.predict(input)
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its closest leaf
+/// subcluster.
+///
+/// # Panics
+/// Panics if no points have been inserted into the CF tree.
+impl<S, A> Predict<ArrayBase<S, Ix2>, (HashMap<usize, Vec<usize>>, Vec<usize>)> for Birch<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let leaves: Vec<_> = self.leaves().collect();
+        assert!(!leaves.is_empty(), "`predict` requires a fitted model");
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let labels: Vec<_> = rows
+            .into_par_iter()
+            .map(|p| {
+                let closest = closest_entry(&leaves, &p).expect("non-empty leaves");
+                if self.subcluster_labels.is_empty() {
+                    Some(closest)
+                } else {
+                    self.subcluster_labels[closest]
+                }
+            })
+            .collect();
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = vec![];
+        for (i, label) in labels.into_iter().enumerate() {
+            match label {
+                Some(id) => clusters.entry(id).or_default().push(i),
+                None => noise.push(i),
+            }
+        }
+        (clusters, noise)
+    }
+}
+
+impl<A> Subcluster<A>
+where
+    A: AddAssign + FloatCore + FromPrimitive,
+{
+    fn new(point: &ArrayView1<A>) -> Self {
+        Self {
+            n: 1,
+            linear_sum: point.to_vec(),
+            squared_sum: point.iter().fold(A::zero(), |acc, v| acc + *v * *v),
+            child: None,
+        }
+    }
+
+    fn add(&mut self, point: &ArrayView1<A>) {
+        self.n += 1;
+        for (s, v) in self.linear_sum.iter_mut().zip(point) {
+            *s += *v;
+            self.squared_sum += *v * *v;
+        }
+    }
+
+    fn centroid(&self) -> Vec<A> {
+        let n = A::from_usize(self.n).expect("valid float");
+        self.linear_sum.iter().map(|s| *s / n).collect()
+    }
+
+    /// Returns the squared distance from `point` to the centroid.
+    fn squared_distance(&self, point: &ArrayView1<A>) -> A {
+        let n = A::from_usize(self.n).expect("valid float");
+        self.linear_sum
+            .iter()
+            .zip(point)
+            .fold(A::zero(), |acc, (s, v)| acc + (*s / n - *v) * (*s / n - *v))
+    }
+
+    /// Returns the squared radius the subcluster would have with `point`.
+    fn squared_radius_with(&self, point: &ArrayView1<A>) -> A {
+        let n = A::from_usize(self.n + 1).expect("valid float");
+        let (sum, centroid) = self
+            .linear_sum
+            .iter()
+            .zip(point)
+            .fold((self.squared_sum, A::zero()), |(sum, centroid), (s, v)| {
+                (sum + *v * *v, centroid + (*s + *v) * (*s + *v))
+            });
+        sum / n - centroid / (n * n)
+    }
+}
+
+fn closest_entry<A, E>(entries: &[E], point: &ArrayView1<A>) -> Option<usize>
+where
+    A: AddAssign + FloatCore + FromPrimitive,
+    E: std::borrow::Borrow<Subcluster<A>>,
+{
+    entries
+        .iter()
+        .map(|e| e.borrow().squared_distance(point))
+        .enumerate()
+        .fold(None, |closest: Option<(usize, A)>, (i, d)| match closest {
+            Some((_, best)) if best <= d => closest,
+            _ => Some((i, d)),
+        })
+        .map(|(i, _)| i)
+}
+
+fn squared_distance<A: FloatCore>(a: &[A], b: &[A]) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, concatenate, Array2, Axis};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::Dbscan;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn blob(center: [f64; 2], n: usize) -> Array2<f64> {
+        Array2::from_shape_fn((n, 2), |(i, j)| {
+            center[j] + ((i * (j + 3)) % 7) as f64 * 0.1 - 0.3
+        })
+    }
+
+    #[test]
+    fn default() {
+        let birch = Birch::<f32>::default();
+        assert!((birch.threshold - 0.5).abs() < f32::EPSILON);
+        assert_eq!(birch.branching_factor, 50);
+        assert_eq!(birch.n_clusters, None);
+    }
+
+    #[test]
+    fn birch() {
+        let data = concatenate![
+            Axis(0),
+            blob([0., 0.], 30),
+            blob([10., 0.], 30),
+            blob([0., 10.], 30)
+        ];
+        let mut birch = Birch::new(0.5, 4);
+        birch.n_clusters = Some(3);
+        let (clusters, noise) = birch.fit(&data, None);
+        assert!(noise.is_empty());
+        assert_eq!(clusters.len(), 3);
+        let mut sizes: Vec<_> = clusters.values().map(Vec::len).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, [30, 30, 30]);
+        for members in clusters.values() {
+            assert!(members.iter().all(|i| i / 30 == members[0] / 30));
+        }
+        assert_eq!(birch.subcluster_sizes().iter().sum::<usize>(), 90);
+    }
+
+    #[test]
+    fn partial_fit() {
+        let first = concatenate![Axis(0), blob([0., 0.], 20), blob([10., 0.], 20)];
+        let second = concatenate![Axis(0), blob([0., 0.], 20), blob([10., 10.], 20)];
+        let mut birch = Birch::new(0.5, 3);
+        birch.partial_fit(&first);
+        birch.partial_fit(&second);
+        assert_eq!(birch.subcluster_sizes().iter().sum::<usize>(), 80);
+
+        birch.global_clustering(&mut Dbscan::new(2., 1, Euclidean::default()));
+        let (clusters, noise) = birch.predict(&array![[0., 0.], [10., 0.], [10., 10.], [0.2, 0.]]);
+        assert!(noise.is_empty());
+        assert_eq!(clusters.len(), 3);
+        assert!(clusters.values().any(|m| m == &[0, 3]));
+    }
+
+    #[test]
+    fn leaf_subclusters() {
+        // points farther apart than the threshold are subclusters of their own
+        #[allow(clippy::cast_precision_loss)]
+        let data = Array2::from_shape_fn((20, 1), |(i, _)| i as f64 * 10.);
+        let mut birch = Birch::new(1., 3);
+        let (clusters, _) = birch.fit(&data, None);
+        assert_eq!(clusters.len(), 20);
+        let mut centers: Vec<_> = birch.subcluster_centers().iter().copied().collect();
+        centers.sort_by(f64::total_cmp);
+        assert_eq!(centers, data.iter().copied().collect::<Vec<_>>());
+        assert!(birch.nodes.len() > 1);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut birch = Birch::default();
+        let (clusters, noise) = birch.fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
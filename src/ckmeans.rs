@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+
+/// Optimal k-means clustering of one-dimensional data with dynamic
+/// programming.
+///
+/// In one dimension, clusters of the optimal k-means solution are intervals
+/// of the sorted values, so the solution with the lowest inertia can be found
+/// exactly, without random initialization, in `O(k n log n)` time. This is
+/// both faster and more accurate than [`KMeans`](crate::KMeans) on a single
+/// feature.
+///
+/// If the input has fewer distinct values than `n_clusters`, each distinct
+/// value forms its own cluster.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Ckmeans1D, Fit, Predict};
+///
+/// let values = array![[4.1], [1.], [1.2], [4.], [0.9], [9.5], [4.2]];
+/// let mut ckmeans = Ckmeans1D::new(3);
+/// let (clusters, noise) = ckmeans.fit(&values, None);
+///
+/// assert_eq!(clusters[&0], [1, 2, 4]);  // clusters are numbered by their centers
+/// assert_eq!(clusters[&1], [0, 3, 6]);
+/// assert_eq!(clusters[&2], [5]);
+/// assert!(noise.is_empty());
+/// assert_eq!(ckmeans.predict(&array![[3.], [8.]]), [1, 2]);
+/// ```
+///
+/// # References
+/// - Wang, Haizhou, and Mingzhou Song. "Ckmeans.1d.dp: optimal k-means clustering in one
+///   dimension by dynamic programming." The R Journal 3.2 (2011): 29-33.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Ckmeans1D<A> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    centers: Vec<A>,
+    inertia: A,
+}
+
+impl<A> Default for Ckmeans1D<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl<A> Ckmeans1D<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model forming `n_clusters` clusters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Ckmeans1D;
+    ///
+    /// let ckmeans = Ckmeans1D::<f64>::new(3);
+    /// assert_eq!(ckmeans.n_clusters, 3);
+    /// assert!(ckmeans.centers().is_empty());
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            n_clusters,
+            centers: vec![],
+            inertia: A::zero(),
+        }
+    }
+
+    /// Returns the centers of the clusters found by the last fit, in
+    /// increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Ckmeans1D, Fit};
+    ///
+    /// let mut ckmeans = Ckmeans1D::new(2);
+    /// ckmeans.fit(&array![[1.], [9.], [2.], [10.]], None);
+    ///
+    /// assert_eq!(ckmeans.centers(), [1.5, 9.5]);
+    /// ```
+    #[must_use]
+    pub fn centers(&self) -> &[A] {
+        &self.centers
+    }
+
+    /// Returns the sum of squared distances of the values to the centers of
+    /// their clusters in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Ckmeans1D, Fit};
+    ///
+    /// let mut ckmeans = Ckmeans1D::new(2);
+    /// ckmeans.fit(&array![[1.], [9.], [2.], [10.]], None);
+    ///
+    /// // each value is 0.5 away from its center
+    /// assert!((ckmeans.inertia() - 1_f64).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn inertia(&self) -> A {
+        self.inertia
+    }
+}
+
+/// Fits optimal one-dimensional k-means to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array with a single column of values to cluster.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+///   Clusters are numbered in increasing order of their centers.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+///
+/// # Panics
+/// Panics if `input` has more than one column, or if a value is NaN.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for Ckmeans1D<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            input.ncols() == 1 || input.is_empty(),
+            "`Ckmeans1D` requires a single column"
+        );
+        self.centers = vec![];
+        self.inertia = A::zero();
+        if input.is_empty() || self.n_clusters == 0 {
+            return (HashMap::new(), Vec::new());
+        }
+
+        let mut order: Vec<usize> = (0..input.nrows()).collect();
+        order.sort_by(|a, b| {
+            input[[*a, 0]]
+                .partial_cmp(&input[[*b, 0]])
+                .expect("values must not be NaN")
+        });
+        let sorted: Vec<A> = order.iter().map(|i| input[[*i, 0]]).collect();
+        let distinct = 1 + sorted.windows(2).filter(|w| w[0] != w[1]).count();
+        let k = self.n_clusters.min(distinct);
+
+        let costs = IntervalCosts::new(&sorted);
+        let starts = optimal_starts(&costs, sorted.len(), k);
+
+        let mut clusters = HashMap::with_capacity(k);
+        let ends = starts.iter().skip(1).copied().chain([sorted.len()]);
+        for (c, (&start, end)) in starts.iter().zip(ends).enumerate() {
+            let size = A::from_usize(end - start).expect("valid float");
+            let sum = sorted[start..end].iter().fold(A::zero(), |acc, v| acc + *v);
+            self.centers.push(sum / size);
+            self.inertia = self.inertia + costs.cost(start, end);
+            let mut members = order[start..end].to_vec();
+            members.sort_unstable();
+            clusters.insert(c, members);
+        }
+        (clusters, Vec::new())
+    }
+}
+
+/// Assigns each value in the given input to the cluster of its closest
+/// center.
+///
+/// # Panics
+/// Panics if the model has not been fitted, or if `input` has more than one
+/// column.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for Ckmeans1D<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        assert!(
+            !self.centers.is_empty(),
+            "`predict` requires a fitted model"
+        );
+        assert!(
+            input.ncols() == 1 || input.is_empty(),
+            "`Ckmeans1D` requires a single column"
+        );
+        input
+            .column(0)
+            .iter()
+            .map(|v| {
+                let upper = self.centers.partition_point(|c| c < v);
+                if upper == 0 {
+                    0
+                } else if upper == self.centers.len()
+                    || *v - self.centers[upper - 1] <= self.centers[upper] - *v
+                {
+                    upper - 1
+                } else {
+                    upper
+                }
+            })
+            .collect()
+    }
+}
+
+/// Computes the sum of squared deviations from the mean of any interval of
+/// sorted values in constant time from prefix sums.
+struct IntervalCosts<A> {
+    sums: Vec<A>,
+    squared_sums: Vec<A>,
+}
+
+impl<A: FloatCore + FromPrimitive> IntervalCosts<A> {
+    fn new(sorted: &[A]) -> Self {
+        // shifting by the median reduces the cancellation in the differences
+        let shift = sorted[sorted.len() / 2];
+        let mut sums = Vec::with_capacity(sorted.len() + 1);
+        let mut squared_sums = Vec::with_capacity(sorted.len() + 1);
+        sums.push(A::zero());
+        squared_sums.push(A::zero());
+        for v in sorted {
+            let v = *v - shift;
+            sums.push(sums[sums.len() - 1] + v);
+            squared_sums.push(squared_sums[squared_sums.len() - 1] + v * v);
+        }
+        Self { sums, squared_sums }
+    }
+
+    /// Returns the cost of the values in `start..end`.
+    fn cost(&self, start: usize, end: usize) -> A {
+        let size = A::from_usize(end - start).expect("valid float");
+        let sum = self.sums[end] - self.sums[start];
+        let cost = self.squared_sums[end] - self.squared_sums[start] - sum * sum / size;
+        cost.max(A::zero())
+    }
+}
+
+/// Returns the start of each of the `k` intervals of the optimal partition of
+/// `n` sorted values.
+fn optimal_starts<A: FloatCore + FromPrimitive>(
+    costs: &IntervalCosts<A>,
+    n: usize,
+    k: usize,
+) -> Vec<usize> {
+    // `cost[c][j]` is the lowest cost of the first `j + 1` values in `c + 1`
+    // intervals, and `start[c][j]` is the start of the last interval.
+    let mut cost = vec![(0..n).map(|j| costs.cost(0, j + 1)).collect::<Vec<_>>()];
+    let mut start = vec![vec![0; n]];
+    for c in 1..k {
+        let mut row = vec![A::infinity(); n];
+        let mut row_start = vec![0; n];
+        fill_row(
+            costs,
+            &cost[c - 1],
+            &mut row,
+            &mut row_start,
+            (c, n - 1),
+            (c, n - 1),
+        );
+        cost.push(row);
+        start.push(row_start);
+    }
+
+    let mut starts = vec![0; k];
+    let mut end = n - 1;
+    for c in (0..k).rev() {
+        starts[c] = start[c][end];
+        end = starts[c].saturating_sub(1);
+    }
+    starts
+}
+
+/// Fills `row[lo..=hi]` given the previous row, knowing that the optimal
+/// start of the last interval is in `start_lo..=start_hi` and does not
+/// decrease with the end of the interval.
+fn fill_row<A: FloatCore + FromPrimitive>(
+    costs: &IntervalCosts<A>,
+    previous: &[A],
+    row: &mut [A],
+    row_start: &mut [usize],
+    (lo, hi): (usize, usize),
+    (start_lo, start_hi): (usize, usize),
+) {
+    if lo > hi {
+        return;
+    }
+    let mid = lo + (hi - lo) / 2;
+    for s in start_lo..=start_hi.min(mid) {
+        let candidate = previous[s - 1] + costs.cost(s, mid + 1);
+        if candidate < row[mid] {
+            row[mid] = candidate;
+            row_start[mid] = s;
+        }
+    }
+    let best = row_start[mid];
+    if mid > lo {
+        fill_row(
+            costs,
+            previous,
+            row,
+            row_start,
+            (lo, mid - 1),
+            (start_lo, best),
+        );
+    }
+    fill_row(
+        costs,
+        previous,
+        row,
+        row_start,
+        (mid + 1, hi),
+        (best, start_hi),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn optimal() {
+        // compare with an exhaustive search over the boundaries
+        let values = [7., 1., 2.5, 9., 3., 8.2, 0.5, 5., 5.5, 12.];
+        let data = Array2::from_shape_vec((values.len(), 1), values.to_vec()).unwrap();
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let costs = IntervalCosts::new(&sorted);
+        let n = sorted.len();
+        let mut best = f64::INFINITY;
+        for a in 1..n {
+            for b in a + 1..n {
+                best = best.min(costs.cost(0, a) + costs.cost(a, b) + costs.cost(b, n));
+            }
+        }
+
+        let mut model = Ckmeans1D::new(3);
+        let (clusters, _) = model.fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert!((model.inertia() - best).abs() < 1e-9);
+        assert!(model.centers().windows(2).all(|w| w[0] < w[1]));
+        let mut kmeans = crate::KMeans::new(3);
+        kmeans.fit(&data, None);
+        assert!(model.inertia() <= kmeans.inertia() + 1e-9);
+    }
+
+    #[test]
+    fn few_distinct_values() {
+        let data = array![[2.], [1.], [2.], [1.]];
+        let mut model = Ckmeans1D::new(3);
+        let (clusters, _) = model.fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[&0], [1, 3]);
+        assert_eq!(clusters[&1], [0, 2]);
+        assert_eq!(model.centers(), [1., 2.]);
+        assert!(model.inertia().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn predict() {
+        let data = array![[0.], [1.], [10.], [11.]];
+        let mut model = Ckmeans1D::new(2);
+        model.fit(&data, None);
+        assert_eq!(
+            model.predict(&array![[-5.], [5.4], [5.6], [20.]]),
+            [0, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 1));
+        let mut model = Ckmeans1D::default();
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
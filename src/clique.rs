@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::float::FloatCore;
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::union_find::TreeUnionFind;
+
+/// CLIQUE subspace clustering, which finds clusters that exist only in
+/// projections of the input onto some of its dimensions.
+///
+/// The range of every dimension is split into `n_intervals` intervals of
+/// equal width, and a unit, one interval in each dimension of a subspace, is
+/// dense if it contains at least `min_points` points. Dense units are found
+/// bottom up, from one dimension to more, as a unit can only be dense if all
+/// of its projections are. A cluster is a maximal set of connected dense
+/// units, which share a face, of a subspace.
+///
+/// Clusters are reported in the maximal subspaces only, those with dense
+/// units that are not part of a larger subspace with dense units. A point may
+/// belong to clusters in several subspaces.
+///
+/// Density-based clustering of all the dimensions fails when distances in
+/// many dimensions become alike, whereas the clusters of a subspace only
+/// depend on its dimensions.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Clique, Fit};
+///
+/// // the first two dimensions separate two groups, the third is noise
+/// let points = array![
+///     [0., 0., 0.], [0.1, 0.1, 5.], [0.2, 0., 9.],
+///     [9., 9., 1.], [9.1, 9., 6.], [9.2, 9.1, 3.],
+/// ];
+/// let (clusters, noise) = Clique::new(10, 3).fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(clusters[0].dimensions, [0, 1]);
+/// assert_eq!(clusters[0].members, [0, 1, 2]);
+/// assert_eq!(clusters[1].members, [3, 4, 5]);
+/// assert!(noise.is_empty());
+/// ```
+///
+/// # References
+/// - Agrawal, Rakesh, et al. "Automatic subspace clustering of high dimensional data for data mining applications."
+///   Proceedings of the 1998 ACM SIGMOD International Conference on Management of Data (1998): 94-105.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Clique {
+    /// The number of intervals each dimension is split into.
+    pub n_intervals: usize,
+
+    /// The minimum number of points in a dense unit.
+    pub min_points: usize,
+}
+
+/// A cluster of a subspace found by [`Clique`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SubspaceCluster {
+    /// The dimensions of the subspace, in increasing order.
+    pub dimensions: Vec<usize>,
+
+    /// The indices of the points in the cluster, in increasing order.
+    pub members: Vec<usize>,
+}
+
+impl Default for Clique {
+    fn default() -> Self {
+        Self::new(10, 5)
+    }
+}
+
+impl Clique {
+    /// Creates a model splitting each dimension into `n_intervals` intervals,
+    /// with at least `min_points` points in each dense unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Clique;
+    ///
+    /// let clique = Clique::new(10, 3);
+    /// assert_eq!(clique.n_intervals, 10);
+    /// assert_eq!(clique.min_points, 3);
+    ///
+    /// ```
+    #[must_use]
+    pub fn new(n_intervals: usize, min_points: usize) -> Self {
+        Self {
+            n_intervals,
+            min_points,
+        }
+    }
+}
+
+/// The dense units of a subspace, keyed by their interval in each dimension
+/// of the subspace, with their points.
+type Units = HashMap<Vec<usize>, Vec<usize>>;
+
+/// Fits CLIQUE to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `Vec<SubspaceCluster>`: The clusters, ordered by subspace and then by their first point.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which belong to no cluster. Non-finite
+///   coordinates are in no interval.
+///
+/// # Panics
+/// Panics if `n_intervals` is zero.
+impl<S, A> Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (Vec<SubspaceCluster>, Vec<usize>)>
+    for Clique
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (Vec<SubspaceCluster>, Vec<usize>) {
+        assert!(self.n_intervals > 0, "`n_intervals` must be positive");
+        let intervals: Vec<Vec<Option<usize>>> = input
+            .columns()
+            .into_iter()
+            .map(|column| interval_indices(column.iter().copied(), self.n_intervals))
+            .collect();
+
+        let mut level: HashMap<Vec<usize>, Units> = HashMap::new();
+        for (d, column) in intervals.iter().enumerate() {
+            let mut units: Units = HashMap::new();
+            for (i, interval) in column.iter().enumerate() {
+                if let Some(interval) = interval {
+                    units.entry(vec![*interval]).or_default().push(i);
+                }
+            }
+            units.retain(|_, points| points.len() >= self.min_points);
+            if !units.is_empty() {
+                level.insert(vec![d], units);
+            }
+        }
+
+        let mut clusters = Vec::new();
+        while !level.is_empty() {
+            let next = self.extend(&level, &intervals);
+            for (subspace, units) in &level {
+                let is_maximal = !next.keys().any(|s| is_subset(subspace, s));
+                if is_maximal {
+                    clusters.extend(connect(units).into_iter().map(|members| SubspaceCluster {
+                        dimensions: subspace.clone(),
+                        members,
+                    }));
+                }
+            }
+            level = next;
+        }
+        clusters.sort_unstable_by(|a, b| {
+            (&a.dimensions, a.members.first()).cmp(&(&b.dimensions, b.members.first()))
+        });
+
+        let mut in_cluster = vec![false; input.nrows()];
+        for &i in clusters.iter().flat_map(|c| &c.members) {
+            in_cluster[i] = true;
+        }
+        let noise = (0..input.nrows()).filter(|i| !in_cluster[*i]).collect();
+        (clusters, noise)
+    }
+}
+
+impl Clique {
+    /// Finds the dense units of the subspaces with one more dimension than
+    /// those of `level`, joining subspaces that differ only in their last
+    /// dimension.
+    fn extend(
+        &self,
+        level: &HashMap<Vec<usize>, Units>,
+        intervals: &[Vec<Option<usize>>],
+    ) -> HashMap<Vec<usize>, Units> {
+        let mut subspaces: Vec<_> = level.keys().collect();
+        subspaces.sort_unstable();
+
+        let mut next = HashMap::new();
+        for (i, a) in subspaces.iter().enumerate() {
+            for b in &subspaces[i + 1..] {
+                let k = a.len();
+                if a[..k - 1] != b[..k - 1] {
+                    break;
+                }
+                let mut candidate = (*a).clone();
+                candidate.push(b[k - 1]);
+                if !(0..candidate.len()).all(|j| level.contains_key(&without(&candidate, j))) {
+                    continue;
+                }
+
+                let mut units: Units = HashMap::new();
+                for (unit, points) in &level[*a] {
+                    for &p in points {
+                        if let Some(interval) = intervals[b[k - 1]][p] {
+                            let mut key = unit.clone();
+                            key.push(interval);
+                            units.entry(key).or_default().push(p);
+                        }
+                    }
+                }
+                units.retain(|unit, points| {
+                    points.len() >= self.min_points
+                        && (0..unit.len()).all(|j| {
+                            level
+                                .get(&without(&candidate, j))
+                                .is_some_and(|units| units.contains_key(&without(unit, j)))
+                        })
+                });
+                if !units.is_empty() {
+                    next.insert(candidate, units);
+                }
+            }
+        }
+        next
+    }
+}
+
+/// Returns the interval of each value among `n_intervals` intervals of equal
+/// width over the range of the finite values.
+fn interval_indices<A: FloatCore>(
+    values: impl Iterator<Item = A> + Clone,
+    n_intervals: usize,
+) -> Vec<Option<usize>> {
+    let (min, max) = values
+        .clone()
+        .filter(|v| v.is_finite())
+        .fold((A::infinity(), A::neg_infinity()), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    let width = max - min;
+    let scale = A::from(n_intervals).expect("valid float");
+    values
+        .map(|v| {
+            if !v.is_finite() {
+                return None;
+            }
+            if width <= A::zero() {
+                return Some(0);
+            }
+            let interval = ((v - min) / width * scale).floor().to_usize()?;
+            Some(interval.min(n_intervals - 1))
+        })
+        .collect()
+}
+
+/// Groups connected dense units, which differ by one interval in a single
+/// dimension, and returns the points of each group in increasing order.
+fn connect(units: &Units) -> Vec<Vec<usize>> {
+    let keys: Vec<_> = units.keys().collect();
+    let index: HashMap<_, _> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+    let mut uf = TreeUnionFind::new(keys.len());
+    for (i, key) in keys.iter().enumerate() {
+        for d in 0..key.len() {
+            let mut neighbor = (*key).clone();
+            neighbor[d] += 1;
+            if let Some(&j) = index.get(&neighbor) {
+                uf.union(i, j);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        groups
+            .entry(uf.find(i))
+            .or_default()
+            .extend_from_slice(&units[*key]);
+    }
+    groups
+        .into_values()
+        .map(|mut members| {
+            members.sort_unstable();
+            members
+        })
+        .collect()
+}
+
+fn without(items: &[usize], j: usize) -> Vec<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != j)
+        .map(|(_, v)| *v)
+        .collect()
+}
+
+/// Returns whether the sorted `a` is a subset of the sorted `b`.
+fn is_subset(a: &[usize], b: &[usize]) -> bool {
+    a.iter().all(|v| b.binary_search(v).is_ok())
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let clique = Clique::default();
+        assert_eq!(clique.n_intervals, 10);
+        assert_eq!(clique.min_points, 5);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn clique() {
+        // two groups in dimensions 0 and 1, a third across them in dimension
+        // 3 only, and uniform noise in dimension 2
+        let data = Array2::from_shape_fn((200, 4), |(i, d)| match d {
+            0 | 1 if i < 100 => 1. + (i % 7) as f64 / 100.,
+            0 | 1 => 8. + (i % 5) as f64 / 100.,
+            2 => (i % 10) as f64,
+            _ if i % 4 == 0 => 9.5,
+            _ => (i % 10) as f64 * 0.9,
+        });
+        let (clusters, noise) = Clique::new(10, 35).fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[0].dimensions, [0, 1]);
+        assert_eq!(clusters[0].members, (0..100).collect::<Vec<_>>());
+        assert_eq!(clusters[1].dimensions, [0, 1]);
+        assert_eq!(clusters[1].members, (100..200).collect::<Vec<_>>());
+        assert_eq!(clusters[2].dimensions, [3]);
+        assert_eq!(clusters[2].members, (0..200).step_by(4).collect::<Vec<_>>());
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    fn interval_indices() {
+        let values = [0., 1., 2.5, f64::NAN, 10.];
+        assert_eq!(
+            super::interval_indices(values.into_iter(), 4),
+            [Some(0), Some(0), Some(1), None, Some(3)]
+        );
+        assert_eq!(
+            super::interval_indices([3., 3.].into_iter(), 4),
+            [Some(0), Some(0)]
+        );
+    }
+
+    #[test]
+    fn fit_empty() {
+        let (clusters, noise) = Clique::new(10, 2).fit(&array![[0.], [5.], [10.]], None);
+        assert!(clusters.is_empty());
+        assert_eq!(noise, [0, 1, 2]);
+
+        let empty: Array2<f64> = Array2::zeros((0, 3));
+        let (clusters, noise) = Clique::default().fit(&empty, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
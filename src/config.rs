@@ -0,0 +1,445 @@
+use ndarray::ArrayView1;
+use num_traits::Float;
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AgglomerativeClustering, Birch, CovarianceType, Dbscan, DynClusterer, FuzzyCMeans,
+    GaussianMixture, HDbscan, KMeans, KMedoids, Linkage, NanEuclidean, Optics,
+};
+
+/// A distance metric chosen at runtime, e.g. in a [`ClustererConfig`].
+///
+/// It implements petal-neighbors' `Metric` by dispatching to the chosen
+/// distance, so it can be passed to any estimator that takes a metric.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricConfig {
+    /// The Euclidean distance.
+    #[default]
+    Euclidean,
+
+    /// The sum of the absolute differences of the coordinates.
+    Manhattan,
+
+    /// The largest absolute difference of the coordinates.
+    Chebyshev,
+
+    /// One minus the cosine of the angle between two points, or one if
+    /// either point is at the origin. It does not satisfy the triangle
+    /// inequality, so neighborhood queries with it may miss some neighbors.
+    Cosine,
+
+    /// The Euclidean distance between points with missing values, as
+    /// computed by [`NanEuclidean`].
+    NanEuclidean,
+}
+
+impl<A: Float> Metric<A> for MetricConfig {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.rdistance_to_distance(self.rdistance(x1, x2))
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        let pairs = x1.iter().zip(x2);
+        match self {
+            Self::Euclidean => pairs.fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b)),
+            Self::Manhattan => pairs.fold(A::zero(), |acc, (a, b)| acc + (*a - *b).abs()),
+            Self::Chebyshev => pairs.fold(A::zero(), |acc, (a, b)| acc.max((*a - *b).abs())),
+            Self::Cosine => {
+                let (dot, n1, n2) = pairs.fold(
+                    (A::zero(), A::zero(), A::zero()),
+                    |(dot, n1, n2), (a, b)| (dot + *a * *b, n1 + *a * *a, n2 + *b * *b),
+                );
+                if n1 == A::zero() || n2 == A::zero() {
+                    return A::one();
+                }
+                A::one() - dot / (n1.sqrt() * n2.sqrt())
+            }
+            Self::NanEuclidean => NanEuclidean.rdistance(x1, x2),
+        }
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        match self {
+            Self::Euclidean | Self::NanEuclidean => d.sqrt(),
+            Self::Manhattan | Self::Chebyshev | Self::Cosine => d,
+        }
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        match self {
+            Self::Euclidean | Self::NanEuclidean => d * d,
+            Self::Manhattan | Self::Chebyshev | Self::Cosine => d,
+        }
+    }
+}
+
+/// The configuration of a clustering algorithm, tagged by the name of the
+/// algorithm, which [`ClustererConfig::build`] turns into a clusterer ready
+/// to be fitted.
+///
+/// The configuration can be read with serde from any format, such as JSON,
+/// TOML or YAML, so the algorithm and its parameters can be changed without
+/// recompiling, e.g. in a batch job. The algorithm is given by the
+/// `algorithm` key, in snake case, and the parameters by the other keys.
+/// Parameters that are `Option`s can be left out, in which case the
+/// estimator's default is used.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "json")]
+/// # {
+/// use ndarray::array;
+/// use petal_clustering::ClustererConfig;
+///
+/// let config: ClustererConfig = serde_json::from_str(
+///     r#"{ "algorithm": "dbscan", "eps": 1.0, "min_samples": 2, "metric": "manhattan" }"#,
+/// )
+/// .unwrap();
+/// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+/// let partition = config.build().cluster(&points.view());
+///
+/// assert_eq!(partition.len(), 2);
+/// # }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "algorithm", rename_all = "snake_case")]
+pub enum ClustererConfig {
+    /// [`KMeans`].
+    #[serde(rename = "kmeans")]
+    KMeans {
+        n_clusters: usize,
+        #[serde(default)]
+        max_iter: Option<usize>,
+        #[serde(default)]
+        n_init: Option<usize>,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+
+    /// [`KMedoids`].
+    #[serde(rename = "kmedoids")]
+    KMedoids {
+        n_clusters: usize,
+        #[serde(default)]
+        max_iter: Option<usize>,
+        #[serde(default)]
+        seed: Option<u64>,
+        #[serde(default)]
+        metric: MetricConfig,
+    },
+
+    /// [`Dbscan`].
+    Dbscan {
+        eps: f64,
+        min_samples: usize,
+        #[serde(default)]
+        metric: MetricConfig,
+    },
+
+    /// [`HDbscan`].
+    #[serde(rename = "hdbscan")]
+    HDbscan {
+        min_cluster_size: usize,
+        #[serde(default)]
+        min_samples: Option<usize>,
+        #[serde(default)]
+        metric: MetricConfig,
+    },
+
+    /// [`Optics`].
+    Optics {
+        eps: f64,
+        min_samples: usize,
+        #[serde(default)]
+        metric: MetricConfig,
+    },
+
+    /// [`AgglomerativeClustering`].
+    Agglomerative {
+        n_clusters: usize,
+        #[serde(default)]
+        linkage: Linkage,
+        #[serde(default)]
+        distance_threshold: Option<f64>,
+        #[serde(default)]
+        metric: MetricConfig,
+    },
+
+    /// [`Birch`].
+    Birch {
+        threshold: f64,
+        branching_factor: usize,
+        #[serde(default)]
+        n_clusters: Option<usize>,
+    },
+
+    /// [`GaussianMixture`].
+    GaussianMixture {
+        n_components: usize,
+        #[serde(default)]
+        covariance_type: Option<CovarianceType>,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+
+    /// [`FuzzyCMeans`].
+    #[serde(rename = "fuzzy_cmeans")]
+    FuzzyCMeans {
+        n_clusters: usize,
+        #[serde(default)]
+        fuzziness: Option<f64>,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+}
+
+impl ClustererConfig {
+    /// Creates the configured clusterer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::ClustererConfig;
+    ///
+    /// let config = ClustererConfig::KMeans {
+    ///     n_clusters: 2,
+    ///     max_iter: None,
+    ///     n_init: None,
+    ///     seed: Some(7),
+    /// };
+    /// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+    /// let partition = config.build().cluster(&points.view());
+    ///
+    /// assert_eq!(partition.len(), 2);
+    /// ```
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn build(&self) -> Box<dyn DynClusterer> {
+        match *self {
+            Self::KMeans {
+                n_clusters,
+                max_iter,
+                n_init,
+                seed,
+            } => {
+                let mut kmeans = KMeans::new(n_clusters);
+                if let Some(max_iter) = max_iter {
+                    kmeans.max_iter = max_iter;
+                }
+                if let Some(n_init) = n_init {
+                    kmeans.n_init = n_init;
+                }
+                if let Some(seed) = seed {
+                    kmeans.seed = seed;
+                }
+                Box::new(kmeans)
+            }
+            Self::KMedoids {
+                n_clusters,
+                max_iter,
+                seed,
+                metric,
+            } => {
+                let mut kmedoids = KMedoids::new(n_clusters, metric);
+                if let Some(max_iter) = max_iter {
+                    kmedoids.max_iter = max_iter;
+                }
+                if let Some(seed) = seed {
+                    kmedoids.seed = seed;
+                }
+                Box::new(kmedoids)
+            }
+            Self::Dbscan {
+                eps,
+                min_samples,
+                metric,
+            } => Box::new(Dbscan::new(eps, min_samples, metric)),
+            Self::HDbscan {
+                min_cluster_size,
+                min_samples,
+                metric,
+            } => {
+                let mut hdbscan = HDbscan::new(min_cluster_size, metric);
+                if let Some(min_samples) = min_samples {
+                    hdbscan.min_samples = min_samples;
+                }
+                Box::new(hdbscan)
+            }
+            Self::Optics {
+                eps,
+                min_samples,
+                metric,
+            } => Box::new(Optics::new(eps, min_samples, metric)),
+            Self::Agglomerative {
+                n_clusters,
+                linkage,
+                distance_threshold,
+                metric,
+            } => {
+                let mut agglomerative = AgglomerativeClustering::new(n_clusters, linkage, metric);
+                agglomerative.distance_threshold = distance_threshold;
+                Box::new(agglomerative)
+            }
+            Self::Birch {
+                threshold,
+                branching_factor,
+                n_clusters,
+            } => {
+                let mut birch = Birch::new(threshold, branching_factor);
+                birch.n_clusters = n_clusters;
+                Box::new(birch)
+            }
+            Self::GaussianMixture {
+                n_components,
+                covariance_type,
+                seed,
+            } => {
+                let mut gmm = GaussianMixture::new(n_components);
+                if let Some(covariance_type) = covariance_type {
+                    gmm.covariance_type = covariance_type;
+                }
+                if let Some(seed) = seed {
+                    gmm.seed = seed;
+                }
+                Box::new(gmm)
+            }
+            Self::FuzzyCMeans {
+                n_clusters,
+                fuzziness,
+                seed,
+            } => {
+                let mut fcm = FuzzyCMeans::new(n_clusters);
+                if let Some(fuzziness) = fuzziness {
+                    fcm.fuzziness = fuzziness;
+                }
+                if let Some(seed) = seed {
+                    fcm.seed = seed;
+                }
+                Box::new(fcm)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, aview1};
+
+    use super::*;
+
+    #[test]
+    fn metrics() {
+        let (x, y) = (aview1(&[0., 3.]), aview1(&[4., 0.]));
+        let expected = [
+            (MetricConfig::Euclidean, 5.),
+            (MetricConfig::Manhattan, 7.),
+            (MetricConfig::Chebyshev, 4.),
+            (MetricConfig::Cosine, 1.),
+            (MetricConfig::NanEuclidean, 5.),
+        ];
+        for (metric, d) in expected {
+            assert!(f64::abs(metric.distance(&x, &y) - d) < 1e-12);
+            let r = metric.rdistance(&x, &y);
+            assert!(f64::abs(metric.rdistance_to_distance(r) - d) < 1e-12);
+            assert!(f64::abs(metric.distance_to_rdistance(d) - r) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn build() {
+        let data = array![
+            [1., 2.],
+            [1.2, 2.],
+            [1., 2.3],
+            [8., 7.],
+            [8., 8.],
+            [8.3, 7.5],
+        ];
+        let configs = [
+            ClustererConfig::KMeans {
+                n_clusters: 2,
+                max_iter: None,
+                n_init: Some(5),
+                seed: Some(1),
+            },
+            ClustererConfig::KMedoids {
+                n_clusters: 2,
+                max_iter: None,
+                seed: None,
+                metric: MetricConfig::Manhattan,
+            },
+            ClustererConfig::Dbscan {
+                eps: 1.,
+                min_samples: 2,
+                metric: MetricConfig::Chebyshev,
+            },
+            ClustererConfig::HDbscan {
+                min_cluster_size: 2,
+                min_samples: None,
+                metric: MetricConfig::Euclidean,
+            },
+            ClustererConfig::Optics {
+                eps: 1.,
+                min_samples: 2,
+                metric: MetricConfig::Euclidean,
+            },
+            ClustererConfig::Agglomerative {
+                n_clusters: 2,
+                linkage: Linkage::Single,
+                distance_threshold: None,
+                metric: MetricConfig::Euclidean,
+            },
+            ClustererConfig::Birch {
+                threshold: 0.5,
+                branching_factor: 50,
+                n_clusters: Some(2),
+            },
+            ClustererConfig::GaussianMixture {
+                n_components: 2,
+                covariance_type: None,
+                seed: None,
+            },
+            ClustererConfig::FuzzyCMeans {
+                n_clusters: 2,
+                fuzziness: None,
+                seed: None,
+            },
+        ];
+        for config in &configs {
+            let partition = config.build().cluster(&data.view());
+            assert_eq!(partition.len(), 2, "{config:?}");
+            assert!(partition.noise().is_empty(), "{config:?}");
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn deserialize() {
+        let config: ClustererConfig = serde_json::from_str(
+            r#"{ "algorithm": "hdbscan", "min_cluster_size": 5, "metric": "cosine" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ClustererConfig::HDbscan {
+                min_cluster_size: 5,
+                min_samples: None,
+                metric: MetricConfig::Cosine,
+            }
+        );
+
+        let config: ClustererConfig =
+            serde_json::from_str(r#"{ "algorithm": "kmeans", "n_clusters": 3 }"#).unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ClustererConfig>(&json).unwrap(),
+            config
+        );
+
+        assert!(serde_json::from_str::<ClustererConfig>(r#"{ "algorithm": "kmeans" }"#).is_err());
+    }
+}
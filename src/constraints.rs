@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::union_find::TreeUnionFind;
+
+/// Pairwise constraints on a clustering, for semi-supervised clustering
+/// with [`Dbscan::fit_constrained`](crate::Dbscan::fit_constrained).
+///
+/// Must-link constraints are transitive: points linked through a chain of
+/// must-link pairs all end up in the same cluster or are all noise. Two
+/// points with a cannot-link constraint never end up in the same cluster.
+///
+/// # Examples
+///
+/// ```
+/// use maplit::hashmap;
+/// use petal_clustering::Constraints;
+///
+/// let constraints = Constraints::from_partial_labels(&hashmap! {0 => vec![1, 3], 1 => vec![5]});
+/// assert_eq!(constraints.must_link, [(1, 3)]);
+/// assert_eq!(constraints.cannot_link, [(1, 5)]);
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Constraints {
+    /// Pairs of points that must be in the same cluster.
+    pub must_link: Vec<(usize, usize)>,
+
+    /// Pairs of points that must not be in the same cluster.
+    pub cannot_link: Vec<(usize, usize)>,
+}
+
+/// An error for constraints that no clustering can satisfy.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ConstraintError {
+    /// A constraint refers to a point beyond the `n_points` points of the
+    /// input.
+    OutOfBounds { point: usize, n_points: usize },
+
+    /// Points `a` and `b` cannot be linked, but are linked through must-link
+    /// constraints.
+    Infeasible { a: usize, b: usize },
+}
+
+impl Constraints {
+    /// Creates constraints from partially labeled points, given as the
+    /// points of each label: points with the same label must be linked, and
+    /// points with different labels cannot be.
+    ///
+    /// Only the first point of each label is cannot-linked to the first
+    /// point of each other label, which is enough given the must-link
+    /// constraints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Constraints;
+    ///
+    /// let partial_labels = hashmap! {0 => vec![1, 2, 3], 1 => vec![5]};
+    /// let constraints = Constraints::from_partial_labels(&partial_labels);
+    ///
+    /// assert_eq!(constraints.must_link, [(1, 2), (2, 3)]);
+    /// assert_eq!(constraints.cannot_link, [(1, 5)]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::implicit_hasher)]
+    pub fn from_partial_labels(labels: &HashMap<usize, Vec<usize>>) -> Self {
+        let mut groups: Vec<_> = labels.iter().filter(|(_, p)| !p.is_empty()).collect();
+        groups.sort_unstable_by_key(|(label, _)| **label);
+
+        let must_link = groups
+            .iter()
+            .flat_map(|(_, points)| points.windows(2).map(|w| (w[0], w[1])))
+            .collect();
+        let mut cannot_link = Vec::new();
+        for (i, (_, a)) in groups.iter().enumerate() {
+            for (_, b) in &groups[i + 1..] {
+                cannot_link.push((a[0], b[0]));
+            }
+        }
+        Self {
+            must_link,
+            cannot_link,
+        }
+    }
+
+    /// Groups `n_points` points into the components of the must-link
+    /// constraints, and finds the components each component cannot be
+    /// linked to.
+    ///
+    /// Returns the component of each point, indexed from zero, the points
+    /// of each component, and the components each component cannot be
+    /// linked to.
+    pub(crate) fn components(
+        &self,
+        n_points: usize,
+    ) -> Result<ConstraintComponents, ConstraintError> {
+        let check = |point: usize| {
+            if point < n_points {
+                Ok(())
+            } else {
+                Err(ConstraintError::OutOfBounds { point, n_points })
+            }
+        };
+        let mut uf = TreeUnionFind::new(n_points);
+        for &(a, b) in &self.must_link {
+            check(a)?;
+            check(b)?;
+            uf.union(a, b);
+        }
+
+        let mut ids = HashMap::new();
+        let mut component = Vec::with_capacity(n_points);
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        for i in 0..n_points {
+            let next = ids.len();
+            let id = *ids.entry(uf.find(i)).or_insert(next);
+            if id == members.len() {
+                members.push(Vec::new());
+            }
+            members[id].push(i);
+            component.push(id);
+        }
+
+        let mut cannot_link = vec![Vec::new(); members.len()];
+        for &(a, b) in &self.cannot_link {
+            check(a)?;
+            check(b)?;
+            let (ca, cb) = (component[a], component[b]);
+            if ca == cb {
+                return Err(ConstraintError::Infeasible { a, b });
+            }
+            cannot_link[ca].push(cb);
+            cannot_link[cb].push(ca);
+        }
+        Ok(ConstraintComponents {
+            component,
+            members,
+            cannot_link,
+        })
+    }
+}
+
+/// The must-link components of points, found by
+/// [`Constraints::components`].
+pub(crate) struct ConstraintComponents {
+    /// The component of each point.
+    pub(crate) component: Vec<usize>,
+
+    /// The points of each component, in increasing order.
+    pub(crate) members: Vec<Vec<usize>>,
+
+    /// The components each component cannot be linked to.
+    pub(crate) cannot_link: Vec<Vec<usize>>,
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { point, n_points } => {
+                write!(f, "point {point} is out of bounds for {n_points} points")
+            }
+            Self::Infeasible { a, b } => write!(
+                f,
+                "points {a} and {b} cannot be linked, but are linked by must-link constraints"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+
+    use super::*;
+
+    #[test]
+    fn from_partial_labels() {
+        let labels = hashmap! {2 => vec![4, 0, 6], 0 => vec![1], 1 => vec![]};
+        let constraints = Constraints::from_partial_labels(&labels);
+        assert_eq!(constraints.must_link, [(4, 0), (0, 6)]);
+        assert_eq!(constraints.cannot_link, [(1, 4)]);
+    }
+
+    #[test]
+    fn components() {
+        let constraints = Constraints {
+            must_link: vec![(0, 2), (3, 2)],
+            cannot_link: vec![(1, 3)],
+        };
+        let components = constraints.components(5).unwrap();
+        assert_eq!(components.component, [0, 1, 0, 0, 2]);
+        assert_eq!(components.members, [vec![0, 2, 3], vec![1], vec![4]]);
+        assert_eq!(components.cannot_link, [vec![1], vec![0], vec![]]);
+
+        assert_eq!(
+            constraints.components(3).err(),
+            Some(ConstraintError::OutOfBounds {
+                point: 3,
+                n_points: 3
+            })
+        );
+        let constraints = Constraints {
+            must_link: vec![(0, 1), (1, 2)],
+            cannot_link: vec![(2, 0)],
+        };
+        assert_eq!(
+            constraints.components(3).err(),
+            Some(ConstraintError::Infeasible { a: 2, b: 0 })
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Synthetic datasets with a known cluster structure, for tests, examples
+//! and benchmarks.
+//!
+//! Each generator is seeded, so that the same arguments always give the same
+//! points, and returns the points along with the label of each, the index of
+//! the group it was drawn from, in increasing order. Comparing a clustering
+//! with the labels, e.g. with [`match_clusters`](crate::match_clusters),
+//! tells whether an algorithm recovers the structure.
+//!
+//! # Examples
+//!
+//! ```
+//! use petal_clustering::datasets::make_moons;
+//! use petal_clustering::{Dbscan, Fit};
+//! use petal_neighbors::distance::Euclidean;
+//!
+//! let (points, labels) = make_moons(200, 0.05, 42);
+//! let (clusters, _) = Dbscan::new(0.3, 5, Euclidean::default()).fit(&points, None);
+//!
+//! assert_eq!(clusters.len(), 2);
+//! for members in clusters.values() {
+//!     assert!(members.iter().all(|&i| labels[i] == labels[members[0]]));
+//! }
+//! ```
+
+use std::f64::consts::PI;
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// The range of each coordinate of the centers drawn by [`make_blobs`].
+const CENTER_BOX: (f64, f64) = (-10., 10.);
+
+/// Draws isotropic Gaussian blobs of `n_features` dimensions, with
+/// standard deviation `cluster_std`, around `n_centers` centers drawn
+/// uniformly from `[-10, 10)` in each dimension.
+///
+/// The points are split among the blobs as evenly as possible, and ordered
+/// by blob.
+///
+/// # Panics
+/// Panics if `n_centers` is zero while `n_samples` is not.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::datasets::make_blobs;
+///
+/// let (points, labels) = make_blobs(10, 2, 3, 1., 7);
+/// assert_eq!(points.dim(), (10, 2));
+/// assert_eq!(labels, [0, 0, 0, 0, 1, 1, 1, 2, 2, 2]);
+/// assert_eq!(make_blobs(10, 2, 3, 1., 7).0, points);
+/// ```
+#[must_use]
+pub fn make_blobs(
+    n_samples: usize,
+    n_features: usize,
+    n_centers: usize,
+    cluster_std: f64,
+    seed: u64,
+) -> (Array2<f64>, Vec<usize>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let centers = Array2::from_shape_fn((n_centers, n_features), |_| {
+        rng.random_range(CENTER_BOX.0..CENTER_BOX.1)
+    });
+    blobs(n_samples, &centers.view(), cluster_std, &mut rng)
+}
+
+/// Draws isotropic Gaussian blobs with standard deviation `cluster_std`
+/// around the given centers, one per row.
+///
+/// The points are split among the blobs as evenly as possible, and ordered
+/// by blob.
+///
+/// # Panics
+/// Panics if `centers` is empty while `n_samples` is not.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::datasets::make_blobs_around;
+///
+/// let centers = array![[0., 0.], [100., 100.]];
+/// let (points, labels) = make_blobs_around(6, &centers, 0.1, 7);
+/// assert_eq!(labels, [0, 0, 0, 1, 1, 1]);
+/// assert!(points.row(4).iter().all(|v| (v - 100.).abs() < 1.));
+/// ```
+#[must_use]
+pub fn make_blobs_around<S>(
+    n_samples: usize,
+    centers: &ArrayBase<S, Ix2>,
+    cluster_std: f64,
+    seed: u64,
+) -> (Array2<f64>, Vec<usize>)
+where
+    S: Data<Elem = f64>,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    blobs(n_samples, &centers.view(), cluster_std, &mut rng)
+}
+
+/// Draws Gaussian blobs as [`make_blobs`] does, and stretches them by
+/// multiplying each point, as a row vector, by `transformation`, so that the
+/// blobs are elongated along the same direction.
+///
+/// # Panics
+/// Panics if `transformation` is not square, or if `n_centers` is zero while
+/// `n_samples` is not.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::datasets::make_anisotropic_blobs;
+///
+/// let transformation = array![[0.6, -0.6], [-0.4, 0.8]];
+/// let (points, labels) = make_anisotropic_blobs(90, 3, &transformation, 1., 170);
+/// assert_eq!(points.dim(), (90, 2));
+/// assert_eq!(labels[89], 2);
+/// ```
+#[must_use]
+pub fn make_anisotropic_blobs<S>(
+    n_samples: usize,
+    n_centers: usize,
+    transformation: &ArrayBase<S, Ix2>,
+    cluster_std: f64,
+    seed: u64,
+) -> (Array2<f64>, Vec<usize>)
+where
+    S: Data<Elem = f64>,
+{
+    assert_eq!(
+        transformation.nrows(),
+        transformation.ncols(),
+        "transformation must be a square matrix"
+    );
+    let (points, labels) = make_blobs(
+        n_samples,
+        transformation.nrows(),
+        n_centers,
+        cluster_std,
+        seed,
+    );
+    (points.dot(transformation), labels)
+}
+
+/// Draws two interleaving half circles of radius one, the upper one centered
+/// at `(0, 0)` and the lower one at `(1, 0.5)`, with Gaussian noise of
+/// standard deviation `noise` added to each coordinate. Clusters of this
+/// shape are not convex, so centroid-based algorithms fail to separate them.
+///
+/// The upper half circle has `n_samples / 2` points, labeled 0, and the
+/// lower one the rest, labeled 1.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::datasets::make_moons;
+///
+/// let (points, labels) = make_moons(5, 0., 0);
+/// assert_eq!(labels, [0, 0, 1, 1, 1]);
+/// assert!((points[[1, 0]] + 1.).abs() < 1e-12);
+/// ```
+#[must_use]
+pub fn make_moons(n_samples: usize, noise: f64, seed: u64) -> (Array2<f64>, Vec<usize>) {
+    let n_upper = n_samples / 2;
+    let n_lower = n_samples - n_upper;
+    let upper = linspace(n_upper, PI, true).map(|t| (t.cos(), t.sin()));
+    let lower = linspace(n_lower, PI, true).map(|t| (1. - t.cos(), 0.5 - t.sin()));
+    shapes(upper, lower, n_upper, noise, seed)
+}
+
+/// Draws two concentric circles around the origin, of radius one and
+/// `factor`, with Gaussian noise of standard deviation `noise` added to each
+/// coordinate. Neither circle can be separated from the other by a line.
+///
+/// The outer circle has `n_samples / 2` points, labeled 0, and the inner
+/// one the rest, labeled 1.
+///
+/// # Panics
+/// Panics if `factor` is not in `[0, 1)`.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::datasets::make_circles;
+///
+/// let (points, labels) = make_circles(8, 0.5, 0., 0);
+/// assert_eq!(labels, [0, 0, 0, 0, 1, 1, 1, 1]);
+/// assert!((points[[5, 1]] - 0.5).abs() < 1e-12);
+/// ```
+#[must_use]
+pub fn make_circles(
+    n_samples: usize,
+    factor: f64,
+    noise: f64,
+    seed: u64,
+) -> (Array2<f64>, Vec<usize>) {
+    assert!(
+        (0. ..1.).contains(&factor),
+        "factor must be in [0, 1), the radius of the inner circle"
+    );
+    let n_outer = n_samples / 2;
+    let n_inner = n_samples - n_outer;
+    let outer = linspace(n_outer, 2. * PI, false).map(|t| (t.cos(), t.sin()));
+    let inner = linspace(n_inner, 2. * PI, false).map(|t| (factor * t.cos(), factor * t.sin()));
+    shapes(outer, inner, n_outer, noise, seed)
+}
+
+/// Draws the points of each blob around its center, splitting the points as
+/// evenly as possible with the first blobs taking the remainder.
+fn blobs<R: Rng>(
+    n_samples: usize,
+    centers: &ArrayView2<f64>,
+    cluster_std: f64,
+    rng: &mut R,
+) -> (Array2<f64>, Vec<usize>) {
+    let n_centers = centers.nrows();
+    assert!(
+        n_centers > 0 || n_samples == 0,
+        "points need at least one center"
+    );
+    let labels: Vec<usize> = (0..n_centers)
+        .flat_map(|c| {
+            let size = n_samples / n_centers + usize::from(c < n_samples % n_centers);
+            std::iter::repeat(c).take(size)
+        })
+        .collect();
+    let points = Array2::from_shape_fn((n_samples, centers.ncols()), |(i, j)| {
+        centers[[labels[i], j]] + cluster_std * normal(rng)
+    });
+    (points, labels)
+}
+
+/// Collects the points of two shapes, the first `n_first` of them labeled
+/// 0, with Gaussian noise added to each coordinate.
+fn shapes(
+    first: impl Iterator<Item = (f64, f64)>,
+    second: impl Iterator<Item = (f64, f64)>,
+    n_first: usize,
+    noise: f64,
+    seed: u64,
+) -> (Array2<f64>, Vec<usize>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let data: Vec<f64> = first
+        .chain(second)
+        .flat_map(|(x, y)| [x, y])
+        .map(|v| v + noise * normal(&mut rng))
+        .collect();
+    let n_samples = data.len() / 2;
+    let labels = (0..n_samples).map(|i| usize::from(i >= n_first)).collect();
+    let points = Array2::from_shape_vec((n_samples, 2), data).expect("two coordinates per point");
+    (points, labels)
+}
+
+/// Returns `n` evenly spaced values from zero to `end`, including `end` if
+/// `endpoint` is set.
+#[allow(clippy::cast_precision_loss)]
+fn linspace(n: usize, end: f64, endpoint: bool) -> impl Iterator<Item = f64> {
+    let steps = if endpoint { n.saturating_sub(1) } else { n };
+    let step = if steps == 0 { 0. } else { end / steps as f64 };
+    (0..n).map(move |i| i as f64 * step)
+}
+
+/// Draws a value from the standard normal distribution with the Box-Muller
+/// transform.
+fn normal<R: Rng>(rng: &mut R) -> f64 {
+    // `1 - u` is in (0, 1], so that its logarithm is finite
+    let u1 = 1. - rng.random::<f64>();
+    let u2 = rng.random::<f64>();
+    (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Axis};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Dbscan, Fit};
+
+    #[test]
+    fn blobs() {
+        let (points, labels) = make_blobs(3000, 3, 4, 0.5, 1);
+        assert_eq!(points.dim(), (3000, 3));
+        assert_eq!(labels.iter().filter(|l| **l == 3).count(), 750);
+        assert_eq!(make_blobs(3000, 3, 4, 0.5, 1).0, points);
+        assert_ne!(make_blobs(3000, 3, 4, 0.5, 2).0, points);
+
+        // the sample mean and standard deviation of a blob
+        let centers = array![[5., -5.]];
+        let (points, _) = make_blobs_around(10_000, &centers, 2., 3);
+        let mean = points.mean_axis(Axis(0)).expect("non-empty");
+        assert!((mean[0] - 5.).abs() < 0.1 && (mean[1] + 5.).abs() < 0.1);
+        let std = points.std_axis(Axis(0), 0.);
+        assert!(std.iter().all(|s| (s - 2.).abs() < 0.1));
+
+        let (points, labels) = make_blobs(0, 2, 0, 1., 0);
+        assert_eq!(points.dim(), (0, 2));
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn shapes() {
+        for (points, labels) in [make_moons(301, 0.05, 5), make_circles(301, 0.3, 0.05, 5)] {
+            assert_eq!(points.dim(), (301, 2));
+            assert_eq!(labels.iter().filter(|l| **l == 0).count(), 150);
+            let (clusters, _) = Dbscan::new(0.2, 5, Euclidean::default()).fit(&points, None);
+            assert_eq!(clusters.len(), 2);
+            for members in clusters.values() {
+                assert!(members.iter().all(|&i| labels[i] == labels[members[0]]));
+            }
+        }
+
+        let (points, _) = make_circles(4, 0., 0., 0);
+        let expected = array![[1., 0.], [-1., 0.], [0., 0.], [0., 0.]];
+        assert!(points
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+}
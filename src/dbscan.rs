@@ -1,16 +1,26 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::{AddAssign, DivAssign};
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::Instant;
 
-use ndarray::{ArrayBase, Data, Ix2};
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
 use num_traits::{float::FloatCore, FromPrimitive};
-use petal_neighbors::{
-    distance::{Euclidean, Metric},
-    BallTree,
-};
-use rayon::prelude::*;
+use petal_neighbors::distance::{Euclidean, Metric};
+use petal_neighbors::BallTree;
 use serde::{Deserialize, Serialize};
 
 use super::Fit;
+use crate::constraints::{ConstraintComponents, ConstraintError, Constraints};
+use crate::diagnostics::{Degeneracy, DegeneracyPolicy, FiniteBallTree};
+use crate::grid::{adjacent, cell_of, neighbor_offsets};
+use crate::interrupt::{unchecked, CancelToken, FitOutcome, LimitExceeded, Limits, CHUNK_SIZE};
+use crate::neighbor_graph::NeighborGraph;
+use crate::noise::assign_noise;
+use crate::parallel::prelude::*;
+use crate::preprocessing::Deduplicated;
+use crate::report::{CountingMetric, FitReport, SizeDistribution};
+use crate::trace::{counts, phase};
+use crate::union_find::{ConcurrentUnionFind, TreeUnionFind};
 
 /// DBSCAN (density-based spatial clustering of applications with noise)
 /// clustering algorithm.
@@ -38,6 +48,87 @@ pub struct Dbscan<A, M> {
     /// The minimum number of points required to form a dense region.
     pub min_samples: usize,
     pub metric: M,
+
+    /// How border points, which are not core points but are within `eps` of
+    /// one, are assigned to clusters.
+    #[serde(default)]
+    pub border_policy: BorderPolicy,
+
+    /// Whether to collapse identical points into one point, weighted by the
+    /// number of copies, before searching the neighborhoods, which saves
+    /// time and memory when the input has many duplicates. The clusters are
+    /// the same, up to the assignment of border points reachable from more
+    /// than one cluster. Only `fit` without partial labels deduplicates.
+    #[serde(default)]
+    pub deduplicate: bool,
+
+    #[serde(default)]
+    core_samples: Vec<usize>,
+    #[serde(default)]
+    core_distances: Vec<A>,
+}
+
+/// How DBSCAN assigns border points, which are not core points but are
+/// within `eps` of at least one core point.
+///
+/// Core points and the number of clusters do not depend on the policy; only
+/// the border points move between clusters or to noise.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{BorderPolicy, Dbscan, Fit};
+///
+/// // the middle point is a border point of both clusters, closer to the right one
+/// let points = array![[0.], [0.5], [1.], [1.5], [3.4], [5.], [5.5], [6.], [6.5]];
+/// let mut dbscan = Dbscan::new(2., 4, Euclidean::default());
+/// let (clusters, _) = dbscan.fit(&points, None);
+/// assert!(clusters[&0].contains(&4));
+///
+/// dbscan.border_policy = BorderPolicy::NearestCore;
+/// let (clusters, _) = dbscan.fit(&points, None);
+/// assert_eq!(clusters[&1], [4, 5, 6, 7, 8]);
+///
+/// dbscan.border_policy = BorderPolicy::Noise;
+/// let (_, noise) = dbscan.fit(&points, None);
+/// assert_eq!(noise, [4]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum BorderPolicy {
+    /// A border point joins the first cluster that reaches it, where
+    /// clusters are expanded in the order of their first core point. The
+    /// result is deterministic for a given input, but may change if the
+    /// points are reordered.
+    #[default]
+    FirstCore,
+
+    /// A border point joins the cluster of its nearest core point, with ties
+    /// broken by the lower index. The result does not depend on the order of
+    /// the points, except for ties.
+    NearestCore,
+
+    /// Border points are noise, so that clusters consist of core points
+    /// only, as in DBSCAN*.
+    Noise,
+}
+
+/// The neighborhoods of a DBSCAN fit at the largest radius of a parameter
+/// sweep, from which the clusterings at smaller radii are extracted without
+/// searching the neighbors again.
+///
+/// It is created by [`Dbscan::sweep`], and each extracted clustering is the
+/// same as that of fitting DBSCAN with the same parameters and a smaller
+/// `eps`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DbscanSweep<A> {
+    min_samples: usize,
+    border_policy: BorderPolicy,
+    max_eps: A,
+
+    /// The neighbors of each point within `max_eps`, with their distances.
+    neighborhoods: Vec<Vec<(usize, A)>>,
 }
 
 impl<A> Default for Dbscan<A, Euclidean>
@@ -49,31 +140,113 @@ where
             eps: A::from(0.5_f32).expect("valid float"),
             min_samples: 5,
             metric: Euclidean::default(),
+            border_policy: BorderPolicy::FirstCore,
+            deduplicate: false,
+            core_samples: Vec::new(),
+            core_distances: Vec::new(),
         }
     }
 }
 
 impl<A, M> Dbscan<A, M> {
+    /// Creates a model with the given `eps`, `min_samples` and `metric`, which
+    /// assigns border points to the first core point reaching them and does not
+    /// deduplicate its input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{BorderPolicy, Dbscan};
+    ///
+    /// let dbscan = Dbscan::new(0.5, 5, Euclidean::default());
+    /// assert_eq!(dbscan.eps, 0.5);
+    /// assert_eq!(dbscan.border_policy, BorderPolicy::FirstCore);
+    /// assert!(!dbscan.deduplicate);
+    /// ```
     #[must_use]
     pub fn new(eps: A, min_samples: usize, metric: M) -> Self {
         Self {
             eps,
             min_samples,
             metric,
+            border_policy: BorderPolicy::FirstCore,
+            deduplicate: false,
+            core_samples: Vec::new(),
+            core_distances: Vec::new(),
         }
     }
+
+    /// Returns the indices of the core points of the last fit, the points
+    /// with at least `min_samples` points within `eps`, in increasing order,
+    /// or an empty slice if the model has not been fitted.
+    ///
+    /// The core points are kept by the fits that search the neighborhood of
+    /// every point of the input; they are not by [`Dbscan::fit_constrained`]
+    /// and [`Dbscan::fit_stream`], nor by `fit` with partial labels or
+    /// `deduplicate` set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(1.02, 3, Euclidean::default());
+    /// dbscan.fit(&points, None);
+    ///
+    /// assert_eq!(dbscan.core_sample_indices(), [1]);
+    /// assert_eq!(dbscan.core_distances(), [1.]);
+    /// ```
+    #[must_use]
+    pub fn core_sample_indices(&self) -> &[usize] {
+        &self.core_samples
+    }
+
+    /// Returns the core distance of each core point of the last fit, in the
+    /// order of [`Dbscan::core_sample_indices`]: the distance to its
+    /// `min_samples`-th nearest point, counting the point itself, which is
+    /// at most `eps`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit};
+    ///
+    /// let points = array![[0.], [1.], [3.], [10.]];
+    /// let mut dbscan = Dbscan::new(2., 2, Euclidean::default());
+    /// dbscan.fit(&points, None);
+    ///
+    /// assert_eq!(dbscan.core_sample_indices(), [0, 1, 2]);
+    /// assert_eq!(dbscan.core_distances(), [1., 1., 2.]);
+    /// ```
+    #[must_use]
+    pub fn core_distances(&self) -> &[A] {
+        &self.core_distances
+    }
 }
 
 /// Fits the DBSCAN clustering algorithm to the given input data.
 ///
 /// # Parameters
 /// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
-/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+/// - `params`: Optional partial labels, mapping each label to the indices of the points known to have it.
+///   Points with the same label are kept in the same cluster, and points with different labels in different
+///   clusters, as in [`Dbscan::fit_constrained`].
 ///
 /// # Returns
 /// A tuple containing:
 /// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
 /// - `Vec<usize>`: A vector of indices representing the noise points that do not belong to any cluster.
+///   Points with a NaN or infinite coordinate have no neighbors, so they are noise, unless `metric` compares them,
+///   as [`NanEuclidean`](crate::NanEuclidean) does.
+///
+/// # Panics
+/// Panics if a point is out of bounds or has more than one label.
 impl<S, A, M>
     Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
     for Dbscan<A, M>
@@ -85,45 +258,1757 @@ where
     fn fit(
         &mut self,
         input: &ArrayBase<S, Ix2>,
-        _params: Option<&HashMap<usize, Vec<usize>>>,
+        params: Option<&HashMap<usize, Vec<usize>>>,
     ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.clear_core_samples();
+        if let Some(labels) = params {
+            let constraints = Constraints::from_partial_labels(labels);
+            return self
+                .fit_constrained(input, &constraints)
+                .unwrap_or_else(|e| panic!("invalid partial labels: {e}"));
+        }
         // `BallTree` does not accept an empty input.
         if input.is_empty() {
             return (HashMap::new(), Vec::new());
         }
 
+        if self.deduplicate {
+            let deduplicated = Deduplicated::new(input);
+            if deduplicated.weights.len() < input.nrows() {
+                return self.fit_deduplicated(&deduplicated);
+            }
+        }
+
+        let _phase = phase!("fit", n_points = input.nrows(), n_features = input.ncols());
+        let input = input.as_standard_layout();
+        let neighborhoods = build_neighborhoods(&input, self.eps, self.metric.clone());
+        let min_samples = vec![self.min_samples; input.nrows()];
+        self.cluster(&input.view(), &neighborhoods, &min_samples)
+    }
+}
+
+impl<A, M> Dbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    /// Fits DBSCAN with a separate `min_samples` for each point, overriding
+    /// `self.min_samples`.
+    ///
+    /// A point is a core point if its neighborhood has at least as many points
+    /// as its own `min_samples`, so points from less reliable sources can be
+    /// required to have more support before they expand a cluster. Such points
+    /// may still join a cluster as border points.
+    ///
+    /// # Panics
+    /// Panics if `min_samples` does not have one entry for each row of
+    /// `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+    /// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+    /// // the last two points need three neighbors to be core points
+    /// let (clusters, noise) = dbscan.fit_with_min_samples(&points, &[2, 2, 2, 3, 3]);
+    ///
+    /// assert_eq!(clusters.len(), 1);
+    /// assert_eq!(noise, [3, 4]);
+    /// ```
+    pub fn fit_with_min_samples<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        min_samples: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        assert_eq!(
+            input.nrows(),
+            min_samples.len(),
+            "each point should have its own `min_samples`"
+        );
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
         let input = input.as_standard_layout();
         let neighborhoods = build_neighborhoods(&input, self.eps, self.metric.clone());
+        self.cluster(&input.view(), &neighborhoods, min_samples)
+    }
+
+    /// Fits DBSCAN with a separate radius for each dimension, overriding
+    /// `self.eps`, for features in incomparable units that should not be
+    /// rescaled globally.
+    ///
+    /// Each coordinate is divided by the radius of its dimension, and the
+    /// neighborhoods are searched with a radius of one, so with the
+    /// Euclidean distance the neighborhood of a point is the axis-aligned
+    /// ellipsoid around it with `radii` as semi-axes. With
+    /// [`BorderPolicy::NearestCore`], border points are assigned by the
+    /// distance between the divided coordinates.
+    ///
+    /// # Panics
+    /// Panics if `radii` does not have one entry for each column of `input`,
+    /// or if a radius is not positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// // a position in meters and a time in seconds
+    /// let points = array![[0., 0.], [1., 50.], [2., 100.], [100., 0.], [101., 30.], [1., 5000.]];
+    /// let mut dbscan = Dbscan::new(0., 2, Euclidean::default());
+    /// let (clusters, noise) = dbscan.fit_with_radii(&points, &[2., 100.]);
+    ///
+    /// let mut clusters: Vec<_> = clusters.into_values().collect();
+    /// clusters.sort_unstable();
+    /// assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4]]);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    pub fn fit_with_radii<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        radii: &[A],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        assert_eq!(
+            input.ncols(),
+            radii.len(),
+            "each dimension should have its own radius"
+        );
+        assert!(
+            radii.iter().all(|r| *r > A::zero()),
+            "the radii should be positive"
+        );
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
+        let mut scaled = input.to_owned();
+        for (mut column, radius) in scaled.columns_mut().into_iter().zip(radii) {
+            column.mapv_inplace(|v| v / *radius);
+        }
+        let neighborhoods = build_neighborhoods(&scaled, A::one(), self.metric.clone());
+        let min_samples = vec![self.min_samples; scaled.nrows()];
+        self.cluster(&scaled.view(), &neighborhoods, &min_samples)
+    }
 
-        let mut visited = vec![false; input.nrows()];
-        let mut clusters = HashMap::new();
-        for (idx, neighbors) in neighborhoods.iter().enumerate() {
-            if visited[idx] || neighbors.len() < self.min_samples {
+    /// Fits DBSCAN expanding clusters only from the given seed points, such
+    /// as known events of interest, so that the clusters are the regions
+    /// density-connected to a seed and every other point is noise.
+    ///
+    /// A seed that is a core point starts a cluster as in `fit`. A seed that
+    /// is not starts one from the core points in its neighborhood, if any,
+    /// and is noise otherwise. Clusters are numbered from zero in the order
+    /// of their first seed, and border points are assigned according to
+    /// `self.border_policy`.
+    ///
+    /// # Panics
+    /// Panics if a seed is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+    /// let (clusters, noise) = dbscan.fit_from_seeds(&points, &[4]);
+    ///
+    /// assert_eq!(clusters.len(), 1);
+    /// assert_eq!(clusters[&0], [3, 4]);
+    /// assert_eq!(noise, [0, 1, 2, 5]);
+    /// ```
+    pub fn fit_from_seeds<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        seeds: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        assert!(
+            seeds.iter().all(|&i| i < input.nrows()),
+            "seeds should be indices of points in `input`"
+        );
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
+        let input = input.as_standard_layout();
+        let neighborhoods = build_neighborhoods(&input, self.eps, self.metric.clone());
+        let min_samples = vec![self.min_samples; input.nrows()];
+        (self.core_samples, self.core_distances) =
+            core_samples(&neighborhoods, &min_samples, |i, j| {
+                self.metric.distance(&input.row(i), &input.row(j))
+            });
+        apply_border_policy(
+            cluster_from_seeds(&neighborhoods, &min_samples, seeds),
+            &neighborhoods,
+            &min_samples,
+            self.border_policy,
+            |i, j| self.metric.distance(&input.row(i), &input.row(j)),
+        )
+    }
+
+    /// Fits DBSCAN subject to must-link and cannot-link constraints, as in
+    /// C-DBSCAN.
+    ///
+    /// Points linked by must-link constraints join a cluster together: when
+    /// a cluster reaches one of them, it takes all of them, and expands from
+    /// those that are core points. A cluster does not take points that cannot
+    /// be linked to any of its points, which may then start or join another
+    /// cluster or be noise. Border points join the first cluster that
+    /// reaches them, regardless of `self.border_policy`.
+    ///
+    /// # Errors
+    /// Returns an error if a constraint refers to a point out of bounds, or
+    /// if two points cannot be linked but are linked through must-link
+    /// constraints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Constraints, Dbscan};
+    ///
+    /// let points = array![[0.], [1.], [2.], [3.], [10.], [11.], [12.]];
+    /// let mut dbscan = Dbscan::new(1.5, 2, Euclidean::default());
+    /// let constraints = Constraints {
+    ///     must_link: vec![(3, 4)],
+    ///     cannot_link: vec![(0, 6)],
+    /// };
+    /// let (clusters, noise) = dbscan.fit_constrained(&points, &constraints).unwrap();
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2, 3, 4, 5]);
+    /// assert_eq!(noise, [6]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_constrained<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        constraints: &Constraints,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>), ConstraintError>
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        let components = constraints.components(input.nrows())?;
+        if input.is_empty() {
+            return Ok((HashMap::new(), Vec::new()));
+        }
+
+        let input = input.as_standard_layout();
+        let neighborhoods = build_neighborhoods(&input, self.eps, self.metric.clone());
+        Ok(cluster_constrained(
+            &neighborhoods,
+            self.min_samples,
+            &components,
+        ))
+    }
+
+    /// Fits DBSCAN to precomputed neighborhoods, such as ones found by a
+    /// brute-force search on a GPU, instead of querying a `BallTree`.
+    ///
+    /// `neighborhoods[i]` lists the points within `eps` of point `i`,
+    /// including the point itself. `input` is only used to measure distances
+    /// for `BorderPolicy::NearestCore`, so the neighborhoods are trusted to
+    /// match `self.eps` and `self.metric`.
+    ///
+    /// # Panics
+    /// Panics if `neighborhoods` does not have one entry for each row of
+    /// `input`, or if a neighbor is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let neighborhoods = [
+    ///     vec![0, 1, 2], vec![0, 1, 2], vec![0, 1, 2], vec![3, 4], vec![3, 4], vec![5],
+    /// ];
+    /// let (clusters, noise) =
+    ///     Dbscan::new(3., 2, Euclidean::default()).fit_from_neighborhoods(&points, &neighborhoods);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    pub fn fit_from_neighborhoods<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        neighborhoods: &[Vec<usize>],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        assert_eq!(
+            input.nrows(),
+            neighborhoods.len(),
+            "each point should have its own neighborhood"
+        );
+        assert!(
+            neighborhoods.iter().flatten().all(|j| *j < input.nrows()),
+            "neighbors should be points of the input"
+        );
+        let input = input.as_standard_layout();
+        let min_samples = vec![self.min_samples; input.nrows()];
+        self.cluster(&input.view(), neighborhoods, &min_samples)
+    }
+
+    /// Fits DBSCAN to a precomputed matrix of distances between points, such
+    /// as the combined distances of several views of the points computed by
+    /// [`MultiView::pairwise`](crate::MultiView::pairwise), ignoring
+    /// `self.metric`.
+    ///
+    /// The neighborhood of point `i` holds the points `j` with
+    /// `distances[[i, j]] <= self.eps`, and the point itself. The distances
+    /// need not satisfy the triangle inequality.
+    ///
+    /// # Panics
+    /// Panics if `distances` is not square.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let distances = array![
+    ///     [0., 1., 1., 9.],
+    ///     [1., 0., 2., 9.],
+    ///     [1., 2., 0., 8.],
+    ///     [9., 9., 8., 0.],
+    /// ];
+    /// let (clusters, noise) =
+    ///     Dbscan::new(1.5, 2, Euclidean::default()).fit_precomputed(&distances);
+    ///
+    /// assert_eq!(clusters[&0].len(), 3);
+    /// assert_eq!(noise, [3]);
+    /// ```
+    pub fn fit_precomputed<S>(
+        &mut self,
+        distances: &ArrayBase<S, Ix2>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        let n = distances.nrows();
+        assert_eq!(n, distances.ncols(), "`distances` should be square");
+        let _phase = phase!("fit", n_points = n);
+        let neighborhoods: Vec<Vec<usize>> = {
+            let _phase = phase!("neighborhoods", n_points = n);
+            distances
+                .rows()
+                .into_iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(j, d)| *j == i || **d <= self.eps)
+                        .map(|(j, _)| j)
+                        .collect()
+                })
+                .collect()
+        };
+        let min_samples = vec![self.min_samples; n];
+        let distance = |i: usize, j: usize| distances[[i, j]];
+        (self.core_samples, self.core_distances) =
+            core_samples(&neighborhoods, &min_samples, distance);
+        self.expand(distance, &neighborhoods, &min_samples)
+    }
+
+    /// Fits DBSCAN to the neighbors of a [`NeighborGraph`] within
+    /// `self.eps`, instead of searching them again, ignoring `self.metric`.
+    ///
+    /// The result is the same as that of `fit` on the input and with the
+    /// metric the graph was built with.
+    ///
+    /// # Panics
+    /// Panics if `self.eps` is greater than the radius of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, NeighborGraph};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let graph = NeighborGraph::new(&points, 3., &Euclidean::default());
+    /// let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit_with_graph(&graph);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    pub fn fit_with_graph(
+        &mut self,
+        graph: &NeighborGraph<A>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let _phase = phase!("fit", n_points = graph.n_points());
+        let neighborhoods = graph.neighborhoods(self.eps);
+        let min_samples = vec![self.min_samples; neighborhoods.len()];
+        self.core_samples = (0..neighborhoods.len())
+            .filter(|i| neighborhoods[*i].len() >= self.min_samples)
+            .collect();
+        // the neighbors are in increasing order of distance
+        self.core_distances = self
+            .core_samples
+            .iter()
+            .map(|&i| {
+                self.min_samples
+                    .checked_sub(1)
+                    .map_or_else(A::zero, |k| graph.neighbors(i)[k].1)
+            })
+            .collect();
+        self.expand(|i, j| graph.distance(i, j), &neighborhoods, &min_samples)
+    }
+
+    /// Fits DBSCAN after excluding points that are obviously noise, which
+    /// are labeled noise afterwards.
+    ///
+    /// The points are counted on a grid of cells of width `eps`. A point is
+    /// excluded if neither its cell nor any adjacent cell has enough points
+    /// around it to contain a core point, which is much cheaper than a
+    /// neighbor search when most points are isolated, as in sparse
+    /// geospatial data. The result is the same as that of `fit` if the
+    /// metric is at least the largest difference between coordinates, as the
+    /// Euclidean, Manhattan, and Chebyshev distances are.
+    ///
+    /// The grid has `3^d` adjacent cells for `d` dimensions, so it is skipped
+    /// if there are fewer points than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let (clusters, noise) =
+    ///     Dbscan::new(3., 2, Euclidean::default()).fit_with_prefilter(&points);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    pub fn fit_with_prefilter<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        let kept = match grid_prefilter(input, self.eps, self.min_samples) {
+            Some(kept) if kept.len() < input.nrows() => kept,
+            _ => return self.fit(input, None),
+        };
+
+        let (clusters, _) = self.fit(&input.select(Axis(0), &kept), None);
+        for i in &mut self.core_samples {
+            *i = kept[*i];
+        }
+        let clusters: HashMap<usize, Vec<usize>> = clusters
+            .into_iter()
+            .map(|(id, members)| (id, members.into_iter().map(|i| kept[i]).collect()))
+            .collect();
+        let mut in_cluster = vec![false; input.nrows()];
+        for &i in clusters.values().flatten() {
+            in_cluster[i] = true;
+        }
+        let noise = (0..input.nrows()).filter(|i| !in_cluster[*i]).collect();
+        (clusters, noise)
+    }
+
+    /// Fits DBSCAN unless `token` is cancelled, in which case the clustering
+    /// of the points processed so far is returned as a partial result.
+    ///
+    /// The neighborhoods of the points are searched in order, in chunks of
+    /// 4096 points, and `token` is checked between chunks. If it is
+    /// cancelled, the points whose neighborhoods have been searched are
+    /// clustered among themselves, as if the other points did not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{CancelToken, Dbscan};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+    /// let token = CancelToken::new();
+    /// let outcome = dbscan.fit_cancellable(&points, &token);
+    ///
+    /// assert!(!outcome.is_partial());
+    /// let (clusters, noise) = outcome.into_result();
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_cancellable<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        token: &CancelToken,
+    ) -> FitOutcome<(HashMap<usize, Vec<usize>>, Vec<usize>)>
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        if input.is_empty() {
+            return FitOutcome::Complete((HashMap::new(), Vec::new()));
+        }
+
+        let input = input.as_standard_layout();
+        let n = input.nrows();
+        let mut neighborhoods = Vec::new();
+        if !token.is_cancelled() {
+            (neighborhoods, _) = self.search_in_chunks(&input.view(), |_| {
+                if token.is_cancelled() {
+                    Err(())
+                } else {
+                    Ok(())
+                }
+            });
+        }
+
+        let processed = neighborhoods.len();
+        if processed == n {
+            return FitOutcome::Complete(self.cluster(
+                &input.view(),
+                &neighborhoods,
+                &vec![self.min_samples; n],
+            ));
+        }
+        for neighbors in &mut neighborhoods {
+            neighbors.retain(|j| *j < processed);
+        }
+        let result = self.cluster(
+            &input.view(),
+            &neighborhoods,
+            &vec![self.min_samples; processed],
+        );
+        FitOutcome::Partial { result, processed }
+    }
+
+    /// Fits DBSCAN unless it exceeds `limits`, in which case it is aborted
+    /// with a [`LimitExceeded`] error.
+    ///
+    /// The limits are checked before and after building the ball tree, after
+    /// each chunk of 4096 points in the neighbor search, and before and after
+    /// merging the neighborhoods of each chunk of 4096 core points in the
+    /// expansion. The estimated memory covers the input, the ball tree, the
+    /// neighborhoods found, and the union-find of the expansion.
+    ///
+    /// # Errors
+    /// Returns [`LimitExceeded`] if the estimated memory or the time elapsed
+    /// exceeds `limits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Limit, Limits};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+    ///
+    /// let limits = Limits::new(Some(1 << 20), Some(Duration::from_secs(10)));
+    /// let (clusters, _) = dbscan.fit_with_limits(&points, &limits).unwrap();
+    /// assert_eq!(clusters.len(), 2);
+    ///
+    /// let error = dbscan
+    ///     .fit_with_limits(&points, &Limits::new(Some(16), None))
+    ///     .unwrap_err();
+    /// assert_eq!(error.limit, Limit::Memory(16));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_with_limits<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        limits: &Limits,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>), LimitExceeded>
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        let guard = limits.start();
+        if input.is_empty() {
+            return Ok((HashMap::new(), Vec::new()));
+        }
+
+        let input = input.as_standard_layout();
+        let n = input.nrows();
+        // the input and the indices of the ball tree
+        let mut memory = input.len() * size_of::<A>() + 2 * n * size_of::<usize>();
+        guard.check("indexing", 0, memory)?;
+        let mut counted = 0;
+        let (neighborhoods, result) = self.search_in_chunks(&input.view(), |neighborhoods| {
+            memory += neighborhoods[counted..]
+                .iter()
+                .map(|n| size_of::<Vec<usize>>() + n.len() * size_of::<usize>())
+                .sum::<usize>();
+            counted = neighborhoods.len();
+            guard.check("neighbor search", counted, memory)
+        });
+        result?;
+
+        let min_samples = vec![self.min_samples; n];
+        let distance = |i: usize, j: usize| self.metric.distance(&input.row(i), &input.row(j));
+        (self.core_samples, self.core_distances) =
+            core_samples(&neighborhoods, &min_samples, distance);
+        // the union-find, and the cluster of each point and of each border
+        // point
+        memory += n * (3 * size_of::<usize>() + size_of::<bool>());
+        self.expand_checked(distance, &neighborhoods, &min_samples, |processed| {
+            guard.check("expansion", processed, memory)
+        })
+    }
+
+    /// Fits DBSCAN and reports the duration of each phase, the number of
+    /// core points, the distribution of the sizes of the neighborhoods, the
+    /// estimated peak memory, and the number of distance evaluations.
+    ///
+    /// The clusters are the same as those of `fit`, but `self.deduplicate`
+    /// is ignored. The phases are timed with the system clock, which is not
+    /// available on every target, such as `wasm32-unknown-unknown`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+    /// let ((clusters, noise), report) = dbscan.fit_with_report(&points);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(report.n_core_points, 5);
+    /// assert_eq!(report.neighbors.max, 3);
+    /// assert!(report.distance_evaluations > 0);
+    /// println!("{report}");
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_with_report<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+    ) -> ((HashMap<usize, Vec<usize>>, Vec<usize>), FitReport)
+    where
+        S: Data<Elem = A>,
+    {
+        self.clear_core_samples();
+        if input.is_empty() {
+            return ((HashMap::new(), Vec::new()), FitReport::default());
+        }
+
+        let input = input.as_standard_layout();
+        let n = input.nrows();
+        let metric = CountingMetric::new(self.metric.clone());
+        let mut phases = Vec::with_capacity(3);
+
+        let start = Instant::now();
+        let db = FiniteBallTree::new(&input.view(), metric.clone());
+        phases.push(("indexing", start.elapsed()));
+
+        let start = Instant::now();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let neighborhoods: Vec<Vec<usize>> = rows
+            .into_par_iter()
+            .map(|p| db.query_radius(&p, self.eps))
+            .collect();
+        phases.push(("neighbor search", start.elapsed()));
+
+        let start = Instant::now();
+        let min_samples = vec![self.min_samples; n];
+        let distance = |i: usize, j: usize| metric.distance(&input.row(i), &input.row(j));
+        (self.core_samples, self.core_distances) =
+            core_samples(&neighborhoods, &min_samples, distance);
+        let result = apply_border_policy(
+            cluster_neighborhoods(&neighborhoods, &min_samples),
+            &neighborhoods,
+            &min_samples,
+            self.border_policy,
+            distance,
+        );
+        phases.push(("expansion", start.elapsed()));
+
+        let sizes: Vec<usize> = neighborhoods.iter().map(Vec::len).collect();
+        // the input, the indices of the ball tree, and the neighborhoods, as
+        // estimated by `fit_with_limits`
+        let memory = input.len() * size_of::<A>()
+            + 2 * n * size_of::<usize>()
+            + sizes
+                .iter()
+                .map(|len| size_of::<Vec<usize>>() + len * size_of::<usize>())
+                .sum::<usize>();
+        let report = FitReport {
+            phases,
+            n_points: n,
+            n_core_points: self.core_samples.len(),
+            neighbors: SizeDistribution::new(sizes),
+            memory,
+            distance_evaluations: metric.count(),
+        };
+        (result, report)
+    }
+
+    /// Fits DBSCAN to points read one at a time from `points`, e.g. rows
+    /// parsed from a file or received from a network source, without
+    /// collecting them into one array first.
+    ///
+    /// The points are indexed in chunks of 4096 as they arrive, and the
+    /// indexes of chunks are merged as they accumulate, so that each point
+    /// is searched in a logarithmic number of them. Only the number of
+    /// neighbors of each point is kept while reading; the neighborhoods,
+    /// which take most of the memory of `fit` with a large `eps`, are
+    /// searched again in chunks at the end. The points themselves stay in
+    /// memory in the indexes.
+    ///
+    /// The clusters are the same as those of `fit` on the points in the same
+    /// order, with the members of each cluster in increasing order.
+    /// `self.deduplicate` is ignored.
+    ///
+    /// # Panics
+    /// Panics if the points do not all have the same number of dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::arr1;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let rows = "1 2\n2 2\n2 2.3\n8 7\n8 8\n25 80";
+    /// let points = rows
+    ///     .lines()
+    ///     .map(|line| arr1(&line.split(' ').map(|v| v.parse().unwrap()).collect::<Vec<f64>>()));
+    /// let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit_stream(points);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2]);
+    /// assert_eq!(clusters[&1], [3, 4]);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    pub fn fit_stream(
+        &mut self,
+        points: impl IntoIterator<Item = Array1<A>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        A: Send + 'static,
+    {
+        self.clear_core_samples();
+        self.fit_stream_in_chunks(points.into_iter(), CHUNK_SIZE)
+    }
+
+    /// Fits DBSCAN as [`Dbscan::fit_stream`] does, reading and searching the
+    /// points in chunks of `chunk_size`.
+    fn fit_stream_in_chunks(
+        &self,
+        points: impl Iterator<Item = Array1<A>>,
+        chunk_size: usize,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        A: Send + 'static,
+    {
+        let (segments, n_neighbors, n_features) = self.index_stream(points, chunk_size);
+        if n_features == Some(0) {
+            return (HashMap::new(), Vec::new());
+        }
+        self.cluster_stream(&segments, &n_neighbors, chunk_size)
+    }
+
+    /// Indexes the points of [`Dbscan::fit_stream`], returning the indexes,
+    /// the number of neighbors of each point and the number of dimensions,
+    /// if there are points.
+    fn index_stream(
+        &self,
+        mut points: impl Iterator<Item = Array1<A>>,
+        chunk_size: usize,
+    ) -> (Vec<Segment<A, M>>, Vec<usize>, Option<usize>)
+    where
+        A: Send + 'static,
+    {
+        let mut segments: Vec<Segment<A, M>> = Vec::new();
+        let mut n_neighbors: Vec<usize> = Vec::new();
+        let mut n_features = None;
+        loop {
+            let start = n_neighbors.len();
+            let mut data = Vec::new();
+            let mut ids = Vec::new();
+            for point in points.by_ref().take(chunk_size) {
+                let dim = *n_features.get_or_insert(point.len());
+                assert_eq!(
+                    point.len(),
+                    dim,
+                    "point {} has a different number of dimensions",
+                    n_neighbors.len()
+                );
+                // points with a non-finite coordinate have no neighbors
+                if point.iter().all(|v| v.is_finite()) {
+                    ids.push(n_neighbors.len());
+                    data.extend(point.iter().copied());
+                }
+                n_neighbors.push(0);
+            }
+            if n_neighbors.len() == start {
+                break;
+            }
+            let dim = n_features.unwrap_or_default();
+            let Some(segment) = Segment::new(data, dim, ids, self.metric.clone()) else {
                 continue;
+            };
+
+            let rows: Vec<_> = segment.rows().collect();
+            let found: Vec<(usize, Vec<usize>)> = rows
+                .par_iter()
+                .map(|(p, _)| {
+                    let older = segments
+                        .iter()
+                        .flat_map(|s| s.query_radius(p, self.eps))
+                        .collect();
+                    (segment.tree.query_radius(p, self.eps).len(), older)
+                })
+                .collect();
+            for (&i, (own, older)) in segment.ids.iter().zip(found) {
+                n_neighbors[i] = own + older.len();
+                for j in older {
+                    n_neighbors[j] += 1;
+                }
             }
 
-            let cid = clusters.len();
+            segments.push(segment);
+            while let [.., a, b] = segments.as_slice() {
+                if a.level != b.level {
+                    break;
+                }
+                let b = segments.pop().expect("two segments");
+                let a = segments.pop().expect("two segments");
+                segments.push(a.merge(b, self.metric.clone()));
+            }
+        }
+        (segments, n_neighbors, n_features)
+    }
 
-            let mut cluster = Vec::new();
-            expand_cluster(
-                &mut cluster,
-                &mut visited,
-                idx,
-                self.min_samples,
-                &neighborhoods,
-            );
-            if cluster.len() >= self.min_samples {
-                clusters.insert(cid, cluster);
+    /// Clusters the points of [`Dbscan::fit_stream`] given their indexes and
+    /// their numbers of neighbors, searching the neighborhoods in chunks.
+    fn cluster_stream(
+        &self,
+        segments: &[Segment<A, M>],
+        n_neighbors: &[usize],
+        chunk_size: usize,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        A: Send + 'static,
+    {
+        let n = n_neighbors.len();
+        let is_core: Vec<bool> = n_neighbors.iter().map(|c| *c >= self.min_samples).collect();
+        let mut components = TreeUnionFind::new(n);
+        // the core points within `eps` of each border point, with distances
+        let mut border_neighbors: HashMap<usize, Vec<(usize, A)>> = HashMap::new();
+        for segment in segments {
+            let rows: Vec<_> = segment.rows().collect();
+            for chunk in rows.chunks(chunk_size) {
+                let found: Vec<Vec<(usize, A)>> = chunk
+                    .par_iter()
+                    .map(|(p, i)| {
+                        let with_distances =
+                            !is_core[*i] && self.border_policy == BorderPolicy::NearestCore;
+                        segments
+                            .iter()
+                            .flat_map(|s| {
+                                s.tree
+                                    .query_radius(p, self.eps)
+                                    .into_iter()
+                                    .filter(|&k| is_core[s.ids[k]])
+                                    .map(|k| {
+                                        let distance = if with_distances {
+                                            self.metric.distance(p, &s.tree.points.row(k))
+                                        } else {
+                                            A::zero()
+                                        };
+                                        (s.ids[k], distance)
+                                    })
+                            })
+                            .collect()
+                    })
+                    .collect();
+                for ((_, i), neighbors) in chunk.iter().zip(found) {
+                    if is_core[*i] {
+                        for (j, _) in neighbors {
+                            components.union(*i, j);
+                        }
+                    } else if !neighbors.is_empty() {
+                        border_neighbors.insert(*i, neighbors);
+                    }
+                }
             }
         }
 
-        let in_cluster: HashSet<usize> = clusters.values().flatten().copied().collect();
-        let noise = (0..input.nrows())
-            .filter(|x| !in_cluster.contains(x))
+        // clusters are expanded in the order of their first core point, and a
+        // border point joins the first cluster that reaches it, as in `fit`
+        let mut labels: Vec<Option<usize>> = vec![None; n];
+        let mut first_ids = HashMap::new();
+        for i in (0..n).filter(|i| is_core[*i]) {
+            let n_clusters = first_ids.len();
+            labels[i] = Some(*first_ids.entry(components.find(i)).or_insert(n_clusters));
+        }
+        for (&i, neighbors) in &border_neighbors {
+            labels[i] = neighbors.iter().filter_map(|(j, _)| labels[*j]).min();
+        }
+        let mut sizes = vec![0; first_ids.len()];
+        for id in labels.iter().flatten() {
+            sizes[*id] += 1;
+        }
+        let mut n_kept = 0;
+        let kept: Vec<Option<usize>> = sizes
+            .into_iter()
+            .map(|size| {
+                (size >= self.min_samples).then(|| {
+                    n_kept += 1;
+                    n_kept - 1
+                })
+            })
             .collect();
+        for label in &mut labels {
+            *label = label.and_then(|id| kept[id]);
+        }
+        for (&i, neighbors) in &border_neighbors {
+            if labels[i].is_none() {
+                continue;
+            }
+            labels[i] = match self.border_policy {
+                BorderPolicy::FirstCore => labels[i],
+                BorderPolicy::NearestCore => neighbors
+                    .iter()
+                    .filter(|(j, _)| labels[*j].is_some())
+                    .map(|(j, distance)| (*distance, *j))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                    .map_or(labels[i], |(_, j)| labels[j]),
+                BorderPolicy::Noise => None,
+            };
+        }
 
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = Vec::new();
+        for (i, label) in labels.into_iter().enumerate() {
+            match label {
+                Some(id) => clusters.entry(id).or_default().push(i),
+                None => noise.push(i),
+            }
+        }
         (clusters, noise)
     }
+
+    /// Fits DBSCAN and checks whether the result is [degenerate](Degeneracy),
+    /// handling it as `policy` says.
+    ///
+    /// Returns the clusters and noise, with the degeneracy of the returned
+    /// result, if any, as a warning. With [`DegeneracyPolicy::RelaxEps`],
+    /// `self.eps` is left at the value of the second fit, if there is one.
+    ///
+    /// # Errors
+    /// Returns the [`Degeneracy`] if the result is degenerate and `policy` is
+    /// [`DegeneracyPolicy::Reject`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Degeneracy, DegeneracyPolicy};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(0.25, 2, Euclidean::default());
+    /// let error = dbscan.fit_with_policy(&points, DegeneracyPolicy::Reject).unwrap_err();
+    /// assert_eq!(error, Degeneracy::AllNoise { n_points: 6 });
+    ///
+    /// let ((clusters, _), warning) = dbscan
+    ///     .fit_with_policy(&points, DegeneracyPolicy::RelaxEps(8.))
+    ///     .unwrap();
+    /// assert_eq!(dbscan.eps, 2.);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(warning, None);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_with_policy<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        policy: DegeneracyPolicy<A>,
+    ) -> Result<((HashMap<usize, Vec<usize>>, Vec<usize>), Option<Degeneracy>), Degeneracy>
+    where
+        S: Data<Elem = A>,
+    {
+        let n = input.nrows();
+        let (clusters, noise) = self.fit(input, None);
+        let Some(degeneracy) = Degeneracy::detect(n, self.min_samples, &clusters, &noise) else {
+            return Ok(((clusters, noise), None));
+        };
+        match (policy, degeneracy) {
+            (DegeneracyPolicy::Accept, _)
+            | (DegeneracyPolicy::RelaxEps(_), Degeneracy::TooFewPoints { .. }) => {
+                Ok(((clusters, noise), Some(degeneracy)))
+            }
+            (DegeneracyPolicy::Reject, _) => Err(degeneracy),
+            (DegeneracyPolicy::RelaxEps(factor), _) => {
+                if let Degeneracy::AllNoise { .. } = degeneracy {
+                    self.eps = self.eps * factor;
+                } else {
+                    self.eps /= factor;
+                }
+                let (clusters, noise) = self.fit(input, None);
+                let degeneracy = Degeneracy::detect(n, self.min_samples, &clusters, &noise);
+                Ok(((clusters, noise), degeneracy))
+            }
+        }
+    }
+
+    /// Searches the neighborhoods of the points within `self.eps` once, so
+    /// that the clusterings at any radius up to `self.eps` can be extracted
+    /// from the returned [`DbscanSweep`] without fitting again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let sweep = Dbscan::new(10., 2, Euclidean::default()).sweep(&points);
+    /// let clusterings = sweep.extract_all(&[0.2, 3., 10.]);
+    ///
+    /// assert_eq!(clusterings[0].1.len(), 6); // all noise
+    /// assert_eq!(clusterings[1].0.len(), 2);
+    /// assert_eq!(clusterings[2].0.len(), 1);
+    /// ```
+    pub fn sweep<S>(&self, input: &ArrayBase<S, Ix2>) -> DbscanSweep<A>
+    where
+        A: Send,
+        S: Data<Elem = A>,
+    {
+        let neighborhoods = if input.is_empty() {
+            Vec::new()
+        } else {
+            let input = input.as_standard_layout();
+            let rows: Vec<_> = input.rows().into_iter().collect();
+            let db = FiniteBallTree::new(&input.view(), self.metric.clone());
+            rows.par_iter()
+                .map(|p| {
+                    db.query_radius(p, self.eps)
+                        .into_iter()
+                        .map(|j| (j, self.metric.distance(p, &rows[j])))
+                        .collect()
+                })
+                .collect()
+        };
+        DbscanSweep {
+            min_samples: self.min_samples,
+            border_policy: self.border_policy,
+            max_eps: self.eps,
+            neighborhoods,
+        }
+    }
+
+    /// Searches the neighborhoods of the points in order, in chunks of
+    /// [`CHUNK_SIZE`] points. `check` is called with the neighborhoods found
+    /// so far after building the ball tree and after each chunk, and the
+    /// search stops if it returns an error.
+    fn search_in_chunks<E>(
+        &self,
+        input: &ArrayView2<A>,
+        mut check: impl FnMut(&[Vec<usize>]) -> Result<(), E>,
+    ) -> (Vec<Vec<usize>>, Result<(), E>) {
+        let mut neighborhoods: Vec<Vec<usize>> = Vec::with_capacity(input.nrows());
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let db = FiniteBallTree::new(input, self.metric.clone());
+        let mut result = check(&neighborhoods);
+        for chunk in rows.chunks(CHUNK_SIZE) {
+            if result.is_err() {
+                break;
+            }
+            neighborhoods.par_extend(chunk.par_iter().map(|p| db.query_radius(p, self.eps)));
+            result = check(&neighborhoods);
+        }
+        (neighborhoods, result)
+    }
+
+    /// Assigns the noise points of a clustering of `input` by this model to
+    /// the cluster of their nearest core point, if it is within
+    /// `max_distance`, for applications that need every point labeled.
+    ///
+    /// Core points are found again with `self.eps` and `self.min_samples`,
+    /// so the clustering should be the result of `fit` on the same input.
+    /// Noise points farther than `max_distance` from every core point, or
+    /// with a non-finite coordinate, stay noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [11., 8.], [25., 80.]];
+    /// let mut dbscan = Dbscan::new(1.5, 2, Euclidean::default());
+    /// let clustering = dbscan.fit(&points, None);
+    /// assert_eq!(clustering.1, [5, 6]);
+    ///
+    /// let (clusters, noise) = dbscan.assign_noise_to_nearest(&points, clustering, 5.);
+    /// assert_eq!(clusters[&1], [3, 4, 5]);
+    /// assert_eq!(noise, [6]);
+    /// ```
+    pub fn assign_noise_to_nearest<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+        max_distance: A,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        let input = input.as_standard_layout();
+        let db = FiniteBallTree::new(&input.view(), self.metric.clone());
+        let members: Vec<_> = clustering
+            .0
+            .iter()
+            .flat_map(|(id, members)| members.iter().map(|i| (*i, *id)))
+            .collect();
+        let cores: Vec<_> = members
+            .into_par_iter()
+            .filter(|(i, _)| db.query_radius(&input.row(*i), self.eps).len() >= self.min_samples)
+            .collect();
+        assign_noise(
+            &input.view(),
+            clustering,
+            &cores,
+            &self.metric,
+            max_distance,
+        )
+    }
+
+    /// Clusters the distinct points of an input, each counting as many
+    /// points as its weight, and maps the clusters back to the input.
+    fn fit_deduplicated(
+        &self,
+        deduplicated: &Deduplicated<A>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let points = &deduplicated.points;
+        let weights = Some(deduplicated.weights.as_slice());
+        let neighborhoods = build_neighborhoods(points, self.eps, self.metric.clone());
+        let min_samples = vec![self.min_samples; points.nrows()];
+        deduplicated.expand(apply_weighted_border_policy(
+            cluster_weighted(&neighborhoods, weights, &min_samples),
+            &neighborhoods,
+            weights,
+            &min_samples,
+            self.border_policy,
+            |i, j| self.metric.distance(&points.row(i), &points.row(j)),
+        ))
+    }
+
+    /// Groups points into clusters given their neighborhoods, and assigns
+    /// border points according to `self.border_policy`.
+    fn cluster(
+        &mut self,
+        input: &ArrayView2<A>,
+        neighborhoods: &[Vec<usize>],
+        min_samples: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let distance = |i: usize, j: usize| self.metric.distance(&input.row(i), &input.row(j));
+        (self.core_samples, self.core_distances) =
+            core_samples(neighborhoods, min_samples, distance);
+        self.expand(distance, neighborhoods, min_samples)
+    }
+
+    /// Groups points into clusters as [`Dbscan::cluster`] does, where
+    /// `distance(i, j)` is the distance between points `i` and `j`.
+    fn expand(
+        &self,
+        distance: impl Fn(usize, usize) -> A,
+        neighborhoods: &[Vec<usize>],
+        min_samples: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        unchecked(self.expand_checked(distance, neighborhoods, min_samples, |_| Ok(())))
+    }
+
+    /// Groups points into clusters as [`Dbscan::expand`] does, calling
+    /// `check` as [`cluster_weighted_checked`] does.
+    #[allow(clippy::type_complexity)]
+    fn expand_checked<E>(
+        &self,
+        distance: impl Fn(usize, usize) -> A,
+        neighborhoods: &[Vec<usize>],
+        min_samples: &[usize],
+        check: impl FnMut(usize) -> Result<(), E>,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>), E> {
+        let _phase = phase!("expansion", n_points = neighborhoods.len());
+        let (clusters, noise) = apply_border_policy(
+            cluster_weighted_checked(neighborhoods, None, min_samples, check)?,
+            neighborhoods,
+            min_samples,
+            self.border_policy,
+            distance,
+        );
+        counts!(n_clusters = clusters.len(), n_noise = noise.len());
+        Ok((clusters, noise))
+    }
+
+    fn clear_core_samples(&mut self) {
+        self.core_samples.clear();
+        self.core_distances.clear();
+    }
+}
+
+impl<A: FloatCore + Sync> DbscanSweep<A> {
+    /// The largest radius at which clusters can be extracted, which is the
+    /// `eps` of the DBSCAN the sweep was created from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [8., 7.]];
+    /// let sweep = Dbscan::new(10., 2, Euclidean::default()).sweep(&points);
+    ///
+    /// assert_eq!(sweep.max_eps(), 10.);
+    /// ```
+    pub fn max_eps(&self) -> A {
+        self.max_eps
+    }
+
+    /// Returns the clusters and noise that DBSCAN finds with radius `eps`.
+    ///
+    /// # Panics
+    /// Panics if `eps` is greater than [`max_eps`](Self::max_eps).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let sweep = Dbscan::new(10., 2, Euclidean::default()).sweep(&points);
+    ///
+    /// let (clusters, noise) = sweep.extract(3.);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// assert_eq!(sweep.extract(0.2).1.len(), 6); // all noise
+    /// ```
+    #[must_use]
+    pub fn extract(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            eps <= self.max_eps,
+            "eps cannot be greater than the radius of the sweep"
+        );
+        let neighborhoods: Vec<Vec<usize>> = self
+            .neighborhoods
+            .iter()
+            .map(|neighbors| {
+                neighbors
+                    .iter()
+                    .filter(|(_, d)| *d <= eps)
+                    .map(|(j, _)| *j)
+                    .collect()
+            })
+            .collect();
+        let min_samples = vec![self.min_samples; neighborhoods.len()];
+        apply_border_policy(
+            cluster_neighborhoods(&neighborhoods, &min_samples),
+            &neighborhoods,
+            &min_samples,
+            self.border_policy,
+            |i, j| {
+                self.neighborhoods[i]
+                    .iter()
+                    .find(|(k, _)| *k == j)
+                    .map_or_else(A::infinity, |(_, d)| *d)
+            },
+        )
+    }
+
+    /// Returns the clusters and noise for each radius in `eps`, extracted in
+    /// parallel.
+    ///
+    /// # Panics
+    /// Panics if a radius is greater than [`max_eps`](Self::max_eps).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let sweep = Dbscan::new(10., 2, Euclidean::default()).sweep(&points);
+    /// let clusterings = sweep.extract_all(&[3., 10.]);
+    ///
+    /// assert_eq!(clusterings, [sweep.extract(3.), sweep.extract(10.)]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    pub fn extract_all(&self, eps: &[A]) -> Vec<(HashMap<usize, Vec<usize>>, Vec<usize>)> {
+        eps.par_iter().map(|eps| self.extract(*eps)).collect()
+    }
+}
+
+/// Returns the points that may be within `eps` of a core point, or `None` if
+/// the grid would be too large.
+fn grid_prefilter<S, A>(input: &ArrayBase<S, Ix2>, eps: A, min_samples: usize) -> Option<Vec<usize>>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    // the grid is not worth it if there are more adjacent cells than points
+    u32::try_from(input.ncols())
+        .ok()
+        .and_then(|d| 3_usize.checked_pow(d))
+        .filter(|n_offsets| *n_offsets <= input.nrows())?;
+    if eps <= A::zero() || !eps.is_finite() {
+        return None;
+    }
+
+    let cells: Vec<Vec<i64>> = input
+        .rows()
+        .into_iter()
+        .map(|p| cell_of(p.iter().copied(), eps))
+        .collect::<Option<_>>()?;
+    let mut counts: HashMap<&[i64], usize> = HashMap::new();
+    for cell in &cells {
+        *counts.entry(cell).or_default() += 1;
+    }
+
+    // a cell may contain a core point only if it has enough points around it
+    let offsets = neighbor_offsets(input.ncols());
+    let dense: HashSet<&[i64]> = counts
+        .keys()
+        .copied()
+        .filter(|cell| {
+            let around: usize = adjacent(cell, &offsets)
+                .filter_map(|a| counts.get(a.as_slice()))
+                .sum();
+            around >= min_samples
+        })
+        .collect();
+
+    let near_dense: HashSet<&[i64]> = counts
+        .keys()
+        .copied()
+        .filter(|cell| adjacent(cell, &offsets).any(|a| dense.contains(a.as_slice())))
+        .collect();
+    Some(
+        cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| near_dense.contains(cell.as_slice()))
+            .map(|(i, _)| i)
+            .collect(),
+    )
+}
+
+/// Groups points into clusters given their neighborhoods, where point `i` is
+/// a core point if it has at least `min_samples[i]` neighbors.
+pub(crate) fn cluster_neighborhoods(
+    neighborhoods: &[Vec<usize>],
+    min_samples: &[usize],
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    cluster_weighted(neighborhoods, None, min_samples)
+}
+
+/// Groups points into clusters as [`cluster_neighborhoods`] does, counting
+/// each point as many times as its weight, if any.
+///
+/// The clusters are the connected components of the graph of the core
+/// points, found by a union-find in parallel. A border point belongs to the
+/// cluster with the smallest first core point among those of the core
+/// points that have it as neighbor, and clusters are numbered in the order
+/// of their first core point, so the result is the same as that of a
+/// sequential expansion from each core point in turn.
+fn cluster_weighted(
+    neighborhoods: &[Vec<usize>],
+    weights: Option<&[usize]>,
+    min_samples: &[usize],
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    unchecked(cluster_weighted_checked(
+        neighborhoods,
+        weights,
+        min_samples,
+        |_| Ok(()),
+    ))
+}
+
+/// Groups points into clusters as [`cluster_weighted`] does, calling `check`
+/// with the number of core points whose edges have been merged before the
+/// merging and after each chunk of [`CHUNK_SIZE`] core points, and stopping
+/// if it returns an error.
+#[allow(clippy::type_complexity)]
+fn cluster_weighted_checked<E>(
+    neighborhoods: &[Vec<usize>],
+    weights: Option<&[usize]>,
+    min_samples: &[usize],
+    mut check: impl FnMut(usize) -> Result<(), E>,
+) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>), E> {
+    let n = neighborhoods.len();
+    let is_core: Vec<bool> = (0..n)
+        .into_par_iter()
+        .map(|i| weight(&neighborhoods[i], weights) >= min_samples[i])
+        .collect();
+
+    let components = ConcurrentUnionFind::new(n);
+    let cores: Vec<usize> = (0..n).filter(|i| is_core[*i]).collect();
+    check(0)?;
+    for (k, chunk) in cores.chunks(CHUNK_SIZE).enumerate() {
+        #[allow(clippy::needless_for_each)] // a parallel loop with `parallel`
+        chunk.par_iter().for_each(|&i| {
+            for &j in &neighborhoods[i] {
+                if is_core[j] {
+                    components.union(i, j);
+                }
+            }
+        });
+        check(k * CHUNK_SIZE + chunk.len())?;
+    }
+
+    // the first core point of the cluster of each point, if any
+    let mut roots: Vec<usize> = (0..n)
+        .into_par_iter()
+        .map(|i| {
+            if is_core[i] {
+                components.find(i)
+            } else {
+                usize::MAX
+            }
+        })
+        .collect();
+    let border_roots: Vec<AtomicUsize> = (0..n).map(|_| AtomicUsize::new(usize::MAX)).collect();
+    (0..n)
+        .into_par_iter()
+        .filter(|i| is_core[*i])
+        .for_each(|i| {
+            for &j in &neighborhoods[i] {
+                if !is_core[j] {
+                    border_roots[j].fetch_min(roots[i], atomic::Ordering::Relaxed);
+                }
+            }
+        });
+    for (root, border_root) in roots.iter_mut().zip(border_roots) {
+        if *root == usize::MAX {
+            *root = border_root.into_inner();
+        }
+    }
+
+    let mut members = vec![Vec::new(); n];
+    for (i, root) in roots.into_iter().enumerate() {
+        if root != usize::MAX {
+            members[root].push(i);
+        }
+    }
+    let mut clusters = HashMap::new();
+    for (root, cluster) in members.into_iter().enumerate() {
+        if !cluster.is_empty() && weight(&cluster, weights) >= min_samples[root] {
+            clusters.insert(clusters.len(), cluster);
+        }
+    }
+
+    let in_cluster: HashSet<usize> = clusters.values().flatten().copied().collect();
+    let noise = (0..n).filter(|x| !in_cluster.contains(x)).collect();
+
+    Ok((clusters, noise))
+}
+
+/// Groups the points density-connected to `seeds` into clusters given their
+/// neighborhoods, as [`cluster_neighborhoods`] does from every core point.
+fn cluster_from_seeds(
+    neighborhoods: &[Vec<usize>],
+    min_samples: &[usize],
+    seeds: &[usize],
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    let is_core: Vec<bool> = neighborhoods
+        .iter()
+        .zip(min_samples)
+        .map(|(neighbors, min_samples)| neighbors.len() >= *min_samples)
+        .collect();
+
+    let mut visited = vec![false; neighborhoods.len()];
+    let mut clusters = HashMap::new();
+    for &seed in seeds {
+        if visited[seed] {
+            continue;
+        }
+        let starts: Vec<usize> = if is_core[seed] {
+            vec![seed]
+        } else {
+            neighborhoods[seed]
+                .iter()
+                .copied()
+                .filter(|&i| is_core[i] && !visited[i])
+                .collect()
+        };
+        let Some(&first) = starts.first() else {
+            continue;
+        };
+
+        let mut cluster = Vec::new();
+        for start in starts {
+            expand_cluster(&mut cluster, &mut visited, start, &is_core, neighborhoods);
+        }
+        if cluster.len() >= min_samples[first] {
+            cluster.sort_unstable();
+            clusters.insert(clusters.len(), cluster);
+        }
+    }
+
+    let mut in_cluster = vec![false; neighborhoods.len()];
+    for &i in clusters.values().flatten() {
+        in_cluster[i] = true;
+    }
+    let noise = (0..neighborhoods.len())
+        .filter(|i| !in_cluster[*i])
+        .collect();
+    (clusters, noise)
+}
+
+/// Groups points into clusters given their neighborhoods, keeping the points
+/// of each must-link component together and the points of components that
+/// cannot be linked apart.
+fn cluster_constrained(
+    neighborhoods: &[Vec<usize>],
+    min_samples: usize,
+    components: &ConstraintComponents,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    let n = neighborhoods.len();
+    let is_core = |i: usize| neighborhoods[i].len() >= min_samples;
+    let mut labels: Vec<Option<usize>> = vec![None; components.members.len()];
+    let mut clusters = HashMap::new();
+    let mut next_id = 0;
+    for idx in 0..n {
+        if labels[components.component[idx]].is_some() || !is_core(idx) {
+            continue;
+        }
+
+        let mut cluster = Vec::new();
+        let mut to_visit = vec![idx];
+        while let Some(cur) = to_visit.pop() {
+            let component = components.component[cur];
+            if labels[component].is_some()
+                || components.cannot_link[component]
+                    .iter()
+                    .any(|c| labels[*c] == Some(next_id))
+            {
+                continue;
+            }
+            labels[component] = Some(next_id);
+            for &member in &components.members[component] {
+                cluster.push(member);
+                if is_core(member) {
+                    to_visit.extend(neighborhoods[member].iter().copied());
+                }
+            }
+        }
+        // the points of a cluster too small to keep stay labeled, as noise
+        if cluster.len() >= min_samples {
+            cluster.sort_unstable();
+            clusters.insert(clusters.len(), cluster);
+        }
+        next_id += 1;
+    }
+
+    let mut in_cluster = vec![false; n];
+    for &i in clusters.values().flatten() {
+        in_cluster[i] = true;
+    }
+    let noise = (0..n).filter(|i| !in_cluster[*i]).collect();
+    (clusters, noise)
+}
+
+/// Returns the core points, those with at least `min_samples[i]` neighbors,
+/// and the distance from each to its `min_samples[i]`-th nearest neighbor,
+/// counting itself, where `distance(i, j)` is the distance between points
+/// `i` and `j`.
+fn core_samples<A: FloatCore>(
+    neighborhoods: &[Vec<usize>],
+    min_samples: &[usize],
+    distance: impl Fn(usize, usize) -> A,
+) -> (Vec<usize>, Vec<A>) {
+    let core_samples: Vec<_> = (0..neighborhoods.len())
+        .filter(|i| neighborhoods[*i].len() >= min_samples[*i])
+        .collect();
+    let core_distances = core_samples
+        .iter()
+        .map(|&i| {
+            let Some(k) = min_samples[i].checked_sub(1) else {
+                return A::zero();
+            };
+            let mut distances: Vec<A> = neighborhoods[i]
+                .iter()
+                .map(|&j| if i == j { A::zero() } else { distance(i, j) })
+                .collect();
+            let (_, d, _) = distances.select_nth_unstable_by(k, |a, b| {
+                a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            *d
+        })
+        .collect();
+    (core_samples, core_distances)
+}
+
+/// Reassigns the border points of a clustering found by
+/// [`cluster_neighborhoods`] according to `policy`, where `distance(i, j)`
+/// is the distance between points `i` and `j`.
+pub(crate) fn apply_border_policy<A: FloatCore>(
+    clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+    neighborhoods: &[Vec<usize>],
+    min_samples: &[usize],
+    policy: BorderPolicy,
+    distance: impl Fn(usize, usize) -> A,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    apply_weighted_border_policy(
+        clustering,
+        neighborhoods,
+        None,
+        min_samples,
+        policy,
+        distance,
+    )
+}
+
+/// Reassigns the border points as [`apply_border_policy`] does, counting
+/// each point as many times as its weight, if any.
+fn apply_weighted_border_policy<A: FloatCore>(
+    (clusters, noise): (HashMap<usize, Vec<usize>>, Vec<usize>),
+    neighborhoods: &[Vec<usize>],
+    weights: Option<&[usize]>,
+    min_samples: &[usize],
+    policy: BorderPolicy,
+    distance: impl Fn(usize, usize) -> A,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    if policy == BorderPolicy::FirstCore {
+        return (clusters, noise);
+    }
+
+    let mut labels = vec![None; neighborhoods.len()];
+    for (id, members) in &clusters {
+        for &i in members {
+            labels[i] = Some(*id);
+        }
+    }
+    let is_core = |i: usize| weight(&neighborhoods[i], weights) >= min_samples[i];
+    let core_labels = labels.clone();
+    for (i, label) in labels.iter_mut().enumerate() {
+        if label.is_none() || is_core(i) {
+            continue;
+        }
+        *label = match policy {
+            BorderPolicy::FirstCore => *label,
+            BorderPolicy::NearestCore => neighborhoods[i]
+                .iter()
+                .filter(|j| is_core(**j) && core_labels[**j].is_some())
+                .map(|j| (distance(i, *j), *j))
+                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                .map_or(*label, |(_, j)| core_labels[j]),
+            BorderPolicy::Noise => None,
+        };
+    }
+
+    let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut noise = Vec::new();
+    for (i, label) in labels.into_iter().enumerate() {
+        match label {
+            Some(id) => clusters.entry(id).or_default().push(i),
+            None => noise.push(i),
+        }
+    }
+    (clusters, noise)
+}
+
+/// Consecutive chunks of the points of [`Dbscan::fit_stream`] with their
+/// ball tree, whose `k`-th point is point `ids[k]` of the stream.
+struct Segment<A: 'static, M> {
+    tree: BallTree<'static, A, M>,
+    ids: Vec<usize>,
+
+    /// The base-2 logarithm of the number of chunks merged into the segment.
+    level: u32,
+}
+
+impl<A, M> Segment<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + 'static,
+    M: Metric<A>,
+{
+    /// Indexes the points in `data`, row by row, or returns `None` if there
+    /// are none.
+    fn new(data: Vec<A>, dim: usize, ids: Vec<usize>, metric: M) -> Option<Self> {
+        let points = Array2::from_shape_vec((ids.len(), dim), data).expect("one row per point");
+        let tree = BallTree::new(points, metric).ok()?;
+        Some(Self {
+            tree,
+            ids,
+            level: 0,
+        })
+    }
+
+    /// Merges the chunks of `self` and `other`, which follows it.
+    fn merge(mut self, other: Self, metric: M) -> Self {
+        let points = ndarray::concatenate(
+            Axis(0),
+            &[self.tree.points.view(), other.tree.points.view()],
+        )
+        .expect("same number of dimensions");
+        self.ids.extend(other.ids);
+        Self {
+            tree: BallTree::new(points, metric).expect("nonempty"),
+            ids: self.ids,
+            level: self.level + 1,
+        }
+    }
+
+    /// Returns the points with their indices in the stream.
+    fn rows(&self) -> impl Iterator<Item = (ArrayView1<'_, A>, usize)> {
+        self.tree
+            .points
+            .rows()
+            .into_iter()
+            .zip(self.ids.iter().copied())
+    }
+
+    /// Returns the indices in the stream of the points within `eps` of
+    /// `point`.
+    fn query_radius(&self, point: &ArrayView1<A>, eps: A) -> impl Iterator<Item = usize> + '_ {
+        self.tree
+            .query_radius(point, eps)
+            .into_iter()
+            .map(|k| self.ids[k])
+    }
 }
 
 fn build_neighborhoods<S, A, M>(input: &ArrayBase<S, Ix2>, eps: A, metric: M) -> Vec<Vec<usize>>
@@ -136,17 +2021,32 @@ where
         return Vec::new();
     }
     let rows: Vec<_> = input.rows().into_iter().collect();
-    let db = BallTree::new(input, metric).expect("non-empty array");
-    rows.into_par_iter()
-        .map(|p| db.query_radius(&p, eps).into_iter().collect::<Vec<usize>>())
-        .collect()
+    let db = {
+        let _phase = phase!("index_build", n_points = rows.len());
+        FiniteBallTree::new(&input.view(), metric)
+    };
+    let _phase = phase!("neighborhoods", n_points = rows.len());
+    let neighborhoods: Vec<Vec<usize>> = rows
+        .into_par_iter()
+        .map(|p| db.query_radius(&p, eps))
+        .collect();
+    counts!(n_neighbors = neighborhoods.iter().map(Vec::len).sum::<usize>());
+    neighborhoods
+}
+
+/// Returns the number of `points`, counting each point as many times as its
+/// weight, if any.
+fn weight(points: &[usize], weights: Option<&[usize]>) -> usize {
+    weights.map_or(points.len(), |weights| {
+        points.iter().map(|&i| weights[i]).sum()
+    })
 }
 
 fn expand_cluster(
     cluster: &mut Vec<usize>,
     visited: &mut [bool],
     idx: usize,
-    min_samples: usize,
+    is_core: &[bool],
     neighborhoods: &[Vec<usize>],
 ) {
     let mut to_visit = vec![idx];
@@ -156,7 +2056,7 @@ fn expand_cluster(
         }
         visited[cur] = true;
         cluster.push(cur);
-        if neighborhoods[cur].len() >= min_samples {
+        if is_core[cur] {
             to_visit.extend(neighborhoods[cur].iter().filter(|&n| !visited[*n]));
         }
     }
@@ -164,10 +2064,161 @@ fn expand_cluster(
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use maplit::hashmap;
-    use ndarray::{array, aview2};
+    use ndarray::{array, aview2, Array2};
 
     use super::*;
+    use crate::interrupt::Limit;
+
+    #[test]
+    fn fit_from_neighborhoods() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [5.0, 5.0],
+        ];
+        let mut model = Dbscan::new(0.5, 2, Euclidean::default());
+        let expected = model.fit(&data, None);
+
+        let neighborhoods: Vec<Vec<usize>> = data
+            .rows()
+            .into_iter()
+            .map(|p| {
+                (0..data.nrows())
+                    .filter(|&j| model.metric.distance(&p, &data.row(j)) <= model.eps)
+                    .collect()
+            })
+            .collect();
+        let (mut clusters, noise) = model.fit_from_neighborhoods(&data, &neighborhoods);
+        for members in clusters.values_mut() {
+            members.sort_unstable();
+        }
+        assert_eq!((clusters, noise), expected);
+    }
+
+    #[test]
+    fn fit_with_graph() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [f64::NAN, 3.0],
+            [5.0, 5.0],
+        ];
+        let graph = NeighborGraph::new(&data, 1., &Euclidean::default());
+        for (eps, policy) in [
+            (0.5, BorderPolicy::FirstCore),
+            (0.2, BorderPolicy::NearestCore),
+            (1., BorderPolicy::Noise),
+        ] {
+            let mut dbscan = Dbscan::new(eps, 3, Euclidean::default());
+            dbscan.border_policy = policy;
+            let (mut expected, noise) = dbscan.fit(&data, None);
+            let core_samples = dbscan.core_sample_indices().to_vec();
+            let core_distances = dbscan.core_distances().to_vec();
+
+            let (mut clusters, graph_noise) = dbscan.fit_with_graph(&graph);
+            for members in clusters.values_mut().chain(expected.values_mut()) {
+                members.sort_unstable();
+            }
+            assert_eq!((clusters, graph_noise), (expected, noise));
+            assert_eq!(dbscan.core_sample_indices(), core_samples);
+            assert_eq!(dbscan.core_distances(), core_distances);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "eps cannot be greater than the radius of the graph")]
+    fn fit_with_graph_radius() {
+        let graph = NeighborGraph::new(&array![[0.], [1.]], 1., &Euclidean::default());
+        Dbscan::new(2., 2, Euclidean::default()).fit_with_graph(&graph);
+    }
+
+    #[test]
+    fn parallel_expansion() {
+        // points on a line with gaps of pseudo-random lengths
+        let mut x = 0.;
+        let mut state = 1_u64;
+        let points: Vec<f64> = (0..500)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1);
+                #[allow(clippy::cast_precision_loss)]
+                let gap = (state >> 40) as f64 / f64::from(1 << 24);
+                x += gap;
+                x
+            })
+            .collect();
+        let neighborhoods: Vec<Vec<usize>> = points
+            .iter()
+            .map(|p| {
+                (0..points.len())
+                    .filter(|&j| (points[j] - p).abs() <= 0.6)
+                    .collect()
+            })
+            .collect();
+        let min_samples: Vec<usize> = (0..points.len()).map(|i| 2 + i % 3).collect();
+
+        // a sequential expansion from each core point in turn
+        let is_core: Vec<bool> = neighborhoods
+            .iter()
+            .zip(&min_samples)
+            .map(|(neighbors, min_samples)| neighbors.len() >= *min_samples)
+            .collect();
+        let mut visited = vec![false; points.len()];
+        let mut expected = HashMap::new();
+        for i in 0..points.len() {
+            if !visited[i] && is_core[i] {
+                let mut cluster = Vec::new();
+                expand_cluster(&mut cluster, &mut visited, i, &is_core, &neighborhoods);
+                if cluster.len() >= min_samples[i] {
+                    cluster.sort_unstable();
+                    expected.insert(expected.len(), cluster);
+                }
+            }
+        }
+
+        let (clusters, _) = cluster_neighborhoods(&neighborhoods, &min_samples);
+        assert!(expected.len() > 10);
+        assert_eq!(clusters, expected);
+    }
+
+    #[test]
+    fn fit_precomputed() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [5.0, 5.0],
+        ];
+        let mut model = Dbscan::new(0.5, 3, Euclidean::default());
+        model.border_policy = BorderPolicy::NearestCore;
+        let expected = model.fit(&data, None);
+        let core_distances = model.core_distances().to_vec();
+
+        let distances = Array2::from_shape_fn((data.nrows(), data.nrows()), |(i, j)| {
+            model.metric.distance(&data.row(i), &data.row(j))
+        });
+        let (mut clusters, noise) = model.fit_precomputed(&distances);
+        for members in clusters.values_mut() {
+            members.sort_unstable();
+        }
+        assert_eq!((clusters, noise), expected);
+        assert_eq!(model.core_distances(), core_distances);
+    }
 
     #[test]
     fn default() {
@@ -205,6 +2256,427 @@ mod test {
         let (clusters, noise) = model.fit(&data, None);
         assert_eq!(clusters.len(), 5); // {0: [0], 1: [1, 2, 3], 2: [4], 3: [5], 4: [6]}
         assert!(noise.is_empty());
+        assert_eq!(model.core_sample_indices(), [0, 1, 2, 3, 4, 5, 6]);
+        assert!(model.core_distances().iter().all(|d| *d == 0.));
+
+        let mut model = Dbscan::new(1.01, 3, Euclidean::default());
+        model.fit(&data, None);
+        assert_eq!(model.core_sample_indices(), [2]);
+        assert_eq!(model.core_distances(), [1.]);
+
+        let (clusters, _) = model.fit_with_prefilter(&data);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(model.core_sample_indices(), [2]);
+
+        model.fit(&array![[0.], [5.]], None);
+        assert!(model.core_sample_indices().is_empty());
+        assert!(model.core_distances().is_empty());
+    }
+
+    #[test]
+    fn border_policy() {
+        // point 4 is a border point of both clusters; the first cluster
+        // reaches it first, but the second one has the nearest core point
+        let data = array![
+            [0.],
+            [0.2],
+            [0.4],
+            [0.6],
+            [2.],
+            [3.1],
+            [3.6],
+            [3.7],
+            [3.8],
+            [9.]
+        ];
+        let mut model = Dbscan::new(1.5, 4, Euclidean::default());
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(clusters[&0].contains(&4));
+        assert_eq!(noise, [9]);
+
+        model.border_policy = BorderPolicy::NearestCore;
+        let (clusters, noise) = model.fit(&data, None);
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 1, 2, 3], 1 => vec![4, 5, 6, 7, 8]}
+        );
+        assert_eq!(noise, [9]);
+
+        model.border_policy = BorderPolicy::Noise;
+        let (clusters, noise) = model.fit(&data, None);
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 1, 2, 3], 1 => vec![5, 6, 7, 8]}
+        );
+        assert_eq!(noise, [4, 9]);
+
+        // the same policy applies to cancellable fits
+        let outcome = model.fit_cancellable(&data, &CancelToken::new());
+        assert_eq!(outcome.into_result().1, [4, 9]);
+    }
+
+    #[test]
+    fn fit_with_min_samples() {
+        let data = array![[0.], [1.], [2.], [3.], [10.], [11.], [12.]];
+        let mut model = Dbscan::new(1.01, 3, Euclidean::default());
+        let (clusters, noise) = model.fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+
+        // the same thresholds for all points match `fit`
+        let (uniform, _) = model.fit_with_min_samples(&data, &[3; 7]);
+        assert_eq!(uniform.len(), 2);
+
+        // unreliable points only join clusters as border points
+        let (mut clusters, mut noise) = model.fit_with_min_samples(&data, &[3, 3, 4, 3, 4, 4, 4]);
+        noise.sort_unstable();
+        for v in clusters.values_mut() {
+            v.sort_unstable();
+        }
+        assert_eq!(hashmap! {0 => vec![0, 1, 2]}, clusters);
+        assert_eq!(noise, [3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn fit_with_radii() {
+        let data = array![[0., 0.], [0.5, 10.], [3., 0.], [0., 40.], [0.9, 20.]];
+        let mut model = Dbscan::new(1., 2, Euclidean::default());
+        let (clusters, noise) = model.fit_with_radii(&data, &[1., 20.]);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[&0], [0, 1, 4]);
+        assert_eq!(noise, [2, 3]);
+
+        // equal radii are the same as `eps`
+        let expected = model.fit(&data, None);
+        assert_eq!(model.fit_with_radii(&data, &[1., 1.]), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "the radii should be positive")]
+    fn fit_with_radii_non_positive() {
+        let data = array![[0., 0.]];
+        let _ = Dbscan::new(1., 2, Euclidean::default()).fit_with_radii(&data, &[1., 0.]);
+    }
+
+    #[test]
+    fn fit_from_seeds() {
+        let data = array![
+            [0.],
+            [1.],
+            [2.],
+            [3.],
+            [10.],
+            [11.],
+            [12.],
+            [20.],
+            [30.],
+            [31.]
+        ];
+        let mut model = Dbscan::new(1.01, 3, Euclidean::default());
+
+        // every core point as a seed matches `fit`
+        let (clusters, noise) = model.fit_from_seeds(&data, &(0..10).collect::<Vec<_>>());
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 1, 2, 3], 1 => vec![4, 5, 6]}
+        );
+        assert_eq!(noise, [7, 8, 9]);
+
+        // a border seed expands from its core neighbors, and an isolated
+        // seed or one in a sparse region is noise
+        let (clusters, noise) = model.fit_from_seeds(&data, &[7, 9, 6, 0]);
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![4, 5, 6], 1 => vec![0, 1, 2, 3]}
+        );
+        assert_eq!(noise, [7, 8, 9]);
+
+        let (clusters, noise) = model.fit_from_seeds(&data, &[]);
+        assert!(clusters.is_empty());
+        assert_eq!(noise.len(), data.nrows());
+    }
+
+    #[test]
+    fn fit_constrained() {
+        let data = array![[0.], [1.], [2.], [3.], [10.], [11.], [12.], [30.]];
+        let mut model = Dbscan::new(1.01, 2, Euclidean::default());
+
+        // no constraints match `fit`
+        let (clusters, noise) = model
+            .fit_constrained(&data, &Constraints::default())
+            .unwrap();
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 1, 2, 3], 1 => vec![4, 5, 6]}
+        );
+        assert_eq!(noise, [7]);
+
+        // a must-linked noise point joins with its partner, and a cluster
+        // stops at a point it cannot be linked to
+        let constraints = Constraints {
+            must_link: vec![(7, 5)],
+            cannot_link: vec![(0, 2)],
+        };
+        let (clusters, noise) = model.fit_constrained(&data, &constraints).unwrap();
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 1], 1 => vec![2, 3], 2 => vec![4, 5, 6, 7]}
+        );
+        assert!(noise.is_empty());
+
+        // a labeled point cut off from its neighbors is too sparse alone
+        let (clusters, noise) = model.fit(&data, Some(&hashmap! {0 => vec![0], 1 => vec![3]}));
+        assert_eq!(clusters, hashmap! {0 => vec![0, 1, 2], 1 => vec![4, 5, 6]});
+        assert_eq!(noise, [3, 7]);
+
+        let constraints = Constraints {
+            must_link: vec![(0, 4), (4, 6)],
+            cannot_link: vec![(6, 0)],
+        };
+        assert_eq!(
+            model.fit_constrained(&data, &constraints),
+            Err(ConstraintError::Infeasible { a: 6, b: 0 })
+        );
+        let constraints = Constraints {
+            must_link: vec![(0, 8)],
+            cannot_link: vec![],
+        };
+        assert!(model.fit_constrained(&data, &constraints).is_err());
+    }
+
+    #[test]
+    fn fit_with_prefilter() {
+        let mut data = vec![];
+        for i in 0..40 {
+            // two dense rows, and points spread far apart
+            data.push([f64::from(i % 20) * 0.3, f64::from(i / 20) * 0.3]);
+            data.push([f64::from(i) * 7., 50. + f64::from(i % 3) * 9.]);
+        }
+        // a point close enough to be a border point
+        data.push([-0.9, 0.]);
+        let data = aview2(&data);
+
+        let mut model = Dbscan::new(1., 4, Euclidean::default());
+        let (mut expected, mut expected_noise) = model.fit(&data, None);
+        let (mut clusters, mut noise) = model.fit_with_prefilter(&data);
+        for v in expected.values_mut().chain(clusters.values_mut()) {
+            v.sort_unstable();
+        }
+        let mut expected: Vec<_> = expected.into_values().collect();
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        expected.sort_unstable();
+        clusters.sort_unstable();
+        expected_noise.sort_unstable();
+        noise.sort_unstable();
+        assert_eq!(clusters, expected);
+        assert_eq!(noise, expected_noise);
+        assert_eq!(clusters.len(), 1);
+        assert!(clusters[0].contains(&80));
+
+        let kept = grid_prefilter(&data, 1., 4).expect("small grid");
+        assert_eq!(kept.len(), 41);
+        assert!(grid_prefilter(&data, 0., 4).is_none());
+    }
+
+    #[test]
+    fn fit_cancellable() {
+        let data = array![[0.], [1.], [2.], [10.], [11.], [12.], [30.]];
+        let mut model = Dbscan::new(1.01, 2, Euclidean::default());
+        let (expected, expected_noise) = model.fit(&data, None);
+
+        let outcome = model.fit_cancellable(&data, &CancelToken::new());
+        assert!(!outcome.is_partial());
+        let (clusters, noise) = outcome.into_result();
+        assert_eq!(clusters, expected);
+        assert_eq!(noise, expected_noise);
+
+        let token = CancelToken::new();
+        token.cancel();
+        let outcome = model.fit_cancellable(&data, &token);
+        assert_eq!(outcome.processed(), Some(0));
+        let (clusters, noise) = outcome.into_result();
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    fn fit_with_limits() {
+        let data = array![[0.], [1.], [2.], [10.], [11.], [12.], [30.]];
+        let mut model = Dbscan::new(1.01, 2, Euclidean::default());
+        let expected = model.fit(&data, None);
+        assert_eq!(
+            model.fit_with_limits(&data, &Limits::default()),
+            Ok(expected)
+        );
+
+        // the input and the ball tree alone take 7 * 8 + 2 * 7 * 8 bytes
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(100), None))
+            .unwrap_err();
+        assert_eq!(error.limit, Limit::Memory(100));
+        assert_eq!(error.phase, "indexing");
+        assert_eq!(error.memory, 168);
+
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(200), None))
+            .unwrap_err();
+        assert_eq!(error.phase, "neighbor search");
+        assert_eq!(error.processed, 7);
+
+        // plus 15 neighbors in 7 lists, then the union-find of the expansion
+        let searched = 168 + 7 * size_of::<Vec<usize>>() + 15 * 8;
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(searched), None))
+            .unwrap_err();
+        assert_eq!(error.phase, "expansion");
+        assert_eq!(error.processed, 0);
+        assert_eq!(error.memory, searched + 7 * (3 * 8 + 1));
+
+        let error = model
+            .fit_with_limits(&data, &Limits::new(None, Some(Duration::ZERO)))
+            .unwrap_err();
+        assert_eq!(error.limit, Limit::Duration(Duration::ZERO));
+    }
+
+    #[test]
+    fn fit_with_report() {
+        let data = array![[0.], [1.], [2.], [10.], [11.], [12.], [30.]];
+        let mut model = Dbscan::new(1.01, 2, Euclidean::default());
+        let expected = model.fit(&data, None);
+        let core_samples = model.core_sample_indices().to_vec();
+        let core_distances = model.core_distances().to_vec();
+        let (result, report) = model.fit_with_report(&data);
+        assert_eq!(result, expected);
+        assert_eq!(model.core_sample_indices(), core_samples);
+        assert_eq!(model.core_distances(), core_distances);
+
+        assert_eq!(report.n_points, 7);
+        assert_eq!(report.n_core_points, 6);
+        assert_eq!(report.neighbors.min, 1);
+        assert_eq!(report.neighbors.median, 2);
+        assert_eq!(report.neighbors.max, 3);
+        let phases: Vec<_> = report.phases.iter().map(|(name, _)| *name).collect();
+        assert_eq!(phases, ["indexing", "neighbor search", "expansion"]);
+        // as estimated by `fit_with_limits`, plus 15 neighbors in 7 lists
+        assert_eq!(report.memory, 168 + 7 * size_of::<Vec<usize>>() + 15 * 8);
+        assert!(report.distance_evaluations >= 7);
+
+        let (_, report) = model.fit_with_report(&Array2::<f64>::zeros((0, 1)));
+        assert_eq!(report, FitReport::default());
+    }
+
+    #[test]
+    fn fit_stream() {
+        let (mut data, _) = crate::datasets::make_blobs(500, 2, 4, 1., 3);
+        data.row_mut(5).fill(f64::NAN);
+        data[[37, 1]] = f64::INFINITY;
+        for policy in [
+            BorderPolicy::FirstCore,
+            BorderPolicy::NearestCore,
+            BorderPolicy::Noise,
+        ] {
+            let mut model = Dbscan::new(0.3, 8, Euclidean::default());
+            model.border_policy = policy;
+            let (mut expected, noise) = model.fit(&data, None);
+            for members in expected.values_mut() {
+                members.sort_unstable();
+            }
+            assert!(expected.len() > 1);
+            let rows = data.rows().into_iter().map(|row| row.to_owned());
+            // 13 chunks, merged into segments of 8, 4 and 1 chunks
+            let clustering = model.fit_stream_in_chunks(rows.clone(), 40);
+            assert_eq!(clustering, (expected.clone(), noise.clone()));
+            assert_eq!(model.fit_stream(rows), (expected, noise));
+        }
+
+        let mut model = Dbscan::new(0.5, 2, Euclidean::default());
+        assert_eq!(model.fit_stream(Vec::new()), (HashMap::new(), vec![]));
+        let nan = array![f64::NAN, 0.];
+        assert_eq!(
+            model.fit_stream(vec![nan.clone(), nan]),
+            (HashMap::new(), vec![0, 1])
+        );
+    }
+
+    #[test]
+    fn fit_with_policy() {
+        let data = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0]];
+        let mut model = Dbscan::new(0.5, 2, Euclidean::default());
+        let ((clusters, noise), warning) = model
+            .fit_with_policy(&data, DegeneracyPolicy::Accept)
+            .unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(noise, [4]);
+        assert_eq!(
+            warning,
+            Some(Degeneracy::SingleCluster {
+                n_points: 5,
+                size: 4
+            })
+        );
+
+        // a smaller eps splits nothing here, so the warning remains
+        let (_, warning) = model
+            .fit_with_policy(&data, DegeneracyPolicy::RelaxEps(2.))
+            .unwrap();
+        assert!((model.eps - 0.25).abs() < f64::EPSILON);
+        assert!(matches!(warning, Some(Degeneracy::SingleCluster { .. })));
+
+        let mut model = Dbscan::new(0.5, 10, Euclidean::default());
+        let error = model
+            .fit_with_policy(&data, DegeneracyPolicy::Reject)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            Degeneracy::TooFewPoints {
+                n_points: 5,
+                min_samples: 10
+            }
+        );
+        let ((_, noise), _) = model
+            .fit_with_policy(&data, DegeneracyPolicy::RelaxEps(2.))
+            .unwrap();
+        assert_eq!(noise.len(), 5);
+        assert!((model.eps - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn sweep() {
+        let data = array![
+            [0.],
+            [0.2],
+            [0.4],
+            [0.6],
+            [2.],
+            [3.1],
+            [3.6],
+            [3.7],
+            [3.8],
+            [9.]
+        ];
+        for policy in [
+            BorderPolicy::FirstCore,
+            BorderPolicy::NearestCore,
+            BorderPolicy::Noise,
+        ] {
+            let mut model = Dbscan::new(6., 4, Euclidean::default());
+            model.border_policy = policy;
+            let sweep = model.sweep(&data);
+            let eps = [0.1, 0.5, 1.5, 2., 6.];
+            for (eps, (mut clusters, noise)) in eps.into_iter().zip(sweep.extract_all(&eps)) {
+                model.eps = eps;
+                let (mut expected, expected_noise) = model.fit(&data, None);
+                for members in clusters.values_mut().chain(expected.values_mut()) {
+                    members.sort_unstable();
+                }
+                assert_eq!(clusters, expected, "eps = {eps}, {policy:?}");
+                assert_eq!(noise, expected_noise, "eps = {eps}, {policy:?}");
+            }
+        }
+
+        let empty = Array2::<f64>::zeros((0, 1));
+        let sweep = Dbscan::new(1., 2, Euclidean::default()).sweep(&empty);
+        assert_eq!(sweep.extract(0.5), (HashMap::new(), Vec::new()));
     }
 
     #[test]
@@ -218,6 +2690,54 @@ mod test {
         assert!(noise.is_empty());
     }
 
+    #[test]
+    fn fit_deduplicated() {
+        // quantized readings in three groups, and a lone reading
+        let data = Array2::from_shape_fn((60, 2), |(i, j)| match i {
+            59 => 20.,
+            _ if i % 2 == 0 => f64::from(u8::try_from(i % 3 + j).unwrap()) * 0.5,
+            _ => 10. + f64::from(u8::try_from(i % 4).unwrap()) * 0.5,
+        });
+        let mut model = Dbscan::new(0.75, 12, Euclidean::default());
+        let expected = model.fit(&data, None);
+
+        model.deduplicate = true;
+        let (mut clusters, noise) = model.fit(&data, None);
+        assert_eq!(noise, expected.1);
+        for members in clusters.values_mut() {
+            members.sort_unstable();
+        }
+        let mut expected_clusters = expected.0;
+        for members in expected_clusters.values_mut() {
+            members.sort_unstable();
+        }
+        assert_eq!(clusters, expected_clusters);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(noise, [59]);
+    }
+
+    #[test]
+    fn fit_non_finite() {
+        let data = array![
+            [1., 2.],
+            [2., 2.],
+            [f64::NAN, 2.],
+            [2., 2.3],
+            [8., f64::INFINITY],
+            [8., 7.],
+            [8., 8.]
+        ];
+        let mut model = Dbscan::new(3., 2, Euclidean::default());
+        let (mut clusters, noise) = model.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.drain().map(|(_, members)| members).collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 3], vec![5, 6]]);
+        assert_eq!(noise, [2, 4]);
+
+        let sweep = model.sweep(&data);
+        assert_eq!(sweep.extract(3.).1, [2, 4]);
+    }
+
     #[test]
     fn fortran_style_input() {
         let data = array![
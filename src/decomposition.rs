@@ -0,0 +1,471 @@
+//! Dimensionality reduction, enabled with the `decomposition` feature.
+//!
+//! Distances in many dimensions become alike, which slows down the
+//! neighborhood queries of a `BallTree` and blurs the density differences
+//! that density-based algorithms rely on. Reducing the input to fewer
+//! dimensions before clustering helps with both. Each reducer is fitted to
+//! an input with [`Fit`] and maps inputs with the same features to the
+//! reduced space with [`Transform`].
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::Array2;
+//! use petal_clustering::decomposition::Pca;
+//! use petal_clustering::{Dbscan, Fit, Transform};
+//! use petal_neighbors::distance::Euclidean;
+//!
+//! // two groups that only differ along the first of 50 dimensions
+//! let points = Array2::from_shape_fn((20, 50), |(i, d)| match d {
+//!     0 => if i < 10 { 0. } else { 10. },
+//!     _ => ((i * 7 + d * 3) % 11) as f64 / 100.,
+//! });
+//! let mut pca = Pca::new(2);
+//! pca.fit(&points, None);
+//! let reduced = pca.transform(&points);
+//! assert_eq!(reduced.dim(), (20, 2));
+//!
+//! let (clusters, noise) = Dbscan::new(1., 3, Euclidean::default()).fit(&reduced, None);
+//! assert_eq!(clusters.len(), 2);
+//! assert!(noise.is_empty());
+//! ```
+
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::linalg::largest_eigen;
+use crate::sum::sum;
+use crate::{Fit, Transform};
+
+/// Principal component analysis, which projects the input onto the
+/// directions of largest variance.
+///
+/// The components are the eigenvectors of the covariance matrix of the
+/// input, so fitting takes O(nd² + d³) time for `n` points with `d`
+/// features. Each component is oriented so that its entry with the largest
+/// absolute value is positive, which makes the projection deterministic.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Pca<A> {
+    /// The number of components to keep, at most the number of features.
+    pub n_components: usize,
+
+    mean: Vec<A>,
+    components: Vec<A>,
+    explained_variance: Vec<A>,
+    total_variance: A,
+}
+
+impl<A: FloatCore> Default for Pca<A> {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl<A: FloatCore> Pca<A> {
+    /// Creates a model projecting onto `n_components` principal components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::decomposition::Pca;
+    ///
+    /// let pca = Pca::<f64>::new(2);
+    /// assert_eq!(pca.n_components, 2);
+    /// assert_eq!(pca.components().nrows(), 0);
+    /// ```
+    #[must_use]
+    pub fn new(n_components: usize) -> Self {
+        Self {
+            n_components,
+            mean: vec![],
+            components: vec![],
+            explained_variance: vec![],
+            total_variance: A::zero(),
+        }
+    }
+
+    /// Returns the principal components of the last fit, one row per
+    /// component, in decreasing order of explained variance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::decomposition::Pca;
+    /// use petal_clustering::Fit;
+    ///
+    /// // the points vary along the first feature only
+    /// let points = array![[0., 1.], [2., 1.], [4., 1.]];
+    /// let mut pca = Pca::new(1);
+    /// pca.fit(&points, None);
+    ///
+    /// assert_eq!(pca.components(), array![[1., 0.]]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of the number of features
+    pub fn components(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (self.explained_variance.len(), self.mean.len()),
+            &self.components,
+        )
+        .expect("components of the fitted model")
+    }
+
+    /// Returns the variance of the input along each component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::decomposition::Pca;
+    /// use petal_clustering::Fit;
+    ///
+    /// let points = array![[0., 1.], [2., 1.], [4., 1.]];
+    /// let mut pca = Pca::new(1);
+    /// pca.fit(&points, None);
+    ///
+    /// assert!((pca.explained_variance()[0] - 4_f64).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn explained_variance(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.explained_variance)
+    }
+
+    /// Returns the fraction of the total variance of the input along each
+    /// component, which helps choose `n_components`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::decomposition::Pca;
+    /// use petal_clustering::Fit;
+    ///
+    /// let points = array![[0., 0.], [2., 1.], [4., 0.], [6., 1.]];
+    /// let mut pca = Pca::new(2);
+    /// pca.fit(&points, None);
+    ///
+    /// let ratio = pca.explained_variance_ratio();
+    /// assert!(ratio[0] > 0.9);
+    /// assert!((ratio[0] + ratio[1] - 1_f64).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn explained_variance_ratio(&self) -> Vec<A> {
+        if self.total_variance <= A::zero() {
+            return vec![A::zero(); self.explained_variance.len()];
+        }
+        self.explained_variance
+            .iter()
+            .map(|v| *v / self.total_variance)
+            .collect()
+    }
+}
+
+/// Computes the principal components of the given input.
+///
+/// # Returns
+/// Nothing; the components are kept for [`Transform::transform`].
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for Pca<A>
+where
+    A: Float + FloatCore + FromPrimitive + 'static,
+    S: Data<Elem = A>,
+{
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, _params: Option<&()>) {
+        let n = A::from_usize(input.nrows().max(1)).expect("valid float");
+        self.mean = input
+            .columns()
+            .into_iter()
+            .map(|column| sum(column.iter().copied()) / n)
+            .collect();
+
+        let mut centered = input.to_owned();
+        for mut row in centered.rows_mut() {
+            for (v, m) in row.iter_mut().zip(&self.mean) {
+                *v = *v - *m;
+            }
+        }
+        let dof = A::from_usize(input.nrows().saturating_sub(1).max(1)).expect("valid float");
+        let covariance = centered.t().dot(&centered).mapv(|v| v / dof);
+        self.total_variance = sum(covariance.diag().iter().copied());
+
+        let (values, mut vectors) = largest_eigen(covariance, self.n_components);
+        for mut vector in vectors.columns_mut() {
+            let largest = vector.iter().copied().fold(A::zero(), |a, v| {
+                if Float::abs(v) > Float::abs(a) {
+                    v
+                } else {
+                    a
+                }
+            });
+            if largest < A::zero() {
+                vector.mapv_inplace(|v| -v);
+            }
+        }
+        self.explained_variance = values.iter().map(|v| Float::max(*v, A::zero())).collect();
+        self.components = vectors.t().iter().copied().collect();
+    }
+}
+
+/// Projects the given input onto the principal components.
+///
+/// # Panics
+/// Panics if the model has not been fitted to an input with the same number
+/// of features.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for Pca<A>
+where
+    A: Float + FloatCore + FromPrimitive + 'static,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        check_features(self.mean.len(), input.ncols());
+        let mut centered = input.to_owned();
+        for mut row in centered.rows_mut() {
+            for (v, m) in row.iter_mut().zip(&self.mean) {
+                *v = *v - *m;
+            }
+        }
+        centered.dot(&self.components().t())
+    }
+}
+
+/// Sparse random projection, which maps the input through a random matrix
+/// whose entries are mostly zero.
+///
+/// Each entry of the matrix is `±1 / sqrt(density * n_components)` with
+/// probability `density / 2` each, and zero otherwise. By the
+/// Johnson-Lindenstrauss lemma, the distances between points are preserved
+/// within a factor of `1 ± eps` with high probability if `n_components` is
+/// at least [`johnson_lindenstrauss_min_dim`]. Unlike [`Pca`], fitting only
+/// draws the matrix, so it scales to inputs with many features.
+///
+/// # References
+/// - Li, Ping, Trevor J. Hastie, and Kenneth W. Church. "Very sparse random projections."
+///   Proceedings of the 12th ACM SIGKDD International Conference on Knowledge Discovery and Data Mining (2006): 287-296.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SparseRandomProjection<A> {
+    /// The number of dimensions to project to.
+    pub n_components: usize,
+
+    /// The fraction of nonzero entries of the projection matrix, in `(0, 1]`,
+    /// or `1 / sqrt(n_features)` if `None`.
+    pub density: Option<f64>,
+
+    /// The seed of the random number generator drawing the projection matrix.
+    pub seed: u64,
+
+    components: Vec<Vec<(usize, A)>>,
+    n_features: usize,
+}
+
+impl<A> SparseRandomProjection<A> {
+    /// Creates a model projecting onto `n_components` dimensions, with a
+    /// density of `1 / sqrt(n_features)` and a seed of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::Array2;
+    /// use petal_clustering::decomposition::SparseRandomProjection;
+    /// use petal_clustering::{Fit, Transform};
+    ///
+    /// let points = Array2::<f64>::ones((4, 100));
+    /// let mut projection = SparseRandomProjection::new(10);
+    /// projection.fit(&points, None);
+    ///
+    /// assert_eq!(projection.transform(&points).dim(), (4, 10));
+    /// ```
+    #[must_use]
+    pub fn new(n_components: usize) -> Self {
+        Self {
+            n_components,
+            density: None,
+            seed: 0,
+            components: vec![],
+            n_features: 0,
+        }
+    }
+}
+
+/// Draws the projection matrix for the features of the given input.
+///
+/// # Returns
+/// Nothing; the matrix is kept for [`Transform::transform`].
+///
+/// # Panics
+/// Panics if `density` is not in `(0, 1]`.
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for SparseRandomProjection<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    #[allow(clippy::cast_precision_loss)]
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, _params: Option<&()>) {
+        let n_features = input.ncols();
+        let density = self
+            .density
+            .unwrap_or_else(|| 1. / (n_features.max(1) as f64).sqrt());
+        assert!(density > 0. && density <= 1., "`density` must be in (0, 1]");
+        let value =
+            A::from(1. / (density * self.n_components.max(1) as f64).sqrt()).expect("valid float");
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.components = (0..self.n_components)
+            .map(|_| {
+                (0..n_features)
+                    .filter_map(|i| {
+                        let u: f64 = rng.random();
+                        if u < density / 2. {
+                            Some((i, value))
+                        } else if u < density {
+                            Some((i, -value))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.n_features = n_features;
+    }
+}
+
+/// Projects the given input with the projection matrix.
+///
+/// # Panics
+/// Panics if the model has not been fitted to an input with the same number
+/// of features.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for SparseRandomProjection<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        check_features(self.n_features, input.ncols());
+        let mut output = Array2::zeros((input.nrows(), self.components.len()));
+        for (point, mut projected) in input.rows().into_iter().zip(output.rows_mut()) {
+            for (p, component) in projected.iter_mut().zip(&self.components) {
+                *p = component
+                    .iter()
+                    .fold(A::zero(), |acc, (i, v)| acc + point[*i] * *v);
+            }
+        }
+        output
+    }
+}
+
+/// Returns the number of dimensions a random projection of `n_samples`
+/// points needs so that the distances between them are preserved within a
+/// factor of `1 ± eps` with high probability.
+///
+/// The bound does not depend on the number of features, only on the number
+/// of points.
+///
+/// # Panics
+/// Panics if `eps` is not in `(0, 1)`.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::decomposition::johnson_lindenstrauss_min_dim;
+///
+/// assert_eq!(johnson_lindenstrauss_min_dim(1_000_000, 0.5), 663);
+/// ```
+#[must_use]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn johnson_lindenstrauss_min_dim(n_samples: usize, eps: f64) -> usize {
+    assert!(eps > 0. && eps < 1., "`eps` must be in (0, 1)");
+    let denominator = eps.powi(2) / 2. - eps.powi(3) / 3.;
+    (4. * (n_samples.max(1) as f64).ln() / denominator) as usize
+}
+
+fn check_features(fitted: usize, given: usize) {
+    assert!(
+        fitted > 0 || given == 0,
+        "`transform` requires a fitted model"
+    );
+    assert_eq!(
+        fitted, given,
+        "the input has {given} features, but the model was fitted to {fitted}"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn pca() {
+        let data = array![[0., 0.], [1., 1.], [2., 2.], [3., 3.]];
+        let mut pca = Pca::new(1);
+        pca.fit(&data, None);
+
+        let half = 0.5_f64.sqrt();
+        let component = pca.components();
+        assert_eq!(component.dim(), (1, 2));
+        assert!((component[[0, 0]] - half).abs() < 1e-12);
+        assert!((component[[0, 1]] - half).abs() < 1e-12);
+        assert!((pca.explained_variance()[0] - 10. / 3.).abs() < 1e-12);
+        assert!((pca.explained_variance_ratio()[0] - 1.).abs() < 1e-12);
+
+        let reduced = pca.transform(&array![[1.5, 1.5], [2.5, 2.5]]);
+        assert!(reduced[[0, 0]].abs() < 1e-12);
+        assert!((reduced[[1, 0]] - 2. * half).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pca_more_components_than_features() {
+        let data = array![[1., 0.], [-1., 0.], [0., 2.], [0., -2.]];
+        let mut pca = Pca::new(5);
+        pca.fit(&data, None);
+        assert_eq!(pca.components().dim(), (2, 2));
+        assert!((pca.components()[[0, 1]] - 1_f64).abs() < 1e-12);
+        assert_eq!(pca.transform(&data).dim(), (4, 2));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn sparse_random_projection() {
+        let data = Array2::from_shape_fn((10, 2000), |(i, d)| ((i * 31 + d * 17) % 23) as f64);
+        let mut projection = SparseRandomProjection::new(1000);
+        projection.seed = 7;
+        projection.fit(&data, None);
+        let reduced = projection.transform(&data);
+        assert_eq!(reduced.dim(), (10, 1000));
+
+        for i in 0..10 {
+            for j in i + 1..10 {
+                let original = (&data.row(i) - &data.row(j)).mapv(|v| v * v).sum();
+                let projected = (&reduced.row(i) - &reduced.row(j)).mapv(|v| v * v).sum();
+                let ratio = projected / original;
+                assert!(0.7 < ratio && ratio < 1.3, "{ratio}");
+            }
+        }
+
+        let mut other = SparseRandomProjection::new(1000);
+        other.seed = 7;
+        other.fit(&data, None);
+        assert_eq!(other.transform(&data), reduced);
+    }
+
+    #[test]
+    #[should_panic(expected = "fitted to 3")]
+    fn transform_other_features() {
+        let mut projection = SparseRandomProjection::new(2);
+        projection.fit(&array![[0., 1., 2.]], None);
+        projection.transform(&array![[0., 1.]]);
+    }
+
+    #[test]
+    fn johnson_lindenstrauss() {
+        assert_eq!(johnson_lindenstrauss_min_dim(1_000, 0.5), 331);
+        assert_eq!(johnson_lindenstrauss_min_dim(1_000, 0.1), 5920);
+    }
+}
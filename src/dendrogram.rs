@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::ops::{AddAssign, Sub};
+
+use ndarray::ArrayView1;
+use num_traits::{float::FloatCore, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::hdbscan::find_clusters;
+use crate::mst::condense_mst;
+use crate::union_find::TreeUnionFind;
+use crate::ClusterExtraction;
+
+/// A hierarchy of clusters, as built by agglomerative clustering and
+/// returned by [`AgglomerativeClustering::hierarchy`], [`HDbscan::hierarchy`]
+/// and [`Optics::hierarchy`], so that hierarchies can be cut and flattened
+/// the same way whichever algorithm built them.
+///
+/// The merges are kept in increasing order of distance, as
+/// `(left, right, distance, size)` tuples. A cluster ID below the number of
+/// points `n` is a point, and the cluster formed by the `i`-th merge has ID
+/// `n + i`, as in the linkage matrices of `SciPy`. A hierarchy may be a
+/// forest, with fewer than `n - 1` merges, if some points are never merged,
+/// e.g. points that OPTICS cannot reach from the others.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::Dendrogram;
+///
+/// let dendrogram = Dendrogram::from_edges(4, &[(0, 1, 1.), (2, 3, 2.), (1, 2, 5.)]);
+/// assert_eq!(dendrogram.merges()[1], (2, 3, 2., 2));
+/// assert_eq!(dendrogram.merges()[2], (4, 5, 5., 4));
+///
+/// let (clusters, noise) = dendrogram.cut_at(2);
+/// assert_eq!(clusters[&0], [0, 1]);
+/// assert_eq!(clusters[&1], [2, 3]);
+/// assert!(noise.is_empty());
+/// assert_eq!(dendrogram.cut_at_distance(1.5).0.len(), 3);
+/// ```
+///
+/// [`AgglomerativeClustering::hierarchy`]: crate::AgglomerativeClustering::hierarchy
+/// [`HDbscan::hierarchy`]: crate::HDbscan::hierarchy
+/// [`Optics::hierarchy`]: crate::Optics::hierarchy
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Dendrogram<A> {
+    n_points: usize,
+    merges: Vec<(usize, usize, A, usize)>,
+}
+
+impl<A> Default for Dendrogram<A> {
+    fn default() -> Self {
+        Self {
+            n_points: 0,
+            merges: Vec::new(),
+        }
+    }
+}
+
+impl<A> Dendrogram<A> {
+    /// Returns the number of points in the hierarchy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(4, &[(0, 1, 1.)]);
+    /// assert_eq!(dendrogram.n_points(), 4);
+    /// ```
+    #[must_use]
+    pub fn n_points(&self) -> usize {
+        self.n_points
+    }
+
+    /// Returns the merges in increasing order of distance, as
+    /// `(left, right, distance, size)` tuples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(3, &[(1, 2, 3.), (0, 1, 1.)]);
+    /// // points 0 and 1 form cluster 3, which merges with point 2
+    /// assert_eq!(dendrogram.merges(), [(0, 1, 1., 2), (2, 3, 3., 3)]);
+    /// ```
+    #[must_use]
+    pub fn merges(&self) -> &[(usize, usize, A, usize)] {
+        &self.merges
+    }
+
+    /// Returns the clusters that are not merged any further, in increasing
+    /// order of ID: the root of a tree, or the roots of a forest.
+    fn roots(&self) -> Vec<usize> {
+        let mut is_root = vec![true; self.n_points + self.merges.len()];
+        for (l, r, _, _) in &self.merges {
+            is_root[*l] = false;
+            is_root[*r] = false;
+        }
+        (0..is_root.len()).filter(|i| is_root[*i]).collect()
+    }
+
+    /// Visits the clusters of the hierarchy depth first, from the roots in
+    /// increasing order of ID and from the left child to the right one,
+    /// without recursion, so that deep hierarchies do not overflow the
+    /// stack.
+    fn walk<F>(&self, mut visit: F) -> io::Result<()>
+    where
+        A: Copy,
+        F: FnMut(Visit<A>) -> io::Result<()>,
+    {
+        let n = self.n_points;
+        let mut stack = Vec::new();
+        for (i, root) in self.roots().into_iter().enumerate().rev() {
+            stack.push(Step::Enter(root, None));
+            if i > 0 {
+                stack.push(Step::Sibling);
+            }
+        }
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(id, parent) if id < n => visit(Visit::Leaf { id, parent })?,
+                Step::Enter(id, parent) => {
+                    let (left, right, distance, size) = self.merges[id - n];
+                    visit(Visit::Open { id, distance, size })?;
+                    stack.push(Step::Leave(id, parent));
+                    stack.push(Step::Enter(right, Some(distance)));
+                    stack.push(Step::Sibling);
+                    stack.push(Step::Enter(left, Some(distance)));
+                }
+                Step::Leave(id, parent) => visit(Visit::Close {
+                    distance: self.merges[id - n].2,
+                    parent,
+                })?,
+                Step::Sibling => visit(Visit::Sibling)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the hierarchy of a subset of `n_points` points into that of all
+    /// of them, where point `j` of the subset is point `rows[j]`. The other
+    /// points are never merged.
+    pub(crate) fn embed(&self, rows: &[usize], n_points: usize) -> Self
+    where
+        A: Copy,
+    {
+        let id = |c: usize| match rows.get(c) {
+            Some(&i) => i,
+            None => n_points + c - self.n_points,
+        };
+        let merges = self
+            .merges
+            .iter()
+            .map(|&(l, r, d, size)| {
+                let (l, r) = (id(l), id(r));
+                (l.min(r), l.max(r), d, size)
+            })
+            .collect();
+        Self { n_points, merges }
+    }
+}
+
+impl<A: Copy + PartialOrd> Dendrogram<A> {
+    /// Builds the hierarchy of `n_points` points from the edges of a
+    /// spanning tree or forest, e.g. a minimum spanning tree, given as
+    /// `(i, j, distance)` tuples of points in any order. Edges between
+    /// points that are already connected are ignored.
+    ///
+    /// # Panics
+    /// Panics if a distance is NaN, or if an edge has a point that is not
+    /// below `n_points`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// // a forest: point 3 is not connected to the others
+    /// let dendrogram = Dendrogram::from_edges(4, &[(0, 1, 1.), (1, 2, 2.), (0, 2, 3.)]);
+    /// assert_eq!(dendrogram.merges().len(), 2);
+    /// assert_eq!(dendrogram.cut_at(1).0.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn from_edges(n_points: usize, edges: &[(usize, usize, A)]) -> Self {
+        let mut edges = edges.to_vec();
+        edges.sort_by(|a, b| a.2.partial_cmp(&b.2).expect("valid distance"));
+
+        let mut uf = TreeUnionFind::new(n_points);
+        let mut labels: Vec<usize> = (0..n_points).collect();
+        let mut sizes = vec![1; n_points];
+        let mut merges = Vec::with_capacity(n_points.saturating_sub(1));
+        for (i, j, d) in edges {
+            let (ri, rj) = (uf.find(i), uf.find(j));
+            if ri == rj {
+                continue;
+            }
+            let (li, lj) = (labels[ri], labels[rj]);
+            let size = sizes[ri] + sizes[rj];
+            uf.union(ri, rj);
+            let root = uf.find(ri);
+            labels[root] = n_points + merges.len();
+            sizes[root] = size;
+            merges.push((li.min(lj), li.max(lj), d, size));
+        }
+        Self { n_points, merges }
+    }
+
+    /// Cuts the hierarchy into `n_clusters` clusters, or as few as it allows
+    /// if it is a forest, by undoing the last merges. The clusters are
+    /// numbered in the order of their first point, and there is no noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(4, &[(0, 1, 1.), (2, 3, 2.), (1, 2, 5.)]);
+    /// let (clusters, noise) = dendrogram.cut_at(3);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1]);
+    /// assert_eq!(clusters[&1], [2]);
+    /// assert_eq!(clusters[&2], [3]);
+    /// assert!(noise.is_empty());
+    /// ```
+    #[must_use]
+    pub fn cut_at(&self, n_clusters: usize) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n_merges = self.n_points.saturating_sub(n_clusters.max(1));
+        self.apply(n_merges.min(self.merges.len()))
+    }
+
+    /// Cuts the hierarchy at `distance`, keeping the merges at or below it.
+    /// The clusters are numbered in the order of their first point, and
+    /// there is no noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(4, &[(0, 1, 1.), (2, 3, 2.), (1, 2, 5.)]);
+    /// let (clusters, _) = dendrogram.cut_at_distance(2.);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1]);
+    /// assert_eq!(clusters[&1], [2, 3]);
+    /// ```
+    #[must_use]
+    pub fn cut_at_distance(&self, distance: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n_merges = self
+            .merges
+            .iter()
+            .take_while(|(_, _, d, _)| *d <= distance)
+            .count();
+        self.apply(n_merges)
+    }
+
+    /// Groups the points given the first `n_merges` merges, numbering the
+    /// clusters in the order of their first point.
+    fn apply(&self, n_merges: usize) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n = self.n_points;
+        let mut uf = TreeUnionFind::new(n);
+        // a point of each cluster formed by a merge
+        let mut points: Vec<usize> = (0..n).collect();
+        for (left, right, _, _) in &self.merges[..n_merges] {
+            let (i, j) = (points[*left], points[*right]);
+            uf.union(i, j);
+            points.push(i);
+        }
+        let mut ids = HashMap::new();
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for point in 0..n {
+            let root = uf.find(point);
+            let next = ids.len();
+            let id = *ids.entry(root).or_insert(next);
+            clusters.entry(id).or_default().push(point);
+        }
+        (clusters, Vec::new())
+    }
+}
+
+impl<A: Display + FloatCore> Dendrogram<A> {
+    /// Writes the hierarchy in the Newick format, to be drawn with tree
+    /// viewers such as `ETE`, `iTOL` or `Bio.Phylo`.
+    ///
+    /// The leaves are named by the indices of their points, and the length
+    /// of a branch is the distance of its parent's merge minus that of its
+    /// own, zero for a point, so that the depth of a merge is its distance.
+    /// The trees of a forest are written as the children of a root without
+    /// branch lengths.
+    ///
+    /// # Errors
+    /// Returns an error if writing to `writer` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(3, &[(0, 1, 1.), (1, 2, 3.)]);
+    /// let mut newick = Vec::new();
+    /// dendrogram.write_newick(&mut newick).unwrap();
+    ///
+    /// assert_eq!(String::from_utf8(newick).unwrap(), "(2:3,(0:1,1:1):2);");
+    /// ```
+    pub fn write_newick<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let forest = self.merges.len() + 1 != self.n_points;
+        if forest {
+            write!(writer, "(")?;
+        }
+        self.walk(|visit| match visit {
+            Visit::Leaf { id, parent } => {
+                write!(writer, "{id}")?;
+                write_length(&mut writer, parent, A::zero())
+            }
+            Visit::Open { .. } => write!(writer, "("),
+            Visit::Close { distance, parent } => {
+                write!(writer, ")")?;
+                write_length(&mut writer, parent, distance)
+            }
+            Visit::Sibling => write!(writer, ","),
+        })?;
+        if forest {
+            write!(writer, ")")?;
+        }
+        write!(writer, ";")
+    }
+
+    /// Writes the hierarchy as JSON, in the nested format read by tree
+    /// layouts such as `d3.hierarchy`:
+    ///
+    /// ```json
+    /// {
+    ///   "n_points": 3,
+    ///   "roots": [
+    ///     {"id": 4, "distance": 3, "size": 3, "children": [
+    ///       {"id": 2, "distance": 0, "size": 1},
+    ///       {"id": 3, "distance": 1, "size": 2, "children": [
+    ///         {"id": 0, "distance": 0, "size": 1},
+    ///         {"id": 1, "distance": 0, "size": 1}
+    ///       ]}
+    ///     ]}
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Each node has the ID of its cluster, as in [`Dendrogram::merges`],
+    /// the distance of its merge, zero for a point, and its number of
+    /// points. Merged clusters also have their left and right children.
+    /// `roots` holds the root of each tree of the hierarchy, in increasing
+    /// order of ID. Distances that are not finite are written as `null`.
+    /// The output has no whitespace.
+    ///
+    /// # Errors
+    /// Returns an error if writing to `writer` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let dendrogram = Dendrogram::from_edges(3, &[(0, 1, 1.5)]);
+    /// let mut json = Vec::new();
+    /// dendrogram.write_json(&mut json).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(json).unwrap(),
+    ///     r#"{"n_points":3,"roots":[{"id":2,"distance":0,"size":1},{"id":3,"distance":1.5,"size":2,"children":[{"id":0,"distance":0,"size":1},{"id":1,"distance":0,"size":1}]}]}"#
+    /// );
+    /// ```
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, r#"{{"n_points":{},"roots":["#, self.n_points)?;
+        self.walk(|visit| match visit {
+            Visit::Leaf { id, .. } => write!(writer, r#"{{"id":{id},"distance":0,"size":1}}"#),
+            Visit::Open { id, distance, size } => {
+                write!(writer, r#"{{"id":{id},"distance":"#)?;
+                if distance.is_finite() {
+                    write!(writer, "{distance}")?;
+                } else {
+                    write!(writer, "null")?;
+                }
+                write!(writer, r#","size":{size},"children":["#)
+            }
+            Visit::Close { .. } => write!(writer, "]}}"),
+            Visit::Sibling => write!(writer, ","),
+        })?;
+        write!(writer, "]}}")
+    }
+}
+
+/// Writes the length of the branch from a cluster merged at `distance` to
+/// its parent, merged at `parent`, if any.
+fn write_length<A, W>(writer: &mut W, parent: Option<A>, distance: A) -> io::Result<()>
+where
+    A: Display + FloatCore,
+    W: Write,
+{
+    match parent {
+        Some(parent) => write!(writer, ":{}", parent - distance),
+        None => Ok(()),
+    }
+}
+
+/// A step of the traversal in [`Dendrogram::walk`].
+enum Step<A> {
+    Enter(usize, Option<A>),
+    Leave(usize, Option<A>),
+    Sibling,
+}
+
+/// A cluster reached in [`Dendrogram::walk`], with the distance of its
+/// parent's merge, if any.
+enum Visit<A> {
+    Leaf { id: usize, parent: Option<A> },
+    Open { id: usize, distance: A, size: usize },
+    Close { distance: A, parent: Option<A> },
+    Sibling,
+}
+
+impl<A> Dendrogram<A>
+where
+    A: AddAssign + FloatCore + FromPrimitive + Sub,
+{
+    /// Selects the clusters of the hierarchy by excess of mass, as HDBSCAN
+    /// does, where groups of fewer than `min_size` points are points falling
+    /// out of a cluster rather than a split. The points outside the selected
+    /// clusters are noise.
+    ///
+    /// The clusters of a forest are only merged at the root of the
+    /// hierarchy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Dendrogram;
+    ///
+    /// let edges = [(0, 1, 1.), (1, 2, 1.), (3, 4, 1.), (4, 5, 1.), (2, 3, 9.), (5, 6, 20.)];
+    /// let (clusters, noise) = Dendrogram::from_edges(7, &edges).flatten(3);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [6]);
+    /// ```
+    #[must_use]
+    pub fn flatten(&self, min_size: usize) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        if self.n_points < 2 {
+            return (HashMap::new(), (0..self.n_points).collect());
+        }
+        find_clusters(
+            &ArrayView1::from(&self.condense(min_size)),
+            None,
+            ClusterExtraction::ExcessOfMass,
+            A::zero(),
+        )
+    }
+
+    /// Returns the condensed tree of the hierarchy, as HDBSCAN builds it, as
+    /// `(parent, child, lambda, size)` tuples, where `lambda` is the inverse
+    /// of the distance at which `child` leaves `parent`. A child below the
+    /// number of points `n` is a point, and the root has ID `n`.
+    pub(crate) fn condense(&self, min_size: usize) -> Vec<(usize, usize, A, usize)> {
+        let n = self.n_points;
+        if n < 2 {
+            return Vec::new();
+        }
+
+        // children of each merge, with the roots of a forest merged last
+        let mut sizes = vec![1; n];
+        sizes.extend(self.merges.iter().map(|(_, _, _, size)| *size));
+        let mut merges: Vec<_> = self
+            .merges
+            .iter()
+            .map(|(l, r, d, _)| (*l, *r, *d))
+            .collect();
+        let mut roots = self.roots().into_iter();
+        if let Some(mut top) = roots.next() {
+            for root in roots {
+                merges.push((top, root, A::infinity()));
+                sizes.push(sizes[top] + sizes[root]);
+                top = sizes.len() - 1;
+            }
+        }
+
+        let labeled: Vec<_> = merges
+            .iter()
+            .enumerate()
+            .flat_map(|(k, (l, r, d))| [(n + k, *l, *d, sizes[*l]), (n + k, *r, *d, sizes[*r])])
+            .collect();
+        condense_mst(&labeled, min_size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_edges() {
+        let dendrogram =
+            Dendrogram::from_edges(5, &[(3, 4, 2.), (0, 1, 1.), (1, 0, 3.), (1, 3, 4.)]);
+        assert_eq!(dendrogram.n_points(), 5);
+        assert_eq!(
+            dendrogram.merges(),
+            [(0, 1, 1., 2), (3, 4, 2., 2), (5, 6, 4., 4)]
+        );
+
+        // point 2 is never merged
+        let (clusters, noise) = dendrogram.cut_at(1);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[&0], [0, 1, 3, 4]);
+        assert_eq!(clusters[&1], [2]);
+        assert!(noise.is_empty());
+        assert_eq!(dendrogram.cut_at(4).0.len(), 4);
+        assert_eq!(dendrogram.cut_at(9).0.len(), 5);
+        assert_eq!(dendrogram.cut_at_distance(1.).0.len(), 4);
+        assert_eq!(dendrogram.cut_at_distance(0.5).0.len(), 5);
+
+        let empty = Dendrogram::<f64>::from_edges(0, &[]);
+        assert_eq!(empty, Dendrogram::default());
+        assert!(empty.cut_at(2).0.is_empty());
+    }
+
+    #[test]
+    fn export() {
+        let forest = Dendrogram::from_edges(4, &[(2, 3, 0.5), (0, 2, f64::INFINITY)]);
+        let mut newick = Vec::new();
+        forest
+            .write_newick(&mut newick)
+            .expect("writing to a vector");
+        assert_eq!(
+            String::from_utf8(newick).expect("valid UTF-8"),
+            "(1,(0:inf,(2:0.5,3:0.5):inf));"
+        );
+        let mut json = Vec::new();
+        forest.write_json(&mut json).expect("writing to a vector");
+        assert_eq!(
+            String::from_utf8(json).expect("valid UTF-8"),
+            r#"{"n_points":4,"roots":[{"id":1,"distance":0,"size":1},{"id":5,"distance":null,"size":3,"children":[{"id":0,"distance":0,"size":1},{"id":4,"distance":0.5,"size":2,"children":[{"id":2,"distance":0,"size":1},{"id":3,"distance":0,"size":1}]}]}]}"#
+        );
+
+        let mut newick = Vec::new();
+        Dendrogram::<f64>::default()
+            .write_newick(&mut newick)
+            .expect("writing to a vector");
+        assert_eq!(newick, b"();");
+
+        // a chain as deep as its number of points
+        let n = 100_000;
+        let edges: Vec<_> = (1..n).map(|i| (i - 1, i, 1.)).collect();
+        let mut json = Vec::new();
+        Dendrogram::from_edges(n, &edges)
+            .write_json(&mut json)
+            .expect("writing to a vector");
+        let json = String::from_utf8(json).expect("valid UTF-8");
+        assert_eq!(json.matches(r#"{"id""#).count(), 2 * n - 1);
+    }
+
+    #[test]
+    fn flatten() {
+        let edges = [
+            (0, 1, 1.),
+            (1, 2, 1.),
+            (2, 3, 1.),
+            (4, 5, 1.),
+            (5, 6, 1.),
+            (6, 7, 1.),
+            (3, 4, 10.),
+        ];
+        let (clusters, noise) = Dendrogram::from_edges(9, &edges).flatten(3);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2, 3], vec![4, 5, 6, 7]]);
+        assert_eq!(noise, [8]);
+
+        assert_eq!(
+            Dendrogram::<f64>::from_edges(1, &[]).flatten(2),
+            (HashMap::new(), vec![0])
+        );
+    }
+}
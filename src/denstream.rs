@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, Data, Ix1, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::parallel::prelude::*;
+
+/// `DenStream` clustering algorithm for evolving data streams.
+///
+/// Points are summarized online by micro-clusters of radius at most `eps`,
+/// whose weights fade exponentially with time, halving every `1 / lambda`
+/// time units, so that the clustering follows the recent data. A
+/// micro-cluster whose weight reaches `beta * mu` is a potential
+/// micro-cluster; lighter ones are outlier micro-clusters, which are dropped
+/// unless they grow as fast as a new potential micro-cluster would. The
+/// macro-clusters are found on demand by [`DenStream::snapshot`], which runs
+/// DBSCAN over the potential micro-clusters.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::DenStream;
+///
+/// let mut stream = DenStream::new(0.5, 3.);
+/// for t in 0..10 {
+///     let t = f64::from(t);
+///     stream.insert(&array![0.1 * t, 0.], t);
+///     stream.insert(&array![10., 0.1 * t], t);
+/// }
+/// let (clusters, noise) = stream.snapshot();
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// assert_eq!(stream.micro_cluster_centers().nrows(), 2);
+/// ```
+///
+/// # References
+/// - Cao, Feng, Martin Ester, Weining Qian, and Aoying Zhou. "Density-based clustering over an evolving data stream with
+///   noise." Proceedings of the 2006 SIAM International Conference on Data Mining (2006): 328-339.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DenStream<A> {
+    /// The maximum radius of a micro-cluster. A point that would make the
+    /// closest micro-cluster larger starts a new one. Potential
+    /// micro-clusters whose centers are within `2 * eps` of each other are
+    /// neighbors in [`DenStream::snapshot`].
+    pub eps: A,
+
+    /// The weight a neighborhood of potential micro-clusters needs for its
+    /// center to be a core micro-cluster in [`DenStream::snapshot`].
+    pub mu: A,
+
+    /// The fraction of `mu` a micro-cluster needs to weigh to be a potential
+    /// micro-cluster. Micro-clusters are pruned only if `beta * mu` is
+    /// greater than one.
+    pub beta: A,
+
+    /// The rate at which the weights of micro-clusters fade: they are
+    /// multiplied by `2^(-lambda * dt)` after `dt` time units.
+    pub lambda: A,
+
+    potential: Vec<MicroCluster<A>>,
+    outliers: Vec<MicroCluster<A>>,
+    n_features: usize,
+    /// The latest timestamp, if any point has been inserted.
+    now: Option<A>,
+    last_pruned: A,
+}
+
+/// A micro-cluster: the faded weight, linear sum and squared sum of its
+/// points.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct MicroCluster<A> {
+    weight: A,
+    linear_sum: Vec<A>,
+    squared_sum: A,
+    /// The time the sums were last faded to.
+    updated: A,
+    created: A,
+}
+
+impl<A> Default for DenStream<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(
+            A::from(0.5_f32).expect("valid float"),
+            A::from(10_u8).expect("valid float"),
+        )
+    }
+}
+
+impl<A> DenStream<A>
+where
+    A: FloatCore,
+{
+    /// Creates `DenStream` with `beta` of 0.5 and `lambda` of 0.25.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::DenStream;
+    ///
+    /// let stream = DenStream::<f64>::new(0.5, 3.);
+    /// assert_eq!(stream.beta, 0.5);
+    /// assert_eq!(stream.lambda, 0.25);
+    /// ```
+    #[must_use]
+    pub fn new(eps: A, mu: A) -> Self {
+        let half = A::one() / (A::one() + A::one());
+        Self {
+            eps,
+            mu,
+            beta: half,
+            lambda: half * half,
+            potential: vec![],
+            outliers: vec![],
+            n_features: 0,
+            now: None,
+            last_pruned: A::zero(),
+        }
+    }
+}
+
+impl<A> DenStream<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Inserts a point that arrived at `timestamp`.
+    ///
+    /// A timestamp earlier than the latest one counts as the latest one.
+    /// Points with a NaN or infinite coordinate are ignored.
+    ///
+    /// # Panics
+    /// Panics if the point has a different number of features than the
+    /// points inserted before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::DenStream;
+    ///
+    /// let mut stream = DenStream::new(0.5, 3.);
+    /// stream.insert(&array![0., 0.], 0.);
+    /// stream.insert(&array![0.1, 0.], 1.);
+    ///
+    /// // both points are in one micro-cluster
+    /// assert_eq!(stream.micro_cluster_centers().nrows(), 1);
+    /// ```
+    pub fn insert<S>(&mut self, point: &ArrayBase<S, Ix1>, timestamp: A)
+    where
+        S: Data<Elem = A>,
+    {
+        let now = match self.now {
+            None => {
+                self.n_features = point.len();
+                self.last_pruned = timestamp;
+                timestamp
+            }
+            Some(now) => FloatCore::max(now, timestamp),
+        };
+        assert_eq!(
+            point.len(),
+            self.n_features,
+            "all points should have the same number of features"
+        );
+        self.now = Some(now);
+        if !point.iter().all(|v| FloatCore::is_finite(*v)) {
+            return;
+        }
+        let point = point.view();
+
+        let squared_eps = self.eps * self.eps;
+        if let Some(i) = closest(&self.potential, &point) {
+            let micro_cluster = &mut self.potential[i];
+            micro_cluster.fade(now, self.lambda);
+            if micro_cluster.squared_radius_with(&point) <= squared_eps {
+                micro_cluster.add(&point);
+                return self.prune();
+            }
+        }
+        match closest(&self.outliers, &point) {
+            Some(i) => {
+                let micro_cluster = &mut self.outliers[i];
+                micro_cluster.fade(now, self.lambda);
+                if micro_cluster.squared_radius_with(&point) <= squared_eps {
+                    micro_cluster.add(&point);
+                    if micro_cluster.weight > self.beta * self.mu {
+                        let micro_cluster = self.outliers.remove(i);
+                        self.potential.push(micro_cluster);
+                    }
+                } else {
+                    self.outliers.push(MicroCluster::new(&point, now));
+                }
+            }
+            None => self.outliers.push(MicroCluster::new(&point, now)),
+        }
+        self.prune();
+    }
+
+    /// Groups the potential micro-clusters into macro-clusters with DBSCAN,
+    /// where two micro-clusters are neighbors if their centers are within
+    /// `2 * eps` of each other, and a micro-cluster is a core micro-cluster
+    /// if the weights of its neighbors, including itself, add up to at least
+    /// `mu`.
+    ///
+    /// Returns the macro-clusters, as indices of the rows of
+    /// [`DenStream::micro_cluster_centers`], and the potential
+    /// micro-clusters that belong to none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::DenStream;
+    ///
+    /// let mut stream = DenStream::new(0.5, 3.);
+    /// for t in 0..5 {
+    ///     stream.insert(&array![0., 0.], f64::from(t));
+    /// }
+    /// let (clusters, noise) = stream.snapshot();
+    ///
+    /// assert_eq!(clusters[&0], [0]);
+    /// assert!(noise.is_empty());
+    /// ```
+    #[must_use]
+    pub fn snapshot(&self) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let centers: Vec<_> = self.potential.iter().map(MicroCluster::center).collect();
+        let weights = self.micro_cluster_weights();
+        let reach = (self.eps + self.eps) * (self.eps + self.eps);
+        let neighborhoods: Vec<Vec<usize>> = centers
+            .par_iter()
+            .map(|c| {
+                (0..centers.len())
+                    .filter(|j| squared_distance(c, &centers[*j]) <= reach)
+                    .collect()
+            })
+            .collect();
+        let is_core: Vec<bool> = neighborhoods
+            .iter()
+            .map(|neighbors| neighbors.iter().fold(A::zero(), |w, j| w + weights[*j]) >= self.mu)
+            .collect();
+
+        let mut labels = vec![None; centers.len()];
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for start in 0..centers.len() {
+            if labels[start].is_some() || !is_core[start] {
+                continue;
+            }
+            let id = clusters.len();
+            let mut members = vec![];
+            let mut to_visit = vec![start];
+            while let Some(cur) = to_visit.pop() {
+                if labels[cur].is_some() {
+                    continue;
+                }
+                labels[cur] = Some(id);
+                members.push(cur);
+                if is_core[cur] {
+                    to_visit.extend(neighborhoods[cur].iter().filter(|j| labels[**j].is_none()));
+                }
+            }
+            members.sort_unstable();
+            clusters.insert(id, members);
+        }
+        let noise = (0..centers.len())
+            .filter(|i| labels[*i].is_none())
+            .collect();
+        (clusters, noise)
+    }
+
+    /// Returns the centers of the potential micro-clusters, one row per
+    /// micro-cluster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::DenStream;
+    ///
+    /// let mut stream = DenStream::new(0.5, 3.);
+    /// stream.insert(&array![0., 0.], 0.);
+    /// stream.insert(&array![0.2, 0.], 0.);
+    ///
+    /// assert_eq!(stream.micro_cluster_centers(), array![[0.1, 0.]]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the shape matches the number of centers
+    pub fn micro_cluster_centers(&self) -> Array2<A> {
+        let centers: Vec<A> = self
+            .potential
+            .iter()
+            .flat_map(MicroCluster::center)
+            .collect();
+        Array2::from_shape_vec((self.potential.len(), self.n_features), centers)
+            .expect("centers of the potential micro-clusters")
+    }
+
+    /// Returns the weight of each potential micro-cluster at the latest
+    /// timestamp, in the same order as [`DenStream::micro_cluster_centers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::DenStream;
+    ///
+    /// let mut stream = DenStream::new(0.5, 3.);
+    /// stream.insert(&array![0., 0.], 0.);
+    /// stream.insert(&array![0., 0.], 0.);
+    /// assert_eq!(stream.micro_cluster_weights(), [2.]);
+    ///
+    /// // weights halve every `1 / lambda` time units
+    /// stream.insert(&array![0., 0.], 4.);
+    /// assert_eq!(stream.micro_cluster_weights(), [2.]);
+    /// ```
+    #[must_use]
+    pub fn micro_cluster_weights(&self) -> Vec<A> {
+        let now = self.now.unwrap_or_else(A::zero);
+        self.potential
+            .iter()
+            .map(|m| m.weight * fading(now - m.updated, self.lambda))
+            .collect()
+    }
+
+    /// Drops the micro-clusters too light to be potential ones, every
+    /// minimal time a potential micro-cluster takes to fade into an outlier
+    /// one.
+    fn prune(&mut self) {
+        let Some(now) = self.now else {
+            return;
+        };
+        let min_weight = self.beta * self.mu;
+        let period = Float::ceil(Float::log2(min_weight / (min_weight - A::one())) / self.lambda);
+        if !(min_weight > A::one()
+            && FloatCore::is_finite(period)
+            && now - self.last_pruned >= period)
+        {
+            return;
+        }
+        self.last_pruned = now;
+
+        let lambda = self.lambda;
+        self.potential.retain_mut(|m| {
+            m.fade(now, lambda);
+            m.weight >= min_weight
+        });
+        self.outliers.retain_mut(|m| {
+            m.fade(now, lambda);
+            // the weight a micro-cluster created at the same time would have
+            // if it had been growing as fast as possible since
+            let expected = (fading(now - m.created + period, lambda) - A::one())
+                / (fading(period, lambda) - A::one());
+            m.weight >= expected
+        });
+    }
+}
+
+/// Inserts each point at the timestamp of its index, and assigns the points
+/// to the macro-clusters of [`DenStream::snapshot`] as `predict` does.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points that do not belong to any cluster.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for DenStream<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.potential.clear();
+        self.outliers.clear();
+        self.now = None;
+        for (i, point) in input.rows().into_iter().enumerate() {
+            self.insert(&point, A::from_usize(i).expect("valid float"));
+        }
+        self.predict(input)
+    }
+}
+
+/// Assigns each point in the given input to the macro-cluster of its closest
+/// potential micro-cluster, if that is within `2 * eps`. Other points are
+/// noise.
+impl<S, A> Predict<ArrayBase<S, Ix2>, (HashMap<usize, Vec<usize>>, Vec<usize>)> for DenStream<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let (macro_clusters, _) = self.snapshot();
+        let mut micro_labels = vec![None; self.potential.len()];
+        for (id, members) in macro_clusters {
+            for m in members {
+                micro_labels[m] = Some(id);
+            }
+        }
+        let reach = (self.eps + self.eps) * (self.eps + self.eps);
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let labels: Vec<_> = rows
+            .into_par_iter()
+            .map(|p| {
+                let i = closest(&self.potential, &p)?;
+                if self.potential[i].squared_distance(&p) <= reach {
+                    micro_labels[i]
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = vec![];
+        for (i, label) in labels.into_iter().enumerate() {
+            match label {
+                Some(id) => clusters.entry(id).or_default().push(i),
+                None => noise.push(i),
+            }
+        }
+        (clusters, noise)
+    }
+}
+
+impl<A> MicroCluster<A>
+where
+    A: AddAssign + Float + FloatCore,
+{
+    fn new(point: &ArrayView1<A>, now: A) -> Self {
+        Self {
+            weight: A::one(),
+            linear_sum: point.to_vec(),
+            squared_sum: point.iter().fold(A::zero(), |acc, v| acc + *v * *v),
+            updated: now,
+            created: now,
+        }
+    }
+
+    /// Fades the sums to time `now`.
+    fn fade(&mut self, now: A, lambda: A) {
+        let factor = fading(now - self.updated, lambda);
+        self.weight = self.weight * factor;
+        for s in &mut self.linear_sum {
+            *s = *s * factor;
+        }
+        self.squared_sum = self.squared_sum * factor;
+        self.updated = now;
+    }
+
+    fn add(&mut self, point: &ArrayView1<A>) {
+        self.weight += A::one();
+        for (s, v) in self.linear_sum.iter_mut().zip(point) {
+            *s += *v;
+            self.squared_sum += *v * *v;
+        }
+    }
+
+    fn center(&self) -> Vec<A> {
+        self.linear_sum.iter().map(|s| *s / self.weight).collect()
+    }
+
+    /// Returns the squared distance from `point` to the center.
+    fn squared_distance(&self, point: &ArrayView1<A>) -> A {
+        self.linear_sum
+            .iter()
+            .zip(point)
+            .fold(A::zero(), |acc, (s, v)| {
+                acc + (*s / self.weight - *v) * (*s / self.weight - *v)
+            })
+    }
+
+    /// Returns the squared radius the micro-cluster would have with `point`.
+    fn squared_radius_with(&self, point: &ArrayView1<A>) -> A {
+        let weight = self.weight + A::one();
+        let (sum, center) = self
+            .linear_sum
+            .iter()
+            .zip(point)
+            .fold((self.squared_sum, A::zero()), |(sum, center), (s, v)| {
+                (sum + *v * *v, center + (*s + *v) * (*s + *v))
+            });
+        sum / weight - center / (weight * weight)
+    }
+}
+
+/// Returns the factor by which weights fade in `dt` time units.
+fn fading<A: Float>(dt: A, lambda: A) -> A {
+    Float::exp2(-lambda * dt)
+}
+
+fn closest<A>(micro_clusters: &[MicroCluster<A>], point: &ArrayView1<A>) -> Option<usize>
+where
+    A: AddAssign + Float + FloatCore,
+{
+    micro_clusters
+        .iter()
+        .map(|m| m.squared_distance(point))
+        .enumerate()
+        .fold(None, |closest: Option<(usize, A)>, (i, d)| match closest {
+            Some((_, best)) if best <= d => closest,
+            _ => Some((i, d)),
+        })
+        .map(|(i, _)| i)
+}
+
+fn squared_distance<A: FloatCore>(a: &[A], b: &[A]) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let stream = DenStream::<f32>::default();
+        assert!((stream.eps - 0.5).abs() < f32::EPSILON);
+        assert!((stream.mu - 10.).abs() < f32::EPSILON);
+        assert!((stream.beta - 0.5).abs() < f32::EPSILON);
+        assert!((stream.lambda - 0.25).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn evolving_stream() {
+        let mut stream = DenStream::new(0.5, 4.);
+        // two sources, then the first one moves away
+        for t in 0..40 {
+            let x = f64::from(t % 5) * 0.05;
+            stream.insert(&array![x, 0.], f64::from(t));
+            stream.insert(&array![x, 10.], f64::from(t));
+        }
+        let (clusters, noise) = stream.snapshot();
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+        let (clusters, noise) = stream.predict(&array![[0.1, 0.], [0.1, 10.], [5., 5.]]);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(noise, [2]);
+
+        for t in 40..80 {
+            let x = f64::from(t % 5) * 0.05;
+            stream.insert(&array![x + 20., 0.], f64::from(t));
+            stream.insert(&array![x, 10.], f64::from(t));
+        }
+        let centers = stream.micro_cluster_centers();
+        assert!(centers.rows().into_iter().all(|c| c[0] > 10. || c[1] > 5.));
+        let (clusters, _) = stream.predict(&array![[0.1, 0.], [20.1, 0.], [0.1, 10.]]);
+        assert_eq!(clusters.len(), 2);
+        assert!(clusters.values().all(|m| m != &[0]));
+
+        // an isolated point stays an outlier micro-cluster and is pruned
+        stream.insert(&array![-50., -50.], 80.);
+        stream.insert(&array![f64::NAN, 0.], 80.);
+        for t in 81..100 {
+            stream.insert(&array![0.1, 10.], f64::from(t));
+        }
+        assert!(stream.outliers.iter().all(|m| m.linear_sum[0] > -1.));
+        assert!(stream.micro_cluster_weights().iter().all(|w| *w >= 2.));
+    }
+
+    #[test]
+    fn fit() {
+        // two sources taking turns
+        let data = Array2::from_shape_fn((60, 2), |(i, _)| if i % 2 == 0 { 0. } else { 8. });
+        let mut stream = DenStream::new(0.5, 2.5);
+        let (clusters, noise) = stream.fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+        assert!(clusters
+            .values()
+            .all(|m| m.iter().all(|i| i % 2 == m[0] % 2)));
+    }
+}
@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use petal_neighbors::BallTree;
+use serde::{Deserialize, Serialize};
+
+/// The number of points sampled to estimate the relative contrast.
+const SAMPLE_SIZE: usize = 128;
+
+/// The relative contrast below which distance-based neighborhoods are
+/// considered meaningless.
+pub const MIN_RELATIVE_CONTRAST: f64 = 0.5;
+
+/// The error returned by [`check_dimensionality`] when the distances between
+/// points are too concentrated for eps-ball neighborhoods to be meaningful.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionalityError {
+    /// The number of dimensions of the input.
+    pub dimensions: usize,
+
+    /// The mean relative contrast `(d_max - d_min) / d_min` of the distances
+    /// from each sampled point to the other sampled points.
+    pub relative_contrast: f64,
+}
+
+impl fmt::Display for DimensionalityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "distances in {} dimensions have a relative contrast of {:.3} (below {}), so \
+             eps-ball neighborhoods are unlikely to be meaningful; consider reducing the \
+             dimensionality (e.g. with `SpectralEmbedding`), a cosine metric, approximate \
+             nearest neighbors, or HDBSCAN, which adapts to the local density",
+            self.dimensions, self.relative_contrast, MIN_RELATIVE_CONTRAST
+        )
+    }
+}
+
+impl std::error::Error for DimensionalityError {}
+
+/// Checks whether the input has so many dimensions that the nearest and the
+/// farthest neighbors of a point are nearly equidistant under `metric`.
+///
+/// As the dimensionality grows, the distances between points concentrate
+/// around their mean, and DBSCAN and OPTICS return noise or one large cluster
+/// for any `eps`. This estimates the mean relative contrast of the distances
+/// on a sample of the input, and can be called before fitting to fail fast
+/// instead of silently returning meaningless clusters.
+///
+/// # Errors
+/// Returns [`DimensionalityError`] if the relative contrast is below
+/// [`MIN_RELATIVE_CONTRAST`].
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::check_dimensionality;
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// assert!(check_dimensionality(&points, &Euclidean::default()).is_ok());
+/// ```
+///
+/// # References
+/// - Beyer, Kevin, et al. "When is “nearest neighbor” meaningful?"
+///   International Conference on Database Theory (1999): 217-235.
+pub fn check_dimensionality<S, A, M>(
+    input: &ArrayBase<S, Ix2>,
+    metric: &M,
+) -> Result<(), DimensionalityError>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+    M: Metric<A>,
+{
+    let n = input.nrows();
+    if n < 3 {
+        return Ok(());
+    }
+    let step = n.div_ceil(SAMPLE_SIZE);
+    let sample: Vec<_> = input.rows().into_iter().step_by(step).collect();
+
+    let mut total = 0.;
+    let mut count = 0_u32;
+    for (i, p) in sample.iter().enumerate() {
+        let (min, max) = sample
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, q)| metric.distance(p, q))
+            .fold((A::infinity(), A::zero()), |(min, max), d| {
+                (min.min(d), max.max(d))
+            });
+        // duplicates do not tell anything about the concentration
+        if min > A::zero() && min.is_finite() {
+            total += ((max - min) / min).to_f64().unwrap_or(f64::INFINITY);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Ok(());
+    }
+
+    let relative_contrast = total / f64::from(count);
+    if relative_contrast < MIN_RELATIVE_CONTRAST {
+        Err(DimensionalityError {
+            dimensions: input.ncols(),
+            relative_contrast,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// The error returned by [`check_input`] when a point cannot be clustered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputError {
+    /// A coordinate is NaN or infinite, so that the distances from the point
+    /// to the others are undefined.
+    NonFinite { row: usize, column: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFinite { row, column } => write!(
+                f,
+                "the value at row {row}, column {column} is not finite, so the distances from \
+                 the point are undefined"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Checks that every coordinate of the input is finite.
+///
+/// `Dbscan`, `Optics` and `HDbscan` report points with a NaN or infinite
+/// coordinate as noise, as they have no neighbors, and leave them out of the
+/// ball trees they search, whose bounds would otherwise be undefined. This
+/// can be called before fitting to reject such input instead.
+///
+/// Other degenerate inputs are valid: duplicate points are at distance zero
+/// from each other, a single point is noise unless `min_samples` is one, and
+/// dimensions with zero variance do not change any distance.
+///
+/// # Errors
+/// Returns [`InputError::NonFinite`] with the first non-finite coordinate,
+/// in row-major order.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{check_input, InputError};
+///
+/// assert!(check_input(&array![[1., 2.], [1., 2.]]).is_ok());
+/// assert_eq!(
+///     check_input(&array![[1., 2.], [f64::NAN, 2.]]),
+///     Err(InputError::NonFinite { row: 1, column: 0 })
+/// );
+/// ```
+pub fn check_input<S, A>(input: &ArrayBase<S, Ix2>) -> Result<(), InputError>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    for (row, point) in input.rows().into_iter().enumerate() {
+        if let Some(column) = point.iter().position(|v| !v.is_finite()) {
+            return Err(InputError::NonFinite { row, column });
+        }
+    }
+    Ok(())
+}
+
+/// Returns the indices of the rows of `input` whose coordinates are all
+/// finite, or `None` if every row is.
+pub(crate) fn finite_rows<S, A>(input: &ArrayBase<S, Ix2>) -> Option<Vec<usize>>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    if check_input(input).is_ok() {
+        return None;
+    }
+    let rows = input.rows().into_iter().enumerate();
+    Some(
+        rows.filter(|(_, point)| point.iter().all(|v| v.is_finite()))
+            .map(|(i, _)| i)
+            .collect(),
+    )
+}
+
+/// A ball tree of the points of an input whose coordinates are all finite.
+///
+/// The bounds of the nodes of a `BallTree` are undefined if a point has a
+/// non-finite coordinate, which breaks its searches, so such points are left
+/// out and have no neighbors, unless the metric compares them, as
+/// [`NanEuclidean`](crate::NanEuclidean) compares points with missing
+/// values. Those points are searched exhaustively.
+pub(crate) struct FiniteBallTree<'a, A, M> {
+    tree: Option<BallTree<'a, A, M>>,
+
+    /// The index in the input of each point of `tree`, if some points are
+    /// left out.
+    rows: Option<Vec<usize>>,
+
+    /// The points left out of `tree` that the metric compares.
+    comparable: Vec<usize>,
+
+    input: ArrayView2<'a, A>,
+
+    /// The metric, if there is no `tree` to hold it.
+    metric: Option<M>,
+}
+
+impl<'a, A, M> FiniteBallTree<'a, A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive,
+    M: Metric<A>,
+{
+    /// Builds the tree of the finite points of `input`, which is in standard
+    /// layout.
+    pub(crate) fn new(input: &ArrayView2<'a, A>, metric: M) -> Self {
+        let Some(rows) = finite_rows(input) else {
+            return Self {
+                tree: BallTree::new(*input, metric).ok(),
+                rows: None,
+                comparable: Vec::new(),
+                input: *input,
+                metric: None,
+            };
+        };
+        let mut finite = vec![false; input.nrows()];
+        for &i in &rows {
+            finite[i] = true;
+        }
+        let comparable = (0..input.nrows())
+            .filter(|i| !finite[*i] && compares(&metric, &input.row(*i)))
+            .collect();
+        let (tree, metric) = if rows.is_empty() {
+            (None, Some(metric))
+        } else {
+            (
+                BallTree::new(input.select(Axis(0), &rows), metric).ok(),
+                None,
+            )
+        };
+        Self {
+            tree,
+            rows: Some(rows),
+            comparable,
+            input: *input,
+            metric,
+        }
+    }
+
+    /// Returns the indices, in the input, of the points within `eps` of
+    /// `point`, or none if `point` has a non-finite coordinate that the
+    /// metric does not compare.
+    pub(crate) fn query_radius(&self, point: &ArrayView1<A>, eps: A) -> Vec<usize> {
+        let finite = point.iter().all(|v| v.is_finite());
+        let mut neighbors = match (&self.tree, finite) {
+            (Some(tree), true) => {
+                let neighbors = tree.query_radius(point, eps);
+                match &self.rows {
+                    None => neighbors,
+                    Some(rows) => neighbors.into_iter().map(|i| rows[i]).collect(),
+                }
+            }
+            _ => Vec::new(),
+        };
+        let Some(metric) = self
+            .tree
+            .as_ref()
+            .map(|t| &t.metric)
+            .or(self.metric.as_ref())
+        else {
+            return neighbors;
+        };
+        let within = |j: &usize| metric.distance(point, &self.input.row(*j)) <= eps;
+        if finite {
+            neighbors.extend(self.comparable.iter().copied().filter(within));
+        } else if compares(metric, point) {
+            neighbors = (0..self.input.nrows()).filter(within).collect();
+        }
+        neighbors
+    }
+
+    /// Returns the indices, in the input, of the `k` points nearest to
+    /// `point`, or fewer if fewer points have a distance to it, and their
+    /// distances, in increasing order of distance. A point with a non-finite
+    /// coordinate that the metric does not compare has no distance to any
+    /// point.
+    pub(crate) fn query(&self, point: &ArrayView1<A>, k: usize) -> (Vec<usize>, Vec<A>) {
+        let finite = point.iter().all(|v| v.is_finite());
+        let mut nearest: Vec<(usize, A)> = match (&self.tree, finite) {
+            (Some(tree), true) => {
+                let n = self.rows.as_ref().map_or(self.input.nrows(), Vec::len);
+                let (indices, distances) = tree.query(point, k.min(n));
+                let indices = indices.into_iter().map(|i| match &self.rows {
+                    None => i,
+                    Some(rows) => rows[i],
+                });
+                indices.zip(distances).collect()
+            }
+            _ => Vec::new(),
+        };
+        let Some(metric) = self
+            .tree
+            .as_ref()
+            .map(|t| &t.metric)
+            .or(self.metric.as_ref())
+        else {
+            return nearest.into_iter().unzip();
+        };
+        let distance = |j: usize| (j, metric.distance(point, &self.input.row(j)));
+        if finite {
+            nearest.extend(self.comparable.iter().map(|j| distance(*j)));
+        } else if compares(metric, point) {
+            nearest = (0..self.input.nrows()).map(distance).collect();
+        }
+        nearest.retain(|(_, d)| !d.is_nan());
+        nearest.sort_by(|a, b| {
+            a.1.partial_cmp(&b.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        });
+        nearest.truncate(k);
+        nearest.into_iter().unzip()
+    }
+}
+
+/// Returns whether `metric` compares `point`, which has a non-finite
+/// coordinate, instead of returning NaN.
+fn compares<A, M>(metric: &M, point: &ArrayView1<A>) -> bool
+where
+    A: FloatCore,
+    M: Metric<A>,
+{
+    metric.distance(point, point).is_finite()
+}
+
+/// A degenerate clustering, which is rarely what the parameters were meant
+/// to produce.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Degeneracy {
+    /// There are fewer points than `min_samples`, so that no point can be a
+    /// core point and every point is noise for any `eps`.
+    TooFewPoints { n_points: usize, min_samples: usize },
+
+    /// Every point is noise.
+    AllNoise { n_points: usize },
+
+    /// There is only one cluster, with `size` of the `n_points` points.
+    SingleCluster { n_points: usize, size: usize },
+}
+
+impl Degeneracy {
+    /// Returns the degeneracy of the clustering of `n_points` points into
+    /// `clusters` and `noise`, if any. An empty input, or a single point in
+    /// a cluster, is not degenerate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Degeneracy;
+    ///
+    /// assert_eq!(
+    ///     Degeneracy::detect(4, 2, &hashmap! {}, &[0, 1, 2, 3]),
+    ///     Some(Degeneracy::AllNoise { n_points: 4 })
+    /// );
+    /// assert_eq!(
+    ///     Degeneracy::detect(4, 2, &hashmap! {0 => vec![0, 1, 2]}, &[3]),
+    ///     Some(Degeneracy::SingleCluster { n_points: 4, size: 3 })
+    /// );
+    /// assert_eq!(Degeneracy::detect(4, 2, &hashmap! {0 => vec![0, 1], 1 => vec![2, 3]}, &[]), None);
+    ///
+    /// ```
+    #[must_use]
+    #[allow(clippy::implicit_hasher)]
+    pub fn detect(
+        n_points: usize,
+        min_samples: usize,
+        clusters: &HashMap<usize, Vec<usize>>,
+        noise: &[usize],
+    ) -> Option<Self> {
+        if n_points == 0 {
+            return None;
+        }
+        if n_points < min_samples {
+            return Some(Self::TooFewPoints {
+                n_points,
+                min_samples,
+            });
+        }
+        if noise.len() == n_points {
+            return Some(Self::AllNoise { n_points });
+        }
+        match clusters.values().next() {
+            Some(members) if clusters.len() == 1 && n_points > 1 => Some(Self::SingleCluster {
+                n_points,
+                size: members.len(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Degeneracy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewPoints {
+                n_points,
+                min_samples,
+            } => write!(
+                f,
+                "{n_points} points are fewer than min_samples ({min_samples}), so every point \
+                 is noise"
+            ),
+            Self::AllNoise { n_points } => {
+                write!(f, "all {n_points} points are noise; consider a larger eps")
+            }
+            Self::SingleCluster { n_points, size } => write!(
+                f,
+                "a single cluster has {size} of {n_points} points; consider a smaller eps"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Degeneracy {}
+
+/// What to do when a fit is [degenerate](Degeneracy).
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum DegeneracyPolicy<A> {
+    /// Returns the degenerate result, with the degeneracy as a warning.
+    #[default]
+    Accept,
+
+    /// Fails with the degeneracy as an error.
+    Reject,
+
+    /// Fits once more with `eps` multiplied by the factor, which should be
+    /// greater than one, if every point is noise, or divided by it if there
+    /// is a single cluster. The second result is returned even if it is
+    /// degenerate, with its degeneracy as a warning. Nothing is refitted if
+    /// there are fewer points than `min_samples`.
+    RelaxEps(A),
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+    use ndarray::{array, Array2};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    fn uniform(n: usize, dim: usize) -> Array2<f64> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        Array2::from_shape_simple_fn((n, dim), || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            #[allow(clippy::cast_precision_loss)]
+            let v = (state >> 11) as f64 / (1_u64 << 53) as f64;
+            v
+        })
+    }
+
+    #[test]
+    fn low_dimensional() {
+        let data = array![
+            [1., 2.],
+            [2., 2.],
+            [2., 2.3],
+            [8., 7.],
+            [8., 8.],
+            [25., 80.]
+        ];
+        assert!(check_dimensionality(&data, &Euclidean::default()).is_ok());
+        assert!(check_dimensionality(&uniform(200, 3), &Euclidean::default()).is_ok());
+    }
+
+    #[test]
+    fn high_dimensional() {
+        let err = check_dimensionality(&uniform(200, 1000), &Euclidean::default())
+            .expect_err("distances should be concentrated");
+        assert_eq!(err.dimensions, 1000);
+        assert!(err.relative_contrast < MIN_RELATIVE_CONTRAST);
+        assert!(err.to_string().contains("HDBSCAN"));
+    }
+
+    #[test]
+    fn small_or_duplicate_input() {
+        let data = array![[1., 2.], [1., 2.]];
+        assert!(check_dimensionality(&data, &Euclidean::default()).is_ok());
+        let data = array![[1., 2.], [1., 2.], [1., 2.]];
+        assert!(check_dimensionality(&data, &Euclidean::default()).is_ok());
+    }
+
+    #[test]
+    fn check_input() {
+        assert_eq!(super::check_input(&array![[1., 2.], [1., 2.]]), Ok(()));
+        assert_eq!(super::check_input(&Array2::<f32>::zeros((0, 3))), Ok(()));
+        let error = super::check_input(&array![[1., 2.], [3., f64::INFINITY], [f64::NAN, 0.]]);
+        assert_eq!(error, Err(InputError::NonFinite { row: 1, column: 1 }));
+        assert!(error.unwrap_err().to_string().contains("row 1, column 1"));
+
+        assert_eq!(finite_rows(&array![[1., 2.], [3., 4.]]), None);
+        assert_eq!(
+            finite_rows(&array![[1., f64::NAN], [3., 4.], [f64::INFINITY, 0.]]),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn degeneracy() {
+        let clusters = hashmap! {0 => vec![0, 1], 1 => vec![2, 3]};
+        assert_eq!(Degeneracy::detect(5, 2, &clusters, &[4]), None);
+        assert_eq!(Degeneracy::detect(0, 2, &HashMap::new(), &[]), None);
+        assert_eq!(
+            Degeneracy::detect(3, 5, &HashMap::new(), &[0, 1, 2]),
+            Some(Degeneracy::TooFewPoints {
+                n_points: 3,
+                min_samples: 5
+            })
+        );
+        assert_eq!(
+            Degeneracy::detect(3, 2, &HashMap::new(), &[0, 1, 2]),
+            Some(Degeneracy::AllNoise { n_points: 3 })
+        );
+        let clusters = hashmap! {0 => vec![0, 1, 2]};
+        let degeneracy = Degeneracy::detect(4, 2, &clusters, &[3]).unwrap();
+        assert_eq!(
+            degeneracy,
+            Degeneracy::SingleCluster {
+                n_points: 4,
+                size: 3
+            }
+        );
+        assert!(degeneracy.to_string().contains("smaller eps"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use ndarray::ArrayView2;
+use petal_neighbors::distance::Metric;
+
+use crate::{Fit, FuzzyCMeans, GaussianMixture, HDbscan, Partition};
+
+/// A clustering algorithm that can be stored as a trait object, so that the
+/// algorithm can be chosen at runtime, e.g. from a configuration file.
+///
+/// [`Fit`] is generic over its input, parameters and output, so estimators
+/// with different outputs cannot be boxed behind one type. This trait fixes
+/// the input to an array of `f64` and the output to a [`Partition`], and is
+/// implemented by every estimator fitted to such an array:
+/// - those whose `fit` returns clusters and noise, such as
+///   [`KMeans`](crate::KMeans) and [`Dbscan`](crate::Dbscan);
+/// - [`HDbscan`], ignoring the outlier scores;
+/// - [`GaussianMixture`] and [`FuzzyCMeans`], assigning each point to its
+///   most likely cluster.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, DynClusterer, HDbscan, KMeans};
+///
+/// fn clusterer(name: &str) -> Box<dyn DynClusterer> {
+///     match name {
+///         "dbscan" => Box::new(Dbscan::new(1., 2, Euclidean::default())),
+///         "hdbscan" => Box::new(HDbscan::new(2, Euclidean::default())),
+///         _ => Box::new(KMeans::new(2)),
+///     }
+/// }
+///
+/// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+/// for name in ["dbscan", "hdbscan", "kmeans"] {
+///     let partition = clusterer(name).cluster(&points.view());
+///     assert_eq!(partition.len(), 2);
+/// }
+/// ```
+pub trait DynClusterer {
+    /// Fits the algorithm to the given input, one point per row, and returns
+    /// its clusters and noise.
+    fn cluster(&mut self, input: &ArrayView2<'_, f64>) -> Partition;
+}
+
+impl<T> DynClusterer for T
+where
+    T: for<'a> Fit<
+        ArrayView2<'a, f64>,
+        HashMap<usize, Vec<usize>>,
+        (HashMap<usize, Vec<usize>>, Vec<usize>),
+    >,
+{
+    fn cluster(&mut self, input: &ArrayView2<'_, f64>) -> Partition {
+        Partition::from(self.fit(input, None))
+    }
+}
+
+impl<M> DynClusterer for HDbscan<f64, M>
+where
+    M: Metric<f64> + Clone + Sync + Send,
+{
+    fn cluster(&mut self, input: &ArrayView2<'_, f64>) -> Partition {
+        let (clusters, noise, _) = self.fit(input, None);
+        Partition::new(clusters, noise)
+    }
+}
+
+impl DynClusterer for GaussianMixture<f64> {
+    fn cluster(&mut self, input: &ArrayView2<'_, f64>) -> Partition {
+        let (_, labels) = self.fit(input, None);
+        from_labels(labels)
+    }
+}
+
+impl DynClusterer for FuzzyCMeans<f64> {
+    fn cluster(&mut self, input: &ArrayView2<'_, f64>) -> Partition {
+        let (_, labels) = self.fit(input, None);
+        from_labels(labels)
+    }
+}
+
+/// Groups the points by label, with no noise.
+fn from_labels(labels: Vec<usize>) -> Partition {
+    let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, label) in labels.into_iter().enumerate() {
+        clusters.entry(label).or_default().push(i);
+    }
+    Partition::new(clusters, vec![])
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{AgglomerativeClustering, Dbscan, KMeans, Linkage};
+
+    #[test]
+    fn dyn_clusterers() {
+        let data = array![
+            [1., 2.],
+            [1.2, 2.],
+            [1., 2.3],
+            [8., 7.],
+            [8., 8.],
+            [8.3, 7.5],
+            [50., 50.]
+        ];
+        let mut clusterers: Vec<Box<dyn DynClusterer>> = vec![
+            Box::new(KMeans::new(2)),
+            Box::new(Dbscan::new(1., 2, Euclidean::default())),
+            Box::new(HDbscan::new(2, Euclidean::default())),
+            Box::new(AgglomerativeClustering::new(
+                2,
+                Linkage::Single,
+                Euclidean::default(),
+            )),
+            Box::new(GaussianMixture::new(2)),
+            Box::new(FuzzyCMeans::new(2)),
+        ];
+        for clusterer in &mut clusterers {
+            let partition = clusterer.cluster(&data.view());
+            let assigned = partition.iter().map(|(_, m)| m.len()).sum::<usize>();
+            assert_eq!(assigned + partition.noise().len(), data.nrows());
+            assert!(partition.len() >= 2);
+        }
+    }
+}
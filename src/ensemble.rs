@@ -0,0 +1,339 @@
+//! Consensus clustering, which combines several clusterings of the same
+//! points into one that is more stable than each of them.
+//!
+//! The clusterings can come from different algorithms, or from the same
+//! algorithm with different parameters or seeds. They are summarized in a
+//! co-association matrix, whose entry `(i, j)` is the fraction of the
+//! clusterings in which points `i` and `j` are in the same cluster, and
+//! [`Consensus`] clusters the points hierarchically with one minus the
+//! co-association as the distance. Points that the clusterings group by
+//! chance, such as noise that each run assigns differently, are rarely
+//! together and end up apart, while the cores of the clusters stay together.
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::ensemble::Consensus;
+//! use petal_clustering::{Dbscan, Fit, KMeans, Partition};
+//!
+//! let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+//! let partitions = [
+//!     Partition::from(KMeans::new(2).fit(&points, None)),
+//!     Partition::from(Dbscan::new(1., 2, Euclidean::default()).fit(&points, None)),
+//!     Partition::from(Dbscan::new(0.6, 2, Euclidean::default()).fit(&points, None)),
+//! ];
+//! let mut consensus = Consensus::new();
+//! let (clusters, noise) = consensus.fit_partitions(&partitions, points.nrows());
+//!
+//! assert_eq!(clusters[&0], [0, 1, 2]);
+//! assert_eq!(clusters[&1], [3, 4, 5]);
+//! assert!(noise.is_empty());
+//! ```
+//!
+//! # References
+//! - Fred, Ana L. N., and Anil K. Jain. "Combining multiple clusterings using evidence accumulation."
+//!   IEEE Transactions on Pattern Analysis and Machine Intelligence 27.6 (2005): 835-850.
+
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+use petal_neighbors::distance::Euclidean;
+use serde::{Deserialize, Serialize};
+
+use crate::{AgglomerativeClustering, Fit, Linkage, Partition};
+
+/// Returns the co-association matrix of the given clusterings of `n_points`
+/// points, whose entry `(i, j)` is the fraction of the clusterings in which
+/// points `i` and `j` are in the same cluster. A noise point is in no
+/// cluster, so it is only associated with itself.
+///
+/// # Panics
+/// Panics if a clustering refers to a point not less than `n_points`.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use petal_clustering::ensemble::co_association;
+/// use petal_clustering::Partition;
+///
+/// let first = Partition::new(HashMap::from([(0, vec![0, 1]), (1, vec![2])]), vec![]);
+/// let second = Partition::new(HashMap::from([(0, vec![0, 1, 2])]), vec![]);
+/// let matrix = co_association(&[first, second], 3);
+/// assert_eq!(matrix[[0, 1]], 1.);
+/// assert_eq!(matrix[[1, 2]], 0.5);
+/// ```
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn co_association(partitions: &[Partition], n_points: usize) -> Array2<f64> {
+    let mut matrix = Array2::<f64>::zeros((n_points, n_points));
+    matrix.diag_mut().fill(1.);
+    if partitions.is_empty() {
+        return matrix;
+    }
+    let weight = 1. / partitions.len() as f64;
+    for partition in partitions {
+        for (_, members) in partition.iter() {
+            for (k, i) in members.iter().enumerate() {
+                for j in &members[k + 1..] {
+                    matrix[[*i, *j]] += weight;
+                    matrix[[*j, *i]] += weight;
+                }
+            }
+        }
+    }
+    matrix
+}
+
+/// Consensus clustering by evidence accumulation: agglomerative clustering
+/// of the co-association matrix of several clusterings.
+///
+/// The distance between two points is one minus their co-association, and
+/// groups of points are merged under `linkage` until `n_clusters` clusters
+/// remain, or, if `n_clusters` is `None`, while their distance is at most
+/// `distance_threshold`. With [`Linkage::Average`] and the default threshold
+/// of 0.5, two groups are merged if their points are in the same cluster in
+/// at least half of the clusterings on average.
+///
+/// Points that are noise in more than half of the clusterings are noise in
+/// the consensus.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Consensus {
+    /// The distance between groups of points, computed from the distances
+    /// between their points. [`Linkage::Ward`] is not supported, as the
+    /// points have no coordinates, and is replaced by
+    /// [`Linkage::Average`].
+    pub linkage: Linkage,
+
+    /// The number of clusters to form. If set, it overrides
+    /// `distance_threshold`.
+    pub n_clusters: Option<usize>,
+
+    /// The largest distance, between zero and one, at which groups of points
+    /// are merged.
+    pub distance_threshold: f64,
+
+    co_association: Vec<f64>,
+    n_points: usize,
+}
+
+impl Default for Consensus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consensus {
+    /// Creates a model merging groups of points under [`Linkage::Average`] while
+    /// their distance is at most 0.5.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::ensemble::Consensus;
+    /// use petal_clustering::Linkage;
+    ///
+    /// let consensus = Consensus::new();
+    /// assert_eq!(consensus.linkage, Linkage::Average);
+    /// assert_eq!(consensus.n_clusters, None);
+    /// assert_eq!(consensus.distance_threshold, 0.5);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            linkage: Linkage::Average,
+            n_clusters: None,
+            distance_threshold: 0.5,
+            co_association: vec![],
+            n_points: 0,
+        }
+    }
+
+    /// Returns the co-association matrix of the clusterings combined by the
+    /// last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use petal_clustering::ensemble::Consensus;
+    /// use petal_clustering::Partition;
+    ///
+    /// let first = Partition::new(HashMap::from([(0, vec![0, 1]), (1, vec![2])]), vec![]);
+    /// let second = Partition::new(HashMap::from([(0, vec![0, 1, 2])]), vec![]);
+    /// let mut consensus = Consensus::new();
+    /// consensus.fit_partitions(&[first, second], 3);
+    ///
+    /// assert_eq!(consensus.co_association()[[0, 1]], 1.);
+    /// assert_eq!(consensus.co_association()[[0, 2]], 0.5);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the matrix is square
+    pub fn co_association(&self) -> ArrayView2<'_, f64> {
+        ArrayView2::from_shape((self.n_points, self.n_points), &self.co_association)
+            .expect("square co-association matrix")
+    }
+
+    /// Combines the given clusterings of `n_points` points into a consensus
+    /// clustering.
+    ///
+    /// # Returns
+    /// A tuple containing:
+    /// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster. Clusters are
+    ///   numbered in the order of their first point.
+    /// - `Vec<usize>`: A vector of indices representing the noise points.
+    ///
+    /// # Panics
+    /// Panics if a clustering refers to a point not less than `n_points`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use petal_clustering::ensemble::Consensus;
+    /// use petal_clustering::Partition;
+    ///
+    /// let clusterings = [
+    ///     Partition::new(HashMap::from([(0, vec![0, 1]), (1, vec![2, 3])]), vec![4]),
+    ///     Partition::new(HashMap::from([(0, vec![0, 1, 2, 3])]), vec![4]),
+    ///     Partition::new(HashMap::from([(0, vec![0, 1]), (1, vec![2, 3])]), vec![]),
+    /// ];
+    /// let (clusters, noise) = Consensus::new().fit_partitions(&clusterings, 5);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1]);
+    /// assert_eq!(clusters[&1], [2, 3]);
+    /// assert_eq!(noise, [4]);
+    /// ```
+    pub fn fit_partitions(
+        &mut self,
+        partitions: &[Partition],
+        n_points: usize,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let matrix = co_association(partitions, n_points);
+        let mut noise_count = vec![0; n_points];
+        for partition in partitions {
+            for i in partition.noise() {
+                noise_count[*i] += 1;
+            }
+        }
+        let (noise, kept): (Vec<usize>, Vec<usize>) =
+            (0..n_points).partition(|i| 2 * noise_count[*i] > partitions.len());
+
+        let distances = Array2::from_shape_fn((kept.len(), kept.len()), |(i, j)| {
+            1. - matrix[[kept[i], kept[j]]]
+        });
+        let linkage = match self.linkage {
+            Linkage::Ward => Linkage::Average,
+            linkage => linkage,
+        };
+        let mut agglomerative = AgglomerativeClustering::new(
+            self.n_clusters.unwrap_or(1),
+            linkage,
+            Euclidean::default(),
+        );
+        if self.n_clusters.is_none() {
+            agglomerative.distance_threshold = Some(self.distance_threshold);
+        }
+        let (clusters, _) = if kept.is_empty() {
+            (HashMap::new(), vec![])
+        } else {
+            agglomerative.fit_precomputed(&distances)
+        };
+        let clusters = clusters
+            .into_iter()
+            .map(|(id, members)| (id, members.into_iter().map(|i| kept[i]).collect()))
+            .collect();
+
+        self.n_points = n_points;
+        self.co_association = matrix.into_iter().collect();
+        (clusters, noise)
+    }
+
+    /// Fits the clusterers made by `clusterer` for the runs `0..n_runs` to
+    /// the given input, and combines their clusterings. `clusterer` can draw
+    /// different parameters or seeds for each run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::ensemble::Consensus;
+    /// use petal_clustering::KMeans;
+    ///
+    /// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+    /// let mut consensus = Consensus::new();
+    /// let (clusters, _) = consensus.fit_runs(&points, 10, |run| {
+    ///     let mut kmeans = KMeans::new(2 + run % 3);
+    ///     kmeans.seed = run as u64;
+    ///     kmeans
+    /// });
+    /// assert_eq!(clusters.len(), 2);
+    /// ```
+    pub fn fit_runs<S, A, C, F>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        n_runs: usize,
+        mut clusterer: F,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+        C: Fit<
+            ArrayBase<S, Ix2>,
+            HashMap<usize, Vec<usize>>,
+            (HashMap<usize, Vec<usize>>, Vec<usize>),
+        >,
+        F: FnMut(usize) -> C,
+    {
+        let partitions: Vec<_> = (0..n_runs)
+            .map(|run| Partition::from(clusterer(run).fit(input, None)))
+            .collect();
+        self.fit_partitions(&partitions, input.nrows())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consensus() {
+        // two groups, with point 4 between them
+        let partition = |clusters: [(usize, Vec<usize>); 2], noise: Vec<usize>| {
+            Partition::new(HashMap::from(clusters), noise)
+        };
+        let partitions = [
+            partition([(0, vec![0, 1, 4]), (1, vec![2, 3])], vec![5]),
+            partition([(0, vec![0, 1]), (1, vec![2, 3])], vec![4]),
+            partition([(0, vec![0, 1]), (1, vec![2, 3])], vec![4, 5]),
+            partition([(0, vec![1, 0]), (1, vec![3, 2, 5])], vec![4]),
+        ];
+        let mut consensus = Consensus::new();
+        let (clusters, noise) = consensus.fit_partitions(&partitions, 6);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[&0], [0, 1]);
+        assert_eq!(clusters[&1], [2, 3]);
+        assert_eq!(clusters[&2], [5]);
+        assert_eq!(noise, [4]);
+        assert!(f64::abs(consensus.co_association()[[0, 4]] - 0.25) < 1e-12);
+
+        consensus.n_clusters = Some(1);
+        let (clusters, _) = consensus.fit_partitions(&partitions, 6);
+        assert_eq!(clusters[&0], [0, 1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn no_partitions() {
+        let mut consensus = Consensus::new();
+        let (clusters, noise) = consensus.fit_partitions(&[], 2);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+
+        let (clusters, noise) = consensus.fit_partitions(&[], 0);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
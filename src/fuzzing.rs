@@ -0,0 +1,148 @@
+//! Internal functions exposed to the fuzz targets in `fuzz/`.
+//!
+//! This module is only available with the `fuzzing` feature, and is not
+//! covered by semantic versioning.
+
+use std::collections::HashMap;
+
+use ndarray::ArrayView1;
+use petal_neighbors::distance::Euclidean;
+
+use crate::{hdbscan, mst, ClusterExtraction, Optics};
+
+/// Extracts OPTICS clusters at `eps` from an arbitrary cluster ordering.
+///
+/// `neighborhoods` holds the neighbors and the core distance of each point.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::fuzzing::optics_extract_clusters_and_noise;
+///
+/// let neighborhoods = vec![(vec![0, 1], 1.), (vec![0, 1], 1.), (vec![2], f64::INFINITY)];
+/// let (clusters, noise) = optics_extract_clusters_and_noise(
+///     2.,
+///     2,
+///     vec![0, 1, 2],
+///     vec![f64::INFINITY, 1., f64::INFINITY],
+///     neighborhoods,
+/// );
+///
+/// assert_eq!(clusters[&0], [0, 1]);
+/// assert_eq!(noise, [2]);
+/// ```
+#[must_use]
+pub fn optics_extract_clusters_and_noise(
+    eps: f64,
+    min_samples: usize,
+    ordered: Vec<usize>,
+    reachability: Vec<f64>,
+    neighborhoods: Vec<(Vec<usize>, f64)>,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    let optics = Optics::from_parts(
+        eps,
+        min_samples,
+        Euclidean::default(),
+        ordered,
+        reachability,
+        neighborhoods,
+    );
+    optics.extract_clusters_and_noise(eps)
+}
+
+/// Labels the merges of a minimum spanning tree sorted by edge weight, as
+/// `(parent, child, distance, child size)` tuples.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::fuzzing::hdbscan_label;
+///
+/// let labeled = hdbscan_label(&[(0, 1, 1.), (1, 2, 2.)]);
+/// // points 0 and 1 merge into cluster 3, which merges with point 2 into cluster 4
+/// assert_eq!(
+///     labeled,
+///     [(3, 0, 1., 1), (3, 1, 1., 1), (4, 3, 2., 2), (4, 2, 2., 1)]
+/// );
+/// ```
+#[must_use]
+pub fn hdbscan_label(mst: &[(usize, usize, f64)]) -> Vec<(usize, usize, f64, usize)> {
+    hdbscan::label(mst)
+}
+
+/// Condenses a labeled hierarchy returned by [`hdbscan_label`].
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::fuzzing::{hdbscan_condense_mst, hdbscan_label};
+///
+/// let labeled = hdbscan_label(&[(0, 1, 1.), (1, 2, 1.), (3, 4, 1.), (4, 5, 1.), (2, 3, 10.)]);
+/// let condensed = hdbscan_condense_mst(&labeled, 3);
+///
+/// // the root splits into two clusters of three points
+/// let children: Vec<_> = condensed.iter().filter(|c| c.3 == 3).collect();
+/// assert_eq!(children.len(), 2);
+/// ```
+#[must_use]
+pub fn hdbscan_condense_mst(
+    labeled: &[(usize, usize, f64, usize)],
+    min_cluster_size: usize,
+) -> Vec<(usize, usize, f64, usize)> {
+    mst::condense_mst(labeled, min_cluster_size)
+}
+
+/// Selects clusters from a condensed tree returned by
+/// [`hdbscan_condense_mst`].
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::fuzzing::{hdbscan_condense_mst, hdbscan_find_clusters, hdbscan_label};
+/// use petal_clustering::ClusterExtraction;
+///
+/// let labeled = hdbscan_label(&[(0, 1, 1.), (1, 2, 1.), (3, 4, 1.), (4, 5, 1.), (2, 3, 10.)]);
+/// let condensed = hdbscan_condense_mst(&labeled, 3);
+/// let (clusters, noise) =
+///     hdbscan_find_clusters(&condensed, None, ClusterExtraction::ExcessOfMass, 0.);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn hdbscan_find_clusters(
+    condensed: &[(usize, usize, f64, usize)],
+    partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+    extraction: ClusterExtraction,
+    cluster_selection_epsilon: f64,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    hdbscan::find_clusters(
+        &ArrayView1::from(condensed),
+        partial_labels,
+        extraction,
+        cluster_selection_epsilon,
+    )
+}
+
+/// Computes the GLOSH outlier scores from a condensed tree returned by
+/// [`hdbscan_condense_mst`].
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::fuzzing::{hdbscan_condense_mst, hdbscan_glosh, hdbscan_label};
+///
+/// let labeled = hdbscan_label(&[(0, 1, 1.), (1, 2, 1.), (3, 4, 1.), (4, 5, 1.), (2, 3, 10.)]);
+/// let scores = hdbscan_glosh(&hdbscan_condense_mst(&labeled, 3), 3);
+///
+/// assert_eq!(scores.len(), 6);
+/// assert!(scores.iter().all(|s| (0. ..=1.).contains(s)));
+/// ```
+#[must_use]
+pub fn hdbscan_glosh(
+    condensed: &[(usize, usize, f64, usize)],
+    min_cluster_size: usize,
+) -> Vec<f64> {
+    hdbscan::glosh(condensed, min_cluster_size)
+}
@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::kmeans::init_centroids;
+use crate::parallel::prelude::*;
+
+/// Fuzzy c-means clustering algorithm, which assigns each point a degree of
+/// membership in each cluster.
+///
+/// Like k-means, fuzzy c-means finds convex clusters around centroids, but
+/// instead of assigning each point to its closest centroid, it gives each
+/// point memberships that sum to one over the clusters and decrease with the
+/// distance to their centroids. Each centroid is the mean of the points
+/// weighted by their memberships raised to the power `fuzziness`. The larger
+/// `fuzziness`, the softer the partition; as it approaches one, the
+/// partition becomes that of k-means.
+///
+/// `fit` returns the memberships along with the cluster of the largest
+/// membership of each point, and [`FuzzyCMeans::predict_memberships`]
+/// computes them for new points.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, FuzzyCMeans, Predict};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5], [4.5, 4.8]];
+/// let mut fcm = FuzzyCMeans::new(2);
+/// let (memberships, labels) = fcm.fit(&points, None);
+///
+/// assert_eq!(memberships.dim(), (7, 2));
+/// assert_eq!(labels[0], labels[1]);
+/// assert_ne!(labels[0], labels[3]);
+/// // the point between the clusters belongs to both
+/// assert!(memberships.row(6).iter().all(|u| *u > 0.4));
+/// assert_eq!(fcm.predict(&array![[1., 2.1]]), [labels[0]]);
+/// ```
+///
+/// # References
+/// - Bezdek, James C., Robert Ehrlich, and William Full. "FCM: The fuzzy c-means clustering algorithm."
+///   Computers & Geosciences 10.2-3 (1984): 191-203.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FuzzyCMeans<A> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The fuzziness exponent `m`, greater than one.
+    pub fuzziness: A,
+
+    /// The maximum number of iterations.
+    pub max_iter: usize,
+
+    /// The iterations stop when no membership changes by more than this.
+    pub tol: A,
+
+    /// The seed of the random number generator choosing the initial
+    /// centroids with k-means++.
+    pub seed: u64,
+
+    centroids: Vec<A>,
+    n_features: usize,
+    converged: bool,
+    objective: A,
+}
+
+impl<A> Default for FuzzyCMeans<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl<A> FuzzyCMeans<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model forming `n_clusters` clusters with a fuzziness of 2,
+    /// running at most 300 iterations with a tolerance of `1e-5`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::FuzzyCMeans;
+    ///
+    /// let fcm = FuzzyCMeans::<f64>::new(3);
+    /// assert_eq!(fcm.fuzziness, 2.);
+    /// assert_eq!(fcm.max_iter, 300);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            n_clusters,
+            fuzziness: A::one() + A::one(),
+            max_iter: 300,
+            tol: A::from(1e-5).unwrap_or_else(A::epsilon),
+            seed: 0,
+            centroids: vec![],
+            n_features: 0,
+            converged: false,
+            objective: A::zero(),
+        }
+    }
+
+    /// Returns the centroids of the clusters found by the last fit, one row
+    /// per cluster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, FuzzyCMeans};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut fcm = FuzzyCMeans::new(2);
+    /// fcm.fit(&points, None);
+    ///
+    /// let centroids = fcm.centroids();
+    /// assert_eq!(centroids.dim(), (2, 2));
+    /// assert!(centroids.rows().into_iter().any(|c| (c[0] - 1_f64).abs() < 0.2));
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn centroids(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (
+                self.centroids.len() / self.n_features.max(1),
+                self.n_features,
+            ),
+            &self.centroids,
+        )
+        .expect("centroids of the fitted model")
+    }
+
+    /// Returns whether the last fit converged within `max_iter` iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, FuzzyCMeans};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut fcm = FuzzyCMeans::new(2);
+    /// fcm.fit(&points, None);
+    ///
+    /// assert!(fcm.converged());
+    /// ```
+    #[must_use]
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Returns the sum of the squared distances of the points to the
+    /// centroids, weighted by their memberships raised to the power
+    /// `fuzziness`, in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, FuzzyCMeans};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut two = FuzzyCMeans::new(2);
+    /// two.fit(&points, None);
+    /// let mut one = FuzzyCMeans::new(1);
+    /// one.fit(&points, None);
+    ///
+    /// assert!(two.objective() < one.objective());
+    /// ```
+    #[must_use]
+    pub fn objective(&self) -> A {
+        self.objective
+    }
+}
+
+impl<A> FuzzyCMeans<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Computes the membership of each point in the given input in each
+    /// cluster, one row per point.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, FuzzyCMeans};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut fcm = FuzzyCMeans::new(2);
+    /// let (_, labels) = fcm.fit(&points, None);
+    ///
+    /// let memberships = fcm.predict_memberships(&array![[1., 2.1], [4.5, 4.8]]);
+    /// assert!(memberships[[0, labels[0]]] > 0.9);
+    /// assert!((memberships.row(1).sum() - 1_f64).abs() < 1e-9);
+    ///
+    /// ```
+    #[must_use]
+    pub fn predict_memberships<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(
+            !self.centroids.is_empty(),
+            "`predict_memberships` requires a fitted model"
+        );
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        memberships(&rows, &self.centroids(), self.fuzziness)
+    }
+}
+
+/// Fits the fuzzy c-means clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `Array2<A>`: The memberships, i.e., the degree to which each point (row) belongs to each cluster (column).
+/// - `Vec<usize>`: The cluster in which each point has the largest membership.
+///
+/// # Panics
+/// Panics if `fuzziness` is not greater than one.
+impl<S, A> Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (Array2<A>, Vec<usize>)>
+    for FuzzyCMeans<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (Array2<A>, Vec<usize>) {
+        assert!(
+            self.fuzziness > A::one(),
+            "`fuzziness` should be greater than one"
+        );
+        self.n_features = input.ncols();
+        self.converged = false;
+        self.objective = A::zero();
+        if input.is_empty() || self.n_clusters == 0 {
+            self.centroids = vec![];
+            return (Array2::zeros((input.nrows(), 0)), vec![]);
+        }
+
+        let input = input.view();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.centroids = init_centroids(&input.view(), self.n_clusters, &mut rng);
+        let mut u = memberships(&rows, &self.centroids(), self.fuzziness);
+        for _ in 0..self.max_iter {
+            self.update_centroids(&rows, &u);
+            let next = memberships(&rows, &self.centroids(), self.fuzziness);
+            let change = next.iter().zip(&u).fold(A::zero(), |acc, (a, b)| {
+                FloatCore::max(acc, FloatCore::abs(*a - *b))
+            });
+            u = next;
+            if change <= self.tol {
+                self.converged = true;
+                break;
+            }
+        }
+
+        let centroids = self.centroids();
+        self.objective = rows
+            .iter()
+            .zip(u.rows())
+            .map(|(p, u)| {
+                centroids
+                    .rows()
+                    .into_iter()
+                    .zip(u)
+                    .fold(A::zero(), |acc, (c, u)| {
+                        acc + Float::powf(*u, self.fuzziness) * squared_distance(p, &c)
+                    })
+            })
+            .fold(A::zero(), |acc, v| acc + v);
+        let labels = u.rows().into_iter().map(|u| argmax(&u)).collect();
+        (u, labels)
+    }
+}
+
+/// Assigns each point in the given input to the cluster in which it has the
+/// largest membership.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for FuzzyCMeans<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        self.predict_memberships(input)
+            .rows()
+            .into_iter()
+            .map(|u| argmax(&u))
+            .collect()
+    }
+}
+
+impl<A> FuzzyCMeans<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Moves each centroid to the mean of the points weighted by their
+    /// memberships raised to the power `fuzziness`.
+    fn update_centroids(&mut self, rows: &[ArrayView1<A>], u: &Array2<A>) {
+        let dim = self.n_features;
+        for (c, centroid) in self.centroids.chunks_mut(dim).enumerate() {
+            let mut total = A::zero();
+            let mut sum = vec![A::zero(); dim];
+            for (p, u) in rows.iter().zip(u.column(c)) {
+                let w = Float::powf(*u, self.fuzziness);
+                total += w;
+                for (s, v) in sum.iter_mut().zip(p) {
+                    *s += w * *v;
+                }
+            }
+            if total > A::zero() {
+                for (c, s) in centroid.iter_mut().zip(sum) {
+                    *c = s / total;
+                }
+            }
+        }
+    }
+}
+
+/// Computes the memberships `1 / sum_k (d_j / d_k)^(2 / (m - 1))` of each
+/// point in each cluster `j`, where `d_j` is the distance to its centroid.
+/// A point on one or more centroids belongs to them equally.
+fn memberships<A>(rows: &[ArrayView1<A>], centroids: &ArrayView2<A>, fuzziness: A) -> Array2<A>
+where
+    A: Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    let k = centroids.nrows();
+    let exponent = A::one() / (fuzziness - A::one());
+    let values: Vec<A> = rows
+        .par_iter()
+        .flat_map_iter(|p| {
+            let d: Vec<A> = centroids
+                .rows()
+                .into_iter()
+                .map(|c| squared_distance(p, &c))
+                .collect();
+            let on_centroid = d.iter().filter(|d| **d <= A::zero()).count();
+            d.iter()
+                .map(|dj| {
+                    if on_centroid > 0 {
+                        if *dj <= A::zero() {
+                            A::one() / A::from_usize(on_centroid).expect("valid float")
+                        } else {
+                            A::zero()
+                        }
+                    } else {
+                        let sum = d
+                            .iter()
+                            .fold(A::zero(), |acc, dk| acc + Float::powf(*dj / *dk, exponent));
+                        A::one() / sum
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Array2::from_shape_vec((rows.len(), k), values).expect("one membership per cluster")
+}
+
+fn squared_distance<A: FloatCore>(a: &ArrayView1<A>, b: &ArrayView1<A>) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (x, y)| acc + (*x - *y) * (*x - *y))
+}
+
+fn argmax<A: FloatCore>(values: &ArrayView1<A>) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .fold((0, A::neg_infinity()), |best, (i, v)| {
+            if *v > best.1 {
+                (i, *v)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn fuzzy_cmeans() {
+        let data = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [10., 10.],
+            [10., 11.],
+            [11., 10.],
+            [5., 5.5]
+        ];
+        let mut model = FuzzyCMeans::new(2);
+        let (u, labels) = model.fit(&data, None);
+        assert!(model.converged());
+        assert!(u.rows().into_iter().all(|r| f64::abs(r.sum() - 1.) < 1e-12));
+        assert_eq!(labels[..3], [labels[0]; 3]);
+        assert_eq!(labels[3..6], [labels[3]; 3]);
+        assert_ne!(labels[0], labels[3]);
+        assert!(u[[0, labels[0]]] > 0.95);
+        assert!(f64::abs(u[[6, 0]] - 0.5) < 0.1);
+
+        // the point in the middle pulls the centroids toward it
+        let centroids = model.centroids();
+        let first = centroids.row(labels[0]);
+        assert!(first.iter().all(|v| *v > 1. / 3. && *v < 1.));
+
+        // a softer partition has a larger membership in the other cluster
+        let mut softer = FuzzyCMeans::new(2);
+        softer.fuzziness = 3.;
+        let (v, _) = softer.fit(&data, None);
+        assert!(v[[0, labels[0]]] < u[[0, labels[0]]]);
+
+        let predicted = model.predict_memberships(&array![[10., 10.5]]);
+        assert!(predicted[[0, labels[3]]] > 0.95);
+    }
+
+    #[test]
+    fn on_centroid() {
+        let data = array![[0., 0.], [0., 0.], [3., 3.]];
+        let mut model = FuzzyCMeans::new(2);
+        let (u, labels) = model.fit(&data, None);
+        assert!(f64::abs(u[[0, labels[0]]] - 1.) < 1e-12);
+        assert!(f64::abs(u[[2, labels[2]]] - 1.) < 1e-12);
+        assert!(f64::abs(model.objective()) < 1e-12);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut model = FuzzyCMeans::default();
+        let (u, labels) = model.fit(&data, None);
+        assert_eq!(u.dim(), (0, 0));
+        assert!(labels.is_empty());
+    }
+}
@@ -0,0 +1,695 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::kmeans::init_centroids;
+use crate::linalg::{cholesky, solve_lower_triangular};
+use crate::parallel::prelude::*;
+
+/// The form of the covariance matrices of the components of a
+/// [`GaussianMixture`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum CovarianceType {
+    /// Each component has its own general covariance matrix.
+    Full,
+
+    /// Each component has its own diagonal covariance matrix.
+    Diagonal,
+
+    /// Each component has its own single variance shared by all features.
+    Spherical,
+}
+
+/// Gaussian mixture model fitted with the expectation-maximization (EM)
+/// algorithm.
+///
+/// Unlike the other clustering algorithms, a mixture model assigns each point
+/// a probability of belonging to each component. `fit` returns these
+/// responsibilities along with the most likely component of each point, and
+/// [`GaussianMixture::predict_proba`] computes them for new points.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, GaussianMixture, Predict};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let mut gmm = GaussianMixture::new(2);
+/// let (responsibilities, labels) = gmm.fit(&points, None);
+///
+/// assert_eq!(responsibilities.dim(), (6, 2));
+/// assert_eq!(labels[0], labels[1]);
+/// assert_ne!(labels[0], labels[3]);
+/// assert_eq!(gmm.predict(&array![[1., 2.1]]), [labels[0]]);
+/// ```
+///
+/// # References
+/// - Dempster, Arthur P., Nan M. Laird, and Donald B. Rubin. "Maximum likelihood from incomplete data via the EM algorithm."
+///   Journal of the Royal Statistical Society: Series B 39.1 (1977): 1-22.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GaussianMixture<A> {
+    /// The number of mixture components.
+    pub n_components: usize,
+
+    /// The form of the covariance matrices.
+    pub covariance_type: CovarianceType,
+
+    /// The maximum number of EM iterations.
+    pub max_iter: usize,
+
+    /// The EM iterations stop when the mean log-likelihood of the points
+    /// improves by less than this.
+    pub tol: A,
+
+    /// A non-negative value added to the diagonal of the covariance matrices,
+    /// which keeps them positive definite.
+    pub reg_covar: A,
+
+    /// The seed of the random number generator choosing the initial means
+    /// with k-means++.
+    pub seed: u64,
+
+    weights: Vec<A>,
+    means: Vec<A>,
+    /// Cholesky factors of the covariance matrices for `Full`, and variances
+    /// for `Diagonal` and `Spherical`.
+    covariances: Vec<A>,
+    n_features: usize,
+    converged: bool,
+    lower_bound: A,
+}
+
+impl<A> Default for GaussianMixture<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl<A> GaussianMixture<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model with `n_components` components with full covariance
+    /// matrices, running at most 100 iterations with a tolerance of `1e-3`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::{CovarianceType, GaussianMixture};
+    ///
+    /// let gmm = GaussianMixture::<f64>::new(3);
+    /// assert_eq!(gmm.covariance_type, CovarianceType::Full);
+    /// assert_eq!(gmm.max_iter, 100);
+    /// ```
+    #[must_use]
+    pub fn new(n_components: usize) -> Self {
+        Self {
+            n_components,
+            covariance_type: CovarianceType::Full,
+            max_iter: 100,
+            tol: A::from(1e-3).unwrap_or_else(A::epsilon),
+            reg_covar: A::from(1e-6).unwrap_or_else(A::epsilon),
+            seed: 0,
+            weights: vec![],
+            means: vec![],
+            covariances: vec![],
+            n_features: 0,
+            converged: false,
+            lower_bound: A::neg_infinity(),
+        }
+    }
+
+    /// Returns the mixing weight of each component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// gmm.fit(&points, None);
+    ///
+    /// for weight in gmm.weights() {
+    ///     assert!((weight - 0.5_f64).abs() < 1e-6);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn weights(&self) -> &[A] {
+        &self.weights
+    }
+
+    /// Returns the mean of each component, one row per component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// let (_, labels) = gmm.fit(&points, None);
+    ///
+    /// let mean = gmm.means().row(labels[0]).to_owned();
+    /// assert!((mean[0] - 1_f64).abs() < 1e-6);
+    /// assert!((mean[1] - 2.033_333_333).abs() < 1e-6);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn means(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (self.weights.len(), self.n_features),
+            &self.means[..self.weights.len() * self.n_features],
+        )
+        .expect("means of the fitted model")
+    }
+
+    /// Returns whether the last fit converged within `max_iter` iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// gmm.fit(&points, None);
+    ///
+    /// assert!(gmm.converged());
+    /// ```
+    #[must_use]
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Returns the mean log-likelihood of the points in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1_f64, 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// gmm.fit(&points, None);
+    ///
+    /// assert!((gmm.lower_bound() - gmm.score(&points)).abs() < 1e-6);
+    /// ```
+    #[must_use]
+    pub fn lower_bound(&self) -> A {
+        self.lower_bound
+    }
+}
+
+impl<A> GaussianMixture<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Computes the probability of each point in the given input to belong to
+    /// each component, one row per point.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// let (_, labels) = gmm.fit(&points, None);
+    ///
+    /// let proba = gmm.predict_proba(&array![[1., 2.1]]);
+    /// assert!(proba[[0, labels[0]]] > 0.99);
+    /// ```
+    #[must_use]
+    pub fn predict_proba<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(
+            !self.weights.is_empty(),
+            "`predict_proba` requires a fitted model"
+        );
+        let (log_resp, _) = self.e_step(&input.view());
+        log_resp.mapv(Float::exp)
+    }
+
+    /// Computes the mean log-likelihood of the points in the given input.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut gmm = GaussianMixture::new(2);
+    /// gmm.fit(&points, None);
+    ///
+    /// // points far from both components are unlikely
+    /// assert!(gmm.score(&array![[4.5, 4.5]]) < gmm.score(&points));
+    /// ```
+    #[must_use]
+    pub fn score<S>(&self, input: &ArrayBase<S, Ix2>) -> A
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(!self.weights.is_empty(), "`score` requires a fitted model");
+        self.e_step(&input.view()).1
+    }
+
+    /// Computes the Bayesian information criterion of the model on the given
+    /// input, `-2 * log-likelihood + n_parameters * ln(n_points)`, which is
+    /// lower for models that fit the input better with fewer parameters.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, GaussianMixture};
+    ///
+    /// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+    /// let mut one = GaussianMixture::new(1);
+    /// one.fit(&points, None);
+    /// let mut two = GaussianMixture::new(2);
+    /// two.fit(&points, None);
+    ///
+    /// assert!(two.bic(&points) < one.bic(&points));
+    /// ```
+    #[must_use]
+    pub fn bic<S>(&self, input: &ArrayBase<S, Ix2>) -> A
+    where
+        S: Data<Elem = A>,
+    {
+        let n = A::from_usize(input.nrows()).expect("valid float");
+        let log_likelihood = self.score(input) * n;
+        let n_parameters = A::from_usize(self.n_parameters()).expect("valid float");
+        n_parameters * Float::ln(n) - (log_likelihood + log_likelihood)
+    }
+
+    /// Returns the number of free parameters of the model.
+    fn n_parameters(&self) -> usize {
+        let (k, d) = (self.weights.len(), self.n_features);
+        let covariance = match self.covariance_type {
+            CovarianceType::Full => k * d * (d + 1) / 2,
+            CovarianceType::Diagonal => k * d,
+            CovarianceType::Spherical => k,
+        };
+        k - 1 + k * d + covariance
+    }
+
+    /// Computes the normalized log responsibilities of the points, and the
+    /// mean log-likelihood of the points.
+    fn e_step(&self, input: &ArrayView2<A>) -> (Array2<A>, A) {
+        let k = self.weights.len();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let log_probs: Vec<(Vec<A>, A)> = rows
+            .par_iter()
+            .map(|x| {
+                let mut log_prob: Vec<A> = (0..k)
+                    .map(|c| self.log_gaussian(x, c) + Float::ln(self.weights[c]))
+                    .collect();
+                let norm = log_sum_exp(&log_prob);
+                for v in &mut log_prob {
+                    *v = *v - norm;
+                }
+                (log_prob, norm)
+            })
+            .collect();
+
+        let mut log_resp = Array2::zeros((rows.len(), k));
+        let mut total = A::zero();
+        for (mut row, (log_prob, norm)) in log_resp.rows_mut().into_iter().zip(log_probs) {
+            row.iter_mut().zip(log_prob).for_each(|(r, v)| *r = v);
+            total += norm;
+        }
+        let n = A::from_usize(rows.len().max(1)).expect("valid float");
+        (log_resp, total / n)
+    }
+
+    /// Computes the log density of component `c` at `x`.
+    fn log_gaussian(&self, x: &ArrayView1<A>, c: usize) -> A {
+        let d = self.n_features;
+        let mean = &self.means[c * d..(c + 1) * d];
+        let mut diff: Vec<A> = x.iter().zip(mean).map(|(x, m)| *x - *m).collect();
+        let (mahalanobis, log_det) = match self.covariance_type {
+            CovarianceType::Full => {
+                let l =
+                    ArrayView2::from_shape((d, d), &self.covariances[c * d * d..(c + 1) * d * d])
+                        .expect("Cholesky factor of a covariance matrix");
+                solve_lower_triangular(&l, &mut diff);
+                let log_det = l
+                    .diag()
+                    .iter()
+                    .fold(A::zero(), |acc, v| acc + Float::ln(*v));
+                (squared_norm(&diff), log_det + log_det)
+            }
+            CovarianceType::Diagonal => {
+                let variances = &self.covariances[c * d..(c + 1) * d];
+                diff.iter().zip(variances).fold(
+                    (A::zero(), A::zero()),
+                    |(mahalanobis, log_det), (v, var)| {
+                        (mahalanobis + *v * *v / *var, log_det + Float::ln(*var))
+                    },
+                )
+            }
+            CovarianceType::Spherical => {
+                let variance = self.covariances[c];
+                let dim = A::from_usize(d).expect("valid float");
+                (squared_norm(&diff) / variance, dim * Float::ln(variance))
+            }
+        };
+        let log_2pi = A::from_f64((2. * std::f64::consts::PI).ln()).expect("valid float");
+        let dim = A::from_usize(d).expect("valid float");
+        -(dim * log_2pi + mahalanobis + log_det) / (A::one() + A::one())
+    }
+
+    /// Estimates the weights, means and covariances of the components from the
+    /// responsibilities of the points.
+    fn m_step(&mut self, input: &ArrayView2<A>, resp: &Array2<A>) {
+        let d = input.ncols();
+        let k = resp.ncols();
+        let n = A::from_usize(input.nrows()).expect("valid float");
+        let ten = A::from_f64(10.).expect("valid float");
+        let counts: Vec<A> = resp
+            .columns()
+            .into_iter()
+            .map(|r| r.iter().fold(A::zero(), |acc, v| acc + *v) + ten * Float::epsilon())
+            .collect();
+
+        let mut means = vec![A::zero(); k * d];
+        for (x, r) in input.rows().into_iter().zip(resp.rows()) {
+            for c in 0..k {
+                for (m, v) in means[c * d..(c + 1) * d].iter_mut().zip(&x) {
+                    *m += r[c] * *v;
+                }
+            }
+        }
+        for c in 0..k {
+            for m in &mut means[c * d..(c + 1) * d] {
+                *m /= counts[c];
+            }
+        }
+
+        let covariances = (0..k)
+            .into_par_iter()
+            .flat_map_iter(|c| {
+                let mean = &means[c * d..(c + 1) * d];
+                let weighted_diffs = input.rows().into_iter().zip(resp.column(c)).map(|(x, r)| {
+                    let diff: Vec<A> = x.iter().zip(mean).map(|(x, m)| *x - *m).collect();
+                    (diff, *r)
+                });
+                match self.covariance_type {
+                    CovarianceType::Full => {
+                        let mut cov = Array2::zeros((d, d));
+                        for (diff, r) in weighted_diffs {
+                            for i in 0..d {
+                                for j in 0..=i {
+                                    cov[[i, j]] += r * diff[i] * diff[j];
+                                }
+                            }
+                        }
+                        for i in 0..d {
+                            for j in 0..i {
+                                cov[[i, j]] /= counts[c];
+                                cov[[j, i]] = cov[[i, j]];
+                            }
+                            cov[[i, i]] = cov[[i, i]] / counts[c] + self.reg_covar;
+                        }
+                        let l = cholesky(&cov).expect(
+                            "covariance matrices should be positive definite; increase `reg_covar`",
+                        );
+                        l.into_iter().collect::<Vec<_>>()
+                    }
+                    CovarianceType::Diagonal | CovarianceType::Spherical => {
+                        let mut variances = vec![A::zero(); d];
+                        for (diff, r) in weighted_diffs {
+                            for (var, v) in variances.iter_mut().zip(&diff) {
+                                *var += r * *v * *v;
+                            }
+                        }
+                        for var in &mut variances {
+                            *var = *var / counts[c] + self.reg_covar;
+                        }
+                        if self.covariance_type == CovarianceType::Spherical {
+                            let sum = variances.iter().fold(A::zero(), |acc, v| acc + *v);
+                            vec![sum / A::from_usize(d).expect("valid float")]
+                        } else {
+                            variances
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        self.weights = counts.into_iter().map(|c| c / n).collect();
+        self.means = means;
+        self.covariances = covariances;
+    }
+}
+
+/// Fits the Gaussian mixture model to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `Array2<A>`: The responsibilities, i.e., the probability of each point (row) to belong to each component (column).
+/// - `Vec<usize>`: The most likely component of each point.
+///
+/// # Panics
+/// Panics if a covariance matrix is not positive definite, which can be
+/// avoided by increasing `reg_covar`.
+impl<S, A> Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (Array2<A>, Vec<usize>)>
+    for GaussianMixture<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (Array2<A>, Vec<usize>) {
+        self.n_features = input.ncols();
+        self.converged = false;
+        self.lower_bound = Float::neg_infinity();
+        if input.is_empty() || self.n_components == 0 {
+            self.weights = vec![];
+            self.means = vec![];
+            self.covariances = vec![];
+            return (Array2::zeros((input.nrows(), 0)), vec![]);
+        }
+
+        // initial responsibilities assign each point to its closest k-means++ seed
+        let input = input.view();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let d = self.n_features;
+        let seeds = init_centroids(&input, self.n_components, &mut rng);
+        let mut resp = Array2::zeros((input.nrows(), self.n_components));
+        for (x, mut r) in input.rows().into_iter().zip(resp.rows_mut()) {
+            let closest = (0..self.n_components)
+                .map(|c| {
+                    let seed = &seeds[c * d..(c + 1) * d];
+                    x.iter()
+                        .zip(seed)
+                        .fold(A::zero(), |acc, (x, s)| acc + (*x - *s) * (*x - *s))
+                })
+                .enumerate()
+                .fold((0, Float::infinity()), |best, (c, dist)| {
+                    if dist < best.1 {
+                        (c, dist)
+                    } else {
+                        best
+                    }
+                })
+                .0;
+            r[closest] = A::one();
+        }
+        self.m_step(&input, &resp);
+
+        for _ in 0..self.max_iter {
+            let previous = self.lower_bound;
+            let (log_resp, lower_bound) = self.e_step(&input);
+            self.m_step(&input, &log_resp.mapv(Float::exp));
+            self.lower_bound = lower_bound;
+            if Float::abs(lower_bound - previous) < self.tol {
+                self.converged = true;
+                break;
+            }
+        }
+
+        // the final responsibilities are consistent with the fitted parameters
+        let (log_resp, _) = self.e_step(&input);
+        let labels = log_resp.rows().into_iter().map(|r| argmax(&r)).collect();
+        (log_resp.mapv(Float::exp), labels)
+    }
+}
+
+/// Assigns each point in the given input to its most likely component.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for GaussianMixture<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        self.predict_proba(input)
+            .rows()
+            .into_iter()
+            .map(|r| argmax(&r))
+            .collect()
+    }
+}
+
+fn log_sum_exp<A: Float>(values: &[A]) -> A {
+    let max = values.iter().copied().fold(A::neg_infinity(), A::max);
+    if max.is_infinite() {
+        return max;
+    }
+    max + values
+        .iter()
+        .fold(A::zero(), |acc, v| acc + (*v - max).exp())
+        .ln()
+}
+
+fn squared_norm<A: Float>(v: &[A]) -> A {
+    v.iter().fold(A::zero(), |acc, x| acc + *x * *x)
+}
+
+fn argmax<A: Float>(values: &ArrayView1<A>) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .fold((0, A::neg_infinity()), |best, (i, v)| {
+            if *v > best.1 {
+                (i, *v)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    /// Two blobs of 50 points around (0, 0) and (10, 5), the second one
+    /// stretched along the first axis.
+    #[allow(clippy::cast_precision_loss)]
+    fn blobs() -> Array2<f64> {
+        Array2::from_shape_fn((100, 2), |(i, j)| {
+            let t = (i % 50) as f64;
+            let noise = ((t * 0.618_033_988_7 + j as f64 * 0.414_213_562).fract() - 0.5) * 2.;
+            if i < 50 {
+                noise
+            } else if j == 0 {
+                10. + 3. * noise
+            } else {
+                5. + noise
+            }
+        })
+    }
+
+    #[test]
+    fn default() {
+        let gmm = GaussianMixture::<f32>::default();
+        assert_eq!(gmm.n_components, 1);
+        assert_eq!(gmm.covariance_type, CovarianceType::Full);
+    }
+
+    #[test]
+    fn gaussian_mixture() {
+        let data = blobs();
+        for covariance_type in [
+            CovarianceType::Full,
+            CovarianceType::Diagonal,
+            CovarianceType::Spherical,
+        ] {
+            let mut gmm = GaussianMixture::new(2);
+            gmm.covariance_type = covariance_type;
+            let (resp, labels) = gmm.fit(&data, None);
+            assert!(gmm.converged());
+            assert!(labels[..50].iter().all(|l| *l == labels[0]));
+            assert!(labels[50..].iter().all(|l| *l != labels[0]));
+            assert!(resp.rows().into_iter().all(|r| (r.sum() - 1.).abs() < 1e-9));
+
+            let weights = gmm.weights();
+            assert!(weights.iter().all(|w| (w - 0.5).abs() < 1e-6));
+            let means = gmm.means();
+            let second = 1 - labels[0];
+            assert!((means[[second, 0]] - 10.).abs() < 0.5);
+            assert!((means[[second, 1]] - 5.).abs() < 0.5);
+
+            let proba = gmm.predict_proba(&array![[0., 0.], [10., 5.]]);
+            assert!(proba[[0, labels[0]]] > 0.99);
+            assert!(proba[[1, second]] > 0.99);
+            assert_eq!(gmm.predict(&array![[11., 5.]]), [second]);
+        }
+    }
+
+    #[test]
+    fn full_covariance_fits_better() {
+        let data = blobs();
+        let mut lower_bounds = vec![];
+        for covariance_type in [CovarianceType::Spherical, CovarianceType::Full] {
+            let mut gmm = GaussianMixture::new(2);
+            gmm.covariance_type = covariance_type;
+            gmm.fit(&data, None);
+            lower_bounds.push(gmm.lower_bound());
+        }
+        assert!(lower_bounds[1] > lower_bounds[0]);
+    }
+
+    #[test]
+    fn bic() {
+        let data = blobs();
+        let bic: Vec<f64> = (1..=2)
+            .map(|k| {
+                let mut gmm = GaussianMixture::new(k);
+                gmm.fit(&data, None);
+                assert!((gmm.score(&data) - gmm.lower_bound()).abs() < 1e-2);
+                gmm.bic(&data)
+            })
+            .collect();
+        assert!(bic[1] < bic[0]);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut gmm = GaussianMixture::new(2);
+        let (resp, labels) = gmm.fit(&data, None);
+        assert_eq!(resp.nrows(), 0);
+        assert!(labels.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+use ndarray::{Array2, ArrayBase, ArrayView1, Data, Ix2};
+use num_traits::Float;
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::prelude::*;
+
+/// The type of a column compared by [`Gower`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ColumnType {
+    /// A number, compared by the absolute difference relative to the range
+    /// of the column.
+    Numeric,
+
+    /// A category code, compared by whether the codes are equal.
+    Categorical,
+}
+
+/// Gower's distance between points with numeric and categorical features,
+/// such as the rows of a table of customers.
+///
+/// Each column contributes a dissimilarity between zero and one: the
+/// absolute difference divided by the range of the column for a numeric
+/// column, at most one, and zero or one for a categorical column, depending
+/// on whether the codes are equal. The distance is the mean of the
+/// contributions of the columns in which both points have a value; a NaN
+/// marks a missing value. If no column has values in both points, the
+/// distance is one.
+///
+/// The distance satisfies the triangle inequality if no value is missing,
+/// so `Gower` can replace `Euclidean` in [`Dbscan`](crate::Dbscan),
+/// [`HDbscan`](crate::HDbscan) and [`KMedoids`](crate::KMedoids). Points
+/// with missing values are handled as with
+/// [`NanEuclidean`](crate::NanEuclidean): DBSCAN compares them with every
+/// point, and HDBSCAN leaves them out as noise. The matrix of
+/// [`Gower::pairwise`] can also be clustered with
+/// [`KMedoids::fit_precomputed`](crate::KMedoids::fit_precomputed) or
+/// [`AgglomerativeClustering::fit_precomputed`](crate::AgglomerativeClustering::fit_precomputed).
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{ColumnType, Dbscan, Fit, Gower};
+///
+/// // age, income, region code
+/// let customers = array![
+///     [25., 30_000., 0.],
+///     [27., 32_000., 0.],
+///     [26., 31_000., 0.],
+///     [60., 90_000., 1.],
+///     [62., 95_000., 1.],
+///     [61., 91_000., 1.],
+/// ];
+/// let columns = vec![ColumnType::Numeric, ColumnType::Numeric, ColumnType::Categorical];
+/// let gower = Gower::from_data(&customers, columns);
+///
+/// let (clusters, noise) = Dbscan::new(0.1, 2, gower).fit(&customers, None);
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+///
+/// # References
+/// - Gower, John C. "A general coefficient of similarity and some of its properties."
+///   Biometrics 27.4 (1971): 857-871.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Gower<A> {
+    /// The type of each column.
+    pub columns: Vec<ColumnType>,
+
+    /// The range of each column, the difference between its largest and
+    /// smallest values, which is ignored for categorical columns.
+    pub ranges: Vec<A>,
+}
+
+impl<A: Float> Gower<A> {
+    /// Creates Gower's distance for the given columns, with the ranges of the
+    /// numeric columns computed from `input`, ignoring missing values.
+    ///
+    /// # Panics
+    /// Panics if `columns` does not have one entry for each column of
+    /// `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Metric;
+    /// use petal_clustering::{ColumnType, Gower};
+    ///
+    /// // a numeric column ranging from 0 to 10, and a categorical one
+    /// let points = array![[0., 1.], [5., 1.], [10., 2.]];
+    /// let gower = Gower::from_data(&points, vec![ColumnType::Numeric, ColumnType::Categorical]);
+    ///
+    /// assert_eq!(gower.distance(&points.row(0), &points.row(1)), 0.25);
+    /// assert_eq!(gower.distance(&points.row(0), &points.row(2)), 1.);
+    /// ```
+    pub fn from_data<S>(input: &ArrayBase<S, Ix2>, columns: Vec<ColumnType>) -> Self
+    where
+        S: Data<Elem = A>,
+    {
+        assert_eq!(
+            columns.len(),
+            input.ncols(),
+            "`columns` should have one entry for each column"
+        );
+        let ranges = input
+            .columns()
+            .into_iter()
+            .zip(&columns)
+            .map(|(values, column)| {
+                if *column == ColumnType::Categorical {
+                    return A::zero();
+                }
+                let (min, max) = values
+                    .iter()
+                    .filter(|v| !v.is_nan())
+                    .fold((A::infinity(), A::neg_infinity()), |(min, max), v| {
+                        (min.min(*v), max.max(*v))
+                    });
+                if min <= max {
+                    max - min
+                } else {
+                    A::zero()
+                }
+            })
+            .collect();
+        Self { columns, ranges }
+    }
+
+    /// Returns the matrix of the distances between all pairs of points in
+    /// `input`.
+    ///
+    /// # Panics
+    /// Panics if `input` does not have one column for each entry of
+    /// `columns`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{ColumnType, Gower};
+    ///
+    /// let points = array![[0., 1.], [5., 1.], [10., 2.]];
+    /// let gower = Gower::from_data(&points, vec![ColumnType::Numeric, ColumnType::Categorical]);
+    ///
+    /// assert_eq!(
+    ///     gower.pairwise(&points),
+    ///     array![[0., 0.25, 1.], [0.25, 0., 0.75], [1., 0.75, 0.]]
+    /// );
+    /// ```
+    pub fn pairwise<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        A: Send + Sync,
+        S: Data<Elem = A>,
+    {
+        let n = input.nrows();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let values: Vec<A> = rows
+            .par_iter()
+            .flat_map_iter(|p| rows.iter().map(|q| self.distance(p, q)))
+            .collect();
+        Array2::from_shape_vec((n, n), values).expect("one distance for each pair")
+    }
+}
+
+/// # Panics
+/// The distances panic if the points do not have one value for each entry of
+/// `columns`.
+impl<A: Float> Metric<A> for Gower<A> {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        assert!(
+            x1.len() == self.columns.len() && x2.len() == self.columns.len(),
+            "points should have one value for each column"
+        );
+        let mut total = A::zero();
+        let mut compared = A::zero();
+        for (((a, b), column), range) in x1.iter().zip(x2).zip(&self.columns).zip(&self.ranges) {
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+            compared = compared + A::one();
+            total = total
+                + match column {
+                    ColumnType::Numeric if *range > A::zero() => {
+                        ((*a - *b).abs() / *range).min(A::one())
+                    }
+                    ColumnType::Numeric | ColumnType::Categorical if a == b => A::zero(),
+                    ColumnType::Numeric | ColumnType::Categorical => A::one(),
+                };
+        }
+        if compared > A::zero() {
+            total / compared
+        } else {
+            A::one()
+        }
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.distance(x1, x2)
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, aview1};
+
+    use super::*;
+    use crate::{Fit, KMedoids};
+
+    #[test]
+    fn distance() {
+        let data = array![[0., 10., 1.], [4., 20., 2.], [2., f64::NAN, 1.]];
+        let gower = Gower::from_data(
+            &data,
+            vec![
+                ColumnType::Numeric,
+                ColumnType::Numeric,
+                ColumnType::Categorical,
+            ],
+        );
+        assert_eq!(gower.ranges, [4., 10., 0.]);
+
+        let d = |i: usize, j: usize| gower.distance(&data.row(i), &data.row(j));
+        assert!((d(0, 1) - 1.).abs() < 1e-12);
+        assert!((d(0, 2) - 0.5 / 2.).abs() < 1e-12);
+        assert!((d(1, 2) - (0.5 + 1.) / 2.).abs() < 1e-12);
+        assert!(d(2, 2).abs() < 1e-12);
+
+        // a value outside the range counts as at most one
+        let far = aview1(&[100., 10., 1.]);
+        assert!((gower.distance(&far, &data.row(0)) - 1. / 3.).abs() < 1e-12);
+
+        let missing = aview1(&[f64::NAN, f64::NAN, f64::NAN]);
+        assert!((gower.distance(&missing, &data.row(0)) - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pairwise() {
+        let data = array![
+            [1., 0.],
+            [1.5, 0.],
+            [f64::NAN, 0.],
+            [9., 1.],
+            [10., 1.],
+            [9.5, f64::NAN],
+        ];
+        let gower = Gower::from_data(&data, vec![ColumnType::Numeric, ColumnType::Categorical]);
+        let distances = gower.pairwise(&data);
+        assert_eq!(distances.dim(), (6, 6));
+        assert!((distances[[0, 1]] - 0.5 / 9. / 2.).abs() < 1e-12);
+        assert!((distances[[0, 1]] - distances[[1, 0]]).abs() < 1e-12);
+
+        // k-medoids compares all pairs, so it handles the missing values
+        let mut kmedoids = KMedoids::new(2, gower);
+        let (clusters, _) = kmedoids.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4, 5]]);
+    }
+}
@@ -0,0 +1,554 @@
+//! Brute-force neighbor searches on a GPU with [wgpu], enabled with the
+//! `gpu` feature.
+//!
+//! [`GpuNeighbors`] computes the Euclidean distances between all pairs of
+//! points on the GPU, a block of rows at a time, and selects the neighbors
+//! of each point on the CPU. It returns the same [`NeighborGraph`] and
+//! [`KnnGraph`] as [`NeighborGraph::new`] and [`knn_graph`], so the
+//! neighborhood search of [`Dbscan`](crate::Dbscan) and
+//! [`Optics`](crate::Optics) can be moved to the GPU with their
+//! `fit_with_graph` methods, and that of [`HDbscan`](crate::HDbscan) with
+//! [`HDbscan::fit_from_knn`](crate::HDbscan::fit_from_knn). Brute force
+//! beats a ball tree on inputs with many features, where the tree prunes
+//! little.
+//!
+//! If there is no GPU, or the input does not fit in its buffers, the
+//! searches run on the CPU instead, with the same results.
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::gpu::GpuNeighbors;
+//! use petal_clustering::{Dbscan, Optics};
+//!
+//! let points = array![[1f32, 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+//! let graph = GpuNeighbors::new().neighbor_graph(&points, 3.);
+//!
+//! let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit_with_graph(&graph);
+//! assert_eq!(clusters.len(), 2);
+//! assert_eq!(noise, [5]);
+//!
+//! let (clusters, noise) = Optics::new(3., 2, Euclidean::default()).fit_with_graph(&graph);
+//! assert_eq!(clusters.len(), 2);
+//! assert_eq!(noise, [5]);
+//! ```
+//!
+//! [wgpu]: https://wgpu.rs
+
+use std::cmp::Ordering;
+use std::sync::mpsc;
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use petal_neighbors::distance::Euclidean;
+use wgpu::util::DeviceExt;
+
+use crate::diagnostics::finite_rows;
+use crate::parallel::prelude::*;
+use crate::{knn_graph, KnnGraph, NeighborGraph};
+
+/// The number of points of a row each invocation of the shader computes
+/// the distances to, one per thread of a workgroup.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Computes the distances from points `start..start + rows` to all `n`
+/// points, stored row by row in `points`.
+const SHADER: &str = r"
+struct Params {
+    n: u32,
+    dim: u32,
+    start: u32,
+    rows: u32,
+}
+
+@group(0) @binding(0) var<storage, read> points: array<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read_write> distances: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let j = id.x;
+    let r = id.y;
+    if (j >= params.n || r >= params.rows) {
+        return;
+    }
+    let i = params.start + r;
+    var sum = 0.0;
+    for (var f = 0u; f < params.dim; f++) {
+        let diff = points[i * params.dim + f] - points[j * params.dim + f];
+        sum += diff * diff;
+    }
+    distances[r * params.n + j] = sqrt(sum);
+}
+";
+
+/// Searches the neighbors of points by brute force on a GPU, or on the CPU
+/// if there is none.
+pub struct GpuNeighbors {
+    gpu: Option<Gpu>,
+}
+
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Default for GpuNeighbors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuNeighbors {
+    /// Requests a GPU from any backend wgpu supports, and falls back to the
+    /// CPU if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// let neighbors = GpuNeighbors::new();
+    /// println!("searching on a GPU: {}", neighbors.has_gpu());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_backends(wgpu::Backends::all())
+    }
+
+    /// Requests a GPU from one of `backends`, such as Vulkan or Metal, and
+    /// falls back to the CPU if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// // there is no GPU without any backend
+    /// assert!(!GpuNeighbors::with_backends(wgpu::Backends::empty()).has_gpu());
+    /// ```
+    #[must_use]
+    pub fn with_backends(backends: wgpu::Backends) -> Self {
+        Self {
+            gpu: pollster::block_on(Gpu::request(backends)),
+        }
+    }
+
+    /// Searches on the CPU, as when there is no GPU.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// let neighbors = GpuNeighbors::cpu();
+    /// assert!(!neighbors.has_gpu());
+    ///
+    /// let graph = neighbors.neighbor_graph(&array![[0f32], [1.], [5.]], 2.);
+    /// assert_eq!(graph.neighbors(0), [(0, 0.), (1, 1.)]);
+    /// ```
+    #[must_use]
+    pub fn cpu() -> Self {
+        Self { gpu: None }
+    }
+
+    /// Returns whether the searches run on a GPU.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// assert!(!GpuNeighbors::cpu().has_gpu());
+    /// ```
+    #[must_use]
+    pub fn has_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    /// Searches the neighbors of each point of `input` within `radius`, as
+    /// [`NeighborGraph::new`] does with the Euclidean distance.
+    ///
+    /// Points with a NaN or infinite coordinate have no neighbors. The
+    /// distances computed on a GPU may differ from those on the CPU in the
+    /// last bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// let points = array![[0f32, 0.], [3., 4.], [30., 40.]];
+    /// let graph = GpuNeighbors::new().neighbor_graph(&points, 5.);
+    ///
+    /// assert_eq!(graph.n_points(), 3);
+    /// assert_eq!(graph.neighbors(0), [(0, 0.), (1, 5.)]);
+    /// assert_eq!(graph.neighbors(2), [(2, 0.)]);
+    /// ```
+    pub fn neighbor_graph<S>(&self, input: &ArrayBase<S, Ix2>, radius: f32) -> NeighborGraph<f32>
+    where
+        S: Data<Elem = f32>,
+    {
+        let Some(gpu) = &self.gpu else {
+            return NeighborGraph::new(input, radius, &Euclidean::default());
+        };
+        let mut neighbors = vec![Vec::new(); input.nrows()];
+        let rows = finite_rows(input).unwrap_or_else(|| (0..input.nrows()).collect());
+        let finite = gpu.distances(input, &rows, |start, distances| {
+            let found: Vec<_> = distances
+                .chunks(rows.len())
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|row| {
+                    let mut found: Vec<_> = row
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, d)| **d <= radius)
+                        .map(|(j, d)| (rows[j], *d))
+                        .collect();
+                    found.sort_unstable_by(by_distance);
+                    found
+                })
+                .collect();
+            for (r, found) in found.into_iter().enumerate() {
+                neighbors[rows[start + r]] = found;
+            }
+        });
+        if finite.is_none() {
+            return NeighborGraph::new(input, radius, &Euclidean::default());
+        }
+        NeighborGraph::from_neighbors(radius, neighbors)
+    }
+
+    /// Finds the `k` nearest neighbors of each point of `input`, counting
+    /// the point itself, as [`knn_graph`] does with the Euclidean distance.
+    ///
+    /// The distances computed on a GPU may differ from those on the CPU in
+    /// the last bits, which may break ties between neighbors differently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::gpu::GpuNeighbors;
+    ///
+    /// let graph = GpuNeighbors::new().knn_graph(&array![[0f32], [1.], [3.]], 2);
+    ///
+    /// assert_eq!(graph.indices, array![[0, 1], [1, 0], [2, 1]]);
+    /// assert_eq!(graph.distances, array![[0., 1.], [0., 1.], [0., 2.]]);
+    /// ```
+    pub fn knn_graph<S>(&self, input: &ArrayBase<S, Ix2>, k: usize) -> KnnGraph<f32>
+    where
+        S: Data<Elem = f32>,
+    {
+        let k = k.min(input.nrows());
+        let Some(gpu) = self.gpu.as_ref().filter(|_| k > 0) else {
+            return knn_graph(input, k, &Euclidean::default());
+        };
+        let n = input.nrows();
+        let mut indices = Array2::zeros((n, k));
+        let mut distances = Array2::from_elem((n, k), f32::INFINITY);
+        for i in 0..n {
+            indices[[i, 0]] = i;
+            distances[[i, 0]] = 0.;
+        }
+        let rows = finite_rows(input).unwrap_or_else(|| (0..n).collect());
+        let mut finite = vec![false; n];
+        for &i in &rows {
+            finite[i] = true;
+        }
+        let searched = gpu.distances(input, &rows, |start, block| {
+            let nearest: Vec<Vec<(usize, f32)>> = block
+                .chunks(rows.len())
+                .enumerate()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|(r, row)| {
+                    let i = rows[start + r];
+                    let mut others: Vec<_> = row
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, _)| rows[*j] != i)
+                        .map(|(j, d)| (rows[j], *d))
+                        .collect();
+                    if others.len() > k - 1 {
+                        others.select_nth_unstable_by(k - 1, by_distance);
+                        others.truncate(k - 1);
+                    }
+                    others.sort_unstable_by(by_distance);
+                    others
+                })
+                .collect();
+            for (r, nearest) in nearest.into_iter().enumerate() {
+                let i = rows[start + r];
+                for (c, (j, d)) in nearest.into_iter().enumerate() {
+                    indices[[i, c + 1]] = j;
+                    distances[[i, c + 1]] = d;
+                }
+            }
+        });
+        if searched.is_none() {
+            return knn_graph(input, k, &Euclidean::default());
+        }
+        // Points with a non-finite coordinate are infinitely far from any
+        // other point, and make up the rest of the rows in index order.
+        for i in 0..n {
+            let found = if finite[i] { rows.len().min(k) } else { 1 };
+            let padding = (0..n).filter(|j| *j != i && (!finite[i] || !finite[*j]));
+            for (c, j) in (found..k).zip(padding) {
+                indices[[i, c]] = j;
+            }
+        }
+        KnnGraph { indices, distances }
+    }
+}
+
+impl Gpu {
+    async fn request(backends: wgpu::Backends) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..wgpu::InstanceDescriptor::new_without_display_handle()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("petal-clustering"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("distances"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("distances"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+        Some(Self {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// Computes the distances between the points of `rows` in `input`, and
+    /// passes them to `select` a block of rows at a time, with the position
+    /// in `rows` of the first row of the block. Row `r` of a block holds the
+    /// distances from point `rows[start + r]` to each point of `rows`.
+    ///
+    /// Returns `None` if the points or a row of distances do not fit in a
+    /// buffer of the GPU, or if the GPU fails, in which case the blocks
+    /// already passed to `select` are incomplete.
+    fn distances<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        rows: &[usize],
+        mut select: impl FnMut(usize, &[f32]),
+    ) -> Option<()>
+    where
+        S: Data<Elem = f32>,
+    {
+        let n = rows.len();
+        if n == 0 {
+            return Some(());
+        }
+        let limits = self.device.limits();
+        let max_bytes = limits
+            .max_storage_buffer_binding_size
+            .min(limits.max_buffer_size);
+        let row_bytes = u64::try_from(n * size_of::<f32>()).ok()?;
+        let points: Vec<f32> = rows.iter().flat_map(|i| input.row(*i).to_vec()).collect();
+        if u64::try_from(points.len() * size_of::<f32>()).ok()? > max_bytes || row_bytes > max_bytes
+        {
+            return None;
+        }
+        let block_rows = (max_bytes / row_bytes)
+            .min(u64::from(limits.max_compute_workgroups_per_dimension))
+            .min(n as u64);
+        let block_rows = usize::try_from(block_rows).ok()?;
+        let n_u32 = u32::try_from(n).ok()?;
+        let dim = u32::try_from(input.ncols()).ok()?;
+
+        let points = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("points"),
+                contents: bytemuck::cast_slice(&points),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let block_bytes = row_bytes * block_rows as u64;
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("distances"),
+            size: block_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: block_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        for start in (0..n).step_by(block_rows) {
+            let rows_in_block = block_rows.min(n - start);
+            let params = [
+                n_u32,
+                dim,
+                u32::try_from(start).ok()?,
+                u32::try_from(rows_in_block).ok()?,
+            ];
+            let mut encoder = self.encode_block(&points, &output, params);
+            let bytes = row_bytes * rows_in_block as u64;
+            encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, bytes);
+            self.queue.submit([encoder.finish()]);
+
+            let (sender, receiver) = mpsc::channel();
+            staging.map_async(wgpu::MapMode::Read, ..bytes, move |result| {
+                let _ = sender.send(result);
+            });
+            self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+            receiver.recv().ok()?.ok()?;
+            {
+                let view = staging.get_mapped_range(..bytes).ok()?;
+                select(start, bytemuck::cast_slice(&view));
+            }
+            staging.unmap();
+        }
+        Some(())
+    }
+
+    /// Encodes the computation of a block of distances into `output`, with
+    /// the parameters of the shader: the number of points, the number of
+    /// features, and the first and the number of rows of the block.
+    fn encode_block(
+        &self,
+        points: &wgpu::Buffer,
+        output: &wgpu::Buffer,
+        params: [u32; 4],
+    ) -> wgpu::CommandEncoder {
+        let [n, _, _, rows] = params;
+        let params = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("params"),
+                contents: bytemuck::cast_slice(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("distances"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(n.div_ceil(WORKGROUP_SIZE), rows, 1);
+        }
+        encoder
+    }
+}
+
+/// Orders neighbors by distance, then by index.
+fn by_distance(a: &(usize, f32), b: &(usize, f32)) -> Ordering {
+    a.1.total_cmp(&b.1).then(a.0.cmp(&b.0))
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+    use ndarray::{array, Array2};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    fn points() -> Array2<f32> {
+        array![
+            [0., 0.],
+            [1., 0.],
+            [0., 2.],
+            [f32::NAN, 1.],
+            [5., 5.],
+            [5., 6.5],
+            [f32::INFINITY, 0.],
+            [9., 9.],
+        ]
+    }
+
+    /// Checks that `gpu` finds the same neighbors as the CPU, which it is
+    /// unless there is a GPU.
+    fn assert_same_neighbors(gpu: &GpuNeighbors) {
+        let points = points();
+        let graph = gpu.neighbor_graph(&points, 2.);
+        let expected = NeighborGraph::new(&points, 2., &Euclidean::default());
+        assert_eq!(graph.n_points(), expected.n_points());
+        for i in 0..points.nrows() {
+            let (indices, distances): (Vec<_>, Vec<_>) = graph.neighbors(i).iter().copied().unzip();
+            let (expected_indices, expected_distances): (Vec<_>, Vec<_>) =
+                expected.neighbors(i).iter().copied().unzip();
+            assert_eq!(indices, expected_indices);
+            for (d, e) in distances.iter().zip(&expected_distances) {
+                assert_relative_eq!(d, e, max_relative = 1e-6);
+            }
+        }
+
+        let finite = points.select(ndarray::Axis(0), &[0, 1, 2, 4, 5, 7]);
+        let graph = gpu.knn_graph(&finite, 3);
+        let expected = knn_graph(&finite, 3, &Euclidean::default());
+        assert_eq!(graph.indices, expected.indices);
+        for (d, e) in graph.distances.iter().zip(&expected.distances) {
+            assert_relative_eq!(d, e, max_relative = 1e-6);
+        }
+    }
+
+    #[test]
+    fn fallback() {
+        let gpu = GpuNeighbors::with_backends(wgpu::Backends::empty());
+        assert!(!gpu.has_gpu());
+        assert_same_neighbors(&gpu);
+        assert_same_neighbors(&GpuNeighbors::cpu());
+    }
+
+    #[test]
+    fn any_device() {
+        let gpu = GpuNeighbors::new();
+        assert_same_neighbors(&gpu);
+
+        let empty = Array2::<f32>::zeros((0, 2));
+        assert_eq!(gpu.neighbor_graph(&empty, 1.).n_points(), 0);
+        assert_eq!(gpu.knn_graph(&empty, 2).indices.shape(), [0, 0]);
+    }
+}
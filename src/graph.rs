@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::{Euclidean, Metric};
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::neighbor_graph::{knn_graph, KnnGraph};
+use crate::trace::{counts, phase};
+
+/// The algorithm [`CommunityDetection`] optimizes the modularity with.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum CommunityAlgorithm {
+    /// Moves each node to the neighboring community that increases the
+    /// modularity the most until no move does, then merges each community
+    /// into a node and starts again, until no node moves.
+    Louvain,
+
+    /// Louvain with a refinement of each community before merging it, which
+    /// splits it into well-connected parts. Communities are thus never
+    /// disconnected, as they can be with Louvain, and the result is usually
+    /// of higher modularity.
+    #[default]
+    Leiden,
+}
+
+/// Community detection on the k-nearest-neighbor graph of the input, the
+/// usual clustering of embeddings such as those of single cells or texts.
+///
+/// Each point is connected to its `n_neighbors` nearest neighbors, with a
+/// weight of one half, or one if each of two points is a neighbor of the
+/// other, as in the graph of [`Affinity::NearestNeighbors`]. The graph is
+/// then partitioned into the communities of highest modularity found by
+/// `algorithm`. Higher `resolution`s find more and smaller communities.
+/// Every point is in a community, so there is no noise.
+///
+/// Nodes are visited in order, so the result does not depend on a seed.
+///
+/// [`Affinity::NearestNeighbors`]: crate::Affinity::NearestNeighbors
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{CommunityDetection, Fit};
+///
+/// let points = array![
+///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+///     [8.0, 7.0], [8.1, 7.2], [7.9, 6.8], [8.0, 7.1],
+/// ];
+/// let mut leiden = CommunityDetection::new(3, Euclidean::default());
+/// let (clusters, noise) = leiden.fit(&points, None);
+///
+/// assert_eq!(clusters[&0], [0, 1, 2, 3]);
+/// assert_eq!(clusters[&1], [4, 5, 6, 7]);
+/// assert!(noise.is_empty());
+/// assert!(leiden.modularity() > 0.4);
+/// ```
+///
+/// # References
+/// - Blondel, Vincent D., et al. "Fast unfolding of communities in large networks."
+///   Journal of Statistical Mechanics: Theory and Experiment 2008.10 (2008): P10008.
+/// - Traag, Vincent A., Ludo Waltman, and Nees Jan van Eck. "From Louvain to Leiden: guaranteeing well-connected communities."
+///   Scientific Reports 9.1 (2019): 5233.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommunityDetection<A, M> {
+    /// The number of nearest neighbors, other than the point itself, each
+    /// point is connected to.
+    pub n_neighbors: usize,
+
+    /// The weight of the expected number of edges within communities in the
+    /// modularity. Higher values find more and smaller communities.
+    pub resolution: A,
+
+    /// The algorithm to optimize the modularity with.
+    pub algorithm: CommunityAlgorithm,
+
+    /// The metric to compute distance between the entries.
+    pub metric: M,
+
+    modularity: A,
+}
+
+impl<A> Default for CommunityDetection<A, Euclidean>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(15, Euclidean::default())
+    }
+}
+
+impl<A, M> CommunityDetection<A, M>
+where
+    A: FloatCore,
+{
+    /// Creates the Leiden algorithm on the graph of the `n_neighbors`
+    /// nearest neighbors of each point, with a resolution of one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{CommunityAlgorithm, CommunityDetection};
+    ///
+    /// let leiden = CommunityDetection::<f64, _>::new(10, Euclidean::default());
+    /// assert_eq!(leiden.resolution, 1.);
+    /// assert_eq!(leiden.algorithm, CommunityAlgorithm::Leiden);
+    /// ```
+    #[must_use]
+    pub fn new(n_neighbors: usize, metric: M) -> Self {
+        Self {
+            n_neighbors,
+            resolution: A::one(),
+            algorithm: CommunityAlgorithm::default(),
+            metric,
+            modularity: A::zero(),
+        }
+    }
+
+    /// Returns the modularity of the communities of the last fit, with
+    /// `resolution`, or zero if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{CommunityDetection, Fit};
+    ///
+    /// let points = array![[0_f64, 0.], [0.1, 0.], [0., 0.1], [5., 5.], [5.1, 5.], [5., 5.1]];
+    /// let mut leiden = CommunityDetection::new(2, Euclidean::default());
+    /// assert_eq!(leiden.modularity(), 0.);
+    ///
+    /// leiden.fit(&points, None);
+    /// // the two groups are not connected
+    /// assert!((leiden.modularity() - 0.5).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn modularity(&self) -> A {
+        self.modularity
+    }
+}
+
+impl<A, M> CommunityDetection<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive,
+{
+    /// Detects the communities of a precomputed k-nearest-neighbor graph,
+    /// such as one returned by [`knn_graph`](crate::knn_graph), ignoring
+    /// `self.n_neighbors` and `self.metric`.
+    ///
+    /// Each point is connected to the neighbors in its row of the graph
+    /// other than itself.
+    ///
+    /// # Panics
+    /// Panics if a neighbor is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{knn_graph, CommunityAlgorithm, CommunityDetection};
+    ///
+    /// let points = array![[0.], [0.1], [0.2], [0.3], [5.], [5.1], [5.2], [5.3]];
+    /// let graph = knn_graph(&points, 3, &Euclidean::default());
+    /// let mut louvain = CommunityDetection::new(2, Euclidean::default());
+    /// louvain.algorithm = CommunityAlgorithm::Louvain;
+    /// let (clusters, _) = louvain.fit_knn_graph(&graph);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2, 3]);
+    /// assert_eq!(clusters[&1], [4, 5, 6, 7]);
+    /// ```
+    pub fn fit_knn_graph(
+        &mut self,
+        graph: &KnnGraph<A>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n = graph.indices.nrows();
+        let half = A::from_f64(0.5).expect("valid float");
+        let mut weights: Vec<HashMap<usize, A>> = vec![HashMap::new(); n];
+        for (i, neighbors) in graph.indices.rows().into_iter().enumerate() {
+            for &j in neighbors.iter().filter(|&&j| j != i) {
+                assert!(j < n, "neighbor {j} is out of bounds");
+                *weights[i].entry(j).or_insert_with(A::zero) += half;
+                *weights[j].entry(i).or_insert_with(A::zero) += half;
+            }
+        }
+        let graph = Graph::new(
+            weights
+                .into_iter()
+                .map(|neighbors| {
+                    let mut neighbors: Vec<_> = neighbors.into_iter().collect();
+                    neighbors.sort_unstable_by_key(|(j, _)| *j);
+                    neighbors
+                })
+                .collect(),
+        );
+
+        let _phase = phase!("communities", n_points = n);
+        let labels = match self.algorithm {
+            CommunityAlgorithm::Louvain => louvain(&graph, self.resolution),
+            CommunityAlgorithm::Leiden => leiden(&graph, self.resolution),
+        };
+        self.modularity = graph.modularity(&labels, self.resolution);
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, label) in labels.into_iter().enumerate() {
+            clusters.entry(label).or_default().push(i);
+        }
+        counts!(n_clusters = clusters.len());
+        (clusters, Vec::new())
+    }
+}
+
+/// Detects the communities of the k-nearest-neighbor graph of the input.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: The communities, numbered in the order of their first point.
+/// - `Vec<usize>`: The noise points, which there are none of.
+///
+/// # Panics
+/// Panics if a distance between points is NaN, as for points with a NaN coordinate.
+impl<S, A, M>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for CommunityDetection<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let _phase = phase!("fit", n_points = input.nrows(), n_features = input.ncols());
+        let graph = knn_graph(input, self.n_neighbors + 1, &self.metric);
+        self.fit_knn_graph(&graph)
+    }
+}
+
+/// An undirected weighted graph, with the degree of each node.
+struct Graph<A> {
+    /// The neighbors of each node other than itself, with the weights of
+    /// the edges.
+    neighbors: Vec<Vec<(usize, A)>>,
+
+    /// The weight of the edge from each node to itself, which counts the
+    /// edges within the nodes merged into it twice.
+    self_loops: Vec<A>,
+
+    /// The sum of the weights of the edges of each node, its self-loop
+    /// included.
+    degrees: Vec<A>,
+
+    /// The sum of the degrees, twice the total weight of the edges.
+    total: A,
+}
+
+impl<A: FloatCore + AddAssign> Graph<A> {
+    fn new(neighbors: Vec<Vec<(usize, A)>>) -> Self {
+        let self_loops = vec![A::zero(); neighbors.len()];
+        Self::with_self_loops(neighbors, self_loops)
+    }
+
+    fn with_self_loops(neighbors: Vec<Vec<(usize, A)>>, self_loops: Vec<A>) -> Self {
+        let degrees: Vec<A> = neighbors
+            .iter()
+            .zip(&self_loops)
+            .map(|(neighbors, self_loop)| neighbors.iter().fold(*self_loop, |sum, (_, w)| sum + *w))
+            .collect();
+        let total = degrees.iter().fold(A::zero(), |sum, d| sum + *d);
+        Self {
+            neighbors,
+            self_loops,
+            degrees,
+            total,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// Merges the nodes of each of the `n_communities` communities into a
+    /// node.
+    fn aggregate(&self, labels: &[usize], n_communities: usize) -> Self {
+        let mut self_loops = vec![A::zero(); n_communities];
+        let mut weights: Vec<HashMap<usize, A>> = vec![HashMap::new(); n_communities];
+        for (i, neighbors) in self.neighbors.iter().enumerate() {
+            let c = labels[i];
+            self_loops[c] += self.self_loops[i];
+            for &(j, w) in neighbors {
+                if labels[j] == c {
+                    self_loops[c] += w;
+                } else {
+                    *weights[c].entry(labels[j]).or_insert_with(A::zero) += w;
+                }
+            }
+        }
+        let neighbors = weights
+            .into_iter()
+            .map(|neighbors| {
+                let mut neighbors: Vec<_> = neighbors.into_iter().collect();
+                neighbors.sort_unstable_by_key(|(j, _)| *j);
+                neighbors
+            })
+            .collect();
+        Self::with_self_loops(neighbors, self_loops)
+    }
+
+    /// Returns the modularity of the partition of the nodes given by
+    /// `labels`, which are less than the number of nodes.
+    fn modularity(&self, labels: &[usize], resolution: A) -> A {
+        if self.total == A::zero() {
+            return A::zero();
+        }
+        let mut within = vec![A::zero(); self.len()];
+        let mut degrees = vec![A::zero(); self.len()];
+        for (i, neighbors) in self.neighbors.iter().enumerate() {
+            let c = labels[i];
+            degrees[c] += self.degrees[i];
+            within[c] += self.self_loops[i];
+            for &(j, w) in neighbors {
+                if labels[j] == c {
+                    within[c] += w;
+                }
+            }
+        }
+        within
+            .iter()
+            .zip(&degrees)
+            .fold(A::zero(), |q, (within, degree)| {
+                q + *within / self.total
+                    - resolution * (*degree / self.total) * (*degree / self.total)
+            })
+    }
+
+    /// Moves each node to the community of its neighbors that increases the
+    /// modularity the most, until no node moves, and returns whether any
+    /// did.
+    fn move_nodes(&self, labels: &mut [usize], resolution: A) -> bool {
+        let n = self.len();
+        let mut community_degrees = vec![A::zero(); n];
+        for (i, degree) in self.degrees.iter().enumerate() {
+            community_degrees[labels[i]] += *degree;
+        }
+        let mut weights = vec![A::zero(); n];
+        let mut touched = Vec::new();
+        let mut moved = false;
+        loop {
+            let mut changed = false;
+            for i in 0..n {
+                let current = labels[i];
+                for &(j, w) in &self.neighbors[i] {
+                    if weights[labels[j]] == A::zero() {
+                        touched.push(labels[j]);
+                    }
+                    weights[labels[j]] += w;
+                }
+                let degree = self.degrees[i];
+                community_degrees[current] = community_degrees[current] - degree;
+                let gain = |c: usize, weight: A| {
+                    weight - resolution * degree * community_degrees[c] / self.total
+                };
+
+                let mut best = (current, gain(current, weights[current]));
+                for &c in &touched {
+                    let g = gain(c, weights[c]);
+                    if g > best.1 {
+                        best = (c, g);
+                    }
+                }
+                community_degrees[best.0] += degree;
+                if best.0 != current {
+                    labels[i] = best.0;
+                    changed = true;
+                }
+                for c in touched.drain(..) {
+                    weights[c] = A::zero();
+                }
+            }
+            if !changed {
+                break;
+            }
+            moved = true;
+        }
+        moved
+    }
+
+    /// Splits each community into well-connected parts: starting from a
+    /// part for each node, each node that is still alone and well connected
+    /// to its community joins the well-connected part of its community that
+    /// increases the modularity the most, if any does not decrease it.
+    fn refine(&self, labels: &[usize], resolution: A) -> Vec<usize> {
+        let n = self.len();
+        let mut community_degrees = vec![A::zero(); n];
+        for (i, degree) in self.degrees.iter().enumerate() {
+            community_degrees[labels[i]] += *degree;
+        }
+        // the weight of the edges from each part to the rest of its community
+        let mut external: Vec<A> = (0..n)
+            .map(|i| {
+                self.neighbors[i]
+                    .iter()
+                    .filter(|(j, _)| labels[*j] == labels[i])
+                    .fold(A::zero(), |sum, (_, w)| sum + *w)
+            })
+            .collect();
+        let mut refined: Vec<usize> = (0..n).collect();
+        let mut part_degrees = self.degrees.clone();
+        let mut sizes = vec![1_usize; n];
+        let well_connected = |external: A, degree: A, community: usize| {
+            external >= resolution * degree * (community_degrees[community] - degree) / self.total
+        };
+
+        let mut weights = vec![A::zero(); n];
+        let mut touched = Vec::new();
+        for i in 0..n {
+            let community = labels[i];
+            if sizes[refined[i]] > 1 || !well_connected(external[i], self.degrees[i], community) {
+                continue;
+            }
+            for &(j, w) in &self.neighbors[i] {
+                if labels[j] != community {
+                    continue;
+                }
+                if weights[refined[j]] == A::zero() {
+                    touched.push(refined[j]);
+                }
+                weights[refined[j]] += w;
+            }
+            let degree = self.degrees[i];
+            let mut best: Option<(usize, A)> = None;
+            for &part in &touched {
+                if !well_connected(external[part], part_degrees[part], community) {
+                    continue;
+                }
+                let gain = weights[part] - resolution * degree * part_degrees[part] / self.total;
+                if gain >= A::zero() && best.map_or(true, |(_, g)| gain > g) {
+                    best = Some((part, gain));
+                }
+            }
+            if let Some((part, _)) = best {
+                external[part] = external[part] + external[i] - weights[part] - weights[part];
+                part_degrees[part] += degree;
+                sizes[part] += 1;
+                sizes[i] = 0;
+                refined[i] = part;
+            }
+            for part in touched.drain(..) {
+                weights[part] = A::zero();
+            }
+        }
+        refined
+    }
+}
+
+/// Numbers the labels contiguously in the order of their first node, and
+/// returns the number of labels.
+fn renumber(labels: &mut [usize]) -> usize {
+    let mut ids = vec![usize::MAX; labels.len()];
+    let mut n = 0;
+    for label in labels.iter_mut() {
+        if ids[*label] == usize::MAX {
+            ids[*label] = n;
+            n += 1;
+        }
+        *label = ids[*label];
+    }
+    n
+}
+
+/// Returns the community of each node found by the Louvain algorithm.
+fn louvain<A: FloatCore + AddAssign>(graph: &Graph<A>, resolution: A) -> Vec<usize> {
+    let mut membership: Vec<usize> = (0..graph.len()).collect();
+    let mut aggregated: Option<Graph<A>> = None;
+    loop {
+        let graph = aggregated.as_ref().unwrap_or(graph);
+        let mut labels: Vec<usize> = (0..graph.len()).collect();
+        if !graph.move_nodes(&mut labels, resolution) {
+            break;
+        }
+        let n_communities = renumber(&mut labels);
+        for m in &mut membership {
+            *m = labels[*m];
+        }
+        aggregated = Some(graph.aggregate(&labels, n_communities));
+    }
+    renumber(&mut membership);
+    membership
+}
+
+/// Returns the community of each node found by the Leiden algorithm.
+fn leiden<A: FloatCore + AddAssign>(graph: &Graph<A>, resolution: A) -> Vec<usize> {
+    let mut membership: Vec<usize> = (0..graph.len()).collect();
+    let mut labels: Vec<usize> = (0..graph.len()).collect();
+    let mut aggregated: Option<Graph<A>> = None;
+    loop {
+        let graph = aggregated.as_ref().unwrap_or(graph);
+        graph.move_nodes(&mut labels, resolution);
+        let n_communities = renumber(&mut labels);
+        if n_communities == graph.len() {
+            break;
+        }
+        let mut refined = graph.refine(&labels, resolution);
+        let n_parts = renumber(&mut refined);
+        if n_parts == graph.len() {
+            break;
+        }
+        // each part starts in the community it was refined from
+        let mut next = vec![0; n_parts];
+        for (part, label) in refined.iter().zip(&labels) {
+            next[*part] = *label;
+        }
+        for m in &mut membership {
+            *m = refined[*m];
+        }
+        aggregated = Some(graph.aggregate(&refined, n_parts));
+        labels = next;
+    }
+    let mut communities: Vec<usize> = membership.iter().map(|m| labels[*m]).collect();
+    renumber(&mut communities);
+    communities
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    /// Two cliques of four nodes joined by an edge between nodes 3 and 4.
+    fn two_cliques() -> Graph<f64> {
+        let mut neighbors = vec![Vec::new(); 8];
+        for group in [0..4, 4..8] {
+            for i in group.clone() {
+                for j in group.clone().filter(|j| *j != i) {
+                    neighbors[i].push((j, 1.));
+                }
+            }
+        }
+        neighbors[3].push((4, 1.));
+        neighbors[4].insert(0, (3, 1.));
+        Graph::new(neighbors)
+    }
+
+    #[test]
+    fn modularity() {
+        let graph = two_cliques();
+        assert!(f64::abs(graph.total - 26.) < 1e-12);
+        // 2 * (12 / 26 - (13 / 26)^2)
+        let q = graph.modularity(&[0, 0, 0, 0, 1, 1, 1, 1], 1.);
+        assert!(f64::abs(q - (24. / 26. - 0.5)) < 1e-12);
+        assert!(graph.modularity(&[0; 8], 1.).abs() < 1e-12);
+
+        // merging preserves the modularity
+        let merged = graph.aggregate(&[0, 0, 0, 0, 1, 1, 1, 1], 2);
+        assert_eq!(merged.self_loops, [12., 12.]);
+        assert!(f64::abs(merged.modularity(&[0, 1], 1.) - q) < 1e-12);
+    }
+
+    #[test]
+    fn louvain_and_leiden() {
+        let graph = two_cliques();
+        let expected = [0, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(louvain(&graph, 1.), expected);
+        assert_eq!(leiden(&graph, 1.), expected);
+        // a low resolution favors a single community
+        assert_eq!(leiden(&graph, 0.01), [0; 8]);
+    }
+
+    #[test]
+    fn refine() {
+        // two triangles joined by nothing
+        let graph = Graph::new(vec![
+            vec![(1, 1.), (2, 1.)],
+            vec![(0, 1.), (2, 1.)],
+            vec![(0, 1.), (1, 1.)],
+            vec![(4, 1.), (5, 1.)],
+            vec![(3, 1.), (5, 1.)],
+            vec![(3, 1.), (4, 1.)],
+        ]);
+        let mut refined = graph.refine(&[0, 0, 0, 1, 1, 1], 1.);
+        renumber(&mut refined);
+        assert_eq!(refined, [0, 0, 0, 1, 1, 1]);
+
+        // a community of both is never refined into parts that span them
+        let refined = graph.refine(&[0; 6], 1.);
+        assert!(refined[..3].iter().all(|part| *part < 3));
+        assert!(refined[3..].iter().all(|part| *part >= 3));
+    }
+
+    #[test]
+    fn fit() {
+        let data = array![
+            [0.0, 0.0],
+            [0.1, 0.0],
+            [0.0, 0.1],
+            [0.1, 0.1],
+            [0.05, 0.05],
+            [5.0, 5.0],
+            [5.1, 5.0],
+            [5.0, 5.1],
+            [5.1, 5.1],
+            [5.05, 5.05],
+        ];
+        for algorithm in [CommunityAlgorithm::Louvain, CommunityAlgorithm::Leiden] {
+            let mut model = CommunityDetection::new(3, Euclidean::default());
+            model.algorithm = algorithm;
+            let (clusters, noise) = model.fit(&data, None);
+            assert_eq!(clusters[&0], [0, 1, 2, 3, 4]);
+            assert_eq!(clusters[&1], [5, 6, 7, 8, 9]);
+            assert!(noise.is_empty());
+            assert!(f64::abs(model.modularity() - 0.5) < 1e-12);
+        }
+
+        let mut model = CommunityDetection::new(3, Euclidean::default());
+        let (clusters, _) = model.fit(&array![[0.0, 0.0]], None);
+        assert_eq!(clusters[&0], [0]);
+        assert!(model.modularity().abs() < 1e-12);
+    }
+}
@@ -0,0 +1,275 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::float::FloatCore;
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::union_find::TreeUnionFind;
+
+/// Grid-based clustering, which bins the points into cells and merges
+/// adjacent dense cells.
+///
+/// A cell is dense if it contains at least `min_points` points, and two cells
+/// are adjacent if their coordinates differ by at most one along every axis.
+/// Connected dense cells form a cluster, as core points do in DBSCAN. A sparse
+/// cell adjacent to dense cells joins the cluster of the one with the most
+/// points, and the points of other sparse cells are noise.
+///
+/// Binning takes linear time and no neighbor search is needed, so this is
+/// much faster than [`Dbscan`](crate::Dbscan) on large 2D or 3D inputs such as
+/// geospatial data, at the cost of cluster boundaries aligned with the grid.
+/// Each cell has `3^d - 1` adjacent cells in `d` dimensions, so it is not
+/// suited to high-dimensional data.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, GridClustering};
+///
+/// let points = array![[0.1, 0.2], [0.3, 0.4], [1.2, 0.5], [1.4, 0.1], [8., 8.], [8.1, 8.3]];
+/// let (clusters, noise) = GridClustering::new(1., 2).fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(clusters[&0], [0, 1, 2, 3]);  // two adjacent dense cells
+/// assert_eq!(clusters[&1], [4, 5]);
+/// assert!(noise.is_empty());
+/// ```
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GridClustering<A> {
+    /// The width of the cells along every axis.
+    pub cell_size: A,
+
+    /// The minimum number of points in a dense cell.
+    pub min_points: usize,
+}
+
+impl<A> Default for GridClustering<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(A::one(), 5)
+    }
+}
+
+impl<A> GridClustering<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model with cells of width `cell_size`, which are dense if
+    /// they contain at least `min_points` points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::GridClustering;
+    ///
+    /// let grid = GridClustering::new(0.5, 3);
+    /// assert_eq!(grid.cell_size, 0.5);
+    /// assert_eq!(grid.min_points, 3);
+    /// ```
+    #[must_use]
+    pub fn new(cell_size: A, min_points: usize) -> Self {
+        Self {
+            cell_size,
+            min_points,
+        }
+    }
+}
+
+/// Fits grid-based clustering to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+///   Clusters are numbered in the order of their first point.
+/// - `Vec<usize>`: A vector of indices representing the noise points, including points with
+///   non-finite coordinates.
+///
+/// # Panics
+/// Panics if `cell_size` is not positive and finite.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for GridClustering<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            self.cell_size > A::zero() && self.cell_size.is_finite(),
+            "`cell_size` must be positive and finite"
+        );
+
+        let mut cell_ids: HashMap<Vec<i64>, usize> = HashMap::new();
+        let mut sizes: Vec<usize> = Vec::new();
+        let point_cells: Vec<Option<usize>> = input
+            .rows()
+            .into_iter()
+            .map(|p| {
+                let cell = cell_of(p.iter().copied(), self.cell_size)?;
+                let id = *cell_ids.entry(cell).or_insert_with(|| {
+                    sizes.push(0);
+                    sizes.len() - 1
+                });
+                sizes[id] += 1;
+                Some(id)
+            })
+            .collect();
+
+        let offsets = neighbor_offsets(input.ncols());
+        let dense: Vec<bool> = sizes.iter().map(|s| *s >= self.min_points).collect();
+        let mut uf = TreeUnionFind::new(sizes.len());
+        let mut border = vec![None; sizes.len()];
+        for (cell, &id) in &cell_ids {
+            let dense_adjacent = adjacent(cell, &offsets)
+                .filter_map(|a| cell_ids.get(&a).copied())
+                .filter(|other| dense[*other]);
+            if dense[id] {
+                for other in dense_adjacent {
+                    uf.union(id, other);
+                }
+            } else {
+                border[id] = dense_adjacent.max_by_key(|other| (sizes[*other], Reverse(*other)));
+            }
+        }
+
+        let mut labels: HashMap<usize, usize> = HashMap::new();
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = Vec::new();
+        for (i, cell) in point_cells.into_iter().enumerate() {
+            let core = cell.and_then(|id| if dense[id] { Some(id) } else { border[id] });
+            let Some(core) = core else {
+                noise.push(i);
+                continue;
+            };
+            let root = uf.find(core);
+            let n_clusters = labels.len();
+            let label = *labels.entry(root).or_insert(n_clusters);
+            clusters.entry(label).or_default().push(i);
+        }
+        (clusters, noise)
+    }
+}
+
+/// Returns the coordinates of the cell of width `cell_size` containing the
+/// given point, or `None` if a coordinate is not finite or out of range.
+pub(crate) fn cell_of<A: FloatCore>(
+    point: impl IntoIterator<Item = A>,
+    cell_size: A,
+) -> Option<Vec<i64>> {
+    point
+        .into_iter()
+        .map(|v| (v / cell_size).floor().to_i64())
+        .collect()
+}
+
+/// Returns the offsets from a cell to its adjacent cells in `dim`
+/// dimensions, including the cell itself.
+///
+/// # Panics
+/// Panics if there are more than `usize::MAX` offsets.
+pub(crate) fn neighbor_offsets(dim: usize) -> Vec<Vec<i64>> {
+    let n_offsets = u32::try_from(dim)
+        .ok()
+        .and_then(|d| 3_usize.checked_pow(d))
+        .expect("too many dimensions for a grid");
+    (0..n_offsets)
+        .map(|mut o| {
+            (0..dim)
+                .map(|_| {
+                    let offset = [-1, 0, 1][o % 3];
+                    o /= 3;
+                    offset
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the cells adjacent to `cell`, including itself.
+pub(crate) fn adjacent<'a>(
+    cell: &'a [i64],
+    offsets: &'a [Vec<i64>],
+) -> impl Iterator<Item = Vec<i64>> + 'a {
+    offsets.iter().map(move |offset| {
+        cell.iter()
+            .zip(offset)
+            .map(|(c, o)| c.saturating_add(*o))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn grid_clustering() {
+        let data = array![
+            [0.1, 0.1],
+            [0.2, 0.3],
+            [0.4, 0.2],
+            [1.5, 1.5], // border cell next to the first dense cell
+            [5.1, 5.1],
+            [5.2, 5.3],
+            [5.4, 5.2],
+            [6.2, 5.3],
+            [6.3, 5.1],
+            [6.5, 5.6],
+            [9.5, 0.5], // isolated
+        ];
+        let (clusters, noise) = GridClustering::new(1., 3).fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[&0], [0, 1, 2, 3]);
+        assert_eq!(clusters[&1], [4, 5, 6, 7, 8, 9]);
+        assert_eq!(noise, [10]);
+    }
+
+    #[test]
+    fn border_joins_largest_dense_cell() {
+        let data = array![[0.5], [0.6], [1.5], [2.5], [2.6], [2.7]];
+        let (clusters, noise) = GridClustering::new(1., 2).fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[&0], [0, 1]);
+        assert_eq!(clusters[&1], [2, 3, 4, 5]);
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    fn non_finite_points_are_noise() {
+        let data = array![[0.1, 0.1], [0.2, 0.2], [f64::NAN, 0.1], [f64::INFINITY, 0.]];
+        let (clusters, noise) = GridClustering::new(1., 2).fit(&data, None);
+        assert_eq!(clusters[&0], [0, 1]);
+        assert_eq!(noise, [2, 3]);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 2));
+        let (clusters, noise) = GridClustering::default().fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    fn neighbor_offsets() {
+        let offsets = super::neighbor_offsets(2);
+        assert_eq!(offsets.len(), 9);
+        assert!(offsets.contains(&vec![0, 0]));
+        assert!(offsets.contains(&vec![-1, 1]));
+    }
+}
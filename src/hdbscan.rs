@@ -3,14 +3,21 @@ use std::fmt::Debug;
 use std::ops::{AddAssign, DivAssign, Sub};
 
 use itertools::Itertools;
-use ndarray::{Array1, ArrayBase, ArrayView1, Data, Ix2};
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
 use num_traits::{float::FloatCore, FromPrimitive};
 use petal_neighbors::distance::{Euclidean, Metric};
 use petal_neighbors::BallTree;
 use serde::{Deserialize, Serialize};
 
-use super::Fit;
+use super::{Fit, Transform};
+use crate::dendrogram::Dendrogram;
+use crate::diagnostics::finite_rows;
+use crate::interrupt::{unchecked, LimitExceeded, Limits, CHUNK_SIZE};
 use crate::mst::{condense_mst, mst_linkage, Boruvka};
+use crate::noise::assign_noise;
+use crate::parallel::prelude::*;
+use crate::sum::Sum;
+use crate::trace::{counts, phase};
 use crate::union_find::TreeUnionFind;
 
 /// HDBSCAN (hierarchical density-based spatial clustering of applications with noise)
@@ -31,32 +38,58 @@ use crate::union_find::TreeUnionFind;
 ///             [-2.0, 3.0],
 ///             [-2.2, 3.1],
 ///         ];
-/// let mut hdbscan = HDbscan {
-///    alpha: 1.,
-///    min_samples: 2,
-///    min_cluster_size: 2,
-///    metric: Euclidean::default(),
-///    boruvka: false,
-///    extraction: ClusterExtraction::ExcessOfMass,
-/// };
+/// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+/// hdbscan.boruvka = false;
+/// hdbscan.extraction = ClusterExtraction::ExcessOfMass;
 /// let (clusters, noise, _outlier_scores) = hdbscan.fit(&points, None);
 /// assert_eq!(clusters.len(), 2);   // two clusters found
 ///
 /// assert_eq!(
 ///     noise.len(),
 ///     points.nrows() - clusters.values().fold(0, |acc, v| acc + v.len()));
+///
+/// // the GLOSH outlier scores are kept for later use
+/// assert_eq!(hdbscan.outlier_scores().len(), points.nrows());
 /// ```
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HDbscan<A, M> {
     /// The radius of a neighborhood.
     pub alpha: A,
 
-    /// The minimum number of points required to form a dense region.
+    /// The number of neighbors, including the point itself, used to compute
+    /// the core distance of a point. Smaller values make the clustering less
+    /// conservative, declaring fewer points as noise.
     pub min_samples: usize,
+
+    /// The minimum number of points in a cluster. Groups of fewer points are
+    /// considered points falling out of a cluster rather than a split.
     pub min_cluster_size: usize,
     pub metric: M,
     pub boruvka: bool,
     pub extraction: ClusterExtraction,
+
+    /// Selected clusters born below this distance are merged into their
+    /// closest ancestor born above it. Zero disables merging.
+    #[serde(
+        default = "A::zero",
+        bound(deserialize = "A: FloatCore + Deserialize<'de>")
+    )]
+    pub cluster_selection_epsilon: A,
+
+    #[serde(default)]
+    outlier_scores: Vec<A>,
+    #[serde(default)]
+    exemplars: HashMap<usize, Vec<usize>>,
+    #[serde(default)]
+    cluster_persistence: HashMap<usize, A>,
+    #[serde(default)]
+    probabilities: Vec<A>,
+    #[serde(default)]
+    exemplar_points: HashMap<usize, Vec<A>>,
+    #[serde(default)]
+    hierarchy: Dendrogram<A>,
+    #[serde(default)]
+    lambda_thresholds: HashMap<usize, (A, A)>,
 }
 
 impl<A> Default for HDbscan<A, Euclidean>
@@ -64,20 +97,202 @@ where
     A: FloatCore,
 {
     fn default() -> Self {
+        Self::new(15, Euclidean::default())
+    }
+}
+
+impl<A, M> HDbscan<A, M>
+where
+    A: FloatCore,
+{
+    /// Creates HDBSCAN with the given minimum cluster size, setting
+    /// `min_samples` to the same value. `min_samples` can be changed
+    /// independently afterwards, e.g. lowered to declare fewer points as
+    /// noise while keeping large clusters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::HDbscan;
+    ///
+    /// let mut hdbscan = HDbscan::<f64, _>::new(5, Euclidean::default());
+    /// assert_eq!(hdbscan.min_samples, 5);
+    ///
+    /// hdbscan.min_samples = 2;
+    /// assert_eq!(hdbscan.min_cluster_size, 5);
+    /// ```
+    #[must_use]
+    pub fn new(min_cluster_size: usize, metric: M) -> Self {
         Self {
             alpha: A::one(),
-            min_samples: 15,
-            min_cluster_size: 15,
-            metric: Euclidean::default(),
+            min_samples: min_cluster_size,
+            min_cluster_size,
+            metric,
             boruvka: true,
             extraction: ClusterExtraction::ExcessOfMass,
+            cluster_selection_epsilon: A::zero(),
+            outlier_scores: vec![],
+            exemplars: HashMap::new(),
+            cluster_persistence: HashMap::new(),
+            probabilities: vec![],
+            exemplar_points: HashMap::new(),
+            hierarchy: Dendrogram::default(),
+            lambda_thresholds: HashMap::new(),
         }
     }
+
+    /// Returns the GLOSH outlier score of each point in the input of the last
+    /// fit, between 0 (an inlier) and 1 (an outlier), or an empty slice if
+    /// the model has not been fitted.
+    ///
+    /// A point's score compares the density at which it leaves its cluster
+    /// to the highest density in the cluster, so the same fit can be used to
+    /// detect anomalies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    /// ];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// hdbscan.fit(&points, None);
+    ///
+    /// let scores = hdbscan.outlier_scores();
+    /// assert_eq!(scores.len(), points.nrows());
+    /// assert!(scores.iter().all(|&score| (0_f64..=1.).contains(&score)));
+    /// ```
+    #[must_use]
+    pub fn outlier_scores(&self) -> &[A] {
+        &self.outlier_scores
+    }
+
+    /// Returns the exemplars of each cluster of the last fit, keyed by
+    /// cluster ID, or an empty map if the model has not been fitted.
+    ///
+    /// The exemplars of a cluster are the points that stay in it up to the
+    /// highest density in each of its leaves in the condensed tree, i.e. the
+    /// cores of its densest regions, in increasing order. They represent the
+    /// cluster better than its centroid when it is not convex, e.g. to label
+    /// it or to assign new points to the cluster of their nearest exemplar.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+    /// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+    /// let (clusters, _, _) = hdbscan.fit(&points, None);
+    ///
+    /// for (id, exemplars) in hdbscan.exemplars() {
+    ///     assert!(exemplars.iter().all(|i| clusters[id].contains(i)));
+    /// }
+    /// ```
+    #[must_use]
+    pub fn exemplars(&self) -> &HashMap<usize, Vec<usize>> {
+        &self.exemplars
+    }
+
+    /// Returns the persistence of each cluster of the last fit, keyed by
+    /// cluster ID, or an empty map if the model has not been fitted.
+    ///
+    /// The persistence is the stability of the cluster in the condensed tree
+    /// divided by its size and the highest density in the tree, between 0
+    /// for a cluster that vanishes as soon as it appears and 1 for one that
+    /// persists over all densities. Weak clusters can be filtered out by
+    /// their persistence after the fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+    /// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+    /// let (clusters, _, _) = hdbscan.fit(&points, None);
+    ///
+    /// let persistence = hdbscan.cluster_persistence();
+    /// assert_eq!(persistence.len(), clusters.len());
+    /// assert!(persistence.values().all(|p| (0. ..=1.).contains(p)));
+    /// ```
+    #[must_use]
+    pub fn cluster_persistence(&self) -> &HashMap<usize, A> {
+        &self.cluster_persistence
+    }
+
+    /// Returns the strength of the membership of each point in the input of
+    /// the last fit in its cluster, between 0 and 1, or an empty slice if
+    /// the model has not been fitted. Noise points have a probability of 0.
+    ///
+    /// The probability of a point is the density at which it leaves its
+    /// cluster relative to the highest density at which any point leaves
+    /// the cluster, so the points at the core of a cluster have a
+    /// probability of 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+    /// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+    /// let (clusters, noise, _) = hdbscan.fit(&points, None);
+    ///
+    /// let probabilities = hdbscan.probabilities();
+    /// assert!(noise.iter().all(|i| probabilities[*i] == 0.));
+    /// for members in clusters.values() {
+    ///     assert!(members.iter().any(|i| probabilities[*i] == 1.));
+    /// }
+    /// ```
+    #[must_use]
+    pub fn probabilities(&self) -> &[A] {
+        &self.probabilities
+    }
+
+    /// Returns the single-linkage tree of the mutual reachability distances
+    /// of the last fit, which the clusters are selected from, or an empty
+    /// hierarchy if the model has not been fitted. Points with a NaN or
+    /// infinite coordinate are never merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+    /// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+    /// hdbscan.fit(&points, None);
+    /// let (clusters, _) = hdbscan.hierarchy().cut_at(2);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2, 3]);
+    /// assert_eq!(clusters[&1], [4, 5]);
+    /// ```
+    #[must_use]
+    pub fn hierarchy(&self) -> &Dendrogram<A> {
+        &self.hierarchy
+    }
 }
 
 /// The cluster extraction method used in HDBSCAN.
 /// - `ExcessOfMass`: Unsupervised clustering using Excess of Mass (`EoM`) algorithm.
 /// - `Fbcubed`: Semi-supervised clustering using F-BCubed (`FBC`) algorithm.
+/// - `Leaf`: Selects the leaves of the condensed tree, which yields many small,
+///   homogeneous clusters.
 ///
 /// # Notes
 /// - `Fbcubed` switches to `ExcessOfMass` when no partial labels are provided.
@@ -87,10 +302,13 @@ where
 ///   ACM Transactions on Knowledge Discovery from Data (TKDD) 10.1 (2015): 1-51.
 /// - Castro Gertrudes, Jadson, et al. "A unified view of density-based methods for semi-supervised clustering and classification."
 ///   Data mining and knowledge discovery 33.6 (2019): 1894-1952.
+/// - Malzer, Claudia, and Marcus Baum. "A hybrid approach to hierarchical density-based cluster selection."
+///   IEEE International Conference on Multisensor Fusion and Integration for Intelligent Systems (2020): 223-228.
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub enum ClusterExtraction {
     ExcessOfMass,
     Fbcubed,
+    Leaf,
 }
 
 /// Fits the HDBSCAN clustering algorithm to the given input data.
@@ -105,6 +323,9 @@ pub enum ClusterExtraction {
 /// - `Vec<usize>`: A vector of indices representing the noise points that do not belong to any cluster.
 /// - `Vec<A>`: A vector of outlier scores for each data point.
 ///
+/// Points with a NaN or infinite coordinate are left out of the hierarchy and reported as noise, with an outlier
+/// score of one.
+///
 /// # Notes
 /// - The outlier scores are computed using the GLOSH algorithm.
 /// - If `partial_labels` is provided, the algorithm will perform semi-supervised clustering using BC (`BCubed`) algorithm,
@@ -131,52 +352,858 @@ where
         input: &ArrayBase<S, Ix2>,
         partial_labels: Option<&HashMap<usize, Vec<usize>>>,
     ) -> (HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>) {
+        unchecked(self.fit_checked(input, partial_labels, &mut |_, _, _| Ok(())))
+    }
+}
+
+impl<A, M> HDbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync + Send,
+    M: Metric<A> + Clone + Sync + Send,
+{
+    /// Fits HDBSCAN unless it exceeds `limits`, in which case it is aborted
+    /// with a [`LimitExceeded`] error.
+    ///
+    /// The limits are checked before and after building the ball tree, after
+    /// each chunk of 4096 core distances if `self.boruvka` is not set, after
+    /// building the minimum spanning tree, and after condensing it. The
+    /// estimated memory covers the input, the ball tree, the minimum
+    /// spanning tree and the condensed tree.
+    ///
+    /// # Errors
+    /// Returns [`LimitExceeded`] if the estimated memory or the time elapsed
+    /// exceeds `limits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{HDbscan, Limit, Limits};
+    ///
+    /// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+    /// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+    ///
+    /// let (clusters, _, _) = hdbscan.fit_with_limits(&points, None, &Limits::default()).unwrap();
+    /// assert_eq!(clusters.len(), 2);
+    ///
+    /// let error = hdbscan
+    ///     .fit_with_limits(&points, None, &Limits::new(Some(64), None))
+    ///     .unwrap_err();
+    /// assert_eq!(error.limit, Limit::Memory(64));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_with_limits<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+        limits: &Limits,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>), LimitExceeded>
+    where
+        S: Data<Elem = A>,
+    {
+        let guard = limits.start();
+        self.fit_checked(input, partial_labels, &mut |phase, processed, memory| {
+            guard.check(phase, processed, memory)
+        })
+    }
+
+    /// Fits HDBSCAN as [`Fit::fit`] does, calling `check` with the phase of
+    /// the fit, the number of points processed in it, and the estimated
+    /// memory in use, at the points documented in
+    /// [`HDbscan::fit_with_limits`], and stopping if it returns an error.
+    #[allow(clippy::type_complexity)]
+    fn fit_checked<S, E>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+        check: &mut impl FnMut(&'static str, usize, usize) -> Result<(), E>,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>), E>
+    where
+        S: Data<Elem = A>,
+    {
         if input.is_empty() {
-            return (HashMap::new(), Vec::new(), Vec::new());
+            self.outlier_scores = Vec::new();
+            self.exemplars = HashMap::new();
+            self.cluster_persistence = HashMap::new();
+            self.probabilities = Vec::new();
+            self.exemplar_points = HashMap::new();
+            self.hierarchy = Dendrogram::default();
+            self.lambda_thresholds = HashMap::new();
+            return Ok((HashMap::new(), Vec::new(), Vec::new()));
+        }
+        if let Some(rows) = finite_rows(input) {
+            return self.fit_finite(input, &rows, partial_labels, check);
         }
+        let _phase = phase!("fit", n_points = input.nrows(), n_features = input.ncols());
         let input = input.as_standard_layout();
-        let db = BallTree::new(input.view(), self.metric.clone()).expect("non-empty array");
-
-        let (mut mst, _offset) = if self.boruvka {
-            let boruvka = Boruvka::new(db, self.min_samples);
-            boruvka.min_spanning_tree().into_raw_vec_and_offset()
-        } else {
-            let core_distances = Array1::from_vec(
-                input
-                    .rows()
-                    .into_iter()
-                    .map(|r| {
-                        db.query(&r, self.min_samples)
+        let n = input.nrows();
+        // the input and the indices of the ball tree
+        let mut memory = input.len() * size_of::<A>() + 2 * n * size_of::<usize>();
+        check("indexing", 0, memory)?;
+        let db = {
+            let _phase = phase!("index_build", n_points = n);
+            BallTree::new(input.view(), self.metric.clone()).expect("non-empty array")
+        };
+        check("indexing", n, memory)?;
+
+        let (mst, _offset) = {
+            let _phase = phase!("mst", n_points = input.nrows(), boruvka = self.boruvka);
+            if input.nrows() == 1 {
+                (Vec::new(), None)
+            } else if self.boruvka {
+                let boruvka = Boruvka::new(db, self.min_samples);
+                boruvka.min_spanning_tree().into_raw_vec_and_offset()
+            } else {
+                let rows: Vec<_> = input.rows().into_iter().collect();
+                let mut core_distances = Vec::with_capacity(n);
+                for chunk in rows.chunks(CHUNK_SIZE) {
+                    core_distances.extend(chunk.iter().map(|r| {
+                        db.query(r, self.min_samples)
                             .1
                             .last()
                             .copied()
                             .expect("at least one point should be returned")
+                    }));
+                    check(
+                        "mst",
+                        core_distances.len(),
+                        memory + core_distances.len() * size_of::<A>(),
+                    )?;
+                }
+                let core_distances = Array1::from_vec(core_distances);
+                mst_linkage(
+                    input.view(),
+                    &self.metric,
+                    core_distances.view(),
+                    self.alpha,
+                )
+                .into_raw_vec_and_offset()
+            }
+        };
+
+        memory += mst.len() * size_of::<(usize, usize, A)>();
+        check("mst", n, memory)?;
+
+        let result = self.cluster_mst(mst, partial_labels, |n_condensed| {
+            check(
+                "condensation",
+                n,
+                memory + n_condensed * size_of::<(usize, usize, A, usize)>(),
+            )
+        })?;
+        self.exemplar_points = self
+            .exemplars
+            .iter()
+            .map(|(id, exemplars)| {
+                let points = exemplars.iter().flat_map(|&i| input.row(i).to_vec());
+                (*id, points.collect())
+            })
+            .collect();
+        Ok(result)
+    }
+}
+
+/// Computes the distance from each point in the given input to each cluster
+/// of the last fit, the distance to the cluster's nearest exemplar, with one
+/// column per cluster in increasing order of cluster ID.
+///
+/// # Panics
+/// Panics if the model has been fitted with [`HDbscan::fit_from_knn`], which
+/// does not see the exemplars' features.
+impl<S, A, M> Transform<ArrayBase<S, Ix2>, Array2<A>> for HDbscan<A, M>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+    M: Metric<A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        assert_eq!(
+            self.exemplar_points.len(),
+            self.exemplars.len(),
+            "`transform` requires a model fitted on points"
+        );
+        let mut ids: Vec<_> = self.exemplar_points.keys().copied().collect();
+        ids.sort_unstable();
+        let dim = input.ncols().max(1);
+        Array2::from_shape_fn((input.nrows(), ids.len()), |(i, c)| {
+            self.exemplar_points[&ids[c]]
+                .chunks_exact(dim)
+                .map(|e| self.metric.distance(&input.row(i), &ArrayView1::from(e)))
+                .fold(A::infinity(), FloatCore::min)
+        })
+    }
+}
+
+impl<A, M> HDbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync + Send,
+{
+    /// Fits HDBSCAN to a precomputed k-nearest-neighbor graph, such as one
+    /// computed on a GPU by an approximate nearest neighbor library.
+    ///
+    /// Row `i` of `indices` and `distances` lists the neighbors of point `i`
+    /// and their distances in increasing order of distance, including the
+    /// point itself, so each row needs at least `min_samples` entries. The
+    /// minimum spanning tree is built over the mutual reachability distances
+    /// of the graph's edges; components the graph leaves disconnected are
+    /// only merged at the root of the hierarchy.
+    ///
+    /// # Returns
+    /// The same tuple as [`Fit::fit`]: the clusters, the noise points, and
+    /// the outlier scores of the points.
+    ///
+    /// # Panics
+    /// Panics if `indices` and `distances` have different shapes, if rows
+    /// have fewer than `min_samples` entries, if an index is not less than
+    /// the number of rows, or if a distance is NaN.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::HDbscan;
+    ///
+    /// // 2-nearest neighbors of points 0, 1, 2 (close together) and 3, 4, 5
+    /// let indices = array![[0, 1], [1, 0], [2, 1], [3, 4], [4, 3], [5, 4]];
+    /// let distances = array![[0., 1.], [0., 1.], [0., 1.], [0., 1.], [0., 1.], [0., 1.]];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// hdbscan.min_samples = 2;
+    /// let (clusters, noise, _) = hdbscan.fit_from_knn(&indices, &distances, None);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert!(noise.is_empty());
+    /// ```
+    pub fn fit_from_knn<S, T>(
+        &mut self,
+        indices: &ArrayBase<S, Ix2>,
+        distances: &ArrayBase<T, Ix2>,
+        partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>)
+    where
+        S: Data<Elem = usize>,
+        T: Data<Elem = A>,
+    {
+        assert_eq!(
+            indices.shape(),
+            distances.shape(),
+            "shapes of indices and distances should match"
+        );
+        if indices.is_empty() {
+            self.outlier_scores = Vec::new();
+            self.exemplars = HashMap::new();
+            self.cluster_persistence = HashMap::new();
+            self.probabilities = Vec::new();
+            self.exemplar_points = HashMap::new();
+            self.hierarchy = Dendrogram::default();
+            self.lambda_thresholds = HashMap::new();
+            return (HashMap::new(), Vec::new(), Vec::new());
+        }
+        assert!(
+            indices.ncols() >= self.min_samples,
+            "each point needs at least `min_samples` neighbors"
+        );
+        let n = indices.nrows();
+        assert!(
+            indices.iter().all(|&j| j < n),
+            "neighbor indices should be less than the number of points"
+        );
+        assert!(
+            !distances.iter().any(|d| d.is_nan()),
+            "neighbor distances should not be NaN"
+        );
+
+        let alpha = self.alpha;
+        let core_distances: Vec<A> = distances
+            .rows()
+            .into_iter()
+            .map(|r| r[self.min_samples.max(1) - 1])
+            .collect();
+
+        let mut edges: Vec<(usize, usize, A)> = indices
+            .rows()
+            .into_iter()
+            .zip(distances.rows())
+            .enumerate()
+            .flat_map(|(i, (neighbors, dists))| {
+                let core_distances = &core_distances;
+                neighbors
+                    .into_iter()
+                    .zip(dists)
+                    .filter(move |(j, _)| **j != i)
+                    .map(move |(j, d)| {
+                        let mut mreach = *d / alpha;
+                        if core_distances[i] > mreach {
+                            mreach = core_distances[i];
+                        }
+                        if core_distances[*j] > mreach {
+                            mreach = core_distances[*j];
+                        }
+                        (i, *j, mreach)
                     })
-                    .collect(),
+            })
+            .collect();
+        edges.sort_unstable_by(|a, b| a.2.partial_cmp(&(b.2)).expect("invalid distance"));
+
+        // Kruskal's algorithm on the mutual reachability graph
+        let mut uf = TreeUnionFind::new(n);
+        let mut mst = Vec::with_capacity(n - 1);
+        for (u, v, w) in edges {
+            if uf.find(u) != uf.find(v) {
+                uf.union(u, v);
+                mst.push((u, v, w));
+            }
+        }
+        // connect what the graph leaves apart at the top of the hierarchy
+        let roots: Vec<_> = (0..n).filter(|&i| uf.find(i) == i).collect();
+        for pair in roots.windows(2) {
+            mst.push((pair[0], pair[1], A::max_value()));
+        }
+
+        self.exemplar_points = HashMap::new();
+        unchecked(self.cluster_mst(mst, partial_labels, |_| Ok(())))
+    }
+
+    /// Assigns the noise points of a clustering of `input` by this model to
+    /// the cluster of their nearest exemplar, if it is within
+    /// `max_distance`, for applications that need every point labeled.
+    ///
+    /// The exemplars are those of the last fit, which should be on the same
+    /// input. Noise points farther than `max_distance` from every exemplar,
+    /// or with a non-finite coordinate, stay noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    ///     [6.0, 2.0],
+    /// ];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// let (clusters, noise, _) = hdbscan.fit(&points, None);
+    /// assert_eq!(noise, [8]);
+    ///
+    /// // the nearest exemplar is [1.0, 2.0]
+    /// let (clusters, noise) = hdbscan.assign_noise_to_nearest(&points, (clusters, noise), 5.);
+    /// assert!(clusters.values().any(|members| members.contains(&0) && members.contains(&8)));
+    /// assert!(noise.is_empty());
+    /// ```
+    pub fn assign_noise_to_nearest<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+        max_distance: A,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+        M: Metric<A> + Clone + Sync,
+    {
+        let exemplars: Vec<_> = self
+            .exemplars
+            .iter()
+            .flat_map(|(id, exemplars)| exemplars.iter().map(|i| (*i, *id)))
+            .collect();
+        assign_noise(
+            &input.view(),
+            clustering,
+            &exemplars,
+            &self.metric,
+            max_distance,
+        )
+    }
+
+    /// Precomputes what [`ApproximatePredictor::approximate_predict`] needs
+    /// to label new points without refitting: the points of `input` with
+    /// their cluster and core distance, and the lambda at which each cluster
+    /// is born and the highest lambda of its points.
+    ///
+    /// `input` and `clusters` should be the input and the clusters of the
+    /// last fit. Points with a NaN or infinite coordinate are left out. The
+    /// returned data can be serialized and used without the model.
+    ///
+    /// # Panics
+    /// Panics if a point of `clusters` is out of bounds, or if a cluster is
+    /// not one of the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    /// ];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// let (clusters, _, _) = hdbscan.fit(&points, None);
+    ///
+    /// let data = hdbscan.generate_prediction_data(&points, &clusters);
+    /// let predictor = data.predictor();
+    /// let (labels, probabilities) = predictor.approximate_predict(&array![[1.0, 2.05], [9.0, 9.0]]);
+    /// assert!(clusters[&labels[0].unwrap()].contains(&0));
+    /// assert!(probabilities[0] > 0.);
+    /// assert_eq!(labels[1], None);
+    /// ```
+    pub fn generate_prediction_data<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clusters: &HashMap<usize, Vec<usize>>,
+    ) -> PredictionData<A, M>
+    where
+        S: Data<Elem = A>,
+        M: Metric<A> + Clone + Sync + Send,
+    {
+        let mut labels = vec![None; input.nrows()];
+        for (id, members) in clusters {
+            assert!(
+                self.lambda_thresholds.contains_key(id),
+                "cluster {id} is not a cluster of the last fit"
             );
-            mst_linkage(
-                input.view(),
-                &self.metric,
-                core_distances.view(),
-                self.alpha,
-            )
-            .into_raw_vec_and_offset()
-        };
+            for &i in members {
+                labels[i] = Some(*id);
+            }
+        }
+        let rows = finite_rows(input).unwrap_or_else(|| (0..input.nrows()).collect());
+        let points = input.select(Axis(0), &rows);
+        PredictionData {
+            min_samples: self.min_samples,
+            metric: self.metric.clone(),
+            n_features: input.ncols(),
+            core_distances: core_distances(&points.view(), self.min_samples, &self.metric),
+            points: points.iter().copied().collect(),
+            labels: rows.iter().map(|i| labels[*i]).collect(),
+            lambda_thresholds: self.lambda_thresholds.clone(),
+        }
+    }
 
+    /// Fits the points of `rows`, those whose coordinates are all finite,
+    /// and reports the others as noise, with an outlier score of one and a
+    /// probability of zero.
+    #[allow(clippy::type_complexity)]
+    fn fit_finite<S, E>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        rows: &[usize],
+        partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+        check: &mut impl FnMut(&'static str, usize, usize) -> Result<(), E>,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>), E>
+    where
+        S: Data<Elem = A>,
+        M: Metric<A> + Clone + Sync + Send,
+    {
+        let partial_labels = partial_labels.map(|labels| {
+            labels
+                .iter()
+                .map(|(label, points)| {
+                    let points = points.iter().filter_map(|i| rows.binary_search(i).ok());
+                    (*label, points.collect::<Vec<_>>())
+                })
+                .collect::<HashMap<_, _>>()
+        });
+        let (clusters, _, scores) =
+            self.fit_checked(&input.select(Axis(0), rows), partial_labels.as_ref(), check)?;
+
+        let n = input.nrows();
+        let clusters: HashMap<_, _> = clusters
+            .into_iter()
+            .map(|(id, members)| (id, members.into_iter().map(|j| rows[j]).collect()))
+            .collect();
+        let mut in_cluster = vec![false; n];
+        for &i in clusters.values().flatten() {
+            in_cluster[i] = true;
+        }
+        let noise = (0..n).filter(|i| !in_cluster[*i]).collect();
+        let mut outlier_scores = vec![A::one(); n];
+        let mut probabilities = vec![A::zero(); n];
+        for (j, &i) in rows.iter().enumerate() {
+            outlier_scores[i] = scores[j];
+            probabilities[i] = self.probabilities[j];
+        }
+        for exemplars in self.exemplars.values_mut() {
+            for i in exemplars {
+                *i = rows[*i];
+            }
+        }
+        self.outlier_scores.clone_from(&outlier_scores);
+        self.probabilities = probabilities;
+        self.hierarchy = self.hierarchy.embed(rows, n);
+        Ok((clusters, noise, outlier_scores))
+    }
+
+    /// Extracts the clusters from the minimum spanning tree `mst`, calling
+    /// `check` with the size of the condensed tree once it is built, and
+    /// stopping if it returns an error.
+    #[allow(clippy::type_complexity)]
+    fn cluster_mst<E>(
+        &mut self,
+        mut mst: Vec<(usize, usize, A)>,
+        partial_labels: Option<&HashMap<usize, Vec<usize>>>,
+        check: impl FnOnce(usize) -> Result<(), E>,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>), E> {
+        if mst.is_empty() {
+            // a single point, which has no hierarchy to extract clusters from
+            self.outlier_scores = vec![A::zero()];
+            self.exemplars = HashMap::new();
+            self.cluster_persistence = HashMap::new();
+            self.probabilities = vec![A::zero()];
+            self.hierarchy = Dendrogram::from_edges(1, &[]);
+            self.lambda_thresholds = HashMap::new();
+            return Ok((HashMap::new(), vec![0], vec![A::zero()]));
+        }
         mst.sort_unstable_by(|a, b| a.2.partial_cmp(&(b.2)).expect("invalid distance"));
-        let labeled = label(&mst);
-        let condensed = condense_mst(&labeled, self.min_cluster_size);
+        self.hierarchy = Dendrogram::from_edges(mst.len() + 1, &mst);
+        let condensed = {
+            let _phase = phase!("condensation", n_edges = mst.len());
+            let condensed = condense_mst(&label(&mst), self.min_cluster_size);
+            counts!(n_condensed = condensed.len());
+            condensed
+        };
+        check(condensed.len())?;
+        let _phase = phase!("extraction", n_condensed = condensed.len());
         let outlier_scores = glosh(&condensed, self.min_cluster_size);
         let (clusters, noise) = find_clusters(
-            &Array1::from_vec(condensed).view(),
+            &ArrayView1::from(&condensed),
             partial_labels,
             self.extraction,
+            self.cluster_selection_epsilon,
+        );
+        counts!(n_clusters = clusters.len(), n_noise = noise.len());
+        self.outlier_scores.clone_from(&outlier_scores);
+        self.exemplars = find_exemplars(&condensed, &clusters);
+        self.cluster_persistence = persistence(&ArrayView1::from(&condensed), &clusters);
+        self.probabilities = probabilities(&condensed, &clusters);
+        self.lambda_thresholds = lambda_thresholds(&condensed, &clusters);
+        Ok((clusters, noise, outlier_scores))
+    }
+}
+
+/// The data [`HDbscan::generate_prediction_data`] precomputes from a fit to
+/// label new points without refitting.
+///
+/// It keeps the training points with their clusters and core distances, and
+/// the lambda thresholds of the clusters, but not the hierarchy. A new point
+/// joins the cluster of the training point it is closest to in mutual
+/// reachability distance, unless that distance is beyond the birth of the
+/// cluster, in which case it is noise.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PredictionData<A, M> {
+    min_samples: usize,
+    metric: M,
+    n_features: usize,
+    points: Vec<A>,
+    labels: Vec<Option<usize>>,
+    core_distances: Vec<A>,
+    lambda_thresholds: HashMap<usize, (A, A)>,
+}
+
+impl<A, M> PredictionData<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive,
+    M: Metric<A> + Clone,
+{
+    /// Builds the ball tree of the training points, to label any number of
+    /// batches of new points with [`ApproximatePredictor::approximate_predict`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    /// ];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// let (clusters, _, _) = hdbscan.fit(&points, None);
+    ///
+    /// let data = hdbscan.generate_prediction_data(&points, &clusters);
+    /// let predictor = data.predictor();
+    /// let (first, _) = predictor.approximate_predict(&array![[1.0, 2.05]]);
+    /// let (second, _) = predictor.approximate_predict(&array![[-2.1, 3.05]]);
+    /// assert_ne!(first[0], second[0]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // one row of `n_features` per label
+    pub fn predictor(&self) -> ApproximatePredictor<'_, A, M> {
+        let points = ArrayView2::from_shape((self.labels.len(), self.n_features), &self.points)
+            .expect("points of the training data");
+        ApproximatePredictor {
+            data: self,
+            tree: BallTree::new(points, self.metric.clone()).ok(),
+        }
+    }
+}
+
+/// Labels new points with the [`PredictionData`] of a fit, returned by
+/// [`PredictionData::predictor`].
+pub struct ApproximatePredictor<'a, A, M> {
+    data: &'a PredictionData<A, M>,
+    tree: Option<BallTree<'a, A, M>>,
+}
+
+impl<A, M> ApproximatePredictor<'_, A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Sync,
+{
+    /// Returns the cluster of each point of `input`, or `None` for noise,
+    /// and the probability that it belongs to that cluster, as
+    /// [`HDbscan::probabilities`] does for the training points.
+    ///
+    /// The core distance of a new point is computed among the training
+    /// points, counting the point itself. Points with a NaN or infinite
+    /// coordinate are noise.
+    ///
+    /// # Panics
+    /// Panics if `input` does not have as many features as the training
+    /// points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, HDbscan};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    /// ];
+    /// let mut hdbscan = HDbscan::new(3, Euclidean::default());
+    /// let (clusters, _, _) = hdbscan.fit(&points, None);
+    ///
+    /// let data = hdbscan.generate_prediction_data(&points, &clusters);
+    /// let (labels, _) = data
+    ///     .predictor()
+    ///     .approximate_predict(&array![[-2.1, 3.05], [f64::NAN, 0.]]);
+    /// assert!(clusters[&labels[0].unwrap()].contains(&4));
+    /// assert_eq!(labels[1], None);
+    /// ```
+    pub fn approximate_predict<S>(&self, input: &ArrayBase<S, Ix2>) -> (Vec<Option<usize>>, Vec<A>)
+    where
+        S: Data<Elem = A>,
+    {
+        assert_eq!(
+            input.ncols(),
+            self.data.n_features,
+            "points should have as many features as the training points"
+        );
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        rows.into_par_iter()
+            .map(|point| self.predict_point(&point))
+            .unzip()
+    }
+
+    fn predict_point(&self, point: &ArrayView1<A>) -> (Option<usize>, A) {
+        let Some(tree) = &self.tree else {
+            return (None, A::zero());
+        };
+        if !point.iter().all(|v| v.is_finite()) {
+            return (None, A::zero());
+        }
+        let data = self.data;
+        let k = (2 * data.min_samples).clamp(1, data.labels.len());
+        let (indices, distances) = tree.query(point, k);
+        let core_distance = if data.min_samples < 2 {
+            A::zero()
+        } else {
+            distances[(data.min_samples - 2).min(distances.len() - 1)]
+        };
+        let Some((nearest, reachability)) = indices
+            .iter()
+            .zip(&distances)
+            .map(|(&j, &d)| (j, d.max(core_distance).max(data.core_distances[j])))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        else {
+            return (None, A::zero());
+        };
+        let Some(id) = data.labels[nearest] else {
+            return (None, A::zero());
+        };
+        let (birth, max) = data.lambda_thresholds[&id];
+        let lambda = reachability.recip();
+        if lambda <= birth {
+            return (None, A::zero());
+        }
+        if max == A::zero() {
+            (Some(id), A::one())
+        } else {
+            (Some(id), lambda.min(max) / max)
+        }
+    }
+}
+
+/// Computes the mutual reachability distance between every pair of points
+/// of the given input, the distance HDBSCAN builds its hierarchy on.
+///
+/// The core distance of a point is the distance to its `min_samples`-th
+/// nearest neighbor, counting the point itself, and the mutual reachability
+/// distance between two points is the largest of their distance and their
+/// core distances. Points in sparse regions are thus pushed away from the
+/// others, while distances within dense regions are unchanged. The diagonal
+/// of the returned matrix is zero.
+///
+/// The matrix takes O(n²) memory; [`mutual_reachability_knn`] only keeps
+/// the distances to the nearest neighbors of each point.
+///
+/// # Panics
+/// Panics if `min_samples` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::mutual_reachability;
+///
+/// let points = array![[0.], [1.], [3.]];
+/// let distances = mutual_reachability(&points, 2, &Euclidean::default());
+/// // the core distances are 1, 1 and 2
+/// assert_eq!(distances, array![[0., 1., 3.], [1., 0., 2.], [3., 2., 0.]]);
+/// ```
+pub fn mutual_reachability<S, A, M>(
+    input: &ArrayBase<S, Ix2>,
+    min_samples: usize,
+    metric: &M,
+) -> Array2<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync + Send,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync + Send,
+{
+    let input = input.as_standard_layout();
+    let core_distances = core_distances(&input.view(), min_samples, metric);
+    let n = input.nrows();
+    let rows: Vec<Vec<A>> = (0..n)
+        .into_par_iter()
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    if i == j {
+                        return A::zero();
+                    }
+                    let d = metric.distance(&input.row(i), &input.row(j));
+                    d.max(core_distances[i]).max(core_distances[j])
+                })
+                .collect()
+        })
+        .collect();
+    Array2::from_shape_vec((n, n), rows.into_iter().flatten().collect())
+        .expect("n rows of n distances")
+}
+
+/// Computes the mutual reachability distances from each point of the given
+/// input to its `n_neighbors` nearest neighbors, counting the point itself.
+///
+/// Row `i` of the returned indices and distances lists the neighbors of
+/// point `i` and their mutual reachability distances, in increasing order of
+/// that distance, with point `i` itself first at distance zero. This is the
+/// sparse counterpart of [`mutual_reachability`], for building custom
+/// hierarchies on large inputs. Points have fewer neighbors than
+/// `n_neighbors` only if the input has fewer points, in which case every
+/// row lists all of them.
+///
+/// # Panics
+/// Panics if `min_samples` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::mutual_reachability_knn;
+///
+/// let points = array![[0.], [1.], [3.]];
+/// let (indices, distances) = mutual_reachability_knn(&points, 2, 2, &Euclidean::default());
+/// assert_eq!(indices, array![[0, 1], [1, 0], [2, 1]]);
+/// assert_eq!(distances, array![[0., 1.], [0., 1.], [0., 2.]]);
+/// ```
+pub fn mutual_reachability_knn<S, A, M>(
+    input: &ArrayBase<S, Ix2>,
+    min_samples: usize,
+    n_neighbors: usize,
+    metric: &M,
+) -> (Array2<usize>, Array2<A>)
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync + Send,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync + Send,
+{
+    let input = input.as_standard_layout();
+    let core_distances = core_distances(&input.view(), min_samples, metric);
+    let k = n_neighbors.min(input.nrows());
+    if k == 0 {
+        return (
+            Array2::zeros((input.nrows(), 0)),
+            Array2::zeros((input.nrows(), 0)),
         );
-        (clusters, noise, outlier_scores)
     }
+    let db = BallTree::new(input.view(), metric.clone()).expect("non-empty array");
+    let rows: Vec<Vec<(usize, A)>> = (0..input.nrows())
+        .into_par_iter()
+        .map(|i| {
+            let (indices, distances) = db.query(&input.row(i), k);
+            let mut row: Vec<(usize, A)> = indices
+                .into_iter()
+                .zip(distances)
+                .map(|(j, d)| {
+                    if i == j {
+                        (j, A::zero())
+                    } else {
+                        (j, d.max(core_distances[i]).max(core_distances[j]))
+                    }
+                })
+                .collect();
+            row.sort_by(|a, b| {
+                (a.0 != i, a.1)
+                    .partial_cmp(&(b.0 != i, b.1))
+                    .expect("invalid distance")
+            });
+            row
+        })
+        .collect();
+    let shape = (input.nrows(), k);
+    let indices = rows.iter().flatten().map(|(j, _)| *j).collect();
+    let distances = rows.iter().flatten().map(|(_, d)| *d).collect();
+    (
+        Array2::from_shape_vec(shape, indices).expect("k neighbors per point"),
+        Array2::from_shape_vec(shape, distances).expect("k neighbors per point"),
+    )
+}
+
+/// Returns the distance from each point to its `min_samples`-th nearest
+/// neighbor, counting the point itself.
+fn core_distances<A, M>(input: &ArrayView2<A>, min_samples: usize, metric: &M) -> Vec<A>
+where
+    A: FloatCore + FromPrimitive + Sync + Send,
+    M: Metric<A> + Clone + Sync + Send,
+{
+    assert!(min_samples > 0, "`min_samples` must be positive");
+    if input.nrows() == 0 {
+        return Vec::new();
+    }
+    let db = BallTree::new(input.view(), metric.clone()).expect("non-empty array");
+    (0..input.nrows())
+        .into_par_iter()
+        .map(|i| {
+            *db.query(&input.row(i), min_samples)
+                .1
+                .last()
+                .expect("at least one point should be returned")
+        })
+        .collect()
 }
 
-fn label<A: FloatCore>(mst: &[(usize, usize, A)]) -> Vec<(usize, usize, A, usize)> {
+pub(crate) fn label<A: FloatCore>(mst: &[(usize, usize, A)]) -> Vec<(usize, usize, A, usize)> {
     let n = mst.len() + 1;
     let mut result: Vec<(usize, usize, A, usize)> = Vec::with_capacity(2 * n);
     let mut next_label = n;
@@ -238,18 +1265,23 @@ fn get_stability<A: FloatCore + FromPrimitive + AddAssign + Sub>(
     let entry = births.entry(min_parent).or_insert_with(A::zero);
     *entry = A::zero();
 
-    condensed_tree.iter().fold(
-        HashMap::new(),
-        |mut stability, (parent, _child, lambda, size)| {
-            let entry = stability.entry(*parent).or_insert_with(A::zero);
-            let birth = births.get(parent).expect("invalid child node.");
-            let Some(size) = A::from_usize(*size) else {
-                panic!("invalid size");
-            };
-            *entry += (*lambda - *birth) * size;
-            stability
-        },
-    )
+    condensed_tree
+        .iter()
+        .fold(
+            HashMap::new(),
+            |mut stability, (parent, _child, lambda, size)| {
+                let entry = stability.entry(*parent).or_insert_with(Sum::new);
+                let birth = births.get(parent).expect("invalid child node.");
+                let Some(size) = A::from_usize(*size) else {
+                    panic!("invalid size");
+                };
+                entry.add((*lambda - *birth) * size);
+                stability
+            },
+        )
+        .into_iter()
+        .map(|(cluster, stability)| (cluster, stability.value()))
+        .collect()
 }
 
 fn get_bcubed<A: FloatCore + FromPrimitive + AddAssign + Sub>(
@@ -323,10 +1355,11 @@ fn get_bcubed<A: FloatCore + FromPrimitive + AddAssign + Sub>(
         })
 }
 
-fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
+pub(crate) fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
     condensed_tree: &ArrayView1<(usize, usize, A, usize)>,
     partial_labels: Option<&HashMap<usize, Vec<usize>>>,
     extraction: ClusterExtraction,
+    cluster_selection_epsilon: A,
 ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
     let mut stability = get_stability(condensed_tree);
     let mut bcubed = if let Some(partial_labels) = partial_labels {
@@ -353,6 +1386,12 @@ fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
         .expect("no maximum parent available")
         .0;
 
+    let num_events = condensed_tree
+        .iter()
+        .min_by_key(|v| v.0)
+        .expect("no minimum parent available")
+        .0;
+
     // bottom-up traverse the nodes to select the most top-level clusters
     let mut clusters: Vec<Option<usize>> = vec![None; num_clusters + 1];
     for node in nodes.iter().rev() {
@@ -379,6 +1418,9 @@ fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
                     *node_bcubed > subtree_bcubed
                         || (*node_bcubed == subtree_bcubed && *node_stability >= subtree_stability)
                 }
+                ClusterExtraction::Leaf => adj
+                    .get(node)
+                    .map_or(true, |children| children.iter().all(|c| *c < num_events)),
             };
 
             if select_node {
@@ -390,21 +1432,22 @@ fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
     }
 
     // now tow-down pass to assign the clusters
-    for node in nodes {
-        if let Some(cluster) = clusters[node] {
-            let children = adj.get(&node).expect("corrupted adjacency dictionary");
-            for child in children {
-                clusters[*child] = Some(cluster);
-            }
+    propagate_clusters(&nodes, &adj, &mut clusters);
+
+    if cluster_selection_epsilon > A::zero() {
+        let selected: Vec<_> = nodes
+            .iter()
+            .copied()
+            .filter(|node| clusters[*node] == Some(*node))
+            .collect();
+        let merged = merge_by_epsilon(condensed_tree, &selected, cluster_selection_epsilon);
+        clusters = vec![None; num_clusters + 1];
+        for node in merged {
+            clusters[node] = Some(node);
         }
+        propagate_clusters(&nodes, &adj, &mut clusters);
     }
 
-    let num_events = condensed_tree
-        .iter()
-        .min_by_key(|v| v.0)
-        .expect("no minimum parent available")
-        .0;
-
     let mut res_clusters: HashMap<_, Vec<_>> = HashMap::new();
     let mut noise = vec![];
     for (point, cluster) in clusters.iter().enumerate().take(num_events) {
@@ -418,6 +1461,207 @@ fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
     (res_clusters, noise)
 }
 
+// Top-down pass assigning the cluster of each selected node to all of its
+// descendants. `nodes` must be sorted, so that parents come before children.
+fn propagate_clusters(
+    nodes: &[usize],
+    adj: &HashMap<usize, Vec<usize>>,
+    clusters: &mut [Option<usize>],
+) {
+    for node in nodes {
+        if let Some(cluster) = clusters[*node] {
+            let children = adj.get(node).expect("corrupted adjacency dictionary");
+            for child in children {
+                clusters[*child] = Some(cluster);
+            }
+        }
+    }
+}
+
+// Replaces each selected cluster born at a distance below `epsilon` with its
+// closest ancestor born above `epsilon`, without ever selecting the root.
+fn merge_by_epsilon<A: FloatCore>(
+    condensed_tree: &ArrayView1<(usize, usize, A, usize)>,
+    selected: &[usize],
+    epsilon: A,
+) -> Vec<usize> {
+    let root = condensed_tree
+        .iter()
+        .map(|(parent, _, _, _)| *parent)
+        .min()
+        .expect("empty condensed tree");
+    let births: HashMap<usize, (usize, A)> = condensed_tree
+        .iter()
+        .filter(|(_, child, _, _)| *child > root)
+        .map(|(parent, child, lambda, _)| (*child, (*parent, *lambda)))
+        .collect();
+
+    // a cluster is born below `epsilon` if its birth lambda is above `1 / epsilon`
+    let max_lambda = A::one() / epsilon;
+    let mut merged: Vec<usize> = selected
+        .iter()
+        .map(|&cluster| {
+            let (_, lambda) = births[&cluster];
+            if lambda <= max_lambda {
+                return cluster;
+            }
+            let mut cur = cluster;
+            loop {
+                let (parent, _) = births[&cur];
+                if parent == root {
+                    return cur;
+                }
+                if births[&parent].1 < max_lambda {
+                    return parent;
+                }
+                cur = parent;
+            }
+        })
+        .collect();
+    merged.sort_unstable();
+    merged.dedup();
+
+    // drop clusters nested in another selected cluster
+    let selected = merged.clone();
+    merged.retain(|&cluster| {
+        let mut cur = cluster;
+        while let Some((parent, _)) = births.get(&cur) {
+            if selected.binary_search(parent).is_ok() {
+                return false;
+            }
+            cur = *parent;
+        }
+        true
+    });
+    merged
+}
+
+// Returns the stability of each cluster divided by its size and the highest
+// lambda in the condensed tree, or one if the highest lambda is not finite.
+fn persistence<A: FloatCore + FromPrimitive + AddAssign + Sub>(
+    condensed_tree: &ArrayView1<(usize, usize, A, usize)>,
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> HashMap<usize, A> {
+    if condensed_tree.is_empty() {
+        return HashMap::new();
+    }
+    let stability = get_stability(condensed_tree);
+    let max_lambda = condensed_tree
+        .iter()
+        .fold(A::zero(), |max, (_, _, lambda, _)| max.max(*lambda));
+    clusters
+        .iter()
+        .map(|(cluster, members)| {
+            let scale = A::from_usize(members.len()).expect("valid float") * max_lambda;
+            let persistence = if scale > A::zero() && scale.is_finite() {
+                stability.get(cluster).map_or(A::zero(), |s| *s / scale)
+            } else {
+                A::one()
+            };
+            (*cluster, persistence)
+        })
+        .collect()
+}
+
+// Returns the lambda at which each point leaves its cluster divided by the
+// highest lambda at which a point leaves the cluster, or zero for noise.
+fn probabilities<A: FloatCore>(
+    condensed_tree: &[(usize, usize, A, usize)],
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> Vec<A> {
+    let Some(num_events) = condensed_tree.iter().map(|(parent, ..)| *parent).min() else {
+        return Vec::new();
+    };
+    let mut lambdas = vec![A::zero(); num_events];
+    for (_, child, lambda, _) in condensed_tree {
+        if *child < num_events {
+            lambdas[*child] = *lambda;
+        }
+    }
+
+    let mut probabilities = vec![A::zero(); num_events];
+    for members in clusters.values() {
+        let max_lambda = members
+            .iter()
+            .fold(A::zero(), |max, i| max.max(lambdas[*i]));
+        for &i in members {
+            probabilities[i] = if max_lambda == A::zero() {
+                A::one()
+            } else {
+                lambdas[i] / max_lambda
+            };
+        }
+    }
+    probabilities
+}
+
+// Returns the lambda at which each cluster is born, zero for the root, and
+// the highest lambda at which one of its points leaves it.
+fn lambda_thresholds<A: FloatCore>(
+    condensed_tree: &[(usize, usize, A, usize)],
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> HashMap<usize, (A, A)> {
+    let lambdas: HashMap<_, _> = condensed_tree
+        .iter()
+        .map(|(_, child, lambda, _)| (*child, *lambda))
+        .collect();
+    clusters
+        .iter()
+        .map(|(cluster, members)| {
+            let birth = lambdas.get(cluster).map_or(A::zero(), |lambda| *lambda);
+            let max = members.iter().fold(A::zero(), |max, i| {
+                lambdas.get(i).map_or(max, |lambda| max.max(*lambda))
+            });
+            (*cluster, (birth, max))
+        })
+        .collect()
+}
+
+// Returns the exemplars of each cluster: the points that fall out of each
+// leaf cluster below it in the condensed tree at the leaf's highest lambda.
+fn find_exemplars<A: FloatCore>(
+    condensed_tree: &[(usize, usize, A, usize)],
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> HashMap<usize, Vec<usize>> {
+    let Some(num_events) = condensed_tree.iter().map(|(parent, ..)| *parent).min() else {
+        return HashMap::new();
+    };
+    let mut child_clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut points: HashMap<usize, Vec<(usize, A)>> = HashMap::new();
+    for (parent, child, lambda, _) in condensed_tree {
+        if *child < num_events {
+            points.entry(*parent).or_default().push((*child, *lambda));
+        } else {
+            child_clusters.entry(*parent).or_default().push(*child);
+        }
+    }
+
+    clusters
+        .keys()
+        .map(|&cluster| {
+            let mut exemplars = Vec::new();
+            let mut to_visit = vec![cluster];
+            while let Some(node) = to_visit.pop() {
+                if let Some(children) = child_clusters.get(&node) {
+                    to_visit.extend(children);
+                    continue;
+                }
+                let leaf = points.get(&node).map_or(&[][..], Vec::as_slice);
+                let max = leaf
+                    .iter()
+                    .fold(A::neg_infinity(), |max, (_, lambda)| max.max(*lambda));
+                exemplars.extend(
+                    leaf.iter()
+                        .filter(|(_, lambda)| *lambda == max)
+                        .map(|(point, _)| *point),
+                );
+            }
+            exemplars.sort_unstable();
+            (cluster, exemplars)
+        })
+        .collect()
+}
+
 // GLOSH: Global-Local Outlier Score from Hierarchies
 // Reference: https://dl.acm.org/doi/10.1145/2733381
 //
@@ -442,7 +1686,7 @@ fn find_clusters<A: FloatCore + FromPrimitive + AddAssign + Sub>(
 //    lambda_x = 1 / eps_x
 //    lambda_A = 1 / eps_A
 //    score(x) = 1 - lambda_x / lambda_A
-fn glosh<A: FloatCore>(
+pub(crate) fn glosh<A: FloatCore>(
     condensed_mst: &[(usize, usize, A, usize)],
     min_cluster_size: usize,
 ) -> Vec<A> {
@@ -501,6 +1745,8 @@ fn max_lambdas<A: FloatCore>(
 mod test {
     #[test]
     fn hdbscan32() {
+        use std::collections::HashMap;
+
         use ndarray::{array, Array2};
         use petal_neighbors::distance::Euclidean;
 
@@ -521,6 +1767,14 @@ mod test {
             metric: Euclidean::default(),
             boruvka: false,
             extraction: super::ClusterExtraction::ExcessOfMass,
+            cluster_selection_epsilon: 0.,
+            outlier_scores: vec![],
+            exemplars: HashMap::new(),
+            cluster_persistence: HashMap::new(),
+            probabilities: vec![],
+            exemplar_points: HashMap::new(),
+            hierarchy: super::Dendrogram::default(),
+            lambda_thresholds: HashMap::new(),
         };
         let (clusters, noise, _) = hdbscan.fit(&data, None);
         assert_eq!(clusters.len(), 2);
@@ -532,6 +1786,8 @@ mod test {
 
     #[test]
     fn hdbscan64() {
+        use std::collections::HashMap;
+
         use ndarray::{array, Array2};
         use petal_neighbors::distance::Euclidean;
 
@@ -552,6 +1808,14 @@ mod test {
             metric: Euclidean::default(),
             boruvka: false,
             extraction: super::ClusterExtraction::ExcessOfMass,
+            cluster_selection_epsilon: 0.,
+            outlier_scores: vec![],
+            exemplars: HashMap::new(),
+            cluster_persistence: HashMap::new(),
+            probabilities: vec![],
+            exemplar_points: HashMap::new(),
+            hierarchy: super::Dendrogram::default(),
+            lambda_thresholds: HashMap::new(),
         };
         let (clusters, noise, _) = hdbscan.fit(&data, None);
         assert_eq!(clusters.len(), 2);
@@ -559,10 +1823,139 @@ mod test {
             noise.len(),
             data.nrows() - clusters.values().fold(0, |acc, v| acc + v.len())
         );
+        let exemplars = hdbscan.exemplars();
+        assert_eq!(exemplars.len(), 2);
+        for (id, members) in &clusters {
+            assert!(!exemplars[id].is_empty());
+            assert!(exemplars[id].iter().all(|i| members.contains(i)));
+        }
+    }
+
+    #[test]
+    fn transform() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        use crate::{Fit, Transform};
+
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+        ];
+        let mut hdbscan = super::HDbscan::<f64, _>::new(2, Euclidean::default());
+        let (clusters, _, _) = hdbscan.fit(&data, None);
+        let mut ids: Vec<_> = clusters.keys().copied().collect();
+        ids.sort_unstable();
+
+        let distances = hdbscan.transform(&data);
+        assert_eq!(distances.dim(), (data.nrows(), ids.len()));
+        for (c, id) in ids.iter().enumerate() {
+            for &i in &hdbscan.exemplars()[id] {
+                assert!(distances[[i, c]].abs() < 1e-12);
+            }
+            for &i in &clusters[id] {
+                assert!(distances.row(i).iter().all(|d| distances[[i, c]] <= *d));
+            }
+        }
+
+        let indices = array![[0, 1], [1, 0], [2, 1], [3, 4], [4, 3], [5, 4]];
+        let knn_distances = array![[0., 1.], [0., 1.], [0., 1.], [0., 1.], [0., 1.], [0., 1.]];
+        hdbscan.min_cluster_size = 3;
+        hdbscan.fit_from_knn(&indices, &knn_distances, None);
+        let result = std::panic::catch_unwind(|| hdbscan.transform(&data));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn mutual_reachability() {
+        use ndarray::{array, Array2};
+        use petal_neighbors::distance::Euclidean;
+
+        let data = array![[0., 0.], [0., 1.], [1., 0.], [5., 5.], [9., 9.]];
+        let dense = super::mutual_reachability(&data, 3, &Euclidean::default());
+        assert_eq!(dense.dim(), (5, 5));
+        assert_eq!(dense, dense.t());
+        assert!(dense.diag().iter().all(|d| *d == 0.));
+        // the core distance of point 1 is its distance to point 2
+        assert!((dense[[0, 1]] - 2_f64.sqrt()).abs() < 1e-12);
+
+        let (indices, distances) =
+            super::mutual_reachability_knn(&data, 3, 3, &Euclidean::default());
+        assert_eq!(indices.dim(), (5, 3));
+        assert_eq!(indices.column(0), array![0, 1, 2, 3, 4]);
+        for (i, (row, dists)) in indices.rows().into_iter().zip(distances.rows()).enumerate() {
+            for (j, d) in row.iter().zip(dists) {
+                assert!((dense[[i, *j]] - d).abs() < 1e-12);
+            }
+            assert!(dists.windows(2).into_iter().all(|w| w[0] <= w[1]));
+        }
+
+        let empty: Array2<f64> = Array2::zeros((0, 2));
+        let dense = super::mutual_reachability(&empty, 3, &Euclidean::default());
+        assert_eq!(dense.dim(), (0, 0));
+        let (indices, _) = super::mutual_reachability_knn(&empty, 3, 3, &Euclidean::default());
+        assert_eq!(indices.dim(), (0, 0));
+    }
+
+    #[test]
+    fn fit_degenerate() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        use crate::Fit;
+
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [f64::NAN, 2.0],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, f64::INFINITY],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [-2.1, 3.2],
+        ];
+        let mut hdbscan = super::HDbscan::new(3, Euclidean::default());
+        let (clusters, noise, outlier_scores) = hdbscan.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 3, 4], vec![6, 7, 8]]);
+        assert_eq!(noise, [2, 5]);
+        assert_eq!(outlier_scores.len(), 9);
+        assert!((outlier_scores[2] - 1.).abs() < f64::EPSILON);
+        assert!(hdbscan.probabilities()[5].abs() < f64::EPSILON);
+        assert!(hdbscan
+            .exemplars()
+            .values()
+            .flatten()
+            .all(|&i| i != 2 && i != 5));
+        let hierarchy = hdbscan.hierarchy();
+        assert_eq!(hierarchy.n_points(), 9);
+        assert_eq!(hierarchy.merges().len(), 6);
+        assert!(hierarchy
+            .merges()
+            .iter()
+            .all(|&(l, r, _, _)| ![2, 5].contains(&l) && ![2, 5].contains(&r) && r < 15));
+        let (clusters, _) = hierarchy.cut_at(1);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[&0], [0, 1, 3, 4, 6, 7, 8]);
+        assert_eq!(clusters[&1], [2]);
+
+        let (clusters, noise, outlier_scores) = hdbscan.fit(&array![[1., 2.]], None);
+        assert!(clusters.is_empty());
+        assert_eq!(noise, [0]);
+        assert_eq!(outlier_scores, [0.]);
+        assert_eq!(hdbscan.hierarchy().n_points(), 1);
     }
 
     #[test]
     fn outlier_scores() {
+        use std::collections::HashMap;
+
         use ndarray::array;
         use petal_neighbors::distance::Euclidean;
 
@@ -601,8 +1994,17 @@ mod test {
             metric: Euclidean::default(),
             boruvka: true,
             extraction: super::ClusterExtraction::ExcessOfMass,
+            cluster_selection_epsilon: 0.,
+            outlier_scores: vec![],
+            exemplars: HashMap::new(),
+            cluster_persistence: HashMap::new(),
+            probabilities: vec![],
+            exemplar_points: HashMap::new(),
+            hierarchy: super::Dendrogram::default(),
+            lambda_thresholds: HashMap::new(),
         };
         let (_, _, outlier_scores) = hdbscan.fit(&data, None);
+        assert_eq!(hdbscan.outlier_scores(), outlier_scores);
 
         // Outlier1 joins the root cluster at:
         //      eps_outlier1 = 3.0
@@ -704,6 +2106,227 @@ mod test {
         assert_eq!(clusters[c3], [9, 10, 11, 12, 13, 14]);
     }
 
+    #[test]
+    fn cluster_selection() {
+        use ndarray::array;
+
+        use crate::Fit;
+
+        let data = array![
+            // Group 1
+            [1., 9.],
+            [2., 9.],
+            [1., 8.],
+            [2., 8.],
+            [3., 7.],
+            // Group 2 (merges with Group 3 at eps = 2)
+            [5., 4.],
+            [6., 4.],
+            [5., 3.],
+            [6., 3.],
+            // Group 3
+            [8., 3.],
+            [9., 3.],
+            [8., 2.],
+            [9., 2.],
+            [8., 1.],
+            [9., 1.],
+            // noise
+            [7., 8.],
+        ];
+        let mut hdbscan = super::HDbscan {
+            min_samples: 4,
+            min_cluster_size: 4,
+            boruvka: false,
+            extraction: super::ClusterExtraction::Leaf,
+            ..Default::default()
+        };
+
+        // Leaf selection splits Group 2 and Group 3
+        let (clusters, noise, _) = hdbscan.fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(noise, [15]);
+        let mut members: Vec<_> = clusters.into_values().collect();
+        members.sort_unstable();
+        assert_eq!(
+            members,
+            [
+                vec![0, 1, 2, 3, 4],
+                vec![5, 6, 7, 8],
+                vec![9, 10, 11, 12, 13, 14]
+            ]
+        );
+
+        // Leaves born below eps = 3 are merged into their parent
+        hdbscan.cluster_selection_epsilon = 3.;
+        let (clusters, noise, _) = hdbscan.fit(&data, None);
+        assert_eq!(noise, [15]);
+        let mut members: Vec<_> = clusters.into_values().collect();
+        members.sort_unstable();
+        assert_eq!(
+            members,
+            [vec![0, 1, 2, 3, 4], vec![5, 6, 7, 8, 9, 10, 11, 12, 13, 14]]
+        );
+    }
+
+    #[test]
+    fn new() {
+        use petal_neighbors::distance::Euclidean;
+
+        let mut hdbscan = super::HDbscan::<f64, _>::new(8, Euclidean::default());
+        assert_eq!(hdbscan.min_cluster_size, 8);
+        assert_eq!(hdbscan.min_samples, 8);
+
+        hdbscan.min_samples = 2;
+        assert_eq!(hdbscan.min_cluster_size, 8);
+    }
+
+    #[test]
+    fn fit_with_limits() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        use crate::{Fit, Limit, Limits};
+
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+        ];
+        for boruvka in [true, false] {
+            let mut model = super::HDbscan::new(2, Euclidean::default());
+            model.boruvka = boruvka;
+            let expected = model.fit(&data, None);
+            assert_eq!(
+                model.fit_with_limits(&data, None, &Limits::default()),
+                Ok(expected)
+            );
+
+            // the input and the ball tree alone take 12 * 8 + 2 * 6 * 8 bytes
+            let error = model
+                .fit_with_limits(&data, None, &Limits::new(Some(100), None))
+                .unwrap_err();
+            assert_eq!(error.limit, Limit::Memory(100));
+            assert_eq!(error.phase, "indexing");
+            assert_eq!(error.memory, 192);
+
+            let error = model
+                .fit_with_limits(&data, None, &Limits::new(Some(192), None))
+                .unwrap_err();
+            assert_eq!(error.phase, "mst");
+            assert_eq!(error.processed, 6);
+
+            // plus 5 edges in the minimum spanning tree
+            let error = model
+                .fit_with_limits(&data, None, &Limits::new(Some(192 + 5 * 24), None))
+                .unwrap_err();
+            assert_eq!(error.phase, "condensation");
+            assert!(error.memory > 192 + 5 * 24);
+        }
+    }
+
+    #[test]
+    fn fit_from_knn() {
+        use ndarray::{array, Array2};
+        use petal_neighbors::distance::{Euclidean, Metric};
+
+        use crate::Fit;
+
+        let data = array![
+            [1., 9.],
+            [2., 9.],
+            [1., 8.],
+            [2., 8.],
+            [3., 7.],
+            [5., 4.],
+            [6., 4.],
+            [5., 3.],
+            [6., 3.],
+            [8., 3.],
+            [9., 3.],
+            [8., 2.],
+            [9., 2.],
+            [8., 1.],
+            [9., 1.],
+            [7., 8.],
+        ];
+        // break distance ties, which make the hierarchy order-dependent
+        #[allow(clippy::cast_precision_loss)]
+        let data = &data
+            + &Array2::from_shape_fn(data.dim(), |(i, j)| ((i * 7 + j * 3) % 11) as f64 / 1000.);
+        let mut hdbscan = super::HDbscan {
+            min_samples: 4,
+            min_cluster_size: 4,
+            boruvka: false,
+            ..Default::default()
+        };
+        let (expected, expected_noise, expected_scores) = hdbscan.fit(&data, None);
+
+        // exact k-NN graph with k = n, sorted by distance
+        let n = data.nrows();
+        let metric = Euclidean::default();
+        let mut indices = Array2::zeros((n, n));
+        let mut distances = Array2::zeros((n, n));
+        for i in 0..n {
+            let mut row: Vec<(f64, usize)> = (0..n)
+                .map(|j| (metric.distance(&data.row(i), &data.row(j)), j))
+                .collect();
+            row.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for (k, (d, j)) in row.into_iter().enumerate() {
+                indices[[i, k]] = j;
+                distances[[i, k]] = d;
+            }
+        }
+        let (clusters, noise, scores) = hdbscan.fit_from_knn(&indices, &distances, None);
+        let mut members: Vec<_> = clusters.into_values().collect();
+        members.sort_unstable();
+        let mut expected: Vec<_> = expected.into_values().collect();
+        expected.sort_unstable();
+        assert_eq!(members, expected);
+        assert_eq!(noise, expected_noise);
+        for (a, b) in scores.iter().zip(&expected_scores) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        // a sparse graph leaving the groups disconnected
+        let k = 5;
+        let (clusters, _, _) = hdbscan.fit_from_knn(
+            &indices.slice(ndarray::s![.., ..k]),
+            &distances.slice(ndarray::s![.., ..k]),
+            None,
+        );
+        assert!(clusters.values().any(|c| c == &[0, 1, 2, 3, 4]));
+    }
+
+    #[test]
+    #[should_panic(expected = "less than the number of points")]
+    fn fit_from_knn_out_of_bounds() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        let mut hdbscan = super::HDbscan::<f64, _>::new(2, Euclidean::default());
+        hdbscan.min_samples = 2;
+        hdbscan.fit_from_knn(&array![[0, 1], [1, 2]], &array![[0., 1.], [0., 1.]], None);
+    }
+
+    #[test]
+    #[should_panic(expected = "NaN")]
+    fn fit_from_knn_nan() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        let mut hdbscan = super::HDbscan::<f64, _>::new(2, Euclidean::default());
+        hdbscan.min_samples = 2;
+        hdbscan.fit_from_knn(
+            &array![[0, 1], [1, 0]],
+            &array![[0., f64::NAN], [0., 1.]],
+            None,
+        );
+    }
+
     #[test]
     fn label() {
         let mst = vec![
@@ -741,6 +2364,148 @@ mod test {
         );
     }
 
+    #[test]
+    fn find_exemplars() {
+        use maplit::hashmap;
+
+        // the root 7 splits into 8 and 9, and 8 into the leaves 10 and 11
+        let condensed = [
+            (7, 8, 0.25, 4),
+            (7, 9, 0.25, 3),
+            (8, 10, 0.5, 2),
+            (8, 11, 0.5, 2),
+            (10, 0, 1., 1),
+            (10, 1, 2., 1),
+            (11, 2, 3., 1),
+            (11, 3, 3., 1),
+            (9, 4, 0.5, 1),
+            (9, 5, 0.5, 1),
+            (9, 6, 0.3, 1),
+        ];
+        let clusters = hashmap! {8 => vec![0, 1, 2, 3], 9 => vec![4, 5, 6]};
+        assert_eq!(
+            super::find_exemplars(&condensed, &clusters),
+            hashmap! {8 => vec![1, 2, 3], 9 => vec![4, 5]}
+        );
+        let leaves = hashmap! {10 => vec![0, 1], 11 => vec![2, 3]};
+        assert_eq!(
+            super::find_exemplars(&condensed, &leaves),
+            hashmap! {10 => vec![1], 11 => vec![2, 3]}
+        );
+    }
+
+    #[test]
+    fn persistence() {
+        use maplit::hashmap;
+        use ndarray::arr1;
+
+        let condensed = arr1(&[
+            (7, 8, 0.25_f64, 4),
+            (7, 9, 0.25, 3),
+            (8, 0, 1., 1),
+            (8, 1, 1., 1),
+            (8, 2, 1., 1),
+            (8, 3, 1., 1),
+            (9, 4, 0.5, 1),
+            (9, 5, 0.5, 1),
+            (9, 6, 0.25, 1),
+        ]);
+        let clusters = hashmap! {8 => vec![0, 1, 2, 3], 9 => vec![4, 5, 6]};
+        // 8 persists from 0.25 to 1 and 9 from 0.25 to 0.5, at most
+        let persistence = super::persistence(&condensed.view(), &clusters);
+        assert!((persistence[&8] - 0.75).abs() < f64::EPSILON);
+        assert!((persistence[&9] - 0.5 / 3.).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn probabilities() {
+        use maplit::hashmap;
+
+        let condensed = [
+            (7, 8, 0.25, 4),
+            (7, 9, 0.25, 3),
+            (8, 0, 1., 1),
+            (8, 1, 1., 1),
+            (8, 2, 0.5, 1),
+            (8, 3, 0.25, 1),
+            (9, 4, 0.5, 1),
+            (9, 5, 0.5, 1),
+            (9, 6, 0.25, 1),
+        ];
+        let clusters = hashmap! {8 => vec![0, 1, 2, 3], 9 => vec![4, 5]};
+        assert_eq!(
+            super::probabilities(&condensed, &clusters),
+            [1., 1., 0.5, 0.25, 1., 1., 0.]
+        );
+    }
+
+    #[test]
+    fn lambda_thresholds() {
+        use maplit::hashmap;
+
+        let condensed = [
+            (7, 8, 0.25, 4),
+            (7, 9, 0.25, 3),
+            (8, 0, 1., 1),
+            (8, 1, 1., 1),
+            (8, 2, 0.5, 1),
+            (8, 3, 0.25, 1),
+            (9, 4, 0.5, 1),
+            (9, 5, 0.5, 1),
+            (9, 6, 0.25, 1),
+        ];
+        let clusters = hashmap! {8 => vec![0, 1, 2, 3], 9 => vec![4, 5]};
+        assert_eq!(
+            super::lambda_thresholds(&condensed, &clusters),
+            hashmap! {8 => (0.25, 1.), 9 => (0.25, 0.5)}
+        );
+        let root = hashmap! {7 => vec![0, 1, 2, 3, 4, 5, 6]};
+        assert_eq!(
+            super::lambda_thresholds(&condensed, &root),
+            hashmap! {7 => (0., 1.)}
+        );
+    }
+
+    #[test]
+    fn approximate_predict() {
+        use ndarray::array;
+        use petal_neighbors::distance::Euclidean;
+
+        use crate::Fit;
+
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [-2.1, 2.9],
+            [-2.0, 3.2],
+            [f64::NAN, 0.0],
+        ];
+        let mut hdbscan = super::HDbscan::new(3, Euclidean::default());
+        let (clusters, _, _) = hdbscan.fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        let prediction = hdbscan.generate_prediction_data(&data, &clusters);
+        let predictor = prediction.predictor();
+
+        // the training points keep their clusters
+        let (labels, probabilities) = predictor.approximate_predict(&data);
+        for (id, members) in &clusters {
+            assert!(members.iter().all(|i| labels[*i] == Some(*id)));
+        }
+        assert!(probabilities[..8].iter().all(|p| *p > 0. && *p <= 1.));
+        assert_eq!((labels[8], probabilities[8]), (None, 0.));
+
+        let points = array![[1.05, 2.05], [-2.1, 3.05], [1.0, 20.0], [6.0, 2.0]];
+        let (labels, probabilities) = predictor.approximate_predict(&points);
+        assert!(clusters[&labels[0].unwrap()].contains(&0));
+        assert!(clusters[&labels[1].unwrap()].contains(&4));
+        assert_eq!(labels[2..], [None, None]);
+        assert_eq!(probabilities[2..], [0., 0.]);
+    }
+
     #[test]
     fn get_stability() {
         use std::collections::HashMap;
@@ -790,4 +2555,19 @@ mod test {
         assert!((bcubed_map[&9] - 8. / 25.).abs() < f64::EPSILON);
         assert!((bcubed_map[&10] - 4. / 15.).abs() < f64::EPSILON);
     }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn deserialize_without_fitted_state() {
+        use crate::MetricConfig;
+
+        let saved = r#"{
+            "alpha": 1.0, "min_samples": 5, "min_cluster_size": 5, "metric": "euclidean",
+            "boruvka": true, "extraction": "ExcessOfMass"
+        }"#;
+        let hdbscan: super::HDbscan<f64, MetricConfig> = serde_json::from_str(saved).unwrap();
+        assert_eq!(hdbscan.min_cluster_size, 5);
+        assert!(hdbscan.cluster_selection_epsilon.abs() < f64::EPSILON);
+        assert!(hdbscan.outlier_scores().is_empty());
+    }
 }
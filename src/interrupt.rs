@@ -0,0 +1,374 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// The number of points processed between two checks of a [`CancelToken`]
+/// or of [`Limits`].
+pub(crate) const CHUNK_SIZE: usize = 4096;
+
+/// A flag to cancel a running fit from another thread.
+///
+/// Clones of a token share the same flag, so one clone can be passed to the
+/// fit while another is kept to cancel it.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{CancelToken, Dbscan};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let token = CancelToken::new();
+/// token.cancel();
+/// let outcome = Dbscan::new(3., 2, Euclidean::default()).fit_cancellable(&points, &token);
+///
+/// assert!(outcome.is_partial());
+/// assert_eq!(outcome.processed(), Some(0));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that has not been cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::CancelToken;
+    ///
+    /// let token = CancelToken::new();
+    /// assert!(!token.is_cancelled());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the fits using this token to stop as soon as possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::CancelToken;
+    ///
+    /// let token = CancelToken::new();
+    /// let clone = token.clone();
+    /// clone.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [`CancelToken::cancel`] has been called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::CancelToken;
+    ///
+    /// let token = CancelToken::new();
+    /// assert!(!token.is_cancelled());
+    /// token.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The result of a fit that may have been cancelled.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum FitOutcome<T> {
+    /// The fit ran to completion.
+    Complete(T),
+
+    /// The fit was cancelled, and the result only covers the first
+    /// `processed` points. The other points are neither in a cluster nor
+    /// noise.
+    Partial { result: T, processed: usize },
+}
+
+impl<T> FitOutcome<T> {
+    /// Returns `true` if the fit was cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::FitOutcome;
+    ///
+    /// assert!(!FitOutcome::Complete(3).is_partial());
+    /// assert!(FitOutcome::Partial { result: 3, processed: 10 }.is_partial());
+    /// ```
+    #[must_use]
+    pub fn is_partial(&self) -> bool {
+        matches!(self, Self::Partial { .. })
+    }
+
+    /// Returns the number of points the result covers if the fit was
+    /// cancelled, or `None` if it ran to completion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::FitOutcome;
+    ///
+    /// assert_eq!(FitOutcome::Complete(3).processed(), None);
+    /// assert_eq!(FitOutcome::Partial { result: 3, processed: 10 }.processed(), Some(10));
+    /// ```
+    #[must_use]
+    pub fn processed(&self) -> Option<usize> {
+        match self {
+            Self::Complete(_) => None,
+            Self::Partial { processed, .. } => Some(*processed),
+        }
+    }
+
+    /// Returns the result, whether it is complete or partial.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::FitOutcome;
+    ///
+    /// assert_eq!(FitOutcome::Complete(3).result(), &3);
+    /// assert_eq!(FitOutcome::Partial { result: 3, processed: 10 }.result(), &3);
+    /// ```
+    #[must_use]
+    pub fn result(&self) -> &T {
+        match self {
+            Self::Complete(result) | Self::Partial { result, .. } => result,
+        }
+    }
+
+    /// Consumes the outcome and returns the result, whether it is complete or
+    /// partial.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::FitOutcome;
+    ///
+    /// let outcome = FitOutcome::Partial { result: vec![0, 1], processed: 2 };
+    /// assert_eq!(outcome.into_result(), [0, 1]);
+    /// ```
+    pub fn into_result(self) -> T {
+        match self {
+            Self::Complete(result) | Self::Partial { result, .. } => result,
+        }
+    }
+}
+
+/// Resource limits of a fit, checked between its phases and between chunks
+/// of points by [`Dbscan::fit_with_limits`](crate::Dbscan::fit_with_limits),
+/// [`Optics::fit_with_limits`](crate::Optics::fit_with_limits) and
+/// [`HDbscan::fit_with_limits`](crate::HDbscan::fit_with_limits).
+///
+/// The memory limit applies to an estimate of the memory used by the data
+/// structures the fit builds, not to the memory of the process.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, Limit, Limits};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+///
+/// let limits = Limits::new(None, Some(Duration::from_secs(10)));
+/// let (clusters, noise) = dbscan.fit_with_limits(&points, &limits).unwrap();
+/// assert_eq!(clusters.len(), 2);
+///
+/// let limits = Limits::new(Some(64), None);
+/// let error = dbscan.fit_with_limits(&points, &limits).unwrap_err();
+/// assert_eq!(error.limit, Limit::Memory(64));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Limits {
+    /// The maximum estimated memory, in bytes.
+    pub max_memory: Option<usize>,
+
+    /// The maximum duration.
+    pub max_duration: Option<Duration>,
+}
+
+impl Limits {
+    /// Creates limits of `max_memory` bytes of estimated memory and
+    /// `max_duration`, where `None` does not limit the resource.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use petal_clustering::Limits;
+    ///
+    /// let limits = Limits::new(None, Some(Duration::from_secs(60)));
+    /// assert_eq!(limits.max_memory, None);
+    /// assert_eq!(limits, Limits { max_memory: None, max_duration: Some(Duration::from_secs(60)) });
+    ///
+    /// ```
+    #[must_use]
+    pub fn new(max_memory: Option<usize>, max_duration: Option<Duration>) -> Self {
+        Self {
+            max_memory,
+            max_duration,
+        }
+    }
+
+    /// Starts measuring the duration of a fit, if it is limited.
+    pub(crate) fn start(&self) -> LimitGuard {
+        LimitGuard {
+            limits: *self,
+            // the clock is not available on every target
+            start: self.max_duration.map(|_| Instant::now()),
+        }
+    }
+}
+
+/// A limit that was exceeded, with its value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    /// The maximum estimated memory, in bytes.
+    Memory(usize),
+
+    /// The maximum duration.
+    Duration(Duration),
+}
+
+/// The error returned by a fit that exceeded one of its [`Limits`], with
+/// diagnostics on how far it got.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LimitExceeded {
+    /// The limit that was exceeded.
+    pub limit: Limit,
+
+    /// The phase of the fit during which the limit was exceeded.
+    pub phase: &'static str,
+
+    /// The number of points processed in that phase.
+    pub processed: usize,
+
+    /// The estimated memory used when the fit was aborted, in bytes.
+    pub memory: usize,
+
+    /// The time elapsed since the fit started, or zero if the duration is not
+    /// limited.
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Limit::Memory(max) => write!(f, "the memory limit of {max} bytes")?,
+            Limit::Duration(max) => write!(f, "the time limit of {max:?}")?,
+        }
+        write!(
+            f,
+            " was exceeded during {} after processing {} points, with an estimated {} bytes \
+             in use after {:?}",
+            self.phase, self.processed, self.memory, self.elapsed
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Checks the [`Limits`] of a running fit.
+pub(crate) struct LimitGuard {
+    limits: Limits,
+    start: Option<Instant>,
+}
+
+impl LimitGuard {
+    /// Returns an error if the estimated `memory` or the time elapsed since
+    /// the fit started exceeds the limits.
+    pub(crate) fn check(
+        &self,
+        phase: &'static str,
+        processed: usize,
+        memory: usize,
+    ) -> Result<(), LimitExceeded> {
+        let elapsed = self.start.map_or(Duration::ZERO, |start| start.elapsed());
+        let limit = match (self.limits.max_memory, self.limits.max_duration) {
+            (Some(max), _) if memory > max => Limit::Memory(max),
+            (_, Some(max)) if elapsed > max => Limit::Duration(max),
+            _ => return Ok(()),
+        };
+        Err(LimitExceeded {
+            limit,
+            phase,
+            processed,
+            memory,
+            elapsed,
+        })
+    }
+}
+
+/// Returns the result of a fit whose checks never fail, as those of the
+/// fits without limits.
+pub(crate) fn unchecked<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cancel_token() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn fit_outcome() {
+        let complete = FitOutcome::Complete(vec![1]);
+        assert!(!complete.is_partial());
+        assert_eq!(complete.processed(), None);
+        assert_eq!(complete.result(), &[1]);
+
+        let partial = FitOutcome::Partial {
+            result: vec![2],
+            processed: 3,
+        };
+        assert!(partial.is_partial());
+        assert_eq!(partial.processed(), Some(3));
+        assert_eq!(partial.into_result(), [2]);
+    }
+
+    #[test]
+    fn limits() {
+        let unlimited = Limits::default().start();
+        assert!(unlimited.check("phase", 1, usize::MAX).is_ok());
+
+        let guard = Limits::new(Some(100), Some(Duration::ZERO)).start();
+        let error = guard.check("phase", 3, 101).unwrap_err();
+        assert_eq!(error.limit, Limit::Memory(100));
+        assert_eq!(error.processed, 3);
+        assert_eq!(error.memory, 101);
+
+        std::thread::sleep(Duration::from_millis(1));
+        let error = guard.check("phase", 3, 100).unwrap_err();
+        assert_eq!(error.limit, Limit::Duration(Duration::ZERO));
+        assert!(error
+            .to_string()
+            .starts_with("the time limit of 0ns was exceeded"));
+    }
+}
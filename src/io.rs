@@ -0,0 +1,116 @@
+//! Loading inputs from CSV files, enabled with the `io` feature.
+
+use std::io;
+use std::path::Path;
+
+use csv::ReaderBuilder;
+use ndarray::Array2;
+
+/// Reads the columns of a comma-separated file at `path` into an array with
+/// one row per record, for quick exploration of a dataset.
+///
+/// Only the columns whose zero-based indices are in `selected_columns` are
+/// read, in that order, or all the columns if it is `None`. The first line
+/// is taken as a header, and skipped, if any of its selected fields is not a
+/// number.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, a record does not have a
+/// selected column, records have different numbers of fields, or a selected
+/// field other than in the header is not a number.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::io::read_csv_to_array;
+///
+/// let path = std::env::temp_dir().join("petal_clustering_io_example.csv");
+/// std::fs::write(&path, "id,x,y\n0,1.,2.\n1,2.,2.\n2,2.,2.3\n").unwrap();
+///
+/// let points = read_csv_to_array(&path, Some(&[1, 2])).unwrap();
+/// assert_eq!(points.dim(), (3, 2));
+/// assert_eq!(points[[2, 1]], 2.3);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn read_csv_to_array<P: AsRef<Path>>(
+    path: P,
+    selected_columns: Option<&[usize]>,
+) -> io::Result<Array2<f64>> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+
+    let mut values = Vec::new();
+    let mut n_columns = 0;
+    let mut n_rows = 0;
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        let fields: Vec<&str> = match selected_columns {
+            Some(columns) => columns
+                .iter()
+                .map(|&c| {
+                    record
+                        .get(c)
+                        .ok_or_else(|| invalid_data(format!("line {} has no column {c}", line + 1)))
+                })
+                .collect::<io::Result<_>>()?,
+            None => record.iter().collect(),
+        };
+        let parsed: Result<Vec<f64>, _> = fields.iter().map(|f| f.parse::<f64>()).collect();
+        let row = match parsed {
+            Ok(row) => row,
+            Err(_) if line == 0 => continue,
+            Err(e) => return Err(invalid_data(format!("line {}: {e}", line + 1))),
+        };
+        n_columns = row.len();
+        values.extend(row);
+        n_rows += 1;
+    }
+    Array2::from_shape_vec((n_rows, n_columns), values).map_err(|e| invalid_data(e.to_string()))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ndarray::array;
+
+    use super::*;
+
+    fn read(name: &str, contents: &str, columns: Option<&[usize]>) -> io::Result<Array2<f64>> {
+        let path = std::env::temp_dir().join(format!("petal_clustering_io_{name}.csv"));
+        fs::write(&path, contents).expect("writable temporary directory");
+        let result = read_csv_to_array(&path, columns);
+        fs::remove_file(&path).expect("the file was just written");
+        result
+    }
+
+    #[test]
+    fn read_csv() {
+        let points = read("plain", "1,2\n3, 4\n", None).unwrap();
+        assert_eq!(points, array![[1., 2.], [3., 4.]]);
+
+        let points = read("header", "name,x,y\na,1,2\nb,3,4\n", Some(&[2, 1])).unwrap();
+        assert_eq!(points, array![[2., 1.], [4., 3.]]);
+
+        let points = read("empty", "", None).unwrap();
+        assert_eq!(points.dim(), (0, 0));
+    }
+
+    #[test]
+    fn read_csv_errors() {
+        let error = read("text", "1,2\n3,x\n", None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2"));
+
+        let error = read("column", "1,2\n3,4\n", Some(&[2])).unwrap_err();
+        assert!(error.to_string().contains("no column 2"));
+
+        assert!(read("fields", "1,2\n3\n", None).is_err());
+    }
+}
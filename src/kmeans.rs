@@ -0,0 +1,1057 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict, Transform};
+use crate::parallel::prelude::*;
+use crate::sum::{sum, Sum};
+
+/// K-means clustering algorithm with k-means++ initialization.
+///
+/// For a single feature, [`Ckmeans1D`](crate::Ckmeans1D) finds the optimal
+/// clusters exactly and faster.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, KMeans, Predict};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let mut kmeans = KMeans::new(2);
+/// let (clusters, noise) = kmeans.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());  // every point belongs to a cluster
+///
+/// let labels = kmeans.predict(&array![[1., 1.], [9., 9.]]);
+/// assert!(clusters[&labels[0]].contains(&0));
+/// assert!(clusters[&labels[1]].contains(&3));
+/// ```
+///
+/// # References
+/// - Arthur, David, and Sergei Vassilvitskii. "k-means++: The advantages of careful seeding."
+///   Proceedings of the 18th Annual ACM-SIAM Symposium on Discrete Algorithms (2007): 1027-1035.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KMeans<A> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The maximum number of Lloyd iterations of a run.
+    pub max_iter: usize,
+
+    /// The tolerance on the shift of the centroids, relative to the mean
+    /// variance of the features, below which a run is considered converged.
+    pub tol: A,
+
+    /// The number of runs with different initial centroids. The run with the
+    /// lowest inertia is kept.
+    pub n_init: usize,
+
+    /// The seed of the random number generator choosing initial centroids.
+    pub seed: u64,
+
+    centroids: Vec<A>,
+    n_features: usize,
+    inertia: A,
+}
+
+impl<A> Default for KMeans<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl<A> KMeans<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model with `n_clusters` clusters, running k-means once for at
+    /// most 300 iterations with a tolerance of `1e-4`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::KMeans;
+    ///
+    /// let kmeans = KMeans::<f64>::new(3);
+    /// assert_eq!(kmeans.n_clusters, 3);
+    /// assert_eq!(kmeans.max_iter, 300);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            n_clusters,
+            max_iter: 300,
+            tol: A::from(1e-4).unwrap_or_else(A::epsilon),
+            n_init: 1,
+            seed: 0,
+            centroids: vec![],
+            n_features: 0,
+            inertia: A::zero(),
+        }
+    }
+
+    /// Returns the centroids of the clusters found by the last fit, one row
+    /// per cluster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KMeans};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let mut kmeans = KMeans::new(2);
+    /// let (clusters, _) = kmeans.fit(&points, None);
+    ///
+    /// let label = clusters.iter().find(|(_, members)| members.contains(&0)).unwrap().0;
+    /// assert_eq!(kmeans.centroids().row(*label), array![0., 0.5]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn centroids(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (
+                self.centroids.len() / self.n_features.max(1),
+                self.n_features,
+            ),
+            &self.centroids,
+        )
+        .expect("centroids of the fitted model")
+    }
+
+    /// Returns the sum of squared distances of the points to their closest
+    /// centroids in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KMeans};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let mut kmeans = KMeans::new(2);
+    /// kmeans.fit(&points, None);
+    ///
+    /// assert!((kmeans.inertia() - 1_f64).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn inertia(&self) -> A {
+        self.inertia
+    }
+}
+
+/// Fits the k-means clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for KMeans<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.n_features = input.ncols();
+        self.inertia = A::zero();
+        if input.is_empty() || self.n_clusters == 0 {
+            self.centroids = vec![];
+            return (HashMap::new(), Vec::new());
+        }
+
+        let input = input.view();
+        let tol = self.tol * mean_variance(&input.view());
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut best: Option<(A, Vec<A>, Vec<usize>)> = None;
+        for _ in 0..self.n_init.max(1) {
+            let mut centroids = init_centroids(&input.view(), self.n_clusters, &mut rng);
+            let (labels, inertia) = lloyd(&input.view(), &mut centroids, self.max_iter, tol);
+            if best.as_ref().map_or(true, |(b, _, _)| inertia < *b) {
+                best = Some((inertia, centroids, labels));
+            }
+        }
+        let (inertia, centroids, labels) = best.expect("at least one run");
+        self.inertia = inertia;
+        self.centroids = centroids;
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, label) in labels.into_iter().enumerate() {
+            clusters.entry(label).or_default().push(i);
+        }
+        (clusters, Vec::new())
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its closest
+/// centroid.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for KMeans<A>
+where
+    A: FloatCore + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        assert!(
+            !self.centroids.is_empty(),
+            "`predict` requires a fitted model"
+        );
+        let centroids = self.centroids();
+        input
+            .rows()
+            .into_iter()
+            .map(|p| closest(&p, &centroids).0)
+            .collect()
+    }
+}
+
+/// Computes the Euclidean distance from each point in the given input to each
+/// centroid, one column per cluster.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for KMeans<A>
+where
+    A: num_traits::Float + FloatCore,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        assert!(
+            !self.centroids.is_empty(),
+            "`transform` requires a fitted model"
+        );
+        centroid_distances(input, &self.centroids())
+    }
+}
+
+/// Mini-batch k-means, which updates the centroids from small random batches
+/// of the input instead of the whole input at each iteration.
+///
+/// Each step draws `batch_size` points, assigns them to their closest
+/// centroids, and moves each centroid towards the mean of its points with a
+/// learning rate that decreases with the number of points it has seen. The
+/// fit is much faster than [`KMeans`] on large inputs, at the cost of a
+/// slightly higher inertia, and [`MiniBatchKMeans::partial_fit`] can build the
+/// model from batches that do not fit in memory together.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, MiniBatchKMeans, Predict};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let mut kmeans = MiniBatchKMeans::new(2, 4);
+/// let (clusters, noise) = kmeans.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+///
+/// let labels = kmeans.predict(&array![[1., 1.], [9., 9.]]);
+/// assert!(clusters[&labels[0]].contains(&0));
+/// assert!(clusters[&labels[1]].contains(&3));
+/// ```
+///
+/// # References
+/// - Sculley, David. "Web-scale k-means clustering." Proceedings of the 19th
+///   International Conference on World Wide Web (2010): 1177-1178.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MiniBatchKMeans<A> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The number of points drawn at each step.
+    pub batch_size: usize,
+
+    /// The maximum number of passes over the input, each made of as many
+    /// steps as there are batches in the input.
+    pub max_iter: usize,
+
+    /// Centroids that have seen fewer points than this fraction of the points
+    /// seen by the most frequent centroid are moved to random points of the
+    /// batch every few steps. Zero disables the reassignment.
+    pub reassignment_ratio: A,
+
+    /// The number of consecutive steps without improvement of the smoothed
+    /// batch inertia after which the fit stops. Zero disables this criterion.
+    pub max_no_improvement: usize,
+
+    /// The tolerance on the shift of the centroids in a step, relative to the
+    /// mean variance of the features, below which the fit stops. Zero
+    /// disables this criterion.
+    pub tol: A,
+
+    /// The seed of the random number generator choosing initial centroids and
+    /// batches.
+    pub seed: u64,
+
+    centroids: Vec<A>,
+    counts: Vec<usize>,
+    n_features: usize,
+    inertia: A,
+    n_steps: usize,
+}
+
+/// The number of steps between two reassignments of rarely chosen centroids.
+const REASSIGNMENT_INTERVAL: usize = 10;
+
+impl<A> Default for MiniBatchKMeans<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(8, 1024)
+    }
+}
+
+impl<A> MiniBatchKMeans<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model with `n_clusters` clusters, taking batches of
+    /// `batch_size` points for at most 100 steps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::MiniBatchKMeans;
+    ///
+    /// let kmeans = MiniBatchKMeans::<f64>::new(3, 256);
+    /// assert_eq!(kmeans.batch_size, 256);
+    /// assert_eq!(kmeans.max_iter, 100);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize, batch_size: usize) -> Self {
+        Self {
+            n_clusters,
+            batch_size,
+            max_iter: 100,
+            reassignment_ratio: A::from(0.01).unwrap_or_else(A::epsilon),
+            max_no_improvement: 10,
+            tol: A::zero(),
+            seed: 0,
+            centroids: vec![],
+            counts: vec![],
+            n_features: 0,
+            inertia: A::zero(),
+            n_steps: 0,
+        }
+    }
+
+    /// Returns the centroids of the clusters, one row per cluster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, MiniBatchKMeans};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let mut kmeans = MiniBatchKMeans::new(2, 4);
+    /// kmeans.fit(&points, None);
+    ///
+    /// let centroids = kmeans.centroids();
+    /// assert_eq!(centroids.nrows(), 2);
+    /// assert!(centroids.rows().into_iter().any(|c| c[0] < 1_f64));
+    /// assert!(centroids.rows().into_iter().any(|c| c[0] > 9_f64));
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn centroids(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (
+                self.centroids.len() / self.n_features.max(1),
+                self.n_features,
+            ),
+            &self.centroids,
+        )
+        .expect("centroids of the fitted model")
+    }
+
+    /// Returns the sum of squared distances of the points to their closest
+    /// centroids in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, MiniBatchKMeans};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let mut kmeans = MiniBatchKMeans::new(2, 4);
+    /// kmeans.fit(&points, None);
+    ///
+    /// assert!(kmeans.inertia() < 2_f64);
+    /// ```
+    #[must_use]
+    pub fn inertia(&self) -> A {
+        self.inertia
+    }
+
+    /// Returns the number of steps taken since the model was last fitted,
+    /// including those of [`MiniBatchKMeans::partial_fit`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, MiniBatchKMeans};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let mut kmeans = MiniBatchKMeans::new(2, 4);
+    /// kmeans.fit(&points, None);
+    /// let steps = kmeans.n_steps();
+    /// assert!(steps > 0 && steps <= kmeans.max_iter);
+    ///
+    /// kmeans.partial_fit(&points);
+    /// assert_eq!(kmeans.n_steps(), steps + 1);
+    /// ```
+    #[must_use]
+    pub fn n_steps(&self) -> usize {
+        self.n_steps
+    }
+}
+
+impl<A> MiniBatchKMeans<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Updates the centroids with one step on the given batch.
+    ///
+    /// The first call chooses the initial centroids from the batch with
+    /// k-means++, so it should contain at least `n_clusters` distinct points.
+    /// `fit` discards the centroids built by earlier calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the batch does not have the same number of features as the
+    /// earlier batches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{MiniBatchKMeans, Predict};
+    ///
+    /// let mut kmeans = MiniBatchKMeans::new(2, 4);
+    /// kmeans.partial_fit(&array![[0., 0.], [10., 0.], [0., 1.], [10., 1.]]);
+    /// kmeans.partial_fit(&array![[0., 0.5], [10., 0.5]]);
+    ///
+    /// let labels = kmeans.predict(&array![[0.2, 0.], [9.8, 1.]]);
+    /// assert_ne!(labels[0], labels[1]);
+    /// ```
+    pub fn partial_fit<S>(&mut self, batch: &ArrayBase<S, Ix2>)
+    where
+        S: Data<Elem = A>,
+    {
+        if batch.is_empty() || self.n_clusters == 0 {
+            return;
+        }
+        let batch = batch.view();
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(self.n_steps as u64));
+        if self.centroids.is_empty() {
+            self.n_features = batch.ncols();
+            self.centroids = init_centroids(&batch.view(), self.n_clusters, &mut rng);
+            self.counts = vec![0; self.n_clusters];
+        }
+        assert_eq!(
+            batch.ncols(),
+            self.n_features,
+            "batches must have the same number of features"
+        );
+        let rows: Vec<_> = batch.rows().into_iter().collect();
+        self.step(&rows, &mut rng);
+    }
+
+    /// Moves the centroids towards the means of their points in the batch,
+    /// and returns the inertia of the batch before the update and the total
+    /// squared shift of the centroids.
+    fn step<R: Rng>(&mut self, rows: &[ArrayView1<A>], rng: &mut R) -> (A, A) {
+        let dim = self.n_features;
+        let assignment = assign(rows, &self.centroids, dim);
+        let mut sums = vec![Sum::new(); self.centroids.len()];
+        let mut batch_counts = vec![0_usize; self.counts.len()];
+        for (p, (label, _)) in rows.iter().zip(&assignment) {
+            batch_counts[*label] += 1;
+            for (s, v) in sums[label * dim..(label + 1) * dim].iter_mut().zip(p) {
+                s.add(*v);
+            }
+        }
+
+        let mut shift = A::zero();
+        for (c, batch_count) in batch_counts.iter().enumerate() {
+            if *batch_count == 0 {
+                continue;
+            }
+            self.counts[c] += batch_count;
+            let old = A::from_usize(self.counts[c] - batch_count).expect("valid float");
+            let total = A::from_usize(self.counts[c]).expect("valid float");
+            for (centroid, sum) in self.centroids[c * dim..(c + 1) * dim]
+                .iter_mut()
+                .zip(&sums[c * dim..(c + 1) * dim])
+            {
+                let updated = (*centroid * old + sum.value()) / total;
+                shift += (updated - *centroid) * (updated - *centroid);
+                *centroid = updated;
+            }
+        }
+
+        self.n_steps += 1;
+        if self.reassignment_ratio > A::zero() && self.n_steps % REASSIGNMENT_INTERVAL == 0 {
+            self.reassign(rows, rng);
+        }
+        (sum(assignment.iter().map(|(_, d)| *d)), shift)
+    }
+
+    /// Moves the centroids that have seen too few points to random points of
+    /// the batch, up to half the batch size.
+    fn reassign<R: Rng>(&mut self, rows: &[ArrayView1<A>], rng: &mut R) {
+        let dim = self.n_features;
+        let max_count = self.counts.iter().copied().max().unwrap_or(0);
+        let threshold = self.reassignment_ratio * A::from_usize(max_count).expect("valid float");
+        let (rare, frequent): (Vec<usize>, Vec<usize>) = (0..self.counts.len())
+            .partition(|c| A::from_usize(self.counts[*c]).expect("valid float") < threshold);
+        if rare.is_empty() {
+            return;
+        }
+        let count = frequent.iter().map(|c| self.counts[*c]).min().unwrap_or(1);
+        let n_moved = rare.len().min(rows.len() / 2).max(1);
+        let targets = rand::seq::index::sample(rng, rows.len(), n_moved.min(rows.len()));
+        for (c, target) in rare.into_iter().zip(targets) {
+            self.centroids[c * dim..(c + 1) * dim]
+                .iter_mut()
+                .zip(rows[target])
+                .for_each(|(centroid, v)| *centroid = *v);
+            self.counts[c] = count;
+        }
+    }
+}
+
+/// Fits mini-batch k-means to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for MiniBatchKMeans<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.n_features = input.ncols();
+        self.inertia = A::zero();
+        self.n_steps = 0;
+        if input.is_empty() || self.n_clusters == 0 {
+            self.centroids = vec![];
+            self.counts = vec![];
+            return (HashMap::new(), Vec::new());
+        }
+
+        let input = input.view();
+        let n = input.nrows();
+        let batch_size = self.batch_size.clamp(1, n);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        // k-means++ on a sample, so the initialization is bounded too
+        let init_size = (3 * batch_size).max(self.n_clusters).min(n);
+        let sample = rand::seq::index::sample(&mut rng, n, init_size).into_vec();
+        let sample = input.select(Axis(0), &sample);
+        self.centroids = init_centroids(&sample.view(), self.n_clusters, &mut rng);
+        self.counts = vec![0; self.n_clusters];
+
+        let tol = self.tol * mean_variance(&input.view());
+        let batch_size_f = A::from_usize(batch_size).expect("valid float");
+        let alpha = (batch_size_f + batch_size_f) / A::from_usize(n + 1).expect("valid float");
+        let alpha = alpha.min(A::one());
+        let mut smoothed: Option<A> = None;
+        let mut best = A::infinity();
+        let mut no_improvement = 0;
+        let mut rows = Vec::with_capacity(batch_size);
+        for _ in 0..self.max_iter.saturating_mul(n.div_ceil(batch_size)) {
+            rows.clear();
+            rows.extend((0..batch_size).map(|_| input.row(rng.random_range(0..n))));
+            let (batch_inertia, shift) = self.step(&rows, &mut rng);
+            if self.n_steps == 1 {
+                // the first step mostly reflects the initialization
+                continue;
+            }
+            if tol > A::zero() && shift <= tol {
+                break;
+            }
+            let batch_inertia = batch_inertia / batch_size_f;
+            let current = smoothed.map_or(batch_inertia, |s| {
+                s * (A::one() - alpha) + batch_inertia * alpha
+            });
+            smoothed = Some(current);
+            if current < best {
+                best = current;
+                no_improvement = 0;
+            } else {
+                no_improvement += 1;
+                if self.max_no_improvement > 0 && no_improvement >= self.max_no_improvement {
+                    break;
+                }
+            }
+        }
+
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let assignment = assign(&rows, &self.centroids, self.n_features);
+        self.inertia = sum(assignment.iter().map(|(_, d)| *d));
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, (label, _)) in assignment.into_iter().enumerate() {
+            clusters.entry(label).or_default().push(i);
+        }
+        (clusters, Vec::new())
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its closest
+/// centroid.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for MiniBatchKMeans<A>
+where
+    A: FloatCore + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        assert!(
+            !self.centroids.is_empty(),
+            "`predict` requires a fitted model"
+        );
+        let centroids = self.centroids();
+        input
+            .rows()
+            .into_iter()
+            .map(|p| closest(&p, &centroids).0)
+            .collect()
+    }
+}
+
+/// Computes the Euclidean distance from each point in the given input to each
+/// centroid, one column per cluster.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for MiniBatchKMeans<A>
+where
+    A: num_traits::Float + FloatCore,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        assert!(
+            !self.centroids.is_empty(),
+            "`transform` requires a fitted model"
+        );
+        centroid_distances(input, &self.centroids())
+    }
+}
+
+/// Chooses initial centroids with k-means++: each centroid is sampled with a
+/// probability proportional to its squared distance to the closest centroid
+/// chosen so far.
+pub(crate) fn init_centroids<A, R>(input: &ArrayView2<A>, n_clusters: usize, rng: &mut R) -> Vec<A>
+where
+    A: FloatCore + Send + Sync,
+    R: Rng,
+{
+    let n = input.nrows();
+    let mut centroids = Vec::with_capacity(n_clusters * input.ncols());
+    let first = input.row(rng.random_range(0..n));
+    centroids.extend(first.iter().copied());
+    let mut distances: Vec<A> = input
+        .rows()
+        .into_iter()
+        .map(|p| squared_distance(&p, &first))
+        .collect();
+
+    for _ in 1..n_clusters {
+        let total = distances
+            .iter()
+            .fold(0., |acc, d| acc + d.to_f64().unwrap_or(0.));
+        let next = if total > 0. {
+            let mut target = rng.random::<f64>() * total;
+            distances
+                .iter()
+                .position(|d| {
+                    target -= d.to_f64().unwrap_or(0.);
+                    target < 0.
+                })
+                .unwrap_or(n - 1)
+        } else {
+            // all points coincide with centroids
+            rng.random_range(0..n)
+        };
+        let start = centroids.len();
+        centroids.extend(input.row(next).iter().copied());
+        let centroid = ArrayView1::from(&centroids[start..]);
+        for (d, p) in distances.iter_mut().zip(input.rows()) {
+            let to_new = squared_distance(&p, &centroid);
+            if to_new < *d {
+                *d = to_new;
+            }
+        }
+    }
+    centroids
+}
+
+/// Runs Lloyd iterations from the given centroids until the total squared
+/// shift of the centroids is at most `tol`, and returns the label of each
+/// point and the inertia.
+pub(crate) fn lloyd<A>(
+    input: &ArrayView2<A>,
+    centroids: &mut [A],
+    max_iter: usize,
+    tol: A,
+) -> (Vec<usize>, A)
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+{
+    let dim = input.ncols();
+    let k = centroids.len() / dim.max(1);
+    let rows: Vec<_> = input.rows().into_iter().collect();
+    let mut assignment = assign(&rows, centroids, dim);
+    for _ in 0..max_iter {
+        let mut sums = vec![Sum::new(); centroids.len()];
+        let mut counts = vec![0_usize; k];
+        for (p, (label, _)) in rows.iter().zip(&assignment) {
+            counts[*label] += 1;
+            for (s, v) in sums[label * dim..(label + 1) * dim].iter_mut().zip(p) {
+                s.add(*v);
+            }
+        }
+
+        // an empty cluster takes over the point farthest from its centroid
+        let mut taken = vec![false; rows.len()];
+        for c in 0..k {
+            if counts[c] > 0 {
+                continue;
+            }
+            let Some((far, _)) = assignment
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !taken[*i])
+                .max_by(|a, b| a.1 .1.partial_cmp(&b.1 .1).expect("valid distance"))
+            else {
+                break;
+            };
+            taken[far] = true;
+            sums[c * dim..(c + 1) * dim]
+                .iter_mut()
+                .zip(rows[far])
+                .for_each(|(s, v)| {
+                    *s = Sum::new();
+                    s.add(*v);
+                });
+            counts[c] = 1;
+        }
+
+        let mut shift = A::zero();
+        for (c, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let count = A::from_usize(*count).expect("valid float");
+            for (centroid, sum) in centroids[c * dim..(c + 1) * dim]
+                .iter_mut()
+                .zip(&sums[c * dim..(c + 1) * dim])
+            {
+                let mean = sum.value() / count;
+                shift += (mean - *centroid) * (mean - *centroid);
+                *centroid = mean;
+            }
+        }
+        assignment = assign(&rows, centroids, dim);
+        if shift <= tol {
+            break;
+        }
+    }
+
+    let inertia = sum(assignment.iter().map(|(_, d)| *d));
+    (
+        assignment.into_iter().map(|(label, _)| label).collect(),
+        inertia,
+    )
+}
+
+/// Finds the closest centroid of each point and the squared distance to it.
+fn assign<A>(rows: &[ArrayView1<A>], centroids: &[A], dim: usize) -> Vec<(usize, A)>
+where
+    A: FloatCore + Send + Sync,
+{
+    let centroids = ArrayView2::from_shape((centroids.len() / dim.max(1), dim), centroids)
+        .expect("centroids with `dim` features");
+    rows.par_iter().map(|p| closest(p, &centroids)).collect()
+}
+
+fn closest<A: FloatCore>(point: &ArrayView1<A>, centroids: &ArrayView2<A>) -> (usize, A) {
+    centroids
+        .rows()
+        .into_iter()
+        .map(|c| squared_distance(point, &c))
+        .enumerate()
+        .fold(
+            (0, A::infinity()),
+            |best, (i, d)| if d < best.1 { (i, d) } else { best },
+        )
+}
+
+/// Computes the Euclidean distance from each point to each centroid.
+fn centroid_distances<S, A>(input: &ArrayBase<S, Ix2>, centroids: &ArrayView2<A>) -> Array2<A>
+where
+    A: num_traits::Float + FloatCore,
+    S: Data<Elem = A>,
+{
+    Array2::from_shape_fn((input.nrows(), centroids.nrows()), |(i, c)| {
+        num_traits::Float::sqrt(squared_distance(&input.row(i), &centroids.row(c)))
+    })
+}
+
+fn squared_distance<A: FloatCore>(a: &ArrayView1<A>, b: &ArrayView1<A>) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (x, y)| acc + (*x - *y) * (*x - *y))
+}
+
+/// Computes the mean of the variances of the features.
+fn mean_variance<A>(input: &ArrayView2<A>) -> A
+where
+    A: AddAssign + FloatCore + FromPrimitive,
+{
+    let n = A::from_usize(input.nrows()).expect("valid float");
+    let mut total = A::zero();
+    for column in input.columns() {
+        let mean = sum(column.iter().copied()) / n;
+        total += sum(column.iter().map(|v| (*v - mean) * (*v - mean))) / n;
+    }
+    total / A::from_usize(input.ncols().max(1)).expect("valid float")
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, s, Array2, ShapeBuilder};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let kmeans = KMeans::<f32>::default();
+        assert_eq!(kmeans.n_clusters, 8);
+        assert_eq!(kmeans.n_init, 1);
+    }
+
+    #[test]
+    fn kmeans() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+        ];
+        let mut model = KMeans::new(2);
+        let (clusters, noise) = model.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2, 3], vec![4, 5]]);
+        assert!(noise.is_empty());
+
+        let centroids = model.centroids();
+        assert_eq!(centroids.dim(), (2, 2));
+        let expected = [[1.0, 2.05], [-2.1, 3.05]];
+        for e in expected {
+            assert!(centroids
+                .rows()
+                .into_iter()
+                .any(|c| (c[0] - e[0]).abs() < 1e-12 && (c[1] - e[1]).abs() < 1e-12));
+        }
+        let inertia = 0.0025 + 0.0325 + 0.0325 + 0.0025 + 0.0125 + 0.0125;
+        assert!((model.inertia() - inertia).abs() < 1e-12);
+    }
+
+    #[test]
+    fn transform() {
+        let data = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+        let mut model = KMeans::new(2);
+        model.fit(&data, None);
+        let labels = model.predict(&data);
+
+        let distances = model.transform(&array![[0., 0.5], [4., 3.5]]);
+        assert_eq!(distances.dim(), (2, 2));
+        let expected = [[0., 10.], [5., 45_f64.sqrt()]];
+        for (i, row) in expected.iter().enumerate() {
+            assert!((distances[[i, labels[0]]] - row[0]).abs() < 1e-12);
+            assert!((distances[[i, labels[2]]] - row[1]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn deterministic() {
+        let data = Array2::from_shape_fn((40, 2), |(i, j)| ((i * 7 + j * 13) % 17) as f64);
+        let mut a = KMeans::new(4);
+        a.n_init = 3;
+        a.seed = 42;
+        let mut b = KMeans::new(4);
+        b.n_init = 3;
+        b.seed = 42;
+        assert_eq!(a.fit(&data, None), b.fit(&data, None));
+        assert_eq!(a.centroids(), b.centroids());
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn strided_input() {
+        let data = Array2::from_shape_fn((40, 3), |(i, j)| ((i * 7 + j * 13) % 17) as f64);
+        let mut column_major = Array2::zeros(data.dim().f());
+        column_major.assign(&data);
+        let columns = data.slice(s![.., ..2]);
+        for (input, standard) in [
+            (column_major.view(), data.view()),
+            (columns, columns.as_standard_layout().view()),
+        ] {
+            assert!(!input.is_standard_layout());
+            let mut a = KMeans::new(4);
+            let mut b = KMeans::new(4);
+            assert_eq!(a.fit(&input, None), b.fit(&standard, None));
+            assert_eq!(a.centroids(), b.centroids());
+
+            let mut a = MiniBatchKMeans::new(4, 8);
+            let mut b = MiniBatchKMeans::new(4, 8);
+            assert_eq!(a.fit(&input, None), b.fit(&standard, None));
+        }
+    }
+
+    #[test]
+    fn more_clusters_than_distinct_points() {
+        let data = array![[0., 0.], [0., 0.], [1., 1.], [1., 1.]];
+        let mut model = KMeans::new(3);
+        let (clusters, _) = model.fit(&data, None);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 4);
+        assert_eq!(model.centroids().nrows(), 3);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut model = KMeans::default();
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn mini_batch() {
+        let data = Array2::from_shape_fn((600, 2), |(i, j)| {
+            let center = [[0., 0.], [10., 10.], [-10., 10.]][i % 3][j];
+            center + ((i * 7 + j * 3) % 11) as f64 / 10.
+        });
+        let mut model = MiniBatchKMeans::new(3, 32);
+        model.seed = 7;
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(noise.is_empty());
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters.len(), 3);
+        for (c, members) in clusters.iter().enumerate() {
+            assert_eq!(members.len(), 200);
+            assert!(
+                members.iter().all(|i| i % 3 == members[0] % 3),
+                "cluster {c}"
+            );
+        }
+        assert_eq!(model.centroids().dim(), (3, 2));
+
+        let mut full = KMeans::new(3);
+        full.fit(&data, None);
+        assert!(model.inertia() < full.inertia() * 1.1);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn mini_batch_convergence() {
+        let data = Array2::from_shape_fn((2000, 2), |(i, j)| {
+            (i % 2) as f64 * 20. + ((i * 13 + j * 5) % 7) as f64 / 10.
+        });
+        let mut model = MiniBatchKMeans::new(2, 50);
+        model.fit(&data, None);
+        let n_batches = 2000 / 50;
+        assert!(model.n_steps() < model.max_iter * n_batches);
+
+        model.max_no_improvement = 0;
+        model.max_iter = 2;
+        model.fit(&data, None);
+        assert_eq!(model.n_steps(), 2 * n_batches);
+
+        let mut a = MiniBatchKMeans::new(2, 50);
+        let mut b = MiniBatchKMeans::new(2, 50);
+        assert_eq!(a.fit(&data, None), b.fit(&data, None));
+        assert_eq!(a.centroids(), b.centroids());
+    }
+
+    #[test]
+    fn mini_batch_partial_fit() {
+        let mut model = MiniBatchKMeans::new(2, 4);
+        model.partial_fit(&array![[0., 0.], [0.2, 0.], [5., 5.], [5.2, 5.]]);
+        model.partial_fit(&array![[0., 0.2], [5., 5.2]]);
+        assert_eq!(model.n_steps(), 2);
+        let labels = model.predict(&array![[0.1, 0.1], [5.1, 5.1], [-1., 0.]]);
+        assert_ne!(labels[0], labels[1]);
+        assert_eq!(labels[0], labels[2]);
+    }
+
+    #[test]
+    fn mini_batch_reassignment() {
+        // one centroid starts on an outlier and never sees another point
+        let mut model = MiniBatchKMeans::new(3, 20);
+        model.partial_fit(&array![[0.], [10.], [1000.]]);
+        let batch = Array2::from_shape_fn((100, 1), |(i, _)| if i < 50 { 0. } else { 10. });
+        for _ in 0..REASSIGNMENT_INTERVAL {
+            model.partial_fit(&batch);
+        }
+        assert!(model.centroids().iter().all(|c| *c < 1000.));
+    }
+
+    #[test]
+    fn mini_batch_fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut model = MiniBatchKMeans::default();
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::{Euclidean, Metric};
+use rand::{rngs::StdRng, seq::index::sample, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Transform};
+use crate::parallel::prelude::*;
+
+/// K-medoids clustering algorithm, which uses the `FasterPAM` algorithm to find
+/// medoids.
+///
+/// Unlike k-means, the center of each cluster is one of its points, the
+/// medoid, and only the dissimilarities between points are needed. This
+/// makes k-medoids suitable for any metric, or for precomputed
+/// dissimilarities with [`KMedoids::fit_precomputed`].
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Fit, KMedoids};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let mut kmedoids = KMedoids::new(2, Euclidean::default());
+/// let (clusters, noise) = kmedoids.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// assert!(clusters[&0].contains(&kmedoids.medoids()[0]));
+/// ```
+///
+/// # References
+/// - Schubert, Erich, and Peter J. Rousseeuw. "Fast and eager k-medoids clustering: O(k) runtime improvement of the PAM, CLARA, and CLARANS algorithms."
+///   Information Systems 101 (2021): 101804.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KMedoids<A, M> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The maximum number of passes over the points looking for a swap that
+    /// reduces the loss.
+    pub max_iter: usize,
+
+    /// The seed of the random number generator choosing initial medoids.
+    pub seed: u64,
+
+    /// The metric to compute distance between the entries.
+    pub metric: M,
+
+    medoids: Vec<usize>,
+    loss: A,
+    #[serde(default)]
+    medoid_points: Vec<A>,
+}
+
+impl<A> Default for KMedoids<A, Euclidean>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(8, Euclidean::default())
+    }
+}
+
+impl<A, M> KMedoids<A, M>
+where
+    A: FloatCore,
+{
+    /// Creates a model with `n_clusters` clusters measured by `metric`,
+    /// running at most 100 iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::KMedoids;
+    ///
+    /// let kmedoids = KMedoids::<f64, _>::new(3, Euclidean::default());
+    /// assert_eq!(kmedoids.n_clusters, 3);
+    /// assert_eq!(kmedoids.max_iter, 100);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize, metric: M) -> Self {
+        Self {
+            n_clusters,
+            max_iter: 100,
+            seed: 0,
+            metric,
+            medoids: vec![],
+            loss: A::zero(),
+            medoid_points: vec![],
+        }
+    }
+
+    /// Returns the index of the medoid of each cluster, indexed by cluster ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, KMedoids};
+    ///
+    /// let points = array![[0.], [1.], [2.], [10.], [11.], [12.]];
+    /// let mut kmedoids = KMedoids::new(2, Euclidean::default());
+    /// kmedoids.fit(&points, None);
+    ///
+    /// let mut medoids = kmedoids.medoids().to_vec();
+    /// medoids.sort_unstable();
+    /// assert_eq!(medoids, [1, 4]);
+    /// ```
+    #[must_use]
+    pub fn medoids(&self) -> &[usize] {
+        &self.medoids
+    }
+
+    /// Returns the sum of the dissimilarities of the points to their medoids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, KMedoids};
+    ///
+    /// let points = array![[0.], [1.], [2.], [10.], [11.], [12.]];
+    /// let mut kmedoids = KMedoids::new(2, Euclidean::default());
+    /// kmedoids.fit(&points, None);
+    ///
+    /// assert!((kmedoids.loss() - 4_f64).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn loss(&self) -> A {
+        self.loss
+    }
+}
+
+impl<A, M> KMedoids<A, M>
+where
+    A: AddAssign + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Fits k-medoids to a matrix of dissimilarities, whose entry `(i, j)` is
+    /// the dissimilarity between points `i` and `j`, ignoring `metric`.
+    ///
+    /// The result is in the same form as that of `fit`.
+    ///
+    /// # Panics
+    /// Panics if `dissimilarities` is not a square matrix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::KMedoids;
+    ///
+    /// let dissimilarities = array![
+    ///     [0., 1., 9., 9.],
+    ///     [1., 0., 9., 9.],
+    ///     [9., 9., 0., 2.],
+    ///     [9., 9., 2., 0.],
+    /// ];
+    /// let mut kmedoids = KMedoids::new(2, Euclidean::default());
+    /// let (clusters, _) = kmedoids.fit_precomputed(&dissimilarities);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(kmedoids.loss(), 3.);
+    /// ```
+    pub fn fit_precomputed<S>(
+        &mut self,
+        dissimilarities: &ArrayBase<S, Ix2>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        assert_eq!(
+            dissimilarities.nrows(),
+            dissimilarities.ncols(),
+            "dissimilarities should be square"
+        );
+        let n = dissimilarities.nrows();
+        let k = self.n_clusters.min(n);
+        self.medoid_points = vec![];
+        if k == 0 {
+            self.medoids = vec![];
+            self.loss = A::zero();
+            return (HashMap::new(), (0..n).collect());
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let medoids = sample(&mut rng, n, k).into_vec();
+        let (medoids, loss) = faster_pam(&dissimilarities.view(), medoids, self.max_iter);
+
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, row) in dissimilarities.rows().into_iter().enumerate() {
+            let cluster = medoids
+                .iter()
+                .position(|m| *m == i)
+                .unwrap_or_else(|| nearest(&medoids, |m| row[m]).0);
+            clusters.entry(cluster).or_default().push(i);
+        }
+        self.medoids = medoids;
+        self.loss = loss;
+        (clusters, Vec::new())
+    }
+}
+
+/// Fits k-medoids to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster. The medoid of cluster `i` is `medoids()[i]`.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A, M>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for KMedoids<A, M>
+where
+    A: AddAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let n = input.nrows();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let values: Vec<A> = rows
+            .par_iter()
+            .flat_map_iter(|p| rows.iter().map(|q| self.metric.distance(p, q)))
+            .collect();
+        let dissimilarities =
+            Array2::from_shape_vec((n, n), values).expect("one dissimilarity for each pair");
+        let result = self.fit_precomputed(&dissimilarities);
+        self.medoid_points = self
+            .medoids
+            .iter()
+            .flat_map(|&m| input.row(m).to_vec())
+            .collect();
+        result
+    }
+}
+
+/// Computes the distance from each point in the given input to each medoid,
+/// one column per cluster.
+///
+/// # Panics
+/// Panics if the model has not been fitted with [`Fit::fit`], since
+/// [`KMedoids::fit_precomputed`] does not see the medoids' features.
+impl<S, A, M> Transform<ArrayBase<S, Ix2>, Array2<A>> for KMedoids<A, M>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+    M: Metric<A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        assert!(
+            !self.medoid_points.is_empty(),
+            "`transform` requires a model fitted on points"
+        );
+        let k = self.medoids.len();
+        let medoids =
+            ArrayView2::from_shape((k, self.medoid_points.len() / k), &self.medoid_points)
+                .expect("one row for each medoid");
+        Array2::from_shape_fn((input.nrows(), k), |(i, c)| {
+            self.metric.distance(&input.row(i), &medoids.row(c))
+        })
+    }
+}
+
+/// The nearest and second nearest medoids of a point, as positions in the
+/// list of medoids, and the dissimilarities to them.
+#[derive(Clone, Copy)]
+struct Assignment<A> {
+    nearest: usize,
+    d_nearest: A,
+    second: usize,
+    d_second: A,
+}
+
+/// Improves the given medoids by swapping a medoid with a non-medoid as long
+/// as it reduces the loss, and returns the medoids and the loss.
+fn faster_pam<A>(d: &ArrayView2<A>, mut medoids: Vec<usize>, max_iter: usize) -> (Vec<usize>, A)
+where
+    A: AddAssign + FloatCore + Send + Sync,
+{
+    let n = d.nrows();
+    if medoids.len() == 1 {
+        // the best medoid has the smallest sum of dissimilarities
+        let (medoid, loss) = nearest(&(0..n).collect::<Vec<_>>(), |i| {
+            d.row(i).iter().fold(A::zero(), |acc, v| acc + *v)
+        });
+        return (vec![medoid], loss);
+    }
+    let mut assignments = assign(d, &medoids);
+    if medoids.len() == n {
+        let loss = assignments
+            .iter()
+            .fold(A::zero(), |acc, a| acc + a.d_nearest);
+        return (medoids, loss);
+    }
+
+    let mut removal_loss = removal_loss(&assignments, medoids.len());
+    let mut last_swap = n;
+    'passes: for _ in 0..max_iter {
+        for candidate in 0..n {
+            if candidate == last_swap {
+                break 'passes;
+            }
+            if medoids.contains(&candidate) {
+                continue;
+            }
+
+            let mut delta = removal_loss.clone();
+            let mut shared = A::zero();
+            for (o, a) in assignments.iter().enumerate() {
+                let doj = d[[o, candidate]];
+                if doj < a.d_nearest {
+                    shared += doj - a.d_nearest;
+                    delta[a.nearest] += a.d_nearest - a.d_second;
+                } else if doj < a.d_second {
+                    delta[a.nearest] += doj - a.d_second;
+                }
+            }
+            let (best, change) = nearest(&(0..medoids.len()).collect::<Vec<_>>(), |m| delta[m]);
+            if change + shared < A::zero() {
+                medoids[best] = candidate;
+                assignments = assign(d, &medoids);
+                removal_loss = self::removal_loss(&assignments, medoids.len());
+                last_swap = candidate;
+            }
+        }
+        if last_swap == n {
+            // no swap improves the initial medoids
+            break;
+        }
+    }
+
+    let loss = assignments
+        .iter()
+        .fold(A::zero(), |acc, a| acc + a.d_nearest);
+    (medoids, loss)
+}
+
+fn assign<A>(d: &ArrayView2<A>, medoids: &[usize]) -> Vec<Assignment<A>>
+where
+    A: FloatCore + Send + Sync,
+{
+    (0..d.nrows())
+        .into_par_iter()
+        .map(|o| {
+            let mut a = Assignment {
+                nearest: 0,
+                d_nearest: A::infinity(),
+                second: 0,
+                d_second: A::infinity(),
+            };
+            for (pos, m) in medoids.iter().enumerate() {
+                let dist = if *m == o { A::zero() } else { d[[o, *m]] };
+                if dist < a.d_nearest {
+                    a.second = a.nearest;
+                    a.d_second = a.d_nearest;
+                    a.nearest = pos;
+                    a.d_nearest = dist;
+                } else if dist < a.d_second {
+                    a.second = pos;
+                    a.d_second = dist;
+                }
+            }
+            a
+        })
+        .collect()
+}
+
+/// Computes the increase of the loss caused by removing each medoid.
+fn removal_loss<A: AddAssign + FloatCore>(assignments: &[Assignment<A>], k: usize) -> Vec<A> {
+    let mut loss = vec![A::zero(); k];
+    for a in assignments {
+        loss[a.nearest] += a.d_second - a.d_nearest;
+    }
+    loss
+}
+
+/// Returns the position of the candidate with the smallest value, and the
+/// value.
+fn nearest<A: FloatCore>(candidates: &[usize], value: impl Fn(usize) -> A) -> (usize, A) {
+    candidates
+        .iter()
+        .enumerate()
+        .fold((0, A::infinity()), |best, (pos, c)| {
+            let v = value(*c);
+            if v < best.1 {
+                (pos, v)
+            } else {
+                best
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let kmedoids = KMedoids::<f32, Euclidean>::default();
+        assert_eq!(kmedoids.n_clusters, 8);
+        assert_eq!(kmedoids.max_iter, 100);
+    }
+
+    #[test]
+    fn kmedoids() {
+        let data = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [0.4, 0.4],
+            [10., 10.],
+            [10., 11.],
+            [11., 10.],
+            [10.4, 10.4],
+            [0., 20.],
+            [1., 20.],
+            [0., 21.],
+            [0.4, 20.4],
+        ];
+        for seed in 0..5 {
+            let mut kmedoids = KMedoids::new(3, Euclidean::default());
+            kmedoids.seed = seed;
+            let (clusters, noise) = kmedoids.fit(&data, None);
+            assert!(noise.is_empty());
+            assert_eq!(clusters.len(), 3);
+            for (id, members) in &clusters {
+                assert_eq!(members.len(), 4);
+                assert!(members.iter().all(|i| i / 4 == members[0] / 4));
+                // the point in the middle of each group is its medoid
+                assert_eq!(kmedoids.medoids()[*id] % 4, 3);
+            }
+        }
+    }
+
+    #[test]
+    fn fit_precomputed() {
+        let data = array![[0.], [1.], [2.], [10.], [12.]];
+        let dissimilarities =
+            Array2::from_shape_fn((5, 5), |(i, j)| f64::abs(data[[i, 0]] - data[[j, 0]]));
+        let mut kmedoids = KMedoids::new(2, Euclidean::default());
+        let (clusters, _) = kmedoids.fit_precomputed(&dissimilarities);
+        let mut medoids = kmedoids.medoids().to_vec();
+        medoids.sort_unstable();
+        assert_eq!(medoids[0], 1);
+        assert!((kmedoids.loss() - 4.).abs() < f64::EPSILON);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 5);
+
+        let (fitted, _) = kmedoids.fit(&data, None);
+        assert!((kmedoids.loss() - 4.).abs() < f64::EPSILON);
+        assert_eq!(fitted.len(), 2);
+    }
+
+    #[test]
+    fn transform() {
+        let data = array![[0.], [1.], [2.], [10.], [12.]];
+        let mut kmedoids = KMedoids::new(2, Euclidean::default());
+        kmedoids.fit(&data, None);
+        let distances = kmedoids.transform(&array![[1.], [7.]]);
+        assert_eq!(distances.dim(), (2, 2));
+        for (c, &m) in kmedoids.medoids().iter().enumerate() {
+            assert!((distances[[0, c]] - (1. - data[[m, 0]]).abs()).abs() < 1e-12);
+            assert!((distances[[1, c]] - (7. - data[[m, 0]]).abs()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "requires a model fitted on points")]
+    fn transform_precomputed() {
+        let mut kmedoids = KMedoids::new(1, Euclidean::default());
+        kmedoids.fit_precomputed(&array![[0., 1.], [1., 0.]]);
+        kmedoids.transform(&array![[0.]]);
+    }
+
+    #[test]
+    fn fit_small() {
+        let mut kmedoids = KMedoids::new(3, Euclidean::default());
+        let (clusters, noise) = kmedoids.fit(&array![[0., 0.], [1., 1.]], None);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+        assert!(kmedoids.loss().abs() < f64::EPSILON);
+
+        let empty: Array2<f64> = Array2::zeros((0, 2));
+        let (clusters, noise) = kmedoids.fit(&empty, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
@@ -0,0 +1,834 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use ndarray::{ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive, Zero};
+use rand::{rngs::StdRng, seq::index::sample, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::parallel::prelude::*;
+use crate::sum::sum;
+
+/// K-modes clustering algorithm for categorical data.
+///
+/// Each feature is a category, such as a color or a country, and two points
+/// are as dissimilar as the number of features in which they differ. The
+/// center of each cluster is its mode, the most frequent category of each
+/// feature, with ties broken by the smallest category. Unlike k-means on
+/// one-hot encoded features, the centers are actual categories and no
+/// feature counts more than another because it has more categories.
+///
+/// The initial modes of a run are distinct points chosen at random, and
+/// the run with the lowest cost out of `n_init` is kept.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, KModes, Predict};
+///
+/// // color, shape, size
+/// let points = array![
+///     ["red", "round", "small"],
+///     ["red", "round", "large"],
+///     ["red", "oval", "small"],
+///     ["green", "square", "large"],
+///     ["green", "square", "small"],
+///     ["blue", "square", "large"],
+/// ];
+/// let mut kmodes = KModes::new(2);
+/// let (clusters, noise) = kmodes.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+///
+/// let labels = kmodes.predict(&array![["red", "oval", "large"]]);
+/// assert!(clusters[&labels[0]].contains(&0));
+/// assert_eq!(kmodes.modes()[[labels[0], 0]], "red");
+/// ```
+///
+/// # References
+/// - Huang, Zhexue. "Extensions to the k-means algorithm for clustering large data sets with categorical values."
+///   Data Mining and Knowledge Discovery 2.3 (1998): 283-304.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KModes<T> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The maximum number of iterations of a run.
+    pub max_iter: usize,
+
+    /// The number of runs with different initial modes. The run with the
+    /// lowest cost is kept.
+    pub n_init: usize,
+
+    /// The seed of the random number generator choosing initial modes.
+    pub seed: u64,
+
+    modes: Vec<T>,
+    n_features: usize,
+    cost: usize,
+}
+
+impl<T> Default for KModes<T> {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl<T> KModes<T> {
+    /// Creates a model with `n_clusters` clusters, running k-modes once for at
+    /// most 100 iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::KModes;
+    ///
+    /// let kmodes = KModes::<&str>::new(3);
+    /// assert_eq!(kmodes.n_clusters, 3);
+    /// assert_eq!(kmodes.n_init, 1);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            n_clusters,
+            max_iter: 100,
+            n_init: 1,
+            seed: 0,
+            modes: vec![],
+            n_features: 0,
+            cost: 0,
+        }
+    }
+
+    /// Returns the modes of the clusters found by the last fit, one row per
+    /// cluster.
+    ///
+    /// There are fewer modes than `n_clusters` if there are fewer points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KModes};
+    ///
+    /// let points = array![
+    ///     ["red", "round"], ["red", "round"], ["red", "oval"],
+    ///     ["blue", "square"], ["blue", "square"],
+    /// ];
+    /// let mut kmodes = KModes::new(2);
+    /// kmodes.n_init = 10;
+    /// let (clusters, _) = kmodes.fit(&points, None);
+    ///
+    /// let label = clusters.iter().find(|(_, members)| members.contains(&0)).unwrap().0;
+    /// assert_eq!(kmodes.modes().row(*label), array!["red", "round"]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn modes(&self) -> ArrayView2<'_, T> {
+        ArrayView2::from_shape(
+            (self.modes.len() / self.n_features.max(1), self.n_features),
+            &self.modes,
+        )
+        .expect("modes of the fitted model")
+    }
+
+    /// Returns the total number of features in which the points differ from
+    /// the modes of their clusters in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KModes};
+    ///
+    /// let points = array![
+    ///     ["red", "round"], ["red", "round"], ["red", "oval"],
+    ///     ["blue", "square"], ["blue", "square"],
+    /// ];
+    /// let mut kmodes = KModes::new(2);
+    /// kmodes.n_init = 10;
+    /// kmodes.fit(&points, None);
+    ///
+    /// assert_eq!(kmodes.cost(), 1);
+    /// ```
+    #[must_use]
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+/// Fits the k-modes clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point, and each column to
+///   a categorical feature.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, T>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for KModes<T>
+where
+    T: Clone + PartialOrd + Send + Sync,
+    S: Data<Elem = T>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.n_features = input.ncols();
+        self.cost = 0;
+        if input.nrows() == 0 || self.n_clusters == 0 {
+            self.modes = vec![];
+            return (HashMap::new(), Vec::new());
+        }
+
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let (modes, labels, cost) = fit_centers(
+            &rows,
+            self.n_clusters,
+            self.n_init,
+            self.max_iter,
+            self.seed,
+            mismatches,
+            |members| {
+                (0..self.n_features)
+                    .map(|j| mode(members.iter().map(|p| p[j].clone())))
+                    .collect()
+            },
+        );
+        self.modes = modes.into_iter().flatten().collect();
+        self.cost = cost;
+        (group(labels), Vec::new())
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its closest
+/// mode.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, T> Predict<ArrayBase<S, Ix2>, Vec<usize>> for KModes<T>
+where
+    T: PartialEq,
+    S: Data<Elem = T>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        assert!(!self.modes.is_empty(), "`predict` requires a fitted model");
+        let modes: Vec<_> = self.modes.chunks(self.n_features.max(1)).collect();
+        input
+            .rows()
+            .into_iter()
+            .map(|p| closest(&p, &modes, |p, m| mismatches(p, m)).0)
+            .collect()
+    }
+}
+
+/// K-prototypes clustering algorithm for data with both numeric and
+/// categorical features.
+///
+/// The features listed in `categorical` hold category codes, and the others
+/// are numeric. The dissimilarity between two points is the squared
+/// Euclidean distance between their numeric features plus `gamma` times the
+/// number of categorical features in which they differ. The center of each
+/// cluster, its prototype, has the mean of each numeric feature and the
+/// mode of each categorical feature, as in [`KModes`].
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, KPrototypes, Predict};
+///
+/// // income, age, region code
+/// let points = array![
+///     [1.0, 25., 0.],
+///     [1.2, 27., 0.],
+///     [0.9, 24., 1.],
+///     [5.0, 60., 2.],
+///     [5.5, 58., 2.],
+///     [4.8, 61., 2.],
+/// ];
+/// let mut kprototypes = KPrototypes::new(2, vec![2]);
+/// let (clusters, _) = kprototypes.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// let labels = kprototypes.predict(&array![[1.1, 26., 0.], [5.2, 59., 2.]]);
+/// assert!(clusters[&labels[0]].contains(&0));
+/// assert!(clusters[&labels[1]].contains(&3));
+/// assert_eq!(kprototypes.prototypes()[[labels[0], 2]], 0.);
+/// ```
+///
+/// # References
+/// - Huang, Zhexue. "Clustering large data sets with mixed numeric and categorical values."
+///   Proceedings of the 1st Pacific-Asia Conference on Knowledge Discovery and Data Mining (1997): 21-34.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KPrototypes<A> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The indices of the categorical features.
+    pub categorical: Vec<usize>,
+
+    /// The weight of a mismatch in a categorical feature, relative to the
+    /// squared distance between the numeric features. If `None`, half of the
+    /// mean standard deviation of the numeric features is used, or one if
+    /// that is zero.
+    pub gamma: Option<A>,
+
+    /// The maximum number of iterations of a run.
+    pub max_iter: usize,
+
+    /// The number of runs with different initial prototypes. The run with
+    /// the lowest cost is kept.
+    pub n_init: usize,
+
+    /// The seed of the random number generator choosing initial prototypes.
+    pub seed: u64,
+
+    prototypes: Vec<A>,
+    n_features: usize,
+    cost: A,
+    fitted_gamma: A,
+}
+
+impl<A> KPrototypes<A>
+where
+    A: FloatCore,
+{
+    /// Creates a model with `n_clusters` clusters, treating the features of
+    /// `categorical` as categorical and the others as numeric.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::KPrototypes;
+    ///
+    /// let kprototypes = KPrototypes::<f64>::new(3, vec![2]);
+    /// assert_eq!(kprototypes.categorical, [2]);
+    /// assert_eq!(kprototypes.gamma, None);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize, categorical: Vec<usize>) -> Self {
+        Self {
+            n_clusters,
+            categorical,
+            gamma: None,
+            max_iter: 100,
+            n_init: 1,
+            seed: 0,
+            prototypes: vec![],
+            n_features: 0,
+            cost: A::zero(),
+            fitted_gamma: A::zero(),
+        }
+    }
+
+    /// Returns the prototypes of the clusters found by the last fit, one row
+    /// per cluster.
+    ///
+    /// There are fewer prototypes than `n_clusters` if there are fewer
+    /// points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KPrototypes};
+    ///
+    /// let points = array![[1., 0.], [1.2, 0.], [1.1, 0.], [5., 1.], [5.2, 1.]];
+    /// let mut kprototypes = KPrototypes::new(2, vec![1]);
+    /// let (clusters, _) = kprototypes.fit(&points, None);
+    ///
+    /// let label = clusters.iter().find(|(_, members)| members.contains(&0)).unwrap().0;
+    /// let prototype = kprototypes.prototypes().row(*label).to_owned();
+    /// assert!((prototype[0] - 1.1_f64).abs() < 1e-9);
+    /// assert_eq!(prototype[1], 0.);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn prototypes(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (
+                self.prototypes.len() / self.n_features.max(1),
+                self.n_features,
+            ),
+            &self.prototypes,
+        )
+        .expect("prototypes of the fitted model")
+    }
+
+    /// Returns the sum of the dissimilarities of the points to the
+    /// prototypes of their clusters in the last fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KPrototypes};
+    ///
+    /// let points = array![[1., 0.], [1., 0.], [1., 1.], [5., 1.], [5., 1.]];
+    /// let mut kprototypes = KPrototypes::new(2, vec![1]);
+    /// kprototypes.gamma = Some(0.5);
+    /// kprototypes.fit(&points, None);
+    ///
+    /// assert!((kprototypes.cost() - 0.5_f64).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn cost(&self) -> A {
+        self.cost
+    }
+
+    /// Returns the weight of a categorical mismatch used by the last fit,
+    /// which is `gamma` if set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, KPrototypes};
+    ///
+    /// let points = array![[1., 0.], [1., 0.], [1., 1.], [5., 1.], [5., 1.]];
+    /// let mut kprototypes = KPrototypes::new(2, vec![1]);
+    /// kprototypes.gamma = Some(0.5);
+    /// kprototypes.fit(&points, None);
+    ///
+    /// assert_eq!(kprototypes.fitted_gamma(), 0.5);
+    ///
+    /// ```
+    #[must_use]
+    pub fn fitted_gamma(&self) -> A {
+        self.fitted_gamma
+    }
+
+    /// Returns whether each feature is categorical.
+    fn is_categorical(&self) -> Vec<bool> {
+        let mut is_categorical = vec![false; self.n_features];
+        for &j in &self.categorical {
+            is_categorical[j] = true;
+        }
+        is_categorical
+    }
+}
+
+/// Fits the k-prototypes clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+///
+/// # Panics
+/// Panics if an index in `categorical` is not less than the number of
+/// features.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for KPrototypes<A>
+where
+    A: AddAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.n_features = input.ncols();
+        assert!(
+            self.categorical.iter().all(|j| *j < self.n_features),
+            "categorical features should be columns of the input"
+        );
+        self.cost = A::zero();
+        if input.nrows() == 0 || self.n_clusters == 0 {
+            self.prototypes = vec![];
+            return (HashMap::new(), Vec::new());
+        }
+
+        let is_categorical = self.is_categorical();
+        self.fitted_gamma = self
+            .gamma
+            .unwrap_or_else(|| default_gamma(&input.view(), &is_categorical));
+        let gamma = self.fitted_gamma;
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let (prototypes, labels, cost) = fit_centers(
+            &rows,
+            self.n_clusters,
+            self.n_init,
+            self.max_iter,
+            self.seed,
+            |p, c| mixed_dissimilarity(p, c, &is_categorical, gamma),
+            |members| {
+                let n = A::from_usize(members.len()).expect("valid float");
+                (0..self.n_features)
+                    .map(|j| {
+                        let values = members.iter().map(|p| p[j]);
+                        if is_categorical[j] {
+                            mode(values)
+                        } else {
+                            sum(values) / n
+                        }
+                    })
+                    .collect()
+            },
+        );
+        self.prototypes = prototypes.into_iter().flatten().collect();
+        self.cost = cost;
+        (group(labels), Vec::new())
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its closest
+/// prototype.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, Vec<usize>> for KPrototypes<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<usize> {
+        assert!(
+            !self.prototypes.is_empty(),
+            "`predict` requires a fitted model"
+        );
+        let is_categorical = self.is_categorical();
+        let prototypes: Vec<_> = self.prototypes.chunks(self.n_features.max(1)).collect();
+        input
+            .rows()
+            .into_iter()
+            .map(|p| {
+                closest(&p, &prototypes, |p, c| {
+                    mixed_dissimilarity(p, c, &is_categorical, self.fitted_gamma)
+                })
+                .0
+            })
+            .collect()
+    }
+}
+
+/// Runs `n_init` rounds of alternately assigning the points to their closest
+/// centers and updating each center from its points with `update`, and
+/// returns the centers, the label of each point and the total dissimilarity
+/// of the round with the lowest one.
+fn fit_centers<T, D>(
+    rows: &[ArrayView1<T>],
+    n_clusters: usize,
+    n_init: usize,
+    max_iter: usize,
+    seed: u64,
+    dissimilarity: impl Fn(&ArrayView1<T>, &[T]) -> D + Sync,
+    update: impl Fn(&[&ArrayView1<T>]) -> Vec<T>,
+) -> (Vec<Vec<T>>, Vec<usize>, D)
+where
+    T: Clone + Sync,
+    D: Copy + PartialOrd + Send + Zero,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut best: Option<(D, Vec<Vec<T>>, Vec<usize>)> = None;
+    for _ in 0..n_init.max(1) {
+        let mut centers = init_centers(rows, n_clusters, &mut rng, &dissimilarity);
+        let (labels, cost) = alternate(rows, &mut centers, max_iter, &dissimilarity, &update);
+        if best.as_ref().map_or(true, |(b, _, _)| cost < *b) {
+            best = Some((cost, centers, labels));
+        }
+    }
+    let (cost, centers, labels) = best.expect("at least one run");
+    (centers, labels, cost)
+}
+
+/// Chooses `n_clusters` points at random as centers, preferring points that
+/// differ from the centers already chosen.
+fn init_centers<T, D, R>(
+    rows: &[ArrayView1<T>],
+    n_clusters: usize,
+    rng: &mut R,
+    dissimilarity: impl Fn(&ArrayView1<T>, &[T]) -> D,
+) -> Vec<Vec<T>>
+where
+    T: Clone,
+    D: PartialOrd + Zero,
+    R: Rng,
+{
+    let order = sample(rng, rows.len(), rows.len()).into_vec();
+    let mut centers: Vec<Vec<T>> = vec![];
+    let mut duplicates = vec![];
+    for i in order {
+        if centers.len() == n_clusters {
+            break;
+        }
+        if centers
+            .iter()
+            .any(|c| dissimilarity(&rows[i], c) <= D::zero())
+        {
+            duplicates.push(i);
+        } else {
+            centers.push(rows[i].to_vec());
+        }
+    }
+    let missing = n_clusters.saturating_sub(centers.len());
+    centers.extend(
+        duplicates
+            .into_iter()
+            .take(missing)
+            .map(|i| rows[i].to_vec()),
+    );
+    centers
+}
+
+/// Alternates between assigning the points to their closest centers and
+/// updating the centers until no point changes its cluster, and returns the
+/// label of each point and the total dissimilarity.
+fn alternate<T, D>(
+    rows: &[ArrayView1<T>],
+    centers: &mut [Vec<T>],
+    max_iter: usize,
+    dissimilarity: &(impl Fn(&ArrayView1<T>, &[T]) -> D + Sync),
+    update: &impl Fn(&[&ArrayView1<T>]) -> Vec<T>,
+) -> (Vec<usize>, D)
+where
+    T: Sync,
+    D: Copy + PartialOrd + Send + Zero,
+{
+    let assign = |centers: &[Vec<T>]| -> Vec<(usize, D)> {
+        rows.par_iter()
+            .map(|p| closest(p, centers, |p, c| dissimilarity(p, c)))
+            .collect()
+    };
+    let mut assignment = assign(centers);
+    for _ in 0..max_iter {
+        let mut members: Vec<Vec<&ArrayView1<T>>> = vec![vec![]; centers.len()];
+        for (p, (label, _)) in rows.iter().zip(&assignment) {
+            members[*label].push(p);
+        }
+
+        // an empty cluster takes over the point farthest from its center
+        let mut taken = vec![false; rows.len()];
+        for cluster in members.iter_mut().filter(|m| m.is_empty()) {
+            let Some((far, _)) = assignment
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !taken[*i])
+                .max_by(|a, b| {
+                    a.1 .1
+                        .partial_cmp(&b.1 .1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+            else {
+                break;
+            };
+            taken[far] = true;
+            cluster.push(&rows[far]);
+        }
+
+        for (center, members) in centers.iter_mut().zip(&members) {
+            *center = update(members);
+        }
+        let next = assign(centers);
+        let changed = next.iter().zip(&assignment).any(|(a, b)| a.0 != b.0);
+        assignment = next;
+        if !changed {
+            break;
+        }
+    }
+
+    let cost = assignment.iter().fold(D::zero(), |acc, (_, d)| acc + *d);
+    (
+        assignment.into_iter().map(|(label, _)| label).collect(),
+        cost,
+    )
+}
+
+/// Returns the position of the center closest to `point`, the first one in
+/// case of a tie, and the dissimilarity to it.
+fn closest<P, C, D>(point: &P, centers: &[C], dissimilarity: impl Fn(&P, &C) -> D) -> (usize, D)
+where
+    D: PartialOrd,
+{
+    centers
+        .iter()
+        .map(|c| dissimilarity(point, c))
+        .enumerate()
+        .reduce(|best, (i, d)| if d < best.1 { (i, d) } else { best })
+        .expect("at least one center")
+}
+
+/// Returns the most frequent of the given values, the smallest one in case
+/// of a tie.
+fn mode<T: Clone + PartialOrd>(values: impl Iterator<Item = T>) -> T {
+    let mut values: Vec<T> = values.collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mut best = (0, 0);
+    let mut start = 0;
+    for i in 1..=values.len() {
+        if i == values.len() || values[i] != values[start] {
+            if i - start > best.1 {
+                best = (start, i - start);
+            }
+            start = i;
+        }
+    }
+    values.swap_remove(best.0)
+}
+
+/// Counts the features in which `point` differs from `center`.
+fn mismatches<T: PartialEq>(point: &ArrayView1<T>, center: &[T]) -> usize {
+    point.iter().zip(center).filter(|(a, b)| a != b).count()
+}
+
+fn mixed_dissimilarity<A: FloatCore>(
+    point: &ArrayView1<A>,
+    center: &[A],
+    is_categorical: &[bool],
+    gamma: A,
+) -> A {
+    point
+        .iter()
+        .zip(center)
+        .zip(is_categorical)
+        .fold(A::zero(), |acc, ((a, b), categorical)| {
+            if !categorical {
+                acc + (*a - *b) * (*a - *b)
+            } else if a == b {
+                acc
+            } else {
+                acc + gamma
+            }
+        })
+}
+
+/// Returns half of the mean standard deviation of the numeric features, or
+/// one if that is zero.
+fn default_gamma<A>(input: &ArrayView2<A>, is_categorical: &[bool]) -> A
+where
+    A: AddAssign + Float + FloatCore + FromPrimitive,
+{
+    let n = A::from_usize(input.nrows()).expect("valid float");
+    let mut total = A::zero();
+    let mut n_numeric = 0;
+    for (column, categorical) in input.columns().into_iter().zip(is_categorical) {
+        if *categorical {
+            continue;
+        }
+        let mean = sum(column.iter().copied()) / n;
+        let variance = sum(column.iter().map(|v| (*v - mean) * (*v - mean))) / n;
+        total += Float::sqrt(variance);
+        n_numeric += 1;
+    }
+    if n_numeric == 0 || total <= A::zero() {
+        return A::one();
+    }
+    total / A::from_usize(2 * n_numeric).expect("valid float")
+}
+
+fn group(labels: Vec<usize>) -> HashMap<usize, Vec<usize>> {
+    let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, label) in labels.into_iter().enumerate() {
+        clusters.entry(label).or_default().push(i);
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn kmodes() {
+        let data = array![
+            [0, 0, 0, 1],
+            [0, 0, 0, 0],
+            [0, 1, 0, 0],
+            [2, 2, 1, 3],
+            [2, 2, 1, 2],
+            [2, 3, 1, 2],
+            [0, 0, 0, 0],
+        ];
+        let mut model = KModes::new(2);
+        model.n_init = 3;
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(noise.is_empty());
+        let labels = model.predict(&array![[0, 1, 0, 1], [2, 2, 1, 0]]);
+        assert_eq!(clusters[&labels[0]], [0, 1, 2, 6]);
+        assert_eq!(clusters[&labels[1]], [3, 4, 5]);
+        assert_eq!(model.cost(), 1 + 1 + 1 + 1);
+
+        let mut centers: Vec<_> = model
+            .modes()
+            .rows()
+            .into_iter()
+            .map(|m| m.to_vec())
+            .collect();
+        centers.sort_unstable();
+        assert_eq!(centers, [vec![0, 0, 0, 0], vec![2, 2, 1, 2]]);
+    }
+
+    #[test]
+    fn mode() {
+        assert_eq!(super::mode([3, 1, 3, 1, 2].into_iter()), 1);
+        assert_eq!(super::mode([3, 1, 3, 2].into_iter()), 3);
+        assert_eq!(super::mode(["b"].into_iter()), "b");
+    }
+
+    #[test]
+    fn more_clusters_than_points() {
+        let data = array![[1, 1], [1, 1], [2, 2]];
+        let mut model = KModes::new(4);
+        let (clusters, _) = model.fit(&data, None);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 3);
+        assert_eq!(model.modes().nrows(), 3);
+        assert_eq!(model.cost(), 0);
+    }
+
+    #[test]
+    fn kprototypes() {
+        // the categorical feature separates points with the same numbers
+        let data = array![
+            [0., 0., 1.],
+            [0.1, 0., 1.],
+            [0., 0.1, 1.],
+            [0., 0., 2.],
+            [0.1, 0., 2.],
+            [0.1, 0.1, 2.],
+        ];
+        let mut model = KPrototypes::new(2, vec![2]);
+        model.gamma = Some(1.);
+        let (clusters, _) = model.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4, 5]]);
+        assert!(f64::abs(model.fitted_gamma() - 1.) < f64::EPSILON);
+        assert!(f64::abs(model.cost() - 24. / 900.) < 1e-12);
+
+        model.gamma = None;
+        model.fit(&data, None);
+        let expected = (0.05 + 2_f64.sqrt() / 30.) / 4.;
+        assert!(f64::abs(model.fitted_gamma() - expected) < 1e-12);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let mut kmodes = KModes::<u8>::default();
+        let (clusters, noise) = kmodes.fit(&Array2::zeros((0, 3)), None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+
+        let mut kprototypes = KPrototypes::<f64>::new(2, vec![0]);
+        let (clusters, noise) = kprototypes.fit(&Array2::zeros((0, 3)), None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
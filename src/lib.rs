@@ -1,12 +1,133 @@
+mod affinity;
+mod agglomerative;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "benchmark")]
+pub mod benchmark;
+mod birch;
+mod ckmeans;
+mod clique;
+mod config;
+mod constraints;
+pub mod datasets;
 mod dbscan;
+#[cfg(feature = "decomposition")]
+pub mod decomposition;
+mod dendrogram;
+mod denstream;
+mod diagnostics;
+mod dynamic;
+pub mod ensemble;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+mod fuzzy_cmeans;
+mod gmm;
+mod gower;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+mod graph;
+mod grid;
 mod hdbscan;
+mod interrupt;
+#[cfg(feature = "io")]
+pub mod io;
+mod kmeans;
+mod kmedoids;
+mod kmodes;
+mod linalg;
+#[cfg(feature = "linfa")]
+pub mod linfa;
+mod mahalanobis;
+mod matching;
+mod metrics;
+mod missing;
+mod model_selection;
 mod mst;
+mod multiview;
+mod neighbor_graph;
+mod noise;
 mod optics;
+mod outlier;
+mod parallel;
+mod partition;
+mod periodic;
+mod persistence;
+mod pipeline;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod preprocessing;
+#[cfg(feature = "python")]
+mod python;
+mod report;
+#[cfg(feature = "simd")]
+pub mod simd;
+mod som;
+mod spectral;
+mod st_dbscan;
+mod stability;
+mod subset;
+mod sum;
+pub mod text;
+mod trace;
+pub mod trajectory;
+pub mod tutorial;
 mod union_find;
+#[cfg(feature = "viz")]
+pub mod viz;
 
-pub use dbscan::Dbscan;
-pub use hdbscan::{ClusterExtraction, HDbscan};
-pub use optics::Optics;
+pub use affinity::AffinityPropagation;
+pub use agglomerative::{AgglomerativeClustering, Linkage};
+pub use birch::Birch;
+pub use ckmeans::Ckmeans1D;
+pub use clique::{Clique, SubspaceCluster};
+pub use config::{ClustererConfig, MetricConfig};
+pub use constraints::{ConstraintError, Constraints};
+pub use dbscan::{BorderPolicy, Dbscan, DbscanSweep};
+pub use dendrogram::Dendrogram;
+pub use denstream::DenStream;
+pub use diagnostics::{
+    check_dimensionality, check_input, Degeneracy, DegeneracyPolicy, DimensionalityError,
+    InputError, MIN_RELATIVE_CONTRAST,
+};
+pub use dynamic::DynClusterer;
+pub use fuzzy_cmeans::FuzzyCMeans;
+pub use gmm::{CovarianceType, GaussianMixture};
+pub use gower::{ColumnType, Gower};
+pub use graph::{CommunityAlgorithm, CommunityDetection};
+pub use grid::GridClustering;
+pub use hdbscan::{
+    mutual_reachability, mutual_reachability_knn, ApproximatePredictor, ClusterExtraction, HDbscan,
+    PredictionData,
+};
+pub use interrupt::{CancelToken, FitOutcome, Limit, LimitExceeded, Limits};
+pub use kmeans::{KMeans, MiniBatchKMeans};
+pub use kmedoids::KMedoids;
+pub use kmodes::{KModes, KPrototypes};
+pub use mahalanobis::Mahalanobis;
+pub use matching::{match_clusters, relabel_clusters};
+pub use metrics::{
+    calinski_harabasz_score, davies_bouldin_score, silhouette_score, InternalMetric,
+};
+pub use missing::NanEuclidean;
+pub use model_selection::{
+    refine_eps, select_k, suggest_eps, EpsSuggestion, GridCandidate, GridSearch, GridSearchResult,
+    KCriterion, KSelection,
+};
+pub use multiview::{MultiView, ViewCombination};
+pub use neighbor_graph::{knn_graph, KnnGraph, NeighborGraph};
+pub use optics::{Optics, OpticsModel};
+pub use outlier::Lof;
+pub use partition::{ClusterStats, ClusterSummary, Partition, PointAssignment};
+pub use periodic::Periodic;
+pub use persistence::{Format, Persist, PersistError, FORMAT_VERSION};
+pub use pipeline::{Passthrough, Pipeline};
+pub use report::{FitReport, SizeDistribution};
+pub use som::{Som, Topology};
+pub use spectral::{Affinity, SpectralClustering, SpectralEmbedding};
+pub use st_dbscan::StDbscan;
+pub use stability::{bootstrap_stability, ClusterStability};
+pub use subset::FitSubset;
+pub use union_find::connected_components;
 
 /// An interface to train a model.
 ///
@@ -34,3 +155,85 @@ where
 {
     fn predict(&mut self, input: &I) -> O;
 }
+
+/// An interface to map input to the space of a trained model's clusters, so
+/// that clustering can be used to engineer features for another model.
+///
+/// Row `i` of the output holds the distances from point `i` of the input to
+/// each cluster, one column per cluster.
+pub trait Transform<I, O>
+where
+    I: ?Sized,
+{
+    fn transform(&self, input: &I) -> O;
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod test {
+    use ndarray::Array2;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    /// Runs `f` in a thread pool of `n_threads` threads.
+    fn on_threads<T: Send>(n_threads: usize, f: impl FnOnce() -> T + Send) -> T {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(n_threads)
+            .build()
+            .expect("thread pool")
+            .install(f)
+    }
+
+    /// Three blobs of pseudo-random points.
+    #[allow(clippy::cast_precision_loss)]
+    fn blobs(n: usize) -> Array2<f64> {
+        let mut state: u64 = 7;
+        Array2::from_shape_fn((n, 2), |(i, _)| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            (i % 3) as f64 * 5. + (state >> 33) as f64 / (1_u64 << 31) as f64
+        })
+    }
+
+    #[test]
+    fn reproducible_across_thread_counts() {
+        let data = blobs(120);
+        let fit_all = || {
+            let mut hdbscan = HDbscan::new(5, Euclidean::default());
+            let mut mini_batch = MiniBatchKMeans::new(3, 32);
+            mini_batch.seed = 1;
+            (
+                Dbscan::new(0.3, 5, Euclidean::default()).fit(&data, None),
+                hdbscan.fit(&data, None),
+                Optics::new(0.5, 5, Euclidean::default()).fit(&data, None),
+                KMeans::new(3).fit(&data, None),
+                mini_batch.fit(&data, None),
+                KMedoids::new(3, Euclidean::default()).fit(&data, None),
+                GaussianMixture::new(3).fit(&data, None),
+                Lof::new(10, Euclidean::default()).fit(&data, None),
+                SpectralClustering::new(
+                    3,
+                    Affinity::NearestNeighbors { n_neighbors: 10 },
+                    Euclidean::default(),
+                )
+                .fit(&data, None),
+                AgglomerativeClustering::new(3, Linkage::Average, Euclidean::default())
+                    .fit(&data, None),
+            )
+        };
+        assert_eq!(on_threads(1, fit_all), on_threads(4, fit_all));
+
+        // large enough for Lanczos iterations
+        let data = blobs(1200);
+        let embed = || {
+            SpectralEmbedding::new(
+                2,
+                Affinity::NearestNeighbors { n_neighbors: 10 },
+                Euclidean::default(),
+            )
+            .fit(&data, None)
+        };
+        assert_eq!(on_threads(1, embed), on_threads(4, embed));
+    }
+}
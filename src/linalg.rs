@@ -0,0 +1,445 @@
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+use num_traits::{Float, FromPrimitive};
+
+/// Computes all eigenvalues and eigenvectors of a real symmetric matrix.
+///
+/// Eigenvalues are returned in ascending order, and the `i`-th column of the
+/// returned matrix is the eigenvector of the `i`-th eigenvalue.
+///
+/// The matrix is reduced to tridiagonal form with Householder reflections and
+/// then diagonalized with the implicit QL algorithm (EISPACK `tred2`/`tql2`).
+pub(crate) fn symmetric_eigen<A: Float + 'static>(matrix: Array2<A>) -> (Array1<A>, Array2<A>) {
+    let n = matrix.nrows();
+    assert_eq!(n, matrix.ncols(), "matrix should be square");
+    let mut v = matrix;
+    let mut d = vec![A::zero(); n];
+    let mut e = vec![A::zero(); n];
+    if n == 0 {
+        return (Array1::from_vec(d), v);
+    }
+    tridiagonalize(&mut v, &mut d, &mut e);
+    diagonalize(&mut v, &mut d, &mut e);
+    (Array1::from_vec(d), v)
+}
+
+/// Returns the `k` largest eigenvalues (in descending order) of a symmetric
+/// matrix and their eigenvectors as columns.
+pub(crate) fn largest_eigen<A: Float + 'static>(
+    matrix: Array2<A>,
+    k: usize,
+) -> (Array1<A>, Array2<A>) {
+    let (values, vectors) = symmetric_eigen(matrix);
+    let n = values.len();
+    let k = k.min(n);
+    let order: Vec<usize> = (n - k..n).rev().collect();
+    (
+        values.select(Axis(0), &order),
+        vectors.select(Axis(1), &order),
+    )
+}
+
+/// Approximates the `k` largest eigenvalues (in descending order) and
+/// eigenvectors of a symmetric `n x n` operator given only by its
+/// matrix-vector product, starting from a pseudo-random vector drawn with
+/// `seed`.
+///
+/// This runs the Lanczos iteration with full reorthogonalization, which keeps
+/// the memory footprint at `O(n * m)` for `m` Lanczos vectors, so sparse
+/// operators never need to be materialized.
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn lanczos_largest<A, F>(
+    n: usize,
+    k: usize,
+    seed: u64,
+    matvec: F,
+) -> (Array1<A>, Array2<A>)
+where
+    A: Float + FromPrimitive + 'static,
+    F: Fn(&Array1<A>) -> Array1<A>,
+{
+    let k = k.min(n);
+    let m = n.min((10 * k).max(100));
+    let mut seed = seed ^ 0x5eed;
+    let mut basis: Vec<Array1<A>> = Vec::with_capacity(m);
+    let mut alpha = Vec::with_capacity(m);
+    let mut beta = Vec::with_capacity(m);
+
+    let mut q = start_vector(n, &mut seed);
+    normalize(&mut q);
+    while basis.len() < m {
+        let mut w = matvec(&q);
+        alpha.push(w.dot(&q));
+        basis.push(q);
+        orthogonalize(&mut w, &basis);
+        if basis.len() == m {
+            break;
+        }
+        let norm = normalize(&mut w);
+        if norm > A::epsilon().sqrt() {
+            beta.push(norm);
+            q = w;
+        } else {
+            // An invariant subspace was found; restart from a new direction.
+            let mut restart = start_vector(n, &mut seed);
+            orthogonalize(&mut restart, &basis);
+            if normalize(&mut restart) <= A::epsilon().sqrt() {
+                break;
+            }
+            beta.push(A::zero());
+            q = restart;
+        }
+    }
+
+    let size = basis.len();
+    let mut tridiagonal = Array2::zeros((size, size));
+    for (i, a) in alpha.iter().enumerate() {
+        tridiagonal[[i, i]] = *a;
+    }
+    for (i, b) in beta.iter().enumerate().take(size.saturating_sub(1)) {
+        tridiagonal[[i, i + 1]] = *b;
+        tridiagonal[[i + 1, i]] = *b;
+    }
+    let (values, ritz) = largest_eigen(tridiagonal, k);
+
+    let mut vectors = Array2::zeros((n, values.len()));
+    for (j, column) in ritz.columns().into_iter().enumerate() {
+        let mut target = vectors.column_mut(j);
+        for (q, c) in basis.iter().zip(column) {
+            target.scaled_add(*c, q);
+        }
+    }
+    (values, vectors)
+}
+
+/// Computes the lower triangular Cholesky factor `L` of a symmetric
+/// positive-definite matrix, such that `L * L^T` is the matrix, or `None` if
+/// the matrix is not positive definite.
+pub(crate) fn cholesky<A: Float>(matrix: &Array2<A>) -> Option<Array2<A>> {
+    let n = matrix.nrows();
+    let mut l = Array2::zeros((n, n));
+    for j in 0..n {
+        let mut diagonal = matrix[[j, j]];
+        for k in 0..j {
+            diagonal = diagonal - l[[j, k]] * l[[j, k]];
+        }
+        if diagonal <= A::zero() || !diagonal.is_finite() {
+            return None;
+        }
+        let diagonal = diagonal.sqrt();
+        l[[j, j]] = diagonal;
+        for i in j + 1..n {
+            let mut v = matrix[[i, j]];
+            for k in 0..j {
+                v = v - l[[i, k]] * l[[j, k]];
+            }
+            l[[i, j]] = v / diagonal;
+        }
+    }
+    Some(l)
+}
+
+/// Solves `L * x = b` for `x` in place, where `L` is lower triangular.
+pub(crate) fn solve_lower_triangular<A: Float>(l: &ArrayView2<A>, b: &mut [A]) {
+    for i in 0..b.len() {
+        let mut v = b[i];
+        for k in 0..i {
+            v = v - l[[i, k]] * b[k];
+        }
+        b[i] = v / l[[i, i]];
+    }
+}
+
+/// A deterministic pseudo-random starting vector for iterative solvers.
+fn start_vector<A: Float + FromPrimitive>(n: usize, seed: &mut u64) -> Array1<A> {
+    Array1::from_shape_fn(n, |_| {
+        *seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        let v = A::from_u64(*seed >> 33).expect("valid float")
+            / A::from_u64(1 << 31).expect("valid float");
+        v - A::from_f64(0.5).expect("valid float")
+    })
+}
+
+/// Removes the components of `v` along the (orthonormal) `basis`.
+fn orthogonalize<A: Float + 'static>(v: &mut Array1<A>, basis: &[Array1<A>]) {
+    // Two passes of Gram-Schmidt keep the basis orthogonal to working precision.
+    for _ in 0..2 {
+        for b in basis {
+            let proj = v.dot(b);
+            v.scaled_add(-proj, b);
+        }
+    }
+}
+
+/// Scales `v` to unit length, returning its original norm.
+fn normalize<A: Float + 'static>(v: &mut Array1<A>) -> A {
+    let norm = v.dot(v).sqrt();
+    if norm > A::zero() {
+        v.mapv_inplace(|x| x / norm);
+    }
+    norm
+}
+
+/// Householder reduction to tridiagonal form (`tred2`).
+///
+/// On return `d` holds the diagonal, `e` the sub-diagonal, and `v` the
+/// accumulated orthogonal transformation.
+#[allow(clippy::many_single_char_names)]
+fn tridiagonalize<A: Float>(v: &mut Array2<A>, d: &mut [A], e: &mut [A]) {
+    let n = d.len();
+    for j in 0..n {
+        d[j] = v[[n - 1, j]];
+    }
+
+    for i in (1..n).rev() {
+        let scale = d[..i].iter().fold(A::zero(), |acc, x| acc + x.abs());
+        let mut h = A::zero();
+        if scale == A::zero() {
+            e[i] = d[i - 1];
+            for j in 0..i {
+                d[j] = v[[i - 1, j]];
+                v[[i, j]] = A::zero();
+                v[[j, i]] = A::zero();
+            }
+        } else {
+            for x in &mut d[..i] {
+                *x = *x / scale;
+                h = h + *x * *x;
+            }
+            let mut f = d[i - 1];
+            let mut g = h.sqrt();
+            if f > A::zero() {
+                g = -g;
+            }
+            e[i] = scale * g;
+            h = h - f * g;
+            d[i - 1] = f - g;
+            for x in &mut e[..i] {
+                *x = A::zero();
+            }
+
+            for j in 0..i {
+                f = d[j];
+                v[[j, i]] = f;
+                g = e[j] + v[[j, j]] * f;
+                for k in j + 1..i {
+                    g = g + v[[k, j]] * d[k];
+                    e[k] = e[k] + v[[k, j]] * f;
+                }
+                e[j] = g;
+            }
+            f = A::zero();
+            for j in 0..i {
+                e[j] = e[j] / h;
+                f = f + e[j] * d[j];
+            }
+            let hh = f / (h + h);
+            for j in 0..i {
+                e[j] = e[j] - hh * d[j];
+            }
+            for j in 0..i {
+                f = d[j];
+                g = e[j];
+                for k in j..i {
+                    v[[k, j]] = v[[k, j]] - (f * e[k] + g * d[k]);
+                }
+                d[j] = v[[i - 1, j]];
+                v[[i, j]] = A::zero();
+            }
+        }
+        d[i] = h;
+    }
+
+    // Accumulate transformations.
+    for i in 0..n - 1 {
+        v[[n - 1, i]] = v[[i, i]];
+        v[[i, i]] = A::one();
+        let h = d[i + 1];
+        if h != A::zero() {
+            for k in 0..=i {
+                d[k] = v[[k, i + 1]] / h;
+            }
+            for j in 0..=i {
+                let mut g = A::zero();
+                for k in 0..=i {
+                    g = g + v[[k, i + 1]] * v[[k, j]];
+                }
+                for k in 0..=i {
+                    v[[k, j]] = v[[k, j]] - g * d[k];
+                }
+            }
+        }
+        for k in 0..=i {
+            v[[k, i + 1]] = A::zero();
+        }
+    }
+    for j in 0..n {
+        d[j] = v[[n - 1, j]];
+        v[[n - 1, j]] = A::zero();
+    }
+    v[[n - 1, n - 1]] = A::one();
+    e[0] = A::zero();
+}
+
+/// Symmetric tridiagonal QL algorithm with implicit shifts (`tql2`), followed
+/// by sorting the eigenvalues in ascending order.
+#[allow(clippy::many_single_char_names)]
+fn diagonalize<A: Float>(v: &mut Array2<A>, d: &mut [A], e: &mut [A]) {
+    let n = d.len();
+    for i in 1..n {
+        e[i - 1] = e[i];
+    }
+    e[n - 1] = A::zero();
+
+    let mut f = A::zero();
+    let mut tst1 = A::zero();
+    let eps = A::epsilon();
+    for l in 0..n {
+        tst1 = tst1.max(d[l].abs() + e[l].abs());
+        let mut m = l;
+        while m < n - 1 && e[m].abs() > eps * tst1 {
+            m += 1;
+        }
+
+        if m > l {
+            loop {
+                let two = A::one() + A::one();
+                let mut g = d[l];
+                let mut p = (d[l + 1] - g) / (two * e[l]);
+                let mut r = p.hypot(A::one());
+                if p < A::zero() {
+                    r = -r;
+                }
+                d[l] = e[l] / (p + r);
+                d[l + 1] = e[l] * (p + r);
+                let dl1 = d[l + 1];
+                let mut h = g - d[l];
+                for x in &mut d[l + 2..n] {
+                    *x = *x - h;
+                }
+                f = f + h;
+
+                p = d[m];
+                let mut c = A::one();
+                let mut c2 = c;
+                let mut c3 = c;
+                let el1 = e[l + 1];
+                let mut s = A::zero();
+                let mut s2 = A::zero();
+                for i in (l..m).rev() {
+                    c3 = c2;
+                    c2 = c;
+                    s2 = s;
+                    g = c * e[i];
+                    h = c * p;
+                    r = p.hypot(e[i]);
+                    e[i + 1] = s * r;
+                    s = e[i] / r;
+                    c = p / r;
+                    p = c * d[i] - s * g;
+                    d[i + 1] = h + s * (c * g + s * d[i]);
+                    for k in 0..n {
+                        h = v[[k, i + 1]];
+                        v[[k, i + 1]] = s * v[[k, i]] + c * h;
+                        v[[k, i]] = c * v[[k, i]] - s * h;
+                    }
+                }
+                p = -s * s2 * c3 * el1 * e[l] / dl1;
+                e[l] = s * p;
+                d[l] = c * p;
+                if e[l].abs() <= eps * tst1 {
+                    break;
+                }
+            }
+        }
+        d[l] = d[l] + f;
+        e[l] = A::zero();
+    }
+
+    for i in 0..n.saturating_sub(1) {
+        let mut k = i;
+        let mut p = d[i];
+        for (j, x) in d.iter().enumerate().skip(i + 1) {
+            if *x < p {
+                k = j;
+                p = *x;
+            }
+        }
+        if k != i {
+            d[k] = d[i];
+            d[i] = p;
+            for row in 0..n {
+                v.swap([row, i], [row, k]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{arr2, Array1, Array2};
+
+    #[test]
+    fn cholesky() {
+        let matrix: Array2<f64> = arr2(&[[4., 2., -2.], [2., 10., 2.], [-2., 2., 5.]]);
+        let l = super::cholesky(&matrix).expect("positive definite");
+        assert!((l.dot(&l.t()) - &matrix).iter().all(|v| v.abs() < 1e-12));
+        assert!(l[[0, 1]].abs() < f64::EPSILON);
+
+        let mut b: [f64; 3] = [2., 7., 0.];
+        super::solve_lower_triangular(&l.view(), &mut b);
+        let x = Array1::from(b.to_vec());
+        assert!((l.dot(&x) - Array1::from(vec![2., 7., 0.]))
+            .iter()
+            .all(|v| v.abs() < 1e-12));
+
+        let singular = arr2(&[[1., 1.], [1., 1.]]);
+        assert!(super::cholesky(&singular).is_none());
+    }
+
+    #[test]
+    fn symmetric_eigen() {
+        let matrix = arr2(&[[2., 1., 0.], [1., 2., 1.], [0., 1., 2.]]);
+        let (values, vectors) = super::symmetric_eigen(matrix.clone());
+
+        let sqrt2 = 2_f64.sqrt();
+        let expected = [2. - sqrt2, 2., 2. + sqrt2];
+        for (v, e) in values.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-12, "{v} != {e}");
+        }
+        // A v = lambda v for every eigenpair
+        for (i, lambda) in values.iter().enumerate() {
+            let v = vectors.column(i);
+            let av = matrix.dot(&v);
+            for (a, b) in av.iter().zip(v.iter()) {
+                assert!((a - lambda * b).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn lanczos_largest() {
+        // path graph Laplacian-like matrix with distinct eigenvalues
+        let n = 50;
+        let matrix = Array2::from_shape_fn((n, n), |(i, j)| {
+            if i == j {
+                #[allow(clippy::cast_precision_loss)]
+                let d = 2. + i as f64 / 10.;
+                d
+            } else if i.abs_diff(j) == 1 {
+                -1.
+            } else {
+                0.
+            }
+        });
+        let (dense, _) = super::largest_eigen(matrix.clone(), 3);
+        let (values, vectors) = super::lanczos_largest(n, 3, 0, |x: &Array1<f64>| matrix.dot(x));
+        for (a, b) in values.iter().zip(dense.iter()) {
+            assert!((a - b).abs() < 1e-8, "{a} != {b}");
+        }
+        for (i, lambda) in values.iter().enumerate() {
+            let v = vectors.column(i);
+            let residual = &matrix.dot(&v) - &(&v * *lambda);
+            assert!(residual.dot(&residual).sqrt() < 1e-6);
+        }
+    }
+}
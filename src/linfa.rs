@@ -0,0 +1,352 @@
+//! [linfa] adapters for DBSCAN, OPTICS and HDBSCAN, enabled with the `linfa`
+//! feature.
+//!
+//! [`Dbscan`], [`Optics`] and [`HDbscan`] implement linfa's `Fit` over the
+//! records of a `DatasetBase`, ignoring its targets, so that they can be
+//! used wherever linfa's estimators are. The fitted models implement
+//! `PredictInplace`, and therefore `Predict`, labeling new points with the
+//! cluster they would join, or `None` for noise.
+//!
+//! linfa arrays come from the version of `ndarray` linfa is built on, so
+//! the records are copied into an array of this crate's version before each
+//! fit or prediction.
+//!
+//! # Examples
+//!
+//! ```
+//! use linfa::traits::{Fit, Predict};
+//! use linfa::DatasetBase;
+//! use ndarray_linfa::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::Dbscan;
+//!
+//! let records = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+//! let dataset = DatasetBase::new(records, ());
+//! let model = Dbscan::new(3., 2, Euclidean::default()).fit(&dataset).unwrap();
+//! assert_eq!(model.labels()[5], None);
+//!
+//! let labels = model.predict(&array![[1.5, 2.], [8., 7.5], [50., 50.]]);
+//! assert_eq!(labels[0], model.labels()[0]);
+//! assert_eq!(labels[1], model.labels()[3]);
+//! assert_eq!(labels[2], None);
+//! ```
+//!
+//! [linfa]: https://docs.rs/linfa
+
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use linfa::traits::{Fit, PredictInplace};
+use linfa::{DatasetBase, Error};
+use ndarray::{Array2, ArrayView2, Axis};
+use ndarray_linfa::{Array1, ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use petal_neighbors::BallTree;
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::FiniteBallTree;
+use crate::parallel::prelude::*;
+use crate::{Dbscan, HDbscan, Optics, PredictionData};
+
+/// The clusters of a DBSCAN or OPTICS fit with linfa's `Fit`, which labels
+/// new points with linfa's `Predict`.
+///
+/// A new point joins the cluster of the closest core point of the fit if it
+/// is within the radius of the fit, as a border point would, and is noise
+/// otherwise.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClusterModel<A, M> {
+    eps: A,
+    metric: M,
+    n_features: usize,
+    labels: Vec<Option<usize>>,
+    core_points: Vec<A>,
+    core_labels: Vec<usize>,
+}
+
+impl<A, M> ClusterModel<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    /// Keeps the clustered points of `input` with at least `min_samples`
+    /// points within `eps`.
+    fn new(
+        input: &ArrayView2<A>,
+        clusters: &HashMap<usize, Vec<usize>>,
+        eps: A,
+        min_samples: usize,
+        metric: M,
+    ) -> Self {
+        let mut labels = vec![None; input.nrows()];
+        for (id, members) in clusters {
+            for &i in members {
+                labels[i] = Some(*id);
+            }
+        }
+        let db = FiniteBallTree::new(input, metric.clone());
+        let mut cores: Vec<_> = labels
+            .par_iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let id = (*id)?;
+                (db.query_radius(&input.row(i), eps).len() >= min_samples).then_some((i, id))
+            })
+            .collect();
+        cores.sort_unstable();
+        let rows: Vec<_> = cores.iter().map(|(i, _)| *i).collect();
+        Self {
+            eps,
+            metric,
+            n_features: input.ncols(),
+            labels,
+            core_points: input.select(Axis(0), &rows).iter().copied().collect(),
+            core_labels: cores.into_iter().map(|(_, id)| id).collect(),
+        }
+    }
+
+    /// Returns the cluster of each point of the fit, or `None` for noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linfa::traits::Fit;
+    /// use linfa::DatasetBase;
+    /// use ndarray_linfa::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Dbscan;
+    ///
+    /// let records = array![[1., 2.], [2., 2.], [2., 2.3], [25., 80.]];
+    /// let model = Dbscan::new(3., 2, Euclidean::default())
+    ///     .fit(&DatasetBase::new(records, ()))
+    ///     .unwrap();
+    /// assert_eq!(model.labels(), [Some(0), Some(0), Some(0), None]);
+    /// ```
+    #[must_use]
+    pub fn labels(&self) -> &[Option<usize>] {
+        &self.labels
+    }
+}
+
+impl<A, M, D> PredictInplace<ArrayBase<D, Ix2>, Array1<Option<usize>>> for ClusterModel<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+    D: Data<Elem = A>,
+{
+    /// Labels each point of `x`. Points with a NaN or infinite coordinate
+    /// are noise.
+    ///
+    /// # Panics
+    /// Panics if `x` does not have as many features as the points of the
+    /// fit, or if `y` does not have one label per point.
+    fn predict_inplace(&self, x: &ArrayBase<D, Ix2>, y: &mut Array1<Option<usize>>) {
+        assert_eq!(
+            x.ncols(),
+            self.n_features,
+            "points should have as many features as the training points"
+        );
+        assert_eq!(y.len(), x.nrows(), "one label per point");
+        let cores =
+            ArrayView2::from_shape((self.core_labels.len(), self.n_features), &self.core_points)
+                .expect("one row of `n_features` per core point");
+        let Ok(tree) = BallTree::new(cores, self.metric.clone()) else {
+            y.fill(None);
+            return;
+        };
+        let input = to_array(x);
+        let labels: Vec<_> = input
+            .rows()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|point| {
+                if !point.iter().all(|v| v.is_finite()) {
+                    return None;
+                }
+                let (j, distance) = tree.query_nearest(&point);
+                (distance <= self.eps).then(|| self.core_labels[j])
+            })
+            .collect();
+        for (label, predicted) in y.iter_mut().zip(labels) {
+            *label = predicted;
+        }
+    }
+
+    fn default_target(&self, x: &ArrayBase<D, Ix2>) -> Array1<Option<usize>> {
+        Array1::from_elem(x.nrows(), None)
+    }
+}
+
+impl<A, M, D, T> Fit<ArrayBase<D, Ix2>, T, Error> for Dbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+    D: Data<Elem = A>,
+{
+    type Object = ClusterModel<A, M>;
+
+    /// Clusters the records of `dataset`.
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<D, Ix2>, T>) -> Result<Self::Object, Error> {
+        let input = to_array(dataset.records());
+        let mut dbscan = Dbscan::new(self.eps, self.min_samples, self.metric.clone());
+        dbscan.border_policy = self.border_policy;
+        dbscan.deduplicate = self.deduplicate;
+        let (clusters, _) = crate::Fit::fit(&mut dbscan, &input, None);
+        Ok(ClusterModel::new(
+            &input.view(),
+            &clusters,
+            self.eps,
+            self.min_samples,
+            self.metric.clone(),
+        ))
+    }
+}
+
+impl<A, M, D, T> Fit<ArrayBase<D, Ix2>, T, Error> for Optics<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+    D: Data<Elem = A>,
+{
+    type Object = ClusterModel<A, M>;
+
+    /// Orders the records of `dataset`, and extracts the clusters at `eps`,
+    /// as DBSCAN would find them.
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<D, Ix2>, T>) -> Result<Self::Object, Error> {
+        let input = to_array(dataset.records());
+        let mut optics = Optics::new(self.eps, self.min_samples, self.metric.clone());
+        optics.low_memory = self.low_memory;
+        crate::Fit::fit(&mut optics, &input, None);
+        let (clusters, _) = optics.extract_clusters_and_noise(self.eps);
+        Ok(ClusterModel::new(
+            &input.view(),
+            &clusters,
+            self.eps,
+            self.min_samples,
+            self.metric.clone(),
+        ))
+    }
+}
+
+impl<A, M, D, T> Fit<ArrayBase<D, Ix2>, T, Error> for HDbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync + Send,
+    D: Data<Elem = A>,
+{
+    type Object = PredictionData<A, M>;
+
+    /// Clusters the records of `dataset`, and returns the data to label new
+    /// points as [`ApproximatePredictor`](crate::ApproximatePredictor) does.
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<D, Ix2>, T>) -> Result<Self::Object, Error> {
+        let input = to_array(dataset.records());
+        let mut hdbscan = HDbscan::new(self.min_cluster_size, self.metric.clone());
+        hdbscan.alpha = self.alpha;
+        hdbscan.min_samples = self.min_samples;
+        hdbscan.boruvka = self.boruvka;
+        hdbscan.extraction = self.extraction;
+        hdbscan.cluster_selection_epsilon = self.cluster_selection_epsilon;
+        let (clusters, _, _) = crate::Fit::fit(&mut hdbscan, &input, None);
+        Ok(hdbscan.generate_prediction_data(&input, &clusters))
+    }
+}
+
+impl<A, M, D> PredictInplace<ArrayBase<D, Ix2>, Array1<Option<usize>>> for PredictionData<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+    D: Data<Elem = A>,
+{
+    /// Labels each point of `x` with
+    /// [`ApproximatePredictor::approximate_predict`](crate::ApproximatePredictor::approximate_predict).
+    ///
+    /// # Panics
+    /// Panics if `x` does not have as many features as the points of the
+    /// fit, or if `y` does not have one label per point.
+    fn predict_inplace(&self, x: &ArrayBase<D, Ix2>, y: &mut Array1<Option<usize>>) {
+        assert_eq!(y.len(), x.nrows(), "one label per point");
+        let (labels, _) = self.predictor().approximate_predict(&to_array(x));
+        for (label, predicted) in y.iter_mut().zip(labels) {
+            *label = predicted;
+        }
+    }
+
+    fn default_target(&self, x: &ArrayBase<D, Ix2>) -> Array1<Option<usize>> {
+        Array1::from_elem(x.nrows(), None)
+    }
+}
+
+/// Copies an array of linfa's `ndarray` into one of this crate's.
+fn to_array<A, D>(records: &ArrayBase<D, Ix2>) -> Array2<A>
+where
+    A: Copy,
+    D: Data<Elem = A>,
+{
+    Array2::from_shape_vec(records.dim(), records.iter().copied().collect())
+        .expect("as many values as the shape of the records")
+}
+
+#[cfg(test)]
+mod test {
+    use linfa::traits::{Fit, Predict};
+    use linfa::DatasetBase;
+    use ndarray_linfa::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use crate::{Dbscan, HDbscan, Optics};
+
+    #[test]
+    fn dbscan_and_optics() {
+        let records = array![
+            [1., 2.],
+            [2., 2.],
+            [2., 2.3],
+            [8., 7.],
+            [8., 8.],
+            [25., 80.]
+        ];
+        let dataset = DatasetBase::new(records, ());
+        let new = array![[1.5, 2.], [8., 7.5], [50., 50.], [f64::NAN, 2.]];
+
+        let dbscan = Dbscan::new(3., 2, Euclidean::default());
+        let optics = Optics::new(3., 2, Euclidean::default());
+        for model in [dbscan.fit(&dataset).unwrap(), optics.fit(&dataset).unwrap()] {
+            let labels = model.labels();
+            assert!(labels[0].is_some());
+            assert_eq!(labels[0], labels[1]);
+            assert_eq!(labels[0], labels[2]);
+            assert!(labels[3].is_some());
+            assert_ne!(labels[0], labels[3]);
+            assert_eq!(labels[3], labels[4]);
+            assert_eq!(labels[5], None);
+
+            let predicted = model.predict(&new);
+            assert_eq!(predicted.to_vec(), [labels[0], labels[3], None, None]);
+        }
+    }
+
+    #[test]
+    fn hdbscan() {
+        let records = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [-2.1, 2.9],
+            [-2.0, 3.2],
+        ];
+        let dataset = DatasetBase::new(records.clone(), ());
+        let data = HDbscan::new(3, Euclidean::default()).fit(&dataset).unwrap();
+
+        let labels = data.predict(&records);
+        assert!(labels[0].is_some());
+        assert!(labels[4].is_some());
+        assert_ne!(labels[0], labels[4]);
+        let predicted = data.predict(&array![[1.0, 2.05], [9.0, 9.0]]);
+        assert_eq!(predicted.to_vec(), [labels[0], None]);
+    }
+}
@@ -0,0 +1,289 @@
+use ndarray::{Array2, ArrayBase, ArrayView1, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::linalg::symmetric_eigen;
+
+/// The Mahalanobis distance, or more generally a distance weighted by a
+/// positive semi-definite matrix `W`: `sqrt((x - y)^T W (x - y))`.
+///
+/// With `W` the inverse of the covariance of the features, the distance
+/// accounts for their scales and correlations, so that a cluster stretched
+/// along correlated features is as compact as a round one. `W` can also be a
+/// learned metric, such as one fitted to pairs of points known to be
+/// similar. If the covariance is singular, its pseudo-inverse is used, which
+/// ignores the directions without variance.
+///
+/// `W` is factored as `T^T T` when the metric is created, and the distance
+/// is the Euclidean norm of `T (x - y)`, so it satisfies the triangle
+/// inequality and `Mahalanobis` can replace `Euclidean` in any clustering
+/// algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Dbscan, Fit, Mahalanobis};
+/// use petal_neighbors::distance::Metric;
+///
+/// // the first feature varies four times as much as the second
+/// let mahalanobis = Mahalanobis::from_covariance(&array![[16_f64, 0.], [0., 1.]]);
+/// let d = mahalanobis.distance(&array![0., 0.].view(), &array![4., 0.].view());
+/// assert!((d - 1.).abs() < 1e-12);
+///
+/// // two elongated clusters along the diagonal
+/// let points = array![
+///     [0., 0.], [1., 1.1], [2., 1.9], [3., 3.],
+///     [0., 2.], [1., 3.1], [2., 3.9], [3., 5.],
+/// ];
+/// let metric = Mahalanobis::from_data(&points);
+/// let (clusters, noise) = Dbscan::new(1.5, 2, metric).fit(&points, None);
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Mahalanobis<A> {
+    /// The rows of `T`, one after another.
+    transform: Vec<A>,
+    n_features: usize,
+}
+
+impl<A> Mahalanobis<A>
+where
+    A: Float + FromPrimitive + 'static,
+{
+    /// Creates the Mahalanobis distance for features with the given
+    /// covariance matrix.
+    ///
+    /// # Panics
+    /// Panics if `covariance` is not a square matrix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Mahalanobis;
+    /// use petal_neighbors::distance::Metric;
+    ///
+    /// let mahalanobis = Mahalanobis::from_covariance(&array![[4_f64, 0.], [0., 1.]]);
+    /// let d = mahalanobis.distance(&array![0., 0.].view(), &array![2., 1.].view());
+    /// assert!((d - 2_f64.sqrt()).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn from_covariance(covariance: &Array2<A>) -> Self {
+        Self::factor(covariance, |value| A::one() / value.sqrt())
+    }
+
+    /// Creates the distance weighted by the given positive semi-definite
+    /// matrix. Negative eigenvalues of `weights`, from rounding errors, are
+    /// taken as zero.
+    ///
+    /// # Panics
+    /// Panics if `weights` is not a square matrix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Mahalanobis;
+    /// use petal_neighbors::distance::Metric;
+    ///
+    /// // the first feature counts four times as much as the second
+    /// let weighted = Mahalanobis::from_weights(&array![[4_f64, 0.], [0., 1.]]);
+    /// let d = weighted.distance(&array![0., 0.].view(), &array![1., 1.].view());
+    /// assert!((d - 5_f64.sqrt()).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn from_weights(weights: &Array2<A>) -> Self {
+        Self::factor(weights, Float::sqrt)
+    }
+
+    /// Creates the Mahalanobis distance for features with the covariance of
+    /// `input`, as estimated by [`Mahalanobis::covariance`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Mahalanobis;
+    /// use petal_neighbors::distance::Metric;
+    ///
+    /// // the second feature varies a hundred times as much as the first
+    /// let points = array![[0_f64, 0.], [1., 0.], [0., 10.], [1., 10.]];
+    /// let mahalanobis = Mahalanobis::from_data(&points);
+    /// let across = mahalanobis.distance(&points.row(0), &points.row(1));
+    /// let along = mahalanobis.distance(&points.row(0), &points.row(2));
+    /// assert!((across - along).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn from_data<S>(input: &ArrayBase<S, Ix2>) -> Self
+    where
+        S: Data<Elem = A>,
+    {
+        Self::from_covariance(&Self::covariance(input))
+    }
+
+    /// Estimates the covariance matrix of the features of `input`, one
+    /// point per row, from the points whose coordinates are all finite.
+    ///
+    /// The estimate is unbiased, dividing by one less than the number of
+    /// points, and is zero if there are fewer than two points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Mahalanobis;
+    ///
+    /// let points = array![[0., 0.], [2., 0.], [0., 2.], [2., 2.], [f64::NAN, 1.]];
+    /// let covariance = Mahalanobis::covariance(&points);
+    /// assert!((covariance[[0, 0]] - 4. / 3.).abs() < 1e-12);
+    /// assert_eq!(covariance[[0, 1]], 0.);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the number of points fits in a float
+    pub fn covariance<S>(input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        let dim = input.ncols();
+        let rows: Vec<_> = input
+            .rows()
+            .into_iter()
+            .filter(|p| p.iter().all(|v| v.is_finite()))
+            .collect();
+        let mut covariance = Array2::zeros((dim, dim));
+        if rows.len() < 2 {
+            return covariance;
+        }
+        let n = A::from_usize(rows.len()).expect("valid float");
+        let mut mean = vec![A::zero(); dim];
+        for p in &rows {
+            for (m, v) in mean.iter_mut().zip(p) {
+                *m = *m + *v;
+            }
+        }
+        for m in &mut mean {
+            *m = *m / n;
+        }
+        for p in &rows {
+            for i in 0..dim {
+                let di = p[i] - mean[i];
+                for j in i..dim {
+                    covariance[[i, j]] = covariance[[i, j]] + di * (p[j] - mean[j]);
+                }
+            }
+        }
+        let n = n - A::one();
+        for i in 0..dim {
+            for j in i..dim {
+                covariance[[i, j]] = covariance[[i, j]] / n;
+                covariance[[j, i]] = covariance[[i, j]];
+            }
+        }
+        covariance
+    }
+
+    /// Factors `V diag(values) V^T` as `T^T T` with the rows of `T` being the
+    /// eigenvectors scaled by `scale(value)`, leaving out the eigenvalues that
+    /// are zero up to rounding errors.
+    fn factor(matrix: &Array2<A>, scale: impl Fn(A) -> A) -> Self {
+        let n_features = matrix.nrows();
+        let (values, vectors) = symmetric_eigen(matrix.to_owned());
+        let largest = values.iter().fold(A::zero(), |acc, v| acc.max(v.abs()));
+        let tol = largest * A::epsilon() * A::from_usize(n_features.max(1)).unwrap_or_else(A::one);
+        let mut transform = vec![];
+        for (value, vector) in values.iter().zip(vectors.columns()) {
+            if *value > tol {
+                let scale = scale(*value);
+                transform.extend(vector.iter().map(|v| *v * scale));
+            }
+        }
+        Self {
+            transform,
+            n_features,
+        }
+    }
+}
+
+/// # Panics
+/// The distances panic if the points do not have as many features as the
+/// matrix has rows.
+impl<A: Float> Metric<A> for Mahalanobis<A> {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.rdistance(x1, x2).sqrt()
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        assert!(
+            x1.len() == self.n_features && x2.len() == self.n_features,
+            "points should have as many features as the matrix"
+        );
+        let diff: Vec<A> = x1.iter().zip(x2).map(|(a, b)| *a - *b).collect();
+        self.transform
+            .chunks(self.n_features.max(1))
+            .map(|row| {
+                row.iter()
+                    .zip(&diff)
+                    .fold(A::zero(), |acc, (t, d)| acc + *t * *d)
+            })
+            .fold(A::zero(), |acc, v| acc + v * v)
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d.sqrt()
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d * d
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, aview1};
+
+    use super::*;
+
+    #[test]
+    fn distance() {
+        let d =
+            |m: &Mahalanobis<f64>, a: [f64; 2], b: [f64; 2]| m.distance(&aview1(&a), &aview1(&b));
+
+        let identity = Mahalanobis::from_weights(&array![[1., 0.], [0., 1.]]);
+        assert!((d(&identity, [0., 0.], [3., 4.]) - 5.).abs() < 1e-12);
+
+        // correlated features: moving along the correlation is cheaper
+        let covariance = array![[1., 0.9], [0.9, 1.]];
+        let mahalanobis = Mahalanobis::from_covariance(&covariance);
+        let along = d(&mahalanobis, [0., 0.], [1., 1.]);
+        let across = d(&mahalanobis, [0., 0.], [1., -1.]);
+        assert!((along - (2. / 1.9_f64).sqrt()).abs() < 1e-12);
+        assert!((across - (2. / 0.1_f64).sqrt()).abs() < 1e-12);
+
+        // the weights are the inverse of the covariance
+        let weights = array![[1., -0.9], [-0.9, 1.]] / (1. - 0.81);
+        let weighted = Mahalanobis::from_weights(&weights);
+        assert!((d(&weighted, [0., 0.], [1., -1.]) - across).abs() < 1e-9);
+
+        // a singular covariance ignores the direction without variance
+        let singular = Mahalanobis::from_covariance(&array![[1., 1.], [1., 1.]]);
+        assert!(d(&singular, [0., 0.], [1., -1.]).abs() < 1e-12);
+        assert!((d(&singular, [0., 0.], [1., 1.]) - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn covariance() {
+        let data = array![[1., 2.], [2., 4.], [3., 6.], [f64::NAN, 0.]];
+        let covariance = Mahalanobis::covariance(&data);
+        let expected = array![[1., 2.], [2., 4.]];
+        assert!(covariance
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| (a - b).abs() < 1e-12));
+
+        let single = Mahalanobis::covariance(&array![[1., 2.]]);
+        assert!(single.iter().all(|v| *v == 0.));
+    }
+}
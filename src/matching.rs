@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+/// Matches the clusters of `current` to those of `previous`, two clusterings
+/// of the same points, maximizing the total number of points the matched
+/// clusters share.
+///
+/// Cluster IDs are arbitrary and change from one fit to the next, even when
+/// the clusters barely do. The returned map gives a stable ID for each
+/// cluster of `current`: the ID of the cluster of `previous` it is matched
+/// to, or, for a cluster sharing no point with any unmatched cluster of
+/// `previous`, a new ID greater than all those of `previous`. Clusters are
+/// matched one to one with the Hungarian algorithm, so comparing the
+/// clusterings after relabeling shows the drift between periodic refits.
+///
+/// # Examples
+///
+/// ```
+/// use maplit::hashmap;
+/// use petal_clustering::match_clusters;
+///
+/// let previous = hashmap! {0 => vec![0, 1, 2], 1 => vec![3, 4]};
+/// let current = hashmap! {0 => vec![3, 4, 5], 1 => vec![0, 1], 2 => vec![2, 6]};
+///
+/// let ids = match_clusters(&previous, &current);
+/// assert_eq!(ids, hashmap! {0 => 1, 1 => 0, 2 => 2});
+/// ```
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn match_clusters(
+    previous: &HashMap<usize, Vec<usize>>,
+    current: &HashMap<usize, Vec<usize>>,
+) -> HashMap<usize, usize> {
+    let mut previous_ids: Vec<_> = previous.keys().copied().collect();
+    previous_ids.sort_unstable();
+    let mut current_ids: Vec<_> = current.keys().copied().collect();
+    current_ids.sort_unstable();
+
+    let mut label = HashMap::new();
+    for (p, id) in previous_ids.iter().enumerate() {
+        for &i in &previous[id] {
+            label.insert(i, p);
+        }
+    }
+    let mut overlap = vec![vec![0_usize; previous_ids.len()]; current_ids.len()];
+    for (c, id) in current_ids.iter().enumerate() {
+        for i in &current[id] {
+            if let Some(&p) = label.get(i) {
+                overlap[c][p] += 1;
+            }
+        }
+    }
+
+    let assignment = max_weight_matching(&overlap);
+    let mut next_id = previous_ids.last().map_or(0, |id| id + 1);
+    current_ids
+        .iter()
+        .zip(assignment)
+        .enumerate()
+        .map(|(c, (id, matched))| match matched {
+            Some(p) if overlap[c][p] > 0 => (*id, previous_ids[p]),
+            _ => {
+                next_id += 1;
+                (*id, next_id - 1)
+            }
+        })
+        .collect()
+}
+
+/// Relabels the clusters of `current` with the IDs [`match_clusters`] gives
+/// them, so that they keep the IDs of the clusters of `previous` they
+/// overlap the most.
+///
+/// # Examples
+///
+/// ```
+/// use maplit::hashmap;
+/// use petal_clustering::relabel_clusters;
+///
+/// let previous = hashmap! {0 => vec![0, 1, 2], 1 => vec![3, 4]};
+/// let current = hashmap! {0 => vec![3, 4], 1 => vec![0, 1, 2]};
+///
+/// assert_eq!(relabel_clusters(&previous, current), previous);
+/// ```
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn relabel_clusters(
+    previous: &HashMap<usize, Vec<usize>>,
+    current: HashMap<usize, Vec<usize>>,
+) -> HashMap<usize, Vec<usize>> {
+    let ids = match_clusters(previous, &current);
+    current
+        .into_iter()
+        .map(|(id, members)| (ids[&id], members))
+        .collect()
+}
+
+/// Finds the column matched to each row of `weights` maximizing the total
+/// weight, with the Hungarian algorithm in O(n²m) for n ≤ m. Rows are left
+/// unmatched only if there are more rows than columns.
+fn max_weight_matching(weights: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let n_rows = weights.len();
+    let n_cols = weights.first().map_or(0, Vec::len);
+    if n_rows > n_cols {
+        let transposed: Vec<Vec<usize>> = (0..n_cols)
+            .map(|c| weights.iter().map(|row| row[c]).collect())
+            .collect();
+        let mut matching = vec![None; n_rows];
+        for (c, r) in max_weight_matching(&transposed).into_iter().enumerate() {
+            if let Some(r) = r {
+                matching[r] = Some(c);
+            }
+        }
+        return matching;
+    }
+
+    // minimizes the cost `max - weight` with 1-based potentials, where
+    // column 0 is a sentinel holding the row being inserted
+    let max = weights.iter().flatten().copied().max().unwrap_or(0);
+    let cost =
+        |r: usize, c: usize| i64::try_from(max - weights[r][c]).expect("overlap fits in i64");
+    let mut u = vec![0_i64; n_rows + 1];
+    let mut v = vec![0_i64; n_cols + 1];
+    let mut row_of = vec![0_usize; n_cols + 1];
+    let mut way = vec![0_usize; n_cols + 1];
+    for r in 1..=n_rows {
+        row_of[0] = r;
+        let mut col = 0;
+        let mut min_slack = vec![i64::MAX; n_cols + 1];
+        let mut used = vec![false; n_cols + 1];
+        loop {
+            used[col] = true;
+            let row = row_of[col];
+            let mut delta = i64::MAX;
+            let mut next = 0;
+            for c in 1..=n_cols {
+                if used[c] {
+                    continue;
+                }
+                let slack = cost(row - 1, c - 1) - u[row] - v[c];
+                if slack < min_slack[c] {
+                    min_slack[c] = slack;
+                    way[c] = col;
+                }
+                if min_slack[c] < delta {
+                    delta = min_slack[c];
+                    next = c;
+                }
+            }
+            for c in 0..=n_cols {
+                if used[c] {
+                    u[row_of[c]] += delta;
+                    v[c] -= delta;
+                } else {
+                    min_slack[c] -= delta;
+                }
+            }
+            col = next;
+            if row_of[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let prev = way[col];
+            row_of[col] = row_of[prev];
+            col = prev;
+        }
+    }
+
+    let mut matching = vec![None; n_rows];
+    for c in 1..=n_cols {
+        if row_of[c] != 0 {
+            matching[row_of[c] - 1] = Some(c - 1);
+        }
+    }
+    matching
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+
+    use super::*;
+
+    #[test]
+    fn max_weight() {
+        let weights = vec![vec![1, 5, 0], vec![4, 6, 0]];
+        assert_eq!(max_weight_matching(&weights), [Some(1), Some(0)]);
+
+        let weights = vec![vec![3], vec![5], vec![1]];
+        assert_eq!(max_weight_matching(&weights), [None, Some(0), None]);
+
+        assert!(max_weight_matching(&[]).is_empty());
+    }
+
+    #[test]
+    fn match_clusters() {
+        // cluster 7 splits into 0 and 1, and the larger part keeps its ID
+        let previous = hashmap! {3 => vec![0, 1], 7 => vec![2, 3, 4, 5, 6]};
+        let current = hashmap! {0 => vec![2, 3], 1 => vec![4, 5, 6], 2 => vec![0, 1]};
+        let ids = super::match_clusters(&previous, &current);
+        assert_eq!(ids, hashmap! {0 => 8, 1 => 7, 2 => 3});
+
+        // clusters disappear, and new ones share no point with old ones
+        let previous = hashmap! {0 => vec![0, 1], 1 => vec![2, 3]};
+        let current = hashmap! {0 => vec![1], 1 => vec![4, 5]};
+        let ids = super::match_clusters(&previous, &current);
+        assert_eq!(ids, hashmap! {0 => 0, 1 => 2});
+
+        let ids = super::match_clusters(&HashMap::new(), &current);
+        assert_eq!(ids, hashmap! {0 => 0, 1 => 1});
+        assert!(super::match_clusters(&previous, &HashMap::new()).is_empty());
+    }
+}
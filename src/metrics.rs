@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use ndarray::{ArrayBase, ArrayView1, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::sum::{sum, Sum};
+
+/// An internal validity index, which scores a clustering from the input data
+/// alone, without ground-truth labels.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum InternalMetric {
+    /// [`calinski_harabasz_score`]; higher is better.
+    CalinskiHarabasz,
+
+    /// [`davies_bouldin_score`]; lower is better.
+    DaviesBouldin,
+
+    /// [`silhouette_score`]; higher is better.
+    Silhouette,
+}
+
+impl InternalMetric {
+    /// Computes the index of the given clustering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use ndarray::array;
+    /// use petal_clustering::{silhouette_score, InternalMetric};
+    ///
+    /// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+    /// let clusters = HashMap::from([(0, vec![0, 1]), (1, vec![2, 3])]);
+    /// let score: f64 = InternalMetric::Silhouette.score(&points, &clusters);
+    /// assert_eq!(score, silhouette_score(&points, &clusters));
+    /// ```
+    #[allow(clippy::implicit_hasher)]
+    pub fn score<S, A>(self, input: &ArrayBase<S, Ix2>, clusters: &HashMap<usize, Vec<usize>>) -> A
+    where
+        A: AddAssign + Float + FloatCore + FromPrimitive,
+        S: Data<Elem = A>,
+    {
+        match self {
+            Self::CalinskiHarabasz => calinski_harabasz_score(input, clusters),
+            Self::DaviesBouldin => davies_bouldin_score(input, clusters),
+            Self::Silhouette => silhouette_score(input, clusters),
+        }
+    }
+
+    /// Returns `true` if a higher index means a better clustering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::InternalMetric;
+    ///
+    /// assert!(InternalMetric::Silhouette.higher_is_better());
+    /// assert!(!InternalMetric::DaviesBouldin.higher_is_better());
+    /// ```
+    #[must_use]
+    pub fn higher_is_better(self) -> bool {
+        match self {
+            Self::CalinskiHarabasz | Self::Silhouette => true,
+            Self::DaviesBouldin => false,
+        }
+    }
+}
+
+/// Computes the Calinski–Harabasz index (variance ratio criterion) of a
+/// clustering: the ratio of the dispersion between clusters to the
+/// dispersion within clusters, each normalized by its degrees of freedom.
+///
+/// Noise points are ignored. The index is zero if there are fewer than two
+/// clusters, and one if every cluster consists of identical points.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use maplit::hashmap;
+/// use petal_clustering::calinski_harabasz_score;
+///
+/// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+/// let score: f64 = calinski_harabasz_score(&points, &hashmap! {0 => vec![0, 1], 1 => vec![2, 3]});
+/// assert!((score - 200.).abs() < 1e-9);
+/// ```
+///
+/// # References
+/// - Caliński, Tadeusz, and Jerzy Harabasz. "A dendrite method for cluster analysis."
+///   Communications in Statistics 3.1 (1974): 1-27.
+#[allow(clippy::implicit_hasher)]
+#[allow(clippy::missing_panics_doc)] // cluster sizes are valid floats
+pub fn calinski_harabasz_score<S, A>(
+    input: &ArrayBase<S, Ix2>,
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> A
+where
+    A: AddAssign + Float + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let clusters: Vec<_> = clusters.values().filter(|c| !c.is_empty()).collect();
+    let n: usize = clusters.iter().map(|c| c.len()).sum();
+    let k = clusters.len();
+    if k < 2 || n <= k {
+        return A::zero();
+    }
+
+    let centroids: Vec<_> = clusters.iter().map(|c| centroid(input, c)).collect();
+    let all: Vec<usize> = clusters.iter().flat_map(|c| c.iter().copied()).collect();
+    let center = centroid(input, &all);
+
+    let mut between = Sum::new();
+    let mut within = Sum::new();
+    for (members, centroid) in clusters.iter().zip(&centroids) {
+        let size = A::from_usize(members.len()).expect("valid float");
+        between.add(size * squared_distance(centroid, &center));
+        for &i in *members {
+            within.add(squared_distance_to(&input.row(i), centroid));
+        }
+    }
+    let (between, within) = (between.value(), within.value());
+    if within == A::zero() {
+        return A::one();
+    }
+    let n = A::from_usize(n).expect("valid float");
+    let k = A::from_usize(k).expect("valid float");
+    between * (n - k) / (within * (k - A::one()))
+}
+
+/// Computes the Davies–Bouldin index of a clustering: the mean, over all
+/// clusters, of the largest ratio of the sum of two clusters' mean distances
+/// to their centroids to the distance between their centroids.
+///
+/// Noise points are ignored. The index is zero if there are fewer than two
+/// clusters.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use maplit::hashmap;
+/// use petal_clustering::davies_bouldin_score;
+///
+/// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+/// let score: f64 = davies_bouldin_score(&points, &hashmap! {0 => vec![0, 1], 1 => vec![2, 3]});
+/// assert!((score - 0.1).abs() < 1e-9);
+/// ```
+///
+/// # References
+/// - Davies, David L., and Donald W. Bouldin. "A cluster separation measure."
+///   IEEE Transactions on Pattern Analysis and Machine Intelligence 2 (1979): 224-227.
+#[allow(clippy::implicit_hasher)]
+#[allow(clippy::missing_panics_doc)] // cluster sizes are valid floats
+pub fn davies_bouldin_score<S, A>(
+    input: &ArrayBase<S, Ix2>,
+    clusters: &HashMap<usize, Vec<usize>>,
+) -> A
+where
+    A: AddAssign + Float + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let clusters: Vec<_> = clusters.values().filter(|c| !c.is_empty()).collect();
+    let k = clusters.len();
+    if k < 2 {
+        return A::zero();
+    }
+
+    let centroids: Vec<_> = clusters.iter().map(|c| centroid(input, c)).collect();
+    let scatters: Vec<A> = clusters
+        .iter()
+        .zip(&centroids)
+        .map(|(members, centroid)| {
+            let total = sum(members
+                .iter()
+                .map(|&i| Float::sqrt(squared_distance_to(&input.row(i), centroid))));
+            total / A::from_usize(members.len()).expect("valid float")
+        })
+        .collect();
+
+    let mut total = Sum::new();
+    for i in 0..k {
+        let worst = (0..k)
+            .filter(|j| *j != i)
+            .map(|j| {
+                let separation = Float::sqrt(squared_distance(&centroids[i], &centroids[j]));
+                if separation == A::zero() {
+                    // coinciding centroids are as bad as it gets
+                    Float::infinity()
+                } else {
+                    (scatters[i] + scatters[j]) / separation
+                }
+            })
+            .fold(A::zero(), FloatCore::max);
+        total.add(worst);
+    }
+    total.value() / A::from_usize(k).expect("valid float")
+}
+
+/// Computes the mean silhouette coefficient of a clustering, with Euclidean
+/// distances. The coefficient of a point compares its mean distance `a` to
+/// the other points of its cluster with its mean distance `b` to the points
+/// of the nearest other cluster, as `(b - a) / max(a, b)`, and is zero for a
+/// point alone in its cluster.
+///
+/// Noise points are ignored. The score is between -1 and 1, and zero if
+/// there are fewer than two clusters. It takes time quadratic in the number
+/// of clustered points.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use maplit::hashmap;
+/// use petal_clustering::silhouette_score;
+///
+/// let points = array![[0., 0.], [0., 1.], [10., 0.], [10., 1.]];
+/// let score: f64 = silhouette_score(&points, &hashmap! {0 => vec![0, 1], 1 => vec![2, 3]});
+/// assert!(score > 0.9);
+/// ```
+///
+/// # References
+/// - Rousseeuw, Peter J. "Silhouettes: a graphical aid to the interpretation and validation of
+///   cluster analysis." Journal of Computational and Applied Mathematics 20 (1987): 53-65.
+#[allow(clippy::implicit_hasher)]
+#[allow(clippy::missing_panics_doc)] // cluster sizes are valid floats
+pub fn silhouette_score<S, A>(input: &ArrayBase<S, Ix2>, clusters: &HashMap<usize, Vec<usize>>) -> A
+where
+    A: AddAssign + Float + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let clusters: Vec<_> = clusters.values().filter(|c| !c.is_empty()).collect();
+    if clusters.len() < 2 {
+        return A::zero();
+    }
+
+    let mean_distance = |i: usize, members: &[usize]| {
+        let total = sum(members
+            .iter()
+            .map(|&j| Float::sqrt(squared_distance_between(&input.row(i), &input.row(j)))));
+        total / A::from_usize(members.len()).expect("valid float")
+    };
+    let mut total = Sum::new();
+    let mut n = 0;
+    for (c, members) in clusters.iter().enumerate() {
+        n += members.len();
+        if members.len() == 1 {
+            continue;
+        }
+        let others = A::from_usize(members.len() - 1).expect("valid float");
+        for &i in *members {
+            // the distance to the point itself is zero
+            let a = mean_distance(i, members) * A::from_usize(members.len()).expect("valid float")
+                / others;
+            let b = clusters
+                .iter()
+                .enumerate()
+                .filter(|(d, _)| *d != c)
+                .map(|(_, other)| mean_distance(i, other))
+                .fold(Float::infinity(), FloatCore::min);
+            let spread = FloatCore::max(a, b);
+            if spread > A::zero() {
+                total.add((b - a) / spread);
+            }
+        }
+    }
+    total.value() / A::from_usize(n).expect("valid float")
+}
+
+fn centroid<S, A>(input: &ArrayBase<S, Ix2>, members: &[usize]) -> Vec<A>
+where
+    A: AddAssign + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let mut sums = vec![Sum::new(); input.ncols()];
+    for &i in members {
+        for (s, v) in sums.iter_mut().zip(input.row(i)) {
+            s.add(*v);
+        }
+    }
+    let size = A::from_usize(members.len()).expect("valid float");
+    sums.iter().map(|s| s.value() / size).collect()
+}
+
+fn squared_distance<A: FloatCore>(a: &[A], b: &[A]) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+}
+
+fn squared_distance_between<A: FloatCore>(a: &ArrayView1<A>, b: &ArrayView1<A>) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+}
+
+fn squared_distance_to<A: FloatCore>(a: &ArrayView1<A>, b: &[A]) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+    use ndarray::array;
+
+    use super::{calinski_harabasz_score, davies_bouldin_score, silhouette_score, InternalMetric};
+
+    #[test]
+    fn calinski_harabasz() {
+        let data = array![[0., 0.], [0., 2.], [4., 0.], [4., 2.], [9., 9.]];
+        let clusters = hashmap! {0 => vec![0, 1], 1 => vec![2, 3]};
+        // between: 2 * 4 + 2 * 4 = 16, within: 4 * 1 = 4
+        let score: f64 = calinski_harabasz_score(&data, &clusters);
+        assert!((score - 16. * 2. / 4.).abs() < 1e-9);
+
+        let single: f64 = calinski_harabasz_score(&data, &hashmap! {0 => vec![0, 1, 2]});
+        assert!(single.abs() < f64::EPSILON);
+        let tight: f64 = calinski_harabasz_score(&data, &hashmap! {0 => vec![0], 1 => vec![4]});
+        assert!(tight.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn davies_bouldin() {
+        let data = array![[0., 0.], [0., 2.], [4., 0.], [4., 2.]];
+        let clusters = hashmap! {0 => vec![0, 1], 1 => vec![2, 3]};
+        let score: f64 = davies_bouldin_score(&data, &clusters);
+        assert!((score - 0.5).abs() < 1e-9);
+        let merged = hashmap! {0 => vec![0, 2], 1 => vec![1, 3]};
+        let worse: f64 = davies_bouldin_score(&data, &merged);
+        assert!(worse > score);
+
+        assert!(InternalMetric::CalinskiHarabasz.higher_is_better());
+        assert!(!InternalMetric::DaviesBouldin.higher_is_better());
+        let same: f64 = InternalMetric::DaviesBouldin.score(&data, &clusters);
+        assert!((same - score).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn silhouette() {
+        let data = array![[0., 0.], [0., 2.], [4., 0.], [4., 2.], [9., 9.]];
+        let clusters = hashmap! {0 => vec![0, 1], 1 => vec![2, 3]};
+        // a = 2 and b = (4 + sqrt(20)) / 2 for every point
+        let b = (4. + 20_f64.sqrt()) / 2.;
+        let score: f64 = silhouette_score(&data, &clusters);
+        assert!((score - (b - 2.) / b).abs() < 1e-9);
+
+        // the singleton cluster counts as zero
+        let with_singleton = hashmap! {0 => vec![0, 1], 1 => vec![2, 3], 2 => vec![4]};
+        let lower: f64 = silhouette_score(&data, &with_singleton);
+        assert!(lower < score);
+
+        let single: f64 = silhouette_score(&data, &hashmap! {0 => vec![0, 1, 2]});
+        assert!(single.abs() < f64::EPSILON);
+        assert!(InternalMetric::Silhouette.higher_is_better());
+    }
+}
@@ -0,0 +1,125 @@
+use ndarray::ArrayView1;
+use num_traits::Float;
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+/// The Euclidean distance between points with missing values, marked by
+/// NaN, computed from the coordinates present in both points.
+///
+/// Instead of propagating NaN, the squared differences of the coordinates
+/// present in both points are summed and scaled by the number of
+/// coordinates over the number of present ones, so that the distance does
+/// not shrink as values go missing. Between points without any coordinate
+/// present in both, the distance is infinite. Without missing values, it is
+/// the Euclidean distance.
+///
+/// Points with missing values are compared with every point, instead of
+/// being searched in a ball tree, so [`Dbscan`](crate::Dbscan) and
+/// [`StDbscan`](crate::StDbscan) slow down with the number of such points.
+/// [`HDbscan`](crate::HDbscan) and [`Optics`](crate::Optics) leave them out
+/// as noise regardless of the metric. [`KMedoids`](crate::KMedoids) and
+/// [`AgglomerativeClustering`](crate::AgglomerativeClustering), which
+/// compare all pairs of points, take them as is.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Dbscan, Fit, NanEuclidean};
+///
+/// let nan = f64::NAN;
+/// let points = array![[1., 2.], [2., 2.], [nan, 2.3], [8., 7.], [8., nan], [25., 80.]];
+/// let (clusters, noise) = Dbscan::new(3., 2, NanEuclidean).fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(noise, [5]); // the points with a missing value are in clusters
+/// ```
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct NanEuclidean;
+
+impl<A: Float> Metric<A> for NanEuclidean {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.rdistance(x1, x2).sqrt()
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        let mut sum = A::zero();
+        let mut present = 0_usize;
+        for (a, b) in x1.iter().zip(x2) {
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+            sum = sum + (*a - *b) * (*a - *b);
+            present += 1;
+        }
+        if present == 0 {
+            return A::infinity();
+        }
+        if present == x1.len() {
+            return sum;
+        }
+        let scale =
+            A::from(x1.len()).unwrap_or_else(A::one) / A::from(present).unwrap_or_else(A::one);
+        sum * scale
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d.sqrt()
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d * d
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, aview1};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Dbscan, Fit, KMedoids};
+
+    #[test]
+    fn distance() {
+        let nan = f64::NAN;
+        let d = |a: &[f64], b: &[f64]| NanEuclidean.distance(&aview1(a), &aview1(b));
+        assert!((d(&[0., 0., 0.], &[1., 2., 2.]) - 3.).abs() < 1e-12);
+        // the two present coordinates count for three
+        assert!((d(&[0., nan, 0.], &[1., 2., 1.]) - 3_f64.sqrt()).abs() < 1e-12);
+        assert!((d(&[0., 0., 0.], &[nan, 4., nan]) - 48_f64.sqrt()).abs() < 1e-12);
+        assert!(d(&[nan, 0.], &[0., nan]).is_infinite());
+        assert!(d(&[f64::INFINITY, 0.], &[f64::INFINITY, 0.]).is_nan());
+    }
+
+    #[test]
+    fn dbscan() {
+        let nan = f64::NAN;
+        let points = array![
+            [0., 0., 0.],
+            [0.5, nan, 0.],
+            [0., 0.5, nan],
+            [nan, nan, nan],
+            [5., 5., 5.],
+            [5., 5.5, 5.],
+            [nan, 5., 5.],
+        ];
+        let (clusters, noise) = Dbscan::new(1., 2, NanEuclidean).fit(&points, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        for cluster in &mut clusters {
+            cluster.sort_unstable();
+        }
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2], vec![4, 5, 6]]);
+        assert_eq!(noise, [3]);
+
+        // `Euclidean` propagates NaN, so points with missing values are noise
+        let (clusters, noise) = Dbscan::new(1., 2, Euclidean::default()).fit(&points, None);
+        assert_eq!(clusters[&0], [4, 5]);
+        assert_eq!(noise, [0, 1, 2, 3, 6]);
+
+        let (clusters, _) =
+            KMedoids::new(2, NanEuclidean).fit(&points.slice(ndarray::s![4.., ..]), None);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 3);
+    }
+}
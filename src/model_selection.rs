@@ -0,0 +1,897 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use petal_neighbors::{distance::Metric, BallTree};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::dbscan::{apply_border_policy, cluster_neighborhoods};
+use crate::metrics::InternalMetric;
+use crate::parallel::prelude::*;
+use crate::{Dbscan, Fit, GaussianMixture, HDbscan, KMeans};
+
+/// Searches for the `eps` of `dbscan` that optimizes an internal validity
+/// index, with at most `budget` fits.
+///
+/// The search starts with `initial_eps` and narrows down the interval
+/// `(0, 2 * initial_eps]` with golden-section search, assuming the index is
+/// unimodal in `eps` there. The neighbors of every point within the upper
+/// bound are computed once and reused by every fit. Noise points are ignored
+/// by the index, and clusterings with fewer than two clusters are considered
+/// the worst.
+///
+/// `dbscan.eps` is set to the best `eps` found, which is returned along with
+/// its index. The best `eps` is never worse than `initial_eps`.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{refine_eps, Dbscan, InternalMetric};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [8.5, 7.5]];
+/// let mut dbscan = Dbscan::new(5., 2, Euclidean::default());
+/// let (eps, score) = refine_eps(&mut dbscan, &points, 5., InternalMetric::CalinskiHarabasz, 10);
+///
+/// assert_eq!(dbscan.eps, eps);
+/// assert!(score > 0.);
+/// ```
+#[allow(clippy::missing_panics_doc)] // the golden ratio is a valid float
+pub fn refine_eps<S, A, M>(
+    dbscan: &mut Dbscan<A, M>,
+    input: &ArrayBase<S, Ix2>,
+    initial_eps: A,
+    metric: InternalMetric,
+    budget: usize,
+) -> (A, A)
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    dbscan.eps = initial_eps;
+    if input.is_empty() || budget == 0 {
+        return (initial_eps, A::zero());
+    }
+
+    let input = input.as_standard_layout();
+    let upper = initial_eps + initial_eps;
+    let neighbors = sorted_neighbors(&input.view(), upper, &dbscan.metric);
+    let min_samples = vec![dbscan.min_samples; input.nrows()];
+    let evaluate = |eps: A| {
+        let neighborhoods: Vec<Vec<usize>> = neighbors
+            .par_iter()
+            .map(|n| {
+                n.iter()
+                    .take_while(|(d, _)| *d <= eps)
+                    .map(|(_, j)| *j)
+                    .collect()
+            })
+            .collect();
+        let (clusters, _) = apply_border_policy(
+            cluster_neighborhoods(&neighborhoods, &min_samples),
+            &neighborhoods,
+            &min_samples,
+            dbscan.border_policy,
+            |i, j| dbscan.metric.distance(&input.row(i), &input.row(j)),
+        );
+        if clusters.len() < 2 {
+            return (Float::neg_infinity(), A::zero());
+        }
+        let score: A = metric.score(&input, &clusters);
+        let objective = if metric.higher_is_better() {
+            score
+        } else {
+            -score
+        };
+        (objective, score)
+    };
+
+    let mut best = (initial_eps, evaluate(initial_eps));
+    let mut evaluations = 1;
+    let track = |eps: A, best: &mut (A, (A, A))| {
+        let result = evaluate(eps);
+        if result.0 > best.1 .0 {
+            *best = (eps, result);
+        }
+        result.0
+    };
+
+    let ratio = A::from_f64((5_f64.sqrt() - 1.) / 2.).expect("valid float");
+    let (mut lo, mut hi) = (A::zero(), upper);
+    let mut c = hi - ratio * (hi - lo);
+    let mut d = lo + ratio * (hi - lo);
+    let (mut fc, mut fd) = (Float::neg_infinity(), Float::neg_infinity());
+    if evaluations < budget {
+        fc = track(c, &mut best);
+        evaluations += 1;
+    }
+    if evaluations < budget {
+        fd = track(d, &mut best);
+        evaluations += 1;
+    }
+    while evaluations < budget {
+        if fc >= fd {
+            hi = d;
+            d = c;
+            fd = fc;
+            c = hi - ratio * (hi - lo);
+            fc = track(c, &mut best);
+        } else {
+            lo = c;
+            c = d;
+            fc = fd;
+            d = lo + ratio * (hi - lo);
+            fd = track(d, &mut best);
+        }
+        evaluations += 1;
+    }
+
+    let (eps, (_, score)) = best;
+    dbscan.eps = eps;
+    (eps, score)
+}
+
+/// The `eps` values suggested by [`suggest_eps`] for a dataset, with the
+/// curve they were found on.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EpsSuggestion<A> {
+    /// The suggested values, best first: the distances at the elbows of
+    /// `k_distances`.
+    pub candidates: Vec<A>,
+
+    /// The distance from each point to its `k`-th nearest neighbor, in
+    /// increasing order.
+    pub k_distances: Vec<A>,
+}
+
+/// Suggests values of DBSCAN's `eps` at the elbow of the sorted
+/// `k`-distances of the input, i.e. the distances from each point to its
+/// `k`-th nearest neighbor other than itself.
+///
+/// With `k = min_samples - 1`, a point is a core point if and only if its
+/// `k`-distance is at most `eps`. Points in clusters have small `k`-distances
+/// and noise points large ones, so the sorted `k`-distances bend sharply
+/// upward where the clusters end. The bends are found with Kneedle: after
+/// scaling both axes to `[0, 1]`, the candidates are the distances where the
+/// curve is locally farthest below the diagonal, at least half as far as at
+/// its farthest point, in decreasing order of that distance.
+///
+/// The suggestion is empty if there are no more than `k` points, or if `k`
+/// is zero.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::suggest_eps;
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let suggestion = suggest_eps(&points, 1, &Euclidean::default());
+///
+/// assert_eq!(suggestion.k_distances.len(), 6);
+/// assert_eq!(suggestion.candidates[0], 1.);
+/// ```
+///
+/// # References
+/// - Satopaa, Ville, et al. "Finding a “kneedle” in a haystack: Detecting
+///   knee points in system behavior." International Conference on
+///   Distributed Computing Systems Workshops (2011): 166-171.
+#[allow(clippy::missing_panics_doc)] // the input has more than `k` points
+pub fn suggest_eps<S, A, M>(input: &ArrayBase<S, Ix2>, k: usize, metric: &M) -> EpsSuggestion<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    let n = input.nrows();
+    if k == 0 || n <= k {
+        return EpsSuggestion {
+            candidates: Vec::new(),
+            k_distances: Vec::new(),
+        };
+    }
+
+    let input = input.as_standard_layout();
+    let rows: Vec<_> = input.rows().into_iter().collect();
+    let db = BallTree::new(input.view(), metric.clone()).expect("non-empty array");
+    let mut k_distances: Vec<A> = rows.par_iter().map(|p| db.query(p, k + 1).1[k]).collect();
+    k_distances.sort_unstable_by(|a, b| a.partial_cmp(b).expect("no NaN distances"));
+
+    let (first, last) = (k_distances[0], k_distances[n - 1]);
+    if n < 3 || first == last {
+        return EpsSuggestion {
+            candidates: vec![last],
+            k_distances,
+        };
+    }
+    let last_index = A::from_usize(n - 1).expect("valid float");
+    let below_diagonal: Vec<A> = k_distances
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            A::from_usize(i).expect("valid float") / last_index - (*d - first) / (last - first)
+        })
+        .collect();
+    let mut elbows: Vec<(A, A)> = (1..n - 1)
+        .filter(|i| {
+            below_diagonal[*i] > below_diagonal[i - 1]
+                && below_diagonal[*i] >= below_diagonal[i + 1]
+        })
+        .map(|i| (below_diagonal[i], k_distances[i]))
+        .collect();
+    let farthest = elbows.iter().fold(A::zero(), |max, (b, _)| max.max(*b));
+    let half = A::from_f32(0.5).expect("valid float");
+    elbows.retain(|(b, _)| *b > A::zero() && *b >= farthest * half);
+    elbows.sort_unstable_by(|a, b| b.0.partial_cmp(&a.0).expect("no NaN distances"));
+    let mut candidates: Vec<A> = Vec::with_capacity(elbows.len());
+    for (_, d) in elbows {
+        if !candidates.contains(&d) {
+            candidates.push(d);
+        }
+    }
+    if candidates.is_empty() {
+        candidates.push(last);
+    }
+    EpsSuggestion {
+        candidates,
+        k_distances,
+    }
+}
+
+/// An exhaustive search over a grid of density parameters, which fits every
+/// combination in parallel and ranks them by an internal validity index.
+///
+/// For DBSCAN, the grid is `eps` by `min_samples`. For HDBSCAN, it is
+/// `cluster_selection_epsilon`, from `eps`, by `min_cluster_size`, from
+/// `min_samples`. An empty list keeps the value of the estimator the search
+/// starts from.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, GridSearch, InternalMetric};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [8.5, 7.5]];
+/// let search = GridSearch::new(vec![0.5, 2., 20.], vec![2, 3], InternalMetric::Silhouette);
+/// let result = search.fit_dbscan(&Dbscan::new(1., 2, Euclidean::default()), &points);
+///
+/// let best = &result.ranking[0];
+/// assert_eq!((best.eps, best.n_clusters), (2., 2));
+/// assert_eq!(result.best.unwrap().eps, 2.);
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GridSearch<A> {
+    /// The values of `eps` for DBSCAN, or of `cluster_selection_epsilon` for
+    /// HDBSCAN.
+    pub eps: Vec<A>,
+
+    /// The values of `min_samples` for DBSCAN, or of `min_cluster_size` for
+    /// HDBSCAN.
+    pub min_samples: Vec<usize>,
+
+    /// The index the combinations are ranked by. Noise points are ignored,
+    /// and clusterings with fewer than two clusters are ranked last.
+    pub scoring: InternalMetric,
+}
+
+/// A combination of parameters tried by [`GridSearch`], with the clustering
+/// it produced.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GridCandidate<A> {
+    /// The value of `eps`, or of `cluster_selection_epsilon` for HDBSCAN.
+    pub eps: A,
+
+    /// The value of `min_samples`, or of `min_cluster_size` for HDBSCAN.
+    pub min_samples: usize,
+
+    /// The index of the clustering, or `None` if it has fewer than two
+    /// clusters.
+    pub score: Option<A>,
+
+    /// The number of clusters.
+    pub n_clusters: usize,
+
+    /// The number of noise points.
+    pub n_noise: usize,
+}
+
+/// The result of a [`GridSearch`].
+#[derive(Debug)]
+pub struct GridSearchResult<A, E> {
+    /// Every combination, best first. Ties keep the order of the grid, with
+    /// `eps` varying slowest.
+    pub ranking: Vec<GridCandidate<A>>,
+
+    /// The estimator fitted with the best combination, or `None` if the grid
+    /// is empty.
+    pub best: Option<E>,
+
+    /// The clusters and noise of the best combination.
+    pub clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+}
+
+impl<A> GridSearch<A> {
+    /// Creates a search over every combination of `eps` and `min_samples`,
+    /// ranked by `scoring`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::{GridSearch, InternalMetric};
+    ///
+    /// let search = GridSearch::new(vec![0.5, 1.], vec![3, 5, 10], InternalMetric::DaviesBouldin);
+    /// assert_eq!(search.eps.len() * search.min_samples.len(), 6);
+    /// ```
+    #[must_use]
+    pub fn new(eps: Vec<A>, min_samples: Vec<usize>, scoring: InternalMetric) -> Self {
+        Self {
+            eps,
+            min_samples,
+            scoring,
+        }
+    }
+}
+
+impl<A> GridSearch<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Fits DBSCAN with every combination of `eps` and `min_samples`, with
+    /// the other parameters of `dbscan`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, GridSearch, InternalMetric};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [8.5, 7.5]];
+    /// let search = GridSearch::new(vec![0.5, 2.], vec![], InternalMetric::Silhouette);
+    /// let result = search.fit_dbscan(&Dbscan::new(1., 2, Euclidean::default()), &points);
+    ///
+    /// assert_eq!(result.ranking.len(), 2);
+    /// assert_eq!(result.ranking[0].eps, 2.);
+    /// assert_eq!(result.clustering.0.len(), 2);
+    /// ```
+    pub fn fit_dbscan<S, M>(
+        &self,
+        dbscan: &Dbscan<A, M>,
+        input: &ArrayBase<S, Ix2>,
+    ) -> GridSearchResult<A, Dbscan<A, M>>
+    where
+        S: Data<Elem = A> + Sync,
+        M: Metric<A> + Clone + Send + Sync,
+    {
+        self.search(dbscan.eps, dbscan.min_samples, input, |eps, min_samples| {
+            let mut model = Dbscan::new(eps, min_samples, dbscan.metric.clone());
+            model.border_policy = dbscan.border_policy;
+            let clustering = model.fit(input, None);
+            (model, clustering)
+        })
+    }
+
+    /// Fits HDBSCAN with every combination of `cluster_selection_epsilon`
+    /// and `min_cluster_size`, with the other parameters of `hdbscan`,
+    /// including `min_samples`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{GridSearch, HDbscan, InternalMetric};
+    ///
+    /// let points = array![
+    ///     [1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1],
+    ///     [-2.0, 3.0], [-2.2, 3.1], [-2.1, 2.9], [-2.0, 3.2],
+    /// ];
+    /// let search = GridSearch::new(vec![0., 0.5], vec![2, 3], InternalMetric::Silhouette);
+    /// let result = search.fit_hdbscan(&HDbscan::new(3, Euclidean::default()), &points);
+    ///
+    /// assert_eq!(result.ranking.len(), 4);
+    /// assert_eq!(result.ranking[0].n_clusters, 2);
+    /// ```
+    pub fn fit_hdbscan<S, M>(
+        &self,
+        hdbscan: &HDbscan<A, M>,
+        input: &ArrayBase<S, Ix2>,
+    ) -> GridSearchResult<A, HDbscan<A, M>>
+    where
+        S: Data<Elem = A> + Sync,
+        M: Metric<A> + Clone + Send + Sync,
+    {
+        self.search(
+            hdbscan.cluster_selection_epsilon,
+            hdbscan.min_cluster_size,
+            input,
+            |eps, min_cluster_size| {
+                let mut model = HDbscan::new(min_cluster_size, hdbscan.metric.clone());
+                model.alpha = hdbscan.alpha;
+                model.min_samples = hdbscan.min_samples;
+                model.boruvka = hdbscan.boruvka;
+                model.extraction = hdbscan.extraction;
+                model.cluster_selection_epsilon = eps;
+                let (clusters, noise, _) = model.fit(input, None);
+                (model, (clusters, noise))
+            },
+        )
+    }
+
+    /// Fits every combination with `fit`, and ranks them.
+    fn search<S, E>(
+        &self,
+        default_eps: A,
+        default_min_samples: usize,
+        input: &ArrayBase<S, Ix2>,
+        fit: impl Fn(A, usize) -> (E, (HashMap<usize, Vec<usize>>, Vec<usize>)) + Sync,
+    ) -> GridSearchResult<A, E>
+    where
+        S: Data<Elem = A> + Sync,
+        E: Send,
+    {
+        let eps = if self.eps.is_empty() {
+            vec![default_eps]
+        } else {
+            self.eps.clone()
+        };
+        let min_samples = if self.min_samples.is_empty() {
+            vec![default_min_samples]
+        } else {
+            self.min_samples.clone()
+        };
+        let grid: Vec<(A, usize)> = eps
+            .iter()
+            .flat_map(|eps| min_samples.iter().map(move |m| (*eps, *m)))
+            .collect();
+
+        let mut fits: Vec<_> = grid
+            .into_par_iter()
+            .map(|(eps, min_samples)| {
+                let (model, (clusters, noise)) = fit(eps, min_samples);
+                let score = (clusters.len() >= 2).then(|| self.scoring.score(input, &clusters));
+                let candidate = GridCandidate {
+                    eps,
+                    min_samples,
+                    score,
+                    n_clusters: clusters.len(),
+                    n_noise: noise.len(),
+                };
+                (candidate, model, (clusters, noise))
+            })
+            .collect();
+        let objective = |candidate: &GridCandidate<A>| match candidate.score {
+            Some(score) if self.scoring.higher_is_better() => score,
+            Some(score) => -score,
+            None => Float::neg_infinity(),
+        };
+        fits.sort_by(|a, b| {
+            objective(&b.0)
+                .partial_cmp(&objective(&a.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut fits = fits.into_iter();
+        let Some((first, best, clustering)) = fits.next() else {
+            return GridSearchResult {
+                ranking: Vec::new(),
+                best: None,
+                clustering: (HashMap::new(), Vec::new()),
+            };
+        };
+        let ranking = std::iter::once(first)
+            .chain(fits.map(|(candidate, _, _)| candidate))
+            .collect();
+        GridSearchResult {
+            ranking,
+            best: Some(best),
+            clustering,
+        }
+    }
+}
+
+/// A criterion for choosing the number of clusters with [`select_k`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum KCriterion {
+    /// The elbow of the inertia of k-means, where adding clusters stops
+    /// paying off, found with Kneedle.
+    Elbow,
+
+    /// The gap statistic: the log inertia of k-means on `n_references`
+    /// uniform samples in the bounding box of the input minus that on the
+    /// input. The smallest `k` whose gap is at least that of the next
+    /// candidate minus its standard error is recommended.
+    Gap { n_references: usize },
+
+    /// The Bayesian information criterion of a Gaussian mixture with full
+    /// covariances. The `k` with the lowest BIC is recommended.
+    Bic,
+}
+
+/// The scores of the candidate numbers of clusters, returned by
+/// [`select_k`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct KSelection<A> {
+    /// The candidate numbers of clusters, in increasing order.
+    pub ks: Vec<usize>,
+
+    /// The score of each candidate: the inertia for [`KCriterion::Elbow`],
+    /// the gap for [`KCriterion::Gap`], and the BIC for [`KCriterion::Bic`].
+    pub scores: Vec<A>,
+
+    /// The standard error of each gap for [`KCriterion::Gap`], and empty
+    /// otherwise.
+    pub errors: Vec<A>,
+
+    /// The recommended number of clusters, or `None` if there are no
+    /// candidates or no points.
+    pub recommended: Option<usize>,
+}
+
+/// Scores each number of clusters in `ks` with `criterion`, fitting the
+/// candidates in parallel, and recommends one.
+///
+/// The models are fitted with `seed`, which also draws the reference samples
+/// of the gap statistic. The candidates should not exceed the number of
+/// points.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{select_k, KCriterion};
+///
+/// let points = array![
+///     [1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5],
+///     [1., 9.], [1.3, 9.2], [0.8, 8.9],
+/// ];
+/// let selection = select_k(&points, &[1, 2, 3, 4, 5], KCriterion::Elbow, 0);
+///
+/// assert_eq!(selection.scores.len(), 5);
+/// assert_eq!(selection.recommended, Some(3));
+/// ```
+///
+/// # References
+/// - Tibshirani, Robert, Guenther Walther, and Trevor Hastie. "Estimating the number of clusters in a data set via the
+///   gap statistic." Journal of the Royal Statistical Society: Series B 63.2 (2001): 411-423.
+/// - Schwarz, Gideon. "Estimating the dimension of a model." The Annals of Statistics 6.2 (1978): 461-464.
+#[allow(clippy::missing_panics_doc)] // the numbers of references and points are valid floats
+pub fn select_k<S, A>(
+    input: &ArrayBase<S, Ix2>,
+    ks: &[usize],
+    criterion: KCriterion,
+    seed: u64,
+) -> KSelection<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A> + Sync,
+{
+    let mut ks = ks.to_vec();
+    ks.sort_unstable();
+    ks.dedup();
+    if input.is_empty() || ks.is_empty() {
+        return KSelection {
+            ks,
+            scores: vec![],
+            errors: vec![],
+            recommended: None,
+        };
+    }
+
+    let inertia = |data: &ArrayView2<A>, k: usize| {
+        let mut kmeans = KMeans::new(k);
+        kmeans.seed = seed;
+        kmeans.fit(data, None);
+        kmeans.inertia()
+    };
+    let (scores, errors, recommended) = match criterion {
+        KCriterion::Elbow => {
+            let input = input.view();
+            let scores: Vec<A> = ks.par_iter().map(|k| inertia(&input, *k)).collect();
+            let elbow = elbow(&scores);
+            (scores, vec![], elbow)
+        }
+        KCriterion::Gap { n_references } => {
+            let references = uniform_references(input, n_references.max(1), seed);
+            let log_inertia = |data: &ArrayView2<A>, k| {
+                Float::ln(FloatCore::max(
+                    inertia(data, k),
+                    FloatCore::min_positive_value(),
+                ))
+            };
+            let input = input.view();
+            let (scores, errors): (Vec<A>, Vec<A>) = ks
+                .par_iter()
+                .map(|k| {
+                    let logs: Vec<A> = references
+                        .iter()
+                        .map(|r| log_inertia(&r.view(), *k))
+                        .collect();
+                    let b = A::from_usize(logs.len()).expect("valid float");
+                    let mean = logs.iter().fold(A::zero(), |acc, v| acc + *v) / b;
+                    let variance = logs
+                        .iter()
+                        .fold(A::zero(), |acc, v| acc + (*v - mean) * (*v - mean))
+                        / b;
+                    let error = Float::sqrt(variance * (A::one() + A::one() / b));
+                    (mean - log_inertia(&input, *k), error)
+                })
+                .unzip();
+            let first = (0..ks.len())
+                .find(|i| i + 1 == ks.len() || scores[*i] >= scores[i + 1] - errors[i + 1]);
+            (scores, errors, first)
+        }
+        KCriterion::Bic => {
+            let scores: Vec<A> = ks
+                .par_iter()
+                .map(|k| {
+                    let mut gmm = GaussianMixture::new(*k);
+                    gmm.seed = seed;
+                    gmm.fit(input, None);
+                    gmm.bic(input)
+                })
+                .collect();
+            let lowest = (0..scores.len()).fold(
+                0,
+                |best, i| {
+                    if scores[i] < scores[best] {
+                        i
+                    } else {
+                        best
+                    }
+                },
+            );
+            (scores, vec![], Some(lowest))
+        }
+    };
+    KSelection {
+        recommended: recommended.map(|i| ks[i]),
+        ks,
+        scores,
+        errors,
+    }
+}
+
+/// Returns the index of the elbow of a decreasing curve, the point farthest
+/// below the line between its ends after scaling both axes to `[0, 1]`.
+fn elbow<A: Float + FromPrimitive>(scores: &[A]) -> Option<usize> {
+    let n = scores.len();
+    let (first, last) = (*scores.first()?, scores[n - 1]);
+    if n < 3 || first <= last {
+        return Some(0);
+    }
+    let last_index = A::from_usize(n - 1).expect("valid float");
+    let below: Vec<A> = scores
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let x = A::from_usize(i).expect("valid float") / last_index;
+            A::one() - x - (*s - last) / (first - last)
+        })
+        .collect();
+    Some((0..n).fold(0, |best, i| if below[i] > below[best] { i } else { best }))
+}
+
+/// Draws `n_references` samples with as many points as `input`, uniformly in
+/// the bounding box of `input`.
+fn uniform_references<S, A>(
+    input: &ArrayBase<S, Ix2>,
+    n_references: usize,
+    seed: u64,
+) -> Vec<Array2<A>>
+where
+    A: Float + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let bounds: Vec<(A, A)> = input
+        .columns()
+        .into_iter()
+        .map(|column| {
+            column
+                .iter()
+                .fold((A::infinity(), A::neg_infinity()), |(min, max), v| {
+                    (min.min(*v), max.max(*v))
+                })
+        })
+        .collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n_references)
+        .map(|_| {
+            Array2::from_shape_fn(input.dim(), |(_, j)| {
+                let (min, max) = bounds[j];
+                let u = A::from_f64(rng.random::<f64>()).expect("valid float");
+                min + (max - min) * u
+            })
+        })
+        .collect()
+}
+
+/// Finds the neighbors of each point within `radius`, sorted by distance, so
+/// that the neighborhood for any smaller radius is a prefix.
+fn sorted_neighbors<A, M>(input: &ArrayView2<A>, radius: A, metric: &M) -> Vec<Vec<(A, usize)>>
+where
+    A: FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    let rows: Vec<_> = input.rows().into_iter().collect();
+    let db = BallTree::new(*input, metric.clone()).expect("non-empty array");
+    rows.par_iter()
+        .map(|p| {
+            let mut neighbors: Vec<_> = db
+                .query_radius(p, radius)
+                .into_iter()
+                .map(|j| (metric.distance(p, &input.row(j)), j))
+                .collect();
+            neighbors.sort_unstable_by(|a, b| a.partial_cmp(b).expect("no NaN distances"));
+            neighbors
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+    use petal_neighbors::distance::Euclidean;
+
+    use crate::{Dbscan, Fit, GridSearch, HDbscan, InternalMetric, KCriterion};
+
+    #[test]
+    fn refine_eps() {
+        // three blobs, separated by any eps between sqrt(2) and 5
+        let data = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [1., 1.],
+            [6., 0.],
+            [6., 1.],
+            [7., 0.],
+            [7., 1.],
+            [0., 6.],
+            [0., 7.],
+            [1., 6.],
+            [1., 7.],
+        ];
+        let mut dbscan = Dbscan::new(8., 2, Euclidean::default());
+        let initial = dbscan.fit(&data, None);
+        assert_eq!(initial.0.len(), 1);
+
+        let (eps, score) =
+            super::refine_eps(&mut dbscan, &data, 8., InternalMetric::CalinskiHarabasz, 20);
+        assert!(eps >= 2_f64.sqrt() && eps < 5., "{eps}");
+        assert!(score > 0.);
+        let (clusters, noise) = dbscan.fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert!(noise.is_empty());
+        let expected: f64 = InternalMetric::CalinskiHarabasz.score(&data, &clusters);
+        assert!((score - expected).abs() < 1e-9);
+
+        let (eps, _) = super::refine_eps(&mut dbscan, &data, 8., InternalMetric::DaviesBouldin, 20);
+        assert!(eps >= 2_f64.sqrt() && eps < 5., "{eps}");
+    }
+
+    #[test]
+    fn suggest_eps() {
+        // three blobs of four points each, with a point between them
+        let data = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [1., 1.],
+            [6., 0.],
+            [6., 1.],
+            [7., 0.],
+            [7., 1.],
+            [0., 6.],
+            [0., 7.],
+            [1., 6.],
+            [1., 7.],
+            [4., 4.],
+        ];
+        let suggestion = super::suggest_eps(&data, 3, &Euclidean::default());
+        assert_eq!(suggestion.k_distances.len(), data.nrows());
+        assert!(suggestion.k_distances.windows(2).all(|w| w[0] <= w[1]));
+        let eps = suggestion.candidates[0];
+        assert!((eps - 2_f64.sqrt()).abs() < 1e-12, "{eps}");
+        let (clusters, noise) = Dbscan::new(eps, 4, Euclidean::default()).fit(&data, None);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(noise, [12]);
+
+        let suggestion = super::suggest_eps(&data, 13, &Euclidean::default());
+        assert!(suggestion.candidates.is_empty());
+        assert!(suggestion.k_distances.is_empty());
+    }
+
+    #[test]
+    fn grid_search() {
+        let data = array![
+            [0., 0.],
+            [0., 1.],
+            [1., 0.],
+            [1., 1.],
+            [6., 0.],
+            [6., 1.],
+            [7., 0.],
+            [7., 1.],
+            [0., 6.],
+            [0., 7.],
+            [1., 6.],
+            [1., 7.],
+        ];
+        let search = GridSearch::new(
+            vec![0.5, 1.5, 8_f64],
+            vec![2, 5],
+            InternalMetric::Silhouette,
+        );
+        let result = search.fit_dbscan(&Dbscan::new(1., 2, Euclidean::default()), &data);
+        assert_eq!(result.ranking.len(), 6);
+        let best = &result.ranking[0];
+        assert!((best.eps - 1.5).abs() < f64::EPSILON);
+        assert_eq!((best.min_samples, best.n_clusters, best.n_noise), (2, 3, 0));
+        assert!(result.ranking[1..]
+            .iter()
+            .all(|c| c.score.map_or(true, |s| s <= best.score.unwrap())));
+        assert!(result.ranking.last().unwrap().score.is_none());
+        assert_eq!(result.clustering.0.len(), 3);
+        assert!((result.best.unwrap().eps - 1.5).abs() < f64::EPSILON);
+
+        let search = GridSearch::new(vec![], vec![2, 4, 12], InternalMetric::DaviesBouldin);
+        let result = search.fit_hdbscan(&HDbscan::new(4, Euclidean::default()), &data);
+        assert_eq!(result.ranking.len(), 3);
+        assert_eq!(result.ranking[0].n_clusters, 3);
+        let best = result.best.unwrap();
+        assert_eq!(best.outlier_scores().len(), data.nrows());
+        assert_eq!(best.min_samples, 4);
+
+        let empty = GridSearch::new(vec![1.], vec![], InternalMetric::Silhouette);
+        let result = empty.fit_dbscan(&Dbscan::new(1., 2, Euclidean::default()), &data);
+        assert_eq!(result.ranking[0].min_samples, 2);
+    }
+
+    #[test]
+    fn refine_eps_budget() {
+        let data: Array2<f64> = array![[0., 0.], [0., 1.], [5., 0.], [5., 1.]];
+        let mut dbscan = Dbscan::new(2., 2, Euclidean::default());
+        let (eps, _) =
+            super::refine_eps(&mut dbscan, &data, 2., InternalMetric::CalinskiHarabasz, 1);
+        assert!((eps - 2.).abs() < f64::EPSILON);
+
+        let empty: Array2<f64> = Array2::zeros((0, 2));
+        let (eps, score) =
+            super::refine_eps(&mut dbscan, &empty, 2., InternalMetric::CalinskiHarabasz, 5);
+        assert!((eps - 2.).abs() < f64::EPSILON);
+        assert!(score.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn select_k() {
+        // three blobs of 30 points
+        let data = Array2::from_shape_fn((90, 2), |(i, j)| {
+            let t = f64::from(u32::try_from(i % 30).unwrap()) + 1.;
+            let noise = ((t * [0.754_877_666, 0.569_840_291][j]).fract() - 0.5) * 2.;
+            [[0., 0.], [10., 5.], [0., 10.]][i / 30][j] + noise
+        });
+        let ks = [6, 1, 2, 3, 4, 5, 3];
+        for criterion in [
+            KCriterion::Elbow,
+            KCriterion::Gap { n_references: 10 },
+            KCriterion::Bic,
+        ] {
+            let selection = super::select_k(&data, &ks, criterion, 0);
+            assert_eq!(selection.ks, [1, 2, 3, 4, 5, 6]);
+            assert_eq!(selection.scores.len(), 6);
+            assert_eq!(selection.recommended, Some(3));
+        }
+
+        let empty = Array2::<f64>::zeros((0, 2));
+        let selection = super::select_k(&empty, &ks, KCriterion::Elbow, 0);
+        assert_eq!(selection.recommended, None);
+    }
+}
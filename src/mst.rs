@@ -7,12 +7,12 @@ use ndarray::{Array1, ArrayView1, ArrayView2};
 use num_traits::{float::FloatCore, FromPrimitive};
 use petal_neighbors::distance::Metric;
 use petal_neighbors::BallTree;
-use rayon::prelude::*;
 
+use crate::parallel::prelude::*;
 use crate::union_find::TreeUnionFind;
 
 #[allow(clippy::needless_pass_by_value)] // Silences clippy warning. TODO: Update the parameter type to [`ArrayRef`](https://docs.rs/ndarray/latest/ndarray/struct.ArrayRef.html).
-pub fn mst_linkage<A: FloatCore>(
+pub(crate) fn mst_linkage<A: FloatCore>(
     input: ArrayView2<A>,
     metric: &dyn Metric<A>,
     core_distances: ArrayView1<A>,
@@ -101,7 +101,7 @@ pub fn mst_linkage<A: FloatCore>(
     unsafe { mst.assume_init() }
 }
 
-pub fn condense_mst<A: FloatCore + Div>(
+pub(crate) fn condense_mst<A: FloatCore + Div>(
     mst: &[(usize, usize, A, usize)],
     min_cluster_size: usize,
 ) -> Vec<(usize, usize, A, usize)> {
@@ -184,7 +184,7 @@ pub fn condense_mst<A: FloatCore + Div>(
 }
 
 #[allow(dead_code)]
-pub struct Boruvka<'a, A, M>
+pub(crate) struct Boruvka<'a, A, M>
 where
     A: FloatCore,
     M: Metric<A>,
@@ -194,20 +194,29 @@ where
     candidates: Candidates<A>,
     components: Components,
     core_distances: Array1<A>,
-    bounds: Vec<A>,
     mst: Vec<(usize, usize, A)>,
 }
 
+/// The number of subtrees of the query tree searched in parallel in each
+/// Borůvka round.
+const SEARCH_TASKS: usize = if cfg!(feature = "parallel") { 16 } else { 1 };
+
+/// The state of a search of the nearest components from a subtree of the
+/// query tree.
+struct Search<A> {
+    candidates: Candidates<A>,
+    bounds: Vec<A>,
+}
+
 #[allow(dead_code)]
 impl<'a, A, M> Boruvka<'a, A, M>
 where
     A: FloatCore + AddAssign + DivAssign + FromPrimitive + Sync + Send,
     M: Metric<A> + Sync + Send,
 {
-    pub fn new(db: BallTree<'a, A, M>, min_samples: usize) -> Self {
+    pub(crate) fn new(db: BallTree<'a, A, M>, min_samples: usize) -> Self {
         let mut candidates = Candidates::new(db.points.nrows());
         let components = Components::new(db.nodes.len(), db.points.nrows());
-        let bounds = vec![A::max_value(); db.nodes.len()];
         let core_distances = compute_core_distances(&db, min_samples, &mut candidates);
         let mst = Vec::with_capacity(db.points.nrows() - 1);
         Boruvka {
@@ -216,21 +225,65 @@ where
             candidates,
             components,
             core_distances,
-            bounds,
             mst,
         }
     }
 
-    pub fn min_spanning_tree(mut self) -> Array1<(usize, usize, A)> {
+    pub(crate) fn min_spanning_tree(mut self) -> Array1<(usize, usize, A)> {
         let mut num_components = self.update_components();
 
         while num_components > 1 {
-            self.traversal(0, 0);
+            self.search();
             num_components = self.update_components();
         }
         Array1::from_vec(self.mst)
     }
 
+    /// Finds the nearest other component of each component with a dual-tree
+    /// traversal.
+    ///
+    /// The query tree is split into up to `SEARCH_TASKS` subtrees, traversed
+    /// in parallel, each with its own copy of the candidates, and the closest
+    /// candidate of each component is kept, the first one found in the order
+    /// of the subtrees on ties, so that the tree does not depend on the
+    /// number of threads.
+    fn search(&mut self) {
+        let mut roots = vec![0];
+        while roots.len() < SEARCH_TASKS {
+            let next: Vec<usize> = roots
+                .iter()
+                .flat_map(|&n| match self.db.children_of(n) {
+                    Some((left, right)) => vec![left, right],
+                    None => vec![n],
+                })
+                .collect();
+            if next.len() == roots.len() {
+                break;
+            }
+            roots = next;
+        }
+        let found: Vec<Candidates<A>> = roots
+            .par_iter()
+            .map(|&query| {
+                let mut search = Search {
+                    candidates: self.candidates.clone(),
+                    bounds: vec![A::max_value(); self.db.num_nodes()],
+                };
+                self.traversal(&mut search, query, 0);
+                search.candidates
+            })
+            .collect();
+        for candidates in found {
+            for i in 0..candidates.distances.len() {
+                if candidates.distances[i] < self.candidates.distances[i] {
+                    self.candidates.distances[i] = candidates.distances[i];
+                    self.candidates.points[i] = candidates.points[i];
+                    self.candidates.neighbors[i] = candidates.neighbors[i];
+                }
+            }
+        }
+    }
+
     fn update_components(&mut self) -> usize {
         let components = self.components.get_current();
         for i in components {
@@ -275,14 +328,13 @@ where
                 }
             }
         }
-        self.reset_bounds();
         self.components.len()
     }
 
-    fn traversal(&mut self, query: usize, reference: usize) {
+    fn traversal(&self, search: &mut Search<A>, query: usize, reference: usize) {
         // prune min{||query - ref||} >= bound_query
         let node_dist = self.db.node_distance_lower_bound(query, reference);
-        if node_dist >= self.bounds[query] {
+        if node_dist >= search.bounds[query] {
             return;
         }
         // prune when query and ref are in the same component
@@ -305,14 +357,14 @@ where
                     let c1 = self.components.point[i];
                     // mreach(i, j) >= core_i > candidate[c1]
                     // i.e. current best candidate for component c1 => prune
-                    if self.core_distances[i] > self.candidates.distances[c1] {
+                    if self.core_distances[i] > search.candidates.distances[c1] {
                         continue;
                     }
                     for &j in self.db.points_of(reference) {
                         let c2 = self.components.point[j];
                         // mreach(i, j) >= core_j > candidate[c1] => prune
                         // i, j in the same component => prune
-                        if self.core_distances[j] > self.candidates.distances[c1] || c1 == c2 {
+                        if self.core_distances[j] > search.candidates.distances[c1] || c1 == c2 {
                             continue;
                         }
 
@@ -327,27 +379,27 @@ where
                             mreach = self.core_distances[i];
                         }
 
-                        if mreach < self.candidates.distances[c1] {
-                            self.candidates.update(c1, (i, j, mreach));
+                        if mreach < search.candidates.distances[c1] {
+                            search.candidates.update(c1, (i, j, mreach));
                         }
                     }
-                    if self.candidates.distances[c1] > upper {
-                        upper = self.candidates.distances[c1];
+                    if search.candidates.distances[c1] > upper {
+                        upper = search.candidates.distances[c1];
                     }
                 }
 
                 // Use only the upper bound (max candidate distance) for
                 // simplicity and performance.
-                if upper < self.bounds[query] {
-                    self.bounds[query] = upper;
+                if upper < search.bounds[query] {
+                    search.bounds[query] = upper;
                     let mut cur = query;
                     while cur > 0 {
                         let p = (cur - 1) / 2;
-                        let new_bound = self.bound(p);
-                        if new_bound >= self.bounds[p] {
+                        let new_bound = search.bound(p);
+                        if new_bound >= search.bounds[p] {
                             break;
                         }
-                        self.bounds[p] = new_bound;
+                        search.bounds[p] = new_bound;
                         cur = p;
                     }
                 }
@@ -358,31 +410,29 @@ where
                 let right_bound = self.db.node_distance_lower_bound(query, right);
 
                 if left_bound < right_bound {
-                    self.traversal(query, left);
-                    self.traversal(query, right);
+                    self.traversal(search, query, left);
+                    self.traversal(search, query, right);
                 } else {
-                    self.traversal(query, right);
-                    self.traversal(query, left);
+                    self.traversal(search, query, right);
+                    self.traversal(search, query, left);
                 }
             }
             (Some((left, right)), _, _) => {
                 let left_bound = self.db.node_distance_lower_bound(reference, left);
                 let right_bound = self.db.node_distance_lower_bound(reference, right);
                 if left_bound < right_bound {
-                    self.traversal(left, reference);
-                    self.traversal(right, reference);
+                    self.traversal(search, left, reference);
+                    self.traversal(search, right, reference);
                 } else {
-                    self.traversal(right, reference);
-                    self.traversal(left, reference);
+                    self.traversal(search, right, reference);
+                    self.traversal(search, left, reference);
                 }
             }
         }
     }
+}
 
-    fn reset_bounds(&mut self) {
-        self.bounds.iter_mut().for_each(|v| *v = A::max_value());
-    }
-
+impl<A: FloatCore> Search<A> {
     #[inline]
     fn bound(&self, parent: usize) -> A {
         let left = 2 * parent + 1;
@@ -414,6 +464,7 @@ where
         .zip(core_distances.iter_mut())
         .enumerate()
         .collect();
+    #[allow(clippy::needless_for_each)] // a parallel loop with the `parallel` feature
     rows.into_par_iter().for_each(|(i, (indices, dist))| {
         let row = db.points.row(i);
         let (idx, d) = db.query(&row, min_samples);
@@ -436,6 +487,7 @@ where
 }
 
 #[allow(dead_code)]
+#[derive(Clone)]
 struct Candidates<A> {
     points: Vec<u32>,
     neighbors: Vec<u32>,
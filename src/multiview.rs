@@ -0,0 +1,286 @@
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::agglomerative::pairwise_distances;
+use crate::parallel::prelude::*;
+
+/// How [`MultiView`] combines the distances between two points in each view.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ViewCombination {
+    /// The weighted sum of the distances, each divided by the mean distance
+    /// between the points of its view, so that views of different scales
+    /// are comparable.
+    #[default]
+    WeightedSum,
+
+    /// The weighted sum of the ranks of the distances: in each view, the
+    /// rank of point `j` among the points sorted by their distance from
+    /// point `i`, divided by the number of other points, averaged with the
+    /// rank of `i` from `j`. Ties share their mean rank. Ranks ignore the
+    /// scale and the distribution of the distances of each view, at the
+    /// cost of the triangle inequality.
+    RankAggregation,
+}
+
+/// A distance between points described by several feature matrices, or
+/// views, such as the gene expression and the methylation of the same
+/// samples in multi-omics, or the image and text embeddings of the same
+/// items.
+///
+/// Each view has its own metric and weight. The distances between two points
+/// in each view are combined as set by `combination`, and the matrix of the
+/// combined distances of [`MultiView::pairwise`] can be clustered with
+/// [`Dbscan::fit_precomputed`](crate::Dbscan::fit_precomputed),
+/// [`KMedoids::fit_precomputed`](crate::KMedoids::fit_precomputed) or
+/// [`AgglomerativeClustering::fit_precomputed`](crate::AgglomerativeClustering::fit_precomputed).
+/// Views with different kinds of metrics can use
+/// [`MetricConfig`](crate::MetricConfig).
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Dbscan, MetricConfig, MultiView};
+///
+/// let expression = array![[0., 0.], [0.1, 0.], [0., 0.1], [5., 5.], [5.1, 5.], [5., 5.1]];
+/// let embedding = array![[1., 0.], [1., 0.1], [0.9, 0.], [0., 1.], [0.1, 1.], [0., 0.9]];
+///
+/// let mut views = MultiView::new(vec![MetricConfig::Euclidean, MetricConfig::Cosine]);
+/// views.weights = vec![1., 0.5];
+/// let distances = views.pairwise(&[expression, embedding]);
+///
+/// let mut dbscan = Dbscan::new(0.5, 2, MetricConfig::Euclidean);
+/// let (clusters, noise) = dbscan.fit_precomputed(&distances);
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MultiView<A, M> {
+    /// The metric of each view.
+    pub metrics: Vec<M>,
+
+    /// The weight of each view.
+    pub weights: Vec<A>,
+
+    /// How the distances of the views are combined.
+    pub combination: ViewCombination,
+}
+
+impl<A, M> MultiView<A, M>
+where
+    A: Float + FromPrimitive + Send + Sync,
+    M: Metric<A> + Sync,
+{
+    /// Creates the distance with one view for each metric, all of weight
+    /// one, combined by [`ViewCombination::WeightedSum`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{MultiView, ViewCombination};
+    ///
+    /// let views = MultiView::<f64, _>::new(vec![Euclidean::default(), Euclidean::default()]);
+    /// assert_eq!(views.weights, [1., 1.]);
+    /// assert_eq!(views.combination, ViewCombination::WeightedSum);
+    /// ```
+    #[must_use]
+    pub fn new(metrics: Vec<M>) -> Self {
+        Self {
+            weights: vec![A::one(); metrics.len()],
+            metrics,
+            combination: ViewCombination::default(),
+        }
+    }
+
+    /// Returns the matrix of the combined distances between all pairs of
+    /// points, where row `i` of each of `views` describes point `i`.
+    ///
+    /// # Panics
+    /// Panics if there is not one view for each metric and weight, if the
+    /// views do not have the same number of rows, or if a weight is negative
+    /// or not finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{MetricConfig, MultiView};
+    ///
+    /// let expression = array![[0., 0.], [0.1, 0.], [5., 5.]];
+    /// let embedding = array![[1., 0.], [1., 0.1], [0., 1.]];
+    /// let views = MultiView::new(vec![MetricConfig::Euclidean, MetricConfig::Cosine]);
+    /// let distances = views.pairwise(&[expression, embedding]);
+    ///
+    /// assert_eq!(distances.dim(), (3, 3));
+    /// assert_eq!(distances[[0, 0]], 0.);
+    /// assert!(distances[[0, 1]] < distances[[0, 2]]);
+    /// ```
+    pub fn pairwise<S>(&self, views: &[ArrayBase<S, Ix2>]) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(
+            views.len() == self.metrics.len() && views.len() == self.weights.len(),
+            "there should be one metric and one weight for each view"
+        );
+        assert!(
+            self.weights
+                .iter()
+                .all(|w| *w >= A::zero() && w.is_finite()),
+            "the weights should be non-negative and finite"
+        );
+        let n = views.first().map_or(0, ArrayBase::nrows);
+        assert!(
+            views.iter().all(|view| view.nrows() == n),
+            "the views should have the same number of points"
+        );
+
+        let mut combined = Array2::zeros((n, n));
+        for ((view, metric), weight) in views.iter().zip(&self.metrics).zip(&self.weights) {
+            let distances = pairwise_distances(&view.view(), metric);
+            let distances = match self.combination {
+                ViewCombination::WeightedSum => scaled(distances),
+                ViewCombination::RankAggregation => ranks(&distances),
+            };
+            combined.zip_mut_with(&distances, |c, d| *c = *c + *weight * *d);
+        }
+        combined
+    }
+}
+
+/// Divides the distances by their mean over the pairs of distinct points,
+/// unless it is zero.
+fn scaled<A: Float + FromPrimitive>(mut distances: Array2<A>) -> Array2<A> {
+    let n = distances.nrows();
+    if n < 2 {
+        return distances;
+    }
+    let total = distances.iter().fold(A::zero(), |acc, d| acc + *d);
+    let mean = total / A::from_usize(n * (n - 1)).expect("valid float");
+    if mean > A::zero() {
+        distances.mapv_inplace(|d| d / mean);
+    }
+    distances
+}
+
+/// Replaces the distances by their symmetrized ranks, between zero and one.
+fn ranks<A>(distances: &Array2<A>) -> Array2<A>
+where
+    A: Float + FromPrimitive + Send + Sync,
+{
+    let n = distances.nrows();
+    if n < 2 {
+        return Array2::zeros((n, n));
+    }
+    let others = A::from_usize(n - 1).expect("valid float");
+    let rows: Vec<_> = distances.rows().into_iter().collect();
+    let ranks: Vec<A> = rows
+        .par_iter()
+        .flat_map_iter(|row| {
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_by(|a, b| {
+                row[*a]
+                    .partial_cmp(&row[*b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let mut ranks = vec![A::zero(); n];
+            let mut start = 0;
+            while start < n {
+                let mut end = start + 1;
+                while end < n && row[order[end]] == row[order[start]] {
+                    end += 1;
+                }
+                // the mean of the positions `start..end`
+                let rank = A::from_usize(start + end - 1).expect("valid float")
+                    / A::from_usize(2).expect("valid float");
+                for &j in &order[start..end] {
+                    ranks[j] = rank / others;
+                }
+                start = end;
+            }
+            ranks
+        })
+        .collect();
+    let ranks = Array2::from_shape_vec((n, n), ranks).expect("n * n ranks");
+    let half = A::from_f64(0.5).expect("valid float");
+    Array2::from_shape_fn((n, n), |(i, j)| {
+        if i == j {
+            A::zero()
+        } else {
+            (ranks[[i, j]] + ranks[[j, i]]) * half
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::Dbscan;
+
+    #[test]
+    fn weighted_sum() {
+        let a = array![[0.], [1.], [3.]];
+        let b = array![[0.], [10.], [10.]];
+        let mut views = MultiView::new(vec![Euclidean::default(); 2]);
+        views.weights = vec![1., 2.];
+        let distances = views.pairwise(&[a, b]);
+
+        // the mean distances are 2 and 20/3
+        let expected = array![
+            [0., 0.5 + 3., 1.5 + 3.],
+            [0.5 + 3., 0., 1.],
+            [1.5 + 3., 1., 0.]
+        ];
+        assert!(distances
+            .iter()
+            .zip(&expected)
+            .all(|(d, e)| f64::abs(d - e) < 1e-12));
+    }
+
+    #[test]
+    fn rank_aggregation() {
+        let a = array![[0.], [1.], [3.], [7.]];
+        let distances = ranks(&pairwise_distances(&a.view(), &Euclidean::default()));
+        // from 1, the ranks of 0 and 2 are 1 and 2, and from 0 and 2, that
+        // of 1 is 1
+        assert!(f64::abs(distances[[1, 0]] - 1. / 3.) < 1e-12);
+        assert!(f64::abs(distances[[1, 2]] - 1.5 / 3.) < 1e-12);
+        assert!(f64::abs(distances[[0, 3]] - 1.) < 1e-12);
+        assert_eq!(distances, distances.t());
+
+        // ties share their mean rank
+        let distances = ranks(&array![[0., 1., 1.], [1., 0., 2.], [1., 2., 0.]]);
+        assert!(f64::abs(distances[[0, 1]] - (1.5 / 2. + 0.5) / 2.) < 1e-12);
+
+        // a view that separates the points only by scale still counts
+        let a = array![[0.], [0.001], [0.002], [1.], [1.001], [1.002]];
+        let b = array![
+            [0.],
+            [1000.],
+            [2000.],
+            [1_000_000.],
+            [1_001_000.],
+            [1_002_000.]
+        ];
+        let mut views = MultiView::new(vec![Euclidean::default(); 2]);
+        views.combination = ViewCombination::RankAggregation;
+        let distances = views.pairwise(&[a, b]);
+        let (clusters, noise) =
+            Dbscan::new(1., 3, Euclidean::default()).fit_precomputed(&distances);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "the views should have the same number of points")]
+    fn mismatched_views() {
+        let views = MultiView::new(vec![Euclidean::default(); 2]);
+        let _ = views.pairwise(&[array![[0.], [1.]], array![[0.]]]);
+    }
+}
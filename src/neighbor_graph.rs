@@ -0,0 +1,407 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::FiniteBallTree;
+use crate::parallel::prelude::*;
+use crate::trace::phase;
+
+/// The neighbors of each point of an input within a radius, with their
+/// distances, computed once and shared by the estimators fitted to the same
+/// input.
+///
+/// Fitting [`Dbscan`](crate::Dbscan) and [`Optics`](crate::Optics), or
+/// several parameterizations of them, to the same input builds a ball tree
+/// and searches the neighborhoods every time. Their `fit_with_graph` methods
+/// take the graph instead, for any `eps` up to its radius, and the metric
+/// and the input the graph was built with replace theirs.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, NeighborGraph, Optics};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let graph = NeighborGraph::new(&points, 3., &Euclidean::default());
+///
+/// let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit_with_graph(&graph);
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(noise, [5]);
+///
+/// let (clusters, noise) = Dbscan::new(0.5, 2, Euclidean::default()).fit_with_graph(&graph);
+/// assert_eq!(clusters.len(), 1);
+/// assert_eq!(noise, [0, 3, 4, 5]);
+///
+/// let (clusters, noise) = Optics::new(3., 2, Euclidean::default()).fit_with_graph(&graph);
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(noise, [5]);
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NeighborGraph<A> {
+    radius: A,
+
+    /// The neighbors of each point within `radius`, including the point
+    /// itself, with their distances, in increasing order of distance.
+    neighbors: Vec<Vec<(usize, A)>>,
+}
+
+impl<A> NeighborGraph<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Searches the neighbors of each point of `input` within `radius`.
+    ///
+    /// Points with a NaN or infinite coordinate have no neighbors, unless
+    /// `metric` compares them, as [`NanEuclidean`](crate::NanEuclidean)
+    /// does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::NeighborGraph;
+    ///
+    /// let graph = NeighborGraph::new(&array![[0.], [1.], [5.]], 2., &Euclidean::default());
+    /// assert_eq!(graph.neighbors(0), [(0, 0.), (1, 1.)]);
+    /// assert_eq!(graph.neighbors(2), [(2, 0.)]);
+    /// ```
+    pub fn new<S, M>(input: &ArrayBase<S, Ix2>, radius: A, metric: &M) -> Self
+    where
+        S: Data<Elem = A>,
+        M: Metric<A> + Clone + Sync,
+    {
+        if input.is_empty() {
+            return Self {
+                radius,
+                neighbors: Vec::new(),
+            };
+        }
+        let input = input.as_standard_layout();
+        let db = {
+            let _phase = phase!("index_build", n_points = input.nrows());
+            FiniteBallTree::new(&input.view(), metric.clone())
+        };
+        let _phase = phase!("neighborhoods", n_points = input.nrows());
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let neighbors = rows
+            .par_iter()
+            .map(|p| {
+                let mut neighbors: Vec<_> = db
+                    .query_radius(p, radius)
+                    .into_iter()
+                    .map(|j| (j, metric.distance(p, &rows[j])))
+                    .collect();
+                neighbors.sort_unstable_by(|a, b| {
+                    a.1.partial_cmp(&b.1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then(a.0.cmp(&b.0))
+                });
+                neighbors
+            })
+            .collect();
+        Self { radius, neighbors }
+    }
+}
+
+impl<A: FloatCore> NeighborGraph<A> {
+    /// Builds a graph from the neighbors of each point within `radius`,
+    /// including the point itself, in increasing order of distance.
+    #[cfg(feature = "gpu")]
+    pub(crate) fn from_neighbors(radius: A, neighbors: Vec<Vec<(usize, A)>>) -> Self {
+        Self { radius, neighbors }
+    }
+
+    /// Returns the radius the neighbors were searched within.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::NeighborGraph;
+    ///
+    /// let graph = NeighborGraph::new(&array![[0.], [1.]], 2., &Euclidean::default());
+    /// assert_eq!(graph.radius(), 2.);
+    /// ```
+    #[must_use]
+    pub fn radius(&self) -> A {
+        self.radius
+    }
+
+    /// Returns the number of points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::NeighborGraph;
+    ///
+    /// let graph = NeighborGraph::new(&array![[0.], [1.], [5.]], 2., &Euclidean::default());
+    /// assert_eq!(graph.n_points(), 3);
+    /// ```
+    #[must_use]
+    pub fn n_points(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// Returns the neighbors of point `i`, including the point itself, with
+    /// their distances, in increasing order of distance.
+    ///
+    /// # Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::NeighborGraph;
+    ///
+    /// let graph = NeighborGraph::new(&array![[0.], [1.], [1.5]], 2., &Euclidean::default());
+    /// assert_eq!(graph.neighbors(2), [(2, 0.), (1, 0.5), (0, 1.5)]);
+    ///
+    /// ```
+    #[must_use]
+    pub fn neighbors(&self, i: usize) -> &[(usize, A)] {
+        &self.neighbors[i]
+    }
+
+    /// Returns the neighbors of point `i` within `eps`, with their
+    /// distances.
+    pub(crate) fn within(&self, i: usize, eps: A) -> &[(usize, A)] {
+        let neighbors = &self.neighbors[i];
+        &neighbors[..neighbors.partition_point(|(_, d)| *d <= eps)]
+    }
+
+    /// Returns the neighborhood of each point within `eps`.
+    ///
+    /// # Panics
+    /// Panics if `eps` is greater than the radius of the graph.
+    pub(crate) fn neighborhoods(&self, eps: A) -> Vec<Vec<usize>> {
+        assert!(
+            eps <= self.radius,
+            "eps cannot be greater than the radius of the graph"
+        );
+        (0..self.neighbors.len())
+            .map(|i| self.within(i, eps).iter().map(|(j, _)| *j).collect())
+            .collect()
+    }
+
+    /// Returns the distance between points `i` and `j`, or infinity if they
+    /// are not neighbors.
+    pub(crate) fn distance(&self, i: usize, j: usize) -> A {
+        self.neighbors[i]
+            .iter()
+            .find(|(k, _)| *k == j)
+            .map_or_else(A::infinity, |(_, d)| *d)
+    }
+}
+
+/// The `k` nearest neighbors of each point of an input, returned by
+/// [`knn_graph`].
+///
+/// The arrays can be passed to
+/// [`HDbscan::fit_from_knn`](crate::HDbscan::fit_from_knn), and the graph
+/// written as an edge list for graph tools with
+/// [`write_edge_list`](Self::write_edge_list).
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnnGraph<A> {
+    /// Row `i` lists the neighbors of point `i`, the point itself first.
+    pub indices: Array2<usize>,
+
+    /// The distances from each point to the neighbors in `indices`, in
+    /// increasing order.
+    pub distances: Array2<A>,
+}
+
+impl<A: Display> KnnGraph<A> {
+    /// Writes the graph as a weighted edge list, one `source target
+    /// distance` line per edge from a point to one of its neighbors other
+    /// than itself, as read by `networkx.read_weighted_edgelist` and
+    /// `igraph.Graph.Read_Ncol`. An edge appears twice if each point is a
+    /// neighbor of the other.
+    ///
+    /// # Errors
+    /// Returns an error if writing to `writer` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::knn_graph;
+    ///
+    /// let graph = knn_graph(&array![[0.], [1.], [3.]], 2, &Euclidean::default());
+    /// let mut edges = Vec::new();
+    /// graph.write_edge_list(&mut edges).unwrap();
+    ///
+    /// assert_eq!(String::from_utf8(edges).unwrap(), "0 1 1\n1 0 1\n2 1 2\n");
+    /// ```
+    pub fn write_edge_list<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (i, (neighbors, distances)) in self
+            .indices
+            .rows()
+            .into_iter()
+            .zip(self.distances.rows())
+            .enumerate()
+        {
+            for (j, d) in neighbors.iter().zip(distances) {
+                if *j != i {
+                    writeln!(writer, "{i} {j} {d}")?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
+
+/// Finds the `k` nearest neighbors of each point of `input`, counting the
+/// point itself, in parallel.
+///
+/// Row `i` of the returned graph lists the neighbors of point `i` and their
+/// distances, in increasing order of distance, with point `i` itself first
+/// at distance zero. Points have fewer neighbors than `k` only if the input
+/// has fewer points, in which case every row lists all of them. Points with
+/// no distance to point `i`, as those with a NaN coordinate, make up the rest
+/// of its row in index order, at infinity.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::knn_graph;
+///
+/// let graph = knn_graph(&array![[0.], [1.], [3.]], 2, &Euclidean::default());
+/// assert_eq!(graph.indices, array![[0, 1], [1, 0], [2, 1]]);
+/// assert_eq!(graph.distances, array![[0., 1.], [0., 1.], [0., 2.]]);
+/// ```
+#[allow(clippy::missing_panics_doc)] // k neighbors per point
+pub fn knn_graph<S, A, M>(input: &ArrayBase<S, Ix2>, k: usize, metric: &M) -> KnnGraph<A>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    let k = k.min(input.nrows());
+    if k == 0 {
+        return KnnGraph {
+            indices: Array2::zeros((input.nrows(), 0)),
+            distances: Array2::zeros((input.nrows(), 0)),
+        };
+    }
+    let input = input.as_standard_layout();
+    let db = {
+        let _phase = phase!("index_build", n_points = input.nrows());
+        FiniteBallTree::new(&input.view(), metric.clone())
+    };
+    let _phase = phase!("neighborhoods", n_points = input.nrows());
+    let rows: Vec<Vec<(usize, A)>> = (0..input.nrows())
+        .into_par_iter()
+        .map(|i| {
+            // duplicates of the point may take its place in the query
+            let (indices, distances) = db.query(&input.row(i), k);
+            let mut row = vec![(i, A::zero())];
+            row.extend(
+                indices
+                    .into_iter()
+                    .zip(distances)
+                    .filter(|(j, _)| *j != i)
+                    .take(k - 1),
+            );
+            if row.len() < k {
+                let mut found = vec![false; input.nrows()];
+                for (j, _) in &row {
+                    found[*j] = true;
+                }
+                let padding = (0..input.nrows()).filter(|j| !found[*j]);
+                row.extend(padding.map(|j| (j, A::infinity())).take(k - row.len()));
+            }
+            row
+        })
+        .collect();
+    let shape = (input.nrows(), k);
+    let indices = rows.iter().flatten().map(|(j, _)| *j).collect();
+    let distances = rows.iter().flatten().map(|(_, d)| *d).collect();
+    KnnGraph {
+        indices: Array2::from_shape_vec(shape, indices).expect("k neighbors per point"),
+        distances: Array2::from_shape_vec(shape, distances).expect("k neighbors per point"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn neighbor_graph() {
+        let points = array![[0.], [1.], [3.], [f64::NAN]];
+        let graph = NeighborGraph::new(&points, 2., &Euclidean::default());
+        assert_eq!(graph.n_points(), 4);
+        assert_eq!(graph.neighbors(1), [(1, 0.), (0, 1.), (2, 2.)]);
+        assert!(graph.neighbors(3).is_empty());
+        assert_eq!(
+            graph.neighborhoods(1.),
+            [vec![0, 1], vec![1, 0], vec![2], vec![]]
+        );
+        assert!(f64::abs(graph.distance(2, 1) - 2.) < 1e-12);
+        assert!(graph.distance(0, 2).is_infinite());
+    }
+
+    #[test]
+    fn knn_graph() {
+        let points = array![[0., 0.], [0., 0.], [1., 0.], [5., 0.]];
+        let graph = super::knn_graph(&points, 3, &Euclidean::default());
+        assert_eq!(graph.indices.column(0), array![0, 1, 2, 3]);
+        assert_eq!(graph.indices.row(1), array![1, 0, 2]);
+        assert_eq!(graph.distances.row(3), array![0., 4., 5.]);
+
+        let graph = super::knn_graph(&points, 10, &Euclidean::default());
+        assert_eq!(graph.indices.dim(), (4, 4));
+        let graph = super::knn_graph(&Array2::<f64>::zeros((0, 1)), 2, &Euclidean::default());
+        assert_eq!(graph.indices.dim(), (0, 0));
+    }
+
+    #[test]
+    fn knn_graph_duplicates() {
+        let points = array![[0.], [0.], [0.], [0.], [1.]];
+        let graph = super::knn_graph(&points, 2, &Euclidean::default());
+        for i in 0..4 {
+            assert_eq!(graph.indices[[i, 0]], i);
+            assert_ne!(graph.indices[[i, 1]], i);
+            assert_eq!(graph.distances.row(i), array![0., 0.]);
+        }
+        assert_eq!(graph.indices.row(4), array![4, 0]);
+    }
+
+    #[test]
+    fn knn_graph_nan() {
+        let points = array![[0.], [f64::NAN], [1.], [3.]];
+        let graph = super::knn_graph(&points, 4, &Euclidean::default());
+        assert_eq!(graph.indices.row(0), array![0, 2, 3, 1]);
+        assert_eq!(graph.distances.row(0), array![0., 1., 3., f64::INFINITY]);
+        assert_eq!(graph.indices.row(1), array![1, 0, 2, 3]);
+        assert!(graph.distances[[1, 0]].abs() < f64::EPSILON);
+        assert!(graph
+            .distances
+            .row(1)
+            .iter()
+            .skip(1)
+            .all(|d| d.is_infinite()));
+
+        let graph = super::knn_graph(&points, 2, &Euclidean::default());
+        assert_eq!(graph.indices, array![[0, 2], [1, 0], [2, 0], [3, 2]]);
+    }
+}
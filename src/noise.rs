@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{ArrayView2, Axis};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use petal_neighbors::BallTree;
+
+use crate::parallel::prelude::*;
+
+/// Moves each noise point to the cluster of its nearest anchor, if that is
+/// within `max_distance`, where `anchors` lists points of `input` with the
+/// cluster they represent.
+///
+/// The reassigned points are appended to their clusters, and the remaining
+/// noise points are returned, in increasing order. Ties between anchors are
+/// broken arbitrarily.
+pub(crate) fn assign_noise<A, M>(
+    input: &ArrayView2<A>,
+    (mut clusters, mut noise): (HashMap<usize, Vec<usize>>, Vec<usize>),
+    anchors: &[(usize, usize)],
+    metric: &M,
+    max_distance: A,
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    let mut anchors: Vec<_> = anchors
+        .iter()
+        .filter(|(i, _)| input.row(*i).iter().all(|v| v.is_finite()))
+        .copied()
+        .collect();
+    anchors.sort_unstable();
+    let points: Vec<_> = anchors.iter().map(|(i, _)| *i).collect();
+    let Ok(tree) = BallTree::new(input.select(Axis(0), &points), metric.clone()) else {
+        return (clusters, noise);
+    };
+
+    noise.sort_unstable();
+    let nearest: Vec<Option<usize>> = noise
+        .par_iter()
+        .map(|&i| {
+            let point = input.row(i);
+            if !point.iter().all(|v| v.is_finite()) {
+                return None;
+            }
+            let (j, distance) = tree.query_nearest(&point);
+            (distance <= max_distance).then(|| anchors[j].1)
+        })
+        .collect();
+
+    let mut remaining = Vec::new();
+    for (i, cluster) in noise.into_iter().zip(nearest) {
+        match cluster {
+            Some(id) => clusters.entry(id).or_default().push(i),
+            None => remaining.push(i),
+        }
+    }
+    (clusters, remaining)
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn assign_noise() {
+        let data = array![
+            [0., 0.],
+            [1., 0.],
+            [10., 0.],
+            [3., 0.],
+            [7., 0.],
+            [f64::NAN, 0.],
+            [30., 0.]
+        ];
+        let clusters = HashMap::from([(0, vec![0, 1]), (1, vec![2])]);
+        let anchors = [(1, 0), (2, 1), (0, 0)];
+        let (clusters, noise) = super::assign_noise(
+            &data.view(),
+            (clusters, vec![6, 5, 4, 3]),
+            &anchors,
+            &Euclidean::default(),
+            5.,
+        );
+        assert_eq!(
+            clusters,
+            HashMap::from([(0, vec![0, 1, 3]), (1, vec![2, 4])])
+        );
+        assert_eq!(noise, [5, 6]);
+
+        let (clusters, noise) = super::assign_noise(
+            &data.view(),
+            (HashMap::new(), vec![0, 1]),
+            &[],
+            &Euclidean::default(),
+            5.,
+        );
+        assert!(clusters.is_empty());
+        assert_eq!(noise, [0, 1]);
+    }
+}
@@ -1,16 +1,23 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ops::{AddAssign, DivAssign};
 
-use ndarray::{Array, ArrayBase, Data, Ix2};
+use ndarray::{ArrayBase, Axis, Data, Ix1, Ix2};
 use num_traits::{float::FloatCore, FromPrimitive};
 use petal_neighbors::{
     distance::{Euclidean, Metric},
     BallTree,
 };
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use super::Fit;
+use crate::dendrogram::Dendrogram;
+use crate::diagnostics::finite_rows;
+use crate::interrupt::{unchecked, LimitExceeded, Limits, CHUNK_SIZE};
+use crate::neighbor_graph::NeighborGraph;
+use crate::noise::assign_noise;
+use crate::parallel::prelude::*;
+use crate::trace::{counts, phase};
 
 /// OPTICS (ordering points to identify the clustering structure) clustering
 /// algorithm.
@@ -40,7 +47,22 @@ pub struct Optics<A, M> {
     /// The metric to compute distance between the entries.
     pub metric: M,
 
+    /// Whether to query the neighbors of each point when it is processed
+    /// instead of keeping every neighborhood in memory during the fit. This
+    /// takes memory linear in the number of points even when neighborhoods
+    /// are huge, e.g. with a large `eps`, at the cost of querying each
+    /// neighborhood twice.
+    #[serde(default)]
+    pub low_memory: bool,
+
     ordered: Vec<usize>,
+    #[serde(
+        with = "crate::persistence::non_finite_vec",
+        bound(
+            serialize = "A: FloatCore + Serialize",
+            deserialize = "A: FloatCore + Deserialize<'de>"
+        )
+    )]
     reachability: Vec<A>,
     neighborhoods: Vec<Neighborhood<A>>,
 }
@@ -54,6 +76,7 @@ where
             eps: A::from(0.5_f32).expect("valid float"),
             min_samples: 5,
             metric: Euclidean::default(),
+            low_memory: false,
             ordered: vec![],
             reachability: vec![],
             neighborhoods: vec![],
@@ -66,43 +89,261 @@ where
     A: FloatCore,
     M: Metric<A>,
 {
+    /// Creates a model with neighborhoods of radius `eps` and dense regions of
+    /// at least `min_samples` points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let optics = Optics::<f64, _>::new(4.5, 2, Euclidean::default());
+    /// assert_eq!(optics.min_samples, 2);
+    /// assert!(!optics.low_memory);
+    /// ```
     #[must_use]
     pub fn new(eps: A, min_samples: usize, metric: M) -> Self {
         Self {
             eps,
             min_samples,
             metric,
+            low_memory: false,
             ordered: vec![],
             reachability: vec![],
             neighborhoods: vec![],
         }
     }
 
+    /// Builds a fitted model from its parts, so that the extraction can be
+    /// fuzzed with arbitrary orderings and reachability distances.
+    #[cfg(feature = "fuzzing")]
+    pub(crate) fn from_parts(
+        eps: A,
+        min_samples: usize,
+        metric: M,
+        ordered: Vec<usize>,
+        reachability: Vec<A>,
+        neighborhoods: Vec<(Vec<usize>, A)>,
+    ) -> Self {
+        Self {
+            eps,
+            min_samples,
+            metric,
+            low_memory: false,
+            ordered,
+            reachability,
+            neighborhoods: neighborhoods
+                .into_iter()
+                .map(|(neighbors, core_distance)| Neighborhood {
+                    size: neighbors.len(),
+                    neighbors,
+                    core_distance,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the points of the last fit in the cluster ordering, or an
+    /// empty slice if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [8., 7.], [2., 2.3], [8., 8.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    ///
+    /// let ordering = optics.ordering();
+    /// assert_eq!(ordering.len(), points.nrows());
+    /// // the points of each cluster are next to each other
+    /// let position = |i| ordering.iter().position(|&j| j == i).unwrap();
+    /// assert_eq!(position(2).abs_diff(position(4)), 1);
+    /// ```
+    #[must_use]
+    pub fn ordering(&self) -> &[usize] {
+        &self.ordered
+    }
+
+    /// Returns the reachability distance of each point of the last fit, by
+    /// point index, or an empty slice if the model has not been fitted. The
+    /// distance is NaN for the first point of each run of the ordering,
+    /// which is not reachable from the points before it.
+    ///
+    /// Plotting the distances in the order of [`Optics::ordering`] gives the
+    /// reachability plot, where clusters are valleys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    ///
+    /// let plot: Vec<f64> = optics.ordering().iter().map(|&i| optics.reachability()[i]).collect();
+    /// assert!(plot[0].is_nan());
+    /// assert_eq!(plot.iter().filter(|d| **d > 3.).count(), 1);
+    /// ```
+    #[must_use]
+    pub fn reachability(&self) -> &[A] {
+        &self.reachability
+    }
+
+    /// Returns the hierarchy of the reachability plot of the last fit, or an
+    /// empty hierarchy if the model has not been fitted.
+    ///
+    /// Each point is merged with the points before it in the cluster
+    /// ordering at its reachability distance, so cutting the hierarchy at a
+    /// distance gives the runs of points whose reachability distances are
+    /// within it, as [`Optics::extract_clusters_and_noise`] does without
+    /// telling border points from noise. Points that are not reachable from
+    /// those before them are never merged with them, so the hierarchy is a
+    /// forest if the points do not all lie within `eps` of each other's
+    /// neighborhoods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    /// let (clusters, _) = optics.hierarchy().cut_at_distance(3.);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2]);
+    /// assert_eq!(clusters[&1], [3, 4]);
+    /// assert_eq!(clusters[&2], [5]);
+    /// ```
+    #[must_use]
+    pub fn hierarchy(&self) -> Dendrogram<A> {
+        self.fitted().hierarchy()
+    }
+
+    /// Extracts the clusters at radius `eps` from the cluster ordering of the
+    /// last fit, as [`Optics::extract_dbscan`] does, without checking that
+    /// `eps` is at most `self.eps`. Beyond `self.eps`, the clusters are those
+    /// at `self.eps`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    ///
+    /// let (clusters, noise) = optics.extract_clusters_and_noise(3.);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
     #[must_use]
     pub fn extract_clusters_and_noise(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
-        let mut noise = vec![];
-        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        self.fitted().extract_clusters_and_noise(eps)
+    }
 
-        for &id in &self.ordered {
-            if self.reachability[id].is_normal() && self.reachability[id] <= eps {
-                if clusters.is_empty() {
-                    noise.push(id);
-                } else {
-                    let Some(v) = clusters.get_mut(&(clusters.len() - 1)) else {
-                        unreachable!("`clusters` is not empty and its key is a sequence number");
-                    };
-                    v.push(id);
-                }
-            } else {
-                let n = &self.neighborhoods[id];
-                if n.neighbors.len() >= self.min_samples && n.core_distance <= eps {
-                    clusters.entry(clusters.len()).or_insert_with(|| vec![id]);
-                } else {
-                    noise.push(id);
-                }
-            }
+    /// Extracts the clustering that DBSCAN finds with radius `eps` and the
+    /// same `min_samples` from the cluster ordering of the last fit, as
+    /// ExtractDBSCAN-Clustering in the OPTICS paper, so that one fit replaces
+    /// DBSCAN fits for every radius up to `self.eps`.
+    ///
+    /// Walking the ordering, a point whose reachability distance is above
+    /// `eps` starts a new cluster if its core distance is at most `eps`,
+    /// and is noise otherwise; any other point joins the current cluster.
+    /// The core points and their clusters are those of DBSCAN. A border
+    /// point joins the cluster of the core point it was reached from, which
+    /// may not be the one [`Dbscan`](crate::Dbscan) assigns it to, and is
+    /// noise if it was first reached from farther than `eps`.
+    ///
+    /// Clusters are numbered from zero in the order of extraction, and their
+    /// members and the noise points are in the cluster ordering.
+    ///
+    /// # Panics
+    /// Panics if `eps` is greater than `self.eps`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    ///
+    /// let (clusters, noise) = optics.extract_dbscan(3.);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    ///
+    /// let (clusters, noise) = optics.extract_dbscan(0.5);
+    /// assert_eq!(clusters.len(), 1);
+    /// assert_eq!(noise.len(), 4);
+    /// ```
+    ///
+    /// # References
+    /// - Ankerst, Mihael, et al. "OPTICS: Ordering points to identify the clustering structure."
+    ///   ACM SIGMOD Record 28.2 (1999): 49-60.
+    #[must_use]
+    pub fn extract_dbscan(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.fitted().extract_dbscan(eps)
+    }
+
+    /// Extracts the clusters at `eps`, as [`Optics::extract_clusters_and_noise`]
+    /// does, keeping only those containing at least one of `seeds`, so that
+    /// the clusters are the regions density-connected to the seeds and every
+    /// other point is noise.
+    ///
+    /// Clusters are numbered from zero in the order of extraction, and noise
+    /// points are in increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 5.], [3., 6.], [8., 7.], [8., 8.], [7., 3.]];
+    /// let mut optics = Optics::new(4.5, 2, Euclidean::default());
+    /// optics.fit(&points, None);
+    ///
+    /// let (clusters, noise) = optics.extract_from_seeds(4.5, &[4]);
+    /// assert_eq!(clusters.len(), 1);
+    /// assert_eq!(clusters[&0], [3, 4, 5]);
+    /// assert_eq!(noise, [0, 1, 2]);
+    /// ```
+    #[must_use]
+    pub fn extract_from_seeds(
+        &self,
+        eps: A,
+        seeds: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.fitted().extract_from_seeds(eps, seeds)
+    }
+
+    fn fitted(&self) -> Fitted<'_, A, M> {
+        Fitted {
+            eps: self.eps,
+            min_samples: self.min_samples,
+            metric: &self.metric,
+            ordered: &self.ordered,
+            reachability: &self.reachability,
+            neighborhoods: &self.neighborhoods,
         }
-        (clusters, noise)
     }
 }
 
@@ -116,6 +357,7 @@ where
 /// A tuple containing:
 /// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
 /// - `Vec<usize>`: A vector of indices representing the noise points that do not belong to any cluster.
+///   Points with a NaN or infinite coordinate have no neighbors, so they are noise.
 impl<S, A, M>
     Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
     for Optics<A, M>
@@ -129,53 +371,754 @@ where
         input: &ArrayBase<S, Ix2>,
         _params: Option<&HashMap<usize, Vec<usize>>>,
     ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let model = self.fit_model(input);
+        self.extract_and_keep(model)
+    }
+}
+
+impl<A, M> Optics<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    /// Computes the cluster ordering of `input` without modifying `self`,
+    /// and returns it as a model from which clusterings can be extracted.
+    ///
+    /// Unlike [`Fit::fit`], this takes `&self`, so one configured `Optics`
+    /// can fit several inputs at the same time, e.g. from several threads,
+    /// each fit keeping its own model. Points with a NaN or infinite
+    /// coordinate have no neighbors, so they are noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let optics = Optics::new(4.5, 2, Euclidean::default());
+    /// let inputs = [
+    ///     array![[1., 2.], [2., 5.], [3., 6.], [8., 7.], [8., 8.], [7., 3.]],
+    ///     array![[1., 2.], [2., 2.], [2., 2.3], [25., 80.]],
+    /// ];
+    ///
+    /// let models: Vec<_> = std::thread::scope(|s| {
+    ///     let fits: Vec<_> = inputs
+    ///         .iter()
+    ///         .map(|input| s.spawn(|| optics.fit_model(input)))
+    ///         .collect();
+    ///     fits.into_iter().map(|fit| fit.join().unwrap()).collect()
+    /// });
+    ///
+    /// assert_eq!(models[0].extract_dbscan(4.5).0.len(), 2);
+    /// assert_eq!(models[1].extract_dbscan(4.5).1, [3]);
+    /// ```
+    pub fn fit_model<S>(&self, input: &ArrayBase<S, Ix2>) -> OpticsModel<A, M>
+    where
+        S: Data<Elem = A>,
+    {
+        unchecked(self.fit_model_checked(input, &mut |_, _, _| Ok(())))
+    }
+
+    /// Fits OPTICS unless it exceeds `limits`, in which case it is aborted
+    /// with a [`LimitExceeded`] error.
+    ///
+    /// The limits are checked before and after building the ball tree, after
+    /// each chunk of 4096 points in the neighbor search, and after each 4096
+    /// points ordered. The estimated memory covers the input, the ball tree,
+    /// the neighborhoods found, and the cluster ordering.
+    ///
+    /// # Errors
+    /// Returns [`LimitExceeded`] if the estimated memory or the time elapsed
+    /// exceeds `limits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Limit, Limits, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 5.], [3., 6.], [8., 7.], [8., 8.], [7., 3.]];
+    /// let mut optics = Optics::new(4.5, 2, Euclidean::default());
+    ///
+    /// let (clusters, _) = optics.fit_with_limits(&points, &Limits::default()).unwrap();
+    /// assert_eq!(clusters.len(), 2);
+    ///
+    /// let error = optics.fit_with_limits(&points, &Limits::new(Some(64), None)).unwrap_err();
+    /// assert_eq!(error.limit, Limit::Memory(64));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn fit_with_limits<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        limits: &Limits,
+    ) -> Result<(HashMap<usize, Vec<usize>>, Vec<usize>), LimitExceeded>
+    where
+        S: Data<Elem = A>,
+    {
+        let guard = limits.start();
+        let model = self.fit_model_checked(input, &mut |phase, processed, memory| {
+            guard.check(phase, processed, memory)
+        })?;
+        Ok(self.extract_and_keep(model))
+    }
+
+    /// Computes the cluster ordering of `input` as [`Optics::fit_model`]
+    /// does, calling `check` with the phase of the fit, the number of points
+    /// processed in it, and the estimated memory in use, at the points
+    /// documented in [`Optics::fit_with_limits`], and stopping if it returns
+    /// an error.
+    #[allow(clippy::missing_panics_doc)] // the input is not empty
+    fn fit_model_checked<S, E>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        check: &mut impl FnMut(&'static str, usize, usize) -> Result<(), E>,
+    ) -> Result<OpticsModel<A, M>, E>
+    where
+        S: Data<Elem = A>,
+    {
+        let mut model = OpticsModel {
+            eps: self.eps,
+            min_samples: self.min_samples,
+            metric: self.metric.clone(),
+            ordered: vec![],
+            reachability: vec![],
+            neighborhoods: vec![],
+        };
         if input.is_empty() {
-            return (HashMap::new(), vec![]);
+            return Ok(model);
+        }
+        if let Some(rows) = finite_rows(input) {
+            return self.fit_finite(input, &rows, check);
         }
 
-        self.neighborhoods = if input.is_standard_layout() {
-            build_neighborhoods(input, self.eps, self.metric.clone())
-        } else {
-            let input = Array::from_shape_vec(input.raw_dim(), input.iter().copied().collect())
-                .expect("valid shape");
-            build_neighborhoods(&input, self.eps, self.metric.clone())
+        let _phase = phase!("fit", n_points = input.nrows(), n_features = input.ncols());
+        let input = input.as_standard_layout();
+        let n = input.nrows();
+        // the input and the indices of the ball tree
+        let mut memory = input.len() * size_of::<A>() + 2 * n * size_of::<usize>();
+        check("indexing", 0, memory)?;
+        let db = {
+            let _phase = phase!("index_build", n_points = n);
+            BallTree::new(input.view(), self.metric.clone()).expect("non-empty array")
         };
-        let mut visited = vec![false; input.nrows()];
-        self.ordered = Vec::with_capacity(input.nrows());
-        self.reachability = vec![A::nan(); input.nrows()];
-        for (idx, n) in self.neighborhoods.iter().enumerate() {
-            if visited[idx] || n.neighbors.len() < self.min_samples {
-                continue;
-            }
-            process(
-                idx,
-                input,
+        let neighborhoods = {
+            let _phase = phase!("neighborhoods", n_points = n);
+            let mut counted = 0;
+            build_neighborhoods(
+                &db,
+                self.eps,
                 self.min_samples,
-                &self.metric,
-                &self.neighborhoods,
-                &mut self.ordered,
-                &mut self.reachability,
-                &mut visited,
-            );
+                !self.low_memory,
+                |neighborhoods| {
+                    memory += neighborhoods[counted..]
+                        .iter()
+                        .map(|n| {
+                            size_of::<Neighborhood<A>>() + n.neighbors.len() * size_of::<usize>()
+                        })
+                        .sum::<usize>();
+                    counted = neighborhoods.len();
+                    check("neighbor search", counted, memory)
+                },
+            )?
+        };
+        let neighbors_of = |i: usize| {
+            let neighbors = if self.low_memory {
+                Cow::Owned(db.query_radius(&input.row(i), self.eps))
+            } else {
+                Cow::Borrowed(neighborhoods[i].neighbors.as_slice())
+            };
+            let input = input.view();
+            (0..neighbors.len()).map(move |k| {
+                let o = neighbors[k];
+                (o, self.metric.distance(&input.row(o), &input.row(i)))
+            })
+        };
+        {
+            let _phase = phase!("ordering", n_points = n);
+            // the ordering, the reachability distances, the visited points,
+            // and the positions of the seeds
+            memory += n * (2 * size_of::<usize>() + size_of::<A>() + size_of::<bool>());
+            (model.ordered, model.reachability) = order(
+                &neighborhoods,
+                self.min_samples,
+                &neighbors_of,
+                |processed| check("ordering", processed, memory),
+            )?;
+        }
+        model.neighborhoods = neighborhoods;
+        Ok(model)
+    }
+
+    /// Fits OPTICS to the neighbors of a [`NeighborGraph`] within
+    /// `self.eps`, instead of searching them again, ignoring `self.metric`
+    /// and `self.low_memory`.
+    ///
+    /// The result is the same as that of `fit` on the input and with the
+    /// metric the graph was built with.
+    ///
+    /// # Panics
+    /// Panics if `self.eps` is greater than the radius of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{NeighborGraph, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 5.], [3., 6.], [8., 7.], [8., 8.], [7., 3.]];
+    /// let graph = NeighborGraph::new(&points, 4.5, &Euclidean::default());
+    /// let mut optics = Optics::new(4.5, 2, Euclidean::default());
+    /// let (clusters, noise) = optics.fit_with_graph(&graph);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// assert!(noise.is_empty());
+    /// ```
+    pub fn fit_with_graph(
+        &mut self,
+        graph: &NeighborGraph<A>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            self.eps <= graph.radius(),
+            "eps cannot be greater than the radius of the graph"
+        );
+        let _phase = phase!("fit", n_points = graph.n_points());
+        let neighborhoods: Vec<_> = (0..graph.n_points())
+            .map(|i| {
+                let neighbors = graph.within(i, self.eps);
+                // the neighbors are in increasing order of distance
+                let core_distance = if self.min_samples > 1 && neighbors.len() >= self.min_samples {
+                    neighbors[self.min_samples - 1].1
+                } else {
+                    A::zero()
+                };
+                Neighborhood {
+                    neighbors: vec![],
+                    core_distance,
+                    size: neighbors.len(),
+                }
+            })
+            .collect();
+        let (ordered, reachability) = {
+            let _phase = phase!("ordering", n_points = graph.n_points());
+            unchecked(order(
+                &neighborhoods,
+                self.min_samples,
+                &|i| graph.within(i, self.eps).iter().copied(),
+                |_| Ok(()),
+            ))
+        };
+        self.extract_and_keep(OpticsModel {
+            eps: self.eps,
+            min_samples: self.min_samples,
+            metric: self.metric.clone(),
+            ordered,
+            reachability,
+            neighborhoods,
+        })
+    }
+
+    /// Extracts the clusters at `self.eps` from `model`, a fit with the same
+    /// parameters, and keeps its cluster ordering.
+    fn extract_and_keep(
+        &mut self,
+        model: OpticsModel<A, M>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let _phase = phase!("extraction", n_points = model.reachability.len());
+        let (clusters, noise) = model.extract_clusters_and_noise(self.eps);
+        counts!(n_clusters = clusters.len(), n_noise = noise.len());
+        self.ordered = model.ordered;
+        self.reachability = model.reachability;
+        self.neighborhoods = model.neighborhoods;
+        (clusters, noise)
+    }
+
+    /// Assigns the noise points of a clustering of `input` extracted from
+    /// this model to the cluster of their nearest core point, if it is
+    /// within `max_distance`, for applications that need every point
+    /// labeled.
+    ///
+    /// The core points are those of the last fit, which should be on the
+    /// same input. Noise points farther than `max_distance` from every core
+    /// point, or with a non-finite coordinate, stay noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [11., 8.], [25., 80.]];
+    /// let mut optics = Optics::new(1.5, 2, Euclidean::default());
+    /// let clustering = optics.fit(&points, None);
+    /// assert_eq!(clustering.1, [5, 6]);
+    ///
+    /// let (clusters, noise) = optics.assign_noise_to_nearest(&points, clustering, 5.);
+    /// assert_eq!(clusters[&1], [3, 4, 5]);
+    /// assert_eq!(noise, [6]);
+    /// ```
+    pub fn assign_noise_to_nearest<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+        max_distance: A,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        self.fitted()
+            .assign_noise_to_nearest(input, clustering, max_distance)
+    }
+
+    /// Fits the points of `rows`, those whose coordinates are all finite,
+    /// and orders the others last, with no neighbors, so that they are noise.
+    fn fit_finite<S, E>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        rows: &[usize],
+        check: &mut impl FnMut(&'static str, usize, usize) -> Result<(), E>,
+    ) -> Result<OpticsModel<A, M>, E>
+    where
+        S: Data<Elem = A>,
+    {
+        let mut model = self.fit_model_checked(&input.select(Axis(0), rows), check)?;
+        let n = input.nrows();
+        let mut neighborhoods: Vec<_> = (0..n)
+            .map(|_| Neighborhood {
+                neighbors: vec![],
+                size: 0,
+                core_distance: A::nan(),
+            })
+            .collect();
+        let mut reachability = vec![A::nan(); n];
+        for (j, mut neighborhood) in model.neighborhoods.drain(..).enumerate() {
+            for i in &mut neighborhood.neighbors {
+                *i = rows[*i];
+            }
+            neighborhoods[rows[j]] = neighborhood;
+            reachability[rows[j]] = model.reachability[j];
+        }
+        let mut ordered: Vec<_> = model.ordered.iter().map(|&j| rows[j]).collect();
+        ordered.extend((0..n).filter(|i| rows.binary_search(i).is_err()));
+
+        model.ordered = ordered;
+        model.reachability = reachability;
+        model.neighborhoods = neighborhoods;
+        Ok(model)
+    }
+}
+
+/// The cluster ordering of an input, computed by [`Optics::fit_model`],
+/// from which clusterings at any radius up to that of the fit can be
+/// extracted.
+///
+/// The model keeps the parameters it was fitted with, so it does not borrow
+/// the [`Optics`] that computed it, and it can be saved and loaded on its
+/// own.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OpticsModel<A, M> {
+    eps: A,
+    min_samples: usize,
+    metric: M,
+    ordered: Vec<usize>,
+    #[serde(
+        with = "crate::persistence::non_finite_vec",
+        bound(
+            serialize = "A: FloatCore + Serialize, M: Serialize",
+            deserialize = "A: FloatCore + Deserialize<'de>, M: Deserialize<'de>"
+        )
+    )]
+    reachability: Vec<A>,
+    neighborhoods: Vec<Neighborhood<A>>,
+}
+
+impl<A, M> OpticsModel<A, M>
+where
+    A: FloatCore,
+    M: Metric<A>,
+{
+    /// Returns the radius of a neighborhood the model was fitted with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    /// assert_eq!(model.eps(), 10.);
+    /// ```
+    #[must_use]
+    pub fn eps(&self) -> A {
+        self.eps
+    }
+
+    /// Returns the minimum number of points of a dense region the model was
+    /// fitted with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    /// assert_eq!(model.min_samples(), 2);
+    /// ```
+    #[must_use]
+    pub fn min_samples(&self) -> usize {
+        self.min_samples
+    }
+
+    /// Returns the points in the cluster ordering, as [`Optics::ordering`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Fit, Optics};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+    /// let mut optics = Optics::new(10., 2, Euclidean::default());
+    /// let model = optics.fit_model(&points);
+    /// optics.fit(&points, None);
+    /// assert_eq!(model.ordering(), optics.ordering());
+    /// ```
+    #[must_use]
+    pub fn ordering(&self) -> &[usize] {
+        &self.ordered
+    }
+
+    /// Returns the reachability distance of each point, by point index, as
+    /// [`Optics::reachability`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1_f64, 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    /// assert!(model.reachability()[model.ordering()[0]].is_nan());
+    /// ```
+    #[must_use]
+    pub fn reachability(&self) -> &[A] {
+        &self.reachability
+    }
+
+    /// Returns the hierarchy of the reachability plot, as
+    /// [`Optics::hierarchy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    /// let (clusters, _) = model.hierarchy().cut_at_distance(3.);
+    /// assert_eq!(clusters.len(), 3);
+    /// ```
+    #[must_use]
+    pub fn hierarchy(&self) -> Dendrogram<A> {
+        self.fitted().hierarchy()
+    }
+
+    /// Extracts the clusters at radius `eps`, as
+    /// [`Optics::extract_clusters_and_noise`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    ///
+    /// // beyond the radius of the fit, the clusters are those at that radius
+    /// assert_eq!(model.extract_clusters_and_noise(100.), model.extract_clusters_and_noise(10.));
+    /// ```
+    #[must_use]
+    pub fn extract_clusters_and_noise(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.fitted().extract_clusters_and_noise(eps)
+    }
+
+    /// Extracts the clustering that DBSCAN finds with radius `eps`, as
+    /// [`Optics::extract_dbscan`].
+    ///
+    /// # Panics
+    /// Panics if `eps` is greater than [`OpticsModel::eps`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    ///
+    /// let (clusters, noise) = model.extract_dbscan(3.);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert_eq!(noise, [5]);
+    /// ```
+    #[must_use]
+    pub fn extract_dbscan(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.fitted().extract_dbscan(eps)
+    }
+
+    /// Extracts the clusters at `eps` containing at least one of `seeds`, as
+    /// [`Optics::extract_from_seeds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let model = Optics::new(10., 2, Euclidean::default()).fit_model(&points);
+    ///
+    /// let (clusters, noise) = model.extract_from_seeds(3., &[0]);
+    /// assert_eq!(clusters[&0], [0, 1, 2]);
+    /// assert_eq!(noise, [3, 4, 5]);
+    /// ```
+    #[must_use]
+    pub fn extract_from_seeds(
+        &self,
+        eps: A,
+        seeds: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.fitted().extract_from_seeds(eps, seeds)
+    }
+
+    /// Assigns the noise points of a clustering of `input`, the input the
+    /// model was fitted to, to the cluster of their nearest core point, as
+    /// [`Optics::assign_noise_to_nearest`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Optics;
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [11., 8.]];
+    /// let model = Optics::new(1.5, 2, Euclidean::default()).fit_model(&points);
+    /// let clustering = model.extract_dbscan(1.5);
+    /// assert_eq!(clustering.1, [5]);
+    ///
+    /// let (_, noise) = model.assign_noise_to_nearest(&points, clustering, 5.);
+    /// assert!(noise.is_empty());
+    ///
+    /// ```
+    pub fn assign_noise_to_nearest<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+        max_distance: A,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        A: AddAssign + DivAssign + FromPrimitive + Sync,
+        S: Data<Elem = A>,
+        M: Clone + Sync,
+    {
+        self.fitted()
+            .assign_noise_to_nearest(input, clustering, max_distance)
+    }
+
+    fn fitted(&self) -> Fitted<'_, A, M> {
+        Fitted {
+            eps: self.eps,
+            min_samples: self.min_samples,
+            metric: &self.metric,
+            ordered: &self.ordered,
+            reachability: &self.reachability,
+            neighborhoods: &self.neighborhoods,
         }
-        self.extract_clusters_and_noise(self.eps)
     }
 }
 
+/// The state of a fitted model, borrowed from an [`Optics`] or an
+/// [`OpticsModel`], which extracts clusterings from it.
+struct Fitted<'a, A, M> {
+    eps: A,
+    min_samples: usize,
+    metric: &'a M,
+    ordered: &'a [usize],
+    reachability: &'a [A],
+    neighborhoods: &'a [Neighborhood<A>],
+}
+
+impl<A, M> Fitted<'_, A, M>
+where
+    A: FloatCore,
+    M: Metric<A>,
+{
+    fn hierarchy(&self) -> Dendrogram<A> {
+        let edges: Vec<_> = self
+            .ordered
+            .windows(2)
+            .filter(|pair| !self.reachability[pair[1]].is_nan())
+            .map(|pair| (pair[0], pair[1], self.reachability[pair[1]]))
+            .collect();
+        Dendrogram::from_edges(self.reachability.len(), &edges)
+    }
+
+    fn extract_clusters_and_noise(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let mut noise = vec![];
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for &id in self.ordered {
+            if self.reachability[id] <= eps {
+                if clusters.is_empty() {
+                    noise.push(id);
+                } else {
+                    let Some(v) = clusters.get_mut(&(clusters.len() - 1)) else {
+                        unreachable!("`clusters` is not empty and its key is a sequence number");
+                    };
+                    v.push(id);
+                }
+            } else {
+                let n = &self.neighborhoods[id];
+                if n.len() >= self.min_samples && n.core_distance <= eps {
+                    clusters.entry(clusters.len()).or_insert_with(|| vec![id]);
+                } else {
+                    noise.push(id);
+                }
+            }
+        }
+        (clusters, noise)
+    }
+
+    fn extract_dbscan(&self, eps: A) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            eps <= self.eps,
+            "eps cannot be greater than the radius of the fit"
+        );
+        self.extract_clusters_and_noise(eps)
+    }
+
+    fn extract_from_seeds(
+        &self,
+        eps: A,
+        seeds: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let (clusters, _) = self.extract_clusters_and_noise(eps);
+        let mut clusters: Vec<_> = clusters.into_iter().collect();
+        clusters.sort_unstable_by_key(|(id, _)| *id);
+
+        let mut seeded = HashMap::new();
+        let mut in_cluster = vec![false; self.neighborhoods.len()];
+        for (_, members) in clusters {
+            if members.iter().any(|i| seeds.contains(i)) {
+                for &i in &members {
+                    in_cluster[i] = true;
+                }
+                seeded.insert(seeded.len(), members);
+            }
+        }
+        let noise = (0..in_cluster.len()).filter(|i| !in_cluster[*i]).collect();
+        (seeded, noise)
+    }
+
+    fn assign_noise_to_nearest<S>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        clustering: (HashMap<usize, Vec<usize>>, Vec<usize>),
+        max_distance: A,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        A: AddAssign + DivAssign + FromPrimitive + Sync,
+        S: Data<Elem = A>,
+        M: Clone + Sync,
+    {
+        let cores: Vec<_> = clustering
+            .0
+            .iter()
+            .flat_map(|(id, members)| members.iter().map(|i| (*i, *id)))
+            .filter(|(i, _)| self.neighborhoods[*i].len() >= self.min_samples)
+            .collect();
+        assign_noise(&input.view(), clustering, &cores, self.metric, max_distance)
+    }
+}
+
+/// Returns the cluster ordering of the points and their reachability
+/// distances, NaN for the points not reachable from any core point, where
+/// `neighbors_of(i)` returns the neighbors of point `i` with their distances
+/// from it.
+///
+/// `check` is called with the number of points ordered before the ordering
+/// and after each [`CHUNK_SIZE`] points, and the ordering stops if it
+/// returns an error.
+fn order<A, I, E>(
+    neighborhoods: &[Neighborhood<A>],
+    min_samples: usize,
+    neighbors_of: &impl Fn(usize) -> I,
+    mut check: impl FnMut(usize) -> Result<(), E>,
+) -> Result<(Vec<usize>, Vec<A>), E>
+where
+    A: FloatCore,
+    I: IntoIterator<Item = (usize, A)>,
+{
+    let n = neighborhoods.len();
+    let mut visited = vec![false; n];
+    let mut ordered = Vec::with_capacity(n);
+    let mut reachability = vec![A::nan(); n];
+    check(0)?;
+    for (idx, neighborhood) in neighborhoods.iter().enumerate() {
+        if visited[idx] || neighborhood.len() < min_samples {
+            continue;
+        }
+        process(
+            idx,
+            min_samples,
+            neighborhoods,
+            neighbors_of,
+            &mut ordered,
+            &mut reachability,
+            &mut visited,
+            &mut check,
+        )?;
+    }
+    // points not reachable from any core point are noise
+    ordered.extend((0..n).filter(|i| !visited[*i]));
+    Ok((ordered, reachability))
+}
+
+/// Orders the points density-reachable from `idx`, where `neighbors_of(i)`
+/// returns the neighbors of point `i` with their distances from it, calling
+/// `check` as [`order`] does.
 #[allow(clippy::too_many_arguments)]
-fn process<S, A, M>(
+fn process<A, I, E>(
     idx: usize,
-    input: &ArrayBase<S, Ix2>,
     min_samples: usize,
-    metric: &M,
     neighborhoods: &[Neighborhood<A>],
+    neighbors_of: &impl Fn(usize) -> I,
     ordered: &mut Vec<usize>,
     reachability: &mut [A],
     visited: &mut [bool],
-) where
+    check: &mut impl FnMut(usize) -> Result<(), E>,
+) -> Result<(), E>
+where
     A: FloatCore,
-    S: Data<Elem = A>,
-    M: Metric<A>,
+    I: IntoIterator<Item = (usize, A)>,
 {
     let mut to_visit = vec![idx];
     while let Some(cur) = to_visit.pop() {
@@ -184,130 +1127,248 @@ fn process<S, A, M>(
         }
         visited[cur] = true;
         ordered.push(cur);
-        if neighborhoods[cur].neighbors.len() < min_samples {
+        if ordered.len() % CHUNK_SIZE == 0 {
+            check(ordered.len())?;
+        }
+        if neighborhoods[cur].len() < min_samples {
             continue;
         }
-        let mut seeds = vec![];
+        let mut seeds = SeedQueue::new(reachability.len());
         update(
-            cur,
-            &neighborhoods[cur],
-            input,
+            neighbors_of(cur),
+            neighborhoods[cur].core_distance,
             visited,
-            metric,
             &mut seeds,
             reachability,
         );
-        while let Some(s) = seeds.pop() {
+        while let Some(s) = seeds.pop(reachability) {
             if visited[s] {
                 continue;
             }
             visited[s] = true;
             ordered.push(s);
-            let n = &neighborhoods[s];
-            if n.neighbors.len() < min_samples {
+            if ordered.len() % CHUNK_SIZE == 0 {
+                check(ordered.len())?;
+            }
+            if neighborhoods[s].len() < min_samples {
                 continue;
             }
             update(
-                s,
-                &neighborhoods[s],
-                input,
+                neighbors_of(s),
+                neighborhoods[s].core_distance,
                 visited,
-                metric,
                 &mut seeds,
                 reachability,
             );
         }
     }
+    Ok(())
 }
 
-fn update<S, A, M>(
-    id: usize,
-    neighborhood: &Neighborhood<A>,
-    input: &ArrayBase<S, Ix2>,
+/// Lowers the reachability distance of the unvisited `neighbors` of a point
+/// to their distance from it, or its core distance if larger, adding them to
+/// `seeds` the first time they are reached.
+fn update<A: FloatCore>(
+    neighbors: impl IntoIterator<Item = (usize, A)>,
+    core_distance: A,
     visited: &[bool],
-    metric: &M,
-    seeds: &mut Vec<usize>,
+    seeds: &mut SeedQueue,
     reachability: &mut [A],
-) where
-    A: FloatCore,
-    S: Data<Elem = A>,
-    M: Metric<A>,
-{
-    for &o in &neighborhood.neighbors {
+) {
+    for (o, dist) in neighbors {
         if visited[o] {
             continue;
         }
-        let reachdist = reachability_distance(o, id, input, neighborhood, metric);
-        if !reachability[o].is_normal() {
+        let reachdist = if dist > core_distance {
+            dist
+        } else {
+            core_distance
+        };
+        if reachability[o].is_nan() {
             reachability[o] = reachdist;
-            seeds.push(o);
-        } else if reachability[o].lt(&reachdist) {
+            seeds.push(o, reachability);
+        } else if reachdist < reachability[o] {
             reachability[o] = reachdist;
+            seeds.decrease(o, reachability);
+        }
+    }
+}
+
+/// The points reached but not yet visited, in a binary min-heap keyed by
+/// their reachability distance, then by index, with the position of each
+/// point in the heap so that its key can be decreased in place.
+///
+/// Pushing, popping and decreasing a key take O(log n) time, instead of
+/// sorting all the seeds after every update.
+struct SeedQueue {
+    heap: Vec<usize>,
+    position: Vec<usize>,
+}
+
+impl SeedQueue {
+    fn new(n: usize) -> Self {
+        Self {
+            heap: Vec::new(),
+            position: vec![usize::MAX; n],
+        }
+    }
+
+    fn push<A: FloatCore>(&mut self, i: usize, keys: &[A]) {
+        self.position[i] = self.heap.len();
+        self.heap.push(i);
+        self.sift_up(self.heap.len() - 1, keys);
+    }
+
+    /// Restores the heap after the key of `i`, which is in the heap, was
+    /// lowered.
+    fn decrease<A: FloatCore>(&mut self, i: usize, keys: &[A]) {
+        self.sift_up(self.position[i], keys);
+    }
+
+    fn pop<A: FloatCore>(&mut self, keys: &[A]) -> Option<usize> {
+        let last = self.heap.pop()?;
+        let top = if self.heap.is_empty() {
+            last
+        } else {
+            let top = std::mem::replace(&mut self.heap[0], last);
+            self.position[last] = 0;
+            self.sift_down(0, keys);
+            top
+        };
+        self.position[top] = usize::MAX;
+        Some(top)
+    }
+
+    fn less<A: FloatCore>(keys: &[A], a: usize, b: usize) -> bool {
+        keys[a] < keys[b] || (keys[a] == keys[b] && a < b)
+    }
+
+    fn sift_up<A: FloatCore>(&mut self, mut pos: usize, keys: &[A]) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if !Self::less(keys, self.heap[pos], self.heap[parent]) {
+                break;
+            }
+            self.swap(pos, parent);
+            pos = parent;
         }
     }
-    seeds.sort_unstable_by(|a, b| {
-        reachability[*a]
-            .partial_cmp(&reachability[*b])
-            .unwrap()
-            .reverse()
-    });
+
+    fn sift_down<A: FloatCore>(&mut self, mut pos: usize, keys: &[A]) {
+        loop {
+            let left = 2 * pos + 1;
+            let right = left + 1;
+            let mut smallest = pos;
+            if left < self.heap.len() && Self::less(keys, self.heap[left], self.heap[smallest]) {
+                smallest = left;
+            }
+            if right < self.heap.len() && Self::less(keys, self.heap[right], self.heap[smallest]) {
+                smallest = right;
+            }
+            if smallest == pos {
+                break;
+            }
+            self.swap(pos, smallest);
+            pos = smallest;
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.position[self.heap[a]] = a;
+        self.position[self.heap[b]] = b;
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Neighborhood<A> {
+    /// The neighbors of the point, or none if they are queried when needed.
     pub neighbors: Vec<usize>,
+
+    /// The distance to the `min_samples`-th nearest point, counting the point
+    /// itself, or zero if the point is not a core point.
     pub core_distance: A,
+
+    /// The number of neighbors of the point, kept even if `neighbors` is
+    /// not. Models saved before it was added have `neighbors` instead.
+    #[serde(default)]
+    pub size: usize,
 }
 
-fn build_neighborhoods<S, A, M>(
-    input: &ArrayBase<S, Ix2>,
+impl<A> Neighborhood<A> {
+    fn len(&self) -> usize {
+        self.neighbors.len().max(self.size)
+    }
+}
+
+/// Finds the neighborhood of each point within `eps`, keeping the
+/// neighbors themselves only if `keep_neighbors` is set, and the core
+/// distance of each core point.
+///
+/// The points are searched in order, in chunks of [`CHUNK_SIZE`] points.
+/// `check` is called with the neighborhoods found so far before the search
+/// and after each chunk, and the search stops if it returns an error.
+fn build_neighborhoods<A, M, E>(
+    db: &BallTree<A, M>,
     eps: A,
-    metric: M,
-) -> Vec<Neighborhood<A>>
+    min_samples: usize,
+    keep_neighbors: bool,
+    mut check: impl FnMut(&[Neighborhood<A>]) -> Result<(), E>,
+) -> Result<Vec<Neighborhood<A>>, E>
 where
     A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
-    S: Data<Elem = A>,
     M: Metric<A> + Sync,
 {
-    if input.nrows() == 0 {
-        return Vec::new();
-    }
-    let rows: Vec<_> = input.rows().into_iter().collect();
-    let db = BallTree::new(input.view(), metric).expect("non-empty array");
-    rows.into_par_iter()
-        .map(|p| {
-            let neighbors = db.query_radius(&p, eps).into_iter().collect::<Vec<usize>>();
-            let core_distance = if neighbors.len() > 1 {
-                db.query(&p, 2).1[1]
+    let rows: Vec<_> = db.points.rows().into_iter().collect();
+    let mut neighborhoods = Vec::with_capacity(rows.len());
+    check(&neighborhoods)?;
+    for (k, chunk) in rows.chunks(CHUNK_SIZE).enumerate() {
+        neighborhoods.par_extend(chunk.par_iter().enumerate().map(|(j, p)| {
+            let i = k * CHUNK_SIZE + j;
+            let neighbors = db.query_radius(p, eps);
+            // the distance to the `min_samples`-th nearest point, counting
+            // the point itself
+            let core_distance = if min_samples > 1 && neighbors.len() >= min_samples {
+                k_nearest(db, p, min_samples - 1, Some(i)).1[min_samples - 2]
             } else {
                 A::zero()
             };
             Neighborhood {
-                neighbors,
+                size: neighbors.len(),
+                neighbors: if keep_neighbors { neighbors } else { vec![] },
                 core_distance,
             }
-        })
-        .collect()
+        }));
+        check(&neighborhoods)?;
+    }
+    Ok(neighborhoods)
 }
 
-fn reachability_distance<S, A, M>(
-    o: usize,
-    p: usize,
-    input: &ArrayBase<S, Ix2>,
-    neighbors: &Neighborhood<A>,
-    metric: &M,
-) -> A
+/// Finds the `k` nearest neighbors of `point` and their distances, in
+/// increasing order of distance, skipping the point at index `exclude`.
+pub(crate) fn k_nearest<S, A, M>(
+    db: &BallTree<A, M>,
+    point: &ArrayBase<S, Ix1>,
+    k: usize,
+    exclude: Option<usize>,
+) -> (Vec<usize>, Vec<A>)
 where
-    A: FloatCore,
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive,
     S: Data<Elem = A>,
     M: Metric<A>,
 {
-    let dist = metric.distance(&input.row(o), &input.row(p));
-    if dist.gt(&neighbors.core_distance) {
-        dist
-    } else {
-        neighbors.core_distance
-    }
+    let Some(exclude) = exclude else {
+        return db.query(point, k);
+    };
+    let (mut indices, mut distances) = db.query(point, k + 1);
+    // duplicates of the point may be returned instead of the point itself
+    let pos = indices
+        .iter()
+        .position(|&i| i == exclude)
+        .unwrap_or(indices.len() - 1);
+    indices.remove(pos);
+    distances.remove(pos);
+    (indices, distances)
 }
 
 #[cfg(test)]
@@ -346,6 +1407,102 @@ mod test {
         assert_eq!(Vec::<usize>::new(), noise);
     }
 
+    #[test]
+    fn fit_model() {
+        let data = array![
+            [1., 2.],
+            [f64::NAN, 2.],
+            [2., 2.],
+            [2., 2.3],
+            [8., 7.],
+            [8., 8.],
+            [25., 80.]
+        ];
+        let optics = Optics::new(3., 2, Euclidean::default());
+        let model = optics.fit_model(&data);
+        assert!(optics.ordering().is_empty());
+        assert_eq!((model.eps(), model.min_samples()), (3., 2));
+
+        let mut fitted = Optics::new(3., 2, Euclidean::default());
+        let clustering = fitted.fit(&data, None);
+        assert_eq!(model.extract_clusters_and_noise(3.), clustering);
+        assert_eq!(model.extract_dbscan(1.), fitted.extract_dbscan(1.));
+        assert_eq!(model.ordering(), fitted.ordering());
+        assert_eq!(
+            model.assign_noise_to_nearest(&data, clustering.clone(), 100.),
+            fitted.assign_noise_to_nearest(&data, clustering, 100.)
+        );
+
+        let model = optics.fit_model(&array![[0., 0.]]);
+        assert_eq!(model.extract_dbscan(3.), (HashMap::new(), vec![0]));
+    }
+
+    #[test]
+    fn fit_with_limits() {
+        use std::time::Duration;
+
+        use crate::Limit;
+
+        let data = array![[0.], [1.], [2.], [10.], [11.], [12.], [30.]];
+        let mut model = Optics::new(1.01, 2, Euclidean::default());
+        let expected = model.fit(&data, None);
+        assert_eq!(
+            model.fit_with_limits(&data, &Limits::default()),
+            Ok(expected)
+        );
+
+        // the input and the ball tree alone take 7 * 8 + 2 * 7 * 8 bytes
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(100), None))
+            .unwrap_err();
+        assert_eq!(error.limit, Limit::Memory(100));
+        assert_eq!(error.phase, "indexing");
+        assert_eq!(error.memory, 168);
+
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(200), None))
+            .unwrap_err();
+        assert_eq!(error.phase, "neighbor search");
+        assert_eq!(error.processed, 7);
+
+        // plus 15 neighbors in 7 neighborhoods, then the ordering
+        let searched = 168 + 7 * size_of::<Neighborhood<f64>>() + 15 * 8;
+        let error = model
+            .fit_with_limits(&data, &Limits::new(Some(searched), None))
+            .unwrap_err();
+        assert_eq!(error.phase, "ordering");
+        assert_eq!(error.processed, 0);
+        assert_eq!(error.memory, searched + 7 * (2 * 8 + 8 + 1));
+
+        let error = model
+            .fit_with_limits(&data, &Limits::new(None, Some(Duration::ZERO)))
+            .unwrap_err();
+        assert_eq!(error.limit, Limit::Duration(Duration::ZERO));
+    }
+
+    #[test]
+    fn fit_with_graph() {
+        let data = array![
+            [1., 2.],
+            [2., 5.],
+            [3., 6.],
+            [8., 7.],
+            [8., 8.],
+            [7., 3.],
+            [2., 2.3],
+            [25., 80.]
+        ];
+        let graph = NeighborGraph::new(&data, 5., &Euclidean::default());
+        for (eps, min_samples) in [(4.5, 2), (5., 3), (1., 2)] {
+            let mut optics = Optics::new(eps, min_samples, Euclidean::default());
+            let expected = optics.fit(&data, None);
+            let mut with_graph = Optics::new(eps, min_samples, Euclidean::default());
+            assert_eq!(with_graph.fit_with_graph(&graph), expected);
+            assert_eq!(with_graph.ordering(), optics.ordering());
+            assert_eq!(with_graph.extract_dbscan(0.5), optics.extract_dbscan(0.5));
+        }
+    }
+
     #[test]
     fn core_samples() {
         let data = array![[0.], [2.], [3.], [4.], [6.], [8.], [10.]];
@@ -355,6 +1512,135 @@ mod test {
         assert!(noise.is_empty());
     }
 
+    #[test]
+    fn low_memory() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [10.0, 10.0],
+        ];
+        for eps in [0.2, 0.5, 5.] {
+            let mut model = Optics::new(eps, 2, Euclidean::default());
+            let expected = model.fit(&data, None);
+            let expected_reachability: Vec<u64> = model
+                .reachability
+                .iter()
+                .map(|r: &f64| r.to_bits())
+                .collect();
+            let expected_half = model.extract_clusters_and_noise(eps / 2.);
+
+            model.low_memory = true;
+            assert_eq!(model.fit(&data, None), expected);
+            assert!(model.neighborhoods.iter().all(|n| n.neighbors.is_empty()));
+            let reachability: Vec<u64> = model
+                .reachability
+                .iter()
+                .map(|r: &f64| r.to_bits())
+                .collect();
+            assert_eq!(reachability, expected_reachability);
+            assert_eq!(model.extract_clusters_and_noise(eps / 2.), expected_half);
+        }
+    }
+
+    #[test]
+    fn seed_queue() {
+        let mut keys = [5., 3., 8., 3., 1., 9.];
+        let mut seeds = SeedQueue::new(keys.len());
+        for i in 0..keys.len() {
+            seeds.push(i, &keys);
+        }
+        keys[5] = 2.;
+        seeds.decrease(5, &keys);
+        keys[2] = 0.;
+        seeds.decrease(2, &keys);
+        let order: Vec<_> = std::iter::from_fn(|| seeds.pop(&keys)).collect();
+        assert_eq!(order, [2, 4, 5, 1, 3, 0]);
+        assert!(seeds.position.iter().all(|p| *p == usize::MAX));
+    }
+
+    #[test]
+    fn extract_dbscan() {
+        use crate::Dbscan;
+
+        let (data, _) = crate::datasets::make_blobs(200, 2, 3, 1., 5);
+        let min_samples = 5;
+        let mut model = Optics::new(3., min_samples, Euclidean::default());
+        model.fit(&data, None);
+        let metric = Euclidean::default();
+        for eps in [0.3, 0.5, 1., 2., 3.] {
+            let is_core: Vec<bool> = data
+                .rows()
+                .into_iter()
+                .map(|p| {
+                    let n = data
+                        .rows()
+                        .into_iter()
+                        .filter(|q| metric.distance(&p, q) <= eps);
+                    n.count() >= min_samples
+                })
+                .collect();
+            // the clusters of each core point
+            let core_labels = |clusters: &HashMap<usize, Vec<usize>>| {
+                let mut labels = vec![None; data.nrows()];
+                for (id, members) in clusters {
+                    for &i in members.iter().filter(|i| is_core[**i]) {
+                        labels[i] = Some(*id);
+                    }
+                }
+                labels
+            };
+
+            let (clusters, noise) = model.extract_dbscan(eps);
+            let (expected, _) = Dbscan::new(eps, min_samples, metric.clone()).fit(&data, None);
+            assert_eq!(clusters.len(), expected.len());
+            assert!(noise.iter().all(|i| !is_core[*i]));
+            let labels = core_labels(&clusters);
+            let expected = core_labels(&expected);
+            // the same partition of the core points, up to the cluster IDs
+            for i in 0..data.nrows() {
+                for j in 0..data.nrows() {
+                    if is_core[i] && is_core[j] {
+                        assert_eq!(labels[i] == labels[j], expected[i] == expected[j]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "eps cannot be greater than the radius of the fit")]
+    fn extract_dbscan_beyond_eps() {
+        let data = array![[0.], [1.]];
+        let mut model = Optics::new(1., 2, Euclidean::default());
+        model.fit(&data, None);
+        let _ = model.extract_dbscan(2.);
+    }
+
+    #[test]
+    fn extract_from_seeds() {
+        let data = array![[0.], [1.], [2.], [10.], [11.], [12.], [20.], [21.], [30.]];
+        let mut model = Optics::new(1.01, 2, Euclidean::default());
+        model.fit(&data, None);
+
+        let (clusters, noise) = model.extract_from_seeds(1.01, &[7, 4, 8]);
+        assert_eq!(clusters.len(), 2);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        for v in &mut clusters {
+            v.sort_unstable();
+        }
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![3, 4, 5], vec![6, 7]]);
+        assert_eq!(noise, [0, 1, 2, 8]);
+
+        let (clusters, noise) = model.extract_from_seeds(1.01, &[]);
+        assert!(clusters.is_empty());
+        assert_eq!(noise.len(), data.nrows());
+    }
+
     #[test]
     fn fit_empty() {
         let data: Vec<[f64; 8]> = vec![];
@@ -365,4 +1651,43 @@ mod test {
         assert!(clusters.is_empty());
         assert!(noise.is_empty());
     }
+
+    #[test]
+    fn fit_degenerate() {
+        // duplicates are at distance zero, and an isolated point is noise
+        let data = array![[1., 1.], [1., 1.], [1., 1.], [9., 9.]];
+        let mut model = Optics::new(0.5, 2, Euclidean::default());
+        let (clusters, noise) = model.fit(&data, None);
+        assert_eq!(clusters, hashmap! {0 => vec![0, 1, 2]});
+        assert_eq!(noise, [3]);
+
+        let (clusters, noise) = model.fit(&array![[1., 1.]], None);
+        assert!(clusters.is_empty());
+        assert_eq!(noise, [0]);
+    }
+
+    #[test]
+    fn fit_non_finite() {
+        let data = array![
+            [1., 2.],
+            [f64::NAN, 2.],
+            [2., 2.],
+            [2., 2.3],
+            [8., 7.],
+            [8., f64::NEG_INFINITY],
+            [8., 8.]
+        ];
+        let mut model = Optics::new(3., 2, Euclidean::default());
+        let (clusters, noise) = model.fit(&data, None);
+        assert_eq!(clusters, hashmap! {0 => vec![0, 2, 3], 1 => vec![4, 6]});
+        assert_eq!(noise, [1, 5]);
+        assert!(model.reachability[1].is_nan());
+        assert!(model.neighborhoods[4].neighbors.contains(&6));
+
+        let (clusters, _) = model.hierarchy().cut_at(1);
+        assert_eq!(
+            clusters,
+            hashmap! {0 => vec![0, 2, 3], 1 => vec![1], 2 => vec![4, 6], 3 => vec![5]}
+        );
+    }
 }
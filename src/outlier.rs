@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{ArrayBase, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::{
+    distance::{Euclidean, Metric},
+    BallTree,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::optics::k_nearest;
+use crate::parallel::prelude::*;
+
+/// LOF (local outlier factor) outlier detection algorithm.
+///
+/// The local outlier factor of a point is the average local reachability
+/// density of its `n_neighbors` nearest neighbors divided by its own. Points
+/// in regions as dense as their neighborhoods score around 1, and outliers
+/// score significantly higher.
+///
+/// `fit` returns the scores of the training points. If `novelty` is set, the
+/// training points are kept so that `predict` can score new points against
+/// them.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Fit, Lof, Predict};
+///
+/// let points = array![[1., 1.], [1., 2.], [2., 1.], [2., 2.], [8., 8.]];
+/// let mut lof = Lof::new(2, Euclidean::default());
+/// lof.novelty = true;
+/// let scores = lof.fit(&points, None);
+/// assert!(scores[4] > 2.);  // [8., 8.] is an outlier
+///
+/// let scores = lof.predict(&array![[1.5, 1.5], [-5., -5.]]);
+/// assert!(scores[0] < 1.5);
+/// assert!(scores[1] > 2.);
+/// ```
+///
+/// # References
+/// - Breunig, Markus M., et al. "LOF: identifying density-based local outliers."
+///   Proceedings of the 2000 ACM SIGMOD International Conference on Management of Data (2000): 93-104.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Lof<A, M> {
+    /// The number of neighbors used to estimate the local density.
+    pub n_neighbors: usize,
+
+    /// Whether to keep the training points to score new points with
+    /// `predict`.
+    pub novelty: bool,
+
+    /// The metric to compute distance between the entries.
+    pub metric: M,
+
+    points: Vec<A>,
+    n_features: usize,
+    k_distances: Vec<A>,
+    #[serde(
+        with = "crate::persistence::non_finite_vec",
+        bound(
+            serialize = "A: FloatCore + Serialize",
+            deserialize = "A: FloatCore + Deserialize<'de>"
+        )
+    )]
+    densities: Vec<A>,
+}
+
+impl<A> Default for Lof<A, Euclidean>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(20, Euclidean::default())
+    }
+}
+
+impl<A, M> Lof<A, M> {
+    /// Creates a detector comparing each point to its `n_neighbors` nearest
+    /// neighbors, which does not keep the training points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Lof;
+    ///
+    /// let mut lof = Lof::<f64, _>::new(20, Euclidean::default());
+    /// assert!(!lof.novelty);
+    /// lof.novelty = true;
+    ///
+    /// ```
+    #[must_use]
+    pub fn new(n_neighbors: usize, metric: M) -> Self {
+        Self {
+            n_neighbors,
+            novelty: false,
+            metric,
+            points: vec![],
+            n_features: 0,
+            k_distances: vec![],
+            densities: vec![],
+        }
+    }
+}
+
+/// Computes the local outlier factor of each point in the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// - `Vec<A>`: The local outlier factor of each point.
+impl<S, A, M> Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, Vec<A>> for Lof<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> Vec<A> {
+        self.points = vec![];
+        self.n_features = input.ncols();
+        if input.is_empty() {
+            self.k_distances = vec![];
+            self.densities = vec![];
+            return vec![];
+        }
+
+        let input = input.as_standard_layout();
+        if self.novelty {
+            self.points = input.iter().copied().collect();
+        }
+        let k = self.n_neighbors.min(input.nrows() - 1);
+        if k == 0 {
+            self.k_distances = vec![A::zero(); input.nrows()];
+            self.densities = vec![A::infinity(); input.nrows()];
+            return vec![A::one(); input.nrows()];
+        }
+        let db = BallTree::new(input.view(), self.metric.clone()).expect("non-empty array");
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        let neighbors: Vec<_> = rows
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, p)| k_nearest(&db, &p, k, Some(i)))
+            .collect();
+
+        self.k_distances = neighbors
+            .iter()
+            .map(|(_, distances)| distances[k - 1])
+            .collect();
+        self.densities = neighbors
+            .par_iter()
+            .map(|(indices, distances)| {
+                local_reachability_density(indices, distances, &self.k_distances)
+            })
+            .collect();
+        let scores = neighbors
+            .par_iter()
+            .zip(&self.densities)
+            .map(|((indices, _), density)| outlier_factor(indices, *density, &self.densities))
+            .collect();
+        scores
+    }
+}
+
+/// Computes the local outlier factor of new points with respect to the
+/// training points.
+///
+/// # Panics
+/// Panics if the model was not fitted with `novelty` set.
+impl<S, A, M> Predict<ArrayBase<S, Ix2>, Vec<A>> for Lof<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> Vec<A> {
+        assert!(
+            !self.points.is_empty(),
+            "`predict` requires a model fitted with `novelty` set"
+        );
+        let n = self.k_distances.len();
+        // as in `fit`, points without neighbors to compare with score 1
+        if self.n_neighbors.min(n - 1) == 0 {
+            return vec![A::one(); input.nrows()];
+        }
+        let k = self.n_neighbors.min(n);
+        let points = ArrayView2::from_shape((n, self.n_features), &self.points)
+            .expect("points of the training data");
+        let db = BallTree::new(points, self.metric.clone()).expect("non-empty array");
+        let input = input.as_standard_layout();
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        rows.into_par_iter()
+            .map(|p| {
+                let (indices, distances) = k_nearest(&db, &p, k, None);
+                let density = local_reachability_density(&indices, &distances, &self.k_distances);
+                outlier_factor(&indices, density, &self.densities)
+            })
+            .collect()
+    }
+}
+
+/// Computes the inverse of the mean reachability distance from a point to
+/// its neighbors.
+fn local_reachability_density<A: FloatCore + FromPrimitive>(
+    indices: &[usize],
+    distances: &[A],
+    k_distances: &[A],
+) -> A {
+    let sum = indices
+        .iter()
+        .zip(distances)
+        .fold(A::zero(), |sum, (&o, &d)| sum + d.max(k_distances[o]));
+    let count = A::from_usize(indices.len()).expect("valid float");
+    // avoids infinite densities when there are more than `k` duplicates
+    let bias = A::from_f64(1e-10).expect("valid float");
+    (sum / count + bias).recip()
+}
+
+fn outlier_factor<A: FloatCore + FromPrimitive>(
+    indices: &[usize],
+    density: A,
+    densities: &[A],
+) -> A {
+    let sum = indices.iter().fold(A::zero(), |sum, &o| sum + densities[o]);
+    sum / A::from_usize(indices.len()).expect("valid float") / density
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let lof = Lof::<f32, Euclidean>::default();
+        assert_eq!(lof.n_neighbors, 20);
+        assert!(!lof.novelty);
+    }
+
+    #[test]
+    fn uniform_grid() {
+        // interior points of a grid are as dense as their neighbors
+        #[allow(clippy::cast_precision_loss)]
+        let data =
+            Array2::from_shape_fn((81, 2), |(i, j)| if j == 0 { i / 9 } else { i % 9 } as f64);
+        let mut lof = Lof::new(4, Euclidean::default());
+        let scores = lof.fit(&data, None);
+        assert_eq!(scores.len(), 81);
+        for i in [30, 31, 39, 40, 50] {
+            assert!((scores[i] - 1.).abs() < 1e-6, "{i}: {}", scores[i]);
+        }
+    }
+
+    #[test]
+    fn outlier() {
+        let data = array![[0.], [1.], [2.], [3.], [4.], [20.]];
+        let mut lof = Lof::new(2, Euclidean::default());
+        let scores = lof.fit(&data, None);
+        // k-distances: 2, 1, 1, 1, 2, 17
+        // densities: 1/1.5, 1/1.5, 1, 1/1.5, 1/1.5, 1/16.5
+        assert!((scores[2] - 1. / 1.5).abs() < 1e-6);
+        assert!((scores[5] - 16.5 / 1.5).abs() < 1e-6);
+        assert!(scores.iter().take(5).all(|s| *s < 1.5));
+    }
+
+    #[test]
+    fn novelty() {
+        let data = array![[0.], [1.], [2.], [3.], [4.]];
+        let mut lof = Lof::new(2, Euclidean::default());
+        lof.novelty = true;
+        lof.fit(&data, None);
+        let scores = lof.predict(&array![[2.5], [20.]]);
+        assert!(scores[0] < 1.5);
+        assert!(scores[1] > 5.);
+    }
+
+    #[test]
+    fn novelty_single_point() {
+        let mut lof = Lof::new(2, Euclidean::default());
+        lof.novelty = true;
+        assert_eq!(lof.fit(&array![[0.]], None), [1.]);
+        assert_eq!(lof.predict(&array![[0.], [20.]]), [1., 1.]);
+    }
+
+    #[test]
+    #[should_panic(expected = "novelty")]
+    fn predict_without_novelty() {
+        let data = array![[0.], [1.], [2.]];
+        let mut lof = Lof::new(2, Euclidean::default());
+        lof.fit(&data, None);
+        lof.predict(&data);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut lof = Lof::default();
+        assert!(lof.fit(&data, None).is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Parallel iterators from rayon with the `parallel` feature, and sequential
+//! iterators with the same method names without it.
+//!
+//! Modules import `crate::parallel::prelude::*` instead of
+//! `rayon::prelude::*`, so that the crate builds for targets without threads,
+//! such as `wasm32-unknown-unknown`. Only the methods used in this crate are
+//! provided.
+
+pub(crate) mod prelude {
+    #[cfg(feature = "parallel")]
+    pub(crate) use rayon::prelude::*;
+
+    #[cfg(not(feature = "parallel"))]
+    pub(crate) use super::sequential::*;
+}
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    use std::iter::FlatMap;
+
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub(crate) trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, I: 'a + ?Sized> IntoParallelRefIterator<'a> for I
+    where
+        &'a I: IntoIterator,
+    {
+        type Iter = <&'a I as IntoIterator>::IntoIter;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub(crate) trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U, F>(self, f: F) -> FlatMap<Self, U, F>
+        where
+            U: IntoIterator,
+            F: FnMut(Self::Item) -> U,
+        {
+            self.flat_map(f)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+
+    pub(crate) trait ParallelExtend<T>: Extend<T> {
+        fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            self.extend(iter);
+        }
+    }
+
+    impl<T, C: Extend<T>> ParallelExtend<T> for C {}
+}
@@ -0,0 +1,1242 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::slice::Chunks;
+
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::prelude::*;
+use crate::union_find::TreeUnionFind;
+
+/// A compact representation of a clustering result.
+///
+/// The members of all clusters are stored in a single buffer, ordered by
+/// cluster ID and by index within each cluster, so that a cluster's members
+/// can be borrowed as a slice, or page by page, without allocating.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, Fit, Partition};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let partition = Partition::from(Dbscan::new(3., 2, Euclidean::default()).fit(&points, None));
+///
+/// assert_eq!(partition.len(), 2);
+/// assert_eq!(partition.members(0), Some(&[0, 1, 2][..]));
+/// assert_eq!(partition.members_paged(0, 1, 5), Some(&[1, 2][..]));
+/// assert_eq!(partition.noise(), [5]);
+/// ```
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Partition {
+    ids: Vec<usize>,
+    offsets: Vec<usize>,
+    members: Vec<usize>,
+    noise: Vec<usize>,
+    #[serde(default)]
+    names: Vec<Option<String>>,
+}
+
+/// A summary of a cluster, given to the callback of
+/// [`Partition::name_clusters`].
+#[derive(Clone, Debug)]
+pub struct ClusterSummary<A> {
+    /// The ID of the cluster.
+    pub id: usize,
+
+    /// The number of members of the cluster.
+    pub size: usize,
+
+    /// The mean of the members of the cluster.
+    pub centroid: Array1<A>,
+
+    /// The members closest to the centroid, closest first.
+    pub exemplars: Vec<usize>,
+}
+
+/// Statistics of a cluster, returned by [`Partition::stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterStats<A> {
+    /// The ID of the cluster.
+    pub id: usize,
+
+    /// The number of members of the cluster.
+    pub size: usize,
+
+    /// The mean of the members of the cluster.
+    pub centroid: Array1<A>,
+
+    /// The member with the smallest total distance to the other members,
+    /// with ties broken by the lower index.
+    pub medoid: usize,
+
+    /// The mean distance between two distinct members, or zero for a single
+    /// member.
+    pub mean_distance: A,
+
+    /// The largest distance between two members.
+    pub diameter: A,
+
+    /// The reciprocal of `mean_distance`, which is infinite if all the
+    /// members coincide.
+    pub density: A,
+}
+
+/// The assignment of a point, as a row of the long-format table returned
+/// by [`Partition::assignments`].
+///
+/// It derives `Serialize`, so a slice of assignments can be written with any
+/// serde-based writer, e.g. of CSV, JSON or Arrow, or inserted into a
+/// database in bulk.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PointAssignment<A = f64> {
+    /// The index of the point in the input.
+    pub point_index: usize,
+
+    /// The ID of the cluster of the point, or `None` if it is noise.
+    pub cluster_id: Option<usize>,
+
+    /// Whether the point is noise.
+    pub is_noise: bool,
+
+    /// The confidence in the assignment, if given.
+    pub confidence: Option<A>,
+}
+
+impl<A: Display> PointAssignment<A> {
+    /// Writes the assignments as CSV with a header row, leaving the cluster
+    /// ID of noise points and missing confidences empty.
+    ///
+    /// # Errors
+    /// Returns an error if writing to `writer` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::{Partition, PointAssignment};
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2]}, vec![1]);
+    /// let mut csv = Vec::new();
+    /// PointAssignment::write_csv(&partition.assignments(), &mut csv).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(csv).unwrap(),
+    ///     "point_index,cluster_id,is_noise,confidence\n0,0,false,\n1,,true,\n2,0,false,\n"
+    /// );
+    /// ```
+    pub fn write_csv<W: Write>(assignments: &[Self], mut writer: W) -> io::Result<()> {
+        writeln!(writer, "point_index,cluster_id,is_noise,confidence")?;
+        for a in assignments {
+            write!(writer, "{},", a.point_index)?;
+            if let Some(id) = a.cluster_id {
+                write!(writer, "{id}")?;
+            }
+            write!(writer, ",{},", a.is_noise)?;
+            if let Some(confidence) = &a.confidence {
+                write!(writer, "{confidence}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+impl Partition {
+    /// Creates a partition from clusters mapped by their IDs and noise points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {3 => vec![0, 2], 1 => vec![1]}, vec![4, 3]);
+    /// assert_eq!(partition.cluster_ids(), [1, 3]);
+    /// assert_eq!(partition.noise(), [3, 4]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::implicit_hasher)]
+    pub fn new(clusters: HashMap<usize, Vec<usize>>, mut noise: Vec<usize>) -> Self {
+        let mut clusters: Vec<_> = clusters.into_iter().collect();
+        clusters.sort_unstable_by_key(|(id, _)| *id);
+
+        let mut ids = Vec::with_capacity(clusters.len());
+        let mut offsets = Vec::with_capacity(clusters.len() + 1);
+        let mut members = Vec::with_capacity(clusters.iter().map(|(_, m)| m.len()).sum());
+        offsets.push(0);
+        for (id, mut cluster) in clusters {
+            cluster.sort_unstable();
+            ids.push(id);
+            members.extend(cluster);
+            offsets.push(members.len());
+        }
+        noise.sort_unstable();
+        let names = vec![None; ids.len()];
+        Self {
+            ids,
+            offsets,
+            members,
+            noise,
+            names,
+        }
+    }
+
+    /// Returns the number of clusters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2], 1 => vec![1]}, vec![3]);
+    /// assert_eq!(partition.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if there are no clusters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::from_labels(&[None, None]);
+    /// assert!(partition.is_empty());
+    /// assert_eq!(partition.noise(), [0, 1]);
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the IDs of the clusters in increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {7 => vec![1], 2 => vec![0]}, vec![]);
+    /// assert_eq!(partition.cluster_ids(), [2, 7]);
+    /// ```
+    #[must_use]
+    pub fn cluster_ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    /// Returns the members of a cluster in increasing order, or `None` if
+    /// there is no cluster with the given ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2], 1 => vec![1]}, vec![3]);
+    /// assert_eq!(partition.members(0), Some(&[0, 2][..]));
+    /// assert_eq!(partition.members(5), None);
+    /// ```
+    #[must_use]
+    pub fn members(&self, cluster_id: usize) -> Option<&[usize]> {
+        let pos = self.ids.binary_search(&cluster_id).ok()?;
+        Some(&self.members[self.offsets[pos]..self.offsets[pos + 1]])
+    }
+
+    /// Returns at most `limit` members of a cluster, skipping the first
+    /// `offset` ones, or `None` if there is no cluster with the given ID. The
+    /// page is empty if `offset` is past the last member.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 1, 2, 3, 4]}, vec![]);
+    /// assert_eq!(partition.members_paged(0, 2, 2), Some(&[2, 3][..]));
+    /// assert_eq!(partition.members_paged(0, 4, 2), Some(&[4][..]));
+    /// assert_eq!(partition.members_paged(0, 9, 2), Some(&[][..]));
+    /// ```
+    #[must_use]
+    pub fn members_paged(
+        &self,
+        cluster_id: usize,
+        offset: usize,
+        limit: usize,
+    ) -> Option<&[usize]> {
+        let members = self.members(cluster_id)?;
+        let start = offset.min(members.len());
+        let end = start.saturating_add(limit).min(members.len());
+        Some(&members[start..end])
+    }
+
+    /// Returns an iterator over the members of a cluster in pages of
+    /// `page_size` members, or `None` if there is no cluster with the given
+    /// ID.
+    ///
+    /// # Panics
+    /// Panics if `page_size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 1, 2, 3, 4]}, vec![]);
+    /// let pages: Vec<_> = partition.pages(0, 2).unwrap().collect();
+    /// assert_eq!(pages, [&[0, 1][..], &[2, 3], &[4]]);
+    /// ```
+    #[must_use]
+    pub fn pages(&self, cluster_id: usize, page_size: usize) -> Option<Chunks<'_, usize>> {
+        Some(self.members(cluster_id)?.chunks(page_size))
+    }
+
+    /// Returns the number of members of a cluster, or `None` if there is no
+    /// cluster with the given ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2], 1 => vec![1]}, vec![3]);
+    /// assert_eq!(partition.cluster_size(0), Some(2));
+    /// assert_eq!(partition.cluster_size(2), None);
+    /// ```
+    #[must_use]
+    pub fn cluster_size(&self, cluster_id: usize) -> Option<usize> {
+        self.members(cluster_id).map(<[usize]>::len)
+    }
+
+    /// Returns the noise points in increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2]}, vec![3, 1]);
+    /// assert_eq!(partition.noise(), [1, 3]);
+    /// ```
+    #[must_use]
+    pub fn noise(&self) -> &[usize] {
+        &self.noise
+    }
+
+    /// Returns a mask of `n_points` elements that is `true` at the members of
+    /// a cluster, or `None` if there is no cluster with the given ID.
+    ///
+    /// # Panics
+    /// Panics if a member of the cluster is not less than `n_points`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::{array, Axis};
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Partition};
+    ///
+    /// let points = array![[1., 2.], [8., 7.], [2., 2.], [8., 8.], [2., 2.3], [25., 80.]];
+    /// let partition = Partition::from(Dbscan::new(3., 2, Euclidean::default()).fit(&points, None));
+    /// let mask = partition.mask(1, points.nrows()).unwrap();
+    ///
+    /// assert_eq!(mask, array![false, true, false, true, false, false]);
+    /// let rows: Vec<_> = points.axis_iter(Axis(0)).zip(&mask).filter(|(_, m)| **m).collect();
+    /// assert_eq!(rows[1].0, array![8., 8.]);
+    /// ```
+    #[must_use]
+    pub fn mask(&self, cluster_id: usize, n_points: usize) -> Option<Array1<bool>> {
+        Some(mask_of(self.members(cluster_id)?, n_points))
+    }
+
+    /// Returns a mask of `n_points` elements that is `true` at the noise
+    /// points.
+    ///
+    /// # Panics
+    /// Panics if a noise point is not less than `n_points`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use ndarray::array;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2]}, vec![1]);
+    /// assert_eq!(partition.noise_mask(4), array![false, true, false, false]);
+    /// ```
+    #[must_use]
+    pub fn noise_mask(&self, n_points: usize) -> Array1<bool> {
+        mask_of(&self.noise, n_points)
+    }
+
+    /// Returns an iterator over the clusters as `(id, members)` pairs, in
+    /// increasing order of ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {1 => vec![1], 0 => vec![0, 2]}, vec![3]);
+    /// let clusters: Vec<_> = partition.iter().collect();
+    /// assert_eq!(clusters, [(0, &[0, 2][..]), (1, &[1])]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[usize])> {
+        self.ids
+            .iter()
+            .zip(self.offsets.windows(2))
+            .map(|(id, range)| (*id, &self.members[range[0]..range[1]]))
+    }
+
+    /// Returns the name of a cluster, or `None` if there is no cluster with
+    /// the given ID or it has not been named.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use ndarray::array;
+    /// use petal_clustering::Partition;
+    ///
+    /// let points = array![[0., 0.], [10., 10.], [0., 1.]];
+    /// let mut partition = Partition::new(hashmap! {0 => vec![0, 2], 1 => vec![1]}, vec![]);
+    /// assert_eq!(partition.name(0), None);
+    ///
+    /// partition.name_clusters(&points, 1, |summary| format!("{} points", summary.size));
+    /// assert_eq!(partition.name(0), Some("2 points"));
+    /// ```
+    #[must_use]
+    pub fn name(&self, cluster_id: usize) -> Option<&str> {
+        let pos = self.ids.binary_search(&cluster_id).ok()?;
+        self.names.get(pos)?.as_deref()
+    }
+
+    /// Names each cluster with the string returned by `name_of` for its
+    /// summary, computed from the points in `input` that the partition was
+    /// built from. Each summary has at most `n_exemplars` exemplars.
+    ///
+    /// The names are kept by the partition, and serialized with it.
+    ///
+    /// # Panics
+    /// Panics if a member of a cluster is not a row of `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Partition};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let mut partition = Partition::from(Dbscan::new(3., 2, Euclidean::default()).fit(&points, None));
+    /// partition.name_clusters(&points, 1, |summary| {
+    ///     let side = if summary.centroid[0] < 5. { "left" } else { "right" };
+    ///     format!("{side} ({} points)", summary.size)
+    /// });
+    ///
+    /// assert_eq!(partition.name(0), Some("left (3 points)"));
+    /// assert_eq!(partition.name(1), Some("right (2 points)"));
+    /// ```
+    pub fn name_clusters<S, A, F>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        n_exemplars: usize,
+        mut name_of: F,
+    ) where
+        A: FloatCore + FromPrimitive,
+        S: Data<Elem = A>,
+        F: FnMut(&ClusterSummary<A>) -> String,
+    {
+        let names = self
+            .iter()
+            .map(|(id, members)| Some(name_of(&summarize(input, id, members, n_exemplars))))
+            .collect();
+        self.names = names;
+    }
+
+    /// Returns the statistics of each cluster, in increasing order of cluster
+    /// ID, computed from the points in `input` that the partition was built
+    /// from, with distances measured by `metric`.
+    ///
+    /// It computes the distances between all pairs of members of each
+    /// cluster, in parallel.
+    ///
+    /// # Panics
+    /// Panics if a member of a cluster is not a row of `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Partition};
+    ///
+    /// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+    /// let partition = Partition::from(Dbscan::new(3., 2, Euclidean::default()).fit(&points, None));
+    /// let stats = partition.stats(&points, &Euclidean::default());
+    ///
+    /// assert_eq!(stats[0].size, 3);
+    /// assert_eq!(stats[0].medoid, 1);
+    /// assert_eq!(stats[1].diameter, 1.);
+    /// ```
+    #[must_use]
+    pub fn stats<S, A, M>(&self, input: &ArrayBase<S, Ix2>, metric: &M) -> Vec<ClusterStats<A>>
+    where
+        A: FloatCore + FromPrimitive + Send + Sync,
+        S: Data<Elem = A> + Sync,
+        M: Metric<A> + Sync,
+    {
+        self.iter()
+            .map(|(id, members)| {
+                // the total and the largest distance from each member to the others
+                let distances: Vec<(A, A)> = members
+                    .par_iter()
+                    .map(|&i| {
+                        members
+                            .iter()
+                            .map(|&j| metric.distance(&input.row(i), &input.row(j)))
+                            .fold((A::zero(), A::zero()), |(total, max), d| {
+                                (total + d, max.max(d))
+                            })
+                    })
+                    .collect();
+                let (medoid, _) = members.iter().zip(&distances).fold(
+                    (members[0], A::infinity()),
+                    |(medoid, best), (&i, (total, _))| {
+                        if *total < best {
+                            (i, *total)
+                        } else {
+                            (medoid, best)
+                        }
+                    },
+                );
+                let total = distances
+                    .iter()
+                    .fold(A::zero(), |acc, (total, _)| acc + *total);
+                let n_pairs = members.len() * (members.len() - 1);
+                let mean_distance = if n_pairs == 0 {
+                    A::zero()
+                } else {
+                    total / A::from_usize(n_pairs).expect("valid float")
+                };
+                ClusterStats {
+                    id,
+                    size: members.len(),
+                    centroid: centroid_of(input, members),
+                    medoid,
+                    mean_distance,
+                    diameter: distances
+                        .iter()
+                        .fold(A::zero(), |diameter, (_, max)| diameter.max(*max)),
+                    density: A::one() / mean_distance,
+                }
+            })
+            .collect()
+    }
+
+    /// Merges the clusters whose centroids, computed from the points in
+    /// `input` that the partition was built from, are within `max_distance`
+    /// of each other under `metric`.
+    ///
+    /// Merging is transitive, so a chain of clusters with close centroids
+    /// becomes a single cluster, even if the centroids at its ends are
+    /// farther apart. Each merged cluster takes the lowest ID and the name of
+    /// the clusters it is made of; the noise points are unchanged.
+    ///
+    /// # Panics
+    /// Panics if a member of a cluster is not a row of `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::Partition;
+    ///
+    /// let points = array![[0., 0.], [1., 0.], [2., 0.], [3., 0.], [9., 0.]];
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 1], 1 => vec![2, 3], 2 => vec![4]}, vec![]);
+    /// let merged = partition.merge_within(&points, &Euclidean::default(), 2.);
+    ///
+    /// assert_eq!(merged.len(), 2);
+    /// assert_eq!(merged.members(0), Some(&[0, 1, 2, 3][..]));
+    /// assert_eq!(merged.members(2), Some(&[4][..]));
+    /// ```
+    #[must_use]
+    pub fn merge_within<S, A, M>(
+        &self,
+        input: &ArrayBase<S, Ix2>,
+        metric: &M,
+        max_distance: A,
+    ) -> Self
+    where
+        A: FloatCore + FromPrimitive,
+        S: Data<Elem = A>,
+        M: Metric<A>,
+    {
+        let centroids: Vec<_> = self
+            .iter()
+            .map(|(_, members)| centroid_of(input, members))
+            .collect();
+        let mut uf = TreeUnionFind::new(centroids.len());
+        for (i, c1) in centroids.iter().enumerate() {
+            for (j, c2) in centroids.iter().enumerate().skip(i + 1) {
+                if metric.distance(&c1.view(), &c2.view()) <= max_distance {
+                    uf.union(i, j);
+                }
+            }
+        }
+
+        // clusters are visited in increasing order of ID, so the first one of
+        // each group has the lowest ID
+        let mut kept = HashMap::new();
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (pos, (id, members)) in self.iter().enumerate() {
+            let target = *kept.entry(uf.find(pos)).or_insert(id);
+            clusters
+                .entry(target)
+                .or_default()
+                .extend_from_slice(members);
+        }
+        let mut merged = Self::new(clusters, self.noise.clone());
+        merged.copy_names(self, None);
+        merged
+    }
+
+    /// Splits a cluster by clustering its members again with `cluster`,
+    /// e.g. with a clustering algorithm set up with tighter parameters, and
+    /// returns the updated partition, or `None` if there is no cluster with
+    /// the given ID.
+    ///
+    /// `cluster` receives the rows of `input` of the members of the cluster,
+    /// in increasing order of index, and returns clusters and noise by
+    /// position in those rows, as `Fit::fit` does. The first of the resulting
+    /// clusters, by ID, keeps `cluster_id`, and the others get new IDs above
+    /// every ID in the partition; the members that `cluster` marks as noise
+    /// become noise. The new clusters are unnamed.
+    ///
+    /// # Panics
+    /// Panics if a member of the cluster is not a row of `input`, or if
+    /// `cluster` returns a position that is not one of the members.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Partition};
+    ///
+    /// let points = array![[0., 0.], [1., 0.], [5., 0.], [6., 0.], [20., 0.], [30., 0.]];
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 1, 2, 3, 4], 3 => vec![5]}, vec![]);
+    /// let split = partition
+    ///     .split(0, &points, |members| {
+    ///         Dbscan::new(2., 2, Euclidean::default()).fit(members, None)
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(split.members(0), Some(&[0, 1][..]));
+    /// assert_eq!(split.members(4), Some(&[2, 3][..]));
+    /// assert_eq!(split.members(3), Some(&[5][..]));
+    /// assert_eq!(split.noise(), [4]);
+    /// ```
+    pub fn split<S, A, F>(
+        &self,
+        cluster_id: usize,
+        input: &ArrayBase<S, Ix2>,
+        cluster: F,
+    ) -> Option<Self>
+    where
+        A: Clone,
+        S: Data<Elem = A>,
+        F: FnOnce(&Array2<A>) -> (HashMap<usize, Vec<usize>>, Vec<usize>),
+    {
+        let members = self.members(cluster_id)?;
+        let (parts, part_noise) = cluster(&input.select(Axis(0), members));
+
+        let mut clusters: HashMap<_, _> = self
+            .iter()
+            .filter(|(id, _)| *id != cluster_id)
+            .map(|(id, members)| (id, members.to_vec()))
+            .collect();
+        let mut parts: Vec<_> = parts.into_iter().filter(|(_, p)| !p.is_empty()).collect();
+        parts.sort_unstable_by_key(|(id, _)| *id);
+        let new_ids =
+            (self.ids.last().map_or(0, |id| id + 1)..).take(parts.len().saturating_sub(1));
+        for (id, (_, part)) in std::iter::once(cluster_id).chain(new_ids).zip(parts) {
+            clusters.insert(id, part.into_iter().map(|j| members[j]).collect());
+        }
+        let mut noise = self.noise.clone();
+        noise.extend(part_noise.into_iter().map(|j| members[j]));
+
+        let mut split = Self::new(clusters, noise);
+        split.copy_names(self, Some(cluster_id));
+        Some(split)
+    }
+
+    /// Returns the partition with its clusters renumbered from zero in
+    /// decreasing order of size, so that cluster 0 is the largest and the IDs
+    /// are contiguous. Clusters of the same size are ordered by their
+    /// smallest member, so the IDs depend only on the clusters, not on the
+    /// IDs the algorithm gave them, and can be compared across runs. Names
+    /// follow their clusters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(
+    ///     hashmap! {3 => vec![5, 6], 7 => vec![0, 1, 2], 8 => vec![3, 4]},
+    ///     vec![9],
+    /// );
+    /// let renumbered = partition.renumber_by_size();
+    ///
+    /// assert_eq!(renumbered.cluster_ids(), [0, 1, 2]);
+    /// assert_eq!(renumbered.members(0), Some(&[0, 1, 2][..]));
+    /// assert_eq!(renumbered.members(1), Some(&[3, 4][..]));
+    /// assert_eq!(renumbered.members(2), Some(&[5, 6][..]));
+    /// assert_eq!(renumbered.noise(), [9]);
+    /// ```
+    #[must_use]
+    pub fn renumber_by_size(&self) -> Self {
+        // `names` is empty for partitions deserialized without names.
+        let mut clusters: Vec<_> = self
+            .iter()
+            .enumerate()
+            .map(|(pos, cluster)| (cluster, self.names.get(pos).cloned().flatten()))
+            .collect();
+        clusters
+            .sort_by_key(|((_, members), _)| (Reverse(members.len()), members.first().copied()));
+
+        let mut offsets = Vec::with_capacity(clusters.len() + 1);
+        let mut members = Vec::with_capacity(self.members.len());
+        let mut names = Vec::with_capacity(clusters.len());
+        offsets.push(0);
+        for ((_, cluster), name) in clusters {
+            members.extend_from_slice(cluster);
+            offsets.push(members.len());
+            names.push(name);
+        }
+        Self {
+            ids: (0..names.len()).collect(),
+            offsets,
+            members,
+            noise: self.noise.clone(),
+            names,
+        }
+    }
+
+    /// Returns the label of each of `n_points` points: the ID of its cluster,
+    /// or `None` if it is noise or not in the partition.
+    ///
+    /// # Panics
+    /// Panics if a point of the partition is not less than `n_points`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2], 1 => vec![3]}, vec![1]);
+    /// let labels = partition.labels(4);
+    ///
+    /// assert_eq!(labels, [Some(0), None, Some(0), Some(1)]);
+    /// assert_eq!(Partition::from_labels(&labels), partition);
+    /// ```
+    #[must_use]
+    pub fn labels(&self, n_points: usize) -> Vec<Option<usize>> {
+        let mut labels = vec![None; n_points];
+        for (id, members) in self.iter() {
+            for &i in members {
+                labels[i] = Some(id);
+            }
+        }
+        labels
+    }
+
+    /// Creates a partition from the label of each point, the ID of its
+    /// cluster or `None` for noise, as returned by [`Partition::labels`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::from_labels(&[Some(0), None, Some(0), Some(1)]);
+    /// assert_eq!(partition.members(0), Some(&[0, 2][..]));
+    /// assert_eq!(partition.noise(), [1]);
+    /// ```
+    #[must_use]
+    pub fn from_labels(labels: &[Option<usize>]) -> Self {
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = Vec::new();
+        for (i, label) in labels.iter().enumerate() {
+            match label {
+                Some(id) => clusters.entry(*id).or_default().push(i),
+                None => noise.push(i),
+            }
+        }
+        Self::new(clusters, noise)
+    }
+
+    /// Returns the assignment of each point in the partition, in increasing
+    /// order of point index, as a long-format table without confidences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![1]}, vec![0]);
+    /// let assignments = partition.assignments();
+    ///
+    /// assert_eq!(assignments[0].cluster_id, None);
+    /// assert!(assignments[0].is_noise);
+    /// assert_eq!(assignments[1].cluster_id, Some(0));
+    /// ```
+    #[must_use]
+    pub fn assignments(&self) -> Vec<PointAssignment> {
+        self.assignments_by(|_| None)
+    }
+
+    /// Returns the assignment of each point in the partition, in increasing
+    /// order of point index, with the confidence of point `i` taken from
+    /// `confidence[i]`, e.g. one minus the HDBSCAN outlier score.
+    ///
+    /// # Panics
+    /// Panics if a point index is out of bounds for `confidence`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use petal_clustering::Partition;
+    ///
+    /// let partition = Partition::new(hashmap! {0 => vec![0, 2]}, vec![1]);
+    /// let assignments = partition.assignments_with_confidence(&[0.9, 0., 0.7]);
+    ///
+    /// assert_eq!(assignments[1].cluster_id, None);
+    /// assert_eq!(assignments[2].confidence, Some(0.7));
+    ///
+    /// ```
+    #[must_use]
+    pub fn assignments_with_confidence<A: Copy>(
+        &self,
+        confidence: &[A],
+    ) -> Vec<PointAssignment<A>> {
+        self.assignments_by(|i| Some(confidence[i]))
+    }
+
+    fn assignments_by<A>(
+        &self,
+        confidence: impl Fn(usize) -> Option<A>,
+    ) -> Vec<PointAssignment<A>> {
+        let mut assignments: Vec<_> = self
+            .iter()
+            .flat_map(|(id, members)| members.iter().map(move |i| (*i, Some(id))))
+            .chain(self.noise.iter().map(|i| (*i, None)))
+            .map(|(point_index, cluster_id)| PointAssignment {
+                point_index,
+                cluster_id,
+                is_noise: cluster_id.is_none(),
+                confidence: confidence(point_index),
+            })
+            .collect();
+        assignments.sort_unstable_by_key(|a| a.point_index);
+        assignments
+    }
+
+    /// Takes the names of the clusters of `other` with the same IDs, except
+    /// for `except`.
+    fn copy_names(&mut self, other: &Self, except: Option<usize>) {
+        self.names = self
+            .ids
+            .iter()
+            .map(|&id| {
+                if Some(id) == except {
+                    None
+                } else {
+                    other.name(id).map(str::to_owned)
+                }
+            })
+            .collect();
+    }
+}
+
+fn mask_of(points: &[usize], n_points: usize) -> Array1<bool> {
+    let mut mask = Array1::from_elem(n_points, false);
+    for &i in points {
+        mask[i] = true;
+    }
+    mask
+}
+
+fn summarize<S, A>(
+    input: &ArrayBase<S, Ix2>,
+    id: usize,
+    members: &[usize],
+    n_exemplars: usize,
+) -> ClusterSummary<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let centroid = centroid_of(input, members);
+    let mut distances: Vec<_> = members
+        .iter()
+        .map(|&i| {
+            let d = input
+                .row(i)
+                .iter()
+                .zip(&centroid)
+                .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b));
+            (d, i)
+        })
+        .collect();
+    distances.sort_unstable_by(|a, b| a.partial_cmp(b).expect("no NaN distances"));
+    ClusterSummary {
+        id,
+        size: members.len(),
+        centroid,
+        exemplars: distances
+            .into_iter()
+            .take(n_exemplars)
+            .map(|(_, i)| i)
+            .collect(),
+    }
+}
+
+fn centroid_of<S, A>(input: &ArrayBase<S, Ix2>, members: &[usize]) -> Array1<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    let mut centroid = Array1::zeros(input.ncols());
+    for &i in members {
+        centroid.zip_mut_with(&input.row(i), |c, v| *c = *c + *v);
+    }
+    if let Some(size) = A::from_usize(members.len()).filter(|s| *s > A::zero()) {
+        centroid.mapv_inplace(|v| v / size);
+    }
+    centroid
+}
+
+impl Default for Partition {
+    fn default() -> Self {
+        Self::new(HashMap::new(), Vec::new())
+    }
+}
+
+impl From<(HashMap<usize, Vec<usize>>, Vec<usize>)> for Partition {
+    fn from((clusters, noise): (HashMap<usize, Vec<usize>>, Vec<usize>)) -> Self {
+        Self::new(clusters, noise)
+    }
+}
+
+#[allow(clippy::implicit_hasher)]
+impl From<Partition> for (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    fn from(partition: Partition) -> Self {
+        let clusters = partition
+            .iter()
+            .map(|(id, members)| (id, members.to_vec()))
+            .collect();
+        (clusters, partition.noise)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn partition() {
+        let clusters = hashmap! {3 => vec![5, 1, 4], 0 => vec![0, 2], 7 => vec![3]};
+        let partition = Partition::new(clusters.clone(), vec![8, 6]);
+        assert_eq!(partition.len(), 3);
+        assert_eq!(partition.cluster_ids(), [0, 3, 7]);
+        assert_eq!(partition.members(3), Some(&[1, 4, 5][..]));
+        assert_eq!(partition.members(1), None);
+        assert_eq!(partition.cluster_size(7), Some(1));
+        assert_eq!(partition.noise(), [6, 8]);
+        assert_eq!(
+            partition.iter().collect::<Vec<_>>(),
+            [(0, &[0, 2][..]), (3, &[1, 4, 5][..]), (7, &[3][..])]
+        );
+
+        let (mut restored, noise) = partition.into();
+        for members in restored.values_mut() {
+            members.sort_unstable();
+        }
+        let mut expected = clusters;
+        for members in expected.values_mut() {
+            members.sort_unstable();
+        }
+        assert_eq!(restored, expected);
+        assert_eq!(noise, [6, 8]);
+    }
+
+    #[test]
+    fn members_paged() {
+        let partition = Partition::new(hashmap! {0 => (0..10).collect()}, vec![]);
+        assert_eq!(partition.members_paged(0, 0, 4), Some(&[0, 1, 2, 3][..]));
+        assert_eq!(partition.members_paged(0, 8, 4), Some(&[8, 9][..]));
+        assert_eq!(partition.members_paged(0, 12, 4), Some(&[][..]));
+        assert_eq!(
+            partition.members_paged(0, 2, usize::MAX),
+            Some(&[2, 3, 4, 5, 6, 7, 8, 9][..])
+        );
+        assert_eq!(partition.members_paged(1, 0, 4), None);
+
+        let pages: Vec<_> = partition.pages(0, 4).expect("cluster 0").collect();
+        assert_eq!(pages, [&[0, 1, 2, 3][..], &[4, 5, 6, 7][..], &[8, 9][..]]);
+    }
+
+    #[test]
+    fn mask() {
+        let partition = Partition::new(hashmap! {0 => vec![3, 0], 2 => vec![1]}, vec![4]);
+        assert_eq!(
+            partition.mask(0, 6),
+            Some(array![true, false, false, true, false, false])
+        );
+        assert_eq!(
+            partition.mask(2, 5),
+            Some(array![false, true, false, false, false])
+        );
+        assert_eq!(partition.mask(1, 5), None);
+        assert_eq!(
+            partition.noise_mask(5),
+            array![false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let partition = Partition::new(HashMap::new(), vec![]);
+        assert!(partition.is_empty());
+        assert_eq!(partition.iter().count(), 0);
+        assert_eq!(partition, Partition::default());
+    }
+
+    #[test]
+    fn name_clusters() {
+        let data = array![[0., 0.], [0., 2.], [0., 1.], [5., 5.], [9., 9.]];
+        let mut partition = Partition::new(hashmap! {0 => vec![0, 1, 2], 4 => vec![3]}, vec![4]);
+        assert_eq!(partition.name(0), None);
+
+        let mut summaries = vec![];
+        partition.name_clusters(&data, 2, |summary| {
+            summaries.push(summary.clone());
+            format!("cluster {}", summary.id)
+        });
+        assert_eq!(summaries[0].size, 3);
+        assert_eq!(summaries[0].centroid, array![0., 1.]);
+        assert_eq!(summaries[0].exemplars[0], 2);
+        assert_eq!(summaries[0].exemplars.len(), 2);
+        assert_eq!(summaries[1].exemplars, [3]);
+        assert_eq!(partition.name(0), Some("cluster 0"));
+        assert_eq!(partition.name(4), Some("cluster 4"));
+        assert_eq!(partition.name(1), None);
+
+        let (clusters, noise): (HashMap<usize, Vec<usize>>, Vec<usize>) = partition.clone().into();
+        assert_eq!(Partition::new(clusters, noise).name(0), None);
+    }
+
+    #[test]
+    fn stats() {
+        let data = array![[0., 0.], [0., 2.], [0., 1.], [5., 5.], [9., 9.]];
+        let partition = Partition::new(hashmap! {0 => vec![0, 1, 2], 4 => vec![3]}, vec![4]);
+        let stats = partition.stats(&data, &Euclidean::default());
+        assert_eq!(stats.len(), 2);
+
+        let cluster = &stats[0];
+        assert_eq!((cluster.id, cluster.size, cluster.medoid), (0, 3, 2));
+        assert_eq!(cluster.centroid, array![0., 1.]);
+        // the pairwise distances are 1, 1 and 2
+        assert!((cluster.mean_distance - 4. / 3.).abs() < 1e-12);
+        assert!((cluster.diameter - 2.).abs() < f64::EPSILON);
+        assert!((cluster.density - 0.75).abs() < 1e-12);
+
+        let single = &stats[1];
+        assert_eq!((single.id, single.size, single.medoid), (4, 1, 3));
+        assert!(single.mean_distance.abs() < f64::EPSILON);
+        assert!(single.density.is_infinite());
+    }
+
+    #[test]
+    fn merge_within() {
+        let data = array![
+            [0., 0.],
+            [0., 2.],
+            [3., 1.],
+            [6., 1.],
+            [20., 0.],
+            [f64::NAN, 0.]
+        ];
+        let mut partition = Partition::new(
+            hashmap! {4 => vec![0, 1], 2 => vec![2], 7 => vec![3], 1 => vec![4], 9 => vec![5]},
+            vec![],
+        );
+        partition.name_clusters(&data, 0, |summary| format!("cluster {}", summary.id));
+        let merged = partition.merge_within(&data, &Euclidean::default(), 3.);
+        assert_eq!(merged.cluster_ids(), [1, 2, 9]);
+        assert_eq!(merged.members(2), Some(&[0, 1, 2, 3][..]));
+        assert_eq!(merged.name(2), Some("cluster 2"));
+        assert_eq!(merged.name(1), Some("cluster 1"));
+        assert_eq!(
+            partition.merge_within(&data, &Euclidean::default(), 0.),
+            partition
+        );
+        assert!(Partition::default()
+            .merge_within(&data, &Euclidean::default(), 1.)
+            .is_empty());
+    }
+
+    #[test]
+    fn split() {
+        let data = array![[0., 0.], [0., 1.], [5., 0.], [9., 9.], [5., 1.]];
+        let mut partition = Partition::new(hashmap! {1 => vec![0, 1, 2, 4], 6 => vec![3]}, vec![]);
+        partition.name_clusters(&data, 0, |summary| format!("cluster {}", summary.id));
+
+        let split = partition
+            .split(1, &data, |members| {
+                assert_eq!(members, array![[0., 0.], [0., 1.], [5., 0.], [5., 1.]]);
+                (
+                    hashmap! {3 => vec![3, 2], 0 => vec![], 5 => vec![1]},
+                    vec![0],
+                )
+            })
+            .expect("existing cluster");
+        assert_eq!(split.cluster_ids(), [1, 6, 7]);
+        assert_eq!(split.members(1), Some(&[2, 4][..]));
+        assert_eq!(split.members(7), Some(&[1][..]));
+        assert_eq!(split.noise(), [0]);
+        assert_eq!(split.name(1), None);
+        assert_eq!(split.name(6), Some("cluster 6"));
+
+        let all_noise = partition
+            .split(6, &data, |_| (HashMap::new(), vec![0]))
+            .expect("existing cluster");
+        assert_eq!(all_noise.cluster_ids(), [1]);
+        assert_eq!(all_noise.noise(), [3]);
+        assert!(partition.split(2, &data, |_| unreachable!()).is_none());
+    }
+
+    #[test]
+    fn renumber_by_size() {
+        let data = array![[0.], [1.], [2.], [5.], [6.], [9.], [10.], [11.], [20.]];
+        let mut partition = Partition::new(
+            hashmap! {2 => vec![3, 4], 5 => vec![5, 6, 7], 9 => vec![0, 1, 2]},
+            vec![8],
+        );
+        partition.name_clusters(&data, 1, |summary| format!("from {}", summary.id));
+
+        let renumbered = partition.renumber_by_size();
+        assert_eq!(renumbered.cluster_ids(), [0, 1, 2]);
+        assert_eq!(renumbered.members(0), Some(&[0, 1, 2][..]));
+        assert_eq!(renumbered.members(1), Some(&[5, 6, 7][..]));
+        assert_eq!(renumbered.members(2), Some(&[3, 4][..]));
+        assert_eq!(renumbered.name(0), Some("from 9"));
+        assert_eq!(renumbered.name(2), Some("from 2"));
+        assert_eq!(renumbered.noise(), [8]);
+        assert_eq!(renumbered.renumber_by_size(), renumbered);
+        assert!(Partition::default().renumber_by_size().is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn renumber_by_size_without_names() {
+        let partition = Partition::new(hashmap! {2 => vec![3, 4], 5 => vec![0, 1, 2]}, vec![5]);
+        let mut saved: serde_json::Value = serde_json::to_value(&partition).unwrap();
+        saved.as_object_mut().unwrap().remove("names");
+        let restored: Partition = serde_json::from_value(saved).unwrap();
+
+        let renumbered = restored.renumber_by_size();
+        assert_eq!(renumbered.cluster_ids(), [0, 1]);
+        assert_eq!(renumbered.members(0), Some(&[0, 1, 2][..]));
+        assert_eq!(renumbered.members(1), Some(&[3, 4][..]));
+        assert_eq!(renumbered.name(0), None);
+        assert_eq!(renumbered.noise(), [5]);
+    }
+
+    #[test]
+    fn labels() {
+        let partition = Partition::new(hashmap! {4 => vec![1, 3], 7 => vec![0]}, vec![2]);
+        let labels = partition.labels(6);
+        assert_eq!(labels, [Some(7), Some(4), None, Some(4), None, None]);
+
+        let from_labels = Partition::from_labels(&labels);
+        assert_eq!(from_labels.cluster_ids(), [4, 7]);
+        assert_eq!(from_labels.members(4), Some(&[1, 3][..]));
+        assert_eq!(from_labels.noise(), [2, 4, 5]);
+    }
+
+    #[test]
+    fn assignments() {
+        let partition = Partition::new(hashmap! {2 => vec![3, 0], 5 => vec![1]}, vec![2]);
+        let assignments = partition.assignments_with_confidence(&[0.9, 0.8, 0.1, 0.7]);
+        assert_eq!(
+            assignments,
+            [
+                PointAssignment {
+                    point_index: 0,
+                    cluster_id: Some(2),
+                    is_noise: false,
+                    confidence: Some(0.9),
+                },
+                PointAssignment {
+                    point_index: 1,
+                    cluster_id: Some(5),
+                    is_noise: false,
+                    confidence: Some(0.8),
+                },
+                PointAssignment {
+                    point_index: 2,
+                    cluster_id: None,
+                    is_noise: true,
+                    confidence: Some(0.1),
+                },
+                PointAssignment {
+                    point_index: 3,
+                    cluster_id: Some(2),
+                    is_noise: false,
+                    confidence: Some(0.7),
+                },
+            ]
+        );
+
+        let mut csv = Vec::new();
+        PointAssignment::write_csv(&assignments, &mut csv).expect("writing to a vector");
+        assert_eq!(
+            String::from_utf8(csv).expect("valid UTF-8"),
+            "point_index,cluster_id,is_noise,confidence\n\
+             0,2,false,0.9\n1,5,false,0.8\n2,,true,0.1\n3,2,false,0.7\n"
+        );
+        assert!(Partition::default().assignments().is_empty());
+    }
+}
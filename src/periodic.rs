@@ -0,0 +1,185 @@
+use ndarray::ArrayView1;
+use num_traits::Float;
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+/// The Euclidean distance with periodic boundary conditions, in which the
+/// coordinates of some dimensions wrap around with a given period, as on a
+/// torus.
+///
+/// The difference between two coordinates of a periodic dimension is the
+/// shorter way around, at most half the period, so points on either side of
+/// the boundary are close: angles and hues with a period of 360 degrees,
+/// times of day with a period of 24 hours, or positions in the box of a
+/// molecular dynamics simulation with the size of the box as period.
+///
+/// The distance is that of the flat torus, so it satisfies the triangle
+/// inequality and `Periodic` can replace `Euclidean` in any clustering
+/// algorithm, such as [`Dbscan`](crate::Dbscan), [`Optics`](crate::Optics)
+/// and [`HDbscan`](crate::HDbscan). The coordinates do not need to be
+/// reduced to a single period.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Dbscan, Fit, Periodic};
+///
+/// // a hue in degrees, and a non-periodic brightness
+/// let points = array![[355., 0.5], [2., 0.5], [10., 0.6], [180., 0.5], [185., 0.5]];
+/// let metric = Periodic::new(vec![Some(360.), None]);
+/// let (clusters, noise) = Dbscan::new(10., 2, metric).fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Periodic<A> {
+    /// The period of each dimension, or `None` for a dimension that does not
+    /// wrap around.
+    pub periods: Vec<Option<A>>,
+}
+
+impl<A: Float> Periodic<A> {
+    /// Creates the distance with the given period for each dimension.
+    ///
+    /// # Panics
+    /// Panics if a period is not positive and finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Periodic;
+    /// use petal_neighbors::distance::Metric;
+    ///
+    /// // a time of day in hours, and a non-periodic temperature
+    /// let metric = Periodic::new(vec![Some(24_f64), None]);
+    /// let d = metric.distance(&array![23., 20.].view(), &array![1., 23.].view());
+    /// assert!((d - 13_f64.sqrt()).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn new(periods: Vec<Option<A>>) -> Self {
+        assert!(
+            periods
+                .iter()
+                .flatten()
+                .all(|p| *p > A::zero() && p.is_finite()),
+            "the periods should be positive and finite"
+        );
+        Self { periods }
+    }
+
+    /// Creates the distance for `n_features` dimensions that all wrap around
+    /// with the same period.
+    ///
+    /// # Panics
+    /// Panics if `period` is not positive and finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Periodic;
+    /// use petal_neighbors::distance::Metric;
+    ///
+    /// // two angles in degrees
+    /// let metric = Periodic::uniform(360_f64, 2);
+    /// let d = metric.distance(&array![350., 10.].view(), &array![10., 350.].view());
+    /// assert!((d - 800_f64.sqrt()).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn uniform(period: A, n_features: usize) -> Self {
+        Self::new(vec![Some(period); n_features])
+    }
+}
+
+impl<A: Float> Metric<A> for Periodic<A> {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.rdistance(x1, x2).sqrt()
+    }
+
+    /// Returns the squared distance. Dimensions beyond `periods` do not wrap
+    /// around.
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        let mut periods = self.periods.iter();
+        x1.iter().zip(x2).fold(A::zero(), |acc, (a, b)| {
+            let mut d = (*a - *b).abs();
+            if let Some(Some(period)) = periods.next() {
+                d = d % *period;
+                d = d.min(*period - d);
+            }
+            acc + d * d
+        })
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d.sqrt()
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d * d
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, aview1};
+
+    use super::*;
+    use crate::{Dbscan, Fit, HDbscan, Optics};
+
+    #[test]
+    fn distance() {
+        let metric = Periodic::new(vec![Some(10.), None]);
+        let d = |a: &[f64], b: &[f64]| metric.distance(&aview1(a), &aview1(b));
+        assert!(f64::abs(d(&[1., 0.], &[9., 0.]) - 2.) < 1e-12);
+        assert!(f64::abs(d(&[1., 0.], &[29., 0.]) - 2.) < 1e-12);
+        assert!(f64::abs(d(&[-1., 0.], &[4., 0.]) - 5.) < 1e-12);
+        assert!(f64::abs(d(&[0., 1.], &[0., 9.]) - 8.) < 1e-12);
+        assert!(f64::abs(d(&[9., 1.], &[2., 4.]) - 18_f64.sqrt()) < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "the periods should be positive and finite")]
+    fn zero_period() {
+        let _ = Periodic::new(vec![Some(0.)]);
+    }
+
+    #[test]
+    fn wrap_around() {
+        // two groups across the boundary of a box of side 10
+        let data = array![
+            [0.1, 5.],
+            [9.9, 5.2],
+            [0.3, 4.9],
+            [9.7, 5.1],
+            [5., 0.1],
+            [5.2, 9.9],
+            [4.9, 0.3],
+            [5.1, 9.8],
+        ];
+        let expected = [vec![0, 1, 2, 3], vec![4, 5, 6, 7]];
+        let sorted = |clusters: std::collections::HashMap<usize, Vec<usize>>| {
+            let mut clusters: Vec<_> = clusters.into_values().collect();
+            for c in &mut clusters {
+                c.sort_unstable();
+            }
+            clusters.sort_unstable();
+            clusters
+        };
+
+        let metric = Periodic::uniform(10., 2);
+        let (clusters, noise) = Dbscan::new(1., 2, metric.clone()).fit(&data, None);
+        assert_eq!(sorted(clusters), expected);
+        assert!(noise.is_empty());
+
+        let (clusters, noise) = Optics::new(1., 2, metric.clone()).fit(&data, None);
+        assert_eq!(sorted(clusters), expected);
+        assert!(noise.is_empty());
+
+        let (clusters, noise, _) = HDbscan::new(3, metric).fit(&data, None);
+        assert_eq!(sorted(clusters), expected);
+        assert!(noise.is_empty());
+    }
+}
@@ -0,0 +1,598 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use num_traits::float::FloatCore;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "decomposition")]
+use crate::decomposition::{Pca, SparseRandomProjection};
+use crate::ensemble::Consensus;
+#[cfg(feature = "linfa")]
+use crate::linfa::ClusterModel;
+use crate::preprocessing::{MinMaxScaler, RobustScaler, StandardScaler};
+use crate::text::StringClustering;
+use crate::trajectory::Traclus;
+use crate::{
+    AffinityPropagation, AgglomerativeClustering, Birch, Ckmeans1D, Clique, CommunityDetection,
+    Dbscan, DbscanSweep, DenStream, Dendrogram, FuzzyCMeans, GaussianMixture, GridClustering,
+    HDbscan, KMeans, KMedoids, KModes, KPrototypes, Lof, MiniBatchKMeans, NeighborGraph, Optics,
+    OpticsModel, Partition, Pipeline, PredictionData, Som, SpectralClustering, SpectralEmbedding,
+    StDbscan,
+};
+
+/// The version of the layout of saved models. It changes whenever a model
+/// saved by an earlier version can no longer be loaded.
+///
+/// Version 2 stores the merges of [`AgglomerativeClustering`] as a
+/// [`Dendrogram`], with the number of points. Version 3 stores the core
+/// distance of each point of [`Optics`] as the distance to its
+/// `min_samples`-th nearest point instead of its nearest one.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// A serialization format for [`Persist`].
+///
+/// Each format is available only with the crate feature of the same name.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Format {
+    /// A compact binary format, requiring the `bincode` feature.
+    Bincode,
+
+    /// A human-readable format, requiring the `json` feature.
+    Json,
+
+    /// A compact binary format readable from other languages, requiring the
+    /// `msgpack` feature.
+    MessagePack,
+}
+
+impl Format {
+    /// Returns the format matching the extension of `path`: `bin` or
+    /// `bincode`, `json`, and `msgpack` or `mp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::Format;
+    ///
+    /// assert_eq!(Format::from_path("model.json"), Some(Format::Json));
+    /// assert_eq!(Format::from_path("model.MP"), Some(Format::MessagePack));
+    /// assert_eq!(Format::from_path("model.txt"), None);
+    /// ```
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "bin" | "bincode" => Some(Self::Bincode),
+            "json" => Some(Self::Json),
+            "msgpack" | "mp" => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when a model cannot be saved or loaded.
+#[derive(Debug)]
+pub enum PersistError {
+    /// Reading or writing failed.
+    Io(io::Error),
+
+    /// The model could not be encoded or decoded.
+    Serialization(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The crate was built without the feature of the format.
+    UnsupportedFormat(Format),
+
+    /// The model was saved with a different [`FORMAT_VERSION`].
+    Version {
+        found: u32,
+        expected: u32,
+        crate_version: String,
+    },
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Serialization(e) => write!(f, "serialization error: {e}"),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "{format:?} support is not enabled")
+            }
+            Self::Version {
+                found,
+                expected,
+                crate_version,
+            } => write!(
+                f,
+                "the model was saved by petal-clustering {crate_version} with format version \
+                 {found}, but version {expected} is expected"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Serialization(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PersistError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// The header written before every model.
+#[derive(Debug, Deserialize, Serialize)]
+struct Header {
+    format_version: u32,
+    crate_version: String,
+}
+
+impl Header {
+    fn current() -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    #[allow(dead_code)] // unused without any format features
+    fn check(self) -> Result<(), PersistError> {
+        if self.format_version == FORMAT_VERSION {
+            Ok(())
+        } else {
+            Err(PersistError::Version {
+                found: self.format_version,
+                expected: FORMAT_VERSION,
+                crate_version: self.crate_version,
+            })
+        }
+    }
+}
+
+/// Saving and loading of models, including their fitted state.
+///
+/// Models are written with a header holding [`FORMAT_VERSION`] and the
+/// version of this crate, and loading a model saved with another format
+/// version fails with [`PersistError::Version`].
+///
+/// This is implemented for the estimators, fitted models and results of this
+/// crate, such as [`HDbscan`], [`OpticsModel`] and [`Partition`], so fitted
+/// models can be stored once and reloaded later, e.g. to score new points
+/// with [`Lof`].
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "json")]
+/// # {
+/// use ndarray::array;
+/// use petal_clustering::{Fit, Format, KMeans, Persist, Predict};
+///
+/// let points = array![[1., 1.], [1.2, 0.8], [8., 8.], [8.2, 7.9]];
+/// let mut kmeans = KMeans::new(2);
+/// kmeans.fit(&points, None);
+///
+/// let mut buffer = Vec::new();
+/// kmeans.save_to(&mut buffer, Format::Json).unwrap();
+/// let mut loaded = KMeans::<f64>::load_from(buffer.as_slice(), Format::Json).unwrap();
+/// assert_eq!(loaded.predict(&points), kmeans.predict(&points));
+/// # }
+/// ```
+pub trait Persist: Serialize + DeserializeOwned {
+    /// Writes the model to the file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written, or if the format is
+    /// not enabled.
+    fn save<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<(), PersistError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.save_to(&mut writer, format)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a model from the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or decoded, if it was
+    /// saved with another format version, or if the format is not enabled.
+    fn load<P: AsRef<Path>>(path: P, format: Format) -> Result<Self, PersistError> {
+        Self::load_from(BufReader::new(File::open(path)?), format)
+    }
+
+    /// Writes the model to `writer`.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails, or if the format is not enabled.
+    fn save_to<W: Write>(&self, writer: W, format: Format) -> Result<(), PersistError> {
+        encode(self, writer, format)
+    }
+
+    /// Reads a model from `reader`.
+    ///
+    /// # Errors
+    /// Returns an error if reading or decoding fails, if the model was saved
+    /// with another format version, or if the format is not enabled.
+    fn load_from<R: Read>(reader: R, format: Format) -> Result<Self, PersistError> {
+        decode(reader, format)
+    }
+}
+
+/// Implements [`Persist`] for models of any parameters that make them
+/// serializable.
+macro_rules! impl_persist {
+    ($($model:ident $(<$($param:ident),+>)?),+ $(,)?) => {
+        $(
+            impl$(<$($param),+>)? Persist for $model$(<$($param),+>)?
+            where
+                Self: Serialize + DeserializeOwned,
+            {
+            }
+        )+
+    };
+}
+
+impl_persist!(
+    AffinityPropagation<A>,
+    AgglomerativeClustering<A, M>,
+    Birch<A>,
+    Ckmeans1D<A>,
+    Clique,
+    CommunityDetection<A, M>,
+    Consensus,
+    Dbscan<A, M>,
+    DbscanSweep<A>,
+    Dendrogram<A>,
+    DenStream<A>,
+    FuzzyCMeans<A>,
+    GaussianMixture<A>,
+    GridClustering<A>,
+    HDbscan<A, M>,
+    KMeans<A>,
+    KMedoids<A, M>,
+    KModes<T>,
+    KPrototypes<A>,
+    Lof<A, M>,
+    MiniBatchKMeans<A>,
+    MinMaxScaler<A>,
+    NeighborGraph<A>,
+    Optics<A, M>,
+    OpticsModel<A, M>,
+    Partition,
+    Pipeline<T, R, C>,
+    PredictionData<A, M>,
+    RobustScaler<A>,
+    Som<A>,
+    SpectralClustering<A, M>,
+    SpectralEmbedding<A, M>,
+    StandardScaler<A>,
+    StDbscan<A, M>,
+    StringClustering<C>,
+    Traclus<A>,
+);
+
+#[cfg(feature = "decomposition")]
+impl_persist!(Pca<A>, SparseRandomProjection<A>);
+
+#[cfg(feature = "linfa")]
+impl_persist!(ClusterModel<A, M>);
+
+#[allow(unused_mut, unused_variables)] // unused without any format features
+fn encode<T: Serialize + ?Sized, W: Write>(
+    model: &T,
+    mut writer: W,
+    format: Format,
+) -> Result<(), PersistError> {
+    let header = Header::current();
+    match format {
+        #[cfg(feature = "bincode")]
+        Format::Bincode => {
+            bincode::serialize_into(&mut writer, &header).map_err(serialization)?;
+            bincode::serialize_into(&mut writer, model).map_err(serialization)
+        }
+        #[cfg(feature = "json")]
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Envelope<'a, T: ?Sized> {
+                #[serde(flatten)]
+                header: Header,
+                model: &'a T,
+            }
+            let envelope = Envelope { header, model };
+            serde_json::to_writer(writer, &envelope).map_err(serialization)
+        }
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack => {
+            rmp_serde::encode::write_named(&mut writer, &header).map_err(serialization)?;
+            rmp_serde::encode::write_named(&mut writer, model).map_err(serialization)
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(PersistError::UnsupportedFormat(format)),
+    }
+}
+
+#[allow(unused_mut, unused_variables)] // unused without any format features
+fn decode<T: DeserializeOwned, R: Read>(mut reader: R, format: Format) -> Result<T, PersistError> {
+    match format {
+        #[cfg(feature = "bincode")]
+        Format::Bincode => {
+            let header: Header = bincode::deserialize_from(&mut reader).map_err(serialization)?;
+            header.check()?;
+            bincode::deserialize_from(reader).map_err(serialization)
+        }
+        #[cfg(feature = "json")]
+        Format::Json => {
+            // the header is checked before decoding the model, whose layout
+            // may differ between format versions
+            let mut envelope: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_reader(reader).map_err(serialization)?;
+            let model = envelope.remove("model").ok_or_else(|| {
+                serialization(<serde_json::Error as de::Error>::missing_field("model"))
+            })?;
+            let header: Header = serde_json::from_value(envelope.into()).map_err(serialization)?;
+            header.check()?;
+            serde_json::from_value(model).map_err(serialization)
+        }
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack => {
+            let header: Header = rmp_serde::from_read(&mut reader).map_err(serialization)?;
+            header.check()?;
+            rmp_serde::from_read(reader).map_err(serialization)
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(PersistError::UnsupportedFormat(format)),
+    }
+}
+
+#[allow(dead_code)] // unused without any format features
+fn serialization<E: std::error::Error + Send + Sync + 'static>(e: E) -> PersistError {
+    PersistError::Serialization(Box::new(e))
+}
+
+/// Serializes a float so that non-finite values survive formats without a
+/// representation for them, such as JSON, where they are written as the
+/// strings `"NaN"`, `"inf"` and `"-inf"`. Binary formats store floats as
+/// they are.
+///
+/// Use it with `#[serde(with = "crate::persistence::non_finite")]`.
+pub(crate) mod non_finite {
+    use super::{de, Deserialize, Deserializer, FloatCore, Serialize, Serializer};
+
+    pub(crate) fn serialize<A, S>(value: &A, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        A: FloatCore + Serialize,
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() || value.is_finite() {
+            value.serialize(serializer)
+        } else if value.is_nan() {
+            serializer.serialize_str("NaN")
+        } else if value.is_sign_positive() {
+            serializer.serialize_str("inf")
+        } else {
+            serializer.serialize_str("-inf")
+        }
+    }
+
+    pub(crate) fn deserialize<'de, A, D>(deserializer: D) -> Result<A, D::Error>
+    where
+        A: FloatCore + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr<A> {
+            Number(A),
+            Text(String),
+        }
+
+        if !deserializer.is_human_readable() {
+            return A::deserialize(deserializer);
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(value) => Ok(value),
+            Repr::Text(text) => match text.as_str() {
+                "NaN" => Ok(A::nan()),
+                "inf" => Ok(A::infinity()),
+                "-inf" => Ok(A::neg_infinity()),
+                _ => Err(de::Error::invalid_value(
+                    de::Unexpected::Str(&text),
+                    &"a number, \"NaN\", \"inf\" or \"-inf\"",
+                )),
+            },
+        }
+    }
+}
+
+/// Serializes a vector of floats with [`non_finite`].
+///
+/// Use it with `#[serde(with = "crate::persistence::non_finite_vec")]`.
+pub(crate) mod non_finite_vec {
+    use super::{Deserialize, Deserializer, FloatCore, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(bound(
+        serialize = "A: FloatCore + Serialize",
+        deserialize = "A: FloatCore + Deserialize<'de>"
+    ))]
+    struct Float<A>(#[serde(with = "super::non_finite")] A);
+
+    pub(crate) fn serialize<A, S>(values: &[A], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        A: FloatCore + Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(values.iter().map(|v| Float(*v)))
+    }
+
+    pub(crate) fn deserialize<'de, A, D>(deserializer: D) -> Result<Vec<A>, D::Error>
+    where
+        A: FloatCore + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let values = Vec::<Float<A>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| v.0).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Model {
+        labels: Vec<usize>,
+        #[serde(with = "non_finite_vec")]
+        reachability: Vec<f64>,
+        #[serde(with = "non_finite")]
+        inertia: f64,
+    }
+
+    impl Persist for Model {}
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Format::from_path("model.json"), Some(Format::Json));
+        assert_eq!(Format::from_path("a/model.BIN"), Some(Format::Bincode));
+        assert_eq!(
+            Format::from_path("model.msgpack"),
+            Some(Format::MessagePack)
+        );
+        assert_eq!(Format::from_path("model"), None);
+        assert_eq!(Format::from_path("model.txt"), None);
+    }
+
+    fn round_trip(format: Format) {
+        let model = Model {
+            labels: vec![0, 1, 1],
+            reachability: vec![f64::NAN, 0.1, f64::INFINITY, f64::NEG_INFINITY],
+            inertia: f64::INFINITY,
+        };
+        let mut buffer = Vec::new();
+        let result = model.save_to(&mut buffer, format);
+        let enabled = match format {
+            Format::Bincode => cfg!(feature = "bincode"),
+            Format::Json => cfg!(feature = "json"),
+            Format::MessagePack => cfg!(feature = "msgpack"),
+        };
+        if !enabled {
+            assert!(matches!(result, Err(PersistError::UnsupportedFormat(f)) if f == format));
+            return;
+        }
+        result.unwrap();
+
+        let loaded = Model::load_from(buffer.as_slice(), format).unwrap();
+        assert_eq!(loaded.labels, model.labels);
+        assert!(loaded.reachability[0].is_nan());
+        assert_eq!(loaded.reachability[1..], model.reachability[1..]);
+        assert!(loaded.inertia.is_infinite() && loaded.inertia > 0.);
+    }
+
+    #[test]
+    fn bincode() {
+        round_trip(Format::Bincode);
+    }
+
+    #[test]
+    fn json() {
+        round_trip(Format::Json);
+    }
+
+    #[test]
+    fn msgpack() {
+        round_trip(Format::MessagePack);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn dendrogram_layout() {
+        use ndarray::array;
+
+        use crate::{AgglomerativeClustering, Fit, Linkage, MetricConfig};
+
+        let points = array![[0., 0.], [0., 1.], [5., 5.]];
+        let mut model = AgglomerativeClustering::new(2, Linkage::Single, MetricConfig::Euclidean);
+        model.fit(&points, None);
+        let mut buffer = Vec::new();
+        model.save_to(&mut buffer, Format::Json).unwrap();
+        let loaded: AgglomerativeClustering<f64, MetricConfig> =
+            Persist::load_from(buffer.as_slice(), Format::Json).unwrap();
+        assert_eq!(loaded.hierarchy(), model.hierarchy());
+
+        // saved before the dendrogram kept the number of points
+        let mut saved: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        saved["format_version"] = 1.into();
+        saved["model"]["dendrogram"] = serde_json::json!([[0, 1, 1.0, 2], [2, 3, 6.4, 3]]);
+        let error = AgglomerativeClustering::<f64, MetricConfig>::load_from(
+            saved.to_string().as_bytes(),
+            Format::Json,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            PersistError::Version {
+                found: 1,
+                expected: FORMAT_VERSION,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn optics_core_distance() {
+        use ndarray::array;
+
+        use crate::{Fit, MetricConfig, Optics};
+
+        let points = array![[0.], [1.], [3.], [10.], [11.], [13.]];
+        let mut model = Optics::new(5., 3, MetricConfig::Euclidean);
+        model.fit(&points, None);
+        let mut buffer = Vec::new();
+        model.save_to(&mut buffer, Format::Json).unwrap();
+        let loaded: Optics<f64, MetricConfig> =
+            Persist::load_from(buffer.as_slice(), Format::Json).unwrap();
+        assert_eq!(loaded.ordering(), model.ordering());
+        assert_eq!(loaded.extract_dbscan(2.5), model.extract_dbscan(2.5));
+
+        // saved when the core distance was the distance to the nearest point
+        let mut saved: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        saved["format_version"] = 2.into();
+        let error =
+            Optics::<f64, MetricConfig>::load_from(saved.to_string().as_bytes(), Format::Json)
+                .unwrap_err();
+        assert!(matches!(
+            error,
+            PersistError::Version {
+                found: 2,
+                expected: FORMAT_VERSION,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn version_mismatch() {
+        let saved = r#"{"format_version":0,"crate_version":"0.1.0","model":{}}"#;
+        let error = crate::KMeans::<f64>::load_from(saved.as_bytes(), Format::Json).unwrap_err();
+        assert!(matches!(
+            error,
+            PersistError::Version {
+                found: 0,
+                expected: FORMAT_VERSION,
+                ..
+            }
+        ));
+    }
+}
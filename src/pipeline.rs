@@ -0,0 +1,204 @@
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use serde::{Deserialize, Serialize};
+
+use crate::{Fit, Predict, Transform};
+
+/// A sequence of a scaler, a reducer and a clusterer, fitted and applied as
+/// one model.
+///
+/// `fit` fits the scaler to the input, the reducer to the scaled input, and
+/// the clusterer to the reduced input, and returns the result of the
+/// clusterer. `predict` scales and reduces new points with the fitted steps
+/// before passing them to the clusterer. The scaler can be any of
+/// [`preprocessing`](crate::preprocessing), the reducer any of
+/// `decomposition`, and [`Passthrough`] skips a step.
+///
+/// The pipeline is serializable if its steps are, so the whole model,
+/// including the fitted scaling and projection, can be saved with
+/// [`Persist`](crate::Persist) or configured in a file.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::preprocessing::StandardScaler;
+/// use petal_clustering::{Fit, KMeans, Passthrough, Pipeline, Predict};
+///
+/// // the second feature is in different units, a thousand times larger
+/// let points = array![[1., 2000.], [1.2, 2100.], [1.1, 1900.], [8., 2100.], [8.2, 1900.], [7.9, 2000.]];
+/// let mut kmeans = KMeans::new(2);
+/// kmeans.n_init = 10;
+/// let mut pipeline = Pipeline::new(StandardScaler::default(), Passthrough, kmeans);
+/// let (clusters, _) = pipeline.fit(&points, None);
+///
+/// // the clusters follow the first feature once both are scaled
+/// let mut clusters: Vec<_> = clusters.into_values().collect();
+/// clusters.sort_unstable();
+/// assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4, 5]]);
+///
+/// let labels = pipeline.predict(&array![[1., 2100.], [8., 1900.]]);
+/// assert_ne!(labels[0], labels[1]);
+/// ```
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Pipeline<T, R, C> {
+    /// The first step, which scales the input.
+    pub scaler: T,
+
+    /// The second step, which reduces the dimension of the scaled input.
+    pub reducer: R,
+
+    /// The last step, which clusters the reduced input.
+    pub clusterer: C,
+}
+
+impl<T, R, C> Pipeline<T, R, C> {
+    /// Creates a pipeline from its three steps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::{KMeans, Passthrough, Pipeline};
+    ///
+    /// let scaler = MinMaxScaler::<f64>::default();
+    /// let pipeline = Pipeline::new(scaler, Passthrough, KMeans::<f64>::new(3));
+    /// assert_eq!(pipeline.clusterer.n_clusters, 3);
+    /// ```
+    #[must_use]
+    pub fn new(scaler: T, reducer: R, clusterer: C) -> Self {
+        Self {
+            scaler,
+            reducer,
+            clusterer,
+        }
+    }
+
+    /// Scales and reduces the given input with the fitted steps, returning
+    /// the points the clusterer sees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::{Fit, KMeans, Passthrough, Pipeline};
+    ///
+    /// let points = array![[0., 100.], [1., 300.], [2., 200.]];
+    /// let mut pipeline = Pipeline::new(MinMaxScaler::default(), Passthrough, KMeans::new(2));
+    /// pipeline.fit(&points, None);
+    ///
+    /// assert_eq!(pipeline.features(&points), array![[0., 0.], [0.5, 1.], [1., 0.5]]);
+    /// ```
+    #[must_use]
+    pub fn features<S, A>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+        T: Transform<ArrayBase<S, Ix2>, Array2<A>>,
+        R: Transform<Array2<A>, Array2<A>>,
+    {
+        self.reducer.transform(&self.scaler.transform(input))
+    }
+}
+
+/// Fits the steps of the pipeline in order to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `params`: The parameters passed to the clusterer.
+///
+/// # Returns
+/// The result of fitting the clusterer to the scaled and reduced input.
+impl<S, A, T, R, C, P, O> Fit<ArrayBase<S, Ix2>, P, O> for Pipeline<T, R, C>
+where
+    S: Data<Elem = A>,
+    T: Fit<ArrayBase<S, Ix2>, (), ()> + Transform<ArrayBase<S, Ix2>, Array2<A>>,
+    R: Fit<Array2<A>, (), ()> + Transform<Array2<A>, Array2<A>>,
+    C: Fit<Array2<A>, P, O>,
+{
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, params: Option<&P>) -> O {
+        self.scaler.fit(input, None);
+        let scaled = self.scaler.transform(input);
+        self.reducer.fit(&scaled, None);
+        let reduced = self.reducer.transform(&scaled);
+        self.clusterer.fit(&reduced, params)
+    }
+}
+
+/// Applies the clusterer to the given input, scaled and reduced by the
+/// fitted steps.
+///
+/// # Panics
+/// Panics if a step has not been fitted.
+impl<S, A, T, R, C, O> Predict<ArrayBase<S, Ix2>, O> for Pipeline<T, R, C>
+where
+    S: Data<Elem = A>,
+    T: Transform<ArrayBase<S, Ix2>, Array2<A>>,
+    R: Transform<Array2<A>, Array2<A>>,
+    C: Predict<Array2<A>, O>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> O {
+        let features = self.features(input);
+        self.clusterer.predict(&features)
+    }
+}
+
+/// A step of a [`Pipeline`] that leaves the input unchanged.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Passthrough;
+
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for Passthrough
+where
+    S: Data<Elem = A>,
+{
+    fn fit(&mut self, _input: &ArrayBase<S, Ix2>, _params: Option<&()>) {}
+}
+
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for Passthrough
+where
+    A: Clone,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        input.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+    use crate::preprocessing::MinMaxScaler;
+    use crate::KMeans;
+
+    #[test]
+    fn pipeline() {
+        let data = array![[0., 100.], [1., 300.], [0., 200.], [10., 100.], [11., 200.]];
+        let mut pipeline = Pipeline::new(MinMaxScaler::default(), Passthrough, KMeans::new(2));
+        let (clusters, noise) = pipeline.fit(&data, None);
+        assert_eq!(clusters.len(), 2);
+        assert!(noise.is_empty());
+
+        let features = pipeline.features(&data);
+        assert!(features.iter().all(|v| (0. ..=1.).contains(v)));
+        let labels = pipeline.predict(&data);
+        assert_eq!(labels[0], labels[1]);
+        assert_ne!(labels[0], labels[3]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn persist() {
+        use crate::{Format, Persist};
+
+        let data = array![[0., 1.], [0.2, 1.], [5., 6.], [5.1, 6.2]];
+        let mut pipeline = Pipeline::new(MinMaxScaler::default(), Passthrough, KMeans::new(2));
+        pipeline.fit(&data, None);
+
+        let mut buffer = Vec::new();
+        pipeline.save_to(&mut buffer, Format::Json).unwrap();
+        let mut loaded: Pipeline<MinMaxScaler<f64>, Passthrough, KMeans<f64>> =
+            Persist::load_from(buffer.as_slice(), Format::Json).unwrap();
+        assert_eq!(loaded.predict(&data), pipeline.predict(&data));
+    }
+}
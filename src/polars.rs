@@ -0,0 +1,158 @@
+//! Conversions between Polars data frames and the inputs and outputs of the
+//! clusterers, enabled with the `polars` feature.
+//!
+//! Data frames with one column per feature are copied into a column-major
+//! array with [`data_frame_to_array`], one copy per column, since Polars
+//! stores each column, and possibly each chunk of it, in a buffer of its own.
+//!
+//! # Examples
+//!
+//! ```
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{polars as pl, Dbscan, Fit};
+//! use polars::prelude::*;
+//!
+//! let df = df!(
+//!     "x" => [1., 2., 2., 8., 8., 25.],
+//!     "y" => [2., 2., 2.3, 7., 8., 80.],
+//! )
+//! .unwrap();
+//! let points = pl::data_frame_to_array::<Float64Type>(&df).unwrap();
+//! let (clusters, noise) = Dbscan::new(3., 2, Euclidean::default()).fit(&points, None);
+//!
+//! let labels = pl::labels("label", df.height(), &clusters, &noise);
+//! let labels = labels.i64().unwrap();
+//! assert_eq!(labels.get(0), Some(0));
+//! assert_eq!(labels.get(3), Some(1));
+//! assert_eq!(labels.get(5), None);
+//! ```
+
+use std::collections::HashMap;
+
+use ndarray::{Array2, ShapeBuilder};
+use polars::prelude::{
+    DataFrame, NamedFrom, PlSmallStr, PolarsError, PolarsNumericType, PolarsResult, Series,
+};
+
+/// Copies the columns of `df` into an array with one row per row of `df` and
+/// one column per column of `df`.
+///
+/// # Errors
+/// Returns an error if a column is not of type `T`, or has null values.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::polars::data_frame_to_array;
+/// use polars::prelude::*;
+///
+/// let df = df!("x" => [1., 2.], "y" => [3., 4.]).unwrap();
+/// let points = data_frame_to_array::<Float64Type>(&df).unwrap();
+/// assert_eq!(points, array![[1., 3.], [2., 4.]]);
+///
+/// // the columns should all have the requested type
+/// assert!(data_frame_to_array::<Float32Type>(&df).is_err());
+/// ```
+pub fn data_frame_to_array<T>(df: &DataFrame) -> PolarsResult<Array2<T::Native>>
+where
+    T: PolarsNumericType,
+{
+    let mut values = Vec::with_capacity(df.height() * df.width());
+    for column in df.get_columns() {
+        let series = column.as_materialized_series();
+        let column = series.unpack::<T>().map_err(|_| {
+            PolarsError::SchemaMismatch(
+                format!(
+                    "column `{}` is of type {}, not {}",
+                    series.name(),
+                    series.dtype(),
+                    T::get_static_dtype()
+                )
+                .into(),
+            )
+        })?;
+        if column.null_count() > 0 {
+            return Err(PolarsError::ComputeError(
+                format!("column `{}` has null values", series.name()).into(),
+            ));
+        }
+        for chunk in column.downcast_iter() {
+            values.extend_from_slice(chunk.values());
+        }
+    }
+    Array2::from_shape_vec((df.height(), df.width()).f(), values)
+        .map_err(|e| PolarsError::ComputeError(e.to_string().into()))
+}
+
+/// Returns a series named `name` with the cluster ID of each of `n` points,
+/// with nulls for noise.
+///
+/// # Panics
+/// Panics if a point is not less than `n`, or if a cluster ID does not fit in
+/// an `i64`.
+///
+/// # Examples
+///
+/// ```
+/// use maplit::hashmap;
+/// use petal_clustering::polars::labels;
+///
+/// let series = labels("cluster", 4, &hashmap! {0 => vec![0, 2], 1 => vec![3]}, &[1]);
+/// let ids: Vec<_> = series.i64().unwrap().into_iter().collect();
+/// assert_eq!(ids, [Some(0), None, Some(0), Some(1)]);
+/// ```
+#[allow(clippy::implicit_hasher)]
+pub fn labels(
+    name: &str,
+    n: usize,
+    clusters: &HashMap<usize, Vec<usize>>,
+    noise: &[usize],
+) -> Series {
+    let mut labels = vec![None; n];
+    for (id, members) in clusters {
+        let id = i64::try_from(*id).expect("cluster ID fits in an i64");
+        for &i in members {
+            labels[i] = Some(id);
+        }
+    }
+    debug_assert!(noise.iter().all(|i| labels[*i].is_none()));
+    Series::new(PlSmallStr::from_str(name), labels)
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+    use ndarray::array;
+    use polars::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn data_frame() {
+        let df = df!("x" => [1f32, 2., 3.], "y" => [4f32, 5., 6.]).unwrap();
+        let points = data_frame_to_array::<Float32Type>(&df).unwrap();
+        assert_eq!(points, array![[1., 4.], [2., 5.], [3., 6.]]);
+
+        let error = data_frame_to_array::<Float64Type>(&df).unwrap_err();
+        assert!(error.to_string().contains("column `x` is of type f32"));
+
+        let mut chunked = df!("x" => [1., 2.]).unwrap();
+        chunked.vstack_mut(&df!("x" => [3.]).unwrap()).unwrap();
+        let points = data_frame_to_array::<Float64Type>(&chunked).unwrap();
+        assert_eq!(points, array![[1.], [2.], [3.]]);
+
+        let df = df!("x" => [Some(1.), None]).unwrap();
+        assert!(data_frame_to_array::<Float64Type>(&df).is_err());
+    }
+
+    #[test]
+    fn labels() {
+        let labels = super::labels("label", 4, &hashmap! {0 => vec![0, 2], 1 => vec![3]}, &[1]);
+        assert_eq!(labels.name().as_str(), "label");
+        assert_eq!(
+            labels.i64().unwrap().iter().collect::<Vec<_>>(),
+            [Some(0), None, Some(0), Some(1)]
+        );
+    }
+}
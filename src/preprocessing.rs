@@ -0,0 +1,779 @@
+//! Feature scaling, so that every feature contributes to the distances
+//! between points in proportion to its spread rather than to its unit.
+//!
+//! Each scaler is fitted to an input with [`Fit`], maps inputs with the
+//! same features to the scaled space with [`Transform`], and maps them back
+//! with `inverse_transform`. A feature without any spread is only shifted.
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_clustering::preprocessing::StandardScaler;
+//! use petal_clustering::{Fit, Transform};
+//!
+//! // the second feature is in different units, a thousand times larger
+//! let points = array![[1_f64, 2000.], [2., 2000.], [2., 2300.], [8., 7000.], [8., 8000.]];
+//! let mut scaler = StandardScaler::default();
+//! scaler.fit(&points, None);
+//!
+//! let scaled = scaler.transform(&points);
+//! assert!(scaled.column(1).iter().all(|v| v.abs() < 2.));
+//! assert!((scaler.inverse_transform(&scaled)[[2, 1]] - 2300.).abs() < 1e-9);
+//! ```
+//!
+//! [`Deduplicated`] collapses identical points into one weighted point, so
+//! that inputs with many repeated measurements, such as quantized sensor
+//! readings, can be clustered in time and memory proportional to the number
+//! of distinct points.
+
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, ArrayView1, Axis, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::sum::sum;
+use crate::{Fit, Transform};
+
+/// Scales each feature to zero mean and unit variance.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StandardScaler<A> {
+    /// Whether to subtract the mean of each feature.
+    pub with_mean: bool,
+
+    /// Whether to divide each feature by its standard deviation.
+    pub with_std: bool,
+
+    center: Vec<A>,
+    scale: Vec<A>,
+}
+
+/// Scales each feature linearly to `feature_range`, mapping its minimum to
+/// the lower bound and its maximum to the upper bound.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MinMaxScaler<A> {
+    /// The range of the scaled features.
+    pub feature_range: (A, A),
+
+    center: Vec<A>,
+    scale: Vec<A>,
+}
+
+/// Scales each feature with statistics that are robust to outliers: it
+/// subtracts the median and divides by the difference between two
+/// quantiles, the interquartile range by default.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RobustScaler<A> {
+    /// Whether to subtract the median of each feature.
+    pub with_centering: bool,
+
+    /// Whether to divide each feature by its quantile range.
+    pub with_scaling: bool,
+
+    /// The lower and upper quantiles, between 0 and 1, of the range each
+    /// feature is divided by.
+    pub quantile_range: (A, A),
+
+    center: Vec<A>,
+    scale: Vec<A>,
+}
+
+impl<A> Default for StandardScaler<A> {
+    fn default() -> Self {
+        Self {
+            with_mean: true,
+            with_std: true,
+            center: vec![],
+            scale: vec![],
+        }
+    }
+}
+
+impl<A: FloatCore> Default for MinMaxScaler<A> {
+    fn default() -> Self {
+        Self::new((A::zero(), A::one()))
+    }
+}
+
+impl<A: FloatCore> Default for RobustScaler<A> {
+    fn default() -> Self {
+        let quarter = A::from(0.25).expect("valid float");
+        Self {
+            with_centering: true,
+            with_scaling: true,
+            quantile_range: (quarter, A::one() - quarter),
+            center: vec![],
+            scale: vec![],
+        }
+    }
+}
+
+impl<A> MinMaxScaler<A> {
+    /// Creates a scaler to the given range of the scaled features.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::{Fit, Transform};
+    ///
+    /// let points = array![[0., 10.], [5., 20.], [10., 30.]];
+    /// let mut scaler = MinMaxScaler::new((-1., 1.));
+    /// scaler.fit(&points, None);
+    /// assert_eq!(scaler.transform(&points), array![[-1., -1.], [0., 0.], [1., 1.]]);
+    /// ```
+    #[must_use]
+    pub fn new(feature_range: (A, A)) -> Self {
+        Self {
+            feature_range,
+            center: vec![],
+            scale: vec![],
+        }
+    }
+}
+
+/// Computes the mean and standard deviation of each feature of the given
+/// input.
+///
+/// # Returns
+/// Nothing; the statistics are kept for [`Transform::transform`].
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for StandardScaler<A>
+where
+    A: Float + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, _params: Option<&()>) {
+        let n = A::from_usize(input.nrows().max(1)).expect("valid float");
+        (self.center, self.scale) = input
+            .columns()
+            .into_iter()
+            .map(|column| {
+                let mean = sum(column.iter().copied()) / n;
+                let std = Float::sqrt(sum(column.iter().map(|v| (*v - mean) * (*v - mean))) / n);
+                (
+                    if self.with_mean { mean } else { A::zero() },
+                    if self.with_std { spread(std) } else { A::one() },
+                )
+            })
+            .unzip();
+    }
+}
+
+/// Computes the range of each feature of the given input.
+///
+/// # Returns
+/// Nothing; the ranges are kept for [`Transform::transform`].
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for MinMaxScaler<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, _params: Option<&()>) {
+        let (lower, upper) = self.feature_range;
+        (self.center, self.scale) = input
+            .columns()
+            .into_iter()
+            .map(|column| {
+                let (min, max) = column
+                    .iter()
+                    .fold((A::infinity(), A::neg_infinity()), |(min, max), v| {
+                        (min.min(*v), max.max(*v))
+                    });
+                if min > max {
+                    return (A::zero(), A::one());
+                }
+                let scale = spread((max - min) / (upper - lower));
+                (min - lower * scale, scale)
+            })
+            .unzip();
+    }
+}
+
+/// Computes the median and quantile range of each feature of the given
+/// input, ignoring NaNs.
+///
+/// # Returns
+/// Nothing; the statistics are kept for [`Transform::transform`].
+///
+/// # Panics
+/// Panics if `quantile_range` is not within `[0, 1]`.
+impl<S, A> Fit<ArrayBase<S, Ix2>, (), ()> for RobustScaler<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn fit(&mut self, input: &ArrayBase<S, Ix2>, _params: Option<&()>) {
+        let (lower, upper) = self.quantile_range;
+        assert!(
+            A::zero() <= lower && lower <= upper && upper <= A::one(),
+            "`quantile_range` must be within [0, 1]"
+        );
+        let half = A::from(0.5).expect("valid float");
+        (self.center, self.scale) = input
+            .columns()
+            .into_iter()
+            .map(|column| {
+                let mut values: Vec<A> = column.iter().copied().filter(|v| !v.is_nan()).collect();
+                values.sort_unstable_by(|a, b| a.partial_cmp(b).expect("not NaN"));
+                let center = if self.with_centering {
+                    quantile(&values, half)
+                } else {
+                    A::zero()
+                };
+                let scale = if self.with_scaling {
+                    spread(quantile(&values, upper) - quantile(&values, lower))
+                } else {
+                    A::one()
+                };
+                (center, scale)
+            })
+            .unzip();
+    }
+}
+
+/// Scales each feature of the given input.
+///
+/// # Panics
+/// Panics if the scaler has not been fitted to an input with the same number
+/// of features.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for StandardScaler<A>
+where
+    A: Float + FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        scale(input, &self.center, &self.scale)
+    }
+}
+
+impl<A: FloatCore> StandardScaler<A> {
+    /// Maps scaled points back to the space of the input the scaler was
+    /// fitted to.
+    ///
+    /// # Panics
+    /// Panics if the scaler has not been fitted to an input with the same
+    /// number of features.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::StandardScaler;
+    /// use petal_clustering::{Fit, Transform};
+    ///
+    /// let points = array![[1_f64, 10.], [2., 20.], [3., 60.]];
+    /// let mut scaler = StandardScaler::default();
+    /// scaler.fit(&points, None);
+    ///
+    /// let restored = scaler.inverse_transform(&scaler.transform(&points));
+    /// assert!((&restored - &points).iter().all(|d| d.abs() < 1e-9));
+    /// ```
+    pub fn inverse_transform<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        unscale(input, &self.center, &self.scale)
+    }
+
+    /// Returns the mean of each feature, or zeros without `with_mean`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::StandardScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// let points = array![[1., 10.], [2., 20.], [3., 60.]];
+    /// let mut scaler = StandardScaler::default();
+    /// scaler.fit(&points, None);
+    /// assert_eq!(scaler.center(), array![2., 30.]);
+    ///
+    /// scaler.with_mean = false;
+    /// scaler.fit(&points, None);
+    /// assert_eq!(scaler.center(), array![0., 0.]);
+    /// ```
+    #[must_use]
+    pub fn center(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.center)
+    }
+
+    /// Returns the standard deviation of each feature, or ones without
+    /// `with_std`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::StandardScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// let points = array![[1_f64, 5.], [3., 5.]];
+    /// let mut scaler = StandardScaler::default();
+    /// scaler.fit(&points, None);
+    ///
+    /// // the second feature has no spread, so it is only shifted
+    /// let scale = scaler.scale();
+    /// assert!((scale[0] - 1.).abs() < 1e-12);
+    /// assert_eq!(scale[1], 1.);
+    /// ```
+    #[must_use]
+    pub fn scale(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.scale)
+    }
+}
+
+/// Scales each feature of the given input.
+///
+/// # Panics
+/// Panics if the scaler has not been fitted to an input with the same number
+/// of features.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for MinMaxScaler<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        scale(input, &self.center, &self.scale)
+    }
+}
+
+impl<A: FloatCore> MinMaxScaler<A> {
+    /// Maps scaled points back to the space of the input the scaler was
+    /// fitted to.
+    ///
+    /// # Panics
+    /// Panics if the scaler has not been fitted to an input with the same
+    /// number of features.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// let mut scaler = MinMaxScaler::default();
+    /// scaler.fit(&array![[0., 10.], [10., 30.]], None);
+    /// assert_eq!(scaler.inverse_transform(&array![[0.5, 0.5]]), array![[5., 20.]]);
+    /// ```
+    pub fn inverse_transform<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        unscale(input, &self.center, &self.scale)
+    }
+
+    /// Returns the value subtracted from each feature before scaling, which
+    /// maps its minimum to the lower bound of `feature_range`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// let mut scaler = MinMaxScaler::default();
+    /// scaler.fit(&array![[0., 10.], [10., 30.]], None);
+    /// assert_eq!(scaler.center(), array![0., 10.]);
+    /// ```
+    #[must_use]
+    pub fn center(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.center)
+    }
+
+    /// Returns the value each feature is divided by, its range over the
+    /// width of `feature_range`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::MinMaxScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// let mut scaler = MinMaxScaler::new((0., 2.));
+    /// scaler.fit(&array![[0., 10.], [10., 30.]], None);
+    /// assert_eq!(scaler.scale(), array![5., 10.]);
+    /// ```
+    #[must_use]
+    pub fn scale(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.scale)
+    }
+}
+
+/// Scales each feature of the given input.
+///
+/// # Panics
+/// Panics if the scaler has not been fitted to an input with the same number
+/// of features.
+impl<S, A> Transform<ArrayBase<S, Ix2>, Array2<A>> for RobustScaler<A>
+where
+    A: FloatCore + FromPrimitive,
+    S: Data<Elem = A>,
+{
+    fn transform(&self, input: &ArrayBase<S, Ix2>) -> Array2<A> {
+        scale(input, &self.center, &self.scale)
+    }
+}
+
+impl<A: FloatCore> RobustScaler<A> {
+    /// Maps scaled points back to the space of the input the scaler was
+    /// fitted to.
+    ///
+    /// # Panics
+    /// Panics if the scaler has not been fitted to an input with the same
+    /// number of features.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::RobustScaler;
+    /// use petal_clustering::{Fit, Transform};
+    ///
+    /// let points = array![[1_f64], [2.], [3.], [4.], [100.]];
+    /// let mut scaler = RobustScaler::default();
+    /// scaler.fit(&points, None);
+    ///
+    /// let restored = scaler.inverse_transform(&scaler.transform(&points));
+    /// assert!((&restored - &points).iter().all(|d| d.abs() < 1e-9));
+    /// ```
+    pub fn inverse_transform<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        unscale(input, &self.center, &self.scale)
+    }
+
+    /// Returns the median of each feature, or zeros without
+    /// `with_centering`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::RobustScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// // the median is not pulled by the outlier
+    /// let mut scaler = RobustScaler::default();
+    /// scaler.fit(&array![[1.], [2.], [3.], [4.], [100.]], None);
+    /// assert_eq!(scaler.center(), array![3.]);
+    /// ```
+    #[must_use]
+    pub fn center(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.center)
+    }
+
+    /// Returns the quantile range of each feature, or ones without
+    /// `with_scaling`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::RobustScaler;
+    /// use petal_clustering::Fit;
+    ///
+    /// // the interquartile range, from 2 to 4
+    /// let mut scaler = RobustScaler::default();
+    /// scaler.fit(&array![[1.], [2.], [3.], [4.], [100.]], None);
+    /// assert_eq!(scaler.scale(), array![2.]);
+    /// ```
+    #[must_use]
+    pub fn scale(&self) -> ArrayView1<'_, A> {
+        ArrayView1::from(&self.scale)
+    }
+}
+
+/// Subtracts `center` from each row of `input` and divides the result by
+/// `scale`, feature by feature.
+fn scale<S, A>(input: &ArrayBase<S, Ix2>, center: &[A], scale: &[A]) -> Array2<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    check_features(scale.len(), input.ncols());
+    let mut output = input.to_owned();
+    for mut row in output.rows_mut() {
+        for ((v, c), s) in row.iter_mut().zip(center).zip(scale) {
+            *v = (*v - *c) / *s;
+        }
+    }
+    output
+}
+
+/// The distinct points of an input, each weighted by the number of points
+/// of the input equal to it.
+///
+/// Points are equal if all their coordinates are, with `0.0` equal to
+/// `-0.0`, and a NaN equal to a NaN with the same bits. Clusters of the
+/// distinct points are mapped back to the points of the input with
+/// [`Deduplicated::expand`].
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::preprocessing::Deduplicated;
+///
+/// let points = array![[1., 2.], [8., 7.], [1., 2.], [1., 2.]];
+/// let deduplicated = Deduplicated::new(&points);
+/// assert_eq!(deduplicated.points, array![[1., 2.], [8., 7.]]);
+/// assert_eq!(deduplicated.weights, [3, 1]);
+/// assert_eq!(deduplicated.inverse, [0, 1, 0, 0]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deduplicated<A> {
+    /// The distinct points, in the order of their first occurrence.
+    pub points: Array2<A>,
+
+    /// The number of points of the input equal to each distinct point.
+    pub weights: Vec<usize>,
+
+    /// The index of the distinct point equal to each point of the input.
+    pub inverse: Vec<usize>,
+}
+
+impl<A: FloatCore> Deduplicated<A> {
+    /// Finds the distinct points of the given input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::Deduplicated;
+    ///
+    /// let points = array![[0., -0.], [1., 1.], [-0., 0.]];
+    /// let deduplicated = Deduplicated::new(&points);
+    /// assert_eq!(deduplicated.weights, [2, 1]);
+    /// ```
+    #[must_use]
+    pub fn new<S>(input: &ArrayBase<S, Ix2>) -> Self
+    where
+        S: Data<Elem = A>,
+    {
+        let mut index: HashMap<Vec<(u64, i16, i8)>, usize> = HashMap::new();
+        let mut first = Vec::new();
+        let mut weights = Vec::new();
+        let inverse = input
+            .rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let key = point
+                    .iter()
+                    .map(|v| {
+                        // `-0.0` decodes with a negative sign
+                        let v = if *v == A::zero() { A::zero() } else { *v };
+                        v.integer_decode()
+                    })
+                    .collect();
+                let j = *index.entry(key).or_insert_with(|| {
+                    first.push(i);
+                    weights.push(0);
+                    weights.len() - 1
+                });
+                weights[j] += 1;
+                j
+            })
+            .collect();
+        Self {
+            points: input.select(Axis(0), &first),
+            weights,
+            inverse,
+        }
+    }
+
+    /// Maps the clusters and noise of the distinct points to the points of
+    /// the input, keeping the cluster IDs. The members of each cluster and
+    /// the noise points are in increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maplit::hashmap;
+    /// use ndarray::array;
+    /// use petal_clustering::preprocessing::Deduplicated;
+    ///
+    /// let points = array![[1., 2.], [8., 7.], [1., 2.], [1., 2.]];
+    /// let deduplicated = Deduplicated::new(&points);
+    ///
+    /// // clusters of the distinct points, [1., 2.] and [8., 7.]
+    /// let (clusters, noise) = deduplicated.expand((hashmap! {0 => vec![0]}, vec![1]));
+    /// assert_eq!(clusters[&0], [0, 2, 3]);
+    /// assert_eq!(noise, [1]);
+    /// ```
+    #[must_use]
+    pub fn expand(
+        &self,
+        (clusters, noise): (HashMap<usize, Vec<usize>>, Vec<usize>),
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        let mut members = vec![Vec::new(); self.weights.len()];
+        for (i, j) in self.inverse.iter().enumerate() {
+            members[*j].push(i);
+        }
+        let expand = |points: Vec<usize>| {
+            let mut expanded: Vec<_> = points
+                .into_iter()
+                .flat_map(|j| members[j].iter().copied())
+                .collect();
+            expanded.sort_unstable();
+            expanded
+        };
+        let clusters = clusters
+            .into_iter()
+            .map(|(id, points)| (id, expand(points)))
+            .collect();
+        (clusters, expand(noise))
+    }
+}
+
+/// Reverses [`scale`].
+fn unscale<S, A>(input: &ArrayBase<S, Ix2>, center: &[A], scale: &[A]) -> Array2<A>
+where
+    A: FloatCore,
+    S: Data<Elem = A>,
+{
+    check_features(scale.len(), input.ncols());
+    let mut output = input.to_owned();
+    for mut row in output.rows_mut() {
+        for ((v, c), s) in row.iter_mut().zip(center).zip(scale) {
+            *v = *v * *s + *c;
+        }
+    }
+    output
+}
+
+fn check_features(fitted: usize, given: usize) {
+    assert!(fitted > 0 || given == 0, "the scaler has not been fitted");
+    assert_eq!(
+        fitted, given,
+        "the input has {given} features, but the scaler was fitted to {fitted}"
+    );
+}
+
+/// Returns the given spread, or one if it is zero or not finite so that the
+/// feature is only shifted.
+fn spread<A: FloatCore>(spread: A) -> A {
+    if spread.is_finite() && spread != A::zero() {
+        spread
+    } else {
+        A::one()
+    }
+}
+
+/// Returns the `q`-quantile of sorted values with linear interpolation, or
+/// zero if there are none.
+fn quantile<A: FloatCore + FromPrimitive>(sorted: &[A], q: A) -> A {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return A::zero();
+    };
+    let position = q * A::from_usize(last).expect("valid float");
+    let lower = position.floor().to_usize().unwrap_or(0).min(last);
+    let upper = (lower + 1).min(last);
+    let fraction = position - position.floor();
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    fn assert_close(a: &Array2<f64>, b: &Array2<f64>) {
+        assert_eq!(a.dim(), b.dim());
+        assert!(
+            a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-12),
+            "{a} != {b}"
+        );
+    }
+
+    #[test]
+    fn standard() {
+        let data = array![[1., 0., 5.], [1., 4., 5.], [3., 0., 5.], [3., 4., 5.]];
+        let mut scaler = StandardScaler::default();
+        scaler.fit(&data, None);
+        assert_eq!(scaler.center(), array![2., 2., 5.]);
+        assert_eq!(scaler.scale(), array![1., 2., 1.]);
+        assert_close(
+            &scaler.transform(&data),
+            &array![[-1., -1., 0.], [-1., 1., 0.], [1., -1., 0.], [1., 1., 0.]],
+        );
+        assert_close(&scaler.inverse_transform(&scaler.transform(&data)), &data);
+
+        scaler.with_mean = false;
+        scaler.fit(&data, None);
+        assert_eq!(scaler.center(), array![0., 0., 0.]);
+    }
+
+    #[test]
+    fn min_max() {
+        let data = array![[1., -10.], [3., 0.], [2., 10.]];
+        let mut scaler = MinMaxScaler::new((-1., 1.));
+        scaler.fit(&data, None);
+        assert_close(
+            &scaler.transform(&data),
+            &array![[-1., -1.], [1., 0.], [0., 1.]],
+        );
+        assert_close(&scaler.transform(&array![[5., 20.]]), &array![[3., 2.]]);
+        assert_close(&scaler.inverse_transform(&scaler.transform(&data)), &data);
+    }
+
+    #[test]
+    fn robust() {
+        let data = array![[1.], [2.], [3.], [4.], [5.], [1000.]];
+        let mut scaler = RobustScaler::default();
+        scaler.fit(&data, None);
+        assert_eq!(scaler.center(), array![3.5]);
+        assert_eq!(scaler.scale(), array![2.5]);
+        assert_close(&scaler.transform(&array![[6.]]), &array![[1.]]);
+        assert_close(&scaler.inverse_transform(&scaler.transform(&data)), &data);
+    }
+
+    #[test]
+    fn quantile() {
+        let sorted = [1., 2., 4., 8.];
+        assert_close(&array![[super::quantile(&sorted, 0.)]], &array![[1.]]);
+        assert_close(&array![[super::quantile(&sorted, 0.5)]], &array![[3.]]);
+        assert_close(&array![[super::quantile(&sorted, 1.)]], &array![[8.]]);
+        assert_close(&array![[super::quantile::<f64>(&[], 0.5)]], &array![[0.]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "fitted to 2")]
+    fn transform_other_features() {
+        let mut scaler = MinMaxScaler::default();
+        scaler.fit(&array![[0., 1.], [1., 2.]], None);
+        let _ = scaler.transform(&array![[0., 1., 2.]]);
+    }
+
+    #[test]
+    fn deduplicated() {
+        let data = array![
+            [0., 1.],
+            [2., 3.],
+            [-0., 1.],
+            [f64::NAN, 1.],
+            [f64::NAN, 1.]
+        ];
+        let deduplicated = Deduplicated::new(&data);
+        assert_eq!(deduplicated.weights, [2, 1, 2]);
+        assert_eq!(deduplicated.inverse, [0, 1, 0, 2, 2]);
+        assert_eq!(deduplicated.points.row(1), array![2., 3.]);
+
+        let clusters = HashMap::from([(0, vec![2, 0])]);
+        let (clusters, noise) = deduplicated.expand((clusters, vec![1]));
+        assert_eq!(clusters, HashMap::from([(0, vec![0, 2, 3, 4])]));
+        assert_eq!(noise, [1]);
+
+        let empty = Deduplicated::new(&Array2::<f32>::zeros((0, 2)));
+        assert!(empty.weights.is_empty());
+        assert_eq!(empty.points.dim(), (0, 2));
+    }
+}
@@ -0,0 +1,336 @@
+//! Python bindings for the density-based clusterers, with an interface
+//! following scikit-learn's.
+//!
+//! The module is built with the `python` feature, e.g. with
+//! `maturin develop --release` using the `pyproject.toml` of this crate.
+//! `maturin` builds the crate as a `cdylib` itself, so crates depending on
+//! this one do not build one. Its estimators accept anything `numpy.asarray`
+//! accepts, and label noise points with -1:
+//!
+//! ```python
+//! import numpy as np
+//! from petal_clustering import DBSCAN
+//!
+//! X = np.array([[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]])
+//! labels = DBSCAN(eps=3., min_samples=2).fit_predict(X)  # [0, 0, 0, 1, 1, -1]
+//! ```
+
+use std::collections::HashMap;
+
+use ndarray::Array2;
+use petal_neighbors::distance::Euclidean;
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{ClusterExtraction, Dbscan, Fit, HDbscan, Optics};
+
+#[pymodule]
+fn petal_clustering(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyDbscan>()?;
+    m.add_class::<PyOptics>()?;
+    m.add_class::<PyHDbscan>()?;
+    Ok(())
+}
+
+/// DBSCAN with Euclidean distances.
+#[pyclass(name = "DBSCAN", module = "petal_clustering")]
+struct PyDbscan {
+    #[pyo3(get, set)]
+    eps: f64,
+    #[pyo3(get, set)]
+    min_samples: usize,
+    labels: Option<Vec<isize>>,
+}
+
+#[pymethods]
+impl PyDbscan {
+    #[new]
+    #[pyo3(signature = (eps = 0.5, min_samples = 5))]
+    fn new(eps: f64, min_samples: usize) -> Self {
+        Self {
+            eps,
+            min_samples,
+            labels: None,
+        }
+    }
+
+    fn fit<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let input = to_array(x)?;
+        let mut dbscan = Dbscan::new(slf.eps, slf.min_samples, Euclidean::default());
+        let (clusters, noise) = x.py().detach(|| dbscan.fit(&input, None));
+        slf.labels = Some(labels(input.nrows(), &clusters, &noise));
+        Ok(slf)
+    }
+
+    fn fit_predict<'py>(
+        slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        Self::fit(slf, x)?.labels_(x.py())
+    }
+
+    #[getter]
+    fn labels_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_numpy(py, self.labels.as_deref())
+    }
+}
+
+/// OPTICS with Euclidean distances, extracting clusters as DBSCAN would with
+/// radius `eps`, which defaults to `max_eps`.
+#[pyclass(name = "OPTICS", module = "petal_clustering")]
+struct PyOptics {
+    #[pyo3(get, set)]
+    min_samples: usize,
+    #[pyo3(get, set)]
+    max_eps: f64,
+    #[pyo3(get, set)]
+    eps: Option<f64>,
+    labels: Option<Vec<isize>>,
+}
+
+#[pymethods]
+impl PyOptics {
+    #[new]
+    #[pyo3(signature = (min_samples = 5, max_eps = f64::INFINITY, eps = None))]
+    fn new(min_samples: usize, max_eps: f64, eps: Option<f64>) -> Self {
+        Self {
+            min_samples,
+            max_eps,
+            eps,
+            labels: None,
+        }
+    }
+
+    fn fit<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let input = to_array(x)?;
+        let eps = slf.eps.unwrap_or(slf.max_eps);
+        let mut optics = Optics::new(slf.max_eps, slf.min_samples, Euclidean::default());
+        let (clusters, noise) = x.py().detach(|| {
+            optics.fit(&input, None);
+            optics.extract_clusters_and_noise(eps)
+        });
+        slf.labels = Some(labels(input.nrows(), &clusters, &noise));
+        Ok(slf)
+    }
+
+    fn fit_predict<'py>(
+        slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        Self::fit(slf, x)?.labels_(x.py())
+    }
+
+    #[getter]
+    fn labels_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_numpy(py, self.labels.as_deref())
+    }
+}
+
+/// HDBSCAN with Euclidean distances. `min_samples` defaults to
+/// `min_cluster_size`, and `cluster_selection_method` is `"eom"` or
+/// `"leaf"`.
+#[pyclass(name = "HDBSCAN", module = "petal_clustering")]
+struct PyHDbscan {
+    #[pyo3(get, set)]
+    min_cluster_size: usize,
+    #[pyo3(get, set)]
+    min_samples: Option<usize>,
+    #[pyo3(get, set)]
+    cluster_selection_epsilon: f64,
+    #[pyo3(get, set)]
+    alpha: f64,
+    #[pyo3(get, set)]
+    cluster_selection_method: String,
+    labels: Option<Vec<isize>>,
+    outlier_scores: Option<Vec<f64>>,
+    probabilities: Option<Vec<f64>>,
+}
+
+#[pymethods]
+impl PyHDbscan {
+    #[new]
+    #[pyo3(signature = (
+        min_cluster_size = 5,
+        min_samples = None,
+        cluster_selection_epsilon = 0.,
+        alpha = 1.,
+        cluster_selection_method = "eom".to_string(),
+    ))]
+    fn new(
+        min_cluster_size: usize,
+        min_samples: Option<usize>,
+        cluster_selection_epsilon: f64,
+        alpha: f64,
+        cluster_selection_method: String,
+    ) -> Self {
+        Self {
+            min_cluster_size,
+            min_samples,
+            cluster_selection_epsilon,
+            alpha,
+            cluster_selection_method,
+            labels: None,
+            outlier_scores: None,
+            probabilities: None,
+        }
+    }
+
+    fn fit<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let extraction = match slf.cluster_selection_method.as_str() {
+            "eom" => ClusterExtraction::ExcessOfMass,
+            "leaf" => ClusterExtraction::Leaf,
+            method => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown cluster_selection_method: {method:?}"
+                )))
+            }
+        };
+        let input = to_array(x)?;
+        let mut hdbscan = HDbscan::new(slf.min_cluster_size, Euclidean::default());
+        hdbscan.min_samples = slf.min_samples.unwrap_or(slf.min_cluster_size);
+        hdbscan.cluster_selection_epsilon = slf.cluster_selection_epsilon;
+        hdbscan.alpha = slf.alpha;
+        hdbscan.extraction = extraction;
+        let (clusters, noise, outlier_scores) = x.py().detach(|| hdbscan.fit(&input, None));
+        slf.labels = Some(labels(input.nrows(), &clusters, &noise));
+        slf.outlier_scores = Some(outlier_scores);
+        slf.probabilities = Some(hdbscan.probabilities().to_vec());
+        Ok(slf)
+    }
+
+    fn fit_predict<'py>(
+        slf: PyRefMut<'py, Self>,
+        x: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        Self::fit(slf, x)?.labels_(x.py())
+    }
+
+    #[getter]
+    fn labels_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_numpy(py, self.labels.as_deref())
+    }
+
+    /// The GLOSH outlier score of each point, between 0 and 1.
+    #[getter]
+    fn outlier_scores_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_numpy(py, self.outlier_scores.as_deref())
+    }
+
+    /// The strength of the membership of each point in its cluster, between
+    /// 0 and 1, with 0 for noise.
+    #[getter]
+    fn probabilities_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_numpy(py, self.probabilities.as_deref())
+    }
+}
+
+/// Copies a two-dimensional array-like object into an array of `f64`.
+fn to_array(x: &Bound<'_, PyAny>) -> PyResult<Array2<f64>> {
+    let py = x.py();
+    let array = py
+        .import("numpy")?
+        .call_method1("ascontiguousarray", (x, "float64"))?;
+    let buffer = PyBuffer::<f64>::get(&array)?;
+    let &[rows, cols] = buffer.shape() else {
+        return Err(PyValueError::new_err(format!(
+            "expected a 2D array, got {} dimensions",
+            buffer.dimensions()
+        )));
+    };
+    let values = buffer.to_vec(py)?;
+    Array2::from_shape_vec((rows, cols), values).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Converts a vector to a `NumPy` array, raising an error if the estimator has
+/// not been fitted.
+fn to_numpy<'py, T>(py: Python<'py>, values: Option<&[T]>) -> PyResult<Bound<'py, PyAny>>
+where
+    T: Copy + IntoPyObject<'py>,
+{
+    let values = values.ok_or_else(|| {
+        PyValueError::new_err("this estimator has not been fitted yet; call `fit` first")
+    })?;
+    py.import("numpy")?
+        .call_method1("asarray", (values.to_vec(),))
+}
+
+/// Returns the label of each of `n` points, with -1 for noise.
+fn labels(n: usize, clusters: &HashMap<usize, Vec<usize>>, noise: &[usize]) -> Vec<isize> {
+    let mut labels = vec![-1; n];
+    for (id, members) in clusters {
+        let id = isize::try_from(*id).expect("cluster IDs are less than the number of points");
+        for &i in members {
+            labels[i] = id;
+        }
+    }
+    debug_assert!(noise.iter().all(|i| labels[*i] == -1));
+    labels
+}
+
+#[cfg(test)]
+mod test {
+    use maplit::hashmap;
+    use pyo3::prelude::*;
+    use pyo3::types::PyModule;
+
+    /// Fits each estimator from Python, which needs `NumPy` in the
+    /// interpreter `pyo3` links to.
+    #[test]
+    fn estimators() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "petal_clustering").unwrap();
+            super::petal_clustering(&module).unwrap();
+            let globals = module.dict();
+            py.run(
+                cr#"
+import numpy as np
+
+X = np.array([[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]])
+
+for estimator in [DBSCAN(eps=3., min_samples=2), OPTICS(min_samples=2, max_eps=3.)]:
+    labels = list(estimator.fit_predict(X))
+    assert labels[0] == labels[1] == labels[2] != labels[3] == labels[4], labels
+    assert labels[5] == -1, labels
+
+hdbscan = HDBSCAN(min_cluster_size=2).fit(X)
+assert len(hdbscan.labels_) == len(hdbscan.outlier_scores_) == len(hdbscan.probabilities_) == 6
+assert all(0. <= p <= 1. for p in hdbscan.probabilities_)
+
+try:
+    DBSCAN().labels_
+    raise AssertionError("unfitted estimator")
+except ValueError:
+    pass
+try:
+    HDBSCAN(cluster_selection_method="tree").fit(X)
+    raise AssertionError("unknown cluster_selection_method")
+except ValueError:
+    pass
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap_or_else(|e| {
+                e.print(py);
+                panic!("{e}");
+            });
+        });
+    }
+
+    #[test]
+    fn labels() {
+        let clusters = hashmap! {0 => vec![0, 2], 1 => vec![3]};
+        assert_eq!(super::labels(5, &clusters, &[1, 4]), [0, -1, 0, 1, -1]);
+    }
+}
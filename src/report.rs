@@ -0,0 +1,199 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ndarray::ArrayView1;
+use petal_neighbors::distance::Metric;
+
+/// Diagnostics of a fit, returned by
+/// [`Dbscan::fit_with_report`](crate::Dbscan::fit_with_report), to see where
+/// the time and memory of a slow fit go and which parameters to tune.
+///
+/// A fit dominated by the neighbor search with large neighborhoods usually
+/// has an `eps` that is too large; one that finds few core points, a
+/// `min_samples` that is too large for the density of the input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FitReport {
+    /// The duration of each phase of the fit, in order, with the names used
+    /// by [`LimitExceeded`](crate::LimitExceeded).
+    pub phases: Vec<(&'static str, Duration)>,
+
+    /// The number of points in the input.
+    pub n_points: usize,
+
+    /// The number of core points.
+    pub n_core_points: usize,
+
+    /// The distribution of the sizes of the neighborhoods, counting each
+    /// point in its own neighborhood.
+    pub neighbors: SizeDistribution,
+
+    /// The estimated peak memory of the data structures built by the fit, in
+    /// bytes: the input, the ball tree, and the neighborhoods.
+    pub memory: usize,
+
+    /// The number of times the metric computed a distance, including those
+    /// between points and the bounds of the ball tree.
+    pub distance_evaluations: u64,
+}
+
+impl FitReport {
+    /// Returns the total duration of the phases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use petal_clustering::FitReport;
+    ///
+    /// let report = FitReport {
+    ///     phases: vec![
+    ///         ("neighbors", Duration::from_millis(30)),
+    ///         ("expansion", Duration::from_millis(5)),
+    ///     ],
+    ///     ..FitReport::default()
+    /// };
+    /// assert_eq!(report.duration(), Duration::from_millis(35));
+    ///
+    /// ```
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.phases.iter().map(|(_, d)| *d).sum()
+    }
+}
+
+impl fmt::Display for FitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} points, {} core points, {} distance evaluations, ~{} bytes",
+            self.n_points, self.n_core_points, self.distance_evaluations, self.memory
+        )?;
+        writeln!(f, "neighbors: {}", self.neighbors)?;
+        for (phase, duration) in &self.phases {
+            writeln!(f, "{phase}: {duration:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Summary statistics of a list of sizes, such as the sizes of the
+/// neighborhoods in a [`FitReport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SizeDistribution {
+    /// The smallest size.
+    pub min: usize,
+
+    /// The median size, the lower one of the two middle sizes if there is an
+    /// even number of them.
+    pub median: usize,
+
+    /// The 90th percentile of the sizes.
+    pub p90: usize,
+
+    /// The largest size.
+    pub max: usize,
+
+    /// The mean size.
+    pub mean: f64,
+}
+
+impl SizeDistribution {
+    /// Summarizes the given sizes, or returns all zeros if there are none.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn new(mut sizes: Vec<usize>) -> Self {
+        if sizes.is_empty() {
+            return Self::default();
+        }
+        sizes.sort_unstable();
+        let n = sizes.len();
+        Self {
+            min: sizes[0],
+            median: sizes[(n - 1) / 2],
+            p90: sizes[(n - 1) * 9 / 10],
+            max: sizes[n - 1],
+            mean: sizes.iter().sum::<usize>() as f64 / n as f64,
+        }
+    }
+}
+
+impl fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {}, median {}, p90 {}, max {}, mean {:.1}",
+            self.min, self.median, self.p90, self.max, self.mean
+        )
+    }
+}
+
+/// A metric that counts the distances it computes. Clones share the count.
+#[derive(Clone, Debug)]
+pub(crate) struct CountingMetric<M> {
+    metric: M,
+    count: Arc<AtomicU64>,
+}
+
+impl<M> CountingMetric<M> {
+    pub(crate) fn new(metric: M) -> Self {
+        Self {
+            metric,
+            count: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl<A, M: Metric<A>> Metric<A> for CountingMetric<M> {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.metric.distance(x1, x2)
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.metric.rdistance(x1, x2)
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        self.metric.rdistance_to_distance(d)
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        self.metric.distance_to_rdistance(d)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::aview1;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn size_distribution() {
+        let sizes = SizeDistribution::new((1..=10).rev().collect());
+        assert_eq!(
+            (sizes.min, sizes.median, sizes.p90, sizes.max),
+            (1, 5, 9, 10)
+        );
+        assert!(f64::abs(sizes.mean - 5.5) < 1e-12);
+        assert_eq!(SizeDistribution::new(vec![]), SizeDistribution::default());
+    }
+
+    #[test]
+    fn counting_metric() {
+        let metric = CountingMetric::new(Euclidean::default());
+        let clone = metric.clone();
+        let (x, y) = (aview1(&[0., 0.]), aview1(&[3., 4.]));
+        assert!(f64::abs(metric.distance(&x, &y) - 5.) < 1e-12);
+        assert!(f64::abs(clone.rdistance(&x, &y) - 25.) < 1e-12);
+        assert_eq!(metric.count(), 2);
+    }
+}
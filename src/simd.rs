@@ -0,0 +1,261 @@
+//! Vectorized distance kernels, enabled with the `simd` feature.
+//!
+//! The kernels split contiguous rows into chunks of `LANES` values and keep
+//! one accumulator per lane, so that the compiler turns the inner loop into
+//! SIMD instructions on stable Rust, without reordering the additions of a
+//! single accumulator the way `-ffast-math` would. Rows that are not
+//! contiguous, such as the columns of a row-major array, fall back to a
+//! scalar loop.
+//!
+//! [`SimdEuclidean`] and [`SimdCosine`] implement petal-neighbors' `Metric`,
+//! so they replace `Euclidean` in the neighborhood queries of DBSCAN, OPTICS
+//! and HDBSCAN and in the computation of their reachability distances. The
+//! speedup grows with the number of features; with only a few, the scalar
+//! loop is as fast. Results may differ from `Euclidean` in the last bits,
+//! since the sums are added in a different order.
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_clustering::simd::SimdEuclidean;
+//! use petal_clustering::{Dbscan, Fit};
+//!
+//! let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+//! let (clusters, noise) = Dbscan::new(3., 2, SimdEuclidean::default()).fit(&points, None);
+//! assert_eq!(clusters.len(), 2);
+//! assert_eq!(noise, [5]);
+//! ```
+
+use ndarray::ArrayView1;
+use num_traits::Float;
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+/// The number of values each kernel processes at once, enough to fill a
+/// 512-bit register with `f64`s or two 256-bit registers with `f32`s.
+const LANES: usize = 8;
+
+/// The Euclidean distance, computed with [`squared_euclidean`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct SimdEuclidean;
+
+impl<A: Float> Metric<A> for SimdEuclidean {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        squared_euclidean(x1, x2).sqrt()
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        squared_euclidean(x1, x2)
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d.sqrt()
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d * d
+    }
+}
+
+/// The cosine distance, one minus the cosine of the angle between two
+/// points, computed with [`cosine_distance`].
+///
+/// The cosine distance does not satisfy the triangle inequality, which the
+/// pruning of a `BallTree` relies on, so neighborhood queries with it may
+/// miss some neighbors. For exact neighborhoods, scale the points to unit
+/// length and use [`SimdEuclidean`] with `sqrt(2 * eps)` as the radius, since
+/// `|x - y|² = 2 * (1 - cos(x, y))` for unit vectors.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct SimdCosine;
+
+impl<A: Float> Metric<A> for SimdCosine {
+    fn distance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        cosine_distance(x1, x2)
+    }
+
+    fn rdistance(&self, x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+        cosine_distance(x1, x2)
+    }
+
+    fn rdistance_to_distance(&self, d: A) -> A {
+        d
+    }
+
+    fn distance_to_rdistance(&self, d: A) -> A {
+        d
+    }
+}
+
+/// Returns the squared Euclidean distance between two points.
+///
+/// # Panics
+/// Panics if the points have different numbers of features.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::simd::squared_euclidean;
+///
+/// let (x, y) = (array![0., 0., 0.], array![1., 2., 2.]);
+/// assert_eq!(squared_euclidean(&x.view(), &y.view()), 9.);
+/// ```
+#[must_use]
+pub fn squared_euclidean<A: Float>(x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+    assert_eq!(x1.len(), x2.len(), "points should have the same dimension");
+    match (x1.as_slice(), x2.as_slice()) {
+        (Some(x1), Some(x2)) => {
+            let mut lanes = [A::zero(); LANES];
+            let chunks = x1.chunks_exact(LANES).zip(x2.chunks_exact(LANES));
+            for (a, b) in chunks {
+                for ((lane, a), b) in lanes.iter_mut().zip(a).zip(b) {
+                    let d = *a - *b;
+                    *lane = *lane + d * d;
+                }
+            }
+            let tail = x1.len() - x1.len() % LANES;
+            let rest = x1[tail..].iter().zip(&x2[tail..]);
+            let rest = rest.fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b));
+            horizontal_sum(&lanes) + rest
+        }
+        _ => x1
+            .iter()
+            .zip(x2)
+            .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b)),
+    }
+}
+
+/// Returns the cosine distance between two points, one minus the cosine of
+/// the angle between them, or one if either point is at the origin.
+///
+/// # Panics
+/// Panics if the points have different numbers of features.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::simd::cosine_distance;
+///
+/// let (x, y, z) = (array![1., 0.], array![0., 2.], array![3., 0.]);
+/// assert_eq!(cosine_distance(&x.view(), &y.view()), 1.);
+/// assert_eq!(cosine_distance(&x.view(), &z.view()), 0.);
+/// ```
+#[must_use]
+pub fn cosine_distance<A: Float>(x1: &ArrayView1<A>, x2: &ArrayView1<A>) -> A {
+    assert_eq!(x1.len(), x2.len(), "points should have the same dimension");
+    let (dot, norm1, norm2) = match (x1.as_slice(), x2.as_slice()) {
+        (Some(x1), Some(x2)) => {
+            let mut dot = [A::zero(); LANES];
+            let mut norm1 = [A::zero(); LANES];
+            let mut norm2 = [A::zero(); LANES];
+            for (a, b) in x1.chunks_exact(LANES).zip(x2.chunks_exact(LANES)) {
+                for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                    dot[i] = dot[i] + *a * *b;
+                    norm1[i] = norm1[i] + *a * *a;
+                    norm2[i] = norm2[i] + *b * *b;
+                }
+            }
+            let tail = x1.len() - x1.len() % LANES;
+            let rest = products(x1[tail..].iter().zip(&x2[tail..]));
+            (
+                horizontal_sum(&dot) + rest.0,
+                horizontal_sum(&norm1) + rest.1,
+                horizontal_sum(&norm2) + rest.2,
+            )
+        }
+        _ => products(x1.iter().zip(x2)),
+    };
+    let magnitude = (norm1 * norm2).sqrt();
+    if magnitude <= A::zero() {
+        return A::one();
+    }
+    // rounding can push the cosine slightly beyond [-1, 1]
+    (A::one() - dot / magnitude).max(A::zero())
+}
+
+/// Sums the lanes pairwise, which keeps the rounding errors of the lanes
+/// from accumulating.
+fn horizontal_sum<A: Float>(lanes: &[A; LANES]) -> A {
+    let quads = [
+        lanes[0] + lanes[4],
+        lanes[1] + lanes[5],
+        lanes[2] + lanes[6],
+        lanes[3] + lanes[7],
+    ];
+    (quads[0] + quads[2]) + (quads[1] + quads[3])
+}
+
+/// Returns the dot product of pairs of values and the squared norms of each
+/// side, with a scalar loop.
+fn products<'a, A: Float + 'a>(pairs: impl Iterator<Item = (&'a A, &'a A)>) -> (A, A, A) {
+    pairs.fold(
+        (A::zero(), A::zero(), A::zero()),
+        |(dot, n1, n2), (a, b)| (dot + *a * *b, n1 + *a * *a, n2 + *b * *b),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Dbscan, Fit};
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn squared_euclidean_kernel() {
+        for n in [0, 1, 7, 8, 9, 19, 64] {
+            let x: Vec<f64> = (0..n).map(|i| f64::from(i).sin()).collect();
+            let y: Vec<f64> = (0..n).map(|i| (f64::from(i) * 0.7).cos()).collect();
+            let (x, y) = (ArrayView1::from(&x), ArrayView1::from(&y));
+            let expected: f64 = x.iter().zip(&y).map(|(a, b)| (a - b) * (a - b)).sum();
+            assert!((squared_euclidean(&x, &y) - expected).abs() < 1e-12);
+            assert!(
+                (Euclidean::default().distance(&x, &y) - SimdEuclidean.distance(&x, &y)).abs()
+                    < 1e-12
+            );
+        }
+
+        // columns of a row-major array are not contiguous
+        let data = Array2::from_shape_fn((20, 2), |(i, j)| (i * (j + 1)) as f32);
+        let (x, y) = (data.column(0), data.column(1));
+        let expected: f32 = (0..20).map(|i| (i * i) as f32).sum();
+        assert!((squared_euclidean(&x, &y) - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cosine_distance_kernel() {
+        assert!(
+            (cosine_distance(&array![1., 0.].view(), &array![0., 2.].view()) - 1.).abs() < 1e-12
+        );
+        assert!(cosine_distance(&array![1., 1.].view(), &array![3., 3.].view()).abs() < 1e-12);
+        assert!(
+            (cosine_distance(&array![1., 0.].view(), &array![-1., 0.].view()) - 2.).abs() < 1e-12
+        );
+        assert!(
+            (cosine_distance(&array![0., 0.].view(), &array![1., 0.].view()) - 1.).abs() < 1e-12
+        );
+
+        let x: Vec<f64> = (0..21).map(|i| f64::from(i) + 1.).collect();
+        let y: Vec<f64> = (0..21).map(|i| 21. - f64::from(i)).collect();
+        let (x, y) = (ArrayView1::from(&x), ArrayView1::from(&y));
+        let (dot, n1, n2) = products(x.iter().zip(&y));
+        let expected = 1. - dot / (n1 * n2).sqrt();
+        assert!((SimdCosine.distance(&x, &y) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn dbscan() {
+        let data = Array2::from_shape_fn((60, 24), |(i, j)| {
+            (i / 20) as f64 * 10. + ((i * 7 + j * 3) % 5) as f64 / 10.
+        });
+        let expected = Dbscan::new(2., 3, Euclidean::default()).fit(&data, None);
+        let simd = Dbscan::new(2., 3, SimdEuclidean).fit(&data, None);
+        assert_eq!(simd, expected);
+        assert_eq!(simd.0.len(), 3);
+    }
+}
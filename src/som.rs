@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use rand::{rngs::StdRng, seq::index::sample, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Fit, Predict};
+use crate::parallel::prelude::*;
+use crate::KMeans;
+
+/// The arrangement of the units of a [`Som`] on its grid.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Topology {
+    /// Units on a square lattice, each with up to four neighbors.
+    Rectangular,
+
+    /// Units on a hexagonal lattice, with every other row shifted by half a
+    /// unit, each with up to six neighbors.
+    Hexagonal,
+}
+
+/// Self-organizing map (SOM), a grid of units whose weight vectors, the
+/// codebook, are trained to follow the input while neighboring units stay
+/// similar.
+///
+/// Each point pulls its best-matching unit, the unit whose weight vector is
+/// the closest to it, and the units around it on the grid toward itself.
+/// The learning rate and the radius of the Gaussian neighborhood decay
+/// exponentially from `learning_rate` and `radius` to `final_learning_rate`
+/// and `final_radius` over the `n_epochs` passes over the input. The trained
+/// map keeps the topology of the input: points that are close in the input
+/// space have best-matching units that are close on the grid.
+///
+/// The map serves two purposes:
+/// - Clustering: each unit is a cluster of the points it best matches, and
+///   the codebook can be clustered further, with k-means with `n_clusters`
+///   clusters in `fit`, or any clusterer passed to [`Som::global_clustering`].
+/// - Visualization: [`Som::project`] maps high-dimensional points to the
+///   plane of the grid, and [`Som::u_matrix`] shows the boundaries between
+///   clusters as units far from their neighbors.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{Fit, Som, Topology};
+///
+/// let points = array![
+///     [0., 0., 0.], [0.1, 0., 0.2], [0., 0.2, 0.1],
+///     [5., 5., 5.], [5.1, 4.9, 5.], [4.9, 5.2, 5.1],
+/// ];
+/// let mut som = Som::new(4, 4);
+/// som.topology = Topology::Hexagonal;
+/// som.n_clusters = Some(2);
+/// let (clusters, noise) = som.fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());
+/// assert_eq!(som.codebook().dim(), (16, 3));
+/// assert_eq!(som.project(&points).dim(), (6, 2));
+/// ```
+///
+/// # References
+/// - Kohonen, Teuvo. "Self-organized formation of topologically correct feature maps."
+///   Biological Cybernetics 43.1 (1982): 59-69.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Som<A> {
+    /// The number of rows of the grid.
+    pub rows: usize,
+
+    /// The number of columns of the grid.
+    pub cols: usize,
+
+    /// The arrangement of the units on the grid.
+    pub topology: Topology,
+
+    /// The number of passes over the input.
+    pub n_epochs: usize,
+
+    /// The learning rate at the start of the training.
+    pub learning_rate: A,
+
+    /// The learning rate at the end of the training.
+    pub final_learning_rate: A,
+
+    /// The standard deviation of the Gaussian neighborhood at the start of
+    /// the training, in units of the grid spacing. If `None`, half the
+    /// larger side of the grid.
+    pub radius: Option<A>,
+
+    /// The standard deviation of the Gaussian neighborhood at the end of the
+    /// training.
+    pub final_radius: A,
+
+    /// The number of clusters k-means finds among the units in `fit`. If
+    /// `None`, each unit is a cluster of its own.
+    pub n_clusters: Option<usize>,
+
+    /// The seed of the random number generator choosing the initial weights
+    /// and the order of the points.
+    pub seed: u64,
+
+    codebook: Vec<A>,
+    n_features: usize,
+    /// The cluster of each unit given by the global clustering, or empty if
+    /// each unit is a cluster of its own.
+    unit_labels: Vec<Option<usize>>,
+}
+
+impl<A> Default for Som<A>
+where
+    A: FloatCore,
+{
+    fn default() -> Self {
+        Self::new(10, 10)
+    }
+}
+
+impl<A> Som<A>
+where
+    A: FloatCore,
+{
+    /// Creates a map with a rectangular grid of `rows` by `cols` units, trained
+    /// for 20 epochs, in which each unit is a cluster of its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::{Som, Topology};
+    ///
+    /// let som = Som::<f64>::new(5, 8);
+    /// assert_eq!(som.topology, Topology::Rectangular);
+    /// assert_eq!(som.n_clusters, None);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the constants are valid floats
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            topology: Topology::Rectangular,
+            n_epochs: 20,
+            learning_rate: A::from(0.5).expect("valid float"),
+            final_learning_rate: A::from(0.01).expect("valid float"),
+            radius: None,
+            final_radius: A::from(0.5).expect("valid float"),
+            n_clusters: None,
+            seed: 0,
+            codebook: vec![],
+            n_features: 0,
+            unit_labels: vec![],
+        }
+    }
+
+    /// Returns the weight vectors of the units, one row per unit. Unit
+    /// `r * cols + c` is at row `r` and column `c` of the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, Som};
+    ///
+    /// let points = array![[0., 0., 0.], [0.1, 0., 0.2], [5., 5., 5.], [5.1, 4.9, 5.]];
+    /// let mut som = Som::new(2, 3);
+    /// som.fit(&points, None);
+    /// assert_eq!(som.codebook().dim(), (6, 3));
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the length is a multiple of `n_features`
+    pub fn codebook(&self) -> ArrayView2<'_, A> {
+        ArrayView2::from_shape(
+            (
+                self.codebook.len() / self.n_features.max(1),
+                self.n_features,
+            ),
+            &self.codebook,
+        )
+        .expect("codebook of the fitted model")
+    }
+
+    /// Returns the position of each unit in the plane of the grid, one row
+    /// per unit, with the neighboring units at distance one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::Som;
+    ///
+    /// let som = Som::<f64>::new(2, 3);
+    /// let positions = som.unit_positions();
+    /// assert_eq!(positions.dim(), (6, 2));
+    /// assert_eq!(positions.row(0), array![0., 0.]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // one position for each unit
+    pub fn unit_positions(&self) -> Array2<A> {
+        let positions: Vec<A> = (0..self.rows * self.cols)
+            .flat_map(|unit| {
+                let (x, y) = self.position(unit);
+                [x, y]
+            })
+            .collect();
+        Array2::from_shape_vec((self.rows * self.cols, 2), positions)
+            .expect("one position for each unit")
+    }
+
+    fn position(&self, unit: usize) -> (A, A) {
+        let (r, c) = (unit / self.cols, unit % self.cols);
+        let (r, c) = (
+            A::from(r).expect("valid float"),
+            A::from(c).expect("valid float"),
+        );
+        match self.topology {
+            Topology::Rectangular => (c, r),
+            Topology::Hexagonal => {
+                let half = A::from(0.5).expect("valid float");
+                let shift = if (unit / self.cols) % 2 == 1 {
+                    half
+                } else {
+                    A::zero()
+                };
+                let height = A::from(3_f64.sqrt() / 2.).expect("valid float");
+                (c + shift, r * height)
+            }
+        }
+    }
+
+    fn squared_grid_distance(&self, a: usize, b: usize) -> A {
+        let (xa, ya) = self.position(a);
+        let (xb, yb) = self.position(b);
+        (xa - xb) * (xa - xb) + (ya - yb) * (ya - yb)
+    }
+}
+
+impl<A> Som<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Returns the best-matching unit of each point in the given input.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, Som};
+    ///
+    /// let points = array![[0., 0., 0.], [0.1, 0., 0.2], [5., 5., 5.], [5.1, 4.9, 5.]];
+    /// let mut som = Som::new(3, 3);
+    /// som.fit(&points, None);
+    ///
+    /// let units = som.best_matching_units(&array![[0., 0.1, 0.1], [5., 5., 4.9]]);
+    /// assert_ne!(units[0], units[1]);
+    /// ```
+    #[must_use]
+    pub fn best_matching_units<S>(&self, input: &ArrayBase<S, Ix2>) -> Vec<usize>
+    where
+        S: Data<Elem = A>,
+    {
+        assert!(
+            !self.codebook.is_empty(),
+            "`best_matching_units` requires a fitted model"
+        );
+        let rows: Vec<_> = input.rows().into_iter().collect();
+        rows.into_par_iter()
+            .map(|p| self.best_matching_unit(&p))
+            .collect()
+    }
+
+    /// Maps each point in the given input to the position of its
+    /// best-matching unit in the plane of the grid, as given by
+    /// [`Som::unit_positions`], for plotting high-dimensional data in two
+    /// dimensions.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, Som};
+    ///
+    /// let points = array![[0., 0., 0.], [0.1, 0., 0.2], [5., 5., 5.], [5.1, 4.9, 5.]];
+    /// let mut som = Som::new(3, 3);
+    /// som.fit(&points, None);
+    ///
+    /// let projected = som.project(&points);
+    /// assert_eq!(projected.dim(), (4, 2));
+    /// assert_ne!(projected.row(0), projected.row(2));
+    /// ```
+    #[must_use]
+    pub fn project<S>(&self, input: &ArrayBase<S, Ix2>) -> Array2<A>
+    where
+        S: Data<Elem = A>,
+    {
+        let positions = self.unit_positions();
+        let units = self.best_matching_units(input);
+        let mut projected = Array2::zeros((units.len(), 2));
+        for (mut row, unit) in projected.rows_mut().into_iter().zip(units) {
+            row.assign(&positions.row(unit));
+        }
+        projected
+    }
+
+    /// Returns the U-matrix, the mean distance from the weight vector of each
+    /// unit to those of its neighbors on the grid, with `rows` rows and
+    /// `cols` columns. Units with large values separate clusters.
+    ///
+    /// # Panics
+    /// Panics if the model has not been fitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::{Fit, Som};
+    ///
+    /// let points = array![[0., 0., 0.], [0.1, 0., 0.2], [5., 5., 5.], [5.1, 4.9, 5.]];
+    /// let mut som = Som::new(3, 4);
+    /// som.fit(&points, None);
+    ///
+    /// let u_matrix = som.u_matrix();
+    /// assert_eq!(u_matrix.dim(), (3, 4));
+    /// assert!(u_matrix.iter().all(|d| *d >= 0_f64));
+    /// ```
+    #[must_use]
+    pub fn u_matrix(&self) -> Array2<A> {
+        assert!(
+            !self.codebook.is_empty(),
+            "`u_matrix` requires a fitted model"
+        );
+        let codebook = self.codebook();
+        let limit = A::from(1.01).expect("valid float");
+        let n_units = self.rows * self.cols;
+        let values: Vec<A> = (0..n_units)
+            .map(|a| {
+                let (total, count) = (0..n_units)
+                    .filter(|b| *b != a && self.squared_grid_distance(a, *b) <= limit)
+                    .fold((A::zero(), A::zero()), |(total, count), b| {
+                        let d = Float::sqrt(squared_distance(&codebook.row(a), &codebook.row(b)));
+                        (total + d, count + A::one())
+                    });
+                if count > A::zero() {
+                    total / count
+                } else {
+                    A::zero()
+                }
+            })
+            .collect();
+        Array2::from_shape_vec((self.rows, self.cols), values).expect("one value for each unit")
+    }
+
+    /// Groups the units by fitting `clusterer` to the codebook.
+    ///
+    /// Units that `clusterer` considers noise are noise in the result of
+    /// `predict`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Dbscan, Fit, Predict, Som};
+    ///
+    /// let points = array![[1., 2.], [1.2, 2.], [1., 2.3], [8., 7.], [8., 8.], [8.3, 7.5]];
+    /// let mut som = Som::new(3, 3);
+    /// som.fit(&points, None);
+    ///
+    /// som.global_clustering(&mut Dbscan::new(3., 1, Euclidean::default()));
+    /// let (clusters, noise) = som.predict(&points);
+    /// assert_eq!(clusters.len(), 2);
+    /// assert!(noise.is_empty());
+    /// ```
+    pub fn global_clustering<C>(&mut self, clusterer: &mut C)
+    where
+        C: Fit<Array2<A>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>,
+    {
+        let codebook = self.codebook().to_owned();
+        let (clusters, _) = clusterer.fit(&codebook, None);
+        let mut labels = vec![None; codebook.nrows()];
+        for (id, members) in clusters {
+            for m in members {
+                labels[m] = Some(id);
+            }
+        }
+        self.unit_labels = labels;
+    }
+
+    fn best_matching_unit(&self, point: &ArrayView1<A>) -> usize {
+        self.codebook
+            .chunks(self.n_features.max(1))
+            .map(|w| {
+                w.iter()
+                    .zip(point)
+                    .fold(A::zero(), |acc, (a, b)| acc + (*a - *b) * (*a - *b))
+            })
+            .enumerate()
+            .fold((0, FloatCore::infinity()), |best, (i, d)| {
+                if d < best.1 {
+                    (i, d)
+                } else {
+                    best
+                }
+            })
+            .0
+    }
+
+    /// Trains the codebook on the points of `input`, starting from randomly
+    /// chosen points.
+    fn train(&mut self, input: &ArrayView2<A>) {
+        let n = input.nrows();
+        let n_units = self.rows * self.cols;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.n_features = input.ncols();
+        self.codebook = if n >= n_units {
+            sample(&mut rng, n, n_units)
+                .into_iter()
+                .flat_map(|i| input.row(i).to_vec())
+                .collect()
+        } else {
+            (0..n_units)
+                .flat_map(|_| input.row(rng.random_range(0..n)).to_vec())
+                .collect()
+        };
+
+        let radius = self.radius.unwrap_or_else(|| {
+            let half = A::from(self.rows.max(self.cols)).expect("valid float")
+                / A::from(2).expect("valid float");
+            FloatCore::max(half, A::one())
+        });
+        let steps = A::from(self.n_epochs * n).expect("valid float");
+        let two = A::one() + A::one();
+        let mut step = A::zero();
+        for _ in 0..self.n_epochs {
+            for i in sample(&mut rng, n, n) {
+                let point = input.row(i);
+                let progress = step / steps;
+                let rate = decay(self.learning_rate, self.final_learning_rate, progress);
+                let sigma = decay(radius, self.final_radius, progress);
+                let bmu = self.best_matching_unit(&point);
+                let dim = self.n_features;
+                for unit in 0..n_units {
+                    let h =
+                        Float::exp(-self.squared_grid_distance(bmu, unit) / (two * sigma * sigma));
+                    let scale = rate * h;
+                    for (w, v) in self.codebook[unit * dim..(unit + 1) * dim]
+                        .iter_mut()
+                        .zip(&point)
+                    {
+                        *w += scale * (*v - *w);
+                    }
+                }
+                step += A::one();
+            }
+        }
+    }
+}
+
+/// Trains the map on the given input data, and clusters its units with
+/// k-means if `n_clusters` is set.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster. Without a global
+///   clustering, the cluster ID is the best-matching unit of the points.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+///
+/// # Panics
+/// Panics if the grid has no units.
+impl<S, A>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for Som<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(self.rows * self.cols > 0, "the grid should have units");
+        self.codebook.clear();
+        self.unit_labels.clear();
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+
+        self.train(&input.view());
+        if let Some(n_clusters) = self.n_clusters {
+            self.global_clustering(&mut KMeans::new(n_clusters));
+        }
+        self.predict(input)
+    }
+}
+
+/// Assigns each point in the given input to the cluster of its
+/// best-matching unit.
+///
+/// # Panics
+/// Panics if the model has not been fitted.
+impl<S, A> Predict<ArrayBase<S, Ix2>, (HashMap<usize, Vec<usize>>, Vec<usize>)> for Som<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn predict(&mut self, input: &ArrayBase<S, Ix2>) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        assert!(
+            !self.codebook.is_empty(),
+            "`predict` requires a fitted model"
+        );
+        let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut noise = vec![];
+        for (i, unit) in self.best_matching_units(input).into_iter().enumerate() {
+            let label = if self.unit_labels.is_empty() {
+                Some(unit)
+            } else {
+                self.unit_labels[unit]
+            };
+            match label {
+                Some(id) => clusters.entry(id).or_default().push(i),
+                None => noise.push(i),
+            }
+        }
+        (clusters, noise)
+    }
+}
+
+/// Interpolates exponentially between `start` and `end`, at `progress`
+/// between zero and one.
+fn decay<A: Float>(start: A, end: A, progress: A) -> A {
+    start * (end / start).powf(progress)
+}
+
+fn squared_distance<A: FloatCore>(a: &ArrayView1<A>, b: &ArrayView1<A>) -> A {
+    a.iter()
+        .zip(b)
+        .fold(A::zero(), |acc, (x, y)| acc + (*x - *y) * (*x - *y))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn som() {
+        let data = array![
+            [0., 0.],
+            [0.2, 0.1],
+            [0.1, 0.3],
+            [10., 10.],
+            [10.2, 9.9],
+            [9.8, 10.1],
+        ];
+        let mut som = Som::new(5, 5);
+        som.n_clusters = Some(2);
+        let (clusters, noise) = som.fit(&data, None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4, 5]]);
+        assert!(noise.is_empty());
+
+        // the two groups are mapped to distant units
+        let projected = som.project(&data);
+        let gap = Float::sqrt(squared_distance(&projected.row(0), &projected.row(3)));
+        assert!(gap > 2.);
+
+        // the units between the groups are far from their neighbors
+        let u = som.u_matrix();
+        assert_eq!(u.dim(), (5, 5));
+        let largest = u.iter().fold(0_f64, |acc, v| acc.max(*v));
+        let smallest = u.iter().fold(f64::INFINITY, |acc, v| acc.min(*v));
+        assert!(largest > 10. * smallest);
+    }
+
+    #[test]
+    fn hexagonal_positions() {
+        let mut som = Som::<f64>::new(2, 2);
+        som.topology = Topology::Hexagonal;
+        let positions = som.unit_positions();
+        assert!(f64::abs(positions[[2, 0]] - 0.5) < 1e-12);
+        // the neighbors of a unit are at distance one
+        let d = |a, b| som.squared_grid_distance(a, b);
+        for (a, b) in [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)] {
+            assert!(f64::abs(d(a, b) - 1.) < 1e-12);
+        }
+        assert!(f64::abs(d(0, 3) - 3.) < 1e-12);
+    }
+
+    #[test]
+    fn fewer_points_than_units() {
+        let data = array![[1., 1.], [2., 2.]];
+        let mut som = Som::new(3, 3);
+        let (clusters, _) = som.fit(&data, None);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 2);
+
+        let empty: Array2<f64> = Array2::zeros((0, 2));
+        let (clusters, noise) = som.fit(&empty, None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{s, Array1, Array2, ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use petal_neighbors::{
+    distance::{Euclidean, Metric},
+    BallTree,
+};
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::linalg::{lanczos_largest, largest_eigen};
+use crate::parallel::prelude::*;
+use crate::KMeans;
+
+/// Graphs with more nodes than this are decomposed with Lanczos iterations on
+/// the sparse graph instead of a dense eigensolver.
+const DENSE_LIMIT: usize = 1000;
+
+/// The similarity graph a spectral embedding is computed from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub enum Affinity<A> {
+    /// A dense graph weighted by the Gaussian (RBF) kernel
+    /// `exp(-gamma * d(x, y)^2)`.
+    Rbf { gamma: A },
+
+    /// A sparse, symmetrized graph connecting each point to its
+    /// `n_neighbors` nearest neighbors.
+    NearestNeighbors { n_neighbors: usize },
+}
+
+/// Spectral embedding (Laplacian eigenmaps) of the input data.
+///
+/// The embedding consists of the eigenvectors of the normalized graph
+/// Laplacian of an affinity graph built from the input, with the trivial
+/// (constant) eigenvector dropped. It can be used for visualization or as the
+/// input of another clustering algorithm.
+///
+/// If the affinity graph is not connected, the leading eigenvalue is repeated
+/// and the returned components are an arbitrary basis of the corresponding
+/// eigenspace.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Affinity, Fit, SpectralEmbedding};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let affinity = Affinity::NearestNeighbors { n_neighbors: 3 };
+/// let embedding = SpectralEmbedding::new(1, affinity, Euclidean::default()).fit(&points, None);
+///
+/// assert_eq!(embedding.dim(), (6, 1));
+/// // the two groups are separated along the first component
+/// assert!(embedding[[0, 0]] * embedding[[3, 0]] < 0.);
+/// ```
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SpectralEmbedding<A, M> {
+    /// The number of dimensions of the embedded space.
+    pub n_components: usize,
+
+    /// The similarity graph to embed.
+    pub affinity: Affinity<A>,
+
+    /// The metric to compute distance between the entries.
+    pub metric: M,
+
+    /// The seed of the starting vector of the Lanczos iterations for large
+    /// graphs.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl<A> Default for SpectralEmbedding<A, Euclidean>
+where
+    A: Float,
+{
+    fn default() -> Self {
+        Self {
+            n_components: 2,
+            affinity: Affinity::NearestNeighbors { n_neighbors: 10 },
+            metric: Euclidean::default(),
+            seed: 0,
+        }
+    }
+}
+
+impl<A, M> SpectralEmbedding<A, M> {
+    /// Creates an embedding into `n_components` dimensions of the graph given
+    /// by `affinity` and `metric`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Affinity, SpectralEmbedding};
+    ///
+    /// let embedding = SpectralEmbedding::new(2, Affinity::Rbf { gamma: 0.5 }, Euclidean::default());
+    /// assert_eq!(embedding.n_components, 2);
+    /// ```
+    #[must_use]
+    pub fn new(n_components: usize, affinity: Affinity<A>, metric: M) -> Self {
+        Self {
+            n_components,
+            affinity,
+            metric,
+            seed: 0,
+        }
+    }
+}
+
+/// Computes the spectral embedding of the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to embed. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// - `Array2<A>`: The embedded points, one row per input point and one column per component.
+impl<S, A, M> Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, Array2<A>>
+    for SpectralEmbedding<A, M>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync + 'static,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> Array2<A> {
+        if input.is_empty() {
+            return Array2::zeros((input.nrows(), self.n_components));
+        }
+        let input = input.as_standard_layout();
+        let graph = affinity_graph(&input, self.affinity, &self.metric);
+        embed(&graph, self.n_components, true, self.seed)
+    }
+}
+
+/// Spectral clustering algorithm.
+///
+/// The points are embedded with the eigenvectors of the `n_clusters` smallest
+/// eigenvalues of the normalized graph Laplacian of an affinity graph, and
+/// clustered in the embedded space with k-means. Unlike centroid methods on
+/// the input itself, this can find non-convex clusters such as concentric
+/// rings. Large k-NN graphs are decomposed with Lanczos iterations.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Affinity, Fit, SpectralClustering};
+///
+/// let points = array![[1., 2.], [1.1, 2.2], [0.9, 1.9], [8., 7.], [8., 8.], [8.2, 7.5]];
+/// let affinity = Affinity::NearestNeighbors { n_neighbors: 2 };
+/// let (clusters, noise) = SpectralClustering::new(2, affinity, Euclidean::default()).fit(&points, None);
+///
+/// assert_eq!(clusters.len(), 2);
+/// assert!(noise.is_empty());  // every point belongs to a cluster
+/// ```
+///
+/// # References
+/// - Ng, Andrew, Michael Jordan, and Yair Weiss. "On spectral clustering: Analysis and an algorithm."
+///   Advances in Neural Information Processing Systems 14 (2001).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SpectralClustering<A, M> {
+    /// The number of clusters to form.
+    pub n_clusters: usize,
+
+    /// The similarity graph to partition.
+    pub affinity: Affinity<A>,
+
+    /// The metric to compute distance between the entries.
+    pub metric: M,
+
+    /// The seed of the starting vector of the Lanczos iterations for large
+    /// graphs, and of the random number generator of k-means in the embedded
+    /// space.
+    pub seed: u64,
+}
+
+impl<A> Default for SpectralClustering<A, Euclidean>
+where
+    A: Float,
+{
+    fn default() -> Self {
+        Self::new(
+            8,
+            Affinity::NearestNeighbors { n_neighbors: 10 },
+            Euclidean::default(),
+        )
+    }
+}
+
+impl<A, M> SpectralClustering<A, M> {
+    /// Creates a model with `n_clusters` clusters of the graph given by
+    /// `affinity` and `metric`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{Affinity, SpectralClustering};
+    ///
+    /// let affinity = Affinity::<f64>::NearestNeighbors { n_neighbors: 5 };
+    /// let spectral = SpectralClustering::new(3, affinity, Euclidean::default());
+    /// assert_eq!(spectral.n_clusters, 3);
+    /// ```
+    #[must_use]
+    pub fn new(n_clusters: usize, affinity: Affinity<A>, metric: M) -> Self {
+        Self {
+            n_clusters,
+            affinity,
+            metric,
+            seed: 0,
+        }
+    }
+}
+
+/// Fits the spectral clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, A, M>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for SpectralClustering<A, M>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync + 'static,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+        let input = input.as_standard_layout();
+        let graph = affinity_graph(&input, self.affinity, &self.metric);
+        let embedding = embed(&graph, self.n_clusters, false, self.seed);
+
+        let mut kmeans = KMeans::new(self.n_clusters);
+        kmeans.n_init = 10;
+        kmeans.seed = self.seed;
+        kmeans.fit(&embedding, None)
+    }
+}
+
+/// Builds the affinity graph as adjacency lists of `(neighbor, weight)`
+/// pairs, without self-loops.
+pub(crate) fn affinity_graph<S, A, M>(
+    input: &ArrayBase<S, Ix2>,
+    affinity: Affinity<A>,
+    metric: &M,
+) -> Vec<Vec<(usize, A)>>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    let n = input.nrows();
+    let rows: Vec<_> = input.rows().into_iter().collect();
+    match affinity {
+        Affinity::Rbf { gamma } => rows
+            .par_iter()
+            .enumerate()
+            .map(|(i, p)| {
+                rows.iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(j, q)| {
+                        let d = metric.distance(p, q);
+                        (j, Float::exp(-gamma * d * d))
+                    })
+                    .collect()
+            })
+            .collect(),
+        Affinity::NearestNeighbors { n_neighbors } => {
+            let k = n_neighbors.min(n - 1);
+            let db = BallTree::new(input.view(), metric.clone()).expect("non-empty array");
+            let knn: Vec<Vec<usize>> = rows
+                .into_par_iter()
+                .map(|p| db.query(&p, k + 1).0)
+                .collect();
+
+            // w(i, j) = (a(i, j) + a(j, i)) / 2 for the k-NN connectivity a
+            let half = A::from_f64(0.5).expect("valid float");
+            let mut graph: Vec<HashMap<usize, A>> = vec![HashMap::new(); n];
+            for (i, neighbors) in knn.iter().enumerate() {
+                for &j in neighbors.iter().filter(|&&j| j != i).take(k) {
+                    *graph[i].entry(j).or_insert_with(A::zero) += half;
+                    *graph[j].entry(i).or_insert_with(A::zero) += half;
+                }
+            }
+            graph
+                .into_iter()
+                .map(|row| {
+                    let mut row: Vec<_> = row.into_iter().collect();
+                    row.sort_unstable_by_key(|(j, _)| *j);
+                    row
+                })
+                .collect()
+        }
+    }
+}
+
+/// Embeds the nodes of an affinity graph into the space spanned by the
+/// eigenvectors of the smallest eigenvalues of its normalized Laplacian.
+pub(crate) fn embed<A>(
+    graph: &[Vec<(usize, A)>],
+    n_components: usize,
+    drop_first: bool,
+    seed: u64,
+) -> Array2<A>
+where
+    A: Float + FromPrimitive + Send + Sync + 'static,
+{
+    let n = graph.len();
+    let inv_sqrt_degree: Vec<A> = graph
+        .iter()
+        .map(|row| {
+            let degree = row.iter().fold(A::zero(), |acc, (_, w)| acc + *w);
+            if degree > A::zero() {
+                degree.sqrt().recip()
+            } else {
+                A::zero()
+            }
+        })
+        .collect();
+
+    // The smallest eigenvalues of L = I - D^-1/2 W D^-1/2 are the largest
+    // eigenvalues of the normalized adjacency D^-1/2 W D^-1/2.
+    let normalized: Vec<Vec<(usize, A)>> = graph
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .map(|(j, w)| (*j, *w * inv_sqrt_degree[i] * inv_sqrt_degree[*j]))
+                .collect()
+        })
+        .collect();
+
+    let skip = usize::from(drop_first);
+    let k = (n_components + skip).min(n);
+    let (_, vectors) = if n > DENSE_LIMIT {
+        lanczos_largest(n, k, seed, |x: &Array1<A>| {
+            normalized
+                .par_iter()
+                .map(|row| row.iter().fold(A::zero(), |acc, (j, w)| acc + *w * x[*j]))
+                .collect::<Vec<_>>()
+                .into()
+        })
+    } else {
+        let mut dense = Array2::zeros((n, n));
+        for (i, row) in normalized.iter().enumerate() {
+            for (j, w) in row {
+                dense[[i, *j]] = *w;
+            }
+        }
+        largest_eigen(dense, k)
+    };
+
+    let mut embedding = vectors.slice(s![.., skip.min(k)..]).to_owned();
+    for (mut row, scale) in embedding.rows_mut().into_iter().zip(inv_sqrt_degree) {
+        row.mapv_inplace(|v| v * scale);
+    }
+
+    // Make the signs deterministic: the largest entry of each vector is positive.
+    for mut column in embedding.columns_mut() {
+        let largest =
+            column.iter().copied().fold(
+                A::zero(),
+                |acc, v| if v.abs() > acc.abs() { v } else { acc },
+            );
+        if largest < A::zero() {
+            column.mapv_inplace(|v| -v);
+        }
+    }
+    embedding
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn default() {
+        let embedding = SpectralEmbedding::<f32, Euclidean>::default();
+        assert_eq!(embedding.n_components, 2);
+    }
+
+    #[test]
+    fn separates_groups() {
+        let data = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [-2.1, 3.2],
+        ];
+        for affinity in [
+            Affinity::Rbf { gamma: 1. },
+            Affinity::NearestNeighbors { n_neighbors: 4 },
+        ] {
+            let mut model = SpectralEmbedding::new(1, affinity, Euclidean::default());
+            let embedding = model.fit(&data, None);
+            assert_eq!(embedding.dim(), (7, 1));
+            let first = embedding.column(0);
+            assert!(first.iter().take(4).all(|v| v * first[0] > 0.));
+            assert!(first.iter().skip(4).all(|v| v * first[0] < 0.));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn ring() {
+        let n = 60;
+        let data = Array2::from_shape_fn((n, 2), |(i, j)| {
+            let angle = 2. * std::f64::consts::PI * i as f64 / n as f64;
+            if j == 0 {
+                angle.cos()
+            } else {
+                angle.sin()
+            }
+        });
+        let graph = affinity_graph(
+            &data,
+            Affinity::NearestNeighbors { n_neighbors: 4 },
+            &Euclidean::default(),
+        );
+        let normalized = embed(&graph, 2, true, 0);
+        assert_eq!(normalized.dim(), (n, 2));
+
+        // the embedding of a ring is a circle: every point has the same norm
+        let norms: Vec<f64> = normalized
+            .rows()
+            .into_iter()
+            .map(|r| r.dot(&r).sqrt())
+            .collect();
+        let mean = norms.iter().sum::<f64>() / norms.len() as f64;
+        assert!(norms.iter().all(|v| (v - mean).abs() < 1e-6 * mean.max(1.)));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn spectral_clustering_rings() {
+        let n = 40;
+        let data = Array2::from_shape_fn((2 * n, 2), |(i, j)| {
+            let radius = if i < n { 1. } else { 5. };
+            let angle = 2. * std::f64::consts::PI * (i % n) as f64 / n as f64;
+            radius * if j == 0 { angle.cos() } else { angle.sin() }
+        });
+        let mut model = SpectralClustering::new(
+            2,
+            Affinity::NearestNeighbors { n_neighbors: 5 },
+            Euclidean::default(),
+        );
+        let (clusters, noise) = model.fit(&data, None);
+        assert!(noise.is_empty());
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [(0..n).collect::<Vec<_>>(), (n..2 * n).collect()]);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let data: Array2<f64> = Array2::zeros((0, 3));
+        let mut model = SpectralEmbedding::default();
+        let embedding = model.fit(&data, None);
+        assert_eq!(embedding.dim(), (0, 2));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{s, ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+use serde::{Deserialize, Serialize};
+
+use super::Fit;
+use crate::dbscan::{apply_border_policy, cluster_neighborhoods};
+use crate::diagnostics::FiniteBallTree;
+use crate::parallel::prelude::*;
+use crate::BorderPolicy;
+
+/// ST-DBSCAN (spatial-temporal DBSCAN) clustering algorithm, for points with
+/// a location and a time, such as the positions of a trajectory or events.
+///
+/// Two points are neighbors if they are within `spatial_eps` of each other
+/// in space and within `temporal_eps` of each other in time, and clusters
+/// are formed from these neighborhoods as in DBSCAN. Points close in space
+/// but far apart in time, e.g. visits of the same place on different days,
+/// are thus in different clusters.
+///
+/// `fit` takes the timestamp of each point from the first column of the
+/// input, and the location from the other columns.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Fit, StDbscan};
+///
+/// // time, x, y
+/// let points = array![
+///     [0., 1., 1.], [1., 1.2, 1.], [2., 1., 1.1],
+///     [50., 1., 1.], [51., 1.1, 1.], [52., 1., 1.2],
+///     [100., 9., 9.],
+/// ];
+/// let mut st_dbscan = StDbscan::new(0.5, 5., 2, Euclidean::default());
+/// let (clusters, noise) = st_dbscan.fit(&points, None);
+///
+/// assert_eq!(clusters[&0], [0, 1, 2]);
+/// assert_eq!(clusters[&1], [3, 4, 5]);
+/// assert_eq!(noise, [6]);
+/// ```
+///
+/// # References
+/// - Birant, Derya, and Alp Kut. "ST-DBSCAN: An algorithm for clustering spatial–temporal data."
+///   Data & Knowledge Engineering 60.1 (2007): 208-221.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StDbscan<A, M> {
+    /// The radius of a neighborhood in space.
+    pub spatial_eps: A,
+
+    /// The radius of a neighborhood in time.
+    pub temporal_eps: A,
+
+    /// The minimum number of points required to form a dense region.
+    pub min_samples: usize,
+
+    /// The metric to compute distance between the locations.
+    pub metric: M,
+
+    /// How border points, which are not core points but are neighbors of
+    /// one, are assigned to clusters.
+    #[serde(default)]
+    pub border_policy: BorderPolicy,
+}
+
+impl<A, M> StDbscan<A, M> {
+    /// Creates ST-DBSCAN with the given radii in space and time, assigning
+    /// border points to the first core point that reaches them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::{BorderPolicy, StDbscan};
+    ///
+    /// let st_dbscan = StDbscan::new(0.5, 60., 3, Euclidean::default());
+    /// assert_eq!(st_dbscan.temporal_eps, 60.);
+    /// assert_eq!(st_dbscan.border_policy, BorderPolicy::FirstCore);
+    /// ```
+    #[must_use]
+    pub fn new(spatial_eps: A, temporal_eps: A, min_samples: usize, metric: M) -> Self {
+        Self {
+            spatial_eps,
+            temporal_eps,
+            min_samples,
+            metric,
+            border_policy: BorderPolicy::FirstCore,
+        }
+    }
+}
+
+impl<A, M> StDbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync,
+    M: Metric<A> + Clone + Sync,
+{
+    /// Fits ST-DBSCAN to the locations in `input`, one per row, where
+    /// `timestamps[i]` is the time of point `i`.
+    ///
+    /// Points with a NaN or infinite timestamp have no neighbors, so they
+    /// are noise, as are points with a NaN or infinite coordinate unless
+    /// `metric` compares them, as [`NanEuclidean`](crate::NanEuclidean)
+    /// does.
+    ///
+    /// # Panics
+    /// Panics if `timestamps` does not have one entry for each row of
+    /// `input`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::StDbscan;
+    ///
+    /// let locations = array![[1., 1.], [1.2, 1.], [1., 1.1], [1., 1.2]];
+    /// let mut st_dbscan = StDbscan::new(0.5, 10., 2, Euclidean::default());
+    /// let (clusters, noise) = st_dbscan.fit_with_timestamps(&locations, &[0., 5., 8., 30.]);
+    ///
+    /// assert_eq!(clusters[&0], [0, 1, 2]);
+    /// assert_eq!(noise, [3]);
+    /// ```
+    pub fn fit_with_timestamps<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        timestamps: &[A],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        assert_eq!(
+            timestamps.len(),
+            input.nrows(),
+            "`timestamps` should have one entry for each point"
+        );
+        // `BallTree` does not accept an empty input.
+        if input.is_empty() {
+            return (HashMap::new(), (0..input.nrows()).collect());
+        }
+
+        let input = input.as_standard_layout();
+        let rows: Vec<_> = input.rows().into_iter().zip(timestamps).collect();
+        let db = FiniteBallTree::new(&input.view(), self.metric.clone());
+        let neighborhoods: Vec<Vec<usize>> = rows
+            .par_iter()
+            .map(|(p, t)| {
+                if !t.is_finite() {
+                    return Vec::new();
+                }
+                let mut neighbors = db.query_radius(p, self.spatial_eps);
+                // a non-finite timestamp is never within `temporal_eps`
+                neighbors.retain(|j| (timestamps[*j] - **t).abs() <= self.temporal_eps);
+                neighbors
+            })
+            .collect();
+
+        let min_samples = vec![self.min_samples; input.nrows()];
+        apply_border_policy(
+            cluster_neighborhoods(&neighborhoods, &min_samples),
+            &neighborhoods,
+            &min_samples,
+            self.border_policy,
+            |i, j| self.metric.distance(&input.row(i), &input.row(j)),
+        )
+    }
+}
+
+/// Fits the ST-DBSCAN clustering algorithm to the given input data.
+///
+/// # Parameters
+/// - `input`: A 2D array representing the dataset to cluster. Each row corresponds to a data point, with its
+///   timestamp in the first column and its location in the others.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of points in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points that do not belong to any cluster.
+impl<S, A, M>
+    Fit<ArrayBase<S, Ix2>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for StDbscan<A, M>
+where
+    A: AddAssign + DivAssign + FloatCore + FromPrimitive + Sync,
+    S: Data<Elem = A>,
+    M: Metric<A> + Clone + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        if input.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+        let timestamps = input.column(0).to_vec();
+        self.fit_with_timestamps(&input.slice(s![.., 1..]), &timestamps)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn st_dbscan() {
+        // a trajectory that stops at the same place twice, with a trip between
+        let points = array![
+            [0., 0., 0.],
+            [1., 0.1, 0.],
+            [2., 0., 0.1],
+            [3., 0.1, 0.1],
+            [4., 5., 5.],
+            [10., 0., 0.],
+            [11., 0.1, 0.],
+            [12., 0., 0.1],
+            [f64::NAN, 0., 0.],
+        ];
+        let mut model = StDbscan::new(0.5, 2., 3, Euclidean::default());
+        let (clusters, noise) = model.fit(&points, None);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[&0], [0, 1, 2, 3]);
+        assert_eq!(clusters[&1], [5, 6, 7]);
+        assert_eq!(noise, [4, 8]);
+
+        // without the time, the two stops are one cluster
+        model.temporal_eps = f64::INFINITY;
+        let (clusters, _) = model.fit(&points, None);
+        assert_eq!(clusters.len(), 1);
+
+        model.border_policy = BorderPolicy::Noise;
+        model.temporal_eps = 1.;
+        let (clusters, noise) = model.fit(&points, None);
+        assert_eq!(clusters[&0], [1, 2]);
+        assert_eq!(clusters[&1], [6]);
+        assert_eq!(noise, [0, 3, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn fit_empty() {
+        let mut model = StDbscan::new(0.5, 2., 3, Euclidean::default());
+        let (clusters, noise) = model.fit(&Array2::<f64>::zeros((0, 3)), None);
+        assert!(clusters.is_empty());
+        assert!(noise.is_empty());
+
+        // timestamps only
+        let (clusters, noise) = model.fit(&Array2::<f64>::zeros((2, 1)), None);
+        assert!(clusters.is_empty());
+        assert_eq!(noise, [0, 1]);
+    }
+}
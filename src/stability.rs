@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, Axis, Data, Ix2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::Fit;
+
+/// The stability of a cluster under resampling, returned by
+/// [`bootstrap_stability`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterStability {
+    /// The ID of the cluster in the clustering of the whole input.
+    pub id: usize,
+
+    /// The number of members of the cluster.
+    pub size: usize,
+
+    /// The Jaccard similarity between the cluster and the most similar
+    /// cluster of each bootstrap run in which at least one of its members
+    /// was drawn.
+    pub jaccard: Vec<f64>,
+}
+
+impl ClusterStability {
+    /// Returns the mean Jaccard similarity over the runs, or zero if no
+    /// member was ever drawn. Clusters above 0.75 are usually considered
+    /// stable, and those below 0.5 artifacts of the algorithm or its
+    /// parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::ClusterStability;
+    ///
+    /// let stability = ClusterStability { id: 0, size: 10, jaccard: vec![1., 0.8, 0.6] };
+    /// assert!((stability.mean_jaccard() - 0.8).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean_jaccard(&self) -> f64 {
+        if self.jaccard.is_empty() {
+            return 0.;
+        }
+        self.jaccard.iter().sum::<f64>() / self.jaccard.len() as f64
+    }
+
+    /// Returns the fraction of the runs in which the cluster dissolved, with
+    /// a Jaccard similarity below 0.5.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::ClusterStability;
+    ///
+    /// let stability = ClusterStability { id: 0, size: 10, jaccard: vec![1., 0.4, 0.6, 0.2] };
+    /// assert_eq!(stability.dissolution_rate(), 0.5);
+    /// ```
+    #[must_use]
+    pub fn dissolution_rate(&self) -> f64 {
+        self.rate(|j| j < 0.5)
+    }
+
+    /// Returns the fraction of the runs in which the cluster was recovered,
+    /// with a Jaccard similarity of at least 0.75.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::ClusterStability;
+    ///
+    /// let stability = ClusterStability { id: 0, size: 10, jaccard: vec![1., 0.75, 0.6, 0.2] };
+    /// assert_eq!(stability.recovery_rate(), 0.5);
+    /// ```
+    #[must_use]
+    pub fn recovery_rate(&self) -> f64 {
+        self.rate(|j| j >= 0.75)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn rate(&self, f: impl Fn(f64) -> bool) -> f64 {
+        if self.jaccard.is_empty() {
+            return 0.;
+        }
+        let count = self.jaccard.iter().filter(|j| f(**j)).count();
+        count as f64 / self.jaccard.len() as f64
+    }
+}
+
+/// Assesses the stability of each cluster found by `clusterer` in `input` by
+/// refitting it to `n_runs` bootstrap samples of the input.
+///
+/// Each bootstrap sample draws as many points as the input with
+/// replacement. A cluster of the whole input, restricted to its members
+/// drawn in a sample, is compared to each cluster of the sample by their
+/// Jaccard similarity, the number of points they share divided by the
+/// number of points in either, and the largest similarity is its score for
+/// the run. A cluster that is real is found again in most samples, while a
+/// cluster that is an artifact of the parameters breaks up or merges with
+/// others. Noise points are in no cluster.
+///
+/// The clusters are returned in increasing order of ID.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::{bootstrap_stability, KMeans};
+///
+/// let points = array![
+///     [1., 2.], [1.2, 2.], [1., 2.3], [1.1, 2.1],
+///     [8., 7.], [8., 8.], [8.3, 7.5], [8.1, 7.7],
+/// ];
+/// let stability = bootstrap_stability(&points, &mut KMeans::new(2), 20, 0);
+///
+/// assert_eq!(stability.len(), 2);
+/// assert!(stability.iter().all(|c| c.mean_jaccard() > 0.9));
+/// ```
+///
+/// # References
+/// - Hennig, Christian. "Cluster-wise assessment of cluster stability."
+///   Computational Statistics & Data Analysis 52.1 (2007): 258-271.
+pub fn bootstrap_stability<S, A, C>(
+    input: &ArrayBase<S, Ix2>,
+    clusterer: &mut C,
+    n_runs: usize,
+    seed: u64,
+) -> Vec<ClusterStability>
+where
+    A: Clone,
+    S: Data<Elem = A>,
+    C: Fit<Array2<A>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>,
+{
+    let n = input.nrows();
+    let (clusters, _) = clusterer.fit(&input.to_owned(), None);
+    let mut reference: Vec<_> = clusters.into_iter().collect();
+    reference.sort_unstable_by_key(|(id, _)| *id);
+    let mut label = vec![None; n];
+    for (c, (_, members)) in reference.iter().enumerate() {
+        for i in members {
+            label[*i] = Some(c);
+        }
+    }
+
+    let mut stability: Vec<_> = reference
+        .iter()
+        .map(|(id, members)| ClusterStability {
+            id: *id,
+            size: members.len(),
+            jaccard: vec![],
+        })
+        .collect();
+    if n == 0 {
+        return stability;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..n_runs {
+        let indices: Vec<usize> = (0..n).map(|_| rng.random_range(0..n)).collect();
+        let (clusters, _) = clusterer.fit(&input.select(Axis(0), &indices), None);
+
+        let mut drawn = vec![false; n];
+        for i in &indices {
+            drawn[*i] = true;
+        }
+        let mut drawn_sizes = vec![0_usize; reference.len()];
+        for (i, c) in label.iter().enumerate() {
+            if let (true, Some(c)) = (drawn[i], c) {
+                drawn_sizes[*c] += 1;
+            }
+        }
+
+        let mut best = vec![0_f64; reference.len()];
+        for members in clusters.values() {
+            let mut points: Vec<usize> = members.iter().map(|m| indices[*m]).collect();
+            points.sort_unstable();
+            points.dedup();
+            let mut shared = vec![0_usize; reference.len()];
+            for p in &points {
+                if let Some(c) = label[*p] {
+                    shared[c] += 1;
+                }
+            }
+            for (c, s) in shared.iter().enumerate() {
+                let union = drawn_sizes[c] + points.len() - s;
+                #[allow(clippy::cast_precision_loss)]
+                let jaccard = *s as f64 / union as f64;
+                best[c] = best[c].max(jaccard);
+            }
+        }
+        for (c, cluster) in stability.iter_mut().enumerate() {
+            if drawn_sizes[c] > 0 {
+                cluster.jaccard.push(best[c]);
+            }
+        }
+    }
+    stability
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Dbscan, KMeans};
+
+    #[test]
+    fn stable_clusters() {
+        let data = array![
+            [0., 0.],
+            [0.1, 0.2],
+            [0.2, 0.1],
+            [0.1, 0.],
+            [10., 10.],
+            [10.1, 10.2],
+            [10.2, 10.1],
+            [10., 10.1],
+        ];
+        let stability =
+            bootstrap_stability(&data, &mut Dbscan::new(1., 1, Euclidean::default()), 10, 1);
+        assert_eq!(stability.len(), 2);
+        for cluster in &stability {
+            assert_eq!(cluster.size, 4);
+            assert_eq!(cluster.jaccard.len(), 10);
+            assert!(f64::abs(cluster.mean_jaccard() - 1.) < 1e-12);
+            assert!(f64::abs(cluster.recovery_rate() - 1.) < 1e-12);
+            assert!(cluster.dissolution_rate().abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn unstable_clusters() {
+        // a single uniform group split in four by k-means
+        let data = Array2::from_shape_fn((40, 1), |(i, _)| f64::from(u32::try_from(i).unwrap()));
+        let stability = bootstrap_stability(&data, &mut KMeans::new(4), 30, 0);
+        assert_eq!(stability.len(), 4);
+        assert!(stability.iter().all(|c| c.mean_jaccard() < 1.));
+
+        let empty = Array2::<f64>::zeros((0, 1));
+        assert!(bootstrap_stability(&empty, &mut KMeans::new(2), 5, 0).is_empty());
+    }
+}
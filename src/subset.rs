@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use ndarray::{Array2, ArrayBase, Axis, Data, Ix2};
+
+use crate::{Fit, HDbscan};
+
+/// An interface to fit a model to a subset of the rows of its input, such as
+/// the points of one category, labeling the points by their index in the
+/// whole input.
+///
+/// Only the selected rows are copied, not the whole input, so a large input
+/// can be clustered one group at a time. The noise holds the selected points
+/// that are not in any cluster; the other points are neither in a cluster
+/// nor noise.
+///
+/// This trait is implemented by every estimator fitted to an array whose
+/// `fit` returns clusters and noise, such as [`KMeans`](crate::KMeans) and
+/// [`Dbscan`](crate::Dbscan), and by [`HDbscan`], ignoring the outlier
+/// scores.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::{Dbscan, FitSubset};
+///
+/// let points = array![[1., 2.], [50., 50.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.]];
+/// let category = ["a", "b", "a", "a", "b", "b"];
+/// let rows: Vec<_> = (0..points.nrows()).filter(|i| category[*i] == "b").collect();
+///
+/// let mut dbscan = Dbscan::new(3., 2, Euclidean::default());
+/// let (clusters, noise) = dbscan.fit_subset(&points, &rows);
+///
+/// assert_eq!(clusters[&0], [4, 5]);
+/// assert_eq!(noise, [1]);
+/// ```
+pub trait FitSubset<A> {
+    /// Fits the model to the rows of `input` at `indices`, and returns its
+    /// clusters and noise in terms of indices of `input`.
+    ///
+    /// # Panics
+    /// Panics if an index is out of bounds or appears more than once.
+    fn fit_subset<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        indices: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>;
+}
+
+impl<T, A> FitSubset<A> for T
+where
+    A: Clone,
+    T: Fit<Array2<A>, HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>,
+{
+    fn fit_subset<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        indices: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        let subset = select(input, indices);
+        to_input_indices(self.fit(&subset, None), indices)
+    }
+}
+
+impl<A, M> FitSubset<A> for HDbscan<A, M>
+where
+    A: Clone,
+    Self: Fit<
+        Array2<A>,
+        HashMap<usize, Vec<usize>>,
+        (HashMap<usize, Vec<usize>>, Vec<usize>, Vec<A>),
+    >,
+{
+    fn fit_subset<S>(
+        &mut self,
+        input: &ArrayBase<S, Ix2>,
+        indices: &[usize],
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>)
+    where
+        S: Data<Elem = A>,
+    {
+        let subset = select(input, indices);
+        let (clusters, noise, _) = self.fit(&subset, None);
+        to_input_indices((clusters, noise), indices)
+    }
+}
+
+/// Copies the rows of `input` at `indices`, which should be distinct.
+fn select<S, A>(input: &ArrayBase<S, Ix2>, indices: &[usize]) -> Array2<A>
+where
+    A: Clone,
+    S: Data<Elem = A>,
+{
+    let mut selected = vec![false; input.nrows()];
+    for &i in indices {
+        assert!(i < input.nrows(), "index {i} is out of bounds");
+        assert!(!selected[i], "index {i} appears more than once");
+        selected[i] = true;
+    }
+    input.select(Axis(0), indices)
+}
+
+/// Maps the points of a clustering of a subset to their indices in the
+/// input, where point `i` of the subset is row `indices[i]` of the input.
+fn to_input_indices(
+    (clusters, noise): (HashMap<usize, Vec<usize>>, Vec<usize>),
+    indices: &[usize],
+) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+    let clusters = clusters
+        .into_iter()
+        .map(|(id, members)| (id, members.into_iter().map(|i| indices[i]).collect()))
+        .collect();
+    let noise = noise.into_iter().map(|i| indices[i]).collect();
+    (clusters, noise)
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Dbscan, KMeans};
+
+    #[test]
+    fn fit_subset() {
+        let data = array![
+            [1., 2.],
+            [100., 100.],
+            [1.1, 2.2],
+            [8., 7.],
+            [0.9, 1.9],
+            [8.1, 7.2],
+            [7.9, 6.8],
+            [-50., 20.]
+        ];
+        let rows = [7, 0, 2, 4, 3, 5, 6];
+
+        let (clusters, noise) = Dbscan::new(0.5, 2, Euclidean::default()).fit_subset(&data, &rows);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 2, 4], vec![3, 5, 6]]);
+        assert_eq!(noise, [7]);
+
+        let (clusters, noise) = KMeans::new(2).fit_subset(&data, &rows[1..]);
+        assert_eq!(clusters.values().map(Vec::len).sum::<usize>(), 6);
+        assert!(noise.is_empty());
+
+        let (clusters, _) = HDbscan::new(2, Euclidean::default()).fit_subset(&data, &rows);
+        assert!(clusters.values().flatten().all(|i| rows.contains(i)));
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 appears more than once")]
+    fn fit_subset_duplicates() {
+        let data = array![[0.], [1.], [2.]];
+        KMeans::new(1).fit_subset(&data, &[2, 0, 2]);
+    }
+}
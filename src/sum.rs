@@ -0,0 +1,85 @@
+use num_traits::float::FloatCore;
+
+/// A running sum of floating-point numbers.
+///
+/// With the `compensated-sum` feature, the rounding error of each addition is
+/// carried over with Neumaier's variant of Kahan summation, so that the sum
+/// barely depends on the order of the numbers. Validation metrics, centroids
+/// and cluster stabilities are then reproducible across chunk sizes and
+/// thread counts. Without the feature, the numbers are simply added.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Sum<A> {
+    sum: A,
+    #[cfg(feature = "compensated-sum")]
+    compensation: A,
+}
+
+impl<A: FloatCore> Sum<A> {
+    pub(crate) fn new() -> Self {
+        Self {
+            sum: A::zero(),
+            #[cfg(feature = "compensated-sum")]
+            compensation: A::zero(),
+        }
+    }
+
+    #[cfg(feature = "compensated-sum")]
+    pub(crate) fn add(&mut self, value: A) {
+        let t = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation = self.compensation + ((self.sum - t) + value);
+        } else {
+            self.compensation = self.compensation + ((value - t) + self.sum);
+        }
+        self.sum = t;
+    }
+
+    #[cfg(not(feature = "compensated-sum"))]
+    pub(crate) fn add(&mut self, value: A) {
+        self.sum = self.sum + value;
+    }
+
+    #[cfg(feature = "compensated-sum")]
+    pub(crate) fn value(&self) -> A {
+        self.sum + self.compensation
+    }
+
+    #[cfg(not(feature = "compensated-sum"))]
+    pub(crate) fn value(&self) -> A {
+        self.sum
+    }
+}
+
+/// Sums the given numbers with [`Sum`].
+pub(crate) fn sum<A: FloatCore>(values: impl IntoIterator<Item = A>) -> A {
+    let mut sum = Sum::new();
+    for v in values {
+        sum.add(v);
+    }
+    sum.value()
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn sum() {
+        assert!((super::sum([0.5, 0.25, 0.25]) - 1_f64).abs() < f64::EPSILON);
+        assert!(super::sum(std::iter::empty::<f32>()).abs() < f32::EPSILON);
+    }
+
+    #[cfg(feature = "compensated-sum")]
+    #[test]
+    fn compensated() {
+        // naive summation loses the small terms entirely
+        let values = [1e16, 1., -1e16, 1.];
+        assert!((super::sum(values) - 2_f64).abs() < f64::EPSILON);
+
+        let mut forward = super::Sum::new();
+        let mut backward = super::Sum::new();
+        for i in 0..1000 {
+            forward.add(0.1 * f64::from(i));
+            backward.add(0.1 * f64::from(999 - i));
+        }
+        assert!((forward.value() - backward.value()).abs() < f64::EPSILON);
+    }
+}
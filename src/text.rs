@@ -0,0 +1,340 @@
+//! Clustering of strings, such as log messages or names, by edit distance.
+//!
+//! The algorithms of this crate cluster rows of numbers, so strings are
+//! clustered through the matrix of the distances between all pairs of them,
+//! computed with [`distance_matrix`], by an algorithm that accepts
+//! precomputed distances. [`StringClustering`] does both steps, and
+//! implements [`Fit`] for slices of strings or byte strings with
+//! [`AgglomerativeClustering`] and [`KMedoids`].
+//!
+//! Strings that are valid UTF-8 are compared character by character, and
+//! other byte strings byte by byte. The matrix takes `O(n^2)` memory and
+//! `O(n^2 * l^2)` time for `n` strings of length `l`.
+//!
+//! # Examples
+//!
+//! ```
+//! use petal_clustering::text::{EditDistance, StringClustering};
+//! use petal_clustering::{AgglomerativeClustering, Fit, Linkage};
+//! use petal_neighbors::distance::Euclidean;
+//!
+//! let messages = [
+//!     "connection to 10.0.0.1 timed out",
+//!     "connection to 10.0.0.7 timed out",
+//!     "disk /dev/sda1 is 91% full",
+//!     "connection to 10.0.0.12 timed out",
+//!     "disk /dev/sdb1 is 95% full",
+//! ];
+//! let mut agglomerative = AgglomerativeClustering::new(1, Linkage::Average, Euclidean::default());
+//! agglomerative.distance_threshold = Some(0.3);
+//! let mut clustering = StringClustering::new(EditDistance::NormalizedLevenshtein, agglomerative);
+//! let (clusters, _) = clustering.fit(&messages[..], None);
+//!
+//! assert_eq!(clusters.len(), 2);
+//! assert_eq!(clusters[&0], [0, 1, 3]);
+//! assert_eq!(clusters[&1], [2, 4]);
+//! ```
+
+use std::collections::HashMap;
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::prelude::*;
+use crate::{AgglomerativeClustering, Fit, KMedoids};
+
+/// A distance between strings.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum EditDistance {
+    /// The number of insertions, deletions and substitutions turning one
+    /// string into the other, as computed by [`levenshtein`].
+    Levenshtein,
+
+    /// The Levenshtein distance divided by the length of the longer string,
+    /// between zero and one, so that a threshold applies to short and long
+    /// strings alike.
+    NormalizedLevenshtein,
+
+    /// One minus the Jaro-Winkler similarity computed by [`jaro_winkler`],
+    /// between zero and one, which favors strings with a common prefix.
+    JaroWinkler,
+}
+
+impl EditDistance {
+    /// Returns the distance between two sequences of symbols.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::text::EditDistance;
+    ///
+    /// assert_eq!(EditDistance::Levenshtein.distance(b"kitten", b"sitting"), 3.);
+    /// assert_eq!(EditDistance::NormalizedLevenshtein.distance(b"ab", b"ac"), 0.5);
+    /// assert_eq!(EditDistance::JaroWinkler.distance(b"same", b"same"), 0.);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn distance<T: PartialEq>(&self, a: &[T], b: &[T]) -> f64 {
+        match self {
+            Self::Levenshtein => levenshtein(a, b) as f64,
+            Self::NormalizedLevenshtein => {
+                let longer = a.len().max(b.len());
+                if longer == 0 {
+                    0.
+                } else {
+                    levenshtein(a, b) as f64 / longer as f64
+                }
+            }
+            Self::JaroWinkler => 1. - jaro_winkler(a, b),
+        }
+    }
+}
+
+/// Returns the Levenshtein distance between two sequences, the minimum
+/// number of insertions, deletions and substitutions of single symbols
+/// turning `a` into `b`.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::text::levenshtein;
+///
+/// assert_eq!(levenshtein(b"kitten", b"sitting"), 3);
+/// ```
+#[must_use]
+pub fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Returns the Jaro-Winkler similarity between two sequences, between zero
+/// for sequences without a symbol in common and one for equal sequences.
+///
+/// The Jaro similarity counts the symbols that match within a window and
+/// how many of them are out of order, and the Winkler bonus raises it for a
+/// common prefix of up to four symbols.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::text::jaro_winkler;
+///
+/// assert!((jaro_winkler(b"martha", b"marhta") - 0.961).abs() < 1e-3);
+/// ```
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn jaro_winkler<T: PartialEq>(a: &[T], b: &[T]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.;
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut taken = vec![false; b.len()];
+    let mut matches = vec![];
+    for (i, x) in a.iter().enumerate() {
+        let end = (i + window + 1).min(b.len());
+        let start = i.saturating_sub(window).min(end);
+        if let Some(j) = (start..end).find(|j| !taken[*j] && b[*j] == *x) {
+            taken[j] = true;
+            matches.push(x);
+        }
+    }
+    if matches.is_empty() {
+        return 0.;
+    }
+    let in_b = b.iter().zip(&taken).filter(|(_, m)| **m).map(|(y, _)| y);
+    let transpositions = matches.iter().zip(in_b).filter(|(x, y)| **x != *y).count() / 2;
+
+    let m = matches.len() as f64;
+    let jaro = (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.;
+    let prefix = a.iter().zip(b).take(4).take_while(|(x, y)| x == y).count();
+    jaro + prefix as f64 * 0.1 * (1. - jaro)
+}
+
+/// Returns the matrix of the distances between all pairs of the given
+/// strings.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::text::{distance_matrix, EditDistance};
+///
+/// let distances = distance_matrix(&["café", "cafe", "tea"], EditDistance::Levenshtein);
+/// assert_eq!(distances[[0, 1]], 1.); // "é" is one character
+/// assert_eq!(distances[[1, 2]], 4.);
+/// ```
+#[must_use]
+#[allow(clippy::missing_panics_doc)] // one distance for each pair
+pub fn distance_matrix<S>(items: &[S], distance: EditDistance) -> Array2<f64>
+where
+    S: AsRef<[u8]> + Sync,
+{
+    let n = items.len();
+    let symbols: Vec<Vec<u32>> = items.par_iter().map(|s| symbols(s.as_ref())).collect();
+    let values: Vec<f64> = (0..n)
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let symbols = &symbols;
+            (0..n).map(move |j| distance.distance(&symbols[i], &symbols[j]))
+        })
+        .collect();
+    Array2::from_shape_vec((n, n), values).expect("one distance for each pair")
+}
+
+/// Returns the characters of `bytes` if it is valid UTF-8, or its bytes.
+fn symbols(bytes: &[u8]) -> Vec<u32> {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.chars().map(u32::from).collect(),
+        Err(_) => bytes.iter().copied().map(u32::from).collect(),
+    }
+}
+
+/// Clusters strings with an algorithm that accepts precomputed distances,
+/// from the matrix of their distances under `distance`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StringClustering<C> {
+    /// The distance between strings.
+    pub distance: EditDistance,
+
+    /// The algorithm clustering the matrix of distances.
+    pub clusterer: C,
+}
+
+impl<C> StringClustering<C> {
+    /// Creates a model clustering the distances between strings under
+    /// `distance` with `clusterer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_neighbors::distance::Euclidean;
+    /// use petal_clustering::text::{EditDistance, StringClustering};
+    /// use petal_clustering::{Fit, KMedoids};
+    ///
+    /// let kmedoids = KMedoids::new(2, Euclidean::default());
+    /// let mut clustering = StringClustering::new(EditDistance::Levenshtein, kmedoids);
+    /// let (clusters, _) = clustering.fit(&["cat", "hat", "bat", "horse", "house"][..], None);
+    ///
+    /// assert_eq!(clusters.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn new(distance: EditDistance, clusterer: C) -> Self {
+        Self {
+            distance,
+            clusterer,
+        }
+    }
+}
+
+/// Fits agglomerative clustering to the distances between the given
+/// strings.
+///
+/// # Parameters
+/// - `input`: The strings to cluster.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of strings in each cluster.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, M> Fit<[S], HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for StringClustering<AgglomerativeClustering<f64, M>>
+where
+    S: AsRef<[u8]> + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &[S],
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.clusterer
+            .fit_precomputed(&distance_matrix(input, self.distance))
+    }
+}
+
+/// Fits k-medoids to the distances between the given strings.
+///
+/// # Parameters
+/// - `input`: The strings to cluster.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// A tuple containing:
+/// - `HashMap<usize, Vec<usize>>`: A mapping of cluster IDs to the indices of strings in each cluster. The medoid of
+///   cluster `i` is the string `clusterer.medoids()[i]`.
+/// - `Vec<usize>`: A vector of indices representing the noise points, which is always empty.
+impl<S, M> Fit<[S], HashMap<usize, Vec<usize>>, (HashMap<usize, Vec<usize>>, Vec<usize>)>
+    for StringClustering<KMedoids<f64, M>>
+where
+    S: AsRef<[u8]> + Sync,
+{
+    fn fit(
+        &mut self,
+        input: &[S],
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> (HashMap<usize, Vec<usize>>, Vec<usize>) {
+        self.clusterer
+            .fit_precomputed(&distance_matrix(input, self.distance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+
+    #[test]
+    fn levenshtein() {
+        assert_eq!(super::levenshtein(b"", b"abc"), 3);
+        assert_eq!(super::levenshtein(b"abc", b"abc"), 0);
+        assert_eq!(super::levenshtein(b"flaw", b"lawn"), 2);
+        assert_eq!(super::levenshtein(b"saturday", b"sunday"), 3);
+    }
+
+    #[test]
+    fn jaro_winkler() {
+        assert!((super::jaro_winkler(b"dixon", b"dicksonx") - 0.813).abs() < 1e-3);
+        assert!((super::jaro_winkler(b"abc", b"abc") - 1.).abs() < 1e-12);
+        assert!(super::jaro_winkler(b"abc", b"xyz").abs() < 1e-12);
+        assert!(super::jaro_winkler(b"", b"abc").abs() < 1e-12);
+        assert!((super::jaro_winkler::<u8>(b"", b"") - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn distance_matrix() {
+        let items: Vec<&[u8]> = vec![b"ab\xff", b"ab", "ab".as_bytes()];
+        let distances = super::distance_matrix(&items, EditDistance::NormalizedLevenshtein);
+        assert!((distances[[0, 1]] - 1. / 3.).abs() < 1e-12);
+        assert!(distances[[1, 2]].abs() < 1e-12);
+        assert!(distances.diag().iter().all(|d| d.abs() < 1e-12));
+    }
+
+    #[test]
+    fn kmedoids() {
+        let names = [
+            "Jonathan".to_string(),
+            "Jonathon".to_string(),
+            "Jonatan".to_string(),
+            "Margaret".to_string(),
+            "Margarett".to_string(),
+        ];
+        let mut clustering = StringClustering::new(
+            EditDistance::JaroWinkler,
+            KMedoids::new(2, Euclidean::default()),
+        );
+        let (clusters, noise) = clustering.fit(&names[..], None);
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, [vec![0, 1, 2], vec![3, 4]]);
+        assert!(noise.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Spans and events from the `tracing` crate with the `tracing` feature, and
+//! nothing without it.
+//!
+//! The major phases of the fits, such as building the index, computing the
+//! neighborhoods and extracting the clusters, are wrapped in [`phase`]
+//! spans, whose fields give the size of their input, so that a subscriber
+//! can time them. [`counts`] emits an event with the sizes of what a phase
+//! produced. Without the feature, the values of the fields are not even
+//! computed.
+
+/// Enters an info-level span named after a phase of a fit, with the given
+/// fields, and returns a guard that exits it when dropped.
+macro_rules! phase {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::info_span!($name $(, $field = $value)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = {
+            let _ = || ($($value,)*);
+            $crate::trace::Phase
+        };
+        guard
+    }};
+}
+
+/// Emits a debug-level event with the given fields, in the current span.
+macro_rules! counts {
+    ($($field:ident = $value:expr),+ $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($field = $value),+);
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = || ($($value,)+);
+        }
+    }};
+}
+
+pub(crate) use counts;
+pub(crate) use phase;
+
+/// The guard returned by [`phase`] without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Phase;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::{Dbscan, Fit, HDbscan, Optics};
+
+    /// Records the names of the spans created.
+    struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn phases() {
+        let data = array![
+            [1., 2.],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [8., 7.],
+            [8.1, 7.2],
+            [7.9, 6.8]
+        ];
+        let names = Arc::new(Mutex::new(vec![]));
+        tracing::subscriber::with_default(Spans(names.clone()), || {
+            Dbscan::new(0.5, 2, Euclidean::default()).fit(&data, None);
+            HDbscan::new(2, Euclidean::default()).fit(&data, None);
+            Optics::new(0.5, 2, Euclidean::default()).fit(&data, None);
+        });
+        let names = names.lock().unwrap();
+        assert_eq!(
+            *names,
+            [
+                "fit",
+                "index_build",
+                "neighborhoods",
+                "expansion",
+                "fit",
+                "index_build",
+                "mst",
+                "condensation",
+                "extraction",
+                "fit",
+                "index_build",
+                "neighborhoods",
+                "ordering",
+                "extraction",
+            ]
+        );
+    }
+}
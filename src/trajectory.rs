@@ -0,0 +1,502 @@
+//! Clustering of trajectories, such as GPS tracks, with TRACLUS.
+//!
+//! [`Traclus`] partitions each trajectory, a polyline given as one point per
+//! row, into line segments at its [characteristic points], where its
+//! direction changes, and clusters the segments of all trajectories with
+//! DBSCAN under a distance between line segments. Each cluster is summarized
+//! by a representative trajectory, the average path of its segments, so
+//! that common sub-trajectories, e.g. a road taken by many tracks, are found
+//! even if the tracks as a whole differ.
+//!
+//! [characteristic points]: characteristic_points
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_clustering::trajectory::Traclus;
+//! use petal_clustering::Fit;
+//!
+//! // three tracks along the x axis, and one going up
+//! let trajectories = [
+//!     array![[0., 0.], [5., 0.1], [10., 0.]],
+//!     array![[0., 0.5], [5., 0.4], [10., 0.5]],
+//!     array![[1., 1.], [6., 0.9], [11., 1.]],
+//!     array![[20., 0.], [20., 10.]],
+//! ];
+//! let clustering = Traclus::new(3., 3).fit(&trajectories[..], None);
+//!
+//! assert_eq!(clustering.clusters.len(), 1);
+//! assert_eq!(clustering.clusters[&0].len(), 3);
+//! let path = &clustering.representatives[&0];
+//! assert!(path.rows().into_iter().all(|p| p[1] > 0. && p[1] < 1.));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::ops::{AddAssign, DivAssign};
+
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, Data, Ix2};
+use num_traits::{float::FloatCore, Float, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::dbscan::cluster_neighborhoods;
+use crate::parallel::prelude::*;
+use crate::Fit;
+
+/// TRACLUS (trajectory clustering) algorithm, which clusters the line
+/// segments of trajectories.
+///
+/// The distance between two segments is the weighted sum of three
+/// components, computed from the projection of the shorter segment onto the
+/// longer one: the perpendicular distance, how far the shorter segment is
+/// from the line of the longer one; the parallel distance, how far it sticks
+/// out past the ends of the longer one; and the angle distance, the length
+/// of the shorter segment times the sine of the angle between them, or its
+/// full length if they point in opposite directions.
+///
+/// # References
+/// - Lee, Jae-Gil, Jiawei Han, and Kyu-Young Whang. "Trajectory clustering: a partition-and-group framework."
+///   Proceedings of the 2007 ACM SIGMOD International Conference on Management of Data (2007): 593-604.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Traclus<A> {
+    /// The radius of a neighborhood of segments.
+    pub eps: A,
+
+    /// The minimum number of segments in the neighborhood of a core segment,
+    /// and the minimum number of trajectories a cluster has segments from.
+    pub min_lines: usize,
+
+    /// The weight of the perpendicular distance.
+    pub perpendicular_weight: A,
+
+    /// The weight of the parallel distance.
+    pub parallel_weight: A,
+
+    /// The weight of the angle distance.
+    pub angle_weight: A,
+
+    /// The minimum distance, along the average direction of a cluster,
+    /// between consecutive points of its representative trajectory.
+    pub min_gap: A,
+}
+
+/// A line segment of a trajectory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineSegment<A> {
+    /// The index of the trajectory the segment is part of.
+    pub trajectory: usize,
+    pub start: Array1<A>,
+    pub end: Array1<A>,
+}
+
+/// The result of [`Traclus`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrajectoryClusters<A> {
+    /// The line segments the trajectories were partitioned into, in the
+    /// order of the trajectories.
+    pub segments: Vec<LineSegment<A>>,
+
+    /// The indices of the segments in each cluster.
+    pub clusters: HashMap<usize, Vec<usize>>,
+
+    /// The indices of the segments that belong to no cluster.
+    pub noise: Vec<usize>,
+
+    /// The representative trajectory of each cluster, one point per row,
+    /// which is empty if the segments of the cluster cancel each other out.
+    pub representatives: HashMap<usize, Array2<A>>,
+}
+
+impl<A> Traclus<A>
+where
+    A: FloatCore,
+{
+    /// Creates TRACLUS with all weights one and a `min_gap` of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petal_clustering::trajectory::Traclus;
+    ///
+    /// let mut traclus = Traclus::new(3., 3);
+    /// traclus.angle_weight = 2.;
+    /// assert_eq!(traclus.parallel_weight, 1.);
+    /// ```
+    #[must_use]
+    pub fn new(eps: A, min_lines: usize) -> Self {
+        Self {
+            eps,
+            min_lines,
+            perpendicular_weight: A::one(),
+            parallel_weight: A::one(),
+            angle_weight: A::one(),
+            min_gap: A::zero(),
+        }
+    }
+}
+
+impl<A> Traclus<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    /// Returns the distance between two line segments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::array;
+    /// use petal_clustering::trajectory::{LineSegment, Traclus};
+    ///
+    /// let a = LineSegment { trajectory: 0, start: array![0., 0.], end: array![10., 0.] };
+    /// let b = LineSegment { trajectory: 1, start: array![0., 1.], end: array![10., 1.] };
+    /// let traclus = Traclus::new(3., 3);
+    /// assert_eq!(traclus.distance(&a, &b), 1.);
+    ///
+    /// ```
+    pub fn distance(&self, a: &LineSegment<A>, b: &LineSegment<A>) -> A {
+        let (perpendicular, parallel, angle) = components(a, b);
+        self.perpendicular_weight * perpendicular
+            + self.parallel_weight * parallel
+            + self.angle_weight * angle
+    }
+
+    /// Returns the representative trajectory of `segments`: the average of
+    /// the segments crossed by a line sweeping along their average
+    /// direction, wherever it crosses at least `min_lines` of them.
+    fn representative(&self, segments: &[&LineSegment<A>], n_features: usize) -> Array2<A> {
+        let mut direction = Array1::zeros(n_features);
+        for s in segments {
+            direction += &(&s.end - &s.start);
+        }
+        let direction_length = norm(&direction);
+        if direction_length <= A::zero() {
+            return Array2::zeros((0, n_features));
+        }
+        direction.mapv_inplace(|d| d / direction_length);
+
+        let intervals: Vec<(A, A)> = segments
+            .iter()
+            .map(|s| (dot(&s.start, &direction), dot(&s.end, &direction)))
+            .collect();
+        let mut positions: Vec<A> = intervals.iter().flat_map(|(a, b)| [*a, *b]).collect();
+        positions.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut points: Vec<A> = vec![];
+        let mut last: Option<A> = None;
+        for x in positions {
+            if last.is_some_and(|last| x - last < self.min_gap || x == last) {
+                continue;
+            }
+            let crossed: Vec<Array1<A>> = segments
+                .iter()
+                .zip(&intervals)
+                .filter(|(_, (a, b))| FloatCore::min(*a, *b) <= x && x <= FloatCore::max(*a, *b))
+                .map(|(s, (a, b))| {
+                    let u = if a == b {
+                        A::zero()
+                    } else {
+                        (x - *a) / (*b - *a)
+                    };
+                    &s.start + &(&s.end - &s.start).mapv(|d| d * u)
+                })
+                .collect();
+            if crossed.len() < self.min_lines {
+                continue;
+            }
+            let n = A::from_usize(crossed.len()).expect("valid float");
+            let mut average = Array1::zeros(n_features);
+            for p in &crossed {
+                average += p;
+            }
+            points.extend(average.iter().map(|v| *v / n));
+            last = Some(x);
+        }
+        let n_points = points.len() / n_features.max(1);
+        Array2::from_shape_vec((n_points, n_features), points).expect("one row per point")
+    }
+}
+
+/// Partitions the given trajectories into line segments at their
+/// characteristic points, and clusters the segments.
+///
+/// # Parameters
+/// - `input`: The trajectories, each with one point per row, in order. Points with a NaN or infinite coordinate are
+///   skipped.
+/// - `_params`: An optional parameter for prelabelled data. Not used in this implementation, but required for consistency.
+///
+/// # Returns
+/// The segments with their clusters, noise and the representative trajectory of each cluster.
+///
+/// # Panics
+/// Panics if the trajectories do not all have the same number of dimensions.
+impl<S, A> Fit<[ArrayBase<S, Ix2>], HashMap<usize, Vec<usize>>, TrajectoryClusters<A>>
+    for Traclus<A>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+    S: Data<Elem = A>,
+{
+    fn fit(
+        &mut self,
+        input: &[ArrayBase<S, Ix2>],
+        _params: Option<&HashMap<usize, Vec<usize>>>,
+    ) -> TrajectoryClusters<A> {
+        let n_features = input.first().map_or(0, ArrayBase::ncols);
+        assert!(
+            input.iter().all(|t| t.ncols() == n_features),
+            "all trajectories should have the same number of dimensions"
+        );
+
+        let mut segments = vec![];
+        for (trajectory, points) in input.iter().enumerate() {
+            let points: Vec<ArrayView1<A>> = points
+                .rows()
+                .into_iter()
+                .filter(|p| p.iter().all(|v| FloatCore::is_finite(*v)))
+                .collect();
+            let corners = characteristic_points(&points);
+            segments.extend(corners.windows(2).map(|w| LineSegment {
+                trajectory,
+                start: points[w[0]].to_owned(),
+                end: points[w[1]].to_owned(),
+            }));
+        }
+
+        let neighborhoods: Vec<Vec<usize>> = segments
+            .par_iter()
+            .map(|a| {
+                (0..segments.len())
+                    .filter(|j| self.distance(a, &segments[*j]) <= self.eps)
+                    .collect()
+            })
+            .collect();
+        let (found, _) =
+            cluster_neighborhoods(&neighborhoods, &vec![self.min_lines; segments.len()]);
+
+        // clusters of too few trajectories are noise
+        let mut found: Vec<Vec<usize>> = found.into_values().collect();
+        found.sort_unstable_by_key(|members| members.iter().min().copied());
+        let mut clusters = HashMap::new();
+        let mut representatives = HashMap::new();
+        for mut members in found {
+            let trajectories: HashSet<usize> =
+                members.iter().map(|i| segments[*i].trajectory).collect();
+            if trajectories.len() < self.min_lines {
+                continue;
+            }
+            members.sort_unstable();
+            let id = clusters.len();
+            let lines: Vec<_> = members.iter().map(|i| &segments[*i]).collect();
+            representatives.insert(id, self.representative(&lines, n_features));
+            clusters.insert(id, members);
+        }
+        let mut in_cluster = vec![false; segments.len()];
+        for &i in clusters.values().flatten() {
+            in_cluster[i] = true;
+        }
+        let noise = (0..segments.len()).filter(|i| !in_cluster[*i]).collect();
+        TrajectoryClusters {
+            segments,
+            clusters,
+            noise,
+            representatives,
+        }
+    }
+}
+
+/// Returns the indices of the characteristic points of a trajectory, where
+/// it is split into line segments, including its first and last points.
+///
+/// The trajectory is extended point by point from the last characteristic
+/// point, and the previous point becomes a characteristic point when
+/// describing the trajectory by a single segment from there costs more,
+/// in the minimum description length sense, than by the original segments.
+/// Lengths are counted as `log2(1 + length)` bits, so that the costs are not
+/// negative.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::trajectory::characteristic_points;
+///
+/// let track = array![[0., 0.], [1., 0.], [2., 0.], [3., 0.], [3., 1.], [3., 2.], [3., 3.]];
+/// let rows: Vec<_> = track.rows().into_iter().collect();
+/// assert_eq!(characteristic_points(&rows), [0, 3, 6]);
+/// ```
+#[must_use]
+pub fn characteristic_points<A>(points: &[ArrayView1<A>]) -> Vec<usize>
+where
+    A: AddAssign + DivAssign + Float + FloatCore + FromPrimitive + Send + Sync,
+{
+    let n = points.len();
+    if n < 2 {
+        return vec![];
+    }
+    let bits = |v: A| Float::log2(A::one() + v);
+    let segment = |i: usize, j: usize| LineSegment {
+        trajectory: 0,
+        start: points[i].to_owned(),
+        end: points[j].to_owned(),
+    };
+    let mut corners = vec![0];
+    let mut start = 0;
+    let mut current = 1;
+    while current < n {
+        let whole = segment(start, current);
+        let with_partition = bits(length(&whole))
+            + (start..current)
+                .map(|k| {
+                    // the parallel distance does not describe a deviation
+                    let (perpendicular, _, angle) = components(&whole, &segment(k, k + 1));
+                    bits(perpendicular) + bits(angle)
+                })
+                .fold(A::zero(), |acc, v| acc + v);
+        let without_partition = (start..current)
+            .map(|k| bits(length(&segment(k, k + 1))))
+            .fold(A::zero(), |acc, v| acc + v);
+        if current - start > 1 && with_partition > without_partition {
+            start = current - 1;
+            corners.push(start);
+        } else {
+            current += 1;
+        }
+    }
+    if corners.last() != Some(&(n - 1)) {
+        corners.push(n - 1);
+    }
+    corners
+}
+
+/// Returns the perpendicular, parallel and angle distances between two line
+/// segments.
+fn components<A: Float + FloatCore>(a: &LineSegment<A>, b: &LineSegment<A>) -> (A, A, A) {
+    let (long, short) = if length(b) > length(a) {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let direction = &long.end - &long.start;
+    let squared_length = dot(&direction, &direction);
+    let project = |p: &Array1<A>| {
+        if squared_length > A::zero() {
+            let u = dot(&(p - &long.start), &direction) / squared_length;
+            &long.start + &direction.mapv(|d| d * u)
+        } else {
+            long.start.clone()
+        }
+    };
+    let (start, end) = (project(&short.start), project(&short.end));
+
+    let (l1, l2) = (norm(&(&short.start - &start)), norm(&(&short.end - &end)));
+    let perpendicular = if l1 + l2 > A::zero() {
+        (l1 * l1 + l2 * l2) / (l1 + l2)
+    } else {
+        A::zero()
+    };
+
+    let from_ends =
+        |p: &Array1<A>| FloatCore::min(norm(&(p - &long.start)), norm(&(p - &long.end)));
+    let parallel = FloatCore::min(from_ends(&start), from_ends(&end));
+
+    let short_direction = &short.end - &short.start;
+    let short_length = norm(&short_direction);
+    let angle = if squared_length > A::zero() && short_length > A::zero() {
+        let cos = dot(&direction, &short_direction) / (Float::sqrt(squared_length) * short_length);
+        if cos < A::zero() {
+            short_length
+        } else {
+            short_length * Float::sqrt(FloatCore::max(A::one() - cos * cos, A::zero()))
+        }
+    } else {
+        A::zero()
+    };
+
+    (perpendicular, parallel, angle)
+}
+
+fn length<A: Float>(segment: &LineSegment<A>) -> A {
+    norm(&(&segment.end - &segment.start))
+}
+
+fn norm<A: Float>(v: &Array1<A>) -> A {
+    Float::sqrt(dot(v, v))
+}
+
+fn dot<A: Float>(a: &Array1<A>, b: &Array1<A>) -> A {
+    a.iter().zip(b).fold(A::zero(), |acc, (x, y)| acc + *x * *y)
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{arr1, array};
+
+    use super::*;
+
+    fn segment(start: [f64; 2], end: [f64; 2]) -> LineSegment<f64> {
+        LineSegment {
+            trajectory: 0,
+            start: arr1(&start),
+            end: arr1(&end),
+        }
+    }
+
+    #[test]
+    fn distance() {
+        let traclus = Traclus::new(1., 2);
+        let long = segment([0., 0.], [10., 0.]);
+        // parallel, one unit above and sticking out by one
+        let d = traclus.distance(&long, &segment([9., 1.], [11., 1.]));
+        assert!((d - 2.).abs() < 1e-12);
+        assert!((traclus.distance(&segment([9., 1.], [11., 1.]), &long) - d).abs() < 1e-12);
+        // perpendicular at the middle, with distances 0 and 2 from the line
+        let d = traclus.distance(&long, &segment([5., 0.], [5., 2.]));
+        assert!((d - (2. + 5. + 2.)).abs() < 1e-12);
+        // opposite direction
+        let d = traclus.distance(&long, &segment([4., 0.], [2., 0.]));
+        assert!((d - (0. + 2. + 2.)).abs() < 1e-12);
+        assert!(traclus.distance(&long, &long).abs() < 1e-12);
+    }
+
+    #[test]
+    fn characteristic_points() {
+        let track = array![
+            [0., 0.],
+            [1., 0.],
+            [2., 0.],
+            [3., 0.],
+            [3., 1.],
+            [3., 2.],
+            [3., 3.]
+        ];
+        let rows: Vec<_> = track.rows().into_iter().collect();
+        assert_eq!(super::characteristic_points(&rows), [0, 3, 6]);
+        assert_eq!(super::characteristic_points(&rows[..2]), [0, 1]);
+        assert!(super::characteristic_points(&rows[..1]).is_empty());
+    }
+
+    #[test]
+    fn traclus() {
+        let trajectories = [
+            array![[0., 0.], [5., 0.], [10., 0.], [10., 10.]],
+            array![[0., 1.], [5., 1.], [10., 1.]],
+            array![[10., 2.], [5., 2.], [0., 2.]],
+            array![[0., 0.5], [f64::NAN, 0.], [10., 0.5]],
+            array![[30., 30.], [31., 40.]],
+        ];
+        let clustering = Traclus::new(3., 3).fit(&trajectories[..], None);
+        assert_eq!(clustering.segments.len(), 6);
+        assert_eq!(clustering.clusters.len(), 1);
+        // the reversed track is too far in angle
+        assert_eq!(clustering.clusters[&0], [0, 2, 4]);
+        assert_eq!(clustering.noise, [1, 3, 5]);
+
+        let path = &clustering.representatives[&0];
+        assert_eq!(path.nrows(), 2);
+        assert!((path[[0, 0]]).abs() < 1e-12 && (path[[1, 0]] - 10.).abs() < 1e-12);
+        assert!((path[[0, 1]] - 0.5).abs() < 1e-12);
+
+        let empty: [Array2<f64>; 0] = [];
+        let clustering = Traclus::new(3., 3).fit(&empty[..], None);
+        assert!(clustering.segments.is_empty());
+    }
+}
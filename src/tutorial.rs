@@ -0,0 +1,319 @@
+//! A tour of the crate on three clusters of points, with every step run as a
+//! doctest.
+//!
+//! The examples share a small generator of blobs, shown here and hidden in
+//! the later examples, which scatters `n` points uniformly in a square of
+//! half width `spread` around each center:
+//!
+//! ```
+//! use ndarray::Array2;
+//! use rand::{rngs::StdRng, Rng, SeedableRng};
+//!
+//! fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//!     let mut rng = StdRng::seed_from_u64(seed);
+//!     let mut points = Array2::zeros((centers.len() * n, 2));
+//!     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//!         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//!         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//!     }
+//!     points
+//! }
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! assert_eq!(points.dim(), (150, 2));
+//! ```
+//!
+//! # Density-based clustering
+//!
+//! [`Dbscan`](crate::Dbscan) groups points with at least `min_samples`
+//! neighbors within `eps`. Before fitting high-dimensional data,
+//! [`check_dimensionality`](crate::check_dimensionality) tells whether
+//! eps-ball neighborhoods are meaningful at all. A [`Partition`](crate::Partition)
+//! stores the result compactly and selects the rows of a cluster:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use ndarray::{array, concatenate, Axis};
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{check_dimensionality, Dbscan, Fit, Partition};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let points = concatenate![Axis(0), points, array![[30., 30.]]];
+//! check_dimensionality(&points, &Euclidean::default()).expect("two dimensions are fine");
+//!
+//! let mut dbscan = Dbscan::new(1.5, 5, Euclidean::default());
+//! let partition = Partition::from(dbscan.fit(&points, None));
+//! assert_eq!(partition.len(), 3);
+//! assert_eq!(partition.noise(), [150]);
+//! assert_eq!(partition.members(1).map(<[usize]>::len), Some(50));
+//!
+//! let mask = partition.mask(2, points.nrows()).expect("cluster 2");
+//! let rows = points.select(Axis(0), partition.members(2).expect("cluster 2"));
+//! assert_eq!(mask.iter().filter(|m| **m).count(), rows.nrows());
+//! assert!(rows.column(1).iter().all(|y| *y > 5.));
+//! ```
+//!
+//! [`HDbscan`](crate::HDbscan) does without `eps`, and scores how much each
+//! point stands out from its cluster. [`Optics`](crate::Optics) orders the
+//! points by reachability once, and extracts the clusters from that order
+//! without searching the neighbors again:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use ndarray::{array, concatenate, Axis};
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{Fit, HDbscan, Optics};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let points = concatenate![Axis(0), points, array![[5., 5.]]];
+//!
+//! let mut hdbscan = HDbscan::new(10, Euclidean::default());
+//! let (clusters, noise, _) = hdbscan.fit(&points, None);
+//! assert_eq!(clusters.len(), 3);
+//! assert_eq!(noise, [150]);
+//! let scores = hdbscan.outlier_scores();
+//! assert!(scores[..150].iter().all(|s| *s < scores[150]));
+//!
+//! let mut optics = Optics::new(3., 5, Euclidean::default());
+//! let (clusters, _) = optics.fit(&points, None);
+//! assert_eq!(clusters.len(), 3);
+//! assert_eq!(optics.extract_clusters_and_noise(3.).0, clusters);
+//! ```
+//!
+//! # Choosing parameters
+//!
+//! An [`InternalMetric`](crate::InternalMetric) scores a clustering from the
+//! points alone, and [`refine_eps`](crate::refine_eps) uses one to tune
+//! the radius of DBSCAN:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{refine_eps, Dbscan, Fit, InternalMetric};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let metric = InternalMetric::DaviesBouldin;
+//! assert!(!metric.higher_is_better());
+//!
+//! let mut dbscan = Dbscan::new(6., 5, Euclidean::default());
+//! let (clusters, _) = dbscan.fit(&points, None);
+//! let initial: f64 = metric.score(&points, &clusters);
+//!
+//! let (eps, score) = refine_eps(&mut dbscan, &points, 6., metric, 10);
+//! assert!(eps > 0. && eps <= 12.);  // searched in (0, 2 * 6]
+//! assert!(score <= initial + 1e-12);  // never worse, up to rounding
+//! assert_eq!(dbscan.fit(&points, None).0.len(), 3);
+//! ```
+//!
+//! # Centroids and mixtures
+//!
+//! [`KMeans`](crate::KMeans) and [`GaussianMixture`](crate::GaussianMixture)
+//! assign every point, including new ones, to one of a fixed number of
+//! clusters, and the mixture gives the probability of each assignment.
+//! [`KMedoids`](crate::KMedoids) picks actual points as centers:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use ndarray::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{Fit, GaussianMixture, KMeans, KMedoids, Predict};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let centers = array![[0., 0.], [10., 0.], [0., 10.]];
+//!
+//! let mut kmeans = KMeans::new(3);
+//! kmeans.fit(&points, None);
+//! let labels = kmeans.predict(&centers);
+//! assert!(labels[0] != labels[1] && labels[1] != labels[2] && labels[0] != labels[2]);
+//! assert!(kmeans.inertia() < 150.);
+//!
+//! let mut gmm = GaussianMixture::new(3);
+//! gmm.fit(&points, None);
+//! assert!(gmm.converged());
+//! assert!(gmm.weights().iter().all(|w| (w - 1. / 3.).abs() < 0.01));
+//! let proba = gmm.predict_proba(&centers);
+//! assert!(proba.rows().into_iter().all(|p| p.iter().any(|v| *v > 0.99)));
+//!
+//! let mut kmedoids = KMedoids::new(3, Euclidean::default());
+//! kmedoids.fit(&points, None);
+//! assert_eq!(kmedoids.medoids().len(), 3);
+//! ```
+//!
+//! # Streaming
+//!
+//! [`MiniBatchKMeans`](crate::MiniBatchKMeans) and [`Birch`](crate::Birch)
+//! learn from batches, so the whole input never needs to be in memory:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{Birch, Dbscan, MiniBatchKMeans, Predict};
+//!
+//! let centers = [[0., 0.], [10., 0.], [0., 10.]];
+//! let mut kmeans = MiniBatchKMeans::new(3, 30);
+//! let mut birch = Birch::new(0.5, 50);
+//! for seed in 0..5 {
+//!     let batch = blobs(&centers, 10, 1., seed);
+//!     kmeans.partial_fit(&batch);
+//!     birch.partial_fit(&batch);
+//! }
+//! assert_eq!(kmeans.n_steps(), 5);
+//! assert_eq!(kmeans.centroids().nrows(), 3);
+//!
+//! assert_eq!(birch.subcluster_sizes().iter().sum::<usize>(), 150);
+//! birch.global_clustering(&mut Dbscan::new(3., 1, Euclidean::default()));
+//! let (clusters, noise) = birch.predict(&blobs(&centers, 10, 1., 5));
+//! assert_eq!(clusters.len(), 3);
+//! assert!(noise.is_empty());
+//! ```
+//!
+//! # Outliers
+//!
+//! [`Lof`](crate::Lof) compares the density around each point to that around
+//! its neighbors, and with `novelty` set, scores new points against the
+//! training points:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use ndarray::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{Fit, Lof, Predict};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let mut lof = Lof::new(20, Euclidean::default());
+//! lof.novelty = true;
+//! let scores = lof.fit(&points, None);
+//! assert!(scores.iter().all(|s| *s < 2.));
+//!
+//! let scores = lof.predict(&array![[0.1, -0.2], [5., 5.]]);
+//! assert!(scores[0] < 1.5);
+//! assert!(scores[1] > 2.);
+//! ```
+//!
+//! # Large inputs
+//!
+//! [`Dbscan::fit_with_limits`](crate::Dbscan::fit_with_limits) aborts a fit
+//! that would use too much memory or time, and
+//! [`Dbscan::fit_cancellable`](crate::Dbscan::fit_cancellable) stops when
+//! another thread cancels it:
+//!
+//! ```
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::{CancelToken, Dbscan, Limit, Limits};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let mut dbscan = Dbscan::new(1.5, 5, Euclidean::default());
+//!
+//! let error = dbscan.fit_with_limits(&points, &Limits::new(Some(4096), None)).unwrap_err();
+//! assert_eq!(error.limit, Limit::Memory(4096));
+//! assert!(error.processed < points.nrows());
+//!
+//! let token = CancelToken::new();
+//! let outcome = dbscan.fit_cancellable(&points, &token);
+//! assert!(!outcome.is_partial());
+//! assert_eq!(outcome.result().0.len(), 3);
+//! ```
+//!
+//! # Saving models
+//!
+//! With one of the `bincode`, `json` or `msgpack` features,
+//! [`Persist`](crate::Persist) saves fitted models to reuse them later:
+//!
+//! ```
+//! # #[cfg(feature = "json")]
+//! # {
+//! # use ndarray::Array2;
+//! # use rand::{rngs::StdRng, Rng, SeedableRng};
+//! # fn blobs(centers: &[[f64; 2]], n: usize, spread: f64, seed: u64) -> Array2<f64> {
+//! #     let mut rng = StdRng::seed_from_u64(seed);
+//! #     let mut points = Array2::zeros((centers.len() * n, 2));
+//! #     for (i, mut p) in points.rows_mut().into_iter().enumerate() {
+//! #         p[0] = centers[i / n][0] + rng.random_range(-spread..spread);
+//! #         p[1] = centers[i / n][1] + rng.random_range(-spread..spread);
+//! #     }
+//! #     points
+//! # }
+//! use petal_clustering::{Fit, Format, KMeans, Persist, Predict};
+//!
+//! let points = blobs(&[[0., 0.], [10., 0.], [0., 10.]], 50, 1., 0);
+//! let mut kmeans = KMeans::new(3);
+//! kmeans.fit(&points, None);
+//!
+//! let path = std::env::temp_dir().join("petal-clustering-tutorial.json");
+//! kmeans.save(&path, Format::from_path(&path).unwrap()).unwrap();
+//! let mut loaded = KMeans::<f64>::load(&path, Format::Json).unwrap();
+//! assert_eq!(loaded.predict(&points), kmeans.predict(&points));
+//! # std::fs::remove_file(&path).unwrap();
+//! # }
+//! ```
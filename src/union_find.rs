@@ -1,12 +1,15 @@
 use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::sync::atomic::{self, AtomicUsize};
 
 use succinct::{BitVecMut, BitVector};
 
+use crate::parallel::prelude::*;
+
 #[allow(dead_code)]
 #[derive(Debug)]
-pub struct TreeUnionFind {
+pub(crate) struct TreeUnionFind {
     parent: Vec<usize>,
     size: Vec<usize>,
     is_component: BitVector<u64>,
@@ -14,7 +17,7 @@ pub struct TreeUnionFind {
 
 #[allow(dead_code)]
 impl TreeUnionFind {
-    pub fn new(n: usize) -> Self {
+    pub(crate) fn new(n: usize) -> Self {
         let parent = (0..n).collect();
         let size = vec![0; n];
         let is_component = BitVector::with_fill(
@@ -28,7 +31,7 @@ impl TreeUnionFind {
         }
     }
 
-    pub fn find(&mut self, x: usize) -> usize {
+    pub(crate) fn find(&mut self, x: usize) -> usize {
         assert!(x < self.parent.len());
         if x != self.parent[x] {
             self.parent[x] = self.find(self.parent[x]);
@@ -40,7 +43,7 @@ impl TreeUnionFind {
         self.parent[x]
     }
 
-    pub fn union(&mut self, x: usize, y: usize) {
+    pub(crate) fn union(&mut self, x: usize, y: usize) {
         let xx = self.find(x);
         let yy = self.find(y);
 
@@ -54,7 +57,7 @@ impl TreeUnionFind {
         }
     }
 
-    pub fn components(&self) -> Vec<usize> {
+    pub(crate) fn components(&self) -> Vec<usize> {
         self.is_component
             .iter()
             .enumerate()
@@ -62,11 +65,126 @@ impl TreeUnionFind {
             .collect()
     }
 
-    pub fn num_components(&self) -> usize {
+    pub(crate) fn num_components(&self) -> usize {
         self.is_component.iter().filter(|b| *b).count()
     }
 }
 
+/// A union-find whose unions can run concurrently, from several threads.
+///
+/// The root of each set is its smallest element, so the sets can be ordered
+/// by their smallest element without another pass.
+#[derive(Debug)]
+pub(crate) struct ConcurrentUnionFind {
+    parent: Vec<AtomicUsize>,
+}
+
+impl ConcurrentUnionFind {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).map(AtomicUsize::new).collect(),
+        }
+    }
+
+    /// Returns the root of the set of `x`, halving the path to it.
+    pub(crate) fn find(&self, mut x: usize) -> usize {
+        loop {
+            let parent = self.parent[x].load(atomic::Ordering::Acquire);
+            if parent == x {
+                return x;
+            }
+            let grandparent = self.parent[parent].load(atomic::Ordering::Acquire);
+            // another thread may have moved `x` already, which is as good
+            let _ = self.parent[x].compare_exchange_weak(
+                parent,
+                grandparent,
+                atomic::Ordering::AcqRel,
+                atomic::Ordering::Acquire,
+            );
+            x = grandparent;
+        }
+    }
+
+    pub(crate) fn union(&self, x: usize, y: usize) {
+        loop {
+            let (x, y) = (self.find(x), self.find(y));
+            let (root, child) = match x.cmp(&y) {
+                Ordering::Equal => return,
+                Ordering::Less => (x, y),
+                Ordering::Greater => (y, x),
+            };
+            // fails if `child` stopped being a root in the meantime
+            if self.parent[child]
+                .compare_exchange(
+                    child,
+                    root,
+                    atomic::Ordering::AcqRel,
+                    atomic::Ordering::Acquire,
+                )
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the connected components of a graph given as adjacency lists,
+/// where `graph[i]` lists the neighbors of node `i`, such as the points
+/// within a radius of each point or its k nearest neighbors.
+///
+/// Edges are undirected: nodes are connected if either one lists the other.
+/// The components are found by a union-find, in parallel with the `parallel`
+/// feature. Each component lists its nodes in increasing order, and the
+/// components are in increasing order of their first node, so isolated
+/// nodes are components of their own.
+///
+/// Clustering with a custom definition of density amounts to keeping the
+/// edges between dense points, as DBSCAN does between core points.
+///
+/// # Panics
+/// Panics if a neighbor is not a node of the graph.
+///
+/// # Examples
+///
+/// ```
+/// use petal_clustering::connected_components;
+///
+/// // the 2-nearest neighbors of points on a line, 0, 1, 2, 10 and 11
+/// let graph = vec![vec![0, 1], vec![1, 0], vec![2, 1], vec![3, 4], vec![4, 3]];
+/// assert_eq!(connected_components(&graph), [vec![0, 1, 2], vec![3, 4]]);
+///
+/// // only the points with a neighbor within a distance of 1 are dense
+/// let dense = [true, true, false, true, true];
+/// let graph: Vec<Vec<usize>> = graph
+///     .iter()
+///     .enumerate()
+///     .map(|(i, neighbors)| {
+///         let dense_neighbors = neighbors.iter().copied().filter(|&j| dense[j]);
+///         if dense[i] { dense_neighbors.collect() } else { vec![] }
+///     })
+///     .collect();
+/// assert_eq!(connected_components(&graph), [vec![0, 1], vec![2], vec![3, 4]]);
+/// ```
+#[must_use]
+pub fn connected_components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = graph.len();
+    let components = ConcurrentUnionFind::new(n);
+    graph.par_iter().enumerate().for_each(|(i, neighbors)| {
+        for &j in neighbors {
+            assert!(j < n, "neighbor {j} is not a node of the graph");
+            components.union(i, j);
+        }
+    });
+
+    let mut members = vec![Vec::new(); n];
+    for i in 0..n {
+        members[components.find(i)].push(i);
+    }
+    members.retain(|m| !m.is_empty());
+    members
+}
+
 mod test {
 
     #[test]
@@ -96,4 +214,30 @@ mod test {
         assert_eq!((0..3).collect::<Vec<_>>(), uf.parent);
         assert_eq!(vec![0; 3], uf.size);
     }
+
+    #[test]
+    fn concurrent_union_find() {
+        use crate::parallel::prelude::*;
+
+        let uf = super::ConcurrentUnionFind::new(10);
+        (0..9).into_par_iter().for_each(|i| {
+            if i % 3 != 2 {
+                uf.union(i + 1, i);
+            }
+        });
+        let roots: Vec<_> = (0..10).map(|i| uf.find(i)).collect();
+        assert_eq!(roots, [0, 0, 0, 3, 3, 3, 6, 6, 6, 9]);
+        uf.union(9, 4);
+        assert_eq!(uf.find(9), 3);
+    }
+
+    #[test]
+    fn connected_components() {
+        let graph = vec![vec![3], vec![], vec![0], vec![], vec![5, 1], vec![]];
+        assert_eq!(
+            super::connected_components(&graph),
+            [vec![0, 2, 3], vec![1, 4, 5]]
+        );
+        assert!(super::connected_components(&[]).is_empty());
+    }
 }
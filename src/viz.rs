@@ -0,0 +1,381 @@
+//! Plots of clustering results with `plotters`, enabled with the `viz`
+//! feature.
+//!
+//! Each function draws on a `plotters` drawing area, so a plot can be
+//! rendered to SVG with [`SVGBackend`], or to PNG with `BitMapBackend` once
+//! the `bitmap_backend` feature of `plotters` is enabled. The version of
+//! `plotters` the functions are built with is re-exported as
+//! [`viz::plotters`](plotters).
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::array;
+//! use petal_neighbors::distance::Euclidean;
+//! use petal_clustering::viz::{self, plotters::prelude::*};
+//! use petal_clustering::{Dbscan, Fit, Partition};
+//!
+//! let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+//! let partition = Partition::from(Dbscan::new(3., 2, Euclidean::default()).fit(&points, None));
+//!
+//! let mut svg = String::new();
+//! {
+//!     let area = SVGBackend::with_string(&mut svg, (640, 480)).into_drawing_area();
+//!     viz::draw_scatter(&area, &points, &partition).unwrap();
+//! }
+//! assert!(svg.contains("<circle"));
+//! ```
+
+use std::ops::{AddAssign, Range, Sub};
+
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::{float::FloatCore, FromPrimitive};
+use petal_neighbors::distance::Metric;
+pub use plotters;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::{Dendrogram, Optics, Partition};
+
+/// The result of drawing on a drawing area of backend `DB`.
+pub type DrawResult<DB> = Result<(), DrawingAreaErrorKind<<DB as DrawingBackend>::ErrorType>>;
+
+/// The color of noise points and of undefined reachability distances.
+const GRAY: RGBColor = RGBColor(170, 170, 170);
+
+/// Draws the reachability plot of a fitted OPTICS model: one bar per point,
+/// in the cluster ordering, as high as its reachability distance, so that
+/// clusters appear as valleys. Points whose reachability distance is
+/// undefined, at the start of each run, are drawn in gray, as high as the
+/// plot.
+///
+/// # Errors
+/// Returns an error if drawing on `area` fails.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::viz::{self, plotters::prelude::*};
+/// use petal_clustering::{Fit, Optics};
+///
+/// let points = array![[1., 2.], [2., 2.], [2., 2.3], [8., 7.], [8., 8.], [25., 80.]];
+/// let mut optics = Optics::new(10., 2, Euclidean::default());
+/// optics.fit(&points, None);
+///
+/// let mut svg = String::new();
+/// {
+///     let area = SVGBackend::with_string(&mut svg, (640, 480)).into_drawing_area();
+///     viz::draw_reachability(&area, &optics).unwrap();
+/// }
+/// assert!(svg.contains("<rect"));
+/// ```
+#[allow(clippy::cast_precision_loss)]
+pub fn draw_reachability<DB, A, M>(
+    area: &DrawingArea<DB, Shift>,
+    optics: &Optics<A, M>,
+) -> DrawResult<DB>
+where
+    DB: DrawingBackend,
+    A: FloatCore,
+    M: Metric<A>,
+{
+    let heights: Vec<f64> = optics
+        .ordering()
+        .iter()
+        .map(|&i| to_f64(optics.reachability()[i]))
+        .collect();
+    let top = upper_bound(heights.iter().copied());
+
+    area.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0.0..heights.len().max(1) as f64, 0.0..top)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_desc("cluster ordering")
+        .y_desc("reachability distance")
+        .draw()?;
+    chart.draw_series(heights.iter().enumerate().map(|(k, &h)| {
+        let (h, color) = if h.is_finite() {
+            (h, BLUE)
+        } else {
+            (top, GRAY)
+        };
+        let x = k as f64;
+        Rectangle::new([(x, 0.), (x + 1., h)], color.filled())
+    }))?;
+    area.present()
+}
+
+/// Draws the condensed tree of a hierarchy, as HDBSCAN builds it from
+/// [`HDbscan::hierarchy`](crate::HDbscan::hierarchy) with `min_size` as the
+/// minimum cluster size, e.g. the model's `min_cluster_size`.
+///
+/// Each cluster of the condensed tree is a box as wide as its number of
+/// points, from the density `lambda`, the inverse of the distance, at which
+/// it splits from its parent down to that at which it splits or its last
+/// points leave it, with its child clusters side by side below it. Long
+/// boxes are stable clusters. Densities beyond the largest finite one, for
+/// points at distance zero, are drawn at the bottom of the plot.
+///
+/// # Errors
+/// Returns an error if drawing on `area` fails.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_neighbors::distance::Euclidean;
+/// use petal_clustering::viz::{self, plotters::prelude::*};
+/// use petal_clustering::{Fit, HDbscan};
+///
+/// let points = array![[1.0, 2.0], [1.1, 2.2], [0.9, 1.9], [1.0, 2.1], [-2.0, 3.0], [-2.2, 3.1]];
+/// let mut hdbscan = HDbscan::new(2, Euclidean::default());
+/// hdbscan.fit(&points, None);
+///
+/// let mut svg = String::new();
+/// {
+///     let area = SVGBackend::with_string(&mut svg, (640, 480)).into_drawing_area();
+///     viz::draw_condensed_tree(&area, hdbscan.hierarchy(), hdbscan.min_cluster_size).unwrap();
+/// }
+/// assert!(svg.contains("<rect"));
+/// ```
+#[allow(clippy::cast_precision_loss)]
+pub fn draw_condensed_tree<DB, A>(
+    area: &DrawingArea<DB, Shift>,
+    hierarchy: &Dendrogram<A>,
+    min_size: usize,
+) -> DrawResult<DB>
+where
+    DB: DrawingBackend,
+    A: AddAssign + FloatCore + FromPrimitive + Sub,
+{
+    let n = hierarchy.n_points();
+    let condensed = hierarchy.condense(min_size);
+    // the root has ID `n`, and clusters the following IDs
+    let n_clusters = condensed
+        .iter()
+        .map(|(parent, child, _, _)| parent.max(child) + 1 - n)
+        .max()
+        .unwrap_or(0);
+    let bottom = upper_bound(
+        condensed
+            .iter()
+            .map(|e| to_f64(e.2))
+            .filter(|lambda| *lambda < to_f64(A::max_value())),
+    );
+    let lambda = |l: A| to_f64(l).min(bottom);
+
+    // the size, birth, death and children of each cluster, by ID minus `n`
+    let mut sizes = vec![n; n_clusters];
+    let mut births = vec![0.; n_clusters];
+    let mut deaths = vec![0_f64; n_clusters];
+    let mut children = vec![Vec::new(); n_clusters];
+    for &(parent, child, l, size) in &condensed {
+        let l = lambda(l);
+        deaths[parent - n] = deaths[parent - n].max(l);
+        if child >= n {
+            sizes[child - n] = size;
+            births[child - n] = l;
+            children[parent - n].push(child - n);
+        }
+    }
+
+    area.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0.0..n.max(1) as f64, bottom..0.0)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_desc("points")
+        .y_desc("lambda")
+        .draw()?;
+    if n_clusters == 0 {
+        return area.present();
+    }
+
+    // lays the clusters out top-down, centering the children of a cluster
+    // under it
+    let mut boxes = Vec::with_capacity(n_clusters);
+    let mut stack = vec![(0, 0.)];
+    while let Some((c, left)) = stack.pop() {
+        boxes.push((c, left));
+        let total: usize = children[c].iter().map(|&k| sizes[k]).sum();
+        let mut start = left + (sizes[c] - total) as f64 / 2.;
+        for &k in &children[c] {
+            stack.push((k, start));
+            start += sizes[k] as f64;
+        }
+    }
+    chart.draw_series(boxes.iter().map(|&(c, left)| {
+        let color = Palette99::pick(c).mix(0.8);
+        let right = left + sizes[c] as f64;
+        Rectangle::new([(left, births[c]), (right, deaths[c])], color.filled())
+    }))?;
+    area.present()
+}
+
+/// Draws the points of `input` by their first two features, colored by
+/// their cluster in `partition`, with noise points in gray. Points with a
+/// NaN or infinite coordinate are left out.
+///
+/// # Errors
+/// Returns an error if drawing on `area` fails.
+///
+/// # Panics
+/// Panics if `input` has fewer than two columns, or if a point of
+/// `partition` is not a row of `input`.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+/// use petal_clustering::viz::{self, plotters::prelude::*};
+/// use petal_clustering::Partition;
+///
+/// let points = array![[1., 2.], [2., 2.], [f64::NAN, 1.]];
+/// let partition = Partition::from_labels(&[Some(0), None, None]);
+///
+/// let mut svg = String::new();
+/// {
+///     let area = SVGBackend::with_string(&mut svg, (320, 240)).into_drawing_area();
+///     viz::draw_scatter(&area, &points, &partition).unwrap();
+/// }
+/// assert_eq!(svg.matches("<circle").count(), 2);
+///
+/// ```
+pub fn draw_scatter<DB, S, A>(
+    area: &DrawingArea<DB, Shift>,
+    input: &ArrayBase<S, Ix2>,
+    partition: &Partition,
+) -> DrawResult<DB>
+where
+    DB: DrawingBackend,
+    S: Data<Elem = A>,
+    A: FloatCore,
+{
+    assert!(input.ncols() >= 2, "a scatter plot needs two features");
+    let point = |i: usize| (to_f64(input[[i, 0]]), to_f64(input[[i, 1]]));
+    let finite = |(x, y): &(f64, f64)| x.is_finite() && y.is_finite();
+    let points: Vec<_> = (0..input.nrows()).map(point).filter(finite).collect();
+
+    area.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(
+            range(points.iter().map(|p| p.0)),
+            range(points.iter().map(|p| p.1)),
+        )?;
+    chart.configure_mesh().draw()?;
+    chart.draw_series(
+        partition
+            .noise()
+            .iter()
+            .map(|&i| point(i))
+            .filter(finite)
+            .map(|p| Circle::new(p, 2, GRAY.filled())),
+    )?;
+    for (k, (_, members)) in partition.iter().enumerate() {
+        let color = Palette99::pick(k);
+        chart.draw_series(
+            members
+                .iter()
+                .map(|&i| point(i))
+                .filter(finite)
+                .map(|p| Circle::new(p, 3, color.filled())),
+        )?;
+    }
+    area.present()
+}
+
+fn to_f64<A: FloatCore>(value: A) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+/// Returns a little more than the largest finite value, or one if there is
+/// none.
+fn upper_bound(values: impl Iterator<Item = f64>) -> f64 {
+    let max = values.filter(|v| v.is_finite()).fold(0_f64, f64::max);
+    if max > 0. {
+        max * 1.05
+    } else {
+        1.
+    }
+}
+
+/// Returns the range of the values with a margin of 5% on each side.
+fn range(values: impl Iterator<Item = f64> + Clone) -> Range<f64> {
+    let min = values.clone().fold(f64::INFINITY, f64::min);
+    let max = values.fold(f64::NEG_INFINITY, f64::max);
+    if min > max {
+        return 0.0..1.0;
+    }
+    let margin = if max > min { (max - min) * 0.05 } else { 0.5 };
+    min - margin..max + margin
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use ndarray::array;
+    use petal_neighbors::distance::Euclidean;
+
+    use super::*;
+    use crate::{Fit, HDbscan};
+
+    fn svg<F>(draw: F) -> String
+    where
+        F: FnOnce(&DrawingArea<SVGBackend, Shift>) -> Result<(), DrawingAreaErrorKind<io::Error>>,
+    {
+        let mut svg = String::new();
+        {
+            let area = SVGBackend::with_string(&mut svg, (320, 240)).into_drawing_area();
+            draw(&area).expect("drawing to a string");
+        }
+        svg
+    }
+
+    #[test]
+    fn plots() {
+        let points = array![
+            [1.0, 2.0],
+            [1.1, 2.2],
+            [0.9, 1.9],
+            [1.0, 2.1],
+            [f64::NAN, 0.],
+            [-2.0, 3.0],
+            [-2.2, 3.1],
+            [-2.1, 3.2]
+        ];
+
+        let mut optics = Optics::new(1., 2, Euclidean::default());
+        optics.fit(&points, None);
+        let plot = svg(|area| draw_reachability(area, &optics));
+        // the background, and a bar per point
+        assert_eq!(plot.matches("<rect").count(), 1 + points.nrows());
+
+        let mut hdbscan = HDbscan::new(3, Euclidean::default());
+        hdbscan.fit(&points, None);
+        let plot = svg(|area| draw_condensed_tree(area, hdbscan.hierarchy(), 3));
+        // the background, the root and two clusters
+        assert_eq!(plot.matches("<rect").count(), 4);
+        let plot = svg(|area| draw_condensed_tree(area, &Dendrogram::<f64>::default(), 3));
+        assert_eq!(plot.matches("<rect").count(), 1);
+
+        let (clusters, noise, _) = hdbscan.fit(&points, None);
+        let partition = Partition::new(clusters, noise);
+        let plot = svg(|area| draw_scatter(area, &points, &partition));
+        assert_eq!(plot.matches("<circle").count(), 7);
+    }
+}